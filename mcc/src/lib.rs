@@ -0,0 +1,266 @@
+//! Cyclomatic complexity and control-flow nesting depth of every function.
+//!
+//! The complexity of a function is 1, plus 1 for each:
+//! - `if` (`else if` and `if let` included), `while`, `for` and `loop`
+//! - `match` arm after the first, and each arm guard
+//! - `&&` and `||`
+//! - `let ... else`
+//!
+//! Its nesting depth is how deeply `if`, `match` and loop bodies nest inside each
+//! other; an `else if` chain stays at the depth of its first `if`. Closures count as
+//! part of the function they are written in, while functions declared inside a body
+//! are measured on their own.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+use syn::spanned::Spanned;
+use syn::visit::{self, Visit};
+use syn::{Attribute, BinOp, Block, Expr, ImplItemFn, ItemFn, ItemImpl, ItemMod, ItemTrait, Signature, TraitItemFn};
+pub use trr_core::Location;
+use trr_core::source::is_cfg_test;
+use trr_core::walk;
+
+/// Functions over this complexity are reported when no limit is given (McCabe's
+/// threshold)
+pub const DEFAULT_COMPLEXITY_LIMIT: usize = 10;
+
+/// Functions nesting control flow deeper than this are reported when no limit is given
+pub const DEFAULT_NESTING_LIMIT: usize = 4;
+
+#[derive(Clone, Debug, Serialize)]
+pub struct FunctionComplexity {
+    /// Inline modules, then the impl type or trait for methods, then the name
+    pub name: String,
+    pub location: Location,
+    pub complexity: usize,
+    pub nesting: usize,
+    /// Lines of the function, signature included
+    pub lines: usize,
+}
+
+/// The functions under a directory
+#[derive(Debug, Default)]
+pub struct Analysis {
+    pub files: usize,
+    /// Most complex first
+    pub functions: Vec<FunctionComplexity>,
+    /// Files that could not be read or parsed
+    pub errors: Vec<(PathBuf, String)>,
+}
+
+impl Analysis {
+    /// The first of the most complex functions
+    pub fn max_complexity(&self) -> Option<&FunctionComplexity> {
+        self.functions.iter().rev().max_by_key(|function| function.complexity)
+    }
+
+    /// The first of the most deeply nesting functions
+    pub fn max_nesting(&self) -> Option<&FunctionComplexity> {
+        self.functions.iter().rev().max_by_key(|function| function.nesting)
+    }
+
+    pub fn average_complexity(&self) -> f64 {
+        if self.functions.is_empty() {
+            return 0.0;
+        }
+        let total: usize = self.functions.iter().map(|function| function.complexity).sum();
+        total as f64 / self.functions.len() as f64
+    }
+}
+
+/// Measure every function of `source`, the contents of `path`. Test-only items are
+/// left out when `no_tests`.
+pub fn analyze_source(path: &Path, source: &str, no_tests: bool) -> syn::Result<Vec<FunctionComplexity>> {
+    let file = syn::parse_file(source)?;
+    let mut visitor = FileVisitor { path, no_tests, scope: Vec::new(), functions: Vec::new() };
+    visitor.visit_file(&file);
+    Ok(visitor.functions)
+}
+
+/// Measure every function of the Rust files under `dir` (or `dir` itself, if it is a
+/// file), leaving out `target/`, and with `no_tests` test files and test-only items
+pub fn analyze(dir: &Path, no_tests: bool) -> io::Result<Analysis> {
+    let files = walk::rust_files(dir, true, &mut |path| {
+        walk::is_target_dir(path) || (no_tests && walk::is_test_path(path))
+    })?;
+
+    let mut analysis = Analysis { files: files.len(), ..Analysis::default() };
+    for file in files {
+        let result = fs::read_to_string(&file)
+            .map_err(|e| e.to_string())
+            .and_then(|source| analyze_source(&file, &source, no_tests).map_err(|e| e.to_string()));
+        match result {
+            Ok(functions) => analysis.functions.extend(functions),
+            Err(e) => analysis.errors.push((file, e)),
+        }
+    }
+    analysis.functions.sort_by(|a, b| b.complexity.cmp(&a.complexity)
+        .then_with(|| b.nesting.cmp(&a.nesting))
+        .then_with(|| a.location.file.cmp(&b.location.file))
+        .then_with(|| a.location.line.cmp(&b.location.line)));
+    Ok(analysis)
+}
+
+struct FileVisitor<'a> {
+    path: &'a Path,
+    no_tests: bool,
+    /// Inline modules, impl types, traits and enclosing functions
+    scope: Vec<String>,
+    functions: Vec<FunctionComplexity>,
+}
+
+impl FileVisitor<'_> {
+    fn skip(&self, attrs: &[Attribute]) -> bool {
+        self.no_tests && is_cfg_test(attrs)
+    }
+
+    fn add_function(&mut self, sig: &Signature, block: &Block, span: proc_macro2::Span) {
+        let mut body = BodyVisitor { complexity: 1, depth: 0, nesting: 0 };
+        body.visit_block(block);
+        let start = sig.ident.span().start();
+        let mut name = self.scope.clone();
+        name.push(sig.ident.to_string());
+        self.functions.push(FunctionComplexity {
+            name: name.join("::"),
+            location: Location { file: self.path.to_path_buf(), line: start.line, column: Some(start.column + 1) },
+            complexity: body.complexity,
+            nesting: body.nesting,
+            lines: span.end().line - span.start().line + 1,
+        });
+
+        // Functions declared in the body
+        self.scope.push(sig.ident.to_string());
+        self.visit_block(block);
+        self.scope.pop();
+    }
+}
+
+impl<'ast> Visit<'ast> for FileVisitor<'_> {
+    fn visit_item_mod(&mut self, item: &'ast ItemMod) {
+        if self.skip(&item.attrs) {
+            return;
+        }
+        self.scope.push(item.ident.to_string());
+        visit::visit_item_mod(self, item);
+        self.scope.pop();
+    }
+
+    fn visit_item_fn(&mut self, item: &'ast ItemFn) {
+        if !self.skip(&item.attrs) {
+            self.add_function(&item.sig, &item.block, item.span());
+        }
+    }
+
+    fn visit_item_impl(&mut self, item: &'ast ItemImpl) {
+        if self.skip(&item.attrs) {
+            return;
+        }
+        let owner = match &*item.self_ty {
+            syn::Type::Path(path) => path.path.segments.last().map(|segment| segment.ident.to_string()),
+            _ => None,
+        };
+        self.scope.push(owner.unwrap_or_else(|| "_".to_string()));
+        visit::visit_item_impl(self, item);
+        self.scope.pop();
+    }
+
+    fn visit_impl_item_fn(&mut self, item: &'ast ImplItemFn) {
+        if !self.skip(&item.attrs) {
+            self.add_function(&item.sig, &item.block, item.span());
+        }
+    }
+
+    fn visit_item_trait(&mut self, item: &'ast ItemTrait) {
+        self.scope.push(item.ident.to_string());
+        visit::visit_item_trait(self, item);
+        self.scope.pop();
+    }
+
+    fn visit_trait_item_fn(&mut self, item: &'ast TraitItemFn) {
+        if let Some(block) = &item.default {
+            self.add_function(&item.sig, block, item.span());
+        }
+    }
+}
+
+/// Complexity and nesting of one body, leaving out the items declared in it
+struct BodyVisitor {
+    complexity: usize,
+    depth: usize,
+    nesting: usize,
+}
+
+impl BodyVisitor {
+    /// Visit a branch or loop body one level deeper
+    fn nested(&mut self, visit: impl FnOnce(&mut Self)) {
+        self.depth += 1;
+        self.nesting = self.nesting.max(self.depth);
+        visit(self);
+        self.depth -= 1;
+    }
+
+    /// An `if` whose `else if`s stay at its depth
+    fn visit_if_chain(&mut self, expr: &syn::ExprIf) {
+        self.complexity += 1;
+        self.visit_expr(&expr.cond);
+        self.nested(|body| body.visit_block(&expr.then_branch));
+        match expr.else_branch.as_ref().map(|(_, branch)| &**branch) {
+            Some(Expr::If(else_if)) => self.visit_if_chain(else_if),
+            Some(branch) => self.nested(|body| body.visit_expr(branch)),
+            None => {}
+        }
+    }
+}
+
+impl<'ast> Visit<'ast> for BodyVisitor {
+    fn visit_item(&mut self, _: &'ast syn::Item) {}
+
+    fn visit_expr_if(&mut self, expr: &'ast syn::ExprIf) {
+        self.visit_if_chain(expr);
+    }
+
+    fn visit_expr_match(&mut self, expr: &'ast syn::ExprMatch) {
+        self.complexity += expr.arms.len().saturating_sub(1);
+        self.complexity += expr.arms.iter().filter(|arm| arm.guard.is_some()).count();
+        self.visit_expr(&expr.expr);
+        self.nested(|body| {
+            for arm in &expr.arms {
+                body.visit_arm(arm);
+            }
+        });
+    }
+
+    fn visit_expr_for_loop(&mut self, expr: &'ast syn::ExprForLoop) {
+        self.complexity += 1;
+        self.visit_expr(&expr.expr);
+        self.nested(|body| body.visit_block(&expr.body));
+    }
+
+    fn visit_expr_while(&mut self, expr: &'ast syn::ExprWhile) {
+        self.complexity += 1;
+        self.visit_expr(&expr.cond);
+        self.nested(|body| body.visit_block(&expr.body));
+    }
+
+    fn visit_expr_loop(&mut self, expr: &'ast syn::ExprLoop) {
+        self.complexity += 1;
+        self.nested(|body| body.visit_block(&expr.body));
+    }
+
+    fn visit_expr_binary(&mut self, expr: &'ast syn::ExprBinary) {
+        if matches!(expr.op, BinOp::And(_) | BinOp::Or(_)) {
+            self.complexity += 1;
+        }
+        visit::visit_expr_binary(self, expr);
+    }
+
+    fn visit_local(&mut self, local: &'ast syn::Local) {
+        if local.init.as_ref().is_some_and(|init| init.diverge.is_some()) {
+            self.complexity += 1;
+        }
+        visit::visit_local(self, local);
+    }
+}
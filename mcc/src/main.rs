@@ -0,0 +1,199 @@
+mod report;
+mod sarif;
+
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::process;
+
+use clap::Parser;
+use serde::Serialize;
+use trr_core::git::Repository;
+use trr_core::output::{self, Format};
+
+use mcc::{Analysis, FunctionComplexity, DEFAULT_COMPLEXITY_LIMIT, DEFAULT_NESTING_LIMIT};
+
+#[derive(Parser)]
+#[clap(author, version, about = "Maximum Cyclomatic Complexity (MCC) Analyzer")]
+#[clap(after_help = "Examples:
+  mcc programs/vault/src
+  mcc --top 10 --no-tests programs
+  mcc --fail-above 15 --fail-nesting-above 5 programs
+  mcc --format markdown programs > complexity.md")]
+struct Args {
+    /// Directory to analyze (relative to --repo when given)
+    #[clap(value_name = "DIR", default_value = ".")]
+    dir: PathBuf,
+
+    /// Git repository URL to clone, or path of a local checkout
+    #[clap(long, value_name = "REPO_URL_OR_PATH")]
+    repo: Option<String>,
+
+    /// Branch, tag or commit of --repo to analyze
+    #[clap(long, value_name = "REV", requires = "repo")]
+    rev: Option<String>,
+
+    /// Output format
+    #[clap(long, value_enum, default_value = "text")]
+    format: Format,
+
+    /// Only the N most complex functions
+    #[clap(long, value_name = "N")]
+    top: Option<usize>,
+
+    /// Exit with status 1 if any function's cyclomatic complexity is greater than N
+    #[clap(long, value_name = "N")]
+    fail_above: Option<usize>,
+
+    /// Exit with status 1 if any function nests control flow deeper than DEPTH
+    #[clap(long, value_name = "DEPTH")]
+    fail_nesting_above: Option<usize>,
+
+    /// Skip #[cfg(test)] items and tests/, benches/, tests.rs files
+    #[clap(long)]
+    no_tests: bool,
+}
+
+impl Args {
+    /// The functions the limits fail, if any limit is given
+    fn offending<'a>(&self, functions: &'a [FunctionComplexity]) -> Vec<&'a FunctionComplexity> {
+        functions.iter()
+            .filter(|function| {
+                self.fail_above.is_some_and(|limit| function.complexity > limit)
+                    || self.fail_nesting_above.is_some_and(|limit| function.nesting > limit)
+            })
+            .collect()
+    }
+}
+
+/// Structured form of the analysis for `--format json`
+#[derive(Serialize)]
+struct JsonReport<'a> {
+    summary: Summary,
+    /// Functions, most complex first
+    functions: &'a [FunctionComplexity],
+    errors: Vec<FileError>,
+}
+
+#[derive(Serialize)]
+struct Summary {
+    files_analyzed: usize,
+    functions: usize,
+    average_complexity: f64,
+    max_complexity: usize,
+    max_nesting: usize,
+    /// Functions over the default limits
+    over_complexity_limit: usize,
+    over_nesting_limit: usize,
+}
+
+#[derive(Serialize)]
+struct FileError {
+    file: PathBuf,
+    error: String,
+}
+
+fn print_text(analysis: &Analysis, functions: &[FunctionComplexity]) {
+    if !functions.is_empty() {
+        println!("\nFunctions by cyclomatic complexity:");
+        println!("\n{:>10} {:>7} {:>5}  Function", "Complexity", "Nesting", "Lines");
+    }
+    for function in functions {
+        println!(
+            "{:>10} {:>7} {:>5}  {} ({})",
+            function.complexity, function.nesting, function.lines, function.name, function.location
+        );
+    }
+
+    println!("\nSummary:");
+    println!("Files analyzed: {}", analysis.files);
+    println!("Functions: {}", analysis.functions.len());
+    println!("Average complexity: {:.2}", analysis.average_complexity());
+    if let Some(function) = analysis.max_complexity() {
+        println!("Maximum complexity: {} ({})", function.complexity, function.name);
+    }
+    if let Some(function) = analysis.max_nesting() {
+        println!("Maximum nesting depth: {} ({})", function.nesting, function.name);
+    }
+}
+
+fn write_json(out: &mut dyn Write, analysis: &Analysis, functions: &[FunctionComplexity]) -> io::Result<()> {
+    let report = JsonReport {
+        summary: Summary {
+            files_analyzed: analysis.files,
+            functions: analysis.functions.len(),
+            average_complexity: (analysis.average_complexity() * 100.0).round() / 100.0,
+            max_complexity: analysis.max_complexity().map_or(0, |function| function.complexity),
+            max_nesting: analysis.max_nesting().map_or(0, |function| function.nesting),
+            over_complexity_limit: analysis.functions.iter().filter(|function| function.complexity > DEFAULT_COMPLEXITY_LIMIT).count(),
+            over_nesting_limit: analysis.functions.iter().filter(|function| function.nesting > DEFAULT_NESTING_LIMIT).count(),
+        },
+        functions,
+        errors: analysis.errors.iter()
+            .map(|(file, error)| FileError { file: file.clone(), error: error.clone() })
+            .collect(),
+    };
+    output::write_json(out, "mcc", &report)
+}
+
+/// With `--fail-above` or `--fail-nesting-above`, list the functions over the limits
+/// and exit with status 1
+fn enforce_limits(args: &Args, functions: &[FunctionComplexity]) {
+    let offending = args.offending(functions);
+    if offending.is_empty() {
+        return;
+    }
+    let header = format!("{} function(s) exceed the complexity limits", offending.len());
+    output::fail(&header, offending.iter().map(|function| {
+        format!("{} (complexity {}, nesting {}) at {}", function.name, function.complexity, function.nesting, function.location)
+    }));
+}
+
+fn run(args: &Args) -> Result<(), Box<dyn std::error::Error>> {
+    let repository = args.repo.as_deref().map(|repo| Repository::open(repo, args.rev.as_deref())).transpose()?;
+    let dir = match &repository {
+        Some(repository) => repository.subdir(&args.dir)?,
+        None => args.dir.clone(),
+    };
+    if !dir.exists() {
+        return Err(format!("Path '{}' does not exist", dir.display()).into());
+    }
+    if args.format == Format::Text {
+        println!("Analyzing: {}", dir.display());
+    }
+
+    let analysis = mcc::analyze(&dir, args.no_tests)?;
+    for (file, error) in &analysis.errors {
+        eprintln!("Error analyzing {}: {}", file.display(), error);
+    }
+    let functions = &analysis.functions[..args.top.map_or(analysis.functions.len(), |top| top.min(analysis.functions.len()))];
+
+    let mut out = io::stdout().lock();
+    match args.format {
+        Format::Text => print_text(&analysis, functions),
+        Format::Json => write_json(&mut out, &analysis, functions)?,
+        Format::Csv => report::write_csv(&mut out, functions)?,
+        Format::Markdown => report::write_markdown(&mut out, &dir, &analysis, functions)?,
+        Format::Sarif => {
+            // Artifact URIs are relative to the repository root, or to where mcc runs
+            let base = repository.as_ref().map_or_else(|| PathBuf::from("."), |repository| repository.root().to_path_buf());
+            let limits = sarif::Limits {
+                complexity: args.fail_above.unwrap_or(DEFAULT_COMPLEXITY_LIMIT),
+                nesting: args.fail_nesting_above.unwrap_or(DEFAULT_NESTING_LIMIT),
+                given: args.fail_above.is_some() || args.fail_nesting_above.is_some(),
+            };
+            sarif::write_sarif(&mut out, functions, &limits, &base)?;
+            writeln!(out)?;
+        }
+    }
+    drop(out);
+    enforce_limits(args, &analysis.functions);
+    Ok(())
+}
+
+fn main() {
+    let args = Args::parse();
+    if let Err(e) = run(&args) {
+        eprintln!("Error: {}", e);
+        process::exit(1);
+    }
+}
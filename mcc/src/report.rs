@@ -0,0 +1,74 @@
+//! `--format csv`, one row per function, and `--format markdown`, a review-ready report
+//! with the summary and the most complex functions.
+
+use std::io::{self, Write};
+use std::path::Path;
+
+use trr_core::output::{markdown_code, write_csv_row};
+
+use mcc::{Analysis, FunctionComplexity, DEFAULT_COMPLEXITY_LIMIT, DEFAULT_NESTING_LIMIT};
+
+/// Rows are most complex first
+pub fn write_csv(out: &mut dyn Write, functions: &[FunctionComplexity]) -> io::Result<()> {
+    write_csv_row(out, &["function", "complexity", "nesting", "lines", "file", "line"])?;
+    for function in functions {
+        write_csv_row(out, &[
+            &function.name,
+            &function.complexity.to_string(),
+            &function.nesting.to_string(),
+            &function.lines.to_string(),
+            &function.location.file.display().to_string(),
+            &function.location.line.to_string(),
+        ])?;
+    }
+    Ok(())
+}
+
+/// A value in bold when it is over `limit`
+fn flagged(value: usize, limit: usize) -> String {
+    if value > limit { format!("**{}**", value) } else { value.to_string() }
+}
+
+pub fn write_markdown(out: &mut dyn Write, target: &Path, analysis: &Analysis, functions: &[FunctionComplexity]) -> io::Result<()> {
+    writeln!(out, "# Cyclomatic Complexity Report\n")?;
+    writeln!(out, "Target: {}\n", markdown_code(&target.display().to_string()))?;
+    writeln!(out, "## Summary\n")?;
+    writeln!(out, "| Metric | Value |")?;
+    writeln!(out, "| --- | ---: |")?;
+    writeln!(out, "| Files analyzed | {} |", analysis.files)?;
+    writeln!(out, "| Functions | {} |", analysis.functions.len())?;
+    writeln!(out, "| Average complexity | {:.2} |", analysis.average_complexity())?;
+    writeln!(out, "| Maximum complexity | {} |", analysis.max_complexity().map_or(0, |function| function.complexity))?;
+    writeln!(out, "| Maximum nesting depth | {} |", analysis.max_nesting().map_or(0, |function| function.nesting))?;
+    writeln!(
+        out,
+        "| Functions over complexity {} | {} |",
+        DEFAULT_COMPLEXITY_LIMIT,
+        analysis.functions.iter().filter(|function| function.complexity > DEFAULT_COMPLEXITY_LIMIT).count()
+    )?;
+    writeln!(
+        out,
+        "| Functions nesting deeper than {} | {} |",
+        DEFAULT_NESTING_LIMIT,
+        analysis.functions.iter().filter(|function| function.nesting > DEFAULT_NESTING_LIMIT).count()
+    )?;
+
+    if functions.is_empty() {
+        return Ok(());
+    }
+    writeln!(out, "\n## Functions\n")?;
+    writeln!(out, "| Function | Complexity | Nesting | Lines | Location |")?;
+    writeln!(out, "| --- | ---: | ---: | ---: | --- |")?;
+    for function in functions {
+        writeln!(
+            out,
+            "| {} | {} | {} | {} | {} |",
+            markdown_code(&function.name),
+            flagged(function.complexity, DEFAULT_COMPLEXITY_LIMIT),
+            flagged(function.nesting, DEFAULT_NESTING_LIMIT),
+            function.lines,
+            markdown_code(&function.location.to_string()),
+        )?;
+    }
+    Ok(())
+}
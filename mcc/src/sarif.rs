@@ -0,0 +1,74 @@
+//! SARIF 2.1.0 output: a `cyclomatic-complexity` result per function over the
+//! complexity limit and a `nesting-depth` result per function over the nesting limit,
+//! located at the function's name.
+
+use std::io::{self, Write};
+use std::path::Path;
+
+use serde_json::json;
+use trr_core::sarif::{location, write_log};
+
+use mcc::FunctionComplexity;
+
+const COMPLEXITY_RULE: &str = "cyclomatic-complexity";
+const NESTING_RULE: &str = "nesting-depth";
+
+pub struct Limits {
+    pub complexity: usize,
+    pub nesting: usize,
+    /// Given on the command line, so results are warnings rather than notes
+    pub given: bool,
+}
+
+pub fn write_sarif(out: &mut dyn Write, functions: &[FunctionComplexity], limits: &Limits, base: &Path) -> io::Result<()> {
+    let level = if limits.given { "warning" } else { "note" };
+    let mut results = Vec::new();
+    for function in functions {
+        if function.complexity > limits.complexity {
+            results.push(json!({
+                "ruleId": COMPLEXITY_RULE,
+                "ruleIndex": 0,
+                "level": level,
+                "message": {
+                    "text": format!("{} has cyclomatic complexity {} (limit {})", function.name, function.complexity, limits.complexity),
+                },
+                "locations": [location(&function.location, base)],
+                "properties": { "complexity": function.complexity, "lines": function.lines },
+            }));
+        }
+        if function.nesting > limits.nesting {
+            results.push(json!({
+                "ruleId": NESTING_RULE,
+                "ruleIndex": 1,
+                "level": level,
+                "message": {
+                    "text": format!("{} nests control flow {} deep (limit {})", function.name, function.nesting, limits.nesting),
+                },
+                "locations": [location(&function.location, base)],
+                "properties": { "nesting": function.nesting },
+            }));
+        }
+    }
+
+    let rules = vec![
+        json!({
+            "id": COMPLEXITY_RULE,
+            "name": "CyclomaticComplexity",
+            "shortDescription": { "text": "Function with more independent paths than the limit" },
+            "fullDescription": {
+                "text": "The function's cyclomatic complexity (1 plus its branches, loops, match arms and boolean operators) exceeds the limit. Such functions are hard to review and to test completely.",
+            },
+            "defaultConfiguration": { "level": "warning" },
+        }),
+        json!({
+            "id": NESTING_RULE,
+            "name": "NestingDepth",
+            "shortDescription": { "text": "Function nesting control flow deeper than the limit" },
+            "fullDescription": {
+                "text": "if, match and loop bodies nest deeper than the limit in this function.",
+            },
+            "defaultConfiguration": { "level": "warning" },
+        }),
+    ];
+    write_log(out, "mcc", env!("CARGO_PKG_VERSION"), rules, results)
+}
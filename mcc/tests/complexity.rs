@@ -0,0 +1,66 @@
+// Measures the functions of tests/fixtures/branches.rs against the complexity and
+// nesting written in their doc comments, and checks the binary's limits.
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+fn fixture() -> PathBuf {
+    Path::new(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/branches.rs")).to_path_buf()
+}
+
+/// Complexity and nesting of each function, by name
+fn measure(no_tests: bool) -> HashMap<String, (usize, usize)> {
+    let path = fixture();
+    let source = fs::read_to_string(&path).unwrap();
+    mcc::analyze_source(&path, &source, no_tests).unwrap().into_iter()
+        .map(|function| (function.name, (function.complexity, function.nesting)))
+        .collect()
+}
+
+#[test]
+fn fixture_functions() {
+    let measured = measure(true);
+    let expected = [
+        ("straight", (1, 0)),
+        ("else_if_chain", (4, 1)),
+        ("classify", (5, 2)),
+        ("loops", (5, 3)),
+        ("Vault::withdraw", (3, 1)),
+        ("Vault::withdraw::helper", (2, 1)),
+    ];
+    assert_eq!(measured.len(), expected.len(), "functions: {:?}", measured);
+    for (name, values) in expected {
+        assert_eq!(measured.get(name), Some(&values), "complexity and nesting of {}", name);
+    }
+}
+
+#[test]
+fn test_code_is_kept_unless_no_tests() {
+    assert_eq!(measure(false).get("tests::only_in_tests"), Some(&(2, 1)));
+}
+
+#[test]
+fn limits() {
+    let run = |args: &[&str]| Command::new(env!("CARGO_BIN_EXE_mcc"))
+        .args(args)
+        .arg(fixture())
+        .output()
+        .expect("failed to run mcc");
+
+    assert!(run(&["--fail-above", "5", "--fail-nesting-above", "3"]).status.success());
+
+    let output = run(&["--fail-above", "4", "--no-tests"]);
+    assert_eq!(output.status.code(), Some(1));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("2 function(s) exceed"), "stderr: {}", stderr);
+    assert!(stderr.contains("classify (complexity 5, nesting 2)"), "stderr: {}", stderr);
+
+    let output = run(&["--fail-nesting-above", "2", "--format", "json", "--top", "1"]);
+    assert_eq!(output.status.code(), Some(1));
+    let report: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(report["summary"]["functions"], 7);
+    assert_eq!(report["summary"]["max_complexity"], 5);
+    assert_eq!(report["functions"].as_array().unwrap().len(), 1);
+}
@@ -0,0 +1,70 @@
+// Each function's expected complexity and nesting depth is in its doc comment.
+
+/// complexity 1, nesting 0
+fn straight(a: u64) -> u64 {
+    a + 1
+}
+
+/// complexity 4, nesting 1: if, else if, &&
+fn else_if_chain(a: u64, b: bool) -> u64 {
+    if a > 10 && b {
+        1
+    } else if a > 5 {
+        2
+    } else {
+        3
+    }
+}
+
+/// complexity 5, nesting 2: three arms after the first, one guard
+fn classify(value: Option<u64>) -> &'static str {
+    match value {
+        Some(0) => "zero",
+        Some(n) if n > 100 => {
+            match n {
+                _ => "large",
+            }
+        }
+        Some(_) => "some",
+        None => "none",
+    }
+}
+
+/// complexity 5, nesting 3: for, while, if, let-else
+fn loops(items: &[u64]) -> u64 {
+    let Some(first) = items.first() else { return 0 };
+    let mut total = *first;
+    for item in items {
+        while total < *item {
+            if total == 0 {
+                break;
+            }
+            total *= 2;
+        }
+    }
+    total
+}
+
+struct Vault;
+
+impl Vault {
+    /// complexity 3, nesting 1: a closure's branches count for the method, the
+    /// nested function's do not
+    fn withdraw(&self, amounts: &[u64]) -> u64 {
+        fn helper(a: u64) -> u64 {
+            if a > 1 { a } else { 1 }
+        }
+        amounts.iter().map(|amount| if *amount > 10 { helper(*amount) } else { 0 }).sum::<u64>()
+            + loop {
+                break 1;
+            }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn only_in_tests() {
+        if true {}
+    }
+}
@@ -0,0 +1,365 @@
+//! Maximum CPI depth: the cross-program invocations reachable from each instruction
+//! handler of a Solana program, and how deeply they nest.
+//!
+//! Call sites are found statically (see [`sites`]), and followed from each handler
+//! through the functions it calls. A CPI into a program that is also analyzed goes on
+//! through that program's handlers, so the depth of a handler is the longest chain of
+//! CPIs it can start.
+
+pub mod sites;
+
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+pub use trr_core::Location;
+use trr_core::walk;
+
+/// The invoke stack height Solana allows (5), less the instruction itself: handlers may
+/// nest CPIs 4 deep before the runtime fails the transaction
+pub const MAX_CPI_DEPTH: usize = 4;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum CpiKind {
+    /// `invoke` or `invoke_unchecked`
+    Invoke,
+    /// `invoke_signed` or `invoke_signed_unchecked`
+    InvokeSigned,
+    /// A call through an Anchor-generated `<program>::cpi` module
+    AnchorCpi,
+    /// A call taking a `CpiContext`, such as `anchor_spl::token::transfer`, or a
+    /// `CpiContext` built and handed on to another function
+    CpiContext,
+}
+
+impl fmt::Display for CpiKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.pad(match self {
+            CpiKind::Invoke => "invoke",
+            CpiKind::InvokeSigned => "invoke_signed",
+            CpiKind::AnchorCpi => "Anchor CPI",
+            CpiKind::CpiContext => "CpiContext",
+        })
+    }
+}
+
+/// A call that invokes another program
+#[derive(Clone, Debug, Serialize)]
+pub struct CpiSite {
+    pub kind: CpiKind,
+    /// The called program, when the source names it (crate names with `_`, `system` for
+    /// the system program)
+    pub program: Option<String>,
+    /// The called instruction, when the source names it
+    pub instruction: Option<String>,
+    /// Path of the function making the call
+    pub function: String,
+    pub location: Location,
+}
+
+impl CpiSite {
+    /// `program::instruction`, with `?` for what the source does not name
+    pub fn target(&self) -> String {
+        match (&self.program, &self.instruction) {
+            (Some(program), Some(instruction)) => format!("{}::{}", program, instruction),
+            (Some(program), None) => program.clone(),
+            (None, Some(instruction)) => format!("?::{}", instruction),
+            (None, None) => "?".to_string(),
+        }
+    }
+}
+
+/// A call a function makes that is not a CPI
+#[derive(Clone, Debug)]
+pub struct Call {
+    /// The function's path, resolved through `use` and `Self`, or for a method call
+    /// just the method's name
+    pub path: Vec<String>,
+    /// A `.name()` call
+    pub method: bool,
+    pub location: Location,
+}
+
+/// A function of a program, with what it calls
+#[derive(Debug)]
+pub struct Function {
+    /// Module path, then the impl or trait for methods, then the name
+    pub path: Vec<String>,
+    pub location: Location,
+    /// Takes `self`, so `.name()` calls may reach it
+    pub method: bool,
+    pub calls: Vec<Call>,
+    /// Indices of its CPI sites in the program's `sites`
+    pub sites: Vec<usize>,
+}
+
+impl Function {
+    pub fn name(&self) -> &str {
+        self.path.last().map_or("", String::as_str)
+    }
+}
+
+/// An instruction handler: a function of the `#[program]` module, or the function
+/// given to `entrypoint!`
+#[derive(Clone, Debug, Serialize)]
+pub struct Handler {
+    pub name: String,
+    pub location: Location,
+    #[serde(skip)]
+    pub function: usize,
+}
+
+/// The analyzed files of one crate
+#[derive(Debug)]
+pub struct Program {
+    /// The package name, with `-` as `_`
+    pub name: String,
+    pub root: PathBuf,
+    pub handlers: Vec<Handler>,
+    pub sites: Vec<CpiSite>,
+    pub functions: Vec<Function>,
+    /// Paths given to `entrypoint!`
+    pub entrypoints: Vec<Vec<String>>,
+}
+
+impl Program {
+    fn new(name: String, root: PathBuf) -> Self {
+        Program { name, root, handlers: Vec::new(), sites: Vec::new(), functions: Vec::new(), entrypoints: Vec::new() }
+    }
+
+    /// The functions a call of `path` (without `crate::`) may reach: those whose path
+    /// ends with it, or for a bare name any function with that name
+    fn resolve(&self, path: &[String]) -> Vec<usize> {
+        let Some(name) = path.last() else { return Vec::new() };
+        self.functions.iter()
+            .enumerate()
+            .filter(|(_, function)| function.name() == name)
+            .filter(|(_, function)| path.len() == 1 || function.path.ends_with(path))
+            .map(|(index, _)| index)
+            .collect()
+    }
+
+    /// The handler for `instruction`, or every handler when no handler has its name (a
+    /// native program's entrypoint takes all of its instructions)
+    fn entries(&self, instruction: Option<&str>) -> Vec<usize> {
+        let named: Vec<usize> = self.handlers.iter()
+            .filter(|handler| Some(handler.name.as_str()) == instruction)
+            .map(|handler| handler.function)
+            .collect();
+        if named.is_empty() {
+            self.handlers.iter().map(|handler| handler.function).collect()
+        } else {
+            named
+        }
+    }
+
+    /// Turn the `entrypoint!` paths into handlers
+    fn resolve_entrypoints(&mut self) {
+        for path in std::mem::take(&mut self.entrypoints) {
+            let path = strip_crate(&path);
+            for function in self.resolve(path) {
+                if self.handlers.iter().any(|handler| handler.function == function) {
+                    continue;
+                }
+                let function_ref = &self.functions[function];
+                self.handlers.push(Handler {
+                    name: function_ref.name().to_string(),
+                    location: function_ref.location.clone(),
+                    function,
+                });
+            }
+        }
+    }
+}
+
+/// `path` without a leading `crate`, `self` or `super`s
+fn strip_crate(path: &[String]) -> &[String] {
+    let skip = path.iter().take_while(|segment| matches!(segment.as_str(), "crate" | "self" | "super")).count();
+    &path[skip..]
+}
+
+/// The CPI depth of one handler
+#[derive(Clone, Debug, Serialize)]
+pub struct HandlerDepth {
+    pub program: String,
+    pub handler: String,
+    pub location: Location,
+    /// Longest chain of nested CPIs the handler can start
+    pub depth: usize,
+    /// The CPI sites of that chain, outermost first
+    pub chain: Vec<CpiSite>,
+    /// CPI sites reachable from the handler in its own program
+    pub sites: usize,
+}
+
+/// The programs under a directory, with their handlers and CPI sites
+#[derive(Debug, Default)]
+pub struct Analysis {
+    pub programs: Vec<Program>,
+    /// Files that could not be read or parsed
+    pub errors: Vec<(PathBuf, String)>,
+}
+
+/// Where a function sits: program and function index
+pub type FunctionId = (usize, usize);
+
+impl Analysis {
+    fn program_named(&self, name: &str) -> Option<usize> {
+        self.programs.iter().position(|program| program.name == name)
+    }
+
+    /// The functions a call from `program` may reach: any method with its name, or the
+    /// functions matching its path in the program's own crate or, for paths starting
+    /// with another analyzed crate's name, in that crate
+    pub fn callees(&self, program: usize, call: &Call) -> Vec<FunctionId> {
+        if call.method {
+            let name = call.path.last().map_or("", String::as_str);
+            return self.programs[program].functions.iter()
+                .enumerate()
+                .filter(|(_, callee)| callee.method && callee.name() == name)
+                .map(|(callee, _)| (program, callee))
+                .collect();
+        }
+        let path = strip_crate(&call.path);
+        if let Some((first, rest)) = path.split_first() {
+            if let Some(other) = self.program_named(first).filter(|&other| other != program && !rest.is_empty()) {
+                return self.programs[other].resolve(rest).into_iter().map(|function| (other, function)).collect();
+            }
+        }
+        self.programs[program].resolve(path).into_iter().map(|function| (program, function)).collect()
+    }
+
+    /// The CPI sites reachable from a function through the functions it calls, as
+    /// program and site index
+    fn reachable_sites(&self, start: FunctionId) -> Vec<(usize, usize)> {
+        let mut visited = HashSet::from([start]);
+        let mut stack = vec![start];
+        let mut sites = Vec::new();
+        while let Some((program, index)) = stack.pop() {
+            let function = &self.programs[program].functions[index];
+            sites.extend(function.sites.iter().map(|&site| (program, site)));
+            for callee in function.calls.iter().flat_map(|call| self.callees(program, call)) {
+                if visited.insert(callee) {
+                    stack.push(callee);
+                }
+            }
+        }
+        sites.sort_unstable();
+        sites
+    }
+
+    /// The longest CPI chain from a function. `active` holds the handlers being
+    /// followed, so a program calling back into itself does not loop; only chains
+    /// computed with nothing else active are remembered, as the others may be cut short.
+    fn deepest_chain(&self, start: FunctionId, active: &mut Vec<FunctionId>, memo: &mut HashMap<FunctionId, Vec<CpiSite>>) -> Vec<CpiSite> {
+        if let Some(chain) = memo.get(&start) {
+            return chain.clone();
+        }
+        active.push(start);
+        let mut deepest: Vec<CpiSite> = Vec::new();
+        for (program, site) in self.reachable_sites(start) {
+            let site = &self.programs[program].sites[site];
+            let mut chain = vec![site.clone()];
+            if let Some(target) = site.program.as_deref().and_then(|name| self.program_named(name)) {
+                let entries: Vec<FunctionId> = self.programs[target].entries(site.instruction.as_deref()).into_iter()
+                    .map(|entry| (target, entry))
+                    .filter(|entry| !active.contains(entry))
+                    .collect();
+                let tail = entries.into_iter()
+                    .map(|entry| self.deepest_chain(entry, active, memo))
+                    .max_by_key(Vec::len);
+                chain.extend(tail.unwrap_or_default());
+            }
+            if chain.len() > deepest.len() {
+                deepest = chain;
+            }
+        }
+        active.pop();
+        if active.is_empty() {
+            memo.insert(start, deepest.clone());
+        }
+        deepest
+    }
+
+    /// Every handler with its CPI depth, deepest first
+    pub fn handler_depths(&self) -> Vec<HandlerDepth> {
+        let mut memo = HashMap::new();
+        let mut depths: Vec<HandlerDepth> = self.programs.iter()
+            .enumerate()
+            .flat_map(|(index, program)| program.handlers.iter().map(move |handler| (index, program, handler)))
+            .map(|(index, program, handler)| {
+                let start = (index, handler.function);
+                let chain = self.deepest_chain(start, &mut Vec::new(), &mut memo);
+                HandlerDepth {
+                    program: program.name.clone(),
+                    handler: handler.name.clone(),
+                    location: handler.location.clone(),
+                    depth: chain.len(),
+                    chain,
+                    sites: self.reachable_sites(start).len(),
+                }
+            })
+            .collect();
+        depths.sort_by(|a, b| b.depth.cmp(&a.depth)
+            .then_with(|| a.program.cmp(&b.program))
+            .then_with(|| a.handler.cmp(&b.handler)));
+        depths
+    }
+
+    /// Every CPI site of every program
+    pub fn sites(&self) -> impl Iterator<Item = &CpiSite> {
+        self.programs.iter().flat_map(|program| &program.sites)
+    }
+}
+
+/// The package name in `Cargo.toml` of `dir`, if it has one
+fn package_name(dir: &Path) -> Option<String> {
+    let manifest: toml::Value = fs::read_to_string(dir.join("Cargo.toml")).ok()?.parse().ok()?;
+    Some(manifest.get("package")?.get("name")?.as_str()?.replace('-', "_"))
+}
+
+/// The crate `file` belongs to: the nearest ancestor with a package `Cargo.toml`, even
+/// above `dir`, or else `dir` named after itself
+fn crate_of(file: &Path, dir: &Path) -> (String, PathBuf) {
+    file.ancestors()
+        .skip(1)
+        .find_map(|ancestor| Some((package_name(ancestor)?, ancestor.to_path_buf())))
+        .unwrap_or_else(|| {
+            let name = fs::canonicalize(dir).ok()
+                .and_then(|dir| dir.file_name().map(|name| name.to_string_lossy().replace('-', "_")))
+                .unwrap_or_else(|| "program".to_string());
+            (name, dir.to_path_buf())
+        })
+}
+
+/// Analyze every Rust file under `dir` (or `dir` itself, if it is a file), leaving out
+/// `target/` and test code, grouped into programs by crate
+pub fn analyze(dir: &Path) -> io::Result<Analysis> {
+    let files = walk::program_files(dir)?;
+
+    let mut analysis = Analysis::default();
+    for file in files {
+        let (name, root) = crate_of(&file, dir);
+        let index = match analysis.program_named(&name) {
+            Some(index) => index,
+            None => {
+                analysis.programs.push(Program::new(name, root));
+                analysis.programs.len() - 1
+            }
+        };
+        let result = fs::read_to_string(&file)
+            .map_err(|e| e.to_string())
+            .and_then(|source| sites::parse_file(&mut analysis.programs[index], &file, &source).map_err(|e| e.to_string()));
+        if let Err(e) = result {
+            analysis.errors.push((file, e));
+        }
+    }
+    for program in &mut analysis.programs {
+        program.resolve_entrypoints();
+    }
+    Ok(analysis)
+}
@@ -0,0 +1,193 @@
+mod report;
+mod sarif;
+
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::process;
+
+use clap::Parser;
+use serde::Serialize;
+use trr_core::git::Repository;
+use trr_core::output::{self, Format};
+
+use mcd::{Analysis, CpiSite, HandlerDepth, MAX_CPI_DEPTH};
+
+#[derive(Parser)]
+#[clap(author, version, about = "Maximum CPI Depth (MCD) Analyzer")]
+#[clap(after_help = "Examples:
+  mcd programs/vault
+  mcd --repo https://github.com/user/repo.git programs
+  mcd --format markdown programs > cpi-depth.md
+  mcd --fail-above 3 programs")]
+struct Args {
+    /// Directory to analyze (relative to --repo when given)
+    #[clap(value_name = "DIR", default_value = ".")]
+    dir: PathBuf,
+
+    /// Git repository URL to clone, or path of a local checkout
+    #[clap(long, value_name = "REPO_URL_OR_PATH")]
+    repo: Option<String>,
+
+    /// Branch, tag or commit of --repo to analyze
+    #[clap(long, value_name = "REV", requires = "repo")]
+    rev: Option<String>,
+
+    /// Output format
+    #[clap(long, value_enum, default_value = "text")]
+    format: Format,
+
+    /// Exit with status 1 if any handler nests CPIs deeper than DEPTH
+    #[clap(long, value_name = "DEPTH")]
+    fail_above: Option<usize>,
+}
+
+/// Structured form of the analysis for `--format json`
+#[derive(Serialize)]
+struct JsonReport<'a> {
+    summary: Summary,
+    programs: Vec<ProgramReport<'a>>,
+    /// Handlers, deepest first
+    handlers: &'a [HandlerDepth],
+    sites: Vec<&'a CpiSite>,
+    errors: Vec<FileError>,
+}
+
+#[derive(Serialize)]
+struct Summary {
+    programs: usize,
+    handlers: usize,
+    cpi_sites: usize,
+    /// CPI sites whose program the source does not name
+    unresolved_sites: usize,
+    max_depth: usize,
+    /// The depth Solana allows
+    limit: usize,
+}
+
+#[derive(Serialize)]
+struct ProgramReport<'a> {
+    name: &'a str,
+    root: &'a std::path::Path,
+    handlers: Vec<&'a str>,
+    cpi_sites: usize,
+}
+
+#[derive(Serialize)]
+struct FileError {
+    file: PathBuf,
+    error: String,
+}
+
+fn print_text(analysis: &Analysis, handlers: &[HandlerDepth]) {
+    println!("\nPrograms:");
+    for program in &analysis.programs {
+        println!("  {} ({} handlers, {} CPI sites)", program.name, program.handlers.len(), program.sites.len());
+    }
+
+    if handlers.is_empty() {
+        println!("\nNo instruction handlers found (no #[program] module or entrypoint!)");
+    } else {
+        println!("\nCPI depth per instruction handler:");
+    }
+    for handler in handlers {
+        let over = if handler.depth > MAX_CPI_DEPTH { " ⚠ over Solana's limit" } else { "" };
+        println!("\n{}::{} (at {}): depth {}{}", handler.program, handler.handler, handler.location, handler.depth, over);
+        for (i, site) in handler.chain.iter().enumerate() {
+            println!("  {}. {} ({}) in {} at {}", i + 1, site.target(), site.kind, site.function, site.location);
+        }
+    }
+
+    let max_depth = handlers.first().map_or(0, |handler| handler.depth);
+    println!("\nMaximum CPI depth: {} (Solana allows {})", max_depth, MAX_CPI_DEPTH);
+    let unresolved = analysis.sites().filter(|site| site.program.is_none()).count();
+    if unresolved > 0 {
+        println!("{} CPI site(s) call a program the source does not name; their depth is counted as 1", unresolved);
+    }
+}
+
+fn write_json(out: &mut dyn Write, analysis: &Analysis, handlers: &[HandlerDepth]) -> io::Result<()> {
+    let report = JsonReport {
+        summary: Summary {
+            programs: analysis.programs.len(),
+            handlers: handlers.len(),
+            cpi_sites: analysis.sites().count(),
+            unresolved_sites: analysis.sites().filter(|site| site.program.is_none()).count(),
+            max_depth: handlers.first().map_or(0, |handler| handler.depth),
+            limit: MAX_CPI_DEPTH,
+        },
+        programs: analysis.programs.iter()
+            .map(|program| ProgramReport {
+                name: &program.name,
+                root: &program.root,
+                handlers: program.handlers.iter().map(|handler| handler.name.as_str()).collect(),
+                cpi_sites: program.sites.len(),
+            })
+            .collect(),
+        handlers,
+        sites: analysis.sites().collect(),
+        errors: analysis.errors.iter()
+            .map(|(file, error)| FileError { file: file.clone(), error: error.clone() })
+            .collect(),
+    };
+    output::write_json(out, "mcd", &report)
+}
+
+/// With `--fail-above`, list the handlers deeper than the limit and exit with status 1
+fn enforce_limit(handlers: &[HandlerDepth], fail_above: Option<usize>) {
+    let Some(limit) = fail_above else { return };
+    let offending: Vec<&HandlerDepth> = handlers.iter().filter(|handler| handler.depth > limit).collect();
+    if offending.is_empty() {
+        return;
+    }
+    let header = format!("{} handler(s) exceed the maximum CPI depth of {}", offending.len(), limit);
+    output::fail(&header, offending.iter().map(|handler| {
+        let chain: Vec<String> = handler.chain.iter().map(CpiSite::target).collect();
+        format!("{}::{} (depth {}): {}", handler.program, handler.handler, handler.depth, chain.join(" → "))
+    }));
+}
+
+fn run(args: &Args) -> Result<(), Box<dyn std::error::Error>> {
+    let repository = args.repo.as_deref().map(|repo| Repository::open(repo, args.rev.as_deref())).transpose()?;
+    let dir = match &repository {
+        Some(repository) => repository.subdir(&args.dir)?,
+        None => args.dir.clone(),
+    };
+    if !dir.exists() {
+        return Err(format!("Path '{}' does not exist", dir.display()).into());
+    }
+    if args.format == Format::Text {
+        println!("Analyzing: {}", dir.display());
+    }
+
+    let analysis = mcd::analyze(&dir)?;
+    for (file, error) in &analysis.errors {
+        eprintln!("Error analyzing {}: {}", file.display(), error);
+    }
+    let handlers = analysis.handler_depths();
+
+    let mut out = io::stdout().lock();
+    match args.format {
+        Format::Text => print_text(&analysis, &handlers),
+        Format::Json => write_json(&mut out, &analysis, &handlers)?,
+        Format::Csv => report::write_csv(&mut out, &handlers)?,
+        Format::Markdown => report::write_markdown(&mut out, &dir, &analysis, &handlers)?,
+        Format::Sarif => {
+            // Artifact URIs are relative to the repository root, or to where mcd runs
+            let base = repository.as_ref().map_or_else(|| PathBuf::from("."), |repository| repository.root().to_path_buf());
+            let limit = args.fail_above.unwrap_or(MAX_CPI_DEPTH);
+            sarif::write_sarif(&mut out, &handlers, limit, &base)?;
+            writeln!(out)?;
+        }
+    }
+    drop(out);
+    enforce_limit(&handlers, args.fail_above);
+    Ok(())
+}
+
+fn main() {
+    let args = Args::parse();
+    if let Err(e) = run(&args) {
+        eprintln!("Error: {}", e);
+        process::exit(1);
+    }
+}
@@ -0,0 +1,61 @@
+//! `--format csv`, one row per handler, and `--format markdown`, a review-ready report
+//! with the summary and every handler's deepest chain.
+
+use std::io::{self, Write};
+use std::path::Path;
+
+use trr_core::output::{markdown_code, write_csv_row};
+
+use mcd::{Analysis, CpiSite, HandlerDepth, MAX_CPI_DEPTH};
+
+/// Rows are deepest first. `chain` lists the called `program::instruction`s, and `file`
+/// and `line` point at the handler.
+pub fn write_csv(out: &mut dyn Write, handlers: &[HandlerDepth]) -> io::Result<()> {
+    write_csv_row(out, &["program", "handler", "depth", "chain", "file", "line"])?;
+    for handler in handlers {
+        let chain: Vec<String> = handler.chain.iter().map(CpiSite::target).collect();
+        write_csv_row(out, &[
+            &handler.program,
+            &handler.handler,
+            &handler.depth.to_string(),
+            &chain.join(" → "),
+            &handler.location.file.display().to_string(),
+            &handler.location.line.to_string(),
+        ])?;
+    }
+    Ok(())
+}
+
+pub fn write_markdown(out: &mut dyn Write, target: &Path, analysis: &Analysis, handlers: &[HandlerDepth]) -> io::Result<()> {
+    writeln!(out, "# CPI Depth Report\n")?;
+    writeln!(out, "Target: {}\n", markdown_code(&target.display().to_string()))?;
+    writeln!(out, "## Summary\n")?;
+    writeln!(out, "| Metric | Value |")?;
+    writeln!(out, "| --- | ---: |")?;
+    writeln!(out, "| Programs | {} |", analysis.programs.len())?;
+    writeln!(out, "| Instruction handlers | {} |", handlers.len())?;
+    writeln!(out, "| CPI sites | {} |", analysis.sites().count())?;
+    writeln!(out, "| CPI sites to an unnamed program | {} |", analysis.sites().filter(|site| site.program.is_none()).count())?;
+    writeln!(out, "| Maximum CPI depth | {} |", handlers.first().map_or(0, |handler| handler.depth))?;
+    writeln!(out, "| Solana's limit | {} |", MAX_CPI_DEPTH)?;
+
+    if handlers.is_empty() {
+        return Ok(());
+    }
+    writeln!(out, "\n## Handlers\n")?;
+    writeln!(out, "| Handler | Depth | Deepest chain | Location |")?;
+    writeln!(out, "| --- | ---: | --- | --- |")?;
+    for handler in handlers {
+        let chain: Vec<String> = handler.chain.iter().map(|site| markdown_code(&site.target())).collect();
+        let depth = if handler.depth > MAX_CPI_DEPTH { format!("**{}**", handler.depth) } else { handler.depth.to_string() };
+        writeln!(
+            out,
+            "| {} | {} | {} | {} |",
+            markdown_code(&format!("{}::{}", handler.program, handler.handler)),
+            depth,
+            chain.join(" → "),
+            markdown_code(&handler.location.to_string()),
+        )?;
+    }
+    Ok(())
+}
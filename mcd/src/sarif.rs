@@ -0,0 +1,56 @@
+//! SARIF 2.1.0 output: one result per handler nesting CPIs deeper than the limit,
+//! located at the handler, with the CPI sites of its deepest chain as related locations.
+
+use std::io::{self, Write};
+use std::path::Path;
+
+use serde_json::json;
+use trr_core::sarif::{location, write_log};
+
+use mcd::{CpiSite, HandlerDepth, MAX_CPI_DEPTH};
+
+const RULE_ID: &str = "cpi-depth";
+
+/// `limit` is `--fail-above`, or Solana's own limit. Going over Solana's limit is an
+/// error, since the runtime fails such transactions.
+pub fn write_sarif(out: &mut dyn Write, handlers: &[HandlerDepth], limit: usize, base: &Path) -> io::Result<()> {
+    let results: Vec<_> = handlers.iter()
+        .filter(|handler| handler.depth > limit)
+        .map(|handler| {
+            let chain: Vec<String> = handler.chain.iter().map(CpiSite::target).collect();
+            let related: Vec<_> = handler.chain.iter()
+                .enumerate()
+                .map(|(i, site)| {
+                    let mut related = location(&site.location, base);
+                    related["id"] = json!(i);
+                    related["message"] = json!({ "text": format!("CPI {} into {}", i + 1, site.target()) });
+                    related
+                })
+                .collect();
+            json!({
+                "ruleId": RULE_ID,
+                "level": if handler.depth > MAX_CPI_DEPTH { "error" } else { "warning" },
+                "message": {
+                    "text": format!(
+                        "{}::{} nests CPIs {} deep (limit {}): {}",
+                        handler.program, handler.handler, handler.depth, limit, chain.join(" → ")
+                    ),
+                },
+                "locations": [location(&handler.location, base)],
+                "relatedLocations": related,
+                "properties": { "depth": handler.depth, "chain": chain },
+            })
+        })
+        .collect();
+
+    let rule = json!({
+        "id": RULE_ID,
+        "name": "MaximumCpiDepth",
+        "shortDescription": { "text": "Instruction handler nesting cross-program invocations deeper than allowed" },
+        "fullDescription": {
+            "text": "The longest chain of nested CPIs an instruction handler can start exceeds the limit. Solana fails transactions nesting more than 4.",
+        },
+        "defaultConfiguration": { "level": "warning" },
+    });
+    write_log(out, "mcd", env!("CARGO_PKG_VERSION"), vec![rule], results)
+}
@@ -0,0 +1,484 @@
+//! Finding the functions of a file, what they call, and their CPI sites:
+//!
+//! - `invoke`, `invoke_signed` and their `_unchecked` forms. The called program is read
+//!   from the instruction: the crate of its builder (`spl_token::instruction::transfer`
+//!   is `spl_token`, `system_instruction::transfer` is `system`), or the `program_id` of
+//!   `Instruction { .. }` and `Instruction::new_with_*` (`router::ID`, `router::id()`)
+//! - calls through an Anchor `<program>::cpi` module, which name the program and the
+//!   instruction
+//! - calls taking a `CpiContext`, named after the module of the function
+//!   (`token::transfer` is `token`) or else the program account the context was built
+//!   with (`token_program` is `token`). A `CpiContext` built and not passed to a call in
+//!   the same function, such as one returned by a helper, is a site where it is built.
+//!
+//! Instructions and contexts bound with `let` are followed back to their value.
+//! `#[cfg(test)]` items and `#[test]` functions are left out.
+
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+
+use proc_macro2::Span;
+use syn::visit::{self, Visit};
+use syn::{Expr, ExprCall, ImplItemFn, ItemFn, ItemImpl, ItemMod, ItemTrait, Local, Pat, TraitItemFn, UseTree};
+use trr_core::source::{self, is_cfg_test};
+
+use crate::{Call, CpiKind, CpiSite, Function, Handler, Location, Program};
+
+/// Module path of a source file, relative to the nearest ancestor holding `lib.rs` or
+/// `main.rs`: `src/instructions/deposit.rs` is `instructions::deposit`
+fn module_path(path: &Path) -> Vec<String> {
+    let is_crate_root = |dir: &Path| dir.join("lib.rs").is_file() || dir.join("main.rs").is_file();
+    let Some(relative) = path.ancestors().skip(1).find(|dir| is_crate_root(dir))
+        .and_then(|root| path.strip_prefix(root).ok())
+    else {
+        return Vec::new();
+    };
+    let mut module: Vec<String> = relative.iter().map(|component| component.to_string_lossy().into_owned()).collect();
+    if let Some(file_name) = module.pop() {
+        match file_name.strip_suffix(".rs") {
+            Some("mod") => {}
+            Some("lib" | "main") if module.is_empty() => {}
+            Some(stem) => module.push(stem.to_string()),
+            None => module.push(file_name),
+        }
+    }
+    module
+}
+
+fn segments(path: &syn::Path) -> Vec<String> {
+    path.segments.iter().map(|segment| segment.ident.to_string()).collect()
+}
+
+/// Names brought into scope by the `use` items of a file, with the paths they stand for
+fn collect_uses(tree: &UseTree, prefix: &mut Vec<String>, uses: &mut HashMap<String, Vec<String>>) {
+    match tree {
+        UseTree::Path(path) => {
+            prefix.push(path.ident.to_string());
+            collect_uses(&path.tree, prefix, uses);
+            prefix.pop();
+        }
+        UseTree::Name(name) if name.ident == "self" => {
+            if let Some(last) = prefix.last() {
+                uses.insert(last.clone(), prefix.clone());
+            }
+        }
+        UseTree::Name(name) => {
+            let mut path = prefix.clone();
+            path.push(name.ident.to_string());
+            uses.insert(name.ident.to_string(), path);
+        }
+        UseTree::Rename(rename) => {
+            let mut path = prefix.clone();
+            path.push(rename.ident.to_string());
+            uses.insert(rename.rename.to_string(), path);
+        }
+        UseTree::Group(group) => {
+            for tree in &group.items {
+                collect_uses(tree, prefix, uses);
+            }
+        }
+        UseTree::Glob(_) => {}
+    }
+}
+
+#[derive(Default)]
+struct Uses(HashMap<String, Vec<String>>);
+
+impl<'ast> Visit<'ast> for Uses {
+    fn visit_item_use(&mut self, item: &'ast syn::ItemUse) {
+        collect_uses(&item.tree, &mut Vec::new(), &mut self.0);
+    }
+}
+
+/// Parse `source`, the contents of `path`, adding its functions, handlers and CPI sites
+/// to `program`
+pub fn parse_file(program: &mut Program, path: &Path, source: &str) -> syn::Result<()> {
+    let file = syn::parse_file(source)?;
+    let mut uses = Uses::default();
+    uses.visit_file(&file);
+    let mut visitor = FileVisitor {
+        program,
+        path,
+        uses: uses.0,
+        module: module_path(path),
+        owner: None,
+        in_program_module: false,
+    };
+    visitor.visit_file(&file);
+    Ok(())
+}
+
+struct FileVisitor<'a> {
+    program: &'a mut Program,
+    path: &'a Path,
+    uses: HashMap<String, Vec<String>>,
+    module: Vec<String>,
+    /// The type of the impl, or the trait, whose functions are being visited
+    owner: Option<String>,
+    /// Inside the `#[program]` module, whose public functions are the handlers
+    in_program_module: bool,
+}
+
+impl FileVisitor<'_> {
+    fn location(&self, span: Span) -> Location {
+        source::location(self.path, span)
+    }
+
+    fn add_function(&mut self, ident: &syn::Ident, method: bool, block: &syn::Block) -> usize {
+        let mut path = self.module.clone();
+        path.extend(self.owner.clone());
+        path.push(ident.to_string());
+        let mut body = BodyVisitor {
+            file: self,
+            function: path.join("::"),
+            locals: HashMap::new(),
+            calls: Vec::new(),
+            sites: Vec::new(),
+            contexts: Vec::new(),
+            consumed: HashSet::new(),
+        };
+        body.visit_block(block);
+        let BodyVisitor { calls, mut sites, contexts, consumed, .. } = body;
+        // Contexts built here but used elsewhere are CPIs of this function
+        for (location, program) in contexts {
+            if !consumed.contains(&(location.line, location.column)) {
+                sites.push(CpiSite {
+                    kind: CpiKind::CpiContext,
+                    program,
+                    instruction: None,
+                    function: path.join("::"),
+                    location,
+                });
+            }
+        }
+
+        let first_site = self.program.sites.len();
+        self.program.sites.extend(sites);
+        self.program.functions.push(Function {
+            path,
+            location: self.location(ident.span()),
+            method,
+            calls,
+            sites: (first_site..self.program.sites.len()).collect(),
+        });
+        self.program.functions.len() - 1
+    }
+
+    /// `path` with its first segment expanded through the file's `use` items, and
+    /// `Self` replaced by the impl's type
+    fn resolve(&self, path: &[String]) -> Vec<String> {
+        let Some((first, rest)) = path.split_first() else { return Vec::new() };
+        let mut resolved = match (first.as_str(), &self.owner) {
+            ("Self", Some(owner)) => vec![owner.clone()],
+            _ => self.uses.get(first).cloned().unwrap_or_else(|| vec![first.clone()]),
+        };
+        resolved.extend(rest.iter().cloned());
+        resolved
+    }
+
+    /// The program a path names by its first segment, with `crate`, `self` and
+    /// `super` naming the analyzed one
+    fn program_of(&self, first: &str) -> String {
+        match first {
+            "crate" | "self" | "super" | "Self" => self.program.name.clone(),
+            _ => first.to_string(),
+        }
+    }
+}
+
+impl<'ast> Visit<'ast> for FileVisitor<'_> {
+    fn visit_item_mod(&mut self, item: &'ast ItemMod) {
+        if is_cfg_test(&item.attrs) {
+            return;
+        }
+        let in_program_module = self.in_program_module;
+        self.in_program_module = item.attrs.iter().any(|attr| attr.path().is_ident("program"));
+        self.module.push(item.ident.to_string());
+        visit::visit_item_mod(self, item);
+        self.module.pop();
+        self.in_program_module = in_program_module;
+    }
+
+    fn visit_item_fn(&mut self, item: &'ast ItemFn) {
+        if is_cfg_test(&item.attrs) {
+            return;
+        }
+        let handler = self.in_program_module && matches!(item.vis, syn::Visibility::Public(_));
+        let function = self.add_function(&item.sig.ident, false, &item.block);
+        if handler {
+            self.program.handlers.push(Handler {
+                name: item.sig.ident.to_string(),
+                location: self.location(item.sig.ident.span()),
+                function,
+            });
+        }
+    }
+
+    fn visit_item_impl(&mut self, item: &'ast ItemImpl) {
+        if is_cfg_test(&item.attrs) {
+            return;
+        }
+        let owner = match &*item.self_ty {
+            syn::Type::Path(path) => path.path.segments.last().map(|segment| segment.ident.to_string()),
+            _ => None,
+        };
+        let outer = std::mem::replace(&mut self.owner, owner);
+        visit::visit_item_impl(self, item);
+        self.owner = outer;
+    }
+
+    fn visit_impl_item_fn(&mut self, item: &'ast ImplItemFn) {
+        if !is_cfg_test(&item.attrs) {
+            self.add_function(&item.sig.ident, item.sig.receiver().is_some(), &item.block);
+        }
+    }
+
+    fn visit_item_trait(&mut self, item: &'ast ItemTrait) {
+        let outer = self.owner.replace(item.ident.to_string());
+        visit::visit_item_trait(self, item);
+        self.owner = outer;
+    }
+
+    fn visit_trait_item_fn(&mut self, item: &'ast TraitItemFn) {
+        if let Some(block) = &item.default {
+            self.add_function(&item.sig.ident, item.sig.receiver().is_some(), block);
+        }
+    }
+
+    fn visit_macro(&mut self, mac: &'ast syn::Macro) {
+        let is_entrypoint = mac.path.segments.last().is_some_and(|segment| segment.ident.to_string().starts_with("entrypoint"));
+        if let (true, Ok(path)) = (is_entrypoint, mac.parse_body::<syn::Path>()) {
+            let path = self.resolve(&segments(&path));
+            self.program.entrypoints.push(path);
+        }
+    }
+}
+
+/// What the body of one function calls
+struct BodyVisitor<'v, 'a> {
+    file: &'v FileVisitor<'a>,
+    function: String,
+    /// `let` bindings of plain names, last one wins
+    locals: HashMap<String, Expr>,
+    calls: Vec<Call>,
+    sites: Vec<CpiSite>,
+    /// The `CpiContext`s built, with the program they were built for
+    contexts: Vec<(Location, Option<String>)>,
+    /// Line and column of the contexts passed to a CPI call
+    consumed: HashSet<(usize, Option<usize>)>,
+}
+
+/// `expr` without references, parentheses and `?`
+fn strip(mut expr: &Expr) -> &Expr {
+    loop {
+        expr = match expr {
+            Expr::Reference(reference) => &reference.expr,
+            Expr::Paren(paren) => &paren.expr,
+            Expr::Try(try_expr) => &try_expr.expr,
+            _ => return expr,
+        };
+    }
+}
+
+/// The last segment of a `_program` account name, such as `token_program` in
+/// `ctx.accounts.token_program.to_account_info()`, as the program it stands for
+fn program_account(mut expr: &Expr) -> Option<String> {
+    loop {
+        expr = match strip(expr) {
+            Expr::MethodCall(call) => &call.receiver,
+            Expr::Field(field) => match &field.member {
+                syn::Member::Named(name) => return program_name(&name.to_string()),
+                syn::Member::Unnamed(_) => &field.base,
+            },
+            Expr::Path(path) => return path.path.get_ident().and_then(|ident| program_name(&ident.to_string())),
+            _ => return None,
+        };
+    }
+}
+
+fn program_name(account: &str) -> Option<String> {
+    account.strip_suffix("_program").filter(|name| !name.is_empty()).map(str::to_string)
+}
+
+impl BodyVisitor<'_, '_> {
+    /// `expr`, or the value it was bound to when it is a local name
+    fn value<'e>(&'e self, expr: &'e Expr) -> &'e Expr {
+        let expr = strip(expr);
+        match expr {
+            Expr::Path(path) => path.path.get_ident()
+                .and_then(|ident| self.locals.get(&ident.to_string()))
+                .map_or(expr, strip),
+            _ => expr,
+        }
+    }
+
+    /// The path of a call's function, resolved
+    fn call_path(&self, call: &ExprCall) -> Option<Vec<String>> {
+        match &*call.func {
+            Expr::Path(path) => Some(self.file.resolve(&segments(&path.path))),
+            _ => None,
+        }
+    }
+
+    /// The `CpiContext::new*` call `expr` is, or is built from with `.with_signer(..)`
+    /// and the like
+    fn cpi_context<'e>(&'e self, expr: &'e Expr) -> Option<&'e ExprCall> {
+        // Each name is followed once: `let accounts = &mut accounts.iter()` names itself
+        let mut followed = HashSet::new();
+        let mut expr = strip(expr);
+        loop {
+            if let Expr::Path(path) = expr {
+                if !path.path.get_ident().is_some_and(|ident| followed.insert(ident.to_string())) {
+                    return None;
+                }
+            }
+            expr = self.value(expr);
+            match expr {
+                Expr::MethodCall(call) => expr = strip(&call.receiver),
+                _ => break,
+            }
+        }
+        match expr {
+            Expr::Call(call) => {
+                let path = self.call_path(call)?;
+                let is_context = path.len() >= 2
+                    && path[path.len() - 2] == "CpiContext"
+                    && path[path.len() - 1].starts_with("new");
+                is_context.then_some(call)
+            }
+            _ => None,
+        }
+    }
+
+    /// The program a path to an instruction builder or program id names
+    fn program_of_path(&self, path: &[String]) -> Option<String> {
+        let start = path.iter()
+            .position(|segment| !matches!(segment.as_str(), "solana_program" | "solana_sdk" | "anchor_lang"))
+            .unwrap_or(path.len());
+        let path = &path[start..];
+        if path.iter().any(|segment| segment == "system_instruction" || segment == "system_program") {
+            return Some("system".to_string());
+        }
+        if let Some(position) = path.iter().position(|segment| segment == "instruction").filter(|&position| position > 0) {
+            return Some(self.file.program_of(&path[position - 1]));
+        }
+        match path {
+            [single] if matches!(single.as_str(), "ID" | "id") => Some(self.file.program.name.clone()),
+            [first, _, ..] => Some(self.file.program_of(first)),
+            _ => None,
+        }
+    }
+
+    /// The program a `program_id` expression names
+    fn program_id(&self, expr: &Expr) -> Option<String> {
+        match self.value(expr) {
+            Expr::Path(path) => self.program_of_path(&self.file.resolve(&segments(&path.path))),
+            Expr::Call(call) => self.program_of_path(&self.call_path(call)?),
+            Expr::Unary(unary) => self.program_id(&unary.expr),
+            expr => program_account(expr),
+        }
+    }
+
+    /// The program and instruction an instruction expression calls
+    fn instruction_target(&self, expr: &Expr) -> (Option<String>, Option<String>) {
+        match self.value(expr) {
+            Expr::Struct(instruction) => {
+                let program_id = instruction.fields.iter()
+                    .find(|field| matches!(&field.member, syn::Member::Named(name) if name == "program_id"));
+                (program_id.and_then(|field| self.program_id(&field.expr)), None)
+            }
+            Expr::Call(call) => {
+                let Some(path) = self.call_path(call) else { return (None, None) };
+                if path.len() >= 2 && path[path.len() - 2] == "Instruction" {
+                    return (call.args.first().and_then(|program_id| self.program_id(program_id)), None);
+                }
+                (self.program_of_path(&path), path.last().cloned())
+            }
+            _ => (None, None),
+        }
+    }
+
+    fn add_site(&mut self, kind: CpiKind, program: Option<String>, instruction: Option<String>, span: Span) {
+        self.sites.push(CpiSite {
+            kind,
+            program,
+            instruction,
+            function: self.function.clone(),
+            location: self.file.location(span),
+        });
+    }
+
+    /// Record `call` as a CPI site if it is one, and tell whether it was
+    fn cpi_call(&mut self, call: &ExprCall, path: &[String]) -> bool {
+        let span = syn::spanned::Spanned::span(call);
+        let name = path.last().map_or("", String::as_str);
+        if matches!(name, "invoke" | "invoke_signed" | "invoke_unchecked" | "invoke_signed_unchecked") {
+            let kind = if name.starts_with("invoke_signed") { CpiKind::InvokeSigned } else { CpiKind::Invoke };
+            let (program, instruction) = call.args.first()
+                .map_or((None, None), |instruction| self.instruction_target(instruction));
+            self.add_site(kind, program, instruction, span);
+            return true;
+        }
+
+        let contexts: Vec<Location> = call.args.iter()
+            .filter_map(|arg| self.cpi_context(arg))
+            .map(|context| self.file.location(syn::spanned::Spanned::span(context)))
+            .collect();
+        let module = path.iter().rposition(|segment| segment == "cpi").filter(|&position| position > 0 && position + 1 < path.len());
+        if module.is_none() && contexts.is_empty() {
+            return false;
+        }
+        let context_program = call.args.iter()
+            .filter_map(|arg| self.cpi_context(arg))
+            .find_map(|context| context.args.first().and_then(|program| program_account(self.value(program))));
+        self.consumed.extend(contexts.iter().map(|location| (location.line, location.column)));
+        match module {
+            Some(position) => {
+                let program = self.file.program_of(&path[position - 1]);
+                self.add_site(CpiKind::AnchorCpi, Some(program), Some(name.to_string()), span);
+            }
+            None => {
+                let program = match path {
+                    [.., module, _] => Some(self.file.program_of(module)),
+                    _ => context_program,
+                };
+                self.add_site(CpiKind::CpiContext, program, Some(name.to_string()), span);
+            }
+        }
+        true
+    }
+}
+
+impl<'ast> Visit<'ast> for BodyVisitor<'_, '_> {
+    fn visit_local(&mut self, local: &'ast Local) {
+        // Visit the value first, so `let ix = ix.clone()` does not follow itself
+        visit::visit_local(self, local);
+        let pat = match &local.pat {
+            Pat::Type(typed) => &*typed.pat,
+            pat => pat,
+        };
+        if let (Pat::Ident(ident), Some(init)) = (pat, &local.init) {
+            self.locals.insert(ident.ident.to_string(), (*init.expr).clone());
+        }
+    }
+
+    fn visit_expr_call(&mut self, call: &'ast ExprCall) {
+        if let Some(path) = self.call_path(call) {
+            let is_context = path.len() >= 2 && path[path.len() - 2] == "CpiContext" && path[path.len() - 1].starts_with("new");
+            if is_context {
+                let program = call.args.first().and_then(|program| program_account(self.value(program)));
+                self.contexts.push((self.file.location(syn::spanned::Spanned::span(call)), program));
+            } else if !self.cpi_call(call, &path) {
+                let location = self.file.location(syn::spanned::Spanned::span(call));
+                self.calls.push(Call { path, method: false, location });
+            }
+        }
+        visit::visit_expr_call(self, call);
+    }
+
+    fn visit_expr_method_call(&mut self, call: &'ast syn::ExprMethodCall) {
+        let location = self.file.location(call.method.span());
+        self.calls.push(Call { path: vec![call.method.to_string()], method: true, location });
+        visit::visit_expr_method_call(self, call);
+    }
+}
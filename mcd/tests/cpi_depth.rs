@@ -0,0 +1,108 @@
+// Runs the mcd binary over the workspace in tests/fixtures, where an Anchor vault calls
+// a native router, which calls an oracle, which calls the system program.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+
+use serde_json::Value;
+
+fn workspace() -> PathBuf {
+    Path::new(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/workspace")).to_path_buf()
+}
+
+fn mcd(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_mcd"))
+        .args(args)
+        .arg(workspace())
+        .output()
+        .expect("failed to run mcd")
+}
+
+/// Run mcd with the given arguments and return its stdout
+fn run_mcd(args: &[&str]) -> String {
+    let output = mcd(args);
+    assert!(
+        output.status.success(),
+        "mcd failed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    String::from_utf8(output.stdout).expect("mcd output is not UTF-8")
+}
+
+fn json_report() -> Value {
+    serde_json::from_str(&run_mcd(&["--format", "json"])).expect("mcd printed invalid JSON")
+}
+
+#[test]
+fn depth_per_handler() {
+    let report = json_report();
+    let depths: HashMap<String, u64> = report["handlers"].as_array().unwrap().iter()
+        .map(|handler| (
+            format!("{}::{}", handler["program"].as_str().unwrap(), handler["handler"].as_str().unwrap()),
+            handler["depth"].as_u64().unwrap(),
+        ))
+        .collect();
+
+    let expected = [
+        // vault → router → oracle → system
+        ("vault::swap_and_deposit", 3),
+        ("router::process_instruction", 2),
+        ("oracle::process", 1),
+        // CpiContext handed to token::transfer in a helper module
+        ("vault::deposit", 1),
+        ("vault::close", 0),
+    ];
+    assert_eq!(depths.len(), expected.len(), "handlers: {:?}", depths);
+    for (handler, depth) in expected {
+        assert_eq!(depths.get(handler), Some(&depth), "depth of {}", handler);
+    }
+    assert_eq!(report["handlers"][0]["handler"], "swap_and_deposit", "deepest handler comes first");
+}
+
+#[test]
+fn chain_and_summary() {
+    let report = json_report();
+    assert_eq!(report["schema_version"], 1);
+    assert_eq!(report["tool"], "mcd");
+
+    let chain: Vec<String> = report["handlers"][0]["chain"].as_array().unwrap().iter()
+        .map(|site| format!("{} {}", site["kind"].as_str().unwrap(), site["program"].as_str().unwrap()))
+        .collect();
+    assert_eq!(chain, ["anchor-cpi router", "invoke oracle", "invoke system"]);
+
+    let summary = &report["summary"];
+    assert_eq!(summary["programs"], 3);
+    assert_eq!(summary["max_depth"], 3);
+    assert_eq!(summary["limit"], 4);
+    // Test files and #[cfg(test)] modules are skipped
+    assert_eq!(summary["cpi_sites"], 6);
+    // oracle::forward invokes an instruction its caller passes in
+    assert_eq!(summary["unresolved_sites"], 1);
+}
+
+#[test]
+fn fail_above() {
+    run_mcd(&["--fail-above", "3"]);
+
+    let output = mcd(&["--fail-above", "2"]);
+    assert_eq!(output.status.code(), Some(1));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("vault::swap_and_deposit (depth 3)"), "stderr: {}", stderr);
+    assert!(!stderr.contains("router::process_instruction"), "stderr: {}", stderr);
+}
+
+#[test]
+fn sarif_reports_handlers_over_the_limit() {
+    let output = mcd(&["--format", "sarif", "--fail-above", "1"]);
+    assert_eq!(output.status.code(), Some(1));
+    let log: Value = serde_json::from_slice(&output.stdout).unwrap();
+    let results = log["runs"][0]["results"].as_array().unwrap();
+    let depths: Vec<u64> = results.iter()
+        .map(|result| result["properties"]["depth"].as_u64().unwrap())
+        .collect();
+    assert_eq!(depths, [3, 2]);
+    assert!(results[0]["message"]["text"].as_str().unwrap().starts_with("vault::swap_and_deposit "));
+    assert_eq!(results[0]["ruleId"], "cpi-depth");
+    assert_eq!(results[0]["relatedLocations"].as_array().unwrap().len(), 3);
+}
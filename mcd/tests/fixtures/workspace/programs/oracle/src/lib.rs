@@ -0,0 +1,17 @@
+use solana_program::{entrypoint, program::invoke, system_instruction};
+
+entrypoint!(process);
+
+fn process(program_id: &Pubkey, accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
+    pay_rent(accounts)?;
+    forward(accounts, data)
+}
+
+fn pay_rent(accounts: &[AccountInfo]) -> ProgramResult {
+    invoke(&system_instruction::transfer(accounts[0].key, accounts[1].key, 1), accounts)
+}
+
+// The instruction comes from the caller, so its program is not known
+fn forward(accounts: &[AccountInfo], instruction: Instruction) -> ProgramResult {
+    invoke(&instruction, accounts)
+}
@@ -0,0 +1,11 @@
+use solana_program::{account_info::AccountInfo, entrypoint, entrypoint::ProgramResult, pubkey::Pubkey};
+
+pub mod processor;
+
+use processor::Processor;
+
+entrypoint!(process_instruction);
+
+pub fn process_instruction(program_id: &Pubkey, accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
+    Processor::process(program_id, accounts, data)
+}
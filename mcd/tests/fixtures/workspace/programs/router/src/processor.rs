@@ -0,0 +1,32 @@
+use solana_program::instruction::Instruction;
+use solana_program::program::{invoke, invoke_signed};
+
+pub struct Processor;
+
+impl Processor {
+    pub fn process(program_id: &Pubkey, accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
+        match data[0] {
+            0 => Self::swap(program_id, accounts),
+            _ => Self::refresh(accounts),
+        }
+    }
+
+    fn swap(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+        let ix = spl_token::instruction::transfer(&spl_token::ID, source, destination, authority, &[], 1)?;
+        invoke_signed(&ix, accounts, &[&[b"router"]])?;
+        Self::refresh(accounts)
+    }
+
+    fn refresh(accounts: &[AccountInfo]) -> ProgramResult {
+        let accounts = &mut accounts.iter();
+        let feed = next_account_info(accounts)?;
+        invoke(&Instruction { program_id: oracle::ID, accounts: vec![], data: vec![] }, &[feed.clone()])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    fn swap_twice() {
+        invoke(&Instruction { program_id: vault::ID, accounts: vec![], data: vec![] }, &[]).unwrap();
+    }
+}
@@ -0,0 +1,11 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token::{self, Transfer};
+
+pub fn handler(ctx: Context<Deposit>, amount: u64) -> Result<()> {
+    let accounts = Transfer {
+        from: ctx.accounts.user_tokens.to_account_info(),
+        to: ctx.accounts.vault_tokens.to_account_info(),
+        authority: ctx.accounts.user.to_account_info(),
+    };
+    token::transfer(CpiContext::new(ctx.accounts.token_program.to_account_info(), accounts), amount)
+}
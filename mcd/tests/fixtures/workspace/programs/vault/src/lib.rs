@@ -0,0 +1,36 @@
+use anchor_lang::prelude::*;
+
+pub mod instructions;
+
+use instructions::*;
+
+#[program]
+pub mod vault {
+    use super::*;
+
+    // deposit → token::transfer: depth 1
+    pub fn deposit(ctx: Context<Deposit>, amount: u64) -> Result<()> {
+        instructions::deposit::handler(ctx, amount)
+    }
+
+    // swap_and_deposit → router::swap → oracle → system: depth 3
+    pub fn swap_and_deposit(ctx: Context<Swap>, amount: u64) -> Result<()> {
+        let cpi_ctx = CpiContext::new(
+            ctx.accounts.router_program.to_account_info(),
+            router::cpi::accounts::Swap { pool: ctx.accounts.pool.to_account_info() },
+        );
+        router::cpi::swap(cpi_ctx, amount)?;
+        ctx.accounts.record(amount)
+    }
+
+    pub fn close(_ctx: Context<Close>) -> Result<()> {
+        Ok(())
+    }
+}
+
+impl<'info> Swap<'info> {
+    pub fn record(&self, amount: u64) -> Result<()> {
+        msg!("swapped {}", amount);
+        Ok(())
+    }
+}
@@ -0,0 +1,4 @@
+// Test code is not part of the program
+fn deposit_twice() {
+    invoke(&spl_token::instruction::transfer(), &[]).unwrap();
+}
@@ -0,0 +1,341 @@
+//! Compute-unit estimate: a heuristic ranking of the instruction handlers of Solana
+//! programs by the compute they may use, from the known expensive operations they
+//! reach (see [`operations`]).
+//!
+//! Handlers, the functions they call and their CPIs come from [`mcd`]. Every operation
+//! has a fixed estimate in compute units, counted [`LOOP_ITERATIONS`] times for each
+//! loop around it, in its own function or around a call leading to it. The numbers
+//! rank handlers to benchmark first; they do not predict what a transaction uses.
+
+pub mod operations;
+
+use std::collections::{BTreeSet, HashMap};
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use mcd::FunctionId;
+use serde::Serialize;
+pub use trr_core::Location;
+
+/// How many times a loop body counts, since the number of iterations is not known
+pub const LOOP_ITERATIONS: u64 = 10;
+
+/// Loops nested deeper than this count as this deep
+pub const MAX_LOOP_NESTING: u32 = 2;
+
+/// Compute units of an instruction without a `SetComputeUnitLimit`
+pub const DEFAULT_COMPUTE_BUDGET: u64 = 200_000;
+
+/// Compute units of a CPI, leaving out what the called program uses
+pub const CPI_UNITS: u64 = 1_000;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Category {
+    /// Borsh, bincode and `Pack` (de)serialization, and Anchor's `reload`
+    Serialization,
+    /// The sha256, keccak and blake3 syscalls
+    Hashing,
+    /// Signature verification, secp256k1 recovery, curve syscalls and instruction
+    /// introspection for the ed25519 and secp256k1 precompiles
+    Crypto,
+    /// `msg!`, `sol_log*` and Anchor events
+    Logging,
+    /// Program address derivation
+    Pda,
+    Cpi,
+    /// Loops over `remaining_accounts`, as long as the caller makes them
+    RemainingAccounts,
+}
+
+impl Category {
+    pub const ALL: [Category; 7] = [
+        Category::Serialization,
+        Category::Hashing,
+        Category::Crypto,
+        Category::Logging,
+        Category::Pda,
+        Category::Cpi,
+        Category::RemainingAccounts,
+    ];
+
+    /// As in JSON and CSV
+    pub fn name(self) -> &'static str {
+        match self {
+            Category::Serialization => "serialization",
+            Category::Hashing => "hashing",
+            Category::Crypto => "crypto",
+            Category::Logging => "logging",
+            Category::Pda => "pda",
+            Category::Cpi => "cpi",
+            Category::RemainingAccounts => "remaining-accounts",
+        }
+    }
+}
+
+impl fmt::Display for Category {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.pad(match self {
+            Category::Serialization => "serialization",
+            Category::Hashing => "hashing",
+            Category::Crypto => "signatures and curves",
+            Category::Logging => "logging",
+            Category::Pda => "PDA derivation",
+            Category::Cpi => "CPI",
+            Category::RemainingAccounts => "remaining_accounts loops",
+        })
+    }
+}
+
+/// An expensive operation in a function body
+#[derive(Clone, Debug, Serialize)]
+pub struct Operation {
+    pub category: Category,
+    /// What is called: `msg!`, `try_to_vec`, `find_program_address`, ...
+    pub name: String,
+    /// Estimated compute units of one execution
+    pub units: u64,
+    pub location: Location,
+}
+
+/// The body of a loop, or of a closure given to an iterator adapter
+#[derive(Clone, Debug)]
+pub struct Loop {
+    /// Line and 1-based column of the start of the body
+    pub start: (usize, usize),
+    /// Line and 1-based column of its end
+    pub end: (usize, usize),
+}
+
+/// The operations and loops of one function
+#[derive(Debug, Default)]
+pub struct FunctionCosts {
+    pub operations: Vec<Operation>,
+    pub loops: Vec<Loop>,
+}
+
+impl FunctionCosts {
+    /// How many of the function's loops `location` is in, up to [`MAX_LOOP_NESTING`]
+    pub fn nesting(&self, location: &Location) -> u32 {
+        let point = (location.line, location.column.unwrap_or(0));
+        let loops = self.loops.iter().filter(|body| body.start <= point && point <= body.end).count();
+        (loops as u32).min(MAX_LOOP_NESTING)
+    }
+}
+
+fn loop_multiplier(nesting: u32) -> u64 {
+    LOOP_ITERATIONS.pow(nesting)
+}
+
+/// How much of the default compute budget a handler's estimate takes
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Risk {
+    Low,
+    /// A tenth of the default budget, or a loop over `remaining_accounts`
+    Medium,
+    /// Half of the default budget
+    High,
+}
+
+impl Risk {
+    fn of(units: u64, unbounded_loops: usize) -> Risk {
+        if units >= DEFAULT_COMPUTE_BUDGET / 2 {
+            Risk::High
+        } else if units >= DEFAULT_COMPUTE_BUDGET / 10 || unbounded_loops > 0 {
+            Risk::Medium
+        } else {
+            Risk::Low
+        }
+    }
+}
+
+impl fmt::Display for Risk {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.pad(match self {
+            Risk::Low => "low",
+            Risk::Medium => "medium",
+            Risk::High => "high",
+        })
+    }
+}
+
+/// An operation a handler reaches, with how many times it counts
+#[derive(Clone, Debug, Serialize)]
+pub struct Cost {
+    #[serde(flatten)]
+    pub operation: Operation,
+    /// Path of the function it is in
+    pub function: String,
+    /// [`LOOP_ITERATIONS`] for each loop around it, up to [`MAX_LOOP_NESTING`]
+    pub multiplier: u64,
+    /// Its units times the multiplier
+    pub total: u64,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct CategoryTotal {
+    pub category: Category,
+    pub count: usize,
+    pub units: u64,
+}
+
+/// The estimate of one handler
+#[derive(Clone, Debug, Serialize)]
+pub struct HandlerEstimate {
+    pub program: String,
+    pub handler: String,
+    pub location: Location,
+    /// Estimated compute units
+    pub units: u64,
+    pub risk: Risk,
+    /// Loops over `remaining_accounts` it reaches
+    pub unbounded_loops: usize,
+    /// Totals of the categories it has operations of, most expensive first
+    pub categories: Vec<CategoryTotal>,
+    /// Its operations, most expensive first
+    pub operations: Vec<Cost>,
+}
+
+/// The programs under a directory, with the costly operations of their functions
+#[derive(Debug, Default)]
+pub struct Analysis {
+    /// Programs, handlers, calls and CPI sites
+    pub programs: mcd::Analysis,
+    /// The operations and loops of each function, by the location of its name
+    pub costs: HashMap<Location, FunctionCosts>,
+    /// Files that could not be read or parsed
+    pub errors: Vec<(PathBuf, String)>,
+}
+
+impl Analysis {
+    fn function_costs(&self, (program, function): FunctionId) -> Option<&FunctionCosts> {
+        self.costs.get(&self.programs.programs[program].functions[function].location)
+    }
+
+    /// The functions a handler reaches, each with the multiplier of the loops around
+    /// the calls leading to it (the largest, when several calls do)
+    fn reachable(&self, start: FunctionId) -> HashMap<FunctionId, u64> {
+        let max = loop_multiplier(MAX_LOOP_NESTING);
+        let mut multipliers = HashMap::from([(start, 1)]);
+        let mut stack = vec![start];
+        while let Some(id) = stack.pop() {
+            let multiplier = multipliers[&id];
+            let costs = self.function_costs(id);
+            for call in &self.programs.programs[id.0].functions[id.1].calls {
+                let nesting = costs.map_or(0, |costs| costs.nesting(&call.location));
+                let callee_multiplier = (multiplier * loop_multiplier(nesting)).min(max);
+                for callee in self.programs.callees(id.0, call) {
+                    if multipliers.get(&callee).is_none_or(|&known| known < callee_multiplier) {
+                        multipliers.insert(callee, callee_multiplier);
+                        stack.push(callee);
+                    }
+                }
+            }
+        }
+        multipliers
+    }
+
+    /// The operations of a function, CPI sites included, counted `multiplier` times
+    fn function_operations(&self, id: FunctionId, multiplier: u64) -> Vec<Cost> {
+        let program = &self.programs.programs[id.0];
+        let function = &program.functions[id.1];
+        let costs = self.function_costs(id);
+        let cpis = function.sites.iter().map(|&site| {
+            let site = &program.sites[site];
+            Operation {
+                category: Category::Cpi,
+                name: site.target(),
+                units: CPI_UNITS,
+                location: site.location.clone(),
+            }
+        });
+        let max = loop_multiplier(MAX_LOOP_NESTING);
+        costs.into_iter()
+            .flat_map(|costs| costs.operations.iter().cloned())
+            .chain(cpis)
+            .map(|operation| {
+                let nesting = costs.map_or(0, |costs| costs.nesting(&operation.location));
+                let multiplier = (multiplier * loop_multiplier(nesting)).min(max);
+                Cost {
+                    total: operation.units * multiplier,
+                    operation,
+                    function: function.path.join("::"),
+                    multiplier,
+                }
+            })
+            .collect()
+    }
+
+    /// Every handler with its estimate, most expensive first
+    pub fn handler_estimates(&self) -> Vec<HandlerEstimate> {
+        let mut estimates: Vec<HandlerEstimate> = self.programs.programs.iter()
+            .enumerate()
+            .flat_map(|(index, program)| program.handlers.iter().map(move |handler| (index, program, handler)))
+            .map(|(index, program, handler)| {
+                let mut operations: Vec<Cost> = self.reachable((index, handler.function)).into_iter()
+                    .flat_map(|(id, multiplier)| self.function_operations(id, multiplier))
+                    .collect();
+                operations.sort_by(|a, b| b.total.cmp(&a.total)
+                    .then_with(|| a.operation.location.file.cmp(&b.operation.location.file))
+                    .then_with(|| a.operation.location.line.cmp(&b.operation.location.line)));
+
+                let mut categories: Vec<CategoryTotal> = Category::ALL.iter()
+                    .map(|&category| {
+                        let of_category = operations.iter().filter(|cost| cost.operation.category == category);
+                        CategoryTotal {
+                            category,
+                            count: of_category.clone().count(),
+                            units: of_category.map(|cost| cost.total).sum(),
+                        }
+                    })
+                    .filter(|total| total.count > 0)
+                    .collect();
+                categories.sort_by_key(|total| std::cmp::Reverse(total.units));
+
+                let units = operations.iter().map(|cost| cost.total).sum();
+                let unbounded_loops = operations.iter()
+                    .filter(|cost| cost.operation.category == Category::RemainingAccounts)
+                    .count();
+                HandlerEstimate {
+                    program: program.name.clone(),
+                    handler: handler.name.clone(),
+                    location: handler.location.clone(),
+                    units,
+                    risk: Risk::of(units, unbounded_loops),
+                    unbounded_loops,
+                    categories,
+                    operations,
+                }
+            })
+            .collect();
+        estimates.sort_by(|a, b| b.units.cmp(&a.units)
+            .then_with(|| a.program.cmp(&b.program))
+            .then_with(|| a.handler.cmp(&b.handler)));
+        estimates
+    }
+}
+
+/// Analyze every Rust file under `dir` (or `dir` itself, if it is a file), leaving out
+/// `target/` and test code, as [`mcd::analyze`] does
+pub fn analyze(dir: &Path) -> io::Result<Analysis> {
+    let programs = mcd::analyze(dir)?;
+    let files: BTreeSet<&Path> = programs.programs.iter()
+        .flat_map(|program| &program.functions)
+        .map(|function| function.location.file.as_path())
+        .collect();
+
+    let mut costs = HashMap::new();
+    let mut errors = programs.errors.clone();
+    for file in files {
+        let result = fs::read_to_string(file)
+            .map_err(|e| e.to_string())
+            .and_then(|source| operations::parse_file(file, &source, &mut costs).map_err(|e| e.to_string()));
+        if let Err(e) = result {
+            errors.push((file.to_path_buf(), e));
+        }
+    }
+    Ok(Analysis { programs, costs, errors })
+}
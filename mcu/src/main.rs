@@ -0,0 +1,203 @@
+mod report;
+mod sarif;
+
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::process;
+
+use clap::Parser;
+use serde::Serialize;
+use trr_core::git::Repository;
+use trr_core::output::{self, Format};
+
+use mcu::{Analysis, HandlerEstimate, Risk, DEFAULT_COMPUTE_BUDGET};
+
+#[derive(Parser)]
+#[clap(author, version, about = "Compute Unit (MCU) Estimator")]
+#[clap(after_help = "Estimates are heuristic: use them to pick the handlers to benchmark.
+
+Examples:
+  mcu programs/vault
+  mcu -v --top 5 programs
+  mcu --format markdown programs > compute-units.md
+  mcu --fail-above 150000 programs")]
+struct Args {
+    /// Directory to analyze (relative to --repo when given)
+    #[clap(value_name = "DIR", default_value = ".")]
+    dir: PathBuf,
+
+    /// Git repository URL to clone, or path of a local checkout
+    #[clap(long, value_name = "REPO_URL_OR_PATH")]
+    repo: Option<String>,
+
+    /// Branch, tag or commit of --repo to analyze
+    #[clap(long, value_name = "REV", requires = "repo")]
+    rev: Option<String>,
+
+    /// Output format
+    #[clap(long, value_enum, default_value = "text")]
+    format: Format,
+
+    /// List every operation of each handler, not just the five most expensive
+    #[clap(short, long)]
+    verbose: bool,
+
+    /// Only the N most expensive handlers
+    #[clap(long, value_name = "N")]
+    top: Option<usize>,
+
+    /// Exit with status 1 if any handler's estimate is over UNITS
+    #[clap(long, value_name = "UNITS")]
+    fail_above: Option<u64>,
+}
+
+/// Structured form of the analysis for `--format json`
+#[derive(Serialize)]
+struct JsonReport<'a> {
+    summary: Summary,
+    /// Handlers, most expensive first
+    handlers: &'a [HandlerEstimate],
+    errors: Vec<FileError>,
+}
+
+#[derive(Serialize)]
+struct Summary {
+    programs: usize,
+    handlers: usize,
+    max_units: u64,
+    high_risk: usize,
+    medium_risk: usize,
+    unbounded_loops: usize,
+    /// The budget risk levels are measured against
+    default_budget: u64,
+}
+
+#[derive(Serialize)]
+struct FileError {
+    file: PathBuf,
+    error: String,
+}
+
+/// Print `handlers` as a ranking, with the most expensive operations of each (all of
+/// them when `verbose`)
+fn print_text(analysis: &Analysis, handlers: &[HandlerEstimate], verbose: bool) {
+    println!("\nPrograms:");
+    for program in &analysis.programs.programs {
+        println!("  {} ({} handlers)", program.name, program.handlers.len());
+    }
+
+    if handlers.is_empty() {
+        println!("\nNo instruction handlers found (no #[program] module or entrypoint!)");
+        return;
+    }
+    println!("\nInstruction handlers by estimated compute units:");
+    for (rank, handler) in handlers.iter().enumerate() {
+        println!(
+            "\n{}. {}::{} (at {}): ~{} CU, {} risk",
+            rank + 1, handler.program, handler.handler, handler.location, handler.units, handler.risk
+        );
+        if !handler.categories.is_empty() {
+            let categories: Vec<String> = handler.categories.iter()
+                .map(|total| format!("{} {} ({})", total.category, total.units, total.count))
+                .collect();
+            println!("   {}", categories.join(", "));
+        }
+        if handler.unbounded_loops > 0 {
+            println!("   ⚠ loops over remaining_accounts: the caller chooses how many times they run");
+        }
+        let shown = if verbose { handler.operations.len() } else { 5 };
+        for cost in handler.operations.iter().take(shown) {
+            let times = if cost.multiplier > 1 { format!(" ×{}", cost.multiplier) } else { String::new() };
+            println!(
+                "   - {}{} ({}): {} CU in {} at {}",
+                cost.operation.name, times, cost.operation.category, cost.total, cost.function, cost.operation.location
+            );
+        }
+        if handler.operations.len() > shown {
+            println!("   ... {} more (-v lists them)", handler.operations.len() - shown);
+        }
+    }
+
+    let max_units = handlers.first().map_or(0, |handler| handler.units);
+    println!("\nHighest estimate: ~{} CU (default budget {} CU per instruction)", max_units, DEFAULT_COMPUTE_BUDGET);
+}
+
+fn write_json(out: &mut dyn Write, analysis: &Analysis, all: &[HandlerEstimate], handlers: &[HandlerEstimate]) -> io::Result<()> {
+    let risk = |level: Risk| all.iter().filter(|handler| handler.risk == level).count();
+    let report = JsonReport {
+        summary: Summary {
+            programs: analysis.programs.programs.len(),
+            handlers: all.len(),
+            max_units: all.first().map_or(0, |handler| handler.units),
+            high_risk: risk(Risk::High),
+            medium_risk: risk(Risk::Medium),
+            unbounded_loops: all.iter().map(|handler| handler.unbounded_loops).sum(),
+            default_budget: DEFAULT_COMPUTE_BUDGET,
+        },
+        handlers,
+        errors: analysis.errors.iter()
+            .map(|(file, error)| FileError { file: file.clone(), error: error.clone() })
+            .collect(),
+    };
+    output::write_json(out, "mcu", &report)
+}
+
+/// With `--fail-above`, list the handlers estimated over the limit and exit with
+/// status 1
+fn enforce_limit(handlers: &[HandlerEstimate], fail_above: Option<u64>) {
+    let Some(limit) = fail_above else { return };
+    let offending: Vec<&HandlerEstimate> = handlers.iter().filter(|handler| handler.units > limit).collect();
+    if offending.is_empty() {
+        return;
+    }
+    let header = format!("{} handler(s) are estimated over {} CU", offending.len(), limit);
+    output::fail(&header, offending.iter().map(|handler| {
+        format!("{}::{} (~{} CU) at {}", handler.program, handler.handler, handler.units, handler.location)
+    }));
+}
+
+fn run(args: &Args) -> Result<(), Box<dyn std::error::Error>> {
+    let repository = args.repo.as_deref().map(|repo| Repository::open(repo, args.rev.as_deref())).transpose()?;
+    let dir = match &repository {
+        Some(repository) => repository.subdir(&args.dir)?,
+        None => args.dir.clone(),
+    };
+    if !dir.exists() {
+        return Err(format!("Path '{}' does not exist", dir.display()).into());
+    }
+    if args.format == Format::Text {
+        println!("Analyzing: {}", dir.display());
+    }
+
+    let analysis = mcu::analyze(&dir)?;
+    for (file, error) in &analysis.errors {
+        eprintln!("Error analyzing {}: {}", file.display(), error);
+    }
+    let all = analysis.handler_estimates();
+    let handlers = &all[..args.top.map_or(all.len(), |top| top.min(all.len()))];
+
+    let mut out = io::stdout().lock();
+    match args.format {
+        Format::Text => print_text(&analysis, handlers, args.verbose),
+        Format::Json => write_json(&mut out, &analysis, &all, handlers)?,
+        Format::Csv => report::write_csv(&mut out, handlers)?,
+        Format::Markdown => report::write_markdown(&mut out, &dir, &analysis, handlers)?,
+        Format::Sarif => {
+            // Artifact URIs are relative to the repository root, or to where mcu runs
+            let base = repository.as_ref().map_or_else(|| PathBuf::from("."), |repository| repository.root().to_path_buf());
+            sarif::write_sarif(&mut out, handlers, args.fail_above, &base)?;
+            writeln!(out)?;
+        }
+    }
+    drop(out);
+    enforce_limit(&all, args.fail_above);
+    Ok(())
+}
+
+fn main() {
+    let args = Args::parse();
+    if let Err(e) = run(&args) {
+        eprintln!("Error: {}", e);
+        process::exit(1);
+    }
+}
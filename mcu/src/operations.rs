@@ -0,0 +1,241 @@
+//! Finding the expensive operations of each function, with their estimated compute
+//! units:
+//!
+//! - (de)serialization: `try_to_vec`, `try_from_slice`, `serialize`/`deserialize`,
+//!   Anchor's `try_serialize`/`try_deserialize` and `reload`, `Pack::unpack`/`pack`,
+//!   `borsh::to_vec` (2,000)
+//! - hashing: `hash`/`hashv` of the `hash`, `keccak` and `blake3` modules and the
+//!   `sol_*` hashing syscalls (200)
+//! - signatures and curves: `secp256k1_recover` (25,000), ed25519 verification
+//!   (30,000), `alt_bn128_*`, `curve25519` and `poseidon` syscalls (10,000), and the
+//!   instruction introspection used to check precompile signatures (1,000)
+//! - logging: `msg!` with a literal (100) or with format arguments (1,000), `sol_log*`
+//!   (100), `emit!` (1,000) and `emit_cpi!` (2,000)
+//! - `find_program_address` (3,000, as it may try several bumps) and
+//!   `create_program_address` (1,500)
+//! - loops over `remaining_accounts` (1,000, for walking the accounts)
+//!
+//! The bodies of `for`, `while` and `loop`, and closures given to iterator adapters
+//! such as `map` and `for_each`, are recorded as loops. `#[cfg(test)]` items and
+//! `#[test]` functions are left out, as [`mcd`] leaves them out.
+
+use std::collections::HashMap;
+use std::path::Path;
+
+use proc_macro2::{LineColumn, Span};
+use syn::punctuated::Punctuated;
+use syn::spanned::Spanned;
+use syn::visit::{self, Visit};
+use syn::{Expr, ExprCall, ExprMethodCall, ImplItemFn, ItemFn, ItemImpl, ItemMod, TraitItemFn};
+use trr_core::source::{is_cfg_test, location};
+
+use crate::{Category, FunctionCosts, Location, Loop, Operation};
+
+/// Methods that (de)serialize their receiver or into it
+const SERIALIZATION_METHODS: &[&str] = &[
+    "try_to_vec", "try_serialize", "try_deserialize", "try_deserialize_unchecked", "serialize", "deserialize",
+    "reload", "pack_into_slice", "unpack_from_slice",
+];
+
+/// Functions that (de)serialize, called by path (`Vault::try_from_slice(..)`)
+const SERIALIZATION_FUNCTIONS: &[&str] = &[
+    "try_from_slice", "try_from_slice_unchecked", "try_serialize", "try_deserialize", "try_deserialize_unchecked",
+    "unpack", "unpack_unchecked", "unpack_from_slice", "pack", "pack_into_slice", "serialize", "deserialize",
+];
+
+const HASH_SYSCALLS: &[&str] = &["sol_sha256", "sol_keccak256", "sol_blake3"];
+
+const INTROSPECTION: &[&str] = &["load_instruction_at_checked", "get_instruction_relative", "load_instruction_at"];
+
+const LOG_FUNCTIONS: &[&str] = &["sol_log", "sol_log_64", "sol_log_data", "sol_log_compute_units", "sol_log_params", "sol_log_slice"];
+
+/// Iterator adapters whose closure runs once per item
+const ITERATOR_METHODS: &[&str] = &[
+    "for_each", "try_for_each", "map", "filter", "filter_map", "flat_map", "fold", "try_fold", "any", "all",
+    "find", "find_map", "position", "scan", "take_while", "skip_while", "inspect", "map_while",
+];
+
+/// Parse `source`, the contents of `path`, adding the costs of its functions to
+/// `costs`, keyed by the location of the function's name
+pub fn parse_file(path: &Path, source: &str, costs: &mut HashMap<Location, FunctionCosts>) -> syn::Result<()> {
+    let file = syn::parse_file(source)?;
+    FileVisitor { path, costs }.visit_file(&file);
+    Ok(())
+}
+
+fn point(position: LineColumn) -> (usize, usize) {
+    (position.line, position.column + 1)
+}
+
+struct FileVisitor<'a> {
+    path: &'a Path,
+    costs: &'a mut HashMap<Location, FunctionCosts>,
+}
+
+impl FileVisitor<'_> {
+    fn add_function(&mut self, ident: &syn::Ident, block: &syn::Block) {
+        let mut body = BodyVisitor { path: self.path, costs: FunctionCosts::default() };
+        body.visit_block(block);
+        self.costs.insert(location(self.path, ident.span()), body.costs);
+    }
+}
+
+impl<'ast> Visit<'ast> for FileVisitor<'_> {
+    fn visit_item_mod(&mut self, item: &'ast ItemMod) {
+        if !is_cfg_test(&item.attrs) {
+            visit::visit_item_mod(self, item);
+        }
+    }
+
+    fn visit_item_fn(&mut self, item: &'ast ItemFn) {
+        if !is_cfg_test(&item.attrs) {
+            self.add_function(&item.sig.ident, &item.block);
+        }
+    }
+
+    fn visit_item_impl(&mut self, item: &'ast ItemImpl) {
+        if !is_cfg_test(&item.attrs) {
+            visit::visit_item_impl(self, item);
+        }
+    }
+
+    fn visit_impl_item_fn(&mut self, item: &'ast ImplItemFn) {
+        if !is_cfg_test(&item.attrs) {
+            self.add_function(&item.sig.ident, &item.block);
+        }
+    }
+
+    fn visit_trait_item_fn(&mut self, item: &'ast TraitItemFn) {
+        if let Some(block) = &item.default {
+            self.add_function(&item.sig.ident, block);
+        }
+    }
+}
+
+/// Where an expression names `remaining_accounts`
+#[derive(Default)]
+struct RemainingAccounts(Option<Span>);
+
+impl<'ast> Visit<'ast> for RemainingAccounts {
+    fn visit_ident(&mut self, ident: &'ast syn::Ident) {
+        if self.0.is_none() && ident == "remaining_accounts" {
+            self.0 = Some(ident.span());
+        }
+    }
+}
+
+fn remaining_accounts(expr: &Expr) -> Option<Span> {
+    let mut finder = RemainingAccounts::default();
+    finder.visit_expr(expr);
+    finder.0
+}
+
+/// The operations and loops of one function body
+struct BodyVisitor<'a> {
+    path: &'a Path,
+    costs: FunctionCosts,
+}
+
+impl BodyVisitor<'_> {
+    fn add(&mut self, category: Category, name: impl Into<String>, units: u64, span: Span) {
+        let location = location(self.path, span);
+        // A chain of adapters over `remaining_accounts` names it once
+        if self.costs.operations.iter().any(|operation| operation.location == location) {
+            return;
+        }
+        self.costs.operations.push(Operation { category, name: name.into(), units, location });
+    }
+
+    fn add_loop(&mut self, span: Span, iterated: Option<&Expr>) {
+        self.costs.loops.push(Loop { start: point(span.start()), end: point(span.end()) });
+        if let Some(span) = iterated.and_then(remaining_accounts) {
+            self.add(Category::RemainingAccounts, "remaining_accounts", 1_000, span);
+        }
+    }
+
+    /// The operation a call of `path` is, if any
+    fn call(&mut self, path: &[String], span: Span) {
+        let Some(name) = path.last().map(String::as_str) else { return };
+        let in_module = |modules: &[&str]| path.iter().any(|segment| modules.contains(&segment.as_str()));
+        if SERIALIZATION_FUNCTIONS.contains(&name)
+            || (name == "to_vec" && in_module(&["borsh", "bincode"]))
+        {
+            self.add(Category::Serialization, name, 2_000, span);
+        } else if HASH_SYSCALLS.contains(&name)
+            || (matches!(name, "hash" | "hashv") && (path.len() == 1 || in_module(&["hash", "keccak", "blake3"])))
+        {
+            self.add(Category::Hashing, name, 200, span);
+        } else if name == "secp256k1_recover" {
+            self.add(Category::Crypto, name, 25_000, span);
+        } else if name.contains("verify") && path.iter().any(|segment| segment.contains("ed25519")) {
+            self.add(Category::Crypto, name, 30_000, span);
+        } else if path.iter().any(|segment| {
+            segment.starts_with("alt_bn128") || segment.starts_with("curve25519") || segment.starts_with("poseidon")
+        }) {
+            self.add(Category::Crypto, name, 10_000, span);
+        } else if INTROSPECTION.contains(&name) {
+            self.add(Category::Crypto, name, 1_000, span);
+        } else if LOG_FUNCTIONS.contains(&name) {
+            self.add(Category::Logging, name, 100, span);
+        } else if matches!(name, "find_program_address" | "try_find_program_address") {
+            self.add(Category::Pda, name, 3_000, span);
+        } else if name == "create_program_address" {
+            self.add(Category::Pda, name, 1_500, span);
+        }
+    }
+}
+
+impl<'ast> Visit<'ast> for BodyVisitor<'_> {
+    fn visit_expr_call(&mut self, call: &'ast ExprCall) {
+        if let Expr::Path(path) = &*call.func {
+            let segments: Vec<String> = path.path.segments.iter().map(|segment| segment.ident.to_string()).collect();
+            self.call(&segments, call.span());
+        }
+        visit::visit_expr_call(self, call);
+    }
+
+    fn visit_expr_method_call(&mut self, call: &'ast ExprMethodCall) {
+        let name = call.method.to_string();
+        if SERIALIZATION_METHODS.contains(&name.as_str()) {
+            self.add(Category::Serialization, name.as_str(), 2_000, call.method.span());
+        } else if name == "verify_strict" {
+            self.add(Category::Crypto, name.as_str(), 30_000, call.method.span());
+        } else if ITERATOR_METHODS.contains(&name.as_str()) {
+            for closure in call.args.iter().filter(|arg| matches!(arg, Expr::Closure(_))) {
+                self.add_loop(closure.span(), Some(&call.receiver));
+            }
+        }
+        visit::visit_expr_method_call(self, call);
+    }
+
+    fn visit_expr_for_loop(&mut self, expr: &'ast syn::ExprForLoop) {
+        self.add_loop(expr.body.span(), Some(&expr.expr));
+        visit::visit_expr_for_loop(self, expr);
+    }
+
+    fn visit_expr_while(&mut self, expr: &'ast syn::ExprWhile) {
+        self.add_loop(expr.body.span(), Some(&expr.cond));
+        visit::visit_expr_while(self, expr);
+    }
+
+    fn visit_expr_loop(&mut self, expr: &'ast syn::ExprLoop) {
+        self.add_loop(expr.body.span(), None);
+        visit::visit_expr_loop(self, expr);
+    }
+
+    fn visit_macro(&mut self, mac: &'ast syn::Macro) {
+        let Some(name) = mac.path.segments.last().map(|segment| segment.ident.to_string()) else { return };
+        let span = mac.path.span();
+        match name.as_str() {
+            "msg" => {
+                let formatted = mac.parse_body_with(Punctuated::<Expr, syn::Token![,]>::parse_terminated)
+                    .map_or(true, |args| args.len() > 1);
+                let units = if formatted { 1_000 } else { 100 };
+                self.add(Category::Logging, "msg!", units, span);
+            }
+            "emit" => self.add(Category::Logging, "emit!", 1_000, span),
+            "emit_cpi" => self.add(Category::Logging, "emit_cpi!", 2_000, span),
+            _ => {}
+        }
+    }
+}
@@ -0,0 +1,82 @@
+//! `--format csv`, one row per handler with its units per category, and `--format
+//! markdown`, a review-ready ranking of the handlers.
+
+use std::io::{self, Write};
+use std::path::Path;
+
+use trr_core::output::{markdown_cell, markdown_code, write_csv_row};
+
+use mcu::{Analysis, Category, HandlerEstimate, Risk, DEFAULT_COMPUTE_BUDGET};
+
+/// Rows are most expensive first, with a column of units per category, and `file` and
+/// `line` pointing at the handler
+pub fn write_csv(out: &mut dyn Write, handlers: &[HandlerEstimate]) -> io::Result<()> {
+    let mut header = vec!["program", "handler", "units", "risk"];
+    header.extend(Category::ALL.iter().map(|category| category.name()));
+    header.extend(["file", "line"]);
+    write_csv_row(out, &header)?;
+    for handler in handlers {
+        let mut row = vec![
+            handler.program.clone(),
+            handler.handler.clone(),
+            handler.units.to_string(),
+            handler.risk.to_string(),
+        ];
+        row.extend(Category::ALL.iter().map(|&category| {
+            let units: u64 = handler.categories.iter()
+                .filter(|total| total.category == category)
+                .map(|total| total.units)
+                .sum();
+            units.to_string()
+        }));
+        row.push(handler.location.file.display().to_string());
+        row.push(handler.location.line.to_string());
+        let row: Vec<&str> = row.iter().map(String::as_str).collect();
+        write_csv_row(out, &row)?;
+    }
+    Ok(())
+}
+
+pub fn write_markdown(out: &mut dyn Write, target: &Path, analysis: &Analysis, handlers: &[HandlerEstimate]) -> io::Result<()> {
+    let risk = |level: Risk| handlers.iter().filter(|handler| handler.risk == level).count();
+    writeln!(out, "# Compute Unit Estimate\n")?;
+    writeln!(out, "Target: {}\n", markdown_code(&target.display().to_string()))?;
+    writeln!(out, "Estimates are heuristic: they rank the handlers to benchmark first.\n")?;
+    writeln!(out, "## Summary\n")?;
+    writeln!(out, "| Metric | Value |")?;
+    writeln!(out, "| --- | ---: |")?;
+    writeln!(out, "| Programs | {} |", analysis.programs.programs.len())?;
+    writeln!(out, "| Instruction handlers | {} |", handlers.len())?;
+    writeln!(out, "| Highest estimate (CU) | {} |", handlers.first().map_or(0, |handler| handler.units))?;
+    writeln!(out, "| High risk handlers | {} |", risk(Risk::High))?;
+    writeln!(out, "| Medium risk handlers | {} |", risk(Risk::Medium))?;
+    writeln!(out, "| Loops over `remaining_accounts` | {} |", handlers.iter().map(|handler| handler.unbounded_loops).sum::<usize>())?;
+    writeln!(out, "| Default budget (CU) | {} |", DEFAULT_COMPUTE_BUDGET)?;
+
+    if handlers.is_empty() {
+        return Ok(());
+    }
+    writeln!(out, "\n## Ranking\n")?;
+    writeln!(out, "| # | Handler | Estimate (CU) | Risk | Main costs | Location |")?;
+    writeln!(out, "| ---: | --- | ---: | --- | --- | --- |")?;
+    for (rank, handler) in handlers.iter().enumerate() {
+        let risk = match handler.risk {
+            Risk::High => "**high**".to_string(),
+            risk => risk.to_string(),
+        };
+        let costs: Vec<String> = handler.categories.iter()
+            .map(|total| markdown_cell(&format!("{} {}", total.category, total.units)))
+            .collect();
+        writeln!(
+            out,
+            "| {} | {} | {} | {} | {} | {} |",
+            rank + 1,
+            markdown_code(&format!("{}::{}", handler.program, handler.handler)),
+            handler.units,
+            risk,
+            costs.join(", "),
+            markdown_code(&handler.location.to_string()),
+        )?;
+    }
+    Ok(())
+}
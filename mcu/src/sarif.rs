@@ -0,0 +1,60 @@
+//! SARIF 2.1.0 output: one result per handler over `--fail-above`, or without it per
+//! high risk handler, with its five most expensive operations as related locations.
+
+use std::io::{self, Write};
+use std::path::Path;
+
+use serde_json::json;
+use trr_core::sarif::{location, write_log};
+
+use mcu::{HandlerEstimate, Risk};
+
+const RULE_ID: &str = "compute-units";
+
+pub fn write_sarif(out: &mut dyn Write, handlers: &[HandlerEstimate], limit: Option<u64>, base: &Path) -> io::Result<()> {
+    let results: Vec<_> = handlers.iter()
+        .filter(|handler| match limit {
+            Some(limit) => handler.units > limit,
+            None => handler.risk == Risk::High,
+        })
+        .map(|handler| {
+            let related: Vec<_> = handler.operations.iter()
+                .take(5)
+                .enumerate()
+                .map(|(i, cost)| {
+                    let mut related = location(&cost.operation.location, base);
+                    related["id"] = json!(i);
+                    related["message"] = json!({
+                        "text": format!("{} {}: {} CU", cost.operation.category, cost.operation.name, cost.total),
+                    });
+                    related
+                })
+                .collect();
+            let over = match limit {
+                Some(limit) => format!("over {} CU", limit),
+                None => "high risk".to_string(),
+            };
+            json!({
+                "ruleId": RULE_ID,
+                "level": "warning",
+                "message": {
+                    "text": format!("{}::{} is estimated at ~{} CU ({})", handler.program, handler.handler, handler.units, over),
+                },
+                "locations": [location(&handler.location, base)],
+                "relatedLocations": related,
+                "properties": { "units": handler.units, "risk": handler.risk },
+            })
+        })
+        .collect();
+
+    let rule = json!({
+        "id": RULE_ID,
+        "name": "ComputeUnitEstimate",
+        "shortDescription": { "text": "Instruction handler with a high compute-unit estimate" },
+        "fullDescription": {
+            "text": "The expensive operations an instruction handler reaches (serialization, hashing, signature checks, logging, PDA derivation, CPIs and loops over remaining_accounts) add up to a high heuristic estimate. Benchmark it against the compute budget.",
+        },
+        "defaultConfiguration": { "level": "warning" },
+    });
+    write_log(out, "mcu", env!("CARGO_PKG_VERSION"), vec![rule], results)
+}
@@ -0,0 +1,83 @@
+// Estimates the handlers of the fixture programs through the library, and checks the
+// binary's JSON and --fail-above.
+
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use mcu::{Category, HandlerEstimate, Risk};
+use serde_json::Value;
+
+fn fixture(name: &str) -> PathBuf {
+    Path::new(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures")).join(name)
+}
+
+fn estimates() -> Vec<HandlerEstimate> {
+    mcu::analyze(&fixture("rewards")).unwrap().handler_estimates()
+}
+
+fn estimate<'a>(estimates: &'a [HandlerEstimate], handler: &str) -> &'a HandlerEstimate {
+    estimates.iter().find(|estimate| estimate.handler == handler).unwrap()
+}
+
+#[test]
+fn ranking() {
+    let estimates = estimates();
+    let ranking: Vec<(&str, u64, Risk)> = estimates.iter()
+        .map(|estimate| (estimate.handler.as_str(), estimate.units, estimate.risk))
+        .collect();
+    assert_eq!(ranking, [
+        ("verify_batch", 300_000, Risk::High),
+        ("settle", 57_200, Risk::Medium),
+        ("claim", 31_000, Risk::Medium),
+        ("initialize", 5_100, Risk::Low),
+    ]);
+}
+
+#[test]
+fn loops_multiply_operations_and_calls() {
+    let estimates = estimates();
+
+    let claim = estimate(&estimates, "claim");
+    assert_eq!(claim.unbounded_loops, 1);
+    let deserialize = claim.operations.iter().find(|cost| cost.operation.name == "try_deserialize").unwrap();
+    assert_eq!((deserialize.multiplier, deserialize.total), (10, 20_000));
+
+    // Pool::settle_member is called in a loop, in another module
+    let settle = estimate(&estimates, "settle");
+    let pda = settle.operations.iter().find(|cost| cost.operation.category == Category::Pda).unwrap();
+    assert_eq!(pda.function, "state::Pool::settle_member");
+    assert_eq!(pda.multiplier, 10);
+    assert_eq!(settle.categories[0].category, Category::Pda);
+}
+
+#[test]
+fn test_code_is_left_out() {
+    let estimates = estimates();
+    assert!(estimates.iter()
+        .flat_map(|estimate| &estimate.operations)
+        .all(|cost| !cost.function.starts_with("tests")));
+}
+
+#[test]
+fn json_and_fail_above() {
+    let run = |args: &[&str]| Command::new(env!("CARGO_BIN_EXE_mcu"))
+        .args(args)
+        .arg(fixture("rewards"))
+        .output()
+        .expect("failed to run mcu");
+
+    let output = run(&["--format", "json", "--top", "2"]);
+    assert!(output.status.success());
+    let report: Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(report["tool"], "mcu");
+    assert_eq!(report["summary"]["handlers"], 4);
+    assert_eq!(report["summary"]["high_risk"], 1);
+    assert_eq!(report["summary"]["max_units"], 300_000);
+    assert_eq!(report["handlers"].as_array().unwrap().len(), 2);
+    assert_eq!(report["handlers"][1]["categories"][0]["category"], "pda");
+
+    let output = run(&["--fail-above", "50000"]);
+    assert_eq!(output.status.code(), Some(1));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("2 handler(s) are estimated over 50000 CU"), "stderr: {}", stderr);
+}
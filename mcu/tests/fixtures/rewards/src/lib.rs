@@ -0,0 +1,56 @@
+use anchor_lang::prelude::*;
+use solana_program::keccak;
+
+pub mod state;
+
+use state::{Member, Pool};
+
+#[program]
+pub mod rewards {
+    use super::*;
+
+    // msg! 100 + find_program_address 3,000 + try_to_vec 2,000 = 5,100: low
+    pub fn initialize(ctx: Context<Initialize>) -> Result<()> {
+        msg!("initializing pool");
+        let (_, bump) = Pubkey::find_program_address(&[b"pool"], ctx.program_id);
+        ctx.accounts.pool.bump = bump;
+        let _ = ctx.accounts.pool.try_to_vec()?;
+        Ok(())
+    }
+
+    // remaining_accounts 1,000 + 10 × (try_deserialize 2,000 + formatted msg! 1,000) = 31,000: medium
+    pub fn claim(ctx: Context<Claim>) -> Result<()> {
+        for account in ctx.remaining_accounts.iter() {
+            let member = Member::try_deserialize(&mut &account.data.borrow()[..])?;
+            msg!("claimed {} for {}", member.points, account.key);
+        }
+        Ok(())
+    }
+
+    // 10 × (Pool::settle_member: find_program_address 3,000 + hashv 200)
+    //   + secp256k1_recover 25,000 + hash 200 = 57,200: medium
+    pub fn settle(ctx: Context<Settle>, members: Vec<Pubkey>, signature: [u8; 64]) -> Result<()> {
+        for member in &members {
+            ctx.accounts.pool.settle_member(member, ctx.program_id);
+        }
+        secp256k1_recover(&keccak::hash(&signature).0, 0, &signature).unwrap();
+        Ok(())
+    }
+
+    // 10 × ed25519 verification 30,000 = 300,000: high
+    pub fn verify_batch(_ctx: Context<Verify>, signatures: Vec<Signed>) -> Result<()> {
+        signatures.iter().for_each(|signed| {
+            signed.key.verify_strict(&signed.message, &signed.signature).unwrap();
+        });
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    fn hash_everything() {
+        for _ in 0..1000 {
+            solana_program::hash::hash(b"test");
+        }
+    }
+}
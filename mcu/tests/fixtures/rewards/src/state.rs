@@ -0,0 +1,19 @@
+use anchor_lang::prelude::*;
+use solana_program::keccak;
+
+#[account]
+pub struct Pool {
+    pub bump: u8,
+}
+
+#[account]
+pub struct Member {
+    pub points: u64,
+}
+
+impl Pool {
+    pub fn settle_member(&mut self, member: &Pubkey, program_id: &Pubkey) {
+        let (address, _) = Pubkey::find_program_address(&[b"member", member.as_ref()], program_id);
+        keccak::hashv(&[address.as_ref()]);
+    }
+}
@@ -0,0 +1,412 @@
+//! Where each function and `#[derive(Accounts)]` struct copies or deserializes account
+//! data whole.
+//!
+//! Account data is a borrow of a `.data` field (`vault.data.borrow()`), `try_borrow_data`
+//! and `try_borrow_mut_data`, and the `let` bindings taken from them. On it:
+//! - a copy is `to_vec()` or `to_owned()`: `vault.data.borrow().to_vec()`
+//! - a deserialization is an associated function of the account type (`try_from_slice`,
+//!   `deserialize`, `try_deserialize`, `unpack` and their variants), `bincode::deserialize`,
+//!   `borsh::from_slice`, `try_from_slice_unchecked` and `AccountInfo::deserialize_data`;
+//!   the type is the path's (`Vault::try_from_slice`), the turbofish's
+//!   (`bincode::deserialize::<Vault>`) or the `let` annotation's
+//! - Anchor's `Account::try_from(info)` deserializes the account it is given
+//!
+//! A copy takes the type of a deserialization of the same account in its function. In
+//! an accounts struct, every `Account<'info, T>` and `InterfaceAccount<'info, T>` is
+//! deserialized whole before the handler runs. `#[cfg(test)]` items and `#[test]`
+//! functions are left out, as [`mcd`] leaves them out.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use proc_macro2::Span;
+use serde::Serialize;
+use syn::punctuated::Punctuated;
+use syn::spanned::Spanned;
+use syn::visit::{self, Visit};
+use syn::{Expr, Fields, ImplItemFn, ItemFn, ItemImpl, ItemMod, ItemStruct, Pat, TraitItemFn, Type};
+use trr_core::anchor::{context_accounts, derives_accounts};
+use trr_core::source::{is_cfg_test, location};
+
+use crate::{Kind, Location};
+
+/// Associated functions deserializing the type they are called on
+const DESERIALIZERS: &[&str] = &[
+    "try_from_slice", "try_from_slice_unchecked", "deserialize", "try_deserialize", "try_deserialize_unchecked",
+    "unpack", "unpack_unchecked", "unpack_from_slice",
+];
+
+/// Traits whose `deserialize` is called as `Trait::deserialize`, naming no type
+const DESERIALIZE_TRAITS: &[&str] = &["BorshDeserialize", "AnchorDeserialize", "AccountDeserialize", "Pack", "Deserialize"];
+
+/// Anchor wrappers deserializing the account they hold
+const ACCOUNT_WRAPPERS: &[&str] = &["Account", "InterfaceAccount"];
+
+/// Methods borrowing an account's data
+const BORROWS: &[&str] = &["try_borrow_data", "try_borrow_mut_data"];
+
+/// Methods borrowing the `RefCell` of an account's `data` field
+const REF_CELL_BORROWS: &[&str] = &["borrow", "borrow_mut", "try_borrow", "try_borrow_mut"];
+
+/// Account data copied or deserialized whole
+#[derive(Clone, Debug, Serialize)]
+pub struct Copy {
+    pub kind: Kind,
+    /// The call or type, as written: `to_vec`, `Vault::try_from_slice`, `Account<'info, Vault>`
+    pub call: String,
+    /// The account, as written
+    pub account: String,
+    /// The type the data holds, when known
+    #[serde(rename = "type")]
+    pub ty: Option<String>,
+    /// In a `for`, `while` or `loop` body
+    pub in_loop: bool,
+    pub location: Location,
+}
+
+#[derive(Debug, Default)]
+pub struct FunctionFacts {
+    /// The accounts struct `T` of a `Context<T>` parameter
+    pub context: Option<String>,
+    pub copies: Vec<Copy>,
+}
+
+#[derive(Clone, Debug)]
+pub struct AccountsStruct {
+    pub name: String,
+    pub file: PathBuf,
+    /// Its `Account` and `InterfaceAccount` fields
+    pub copies: Vec<Copy>,
+}
+
+#[derive(Debug, Default)]
+pub struct Facts {
+    /// Keyed by the location of the function's name
+    pub functions: HashMap<Location, FunctionFacts>,
+    pub structs: Vec<AccountsStruct>,
+}
+
+/// Add the functions and accounts structs of `file`, the syntax of `path`, whose
+/// contents are `source`, to `facts`
+pub fn add_file(path: &Path, source: &str, file: &syn::File, facts: &mut Facts) {
+    FileVisitor { path, source, facts }.visit_file(file);
+}
+
+/// The source of `span`, its lines joined with spaces
+fn code(source: &str, span: Span) -> String {
+    let (start, end) = (span.start(), span.end());
+    let lines: Vec<&str> = source.lines().skip(start.line.saturating_sub(1)).take(end.line + 1 - start.line).collect();
+    if lines.len() == 1 {
+        return lines[0].chars().skip(start.column).take(end.column - start.column).collect();
+    }
+    let last = lines.len().saturating_sub(1);
+    lines.iter().enumerate()
+        .map(|(index, line)| match index {
+            0 => line.chars().skip(start.column).collect::<String>(),
+            _ if index == last => line.chars().take(end.column).collect::<String>(),
+            _ => line.to_string(),
+        })
+        .map(|line| line.trim().to_string())
+        .filter(|line| !line.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// The last type argument of a path segment: the `T` of `Account<'info, T>`
+fn last_type_argument(segment: &syn::PathSegment) -> Option<&Type> {
+    let syn::PathArguments::AngleBracketed(args) = &segment.arguments else { return None };
+    args.args.iter().rev().find_map(|arg| match arg {
+        syn::GenericArgument::Type(ty) => Some(ty),
+        _ => None,
+    })
+}
+
+/// The name of the type held: `Vault` for `Vault`, `Box<Account<'info, Vault>>` or
+/// `&Vault`
+fn held_type(ty: &Type) -> Option<String> {
+    match ty {
+        Type::Path(path) => {
+            let segment = path.path.segments.last()?;
+            match segment.ident.to_string().as_str() {
+                "Box" | "Option" | "Account" | "InterfaceAccount" => held_type(last_type_argument(segment)?),
+                name => Some(name.to_string()),
+            }
+        }
+        Type::Reference(reference) => held_type(&reference.elem),
+        Type::Paren(paren) => held_type(&paren.elem),
+        _ => None,
+    }
+}
+
+/// The names a pattern binds
+fn bindings(pattern: &Pat, names: &mut Vec<String>) {
+    match pattern {
+        Pat::Ident(ident) => names.push(ident.ident.to_string()),
+        Pat::Type(typed) => bindings(&typed.pat, names),
+        Pat::Reference(reference) => bindings(&reference.pat, names),
+        Pat::Tuple(tuple) => tuple.elems.iter().for_each(|elem| bindings(elem, names)),
+        _ => {}
+    }
+}
+
+/// Whether a field's type is an `Account` or `InterfaceAccount`, through `Box`
+fn is_deserialized_account(ty: &Type) -> bool {
+    let Type::Path(path) = ty else { return false };
+    let Some(segment) = path.path.segments.last() else { return false };
+    match segment.ident.to_string().as_str() {
+        "Box" => last_type_argument(segment).is_some_and(is_deserialized_account),
+        name => ACCOUNT_WRAPPERS.contains(&name),
+    }
+}
+
+struct FileVisitor<'a> {
+    path: &'a Path,
+    source: &'a str,
+    facts: &'a mut Facts,
+}
+
+impl FileVisitor<'_> {
+    fn add_function(&mut self, sig: &syn::Signature, block: &syn::Block) {
+        let mut body = BodyVisitor {
+            path: self.path,
+            source: self.source,
+            data: HashMap::new(),
+            loops: 0,
+            facts: FunctionFacts { context: context_accounts(sig), ..FunctionFacts::default() },
+        };
+        body.visit_block(block);
+        let mut facts = body.facts;
+
+        // A copy holds what the same account deserializes to
+        let types: HashMap<String, String> = facts.copies.iter()
+            .filter(|copy| copy.kind == Kind::Deserialize)
+            .filter_map(|copy| Some((copy.account.clone(), copy.ty.clone()?)))
+            .collect();
+        for copy in facts.copies.iter_mut().filter(|copy| copy.ty.is_none()) {
+            copy.ty = types.get(&copy.account).cloned();
+        }
+        self.facts.functions.insert(location(self.path, sig.ident.span()), facts);
+    }
+}
+
+impl<'ast> Visit<'ast> for FileVisitor<'_> {
+    fn visit_item_mod(&mut self, item: &'ast ItemMod) {
+        if !is_cfg_test(&item.attrs) {
+            visit::visit_item_mod(self, item);
+        }
+    }
+
+    fn visit_item_fn(&mut self, item: &'ast ItemFn) {
+        if !is_cfg_test(&item.attrs) {
+            self.add_function(&item.sig, &item.block);
+        }
+    }
+
+    fn visit_item_impl(&mut self, item: &'ast ItemImpl) {
+        if !is_cfg_test(&item.attrs) {
+            visit::visit_item_impl(self, item);
+        }
+    }
+
+    fn visit_impl_item_fn(&mut self, item: &'ast ImplItemFn) {
+        if !is_cfg_test(&item.attrs) {
+            self.add_function(&item.sig, &item.block);
+        }
+    }
+
+    fn visit_trait_item_fn(&mut self, item: &'ast TraitItemFn) {
+        if let Some(block) = &item.default {
+            self.add_function(&item.sig, block);
+        }
+    }
+
+    fn visit_item_struct(&mut self, item: &'ast ItemStruct) {
+        if is_cfg_test(&item.attrs) || !derives_accounts(&item.attrs) {
+            return;
+        }
+        let Fields::Named(fields) = &item.fields else { return };
+        let copies = fields.named.iter()
+            .filter(|field| is_deserialized_account(&field.ty))
+            .filter_map(|field| {
+                let ident = field.ident.as_ref()?;
+                Some(Copy {
+                    kind: Kind::Account,
+                    call: code(self.source, field.ty.span()),
+                    account: ident.to_string(),
+                    ty: held_type(&field.ty),
+                    in_loop: false,
+                    location: location(self.path, ident.span()),
+                })
+            })
+            .collect();
+        self.facts.structs.push(AccountsStruct { name: item.ident.to_string(), file: self.path.to_path_buf(), copies });
+    }
+}
+
+/// The account whose data an expression reads first: the account of a data borrow, or
+/// the account a local name was taken from
+struct DataOf<'a> {
+    source: &'a str,
+    data: &'a HashMap<String, String>,
+    account: Option<String>,
+}
+
+impl<'ast> Visit<'ast> for DataOf<'_> {
+    fn visit_expr_method_call(&mut self, call: &'ast syn::ExprMethodCall) {
+        if self.account.is_some() {
+            return;
+        }
+        let method = call.method.to_string();
+        if BORROWS.contains(&method.as_str()) {
+            self.account = Some(code(self.source, call.receiver.span()));
+            return;
+        }
+        if let Expr::Field(field) = &*call.receiver {
+            if REF_CELL_BORROWS.contains(&method.as_str()) && matches!(&field.member, syn::Member::Named(name) if name == "data") {
+                self.account = Some(code(self.source, field.base.span()));
+                return;
+            }
+        }
+        visit::visit_expr_method_call(self, call);
+    }
+
+    fn visit_expr_path(&mut self, path: &'ast syn::ExprPath) {
+        if self.account.is_none() {
+            self.account = path.path.get_ident().and_then(|ident| self.data.get(&ident.to_string())).cloned();
+        }
+    }
+}
+
+/// The copies and deserializations of one function body, leaving out the items
+/// declared in it
+struct BodyVisitor<'a> {
+    path: &'a Path,
+    source: &'a str,
+    /// Local names holding account data, with the account
+    data: HashMap<String, String>,
+    /// Loops the visitor is in
+    loops: usize,
+    facts: FunctionFacts,
+}
+
+impl BodyVisitor<'_> {
+    fn data_of<'e>(&self, exprs: impl IntoIterator<Item = &'e Expr>) -> Option<String> {
+        let mut data_of = DataOf { source: self.source, data: &self.data, account: None };
+        for expr in exprs {
+            data_of.visit_expr(expr);
+        }
+        data_of.account
+    }
+
+    fn add(&mut self, kind: Kind, call: String, account: String, ty: Option<String>, span: Span) {
+        self.facts.copies.push(Copy { kind, call, account, ty, in_loop: self.loops > 0, location: location(self.path, span) });
+    }
+
+    fn in_loop(&mut self, visit: impl FnOnce(&mut Self)) {
+        self.loops += 1;
+        visit(self);
+        self.loops -= 1;
+    }
+}
+
+impl<'ast> Visit<'ast> for BodyVisitor<'_> {
+    fn visit_item(&mut self, _: &'ast syn::Item) {}
+
+    fn visit_local(&mut self, local: &'ast syn::Local) {
+        let start = self.facts.copies.len();
+        visit::visit_local(self, local);
+        let Some(init) = &local.init else { return };
+
+        // `let vault: Vault = bincode::deserialize(&data)?`
+        if let Pat::Type(typed) = &local.pat {
+            let ty = held_type(&typed.ty);
+            for copy in self.facts.copies[start..].iter_mut().filter(|copy| copy.kind == Kind::Deserialize && copy.ty.is_none()) {
+                copy.ty = ty.clone();
+            }
+        }
+        if let Some(account) = self.data_of([&*init.expr]) {
+            let mut bound = Vec::new();
+            bindings(&local.pat, &mut bound);
+            for name in bound {
+                self.data.insert(name, account.clone());
+            }
+        }
+    }
+
+    fn visit_expr_call(&mut self, call: &'ast syn::ExprCall) {
+        if let Expr::Path(path) = &*call.func {
+            let segments: Vec<&syn::PathSegment> = path.path.segments.iter().collect();
+            let names: Vec<String> = segments.iter().map(|segment| segment.ident.to_string()).collect();
+            let function = names.last().map(String::as_str).unwrap_or_default();
+            let owner = names.len().checked_sub(2).map(|index| names[index].as_str());
+            let turbofish = segments.last().and_then(|segment| last_type_argument(segment)).and_then(held_type);
+            let call_text = || names.join("::");
+
+            match (owner, function) {
+                // `Account::<Vault>::try_from(&info)`
+                (Some(wrapper), "try_from" | "try_from_unchecked") if ACCOUNT_WRAPPERS.contains(&wrapper) => {
+                    if let Some(info) = call.args.first() {
+                        let ty = segments[segments.len() - 2..].iter().find_map(|segment| last_type_argument(segment)).and_then(held_type);
+                        self.add(Kind::Deserialize, call_text(), code(self.source, info.span()), ty, path.span());
+                    }
+                }
+                (Some("bincode"), "deserialize") | (Some("borsh"), "from_slice") | (_, "try_from_slice_unchecked")
+                    if owner.is_none_or(|owner| !owner.starts_with(char::is_uppercase)) =>
+                {
+                    if let Some(account) = self.data_of(&call.args) {
+                        self.add(Kind::Deserialize, call_text(), account, turbofish, path.span());
+                    }
+                }
+                // `Vault::try_from_slice(&data)`
+                (Some(owner), function) if DESERIALIZERS.contains(&function) && owner.starts_with(char::is_uppercase) => {
+                    if let Some(account) = self.data_of(&call.args) {
+                        let ty = (!DESERIALIZE_TRAITS.contains(&owner)).then(|| owner.to_string()).or(turbofish);
+                        self.add(Kind::Deserialize, call_text(), account, ty, path.span());
+                    }
+                }
+                _ => {}
+            }
+        }
+        visit::visit_expr_call(self, call);
+    }
+
+    fn visit_expr_method_call(&mut self, call: &'ast syn::ExprMethodCall) {
+        match call.method.to_string().as_str() {
+            "to_vec" | "to_owned" => {
+                if let Some(account) = self.data_of([&*call.receiver]) {
+                    self.add(Kind::Copy, call.method.to_string(), account, None, call.method.span());
+                }
+            }
+            // `AccountInfo::deserialize_data`, bincode
+            "deserialize_data" => {
+                let ty = call.turbofish.as_ref().and_then(|turbofish| turbofish.args.first()).and_then(|arg| match arg {
+                    syn::GenericArgument::Type(ty) => held_type(ty),
+                    _ => None,
+                });
+                self.add(Kind::Deserialize, "deserialize_data".to_string(), code(self.source, call.receiver.span()), ty, call.method.span());
+            }
+            _ => {}
+        }
+        visit::visit_expr_method_call(self, call);
+    }
+
+    fn visit_expr_for_loop(&mut self, expr: &'ast syn::ExprForLoop) {
+        self.visit_expr(&expr.expr);
+        self.in_loop(|body| body.visit_block(&expr.body));
+    }
+
+    fn visit_expr_while(&mut self, expr: &'ast syn::ExprWhile) {
+        self.in_loop(|body| visit::visit_expr_while(body, expr));
+    }
+
+    fn visit_expr_loop(&mut self, expr: &'ast syn::ExprLoop) {
+        self.in_loop(|body| visit::visit_expr_loop(body, expr));
+    }
+
+    fn visit_macro(&mut self, mac: &'ast syn::Macro) {
+        let Ok(args) = mac.parse_body_with(Punctuated::<Expr, syn::Token![,]>::parse_terminated) else { return };
+        for arg in &args {
+            self.visit_expr(arg);
+        }
+    }
+}
@@ -0,0 +1,194 @@
+//! Account data copy detection: the account data the instruction handlers of a program
+//! copy (`data.borrow().to_vec()`) or deserialize whole (borsh, bincode, `Pack`, Anchor's
+//! `Account`), ranked by the estimated size of what they copy.
+//!
+//! A copy costs compute units and heap for every byte of the account, on every call of
+//! the handler, and a whole deserialization of a large account to read one field is the
+//! common way programs run out of either. See [`facts`] for what counts as a copy and
+//! how its type is found.
+//!
+//! Handlers and the functions they call come from [`mcd`]; only copies a handler
+//! reaches are reported, and an accounts struct's `Account` fields count for the
+//! handlers taking it as their context. Sizes are the in-memory layouts [`msf`] computes
+//! for the types of the tree. A `Vec` or `String` field counts for its 24-byte header,
+//! not its contents, so the size of an account with one is a lower bound.
+
+pub mod facts;
+
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use mcd::FunctionId;
+use msf::layout::Types;
+use serde::Serialize;
+pub use trr_core::Location;
+use trr_core::{source, walk};
+
+use facts::{AccountsStruct, Copy, Facts, FunctionFacts};
+
+/// Copies of at least this many bytes are large when no size is given
+pub const DEFAULT_MIN_SIZE: u64 = 1024;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Kind {
+    /// `to_vec()` or `to_owned()` of the data
+    Copy,
+    /// A whole-struct deserialization of the data
+    Deserialize,
+    /// An Anchor `Account` or `InterfaceAccount` in an accounts struct
+    Account,
+}
+
+impl fmt::Display for Kind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.pad(match self {
+            Kind::Copy => "copy",
+            Kind::Deserialize => "deserialization",
+            Kind::Account => "Anchor account",
+        })
+    }
+}
+
+/// A copy the handlers of a program reach
+#[derive(Clone, Debug, Serialize)]
+pub struct CheckedCopy {
+    #[serde(flatten)]
+    pub copy: Copy,
+    pub program: String,
+    /// Path of the function it is in, or the accounts struct of an `Account` field
+    pub function: String,
+    /// The handlers reaching it
+    pub handlers: Vec<String>,
+    /// Estimated size of the type copied, in bytes
+    pub size: Option<u64>,
+}
+
+#[derive(Debug, Default)]
+pub struct Check {
+    /// Largest first, then those of unknown size, by program, then file and line
+    pub copies: Vec<CheckedCopy>,
+}
+
+impl Check {
+    /// The copies of at least `min_size` bytes
+    pub fn large(&self, min_size: u64) -> impl Iterator<Item = &CheckedCopy> {
+        self.copies.iter().filter(move |copy| copy.size.is_some_and(|size| size >= min_size))
+    }
+
+    pub fn count(&self, kind: Kind) -> usize {
+        self.copies.iter().filter(|copy| copy.copy.kind == kind).count()
+    }
+
+    pub fn largest(&self) -> Option<&CheckedCopy> {
+        self.copies.first().filter(|copy| copy.size.is_some())
+    }
+}
+
+#[derive(Default)]
+pub struct Analysis {
+    pub programs: mcd::Analysis,
+    pub facts: Facts,
+    pub types: Types,
+    /// Files that could not be read or parsed
+    pub errors: Vec<(PathBuf, String)>,
+}
+
+impl Analysis {
+    fn function_facts(&self, (program, function): FunctionId) -> Option<&FunctionFacts> {
+        self.facts.functions.get(&self.programs.programs[program].functions[function].location)
+    }
+
+    /// The functions reachable from `start`, itself included
+    fn reachable(&self, start: FunctionId) -> HashSet<FunctionId> {
+        let mut reached = HashSet::from([start]);
+        let mut stack = vec![start];
+        while let Some(id) = stack.pop() {
+            for call in &self.programs.programs[id.0].functions[id.1].calls {
+                for callee in self.programs.callees(id.0, call) {
+                    if reached.insert(callee) {
+                        stack.push(callee);
+                    }
+                }
+            }
+        }
+        reached
+    }
+
+    /// The accounts struct named `name`, preferably in `program`'s own crate
+    fn accounts_struct(&self, program: usize, name: &str) -> Option<&AccountsStruct> {
+        let root = &self.programs.programs[program].root;
+        let mut named = self.facts.structs.iter().filter(|accounts| accounts.name == name);
+        named.clone().find(|accounts| accounts.file.starts_with(root)).or_else(|| named.next())
+    }
+
+    fn checked(&self, copy: &Copy, program: &str, function: String, handlers: Vec<String>) -> CheckedCopy {
+        let size = copy.ty.as_deref().and_then(|ty| self.types.layout_of(ty)).map(|layout| layout.size);
+        CheckedCopy { copy: copy.clone(), program: program.to_string(), function, handlers, size }
+    }
+
+    /// Every copy the handlers reach, and the `Account` fields of their accounts structs
+    pub fn check(&self) -> Check {
+        let mut check = Check::default();
+        for (index, program) in self.programs.programs.iter().enumerate() {
+            let mut reached_by: HashMap<FunctionId, Vec<String>> = HashMap::new();
+            for handler in &program.handlers {
+                for id in self.reachable((index, handler.function)) {
+                    if self.function_facts(id).is_some_and(|facts| !facts.copies.is_empty()) {
+                        reached_by.entry(id).or_default().push(handler.name.clone());
+                    }
+                }
+            }
+            for (id, mut handlers) in reached_by {
+                let Some(facts) = self.function_facts(id) else { continue };
+                handlers.sort();
+                let function = program.functions[id.1].path.join("::");
+                for copy in &facts.copies {
+                    check.copies.push(self.checked(copy, &program.name, function.clone(), handlers.clone()));
+                }
+            }
+
+            // The accounts structs of the program's handlers
+            let mut structs: Vec<(&AccountsStruct, Vec<String>)> = Vec::new();
+            for handler in &program.handlers {
+                let Some(accounts) = self.function_facts((index, handler.function))
+                    .and_then(|facts| facts.context.as_deref())
+                    .and_then(|name| self.accounts_struct(index, name))
+                else {
+                    continue;
+                };
+                match structs.iter_mut().find(|(known, _)| std::ptr::eq(*known, accounts)) {
+                    Some((_, handlers)) => handlers.push(handler.name.clone()),
+                    None => structs.push((accounts, vec![handler.name.clone()])),
+                }
+            }
+            for (accounts, handlers) in structs {
+                for copy in &accounts.copies {
+                    check.copies.push(self.checked(copy, &program.name, accounts.name.clone(), handlers.clone()));
+                }
+            }
+        }
+        check.copies.sort_by(|a, b| b.size.cmp(&a.size)
+            .then_with(|| a.program.cmp(&b.program))
+            .then_with(|| a.copy.location.file.cmp(&b.copy.location.file))
+            .then_with(|| a.copy.location.line.cmp(&b.copy.location.line)));
+        check
+    }
+}
+
+/// Analyze every Rust file under `dir` (or `dir` itself, if it is a file), leaving out
+/// `target/` and test code
+pub fn analyze(dir: &Path) -> io::Result<Analysis> {
+    let programs = mcd::analyze(dir)?;
+    let mut facts = Facts::default();
+    let mut parsed = Vec::new();
+    let mut errors = programs.errors.clone();
+    source::parse_files(walk::program_files(dir)?, &mut errors, |path, source, syntax| {
+        facts::add_file(&path, source, &syntax, &mut facts);
+        parsed.push((path, syntax));
+    });
+    let types = Types::collect(&parsed);
+    Ok(Analysis { programs, facts, types, errors })
+}
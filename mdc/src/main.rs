@@ -0,0 +1,190 @@
+mod report;
+mod sarif;
+
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::process;
+
+use clap::Parser;
+use serde::Serialize;
+use trr_core::git::Repository;
+use trr_core::output::{self, Format};
+
+use mdc::{Analysis, Check, CheckedCopy, Kind, DEFAULT_MIN_SIZE};
+
+#[derive(Parser)]
+#[clap(author, version, about = "Account Data Copy (MDC) Detector")]
+#[clap(after_help = "Examples:
+  mdc programs/vault
+  mdc --top 10 -v programs
+  mdc --min-size 4096 --format markdown programs > data-copies.md
+  mdc --fail-above 0 programs")]
+struct Args {
+    /// Directory to analyze (relative to --repo when given)
+    #[clap(value_name = "DIR", default_value = ".")]
+    dir: PathBuf,
+
+    /// Git repository URL to clone, or path of a local checkout
+    #[clap(long, value_name = "REPO_URL_OR_PATH")]
+    repo: Option<String>,
+
+    /// Branch, tag or commit of --repo to analyze
+    #[clap(long, value_name = "REV", requires = "repo")]
+    rev: Option<String>,
+
+    /// Output format
+    #[clap(long, value_enum, default_value = "text")]
+    format: Format,
+
+    /// List the copies of unknown size too
+    #[clap(short, long)]
+    verbose: bool,
+
+    /// Copies of at least BYTES are large
+    #[clap(long, value_name = "BYTES", default_value_t = DEFAULT_MIN_SIZE)]
+    min_size: u64,
+
+    /// Only the N largest copies
+    #[clap(long, value_name = "N")]
+    top: Option<usize>,
+
+    /// Exit with status 1 if more than N copies are large
+    #[clap(long, value_name = "N")]
+    fail_above: Option<usize>,
+}
+
+/// Structured form of the analysis for `--format json`
+#[derive(Serialize)]
+struct JsonReport<'a> {
+    summary: Summary,
+    /// Largest first, then those of unknown size
+    copies: &'a [CheckedCopy],
+    errors: Vec<FileError>,
+}
+
+#[derive(Serialize)]
+struct Summary {
+    programs: usize,
+    copies: usize,
+    data_copies: usize,
+    deserializations: usize,
+    anchor_accounts: usize,
+    /// Copies whose type has a size
+    sized: usize,
+    min_size: u64,
+    /// Copies of at least `min_size` bytes
+    large: usize,
+    largest: u64,
+    in_loops: usize,
+}
+
+#[derive(Serialize)]
+struct FileError {
+    file: PathBuf,
+    error: String,
+}
+
+fn size(copy: &CheckedCopy) -> String {
+    copy.size.map_or_else(|| "?".to_string(), |size| size.to_string())
+}
+
+fn print_text(analysis: &Analysis, check: &Check, copies: &[CheckedCopy], args: &Args) {
+    let listed: Vec<&CheckedCopy> = copies.iter().filter(|copy| args.verbose || copy.size.is_some()).collect();
+    if !listed.is_empty() {
+        println!("\nAccount data copies by estimated size:");
+        println!("\n{:>8}  Copy", "Bytes");
+    }
+    for copy in listed {
+        let ty = copy.copy.ty.as_deref().map(|ty| format!(" as {}", ty)).unwrap_or_default();
+        let in_loop = if copy.copy.in_loop { ", in a loop" } else { "" };
+        println!(
+            "{:>8}  {} of {}{} in {}::{} ({}{})",
+            size(copy), copy.copy.kind, copy.copy.account, ty, copy.program, copy.function, copy.copy.location, in_loop
+        );
+        println!("{:>8}    {}; handlers: {}", "", copy.copy.call, copy.handlers.join(", "));
+    }
+
+    println!("\nSummary:");
+    println!("Programs: {}", analysis.programs.programs.len());
+    println!(
+        "Account data copies: {} ({} copies, {} deserializations, {} Anchor accounts)",
+        check.copies.len(), check.count(Kind::Copy), check.count(Kind::Deserialize), check.count(Kind::Account)
+    );
+    println!("Of known size: {}", check.copies.iter().filter(|copy| copy.size.is_some()).count());
+    println!("Of {} bytes or more: {}", args.min_size, check.large(args.min_size).count());
+    if let Some(largest) = check.largest() {
+        println!("Largest: {} bytes ({} in {}::{})", size(largest), largest.copy.account, largest.program, largest.function);
+    }
+    println!("In loops: {}", check.copies.iter().filter(|copy| copy.copy.in_loop).count());
+}
+
+fn write_json(out: &mut dyn Write, analysis: &Analysis, check: &Check, copies: &[CheckedCopy], min_size: u64) -> io::Result<()> {
+    let report = JsonReport {
+        summary: Summary {
+            programs: analysis.programs.programs.len(),
+            copies: check.copies.len(),
+            data_copies: check.count(Kind::Copy),
+            deserializations: check.count(Kind::Deserialize),
+            anchor_accounts: check.count(Kind::Account),
+            sized: check.copies.iter().filter(|copy| copy.size.is_some()).count(),
+            min_size,
+            large: check.large(min_size).count(),
+            largest: check.largest().and_then(|copy| copy.size).unwrap_or(0),
+            in_loops: check.copies.iter().filter(|copy| copy.copy.in_loop).count(),
+        },
+        copies,
+        errors: analysis.errors.iter()
+            .map(|(file, error)| FileError { file: file.clone(), error: error.clone() })
+            .collect(),
+    };
+    output::write_json(out, "mdc", &report)
+}
+
+fn run(args: &Args) -> Result<(), Box<dyn std::error::Error>> {
+    let repository = args.repo.as_deref().map(|repo| Repository::open(repo, args.rev.as_deref())).transpose()?;
+    let dir = match &repository {
+        Some(repository) => repository.subdir(&args.dir)?,
+        None => args.dir.clone(),
+    };
+    if !dir.exists() {
+        return Err(format!("Path '{}' does not exist", dir.display()).into());
+    }
+    if args.format == Format::Text {
+        println!("Analyzing: {}", dir.display());
+    }
+
+    let analysis = mdc::analyze(&dir)?;
+    for (file, error) in &analysis.errors {
+        eprintln!("Error analyzing {}: {}", file.display(), error);
+    }
+    let check = analysis.check();
+    let copies = &check.copies[..args.top.unwrap_or(check.copies.len()).min(check.copies.len())];
+
+    let mut out = io::stdout().lock();
+    match args.format {
+        Format::Text => print_text(&analysis, &check, copies, args),
+        Format::Json => write_json(&mut out, &analysis, &check, copies, args.min_size)?,
+        Format::Csv => report::write_csv(&mut out, copies)?,
+        Format::Markdown => report::write_markdown(&mut out, &dir, &check, copies, args.min_size)?,
+        Format::Sarif => {
+            // Artifact URIs are relative to the repository root, or to where mdc runs
+            let base = repository.as_ref().map_or_else(|| PathBuf::from("."), |repository| repository.root().to_path_buf());
+            sarif::write_sarif(&mut out, &check, args.min_size, &base)?;
+            writeln!(out)?;
+        }
+    }
+    drop(out);
+    let what = format!("account data copies of {} bytes or more", args.min_size);
+    output::enforce_limit(args.fail_above, check.large(args.min_size).count(), &what, check.large(args.min_size).map(|copy| {
+        format!("{} bytes: {} of {} in {}::{} at {}", size(copy), copy.copy.kind, copy.copy.account, copy.program, copy.function, copy.copy.location)
+    }));
+    Ok(())
+}
+
+fn main() {
+    let args = Args::parse();
+    if let Err(e) = run(&args) {
+        eprintln!("Error: {}", e);
+        process::exit(1);
+    }
+}
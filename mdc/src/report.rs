@@ -0,0 +1,68 @@
+//! `--format csv`, one row per copy, and `--format markdown`, a summary and the copies
+//! ranked by size.
+
+use std::io::{self, Write};
+use std::path::Path;
+
+use trr_core::output::{markdown_cell, markdown_code, write_csv_row};
+
+use mdc::{Check, CheckedCopy};
+
+fn size(copy: &CheckedCopy) -> String {
+    copy.size.map(|size| size.to_string()).unwrap_or_default()
+}
+
+pub fn write_csv(out: &mut dyn Write, copies: &[CheckedCopy]) -> io::Result<()> {
+    write_csv_row(out, &["program", "function", "handlers", "kind", "call", "account", "type", "size", "in_loop", "file", "line"])?;
+    for copy in copies {
+        write_csv_row(out, &[
+            &copy.program,
+            &copy.function,
+            &copy.handlers.join(" "),
+            &copy.copy.kind.to_string(),
+            &copy.copy.call,
+            &copy.copy.account,
+            copy.copy.ty.as_deref().unwrap_or_default(),
+            &size(copy),
+            &copy.copy.in_loop.to_string(),
+            &copy.copy.location.file.display().to_string(),
+            &copy.copy.location.line.to_string(),
+        ])?;
+    }
+    Ok(())
+}
+
+pub fn write_markdown(out: &mut dyn Write, target: &Path, check: &Check, copies: &[CheckedCopy], min_size: u64) -> io::Result<()> {
+    writeln!(out, "# Account Data Copies\n")?;
+    writeln!(out, "Target: {}\n", markdown_code(&target.display().to_string()))?;
+    writeln!(out, "## Summary\n")?;
+    writeln!(out, "| Metric | Value |")?;
+    writeln!(out, "| --- | ---: |")?;
+    writeln!(out, "| Account data copies | {} |", check.copies.len())?;
+    writeln!(out, "| Of known size | {} |", check.copies.iter().filter(|copy| copy.size.is_some()).count())?;
+    writeln!(out, "| Of {} bytes or more | {} |", min_size, check.large(min_size).count())?;
+    writeln!(out, "| Largest (bytes) | {} |", check.largest().and_then(|copy| copy.size).unwrap_or(0))?;
+    writeln!(out, "| In loops | {} |", check.copies.iter().filter(|copy| copy.copy.in_loop).count())?;
+
+    if copies.is_empty() {
+        return Ok(());
+    }
+    writeln!(out, "\n## Copies by Estimated Size\n")?;
+    writeln!(out, "| Bytes | Kind | Account | Type | Function | Handlers | Location |")?;
+    writeln!(out, "| ---: | --- | --- | --- | --- | --- | --- |")?;
+    for copy in copies {
+        let kind = if copy.copy.in_loop { format!("{} (in a loop)", copy.copy.kind) } else { copy.copy.kind.to_string() };
+        writeln!(
+            out,
+            "| {} | {} | {} | {} | {} | {} | {} |",
+            size(copy),
+            kind,
+            markdown_code(&copy.copy.account),
+            copy.copy.ty.as_deref().map(markdown_code).unwrap_or_default(),
+            markdown_code(&format!("{}::{}", copy.program, copy.function)),
+            markdown_cell(&copy.handlers.join(", ")),
+            markdown_code(&copy.copy.location.to_string()),
+        )?;
+    }
+    Ok(())
+}
@@ -0,0 +1,40 @@
+//! SARIF 2.1.0 output: a `large-account-copy` result per copy of at least the minimum
+//! size, located at the copy.
+
+use std::io::{self, Write};
+use std::path::Path;
+
+use serde_json::json;
+use trr_core::sarif::{location, write_log};
+
+use mdc::Check;
+
+pub fn write_sarif(out: &mut dyn Write, check: &Check, min_size: u64, base: &Path) -> io::Result<()> {
+    let mut results: Vec<_> = Vec::new();
+    for copy in check.large(min_size) {
+        results.push(json!({
+            "ruleId": "large-account-copy",
+            "level": "warning",
+            "message": {
+                "text": format!(
+                    "{} of {} ({}, about {} bytes) in {}::{} ({}){}",
+                    copy.copy.kind, copy.copy.account, copy.copy.ty.as_deref().unwrap_or_default(), copy.size.unwrap_or(0),
+                    copy.program, copy.function, copy.handlers.join(", "), if copy.copy.in_loop { ", in a loop" } else { "" }
+                ),
+            },
+            "locations": [location(&copy.copy.location, base)],
+            "properties": { "kind": copy.copy.kind, "size": copy.size, "inLoop": copy.copy.in_loop },
+        }));
+    }
+
+    let rules = vec![json!({
+        "id": "large-account-copy",
+        "name": "LargeAccountCopy",
+        "shortDescription": { "text": "Large account data copied or deserialized whole" },
+        "fullDescription": {
+            "text": "Copying or deserializing a large account costs compute units and heap for every byte, on every call. Read the fields needed in place, or use a zero-copy account (AccountLoader, bytemuck) instead of Account or a borsh deserialization.",
+        },
+        "defaultConfiguration": { "level": "warning" },
+    })];
+    write_log(out, "mdc", env!("CARGO_PKG_VERSION"), rules, results)
+}
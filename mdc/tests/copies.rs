@@ -0,0 +1,87 @@
+// Ranks the copies in tests/fixtures/workspace: an Anchor program deserializing a large
+// market through its accounts struct and again in a crank loop, and a native program
+// copying then deserializing its ledger, reading a small bincode header and copying an
+// account of unknown type.
+
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use mdc::{Check, Kind};
+use serde_json::Value;
+
+fn fixture() -> PathBuf {
+    Path::new(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/workspace")).to_path_buf()
+}
+
+fn check() -> Check {
+    let analysis = mdc::analyze(&fixture()).unwrap();
+    assert!(analysis.errors.is_empty(), "{:?}", analysis.errors);
+    analysis.check()
+}
+
+#[test]
+fn ranked_by_size() {
+    let check = check();
+    let copies: Vec<(&str, Kind, Option<u64>)> = check.copies.iter()
+        .map(|copy| (copy.function.as_str(), copy.copy.kind, copy.size))
+        .collect();
+    assert_eq!(copies, [
+        ("exchange::crank", Kind::Deserialize, Some(3080)),
+        ("PlaceOrder", Kind::Account, Some(3080)),
+        ("processor::record", Kind::Copy, Some(520)),
+        ("processor::record", Kind::Deserialize, Some(520)),
+        ("PlaceOrder", Kind::Account, Some(34)),
+        ("UpdateFee", Kind::Account, Some(34)),
+        ("processor::peek", Kind::Deserialize, Some(33)),
+        ("processor::peek", Kind::Copy, None),
+    ]);
+    assert_eq!(check.large(1024).count(), 2);
+    assert_eq!(check.large(512).count(), 4);
+}
+
+#[test]
+fn accounts_and_types() {
+    let check = check();
+
+    // The loop, and the handler taking the accounts struct
+    assert!(check.copies[0].copy.in_loop);
+    assert_eq!(check.copies[0].copy.account, "info");
+    assert_eq!(check.copies[1].copy.call, "Box<Account<'info, Market>>");
+    assert_eq!(check.copies[1].handlers, ["place_order"]);
+
+    // The copy is typed by the deserialization of its buffer, the bincode call by its `let`
+    assert_eq!(check.copies[2].copy.account, "ledger_info");
+    assert_eq!(check.copies[2].copy.ty.as_deref(), Some("Ledger"));
+    assert_eq!(check.copies[6].copy.ty.as_deref(), Some("Header"));
+    assert_eq!(check.copies[6].copy.account, "header_info");
+
+    // Neither the instruction data nor the test helper is an account copy
+    assert!(check.copies.iter().all(|copy| copy.copy.ty.as_deref() != Some("Instruction")));
+    assert_eq!(check.count(Kind::Copy), 2);
+}
+
+#[test]
+fn json_and_fail_above() {
+    let run = |args: &[&str]| Command::new(env!("CARGO_BIN_EXE_mdc"))
+        .args(args)
+        .arg(fixture())
+        .output()
+        .expect("failed to run mdc");
+
+    let output = run(&["--format", "json", "--min-size", "512", "--top", "3"]);
+    assert!(output.status.success());
+    let report: Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(report["tool"], "mdc");
+    assert_eq!(report["summary"]["copies"], 8);
+    assert_eq!(report["summary"]["large"], 4);
+    assert_eq!(report["summary"]["largest"], 3080);
+    assert_eq!(report["summary"]["in_loops"], 1);
+    assert_eq!(report["copies"].as_array().unwrap().len(), 3);
+    assert_eq!(report["copies"][0]["type"], "Market");
+    assert_eq!(report["copies"][0]["kind"], "deserialize");
+
+    let output = run(&["--fail-above", "1"]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("2 account data copies of 1024 bytes or more, more than 1"));
+    assert!(run(&["--fail-above", "2"]).status.success());
+}
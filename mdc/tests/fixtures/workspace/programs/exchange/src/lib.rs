@@ -0,0 +1,70 @@
+use anchor_lang::prelude::*;
+
+const MAX_ORDERS: usize = 64;
+
+#[program]
+pub mod exchange {
+    use super::*;
+
+    // The whole book is deserialized to append one order
+    pub fn place_order(ctx: Context<PlaceOrder>, price: u64, size: u64) -> Result<()> {
+        let market = &mut ctx.accounts.market;
+        let index = market.count as usize % MAX_ORDERS;
+        market.orders[index] = Order { owner: ctx.accounts.owner.key(), price, size };
+        market.count += 1;
+        Ok(())
+    }
+
+    pub fn update_fee(ctx: Context<UpdateFee>, fee: u16) -> Result<()> {
+        ctx.accounts.config.fee = fee;
+        Ok(())
+    }
+
+    // Every market passed is deserialized again on each pass
+    pub fn crank(ctx: Context<Crank>) -> Result<()> {
+        for info in ctx.remaining_accounts.iter() {
+            let market = Market::try_deserialize(&mut &info.data.borrow()[..])?;
+            msg!("{} orders", market.count);
+        }
+        Ok(())
+    }
+}
+
+#[derive(Accounts)]
+pub struct PlaceOrder<'info> {
+    #[account(mut)]
+    pub market: Box<Account<'info, Market>>,
+    pub config: Account<'info, Config>,
+    pub owner: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateFee<'info> {
+    #[account(mut, has_one = authority)]
+    pub config: Account<'info, Config>,
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct Crank<'info> {
+    pub cranker: Signer<'info>,
+}
+
+#[account]
+pub struct Market {
+    pub count: u64,
+    pub orders: [Order; MAX_ORDERS],
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default)]
+pub struct Order {
+    pub owner: Pubkey,
+    pub price: u64,
+    pub size: u64,
+}
+
+#[account]
+pub struct Config {
+    pub authority: Pubkey,
+    pub fee: u16,
+}
@@ -0,0 +1,50 @@
+use borsh::BorshDeserialize;
+use solana_program::account_info::{next_account_info, AccountInfo};
+use solana_program::clock::Clock;
+use solana_program::entrypoint::ProgramResult;
+use solana_program::msg;
+use solana_program::program_error::ProgramError;
+use solana_program::pubkey::Pubkey;
+use solana_program::sysvar::Sysvar;
+
+use crate::state::{Entry, Header, Instruction, Ledger};
+
+pub fn process(_program_id: &Pubkey, accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
+    // Instruction data, not an account
+    match Instruction::try_from_slice(data)? {
+        Instruction::Record { amount } => record(accounts, amount),
+        Instruction::Peek => peek(accounts),
+    }
+}
+
+// Copied, then deserialized, to write one entry
+fn record(accounts: &[AccountInfo], amount: u64) -> ProgramResult {
+    let accounts = &mut accounts.iter();
+    let ledger_info = next_account_info(accounts)?;
+    let data = ledger_info.data.borrow().to_vec();
+    let mut ledger = Ledger::try_from_slice(&data)?;
+    let index = ledger.count as usize % ledger.entries.len();
+    ledger.entries[index] = Entry { amount, slot: Clock::get()?.slot };
+    ledger.count += 1;
+    borsh::to_writer(&mut ledger_info.data.borrow_mut()[..], &ledger).map_err(|_| ProgramError::InvalidAccountData)
+}
+
+// A small header, and a copy of something else
+fn peek(accounts: &[AccountInfo]) -> ProgramResult {
+    let accounts = &mut accounts.iter();
+    let header_info = next_account_info(accounts)?;
+    let header: Header = bincode::deserialize(&header_info.try_borrow_data()?).map_err(|_| ProgramError::InvalidAccountData)?;
+    let other = next_account_info(accounts)?;
+    let raw = other.data.borrow().to_vec();
+    msg!("version {}, {} bytes", header.version, raw.len());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn snapshot(info: &AccountInfo) -> Vec<u8> {
+        info.data.borrow().to_vec()
+    }
+}
@@ -0,0 +1,28 @@
+use borsh::{BorshDeserialize, BorshSerialize};
+use serde::Deserialize;
+
+pub const ENTRIES: usize = 32;
+
+#[derive(BorshSerialize, BorshDeserialize)]
+pub struct Ledger {
+    pub count: u32,
+    pub entries: [Entry; ENTRIES],
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Clone, Copy)]
+pub struct Entry {
+    pub amount: u64,
+    pub slot: u64,
+}
+
+#[derive(Deserialize)]
+pub struct Header {
+    pub version: u8,
+    pub authority: [u8; 32],
+}
+
+#[derive(BorshDeserialize)]
+pub enum Instruction {
+    Record { amount: u64 },
+    Peek,
+}
@@ -0,0 +1,270 @@
+//! Dependency tree depth of the crates of a Cargo workspace, from its `Cargo.lock`.
+//!
+//! The lockfile records every package of the resolved graph with its dependencies, so
+//! no build, registry access or `cargo` invocation is needed. The workspace members
+//! are its packages without a `source`. A crate's depth is the number of edges on its
+//! longest dependency chain: 0 with no dependencies, 1 when its dependencies have none.
+//! The lockfile does not tell normal, build and development dependencies apart, so all
+//! of them count.
+
+pub mod manifests;
+
+use std::collections::{BTreeSet, HashMap};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+pub use trr_core::Location;
+
+/// Crates depending directly on one of these are Solana programs
+pub const PROGRAM_FRAMEWORKS: [&str; 4] = ["solana-program", "anchor-lang", "pinocchio", "solana-program-entrypoint"];
+
+/// Longest chains kept per crate when no count is given
+pub const DEFAULT_CHAINS: usize = 3;
+
+#[derive(Clone, Debug)]
+pub struct Package {
+    pub name: String,
+    pub version: String,
+    /// `None` for workspace members and other path dependencies
+    pub source: Option<String>,
+    /// Indices into [`Lockfile::packages`]
+    pub dependencies: Vec<usize>,
+    /// Line of the package's `name` in the lockfile
+    pub line: usize,
+}
+
+#[derive(Debug)]
+pub struct Lockfile {
+    pub path: PathBuf,
+    pub packages: Vec<Package>,
+}
+
+#[derive(Deserialize)]
+struct RawLockfile {
+    #[serde(default)]
+    package: Vec<RawPackage>,
+}
+
+#[derive(Deserialize)]
+struct RawPackage {
+    name: String,
+    version: String,
+    source: Option<String>,
+    #[serde(default)]
+    dependencies: Vec<String>,
+}
+
+impl Lockfile {
+    /// The `Cargo.lock` in `dir` or its nearest ancestor, as a path relative to `dir`
+    /// when `dir` is relative
+    pub fn find(dir: &Path) -> Option<PathBuf> {
+        let levels = dir.canonicalize().ok()?.ancestors().position(|ancestor| ancestor.join("Cargo.lock").is_file())?;
+        let mut found = dir.to_path_buf();
+        for _ in 0..levels {
+            if found.file_name().is_some() {
+                found.pop();
+            } else {
+                found.push("..");
+            }
+        }
+        Some(found.join("Cargo.lock"))
+    }
+
+    pub fn load(path: &Path) -> io::Result<Lockfile> {
+        Lockfile::parse(path, &fs::read_to_string(path)?)
+    }
+
+    /// Parse `content`, the lockfile at `path`. Dependencies are written `name`,
+    /// `name version` or `name version (source)`, with only as much as needed to tell
+    /// the packages of that name apart.
+    pub fn parse(path: &Path, content: &str) -> io::Result<Lockfile> {
+        let invalid = |message: String| {
+            io::Error::new(io::ErrorKind::InvalidData, format!("Invalid lockfile {}: {}", path.display(), message))
+        };
+        let raw: RawLockfile = toml::from_str(content).map_err(|e| invalid(e.to_string()))?;
+        let lines = name_lines(content);
+
+        let mut by_name: HashMap<&str, Vec<usize>> = HashMap::new();
+        for (index, package) in raw.package.iter().enumerate() {
+            by_name.entry(&package.name).or_default().push(index);
+        }
+        let resolve = |dependency: &str| -> Option<usize> {
+            let mut parts = dependency.splitn(3, ' ');
+            let name = parts.next()?;
+            let version = parts.next();
+            let source = parts.next().map(|source| source.trim_start_matches('(').trim_end_matches(')'));
+            let mut candidates = by_name.get(name)?.iter().copied().filter(|&index| {
+                let package = &raw.package[index];
+                version.is_none_or(|version| package.version == version)
+                    && source.is_none_or(|source| package.source.as_deref() == Some(source))
+            });
+            let found = candidates.next();
+            if candidates.next().is_some() { None } else { found }
+        };
+
+        let mut packages = Vec::with_capacity(raw.package.len());
+        for (index, package) in raw.package.iter().enumerate() {
+            let dependencies = package.dependencies.iter()
+                .map(|dependency| resolve(dependency)
+                    .ok_or_else(|| invalid(format!("{} depends on unknown package '{}'", package.name, dependency))))
+                .collect::<io::Result<_>>()?;
+            packages.push(Package {
+                name: package.name.clone(),
+                version: package.version.clone(),
+                source: package.source.clone(),
+                dependencies,
+                line: lines.get(index).copied().unwrap_or(1),
+            });
+        }
+        Ok(Lockfile { path: path.to_path_buf(), packages })
+    }
+
+    /// The workspace members (and other path dependencies)
+    pub fn members(&self) -> impl Iterator<Item = usize> + '_ {
+        self.packages.iter().enumerate()
+            .filter(|(_, package)| package.source.is_none())
+            .map(|(index, _)| index)
+    }
+
+    /// The package's name, with its version when the lockfile has several versions
+    /// of it
+    pub fn label(&self, index: usize) -> String {
+        let package = &self.packages[index];
+        let versions = self.packages.iter().filter(|other| other.name == package.name).count();
+        if versions > 1 { format!("{}@{}", package.name, package.version) } else { package.name.clone() }
+    }
+}
+
+/// Line of each `[[package]]`'s `name`, in order
+fn name_lines(content: &str) -> Vec<usize> {
+    let mut lines = Vec::new();
+    let mut in_package = false;
+    for (number, line) in content.lines().enumerate() {
+        let line = line.trim();
+        if line.starts_with('[') {
+            in_package = line == "[[package]]";
+        } else if in_package && line.starts_with("name") {
+            lines.push(number + 1);
+            in_package = false;
+        }
+    }
+    lines
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct CrateDepth {
+    pub name: String,
+    pub version: String,
+    /// Depends directly on one of the [`PROGRAM_FRAMEWORKS`]
+    pub program: bool,
+    /// The crate's entry in the lockfile
+    pub location: Location,
+    pub direct: usize,
+    /// Distinct packages it depends on, directly or not
+    pub transitive: usize,
+    pub depth: usize,
+    /// The longest chain through each direct dependency, longest first, each from
+    /// the crate itself to a package with no dependencies
+    pub longest_chains: Vec<Vec<String>>,
+}
+
+/// Longest path from each package, following the lockfile's edges. A dependency cycle
+/// (possible through development dependencies) is cut where it closes.
+struct Depths<'a> {
+    lockfile: &'a Lockfile,
+    depth: Vec<Option<usize>>,
+    /// The dependency on the longest path
+    next: Vec<Option<usize>>,
+    visiting: Vec<bool>,
+}
+
+impl<'a> Depths<'a> {
+    fn new(lockfile: &'a Lockfile) -> Self {
+        let count = lockfile.packages.len();
+        Depths { lockfile, depth: vec![None; count], next: vec![None; count], visiting: vec![false; count] }
+    }
+
+    fn of(&mut self, index: usize) -> usize {
+        if let Some(depth) = self.depth[index] {
+            return depth;
+        }
+        self.visiting[index] = true;
+        let mut depth = 0;
+        for &dependency in &self.lockfile.packages[index].dependencies {
+            if self.visiting[dependency] {
+                continue;
+            }
+            let through = self.of(dependency) + 1;
+            if through > depth {
+                depth = through;
+                self.next[index] = Some(dependency);
+            }
+        }
+        self.visiting[index] = false;
+        self.depth[index] = Some(depth);
+        depth
+    }
+
+    /// `from`, then the packages on the longest path from `start`
+    fn chain(&mut self, from: usize, start: usize) -> Vec<String> {
+        self.of(start);
+        let mut chain = vec![self.lockfile.label(from)];
+        let mut current = Some(start);
+        while let Some(index) = current {
+            chain.push(self.lockfile.label(index));
+            current = self.next[index];
+        }
+        chain
+    }
+}
+
+/// Everything the package at `index` depends on, directly or not
+pub fn transitive(lockfile: &Lockfile, index: usize) -> BTreeSet<usize> {
+    let mut seen = BTreeSet::new();
+    let mut stack = lockfile.packages[index].dependencies.clone();
+    while let Some(dependency) = stack.pop() {
+        if dependency != index && seen.insert(dependency) {
+            stack.extend(&lockfile.packages[dependency].dependencies);
+        }
+    }
+    seen
+}
+
+/// The depth of each workspace member, deepest first, with up to `chains` of its
+/// longest chains. With `only`, just the member of that name.
+pub fn analyze(lockfile: &Lockfile, chains: usize, only: Option<&str>) -> Vec<CrateDepth> {
+    let mut depths = Depths::new(lockfile);
+    let mut crates = Vec::new();
+    for index in lockfile.members() {
+        let package = &lockfile.packages[index];
+        if only.is_some_and(|name| name != package.name) {
+            continue;
+        }
+        let mut direct: Vec<usize> = package.dependencies.clone();
+        direct.sort_by_key(|&dependency| std::cmp::Reverse(depths.of(dependency)));
+        let longest_chains = direct.iter().take(chains).map(|&dependency| depths.chain(index, dependency)).collect();
+        crates.push(CrateDepth {
+            name: package.name.clone(),
+            version: package.version.clone(),
+            program: package.dependencies.iter()
+                .any(|&dependency| PROGRAM_FRAMEWORKS.contains(&lockfile.packages[dependency].name.as_str())),
+            location: Location { file: lockfile.path.clone(), line: package.line, column: None },
+            direct: package.dependencies.len(),
+            transitive: transitive(lockfile, index).len(),
+            depth: depths.of(index),
+            longest_chains,
+        });
+    }
+    crates.sort_by(|a, b| b.depth.cmp(&a.depth)
+        .then_with(|| b.transitive.cmp(&a.transitive))
+        .then_with(|| a.name.cmp(&b.name)));
+    crates
+}
+
+/// The `[package]` name of the manifest in `dir`, if it has one
+pub fn package_name(dir: &Path) -> Option<String> {
+    let manifest: toml::Table = toml::from_str(&fs::read_to_string(dir.join("Cargo.toml")).ok()?).ok()?;
+    manifest.get("package")?.get("name")?.as_str().map(str::to_string)
+}
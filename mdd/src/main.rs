@@ -0,0 +1,189 @@
+mod report;
+mod sarif;
+
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::process;
+
+use clap::Parser;
+use serde::Serialize;
+use trr_core::git::Repository;
+use trr_core::output::{self, Format};
+
+use mdd::{CrateDepth, Lockfile, DEFAULT_CHAINS};
+
+#[derive(Parser)]
+#[clap(author, version, about = "Maximum Dependency Depth (MDD) Analyzer")]
+#[clap(after_help = "Examples:
+  mdd
+  mdd programs/vault
+  mdd --programs-only --chains 1
+  mdd --fail-above 12 --fail-transitive-above 250
+  mdd --format markdown > dependencies.md")]
+struct Args {
+    /// Workspace to analyze, or one of its crates (relative to --repo when given)
+    #[clap(value_name = "DIR", default_value = ".")]
+    dir: PathBuf,
+
+    /// Git repository URL to clone, or path of a local checkout
+    #[clap(long, value_name = "REPO_URL_OR_PATH")]
+    repo: Option<String>,
+
+    /// Branch, tag or commit of --repo to analyze
+    #[clap(long, value_name = "REV", requires = "repo")]
+    rev: Option<String>,
+
+    /// Output format
+    #[clap(long, value_enum, default_value = "text")]
+    format: Format,
+
+    /// Longest chains to show per crate
+    #[clap(long, value_name = "N", default_value_t = DEFAULT_CHAINS)]
+    chains: usize,
+
+    /// Only crates depending directly on a Solana program framework
+    #[clap(long)]
+    programs_only: bool,
+
+    /// Exit with status 1 if any crate's dependency tree is deeper than DEPTH
+    #[clap(long, value_name = "DEPTH")]
+    fail_above: Option<usize>,
+
+    /// Exit with status 1 if any crate has more than N transitive dependencies
+    #[clap(long, value_name = "N")]
+    fail_transitive_above: Option<usize>,
+}
+
+impl Args {
+    /// The crates the limits fail, if any limit is given
+    fn offending<'a>(&self, crates: &'a [CrateDepth]) -> Vec<&'a CrateDepth> {
+        crates.iter()
+            .filter(|krate| {
+                self.fail_above.is_some_and(|limit| krate.depth > limit)
+                    || self.fail_transitive_above.is_some_and(|limit| krate.transitive > limit)
+            })
+            .collect()
+    }
+}
+
+/// Structured form of the analysis for `--format json`
+#[derive(Serialize)]
+struct JsonReport<'a> {
+    summary: Summary<'a>,
+    /// Crates, deepest first
+    crates: &'a [CrateDepth],
+}
+
+#[derive(Serialize)]
+struct Summary<'a> {
+    lockfile: &'a Path,
+    /// Packages in the lockfile
+    packages: usize,
+    crates: usize,
+    programs: usize,
+    max_depth: usize,
+    max_transitive: usize,
+}
+
+fn print_text(lockfile: &Lockfile, crates: &[CrateDepth]) {
+    println!("Lockfile: {} ({} packages)", lockfile.path.display(), lockfile.packages.len());
+    if !crates.is_empty() {
+        println!("\nCrates by dependency depth:");
+        println!("\n{:>5} {:>6} {:>10}  Crate", "Depth", "Direct", "Transitive");
+    }
+    for krate in crates {
+        let kind = if krate.program { " (program)" } else { "" };
+        println!("{:>5} {:>6} {:>10}  {} {}{}", krate.depth, krate.direct, krate.transitive, krate.name, krate.version, kind);
+        for chain in &krate.longest_chains {
+            println!("{:>26}{}", "", chain.join(" → "));
+        }
+    }
+
+    println!("\nSummary:");
+    println!("Crates: {}", crates.len());
+    println!("Programs: {}", crates.iter().filter(|krate| krate.program).count());
+    if let Some(krate) = crates.iter().rev().max_by_key(|krate| krate.depth) {
+        println!("Maximum depth: {} ({})", krate.depth, krate.name);
+    }
+    if let Some(krate) = crates.iter().rev().max_by_key(|krate| krate.transitive) {
+        println!("Most transitive dependencies: {} ({})", krate.transitive, krate.name);
+    }
+}
+
+fn write_json(out: &mut dyn Write, lockfile: &Lockfile, crates: &[CrateDepth]) -> io::Result<()> {
+    let report = JsonReport {
+        summary: Summary {
+            lockfile: &lockfile.path,
+            packages: lockfile.packages.len(),
+            crates: crates.len(),
+            programs: crates.iter().filter(|krate| krate.program).count(),
+            max_depth: crates.iter().map(|krate| krate.depth).max().unwrap_or(0),
+            max_transitive: crates.iter().map(|krate| krate.transitive).max().unwrap_or(0),
+        },
+        crates,
+    };
+    output::write_json(out, "mdd", &report)
+}
+
+/// With `--fail-above` or `--fail-transitive-above`, list the crates over the limits
+/// and exit with status 1
+fn enforce_limits(args: &Args, crates: &[CrateDepth]) {
+    let offending = args.offending(crates);
+    if offending.is_empty() {
+        return;
+    }
+    let header = format!("{} crate(s) exceed the dependency limits", offending.len());
+    output::fail(&header, offending.iter().map(|krate| {
+        format!("{} (depth {}, {} transitive dependencies)", krate.name, krate.depth, krate.transitive)
+    }));
+}
+
+fn run(args: &Args) -> Result<(), Box<dyn std::error::Error>> {
+    let repository = args.repo.as_deref().map(|repo| Repository::open(repo, args.rev.as_deref())).transpose()?;
+    let dir = match &repository {
+        Some(repository) => repository.subdir(&args.dir)?,
+        None => args.dir.clone(),
+    };
+    if !dir.exists() {
+        return Err(format!("Path '{}' does not exist", dir.display()).into());
+    }
+    if args.format == Format::Text {
+        println!("Analyzing: {}", dir.display());
+    }
+
+    let path = Lockfile::find(&dir)
+        .ok_or_else(|| format!("No Cargo.lock in '{}' or its parent directories", dir.display()))?;
+    let lockfile = Lockfile::load(&path)?;
+    // A crate below the workspace root is analyzed on its own
+    let only = if path.parent() == Some(dir.as_path()) { None } else { mdd::package_name(&dir) };
+    let mut crates = mdd::analyze(&lockfile, args.chains, only.as_deref());
+    if args.programs_only {
+        crates.retain(|krate| krate.program);
+    }
+
+    let mut out = io::stdout().lock();
+    match args.format {
+        Format::Text => print_text(&lockfile, &crates),
+        Format::Json => write_json(&mut out, &lockfile, &crates)?,
+        Format::Csv => report::write_csv(&mut out, &crates)?,
+        Format::Markdown => report::write_markdown(&mut out, &lockfile, &crates)?,
+        Format::Sarif => {
+            // Artifact URIs are relative to the repository root, or to where mdd runs
+            let base = repository.as_ref().map_or_else(|| PathBuf::from("."), |repository| repository.root().to_path_buf());
+            let limits = sarif::Limits { depth: args.fail_above, transitive: args.fail_transitive_above };
+            sarif::write_sarif(&mut out, &crates, &limits, &base)?;
+            writeln!(out)?;
+        }
+    }
+    drop(out);
+    enforce_limits(args, &crates);
+    Ok(())
+}
+
+fn main() {
+    let args = Args::parse();
+    if let Err(e) = run(&args) {
+        eprintln!("Error: {}", e);
+        process::exit(1);
+    }
+}
@@ -0,0 +1,59 @@
+//! The package manifests around source files: which crate a file belongs to, whether
+//! it is a Solana program, and the root of its workspace.
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::PROGRAM_FRAMEWORKS;
+
+/// The crate a source file belongs to
+#[derive(Clone, Debug)]
+pub struct CrateRoot {
+    pub name: String,
+    /// The directory of its manifest
+    pub root: PathBuf,
+    /// Depends directly on one of [`PROGRAM_FRAMEWORKS`], or has no manifest to tell
+    pub program: bool,
+}
+
+/// Parsed manifests by directory, `None` where there is none
+#[derive(Debug, Default)]
+pub struct Manifests(HashMap<PathBuf, Option<toml::Table>>);
+
+impl Manifests {
+    /// The manifest in `dir`, parsed once
+    pub fn get(&mut self, dir: &Path) -> Option<&toml::Table> {
+        self.0.entry(dir.to_path_buf())
+            .or_insert_with(|| fs::read_to_string(dir.join("Cargo.toml")).ok()?.parse().ok())
+            .as_ref()
+    }
+
+    /// The crate `file` belongs to: the nearest ancestor with a package manifest, even
+    /// above `dir`, or else `dir` named after itself
+    pub fn crate_of(&mut self, file: &Path, dir: &Path) -> CrateRoot {
+        for ancestor in file.ancestors().skip(1) {
+            let Some(manifest) = self.get(ancestor) else { continue };
+            let Some(name) = manifest.get("package").and_then(|package| package.get("name")).and_then(|name| name.as_str()) else {
+                continue;
+            };
+            let program = manifest.get("dependencies")
+                .and_then(toml::Value::as_table)
+                .is_some_and(|dependencies| PROGRAM_FRAMEWORKS.iter().any(|framework| dependencies.contains_key(*framework)));
+            return CrateRoot { name: name.to_string(), root: ancestor.to_path_buf(), program };
+        }
+        let name = fs::canonicalize(dir).ok()
+            .and_then(|dir| dir.file_name().map(|name| name.to_string_lossy().into_owned()))
+            .unwrap_or_else(|| "program".to_string());
+        CrateRoot { name, root: dir.to_path_buf(), program: true }
+    }
+
+    /// The root of the workspace of the crate at `root`: the nearest manifest with a
+    /// `[workspace]` table, or the crate's own. Cargo only reads profiles from there.
+    pub fn workspace_root(&mut self, root: &Path) -> PathBuf {
+        root.ancestors()
+            .find(|ancestor| self.get(ancestor).is_some_and(|manifest| manifest.contains_key("workspace")))
+            .unwrap_or(root)
+            .to_path_buf()
+    }
+}
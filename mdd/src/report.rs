@@ -0,0 +1,67 @@
+//! `--format csv`, one row per crate, and `--format markdown`, a review-ready report
+//! with the summary, the crates and their longest dependency chains.
+
+use std::io::{self, Write};
+
+use trr_core::output::{markdown_cell, markdown_code, write_csv_row};
+
+use mdd::{CrateDepth, Lockfile};
+
+/// Rows are deepest first; `longest_chain` is the first chain, ` > `-separated
+pub fn write_csv(out: &mut dyn Write, crates: &[CrateDepth]) -> io::Result<()> {
+    write_csv_row(out, &["crate", "version", "program", "depth", "direct", "transitive", "longest_chain"])?;
+    for krate in crates {
+        write_csv_row(out, &[
+            &krate.name,
+            &krate.version,
+            &krate.program.to_string(),
+            &krate.depth.to_string(),
+            &krate.direct.to_string(),
+            &krate.transitive.to_string(),
+            &krate.longest_chains.first().map_or_else(String::new, |chain| chain.join(" > ")),
+        ])?;
+    }
+    Ok(())
+}
+
+pub fn write_markdown(out: &mut dyn Write, lockfile: &Lockfile, crates: &[CrateDepth]) -> io::Result<()> {
+    writeln!(out, "# Dependency Depth Report\n")?;
+    writeln!(out, "Lockfile: {}\n", markdown_code(&lockfile.path.display().to_string()))?;
+    writeln!(out, "## Summary\n")?;
+    writeln!(out, "| Metric | Value |")?;
+    writeln!(out, "| --- | ---: |")?;
+    writeln!(out, "| Packages in lockfile | {} |", lockfile.packages.len())?;
+    writeln!(out, "| Crates | {} |", crates.len())?;
+    writeln!(out, "| Programs | {} |", crates.iter().filter(|krate| krate.program).count())?;
+    writeln!(out, "| Maximum depth | {} |", crates.iter().map(|krate| krate.depth).max().unwrap_or(0))?;
+    writeln!(out, "| Most transitive dependencies | {} |", crates.iter().map(|krate| krate.transitive).max().unwrap_or(0))?;
+
+    if crates.is_empty() {
+        return Ok(());
+    }
+    writeln!(out, "\n## Crates\n")?;
+    writeln!(out, "| Crate | Program | Depth | Direct | Transitive |")?;
+    writeln!(out, "| --- | --- | ---: | ---: | ---: |")?;
+    for krate in crates {
+        writeln!(
+            out,
+            "| {} | {} | {} | {} | {} |",
+            markdown_code(&format!("{} {}", krate.name, krate.version)),
+            if krate.program { "yes" } else { "" },
+            krate.depth,
+            krate.direct,
+            krate.transitive,
+        )?;
+    }
+
+    writeln!(out, "\n## Longest Chains\n")?;
+    for krate in crates.iter().filter(|krate| !krate.longest_chains.is_empty()) {
+        writeln!(out, "### {}\n", markdown_cell(&krate.name))?;
+        for chain in &krate.longest_chains {
+            let chain: Vec<String> = chain.iter().map(|name| markdown_code(name)).collect();
+            writeln!(out, "- {} ({})", chain.join(" → "), chain.len() - 1)?;
+        }
+        writeln!(out)?;
+    }
+    Ok(())
+}
@@ -0,0 +1,82 @@
+//! SARIF 2.1.0 output: a `dependency-depth` result per crate deeper than the depth
+//! limit and a `transitive-dependencies` result per crate over the count limit,
+//! located at the crate's entry in `Cargo.lock`.
+
+use std::io::{self, Write};
+use std::path::Path;
+
+use serde_json::json;
+use trr_core::sarif::{location, write_log};
+
+use mdd::CrateDepth;
+
+const DEPTH_RULE: &str = "dependency-depth";
+const TRANSITIVE_RULE: &str = "transitive-dependencies";
+
+/// The limits given on the command line. With neither, the deepest crates are
+/// reported as notes.
+pub struct Limits {
+    pub depth: Option<usize>,
+    pub transitive: Option<usize>,
+}
+
+pub fn write_sarif(out: &mut dyn Write, crates: &[CrateDepth], limits: &Limits, base: &Path) -> io::Result<()> {
+    let given = limits.depth.is_some() || limits.transitive.is_some();
+    let max_depth = crates.iter().map(|krate| krate.depth).max().unwrap_or(0);
+    let mut results = Vec::new();
+    for krate in crates {
+        let chain = krate.longest_chains.first().map_or_else(String::new, |chain| format!(": {}", chain.join(" → ")));
+        let over_depth = match limits.depth {
+            Some(limit) => krate.depth > limit,
+            None => !given && krate.depth == max_depth,
+        };
+        if over_depth {
+            let text = match limits.depth {
+                Some(limit) => format!("{} has a dependency tree {} deep (limit {}){}", krate.name, krate.depth, limit, chain),
+                None => format!("{} has the deepest dependency tree, {} deep{}", krate.name, krate.depth, chain),
+            };
+            results.push(json!({
+                "ruleId": DEPTH_RULE,
+                "ruleIndex": 0,
+                "level": if given { "warning" } else { "note" },
+                "message": { "text": text },
+                "locations": [location(&krate.location, base)],
+                "properties": { "depth": krate.depth, "longestChains": krate.longest_chains },
+            }));
+        }
+        if let Some(limit) = limits.transitive.filter(|&limit| krate.transitive > limit) {
+            results.push(json!({
+                "ruleId": TRANSITIVE_RULE,
+                "ruleIndex": 1,
+                "level": "warning",
+                "message": {
+                    "text": format!("{} has {} transitive dependencies (limit {})", krate.name, krate.transitive, limit),
+                },
+                "locations": [location(&krate.location, base)],
+                "properties": { "transitive": krate.transitive, "direct": krate.direct },
+            }));
+        }
+    }
+
+    let rules = vec![
+        json!({
+            "id": DEPTH_RULE,
+            "name": "DependencyDepth",
+            "shortDescription": { "text": "Crate with a dependency tree deeper than the limit" },
+            "fullDescription": {
+                "text": "The longest chain of dependencies from this crate is deeper than the limit. Every crate on the chain is part of the program's supply chain.",
+            },
+            "defaultConfiguration": { "level": "warning" },
+        }),
+        json!({
+            "id": TRANSITIVE_RULE,
+            "name": "TransitiveDependencies",
+            "shortDescription": { "text": "Crate with more transitive dependencies than the limit" },
+            "fullDescription": {
+                "text": "The crate depends, directly or not, on more packages than the limit.",
+            },
+            "defaultConfiguration": { "level": "warning" },
+        }),
+    ];
+    write_log(out, "mdd", env!("CARGO_PKG_VERSION"), rules, results)
+}
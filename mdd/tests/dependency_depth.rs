@@ -0,0 +1,75 @@
+// Analyzes the lockfile of tests/fixtures/workspace: cli depends on vault, an Anchor
+// program, and both on shared, which pulls a second version of borsh.
+
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+
+use mdd::Lockfile;
+
+fn workspace() -> PathBuf {
+    Path::new(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/workspace")).to_path_buf()
+}
+
+fn run(args: &[&str], dir: &Path) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_mdd"))
+        .args(args)
+        .arg(dir)
+        .output()
+        .expect("failed to run mdd")
+}
+
+#[test]
+fn depth_per_crate() {
+    let lockfile = Lockfile::load(&workspace().join("Cargo.lock")).unwrap();
+    assert_eq!(lockfile.packages.len(), 17);
+    let crates = mdd::analyze(&lockfile, 3, None);
+    let measured: Vec<_> = crates.iter()
+        .map(|krate| (krate.name.as_str(), krate.program, krate.direct, krate.transitive, krate.depth))
+        .collect();
+    assert_eq!(measured, [("cli", false, 2, 16, 7), ("vault", true, 2, 15, 6), ("shared", false, 1, 1, 1)]);
+    assert_eq!(crates[1].location.line, 143);
+}
+
+#[test]
+fn longest_chains() {
+    let lockfile = Lockfile::load(&workspace().join("Cargo.lock")).unwrap();
+    let crates = mdd::analyze(&lockfile, 3, Some("vault"));
+    assert_eq!(crates.len(), 1);
+    assert_eq!(crates[0].longest_chains, [
+        vec!["vault", "anchor-lang", "solana-program", "borsh@0.10.3", "borsh-derive", "proc-macro2", "unicode-ident"],
+        vec!["vault", "shared", "borsh@1.5.1"],
+    ]);
+}
+
+#[test]
+fn crate_directory_and_programs_only() {
+    let output = run(&["--format", "json"], &workspace().join("programs/vault"));
+    assert!(output.status.success());
+    let report: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(report["summary"]["crates"], 1);
+    assert_eq!(report["crates"][0]["name"], "vault");
+
+    let output = run(&["--format", "json", "--programs-only", "--chains", "1"], &workspace());
+    let report: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(report["summary"]["packages"], 17);
+    assert_eq!(report["summary"]["max_depth"], 6);
+    assert_eq!(report["crates"][0]["longest_chains"].as_array().unwrap().len(), 1);
+}
+
+#[test]
+fn limits() {
+    assert!(run(&["--fail-above", "7", "--fail-transitive-above", "16"], &workspace()).status.success());
+
+    let output = run(&["--fail-above", "6"], &workspace());
+    assert_eq!(output.status.code(), Some(1));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("1 crate(s) exceed"), "stderr: {}", stderr);
+    assert!(stderr.contains("cli (depth 7, 16 transitive dependencies)"), "stderr: {}", stderr);
+
+    let output = run(&["--fail-transitive-above", "10", "--format", "sarif"], &workspace());
+    assert_eq!(output.status.code(), Some(1));
+    let log: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let results = log["runs"][0]["results"].as_array().unwrap();
+    assert_eq!(results.len(), 2);
+    assert!(results.iter().all(|result| result["ruleId"] == "transitive-dependencies"));
+}
@@ -0,0 +1,103 @@
+//! Crates a Cargo workspace locks under several versions.
+//!
+//! Every version of a crate is compiled and linked on its own, which grows program
+//! size, and values of one version's types are not the other's: an account
+//! deserialized with borsh 0.10 and checked against solana-program 2's `Pubkey` fails
+//! to compile at best. For each version the report names the workspace members that
+//! pull it, directly or not, and the packages that depend on it directly.
+
+use std::collections::BTreeSet;
+
+use mdd::Lockfile;
+use serde::Serialize;
+pub use trr_core::Location;
+
+/// Crates whose types cross program and client boundaries, so that two versions of
+/// them in one build are a likely mismatch rather than only extra code
+pub const WATCHED: [&str; 7] = [
+    "solana-program",
+    "solana-sdk",
+    "anchor-lang",
+    "borsh",
+    "spl-token",
+    "spl-token-2022",
+    "spl-associated-token-account",
+];
+
+#[derive(Clone, Debug, Serialize)]
+pub struct LockedVersion {
+    pub version: String,
+    /// `None` for path dependencies
+    pub source: Option<String>,
+    /// The version's entry in the lockfile
+    pub location: Location,
+    /// Workspace members depending on this version, directly or not
+    pub pulled_by: Vec<String>,
+    /// Packages depending on this version directly
+    pub dependents: Vec<String>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct Duplicate {
+    pub name: String,
+    /// One of the [`WATCHED`] crates, or one given on the command line
+    pub watched: bool,
+    /// In lockfile order, oldest first
+    pub versions: Vec<LockedVersion>,
+}
+
+/// The crates of `lockfile` with several versions, watched ones first. With `only`,
+/// just the versions the member of that name pulls.
+pub fn duplicates(lockfile: &Lockfile, watched: &[String], only: Option<&str>) -> Vec<Duplicate> {
+    // What each member pulls in, itself included
+    let members: Vec<(&str, BTreeSet<usize>)> = lockfile.members()
+        .filter(|&index| only.is_none_or(|name| name == lockfile.packages[index].name))
+        .map(|index| {
+            let mut pulled = mdd::transitive(lockfile, index);
+            pulled.insert(index);
+            (lockfile.packages[index].name.as_str(), pulled)
+        })
+        .collect();
+
+    let names: BTreeSet<&str> = lockfile.packages.iter().map(|package| package.name.as_str()).collect();
+    let mut duplicates = Vec::new();
+    for name in names {
+        let versions: Vec<LockedVersion> = lockfile.packages.iter().enumerate()
+            .filter(|(_, package)| package.name == name)
+            .map(|(index, package)| {
+                let mut pulled_by: Vec<String> = members.iter()
+                    .filter(|(_, pulled)| pulled.contains(&index))
+                    .map(|(member, _)| member.to_string())
+                    .collect();
+                pulled_by.sort();
+                let mut dependents: Vec<String> = lockfile.packages.iter().enumerate()
+                    .filter(|(_, dependent)| dependent.dependencies.contains(&index))
+                    .map(|(dependent, _)| lockfile.label(dependent))
+                    .collect();
+                dependents.sort();
+                LockedVersion {
+                    version: package.version.clone(),
+                    source: package.source.clone(),
+                    location: Location { file: lockfile.path.clone(), line: package.line, column: None },
+                    pulled_by,
+                    dependents,
+                }
+            })
+            .filter(|version| only.is_none() || !version.pulled_by.is_empty())
+            .collect();
+        if versions.len() > 1 {
+            duplicates.push(Duplicate {
+                name: name.to_string(),
+                watched: WATCHED.contains(&name) || watched.iter().any(|watched| watched == name),
+                versions,
+            });
+        }
+    }
+    duplicates.sort_by_key(|duplicate| !duplicate.watched);
+    duplicates
+}
+
+/// Versions beyond the first of each crate: the copies a build could do without
+pub fn extra_versions(duplicates: &[Duplicate]) -> usize {
+    duplicates.iter().map(|duplicate| duplicate.versions.len() - 1).sum()
+}
@@ -0,0 +1,192 @@
+mod report;
+mod sarif;
+
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::process;
+
+use clap::Parser;
+use serde::Serialize;
+use trr_core::git::Repository;
+use trr_core::output::{self, Format};
+
+use mdd::Lockfile;
+use mdv::Duplicate;
+
+#[derive(Parser)]
+#[clap(author, version, about = "Multiple Dependency Versions (MDV) Detector")]
+#[clap(after_help = "Examples:
+  mdv
+  mdv programs/vault
+  mdv --watched-only --fail-on-watched
+  mdv --watch solana-zk-token-sdk --fail-above 10
+  mdv --format markdown > duplicates.md")]
+struct Args {
+    /// Workspace to analyze, or one of its crates (relative to --repo when given)
+    #[clap(value_name = "DIR", default_value = ".")]
+    dir: PathBuf,
+
+    /// Git repository URL to clone, or path of a local checkout
+    #[clap(long, value_name = "REPO_URL_OR_PATH")]
+    repo: Option<String>,
+
+    /// Branch, tag or commit of --repo to analyze
+    #[clap(long, value_name = "REV", requires = "repo")]
+    rev: Option<String>,
+
+    /// Output format
+    #[clap(long, value_enum, default_value = "text")]
+    format: Format,
+
+    /// Watch CRATE as well as solana-program, borsh, spl-token and the others (repeatable)
+    #[clap(long, value_name = "CRATE")]
+    watch: Vec<String>,
+
+    /// Only watched crates
+    #[clap(long)]
+    watched_only: bool,
+
+    /// Exit with status 1 if more than N crates have several versions
+    #[clap(long, value_name = "N")]
+    fail_above: Option<usize>,
+
+    /// Exit with status 1 if any watched crate has several versions
+    #[clap(long)]
+    fail_on_watched: bool,
+}
+
+/// Structured form of the analysis for `--format json`
+#[derive(Serialize)]
+struct JsonReport<'a> {
+    summary: Summary<'a>,
+    /// Watched crates first, then by name
+    duplicates: &'a [Duplicate],
+}
+
+#[derive(Serialize)]
+struct Summary<'a> {
+    lockfile: &'a Path,
+    /// Packages in the lockfile
+    packages: usize,
+    duplicated: usize,
+    watched_duplicated: usize,
+    extra_versions: usize,
+}
+
+fn print_text(lockfile: &Lockfile, duplicates: &[Duplicate]) {
+    println!("Lockfile: {} ({} packages)", lockfile.path.display(), lockfile.packages.len());
+    if !duplicates.is_empty() {
+        println!("\nCrates with several versions:");
+    }
+    for duplicate in duplicates {
+        let kind = if duplicate.watched { " (watched)" } else { "" };
+        println!("\n{}{}: {} versions", duplicate.name, kind, duplicate.versions.len());
+        for version in &duplicate.versions {
+            println!(
+                "  {:<10} pulled by {} (via {})",
+                version.version,
+                or_none(&version.pulled_by),
+                or_none(&version.dependents)
+            );
+        }
+    }
+
+    let watched: Vec<&str> = duplicates.iter().filter(|duplicate| duplicate.watched).map(|duplicate| duplicate.name.as_str()).collect();
+    println!("\nSummary:");
+    println!("Crates with several versions: {}", duplicates.len());
+    if watched.is_empty() {
+        println!("Watched crates with several versions: 0");
+    } else {
+        println!("Watched crates with several versions: {} ({})", watched.len(), watched.join(", "));
+    }
+    println!("Extra versions: {}", mdv::extra_versions(duplicates));
+}
+
+fn or_none(names: &[String]) -> String {
+    if names.is_empty() { "none".to_string() } else { names.join(", ") }
+}
+
+fn write_json(out: &mut dyn Write, lockfile: &Lockfile, duplicates: &[Duplicate]) -> io::Result<()> {
+    let report = JsonReport {
+        summary: Summary {
+            lockfile: &lockfile.path,
+            packages: lockfile.packages.len(),
+            duplicated: duplicates.len(),
+            watched_duplicated: duplicates.iter().filter(|duplicate| duplicate.watched).count(),
+            extra_versions: mdv::extra_versions(duplicates),
+        },
+        duplicates,
+    };
+    output::write_json(out, "mdv", &report)
+}
+
+/// With `--fail-above` or `--fail-on-watched`, say which limit failed and exit with
+/// status 1
+fn enforce_limits(args: &Args, duplicates: &[Duplicate]) {
+    let mut failed = false;
+    if let Some(limit) = args.fail_above.filter(|&limit| duplicates.len() > limit) {
+        eprintln!("\n{} crates have several versions (limit {})", duplicates.len(), limit);
+        failed = true;
+    }
+    let watched: Vec<&Duplicate> = duplicates.iter().filter(|duplicate| duplicate.watched).collect();
+    if args.fail_on_watched && !watched.is_empty() {
+        let header = format!("{} watched crate(s) have several versions", watched.len());
+        output::list_failures(&header, watched.iter().map(|duplicate| {
+            let versions: Vec<&str> = duplicate.versions.iter().map(|version| version.version.as_str()).collect();
+            format!("{} ({})", duplicate.name, versions.join(", "))
+        }));
+        failed = true;
+    }
+    if failed {
+        process::exit(1);
+    }
+}
+
+fn run(args: &Args) -> Result<(), Box<dyn std::error::Error>> {
+    let repository = args.repo.as_deref().map(|repo| Repository::open(repo, args.rev.as_deref())).transpose()?;
+    let dir = match &repository {
+        Some(repository) => repository.subdir(&args.dir)?,
+        None => args.dir.clone(),
+    };
+    if !dir.exists() {
+        return Err(format!("Path '{}' does not exist", dir.display()).into());
+    }
+    if args.format == Format::Text {
+        println!("Analyzing: {}", dir.display());
+    }
+
+    let path = Lockfile::find(&dir)
+        .ok_or_else(|| format!("No Cargo.lock in '{}' or its parent directories", dir.display()))?;
+    let lockfile = Lockfile::load(&path)?;
+    // A crate below the workspace root is analyzed on its own
+    let only = if path.parent() == Some(dir.as_path()) { None } else { mdd::package_name(&dir) };
+    let mut duplicates = mdv::duplicates(&lockfile, &args.watch, only.as_deref());
+    if args.watched_only {
+        duplicates.retain(|duplicate| duplicate.watched);
+    }
+
+    let mut out = io::stdout().lock();
+    match args.format {
+        Format::Text => print_text(&lockfile, &duplicates),
+        Format::Json => write_json(&mut out, &lockfile, &duplicates)?,
+        Format::Csv => report::write_csv(&mut out, &duplicates)?,
+        Format::Markdown => report::write_markdown(&mut out, &lockfile, &duplicates)?,
+        Format::Sarif => {
+            // Artifact URIs are relative to the repository root, or to where mdv runs
+            let base = repository.as_ref().map_or_else(|| PathBuf::from("."), |repository| repository.root().to_path_buf());
+            sarif::write_sarif(&mut out, &duplicates, &base)?;
+            writeln!(out)?;
+        }
+    }
+    drop(out);
+    enforce_limits(args, &duplicates);
+    Ok(())
+}
+
+fn main() {
+    let args = Args::parse();
+    if let Err(e) = run(&args) {
+        eprintln!("Error: {}", e);
+        process::exit(1);
+    }
+}
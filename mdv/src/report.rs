@@ -0,0 +1,59 @@
+//! `--format csv`, one row per locked version, and `--format markdown`, a review-ready
+//! report with the summary and each crate's versions.
+
+use std::io::{self, Write};
+
+use trr_core::output::{markdown_cell, markdown_code, write_csv_row};
+
+use mdd::Lockfile;
+use mdv::Duplicate;
+
+/// Members and dependents are `;`-separated
+pub fn write_csv(out: &mut dyn Write, duplicates: &[Duplicate]) -> io::Result<()> {
+    write_csv_row(out, &["crate", "version", "watched", "source", "pulled_by", "dependents"])?;
+    for duplicate in duplicates {
+        for version in &duplicate.versions {
+            write_csv_row(out, &[
+                &duplicate.name,
+                &version.version,
+                &duplicate.watched.to_string(),
+                version.source.as_deref().unwrap_or(""),
+                &version.pulled_by.join(";"),
+                &version.dependents.join(";"),
+            ])?;
+        }
+    }
+    Ok(())
+}
+
+fn names(names: &[String]) -> String {
+    names.iter().map(|name| markdown_code(name)).collect::<Vec<_>>().join(", ")
+}
+
+pub fn write_markdown(out: &mut dyn Write, lockfile: &Lockfile, duplicates: &[Duplicate]) -> io::Result<()> {
+    writeln!(out, "# Duplicate Dependency Versions Report\n")?;
+    writeln!(out, "Lockfile: {}\n", markdown_code(&lockfile.path.display().to_string()))?;
+    writeln!(out, "## Summary\n")?;
+    writeln!(out, "| Metric | Value |")?;
+    writeln!(out, "| --- | ---: |")?;
+    writeln!(out, "| Packages in lockfile | {} |", lockfile.packages.len())?;
+    writeln!(out, "| Crates with several versions | {} |", duplicates.len())?;
+    writeln!(out, "| Watched crates with several versions | {} |", duplicates.iter().filter(|duplicate| duplicate.watched).count())?;
+    writeln!(out, "| Extra versions | {} |", mdv::extra_versions(duplicates))?;
+
+    if duplicates.is_empty() {
+        return Ok(());
+    }
+    writeln!(out, "\n## Crates\n")?;
+    for duplicate in duplicates {
+        let kind = if duplicate.watched { " (watched)" } else { "" };
+        writeln!(out, "### {}{}\n", markdown_cell(&duplicate.name), kind)?;
+        writeln!(out, "| Version | Pulled by | Via |")?;
+        writeln!(out, "| --- | --- | --- |")?;
+        for version in &duplicate.versions {
+            writeln!(out, "| {} | {} | {} |", markdown_code(&version.version), names(&version.pulled_by), names(&version.dependents))?;
+        }
+        writeln!(out)?;
+    }
+    Ok(())
+}
@@ -0,0 +1,47 @@
+//! SARIF 2.1.0 output: a `duplicate-version` result per crate with several versions,
+//! a warning for watched crates and a note for the others, located at the lockfile
+//! entry of its first version.
+
+use std::io::{self, Write};
+use std::path::Path;
+
+use serde_json::json;
+use trr_core::sarif::{location, write_log};
+
+use mdv::Duplicate;
+
+const RULE: &str = "duplicate-version";
+
+pub fn write_sarif(out: &mut dyn Write, duplicates: &[Duplicate], base: &Path) -> io::Result<()> {
+    let results = duplicates.iter()
+        .map(|duplicate| {
+            let versions: Vec<String> = duplicate.versions.iter()
+                .map(|version| format!("{} (pulled by {})", version.version, version.pulled_by.join(", ")))
+                .collect();
+            json!({
+                "ruleId": RULE,
+                "ruleIndex": 0,
+                "level": if duplicate.watched { "warning" } else { "note" },
+                "message": {
+                    "text": format!("{} is locked at {} versions: {}", duplicate.name, duplicate.versions.len(), versions.join("; ")),
+                },
+                "locations": [location(&duplicate.versions[0].location, base)],
+                "relatedLocations": duplicate.versions[1..].iter()
+                    .map(|version| location(&version.location, base))
+                    .collect::<Vec<_>>(),
+                "properties": { "versions": duplicate.versions },
+            })
+        })
+        .collect();
+
+    let rules = vec![json!({
+        "id": RULE,
+        "name": "DuplicateVersion",
+        "shortDescription": { "text": "Crate locked at several versions" },
+        "fullDescription": {
+            "text": "The lockfile has several versions of this crate. Each is built into the program, and types of one version do not match the other's; for solana-program, borsh or spl-token this usually means a dependency should be aligned.",
+        },
+        "defaultConfiguration": { "level": "warning" },
+    })];
+    write_log(out, "mdv", env!("CARGO_PKG_VERSION"), rules, results)
+}
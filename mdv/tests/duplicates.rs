@@ -0,0 +1,84 @@
+// Analyzes the lockfile of tests/fixtures/workspace: vault (Anchor and spl-token on
+// solana-program 1.18) and staking (solana-program 2) lock two versions of
+// solana-program and borsh, and cli two versions of syn.
+
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+
+use mdd::Lockfile;
+
+fn workspace() -> PathBuf {
+    Path::new(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/workspace")).to_path_buf()
+}
+
+fn run(args: &[&str], dir: &Path) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_mdv"))
+        .args(args)
+        .arg(dir)
+        .output()
+        .expect("failed to run mdv")
+}
+
+#[test]
+fn versions_and_members() {
+    let lockfile = Lockfile::load(&workspace().join("Cargo.lock")).unwrap();
+    let duplicates = mdv::duplicates(&lockfile, &[], None);
+    let names: Vec<_> = duplicates.iter().map(|duplicate| (duplicate.name.as_str(), duplicate.watched)).collect();
+    assert_eq!(names, [("borsh", true), ("solana-program", true), ("syn", false)]);
+    assert_eq!(mdv::extra_versions(&duplicates), 3);
+
+    let solana_program = &duplicates[1].versions;
+    assert_eq!(solana_program[0].version, "1.18.26");
+    assert_eq!(solana_program[0].pulled_by, ["cli", "vault"]);
+    assert_eq!(solana_program[0].dependents, ["anchor-lang", "spl-token"]);
+    assert_eq!(solana_program[1].version, "2.0.3");
+    assert_eq!(solana_program[1].pulled_by, ["cli", "staking"]);
+    assert_eq!(solana_program[1].dependents, ["staking"]);
+
+    let borsh = &duplicates[0].versions;
+    assert_eq!(borsh[1].pulled_by, ["cli", "staking", "vault"]);
+    assert_eq!(borsh[1].location.line, 23);
+}
+
+#[test]
+fn watched_crates_from_the_command_line() {
+    let lockfile = Lockfile::load(&workspace().join("Cargo.lock")).unwrap();
+    let duplicates = mdv::duplicates(&lockfile, &["syn".to_string()], None);
+    assert!(duplicates.iter().all(|duplicate| duplicate.watched));
+}
+
+#[test]
+fn crate_directory() {
+    let lockfile = Lockfile::load(&workspace().join("Cargo.lock")).unwrap();
+    // staking pulls one version of each crate
+    assert!(mdv::duplicates(&lockfile, &[], Some("staking")).is_empty());
+    let vault = mdv::duplicates(&lockfile, &[], Some("vault"));
+    assert_eq!(vault.len(), 1);
+    assert_eq!(vault[0].name, "borsh");
+
+    let output = run(&["--format", "json"], &workspace().join("programs/staking"));
+    assert!(output.status.success());
+    let report: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(report["summary"]["duplicated"], 0);
+}
+
+#[test]
+fn limits() {
+    assert!(run(&["--fail-above", "3"], &workspace()).status.success());
+
+    let output = run(&["--fail-on-watched", "--watched-only", "--format", "json"], &workspace());
+    assert_eq!(output.status.code(), Some(1));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("2 watched crate(s)"), "stderr: {}", stderr);
+    assert!(stderr.contains("solana-program (1.18.26, 2.0.3)"), "stderr: {}", stderr);
+    let report: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(report["summary"]["duplicated"], 2);
+
+    let output = run(&["--fail-above", "2", "--format", "sarif"], &workspace());
+    assert_eq!(output.status.code(), Some(1));
+    let log: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let levels: Vec<_> = log["runs"][0]["results"].as_array().unwrap().iter()
+        .map(|result| result["level"].as_str().unwrap())
+        .collect();
+    assert_eq!(levels, ["warning", "warning", "note"]);
+}
@@ -0,0 +1,437 @@
+//! The custom error codes of Solana programs: the variants of Anchor `#[error_code]`
+//! enums, of error enums converted to `ProgramError::Custom` (thiserror enums and those
+//! with a `From` impl), and the literal codes of `ProgramError::Custom(n)`, with their
+//! messages, and the codes of a program defined more than once.
+//!
+//! Clients, explorers and monitoring map a failed transaction's custom code back to an
+//! error through the program's code table. When two enums, or an enum and an inline
+//! code, give the same number different meanings, failures are misreported and
+//! alerting on a code catches the wrong error: two native error enums both numbered
+//! from 0, or two `#[error_code]` enums both starting at 6000.
+//!
+//! Anchor numbers `#[error_code]` variants from 6000, or from `offset` in
+//! `#[error_code(offset = N)]`; other enums use their discriminants. A code collides
+//! when it comes from more than one enum, or from an enum and an inline code, or from
+//! inline codes in more than one module. Only crates depending on a Solana program
+//! framework are analyzed unless asked, and test code is left out.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use mdd::manifests::Manifests;
+use quote::ToTokens;
+use serde::Serialize;
+use syn::punctuated::Punctuated;
+use syn::spanned::Spanned;
+use syn::visit::{self, Visit};
+use syn::{Attribute, Expr, Lit, Token, Type};
+pub use trr_core::Location;
+use trr_core::source::{is_cfg_test, location};
+use trr_core::walk;
+
+/// The first code of an `#[error_code]` enum without an `offset`
+pub const ANCHOR_ERROR_OFFSET: u64 = 6000;
+
+/// Where a code is defined
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Origin {
+    /// A variant of an Anchor `#[error_code]` enum
+    ErrorCode,
+    /// A variant of an enum deriving `thiserror::Error` or converted into
+    /// `ProgramError`
+    Enum,
+    /// `ProgramError::Custom(n)` with a literal or `const` code
+    Custom,
+}
+
+impl fmt::Display for Origin {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.pad(match self {
+            Origin::ErrorCode => "error_code",
+            Origin::Enum => "enum",
+            Origin::Custom => "custom",
+        })
+    }
+}
+
+/// A crate the analyzed files belong to
+#[derive(Clone, Debug, Serialize)]
+pub struct Crate {
+    pub name: String,
+    pub root: PathBuf,
+    /// Depends directly on one of [`mdd::PROGRAM_FRAMEWORKS`], or has no manifest to
+    /// tell
+    pub program: bool,
+    /// Analyzed: a program, or any crate when asked
+    pub analyzed: bool,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct ErrorEntry {
+    #[serde(rename = "crate")]
+    pub krate: String,
+    pub code: u64,
+    /// `Enum::Variant`, or `Custom(n)` for inline codes
+    pub name: String,
+    /// `#[msg]`, `#[error]`, or the variant's doc comment
+    pub message: Option<String>,
+    pub origin: Origin,
+    /// Module path in the crate, empty at its root
+    pub module: String,
+    pub location: Location,
+}
+
+impl ErrorEntry {
+    /// What defines the code: the enum, or the module of an inline code
+    fn owner(&self) -> String {
+        match self.origin {
+            Origin::Custom => format!("{}::", self.module),
+            _ => format!("{}::{}", self.module, self.name.split("::").next().unwrap_or_default()),
+        }
+    }
+}
+
+/// A code of a crate defined by more than one enum or module
+#[derive(Clone, Debug, Serialize)]
+pub struct Collision {
+    #[serde(rename = "crate")]
+    pub krate: String,
+    pub code: u64,
+    /// The first definition of each
+    pub entries: Vec<ErrorEntry>,
+}
+
+#[derive(Debug, Default)]
+pub struct Analysis {
+    pub crates: Vec<Crate>,
+    /// By crate, then code; inline codes once per module
+    pub entries: Vec<ErrorEntry>,
+    /// By crate, then code
+    pub collisions: Vec<Collision>,
+    /// Files that could not be read or parsed
+    pub errors: Vec<(PathBuf, String)>,
+}
+
+impl Analysis {
+    pub fn analyzed_crates(&self) -> usize {
+        self.crates.iter().filter(|krate| krate.analyzed).count()
+    }
+
+    /// The error enums, by crate
+    pub fn enums(&self) -> usize {
+        self.entries.iter()
+            .filter(|entry| entry.origin != Origin::Custom)
+            .map(|entry| (&entry.krate, entry.owner()))
+            .collect::<HashSet<_>>()
+            .len()
+    }
+
+    /// Codes of a crate given more than one meaning
+    pub fn colliding_codes(&self) -> usize {
+        self.collisions.len()
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Options {
+    /// Library crates too, not only programs
+    pub all_crates: bool,
+}
+
+/// The error codes of the crates under `dir` (or `dir` itself, if it is a file) that
+/// `options` select, leaving out `target/` and test code
+pub fn analyze(dir: &Path, options: Options) -> io::Result<Analysis> {
+    let files = walk::program_files(dir)?;
+
+    let mut manifests = Manifests::default();
+    let mut analysis = Analysis::default();
+    // Parsed files by crate
+    let mut parsed: Vec<Vec<(PathBuf, syn::File)>> = Vec::new();
+    for file in files {
+        let krate = manifests.crate_of(&file, dir);
+        let index = match analysis.crates.iter().position(|known| known.root == krate.root) {
+            Some(index) => index,
+            None => {
+                let analyzed = krate.program || options.all_crates;
+                analysis.crates.push(Crate { name: krate.name, root: krate.root, program: krate.program, analyzed });
+                parsed.push(Vec::new());
+                analysis.crates.len() - 1
+            }
+        };
+        if !analysis.crates[index].analyzed {
+            continue;
+        }
+        let result = fs::read_to_string(&file)
+            .map_err(|e| e.to_string())
+            .and_then(|source| syn::parse_file(&source).map_err(|e| e.to_string()));
+        match result {
+            Ok(syntax) => parsed[index].push((file, syntax)),
+            Err(e) => analysis.errors.push((file, e)),
+        }
+    }
+
+    for (krate, files) in analysis.crates.iter().zip(&parsed) {
+        let mut declarations = Declarations::default();
+        for (_, file) in files {
+            declarations.visit_file(file);
+        }
+        let mut entries = Vec::new();
+        for (path, file) in files {
+            let mut visitor = FileVisitor {
+                path,
+                krate: &krate.name,
+                declarations: &declarations,
+                module: module_path(path),
+                entries: &mut entries,
+            };
+            visitor.visit_file(file);
+        }
+        // Inline codes once per module
+        let mut seen = HashSet::new();
+        entries.retain(|entry| entry.origin != Origin::Custom || seen.insert((entry.code, entry.module.clone())));
+        entries.sort_by(|a, b| a.code.cmp(&b.code)
+            .then_with(|| a.location.file.cmp(&b.location.file))
+            .then_with(|| a.location.line.cmp(&b.location.line)));
+
+        let mut by_code: BTreeMap<u64, Vec<&ErrorEntry>> = BTreeMap::new();
+        for entry in &entries {
+            by_code.entry(entry.code).or_default().push(entry);
+        }
+        for (code, defined) in by_code {
+            let mut owners = BTreeSet::new();
+            let firsts: Vec<ErrorEntry> = defined.into_iter().filter(|entry| owners.insert(entry.owner())).cloned().collect();
+            if firsts.len() > 1 {
+                analysis.collisions.push(Collision { krate: krate.name.clone(), code, entries: firsts });
+            }
+        }
+        analysis.entries.extend(entries);
+    }
+    analysis.crates.sort_by(|a, b| a.name.cmp(&b.name));
+    analysis.entries.sort_by(|a, b| a.krate.cmp(&b.krate).then_with(|| a.code.cmp(&b.code)));
+    analysis.collisions.sort_by(|a, b| a.krate.cmp(&b.krate).then_with(|| a.code.cmp(&b.code)));
+    Ok(analysis)
+}
+
+/// Module path of a source file, relative to the nearest ancestor holding `lib.rs` or
+/// `main.rs`: `src/instructions/deposit.rs` is `instructions::deposit`
+fn module_path(path: &Path) -> Vec<String> {
+    let is_crate_root = |dir: &Path| dir.join("lib.rs").is_file() || dir.join("main.rs").is_file();
+    let Some(relative) = path.ancestors().skip(1).find(|dir| is_crate_root(dir))
+        .and_then(|root| path.strip_prefix(root).ok())
+    else {
+        return Vec::new();
+    };
+    let mut module: Vec<String> = relative.iter().map(|component| component.to_string_lossy().into_owned()).collect();
+    if let Some(file_name) = module.pop() {
+        match file_name.strip_suffix(".rs") {
+            Some("mod") => {}
+            Some("lib" | "main") if module.is_empty() => {}
+            Some(stem) => module.push(stem.to_string()),
+            None => module.push(file_name),
+        }
+    }
+    module
+}
+
+/// The last identifier of a type's path: `Vault` for `crate::state::Vault`
+fn type_name(ty: &Type) -> Option<String> {
+    match ty {
+        Type::Path(path) => path.path.segments.last().map(|segment| segment.ident.to_string()),
+        _ => None,
+    }
+}
+
+/// The string literal of `#[name("...")]`
+fn string_attr(attrs: &[Attribute], name: &str) -> Option<String> {
+    attrs.iter()
+        .filter(|attr| attr.path().is_ident(name))
+        .find_map(|attr| attr.parse_args::<syn::LitStr>().ok())
+        .map(|message| message.value())
+}
+
+/// The first line of the doc comment
+fn doc(attrs: &[Attribute]) -> Option<String> {
+    attrs.iter().find_map(|attr| match &attr.meta {
+        syn::Meta::NameValue(meta) if meta.path.is_ident("doc") => match &meta.value {
+            Expr::Lit(syn::ExprLit { lit: Lit::Str(text), .. }) => Some(text.value().trim().to_string()).filter(|text| !text.is_empty()),
+            _ => None,
+        },
+        _ => None,
+    })
+}
+
+/// What the files of a crate declare: integer constants, and the enums converted into
+/// `ProgramError`
+#[derive(Default)]
+struct Declarations {
+    consts: HashMap<String, u64>,
+    converted: HashSet<String>,
+}
+
+impl Declarations {
+    /// The value of a code: a literal, a constant, or a cast of one
+    fn eval(&self, expr: &Expr) -> Option<u64> {
+        match expr {
+            Expr::Lit(syn::ExprLit { lit: Lit::Int(int), .. }) => int.base10_parse().ok(),
+            Expr::Path(path) => self.consts.get(&path.path.segments.last()?.ident.to_string()).copied(),
+            Expr::Cast(cast) => self.eval(&cast.expr),
+            Expr::Paren(paren) => self.eval(&paren.expr),
+            Expr::Binary(binary) => {
+                let (left, right) = (self.eval(&binary.left)?, self.eval(&binary.right)?);
+                match binary.op {
+                    syn::BinOp::Add(_) => left.checked_add(right),
+                    syn::BinOp::Sub(_) => left.checked_sub(right),
+                    syn::BinOp::Mul(_) => left.checked_mul(right),
+                    _ => None,
+                }
+            }
+            _ => None,
+        }
+    }
+}
+
+impl<'ast> Visit<'ast> for Declarations {
+    fn visit_item_const(&mut self, item: &'ast syn::ItemConst) {
+        if let Expr::Lit(syn::ExprLit { lit: Lit::Int(int), .. }) = &*item.expr {
+            if let Ok(value) = int.base10_parse() {
+                self.consts.insert(item.ident.to_string(), value);
+            }
+        }
+    }
+
+    // `impl From<VaultError> for ProgramError`
+    fn visit_item_impl(&mut self, item: &'ast syn::ItemImpl) {
+        let converted = item.trait_.as_ref()
+            .and_then(|(_, path, _)| path.segments.last())
+            .filter(|segment| segment.ident == "From" && type_name(&item.self_ty).as_deref() == Some("ProgramError"))
+            .and_then(|segment| match &segment.arguments {
+                syn::PathArguments::AngleBracketed(arguments) => arguments.args.first().and_then(|argument| match argument {
+                    syn::GenericArgument::Type(ty) => type_name(ty),
+                    _ => None,
+                }),
+                _ => None,
+            });
+        self.converted.extend(converted);
+        visit::visit_item_impl(self, item);
+    }
+}
+
+struct FileVisitor<'a> {
+    path: &'a Path,
+    krate: &'a str,
+    declarations: &'a Declarations,
+    /// Of the file, then its inline modules
+    module: Vec<String>,
+    entries: &'a mut Vec<ErrorEntry>,
+}
+
+impl FileVisitor<'_> {
+    fn add_enum(&mut self, item: &syn::ItemEnum, origin: Origin, offset: u64) {
+        let mut next = 0u64;
+        for variant in &item.variants {
+            let discriminant = variant.discriminant.as_ref().and_then(|(_, expr)| self.declarations.eval(expr)).unwrap_or(next);
+            next = discriminant + 1;
+            let message = match origin {
+                Origin::ErrorCode => string_attr(&variant.attrs, "msg"),
+                _ => string_attr(&variant.attrs, "error"),
+            };
+            self.entries.push(ErrorEntry {
+                krate: self.krate.to_string(),
+                code: offset + discriminant,
+                name: format!("{}::{}", item.ident, variant.ident),
+                message: message.or_else(|| doc(&variant.attrs)),
+                origin,
+                module: self.module.join("::"),
+                location: location(self.path, variant.ident.span()),
+            });
+        }
+    }
+}
+
+impl<'ast> Visit<'ast> for FileVisitor<'_> {
+    fn visit_item_mod(&mut self, item: &'ast syn::ItemMod) {
+        if is_cfg_test(&item.attrs) {
+            return;
+        }
+        self.module.push(item.ident.to_string());
+        visit::visit_item_mod(self, item);
+        self.module.pop();
+    }
+
+    fn visit_item_fn(&mut self, item: &'ast syn::ItemFn) {
+        if !is_cfg_test(&item.attrs) {
+            visit::visit_item_fn(self, item);
+        }
+    }
+
+    fn visit_impl_item_fn(&mut self, item: &'ast syn::ImplItemFn) {
+        if !is_cfg_test(&item.attrs) {
+            visit::visit_impl_item_fn(self, item);
+        }
+    }
+
+    fn visit_item_enum(&mut self, item: &'ast syn::ItemEnum) {
+        if is_cfg_test(&item.attrs) {
+            return;
+        }
+        let error_code = item.attrs.iter().find(|attr| attr.path().is_ident("error_code"));
+        let derives_error = item.attrs.iter()
+            .filter(|attr| attr.path().is_ident("derive"))
+            .any(|attr| {
+                attr.parse_args_with(Punctuated::<syn::Path, Token![,]>::parse_terminated)
+                    .is_ok_and(|paths| paths.iter().any(|path| path.segments.last().is_some_and(|segment| segment.ident == "Error")))
+            });
+        if let Some(attr) = error_code {
+            // `#[error_code(offset = 100)]`
+            let mut offset = ANCHOR_ERROR_OFFSET;
+            if let syn::Meta::List(_) = attr.meta {
+                let _ = attr.parse_nested_meta(|meta| {
+                    if meta.path.is_ident("offset") {
+                        let value: Expr = meta.value()?.parse()?;
+                        offset = self.declarations.eval(&value).unwrap_or(offset);
+                    }
+                    Ok(())
+                });
+            }
+            self.add_enum(item, Origin::ErrorCode, offset);
+        } else if derives_error || self.declarations.converted.contains(&item.ident.to_string()) {
+            self.add_enum(item, Origin::Enum, 0);
+        }
+    }
+
+    fn visit_expr_call(&mut self, call: &'ast syn::ExprCall) {
+        let custom = match &*call.func {
+            Expr::Path(path) => {
+                let segments: Vec<String> = path.path.segments.iter().map(|segment| segment.ident.to_string()).collect();
+                segments.ends_with(&["ProgramError".to_string(), "Custom".to_string()])
+            }
+            _ => false,
+        };
+        let argument = call.args.first().filter(|_| custom && call.args.len() == 1);
+        if let Some((argument, code)) = argument.and_then(|argument| Some((argument, self.declarations.eval(argument)?))) {
+            self.entries.push(ErrorEntry {
+                krate: self.krate.to_string(),
+                code,
+                name: format!("Custom({})", argument.to_token_stream()),
+                message: None,
+                origin: Origin::Custom,
+                module: self.module.join("::"),
+                location: location(self.path, call.func.span()),
+            });
+        }
+        visit::visit_expr_call(self, call);
+    }
+
+    fn visit_macro(&mut self, mac: &'ast syn::Macro) {
+        // Arguments written as expressions, as in `require!` and `msg!`
+        if let Ok(arguments) = mac.parse_body_with(Punctuated::<Expr, Token![,]>::parse_terminated) {
+            for argument in &arguments {
+                self.visit_expr(argument);
+            }
+        }
+    }
+}
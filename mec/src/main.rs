@@ -0,0 +1,176 @@
+mod report;
+mod sarif;
+
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::process;
+
+use clap::Parser;
+use serde::Serialize;
+use trr_core::git::Repository;
+use trr_core::output::{self, Format};
+
+use mec::{Analysis, Collision, Crate, ErrorEntry, Options};
+
+#[derive(Parser)]
+#[clap(author, version, about = "MEC (Error Code) Inventory")]
+#[clap(after_help = "Examples:
+  mec programs/vault
+  mec --all-crates .
+  mec --format markdown programs > error-codes.md
+  mec --fail-above 0 programs")]
+struct Args {
+    /// Directory to analyze (relative to --repo when given)
+    #[clap(value_name = "DIR", default_value = ".")]
+    dir: PathBuf,
+
+    /// Git repository URL to clone, or path of a local checkout
+    #[clap(long, value_name = "REPO_URL_OR_PATH")]
+    repo: Option<String>,
+
+    /// Branch, tag or commit of --repo to analyze
+    #[clap(long, value_name = "REV", requires = "repo")]
+    rev: Option<String>,
+
+    /// Output format
+    #[clap(long, value_enum, default_value = "text")]
+    format: Format,
+
+    /// Analyze every crate, not only those depending on a Solana program framework
+    #[clap(long)]
+    all_crates: bool,
+
+    /// Exit with status 1 if more than N codes collide
+    #[clap(long, value_name = "N")]
+    fail_above: Option<usize>,
+}
+
+/// Structured form of the analysis for `--format json`
+#[derive(Serialize)]
+struct JsonReport<'a> {
+    summary: Summary,
+    crates: &'a [Crate],
+    /// By crate, then code
+    codes: &'a [ErrorEntry],
+    collisions: &'a [Collision],
+    errors: Vec<FileError>,
+}
+
+#[derive(Serialize)]
+struct Summary {
+    /// Crates analyzed
+    crates: usize,
+    enums: usize,
+    codes: usize,
+    collisions: usize,
+}
+
+#[derive(Serialize)]
+struct FileError {
+    file: PathBuf,
+    error: String,
+}
+
+fn print_text(analysis: &Analysis) {
+    println!("\nCrates:");
+    for krate in &analysis.crates {
+        let kind = if krate.program { "program" } else { "library" };
+        if krate.analyzed {
+            let codes = analysis.entries.iter().filter(|entry| entry.krate == krate.name).count();
+            println!("  {} ({}): {} code(s)", krate.name, kind, codes);
+        } else {
+            println!("  {} ({}, skipped)", krate.name, kind);
+        }
+    }
+    let mut krate = None;
+    for entry in &analysis.entries {
+        if krate != Some(&entry.krate) {
+            println!("\n{}:", entry.krate);
+            krate = Some(&entry.krate);
+        }
+        match &entry.message {
+            Some(message) => println!("  {:>6}  {}: {} ({})", entry.code, entry.name, message, entry.location),
+            None => println!("  {:>6}  {} ({})", entry.code, entry.name, entry.location),
+        }
+    }
+    if !analysis.collisions.is_empty() {
+        println!("\nColliding codes:");
+    }
+    for collision in &analysis.collisions {
+        println!("\n{} code {}:", collision.krate, collision.code);
+        for entry in &collision.entries {
+            println!("  {} ({}) at {}", entry.name, entry.origin, entry.location);
+        }
+    }
+
+    println!("\nSummary:");
+    println!("Crates analyzed: {}", analysis.analyzed_crates());
+    println!("Error enums: {}", analysis.enums());
+    println!("Error codes: {}", analysis.entries.len());
+    println!("Colliding codes: {}", analysis.colliding_codes());
+}
+
+fn write_json(out: &mut dyn Write, analysis: &Analysis) -> io::Result<()> {
+    let report = JsonReport {
+        summary: Summary {
+            crates: analysis.analyzed_crates(),
+            enums: analysis.enums(),
+            codes: analysis.entries.len(),
+            collisions: analysis.colliding_codes(),
+        },
+        crates: &analysis.crates,
+        codes: &analysis.entries,
+        collisions: &analysis.collisions,
+        errors: analysis.errors.iter()
+            .map(|(file, error)| FileError { file: file.clone(), error: error.clone() })
+            .collect(),
+    };
+    output::write_json(out, "mec", &report)
+}
+
+fn run(args: &Args) -> Result<(), Box<dyn std::error::Error>> {
+    let repository = args.repo.as_deref().map(|repo| Repository::open(repo, args.rev.as_deref())).transpose()?;
+    let dir = match &repository {
+        Some(repository) => repository.subdir(&args.dir)?,
+        None => args.dir.clone(),
+    };
+    if !dir.exists() {
+        return Err(format!("Path '{}' does not exist", dir.display()).into());
+    }
+    if args.format == Format::Text {
+        println!("Analyzing: {}", dir.display());
+    }
+
+    let analysis = mec::analyze(&dir, Options { all_crates: args.all_crates })?;
+    for (file, error) in &analysis.errors {
+        eprintln!("Error analyzing {}: {}", file.display(), error);
+    }
+
+    let mut out = io::stdout().lock();
+    match args.format {
+        Format::Text => print_text(&analysis),
+        Format::Json => write_json(&mut out, &analysis)?,
+        Format::Csv => report::write_csv(&mut out, &analysis)?,
+        Format::Markdown => report::write_markdown(&mut out, &dir, &analysis)?,
+        Format::Sarif => {
+            // Artifact URIs are relative to the repository root, or to where mec runs
+            let base = repository.as_ref().map_or_else(|| PathBuf::from("."), |repository| repository.root().to_path_buf());
+            sarif::write_sarif(&mut out, &analysis.collisions, &base)?;
+            writeln!(out)?;
+        }
+    }
+    drop(out);
+    output::enforce_limit(args.fail_above, analysis.collisions.len(), "colliding error code(s)", analysis.collisions.iter().map(|collision| {
+        let names: Vec<&str> = collision.entries.iter().map(|entry| entry.name.as_str()).collect();
+        format!("{} code {}: {}", collision.krate, collision.code, names.join(", "))
+    }));
+    Ok(())
+}
+
+fn main() {
+    let args = Args::parse();
+    if let Err(e) = run(&args) {
+        eprintln!("Error: {}", e);
+        process::exit(1);
+    }
+}
@@ -0,0 +1,73 @@
+//! `--format csv`, one row per code, and `--format markdown`, a review-ready report with
+//! the code table of each crate and the colliding codes.
+
+use std::io::{self, Write};
+use std::path::Path;
+
+use trr_core::output::{markdown_cell, markdown_code, write_csv_row};
+
+use mec::Analysis;
+
+/// Rows are by crate, then code, with whether the code collides
+pub fn write_csv(out: &mut dyn Write, analysis: &Analysis) -> io::Result<()> {
+    write_csv_row(out, &["crate", "code", "name", "message", "origin", "module", "colliding", "file", "line"])?;
+    for entry in &analysis.entries {
+        let colliding = analysis.collisions.iter().any(|collision| collision.krate == entry.krate && collision.code == entry.code);
+        write_csv_row(out, &[
+            &entry.krate,
+            &entry.code.to_string(),
+            &entry.name,
+            entry.message.as_deref().unwrap_or(""),
+            &entry.origin.to_string(),
+            &entry.module,
+            if colliding { "true" } else { "false" },
+            &entry.location.file.display().to_string(),
+            &entry.location.line.to_string(),
+        ])?;
+    }
+    Ok(())
+}
+
+pub fn write_markdown(out: &mut dyn Write, target: &Path, analysis: &Analysis) -> io::Result<()> {
+    writeln!(out, "# Error Code Inventory\n")?;
+    writeln!(out, "Target: {}\n", markdown_code(&target.display().to_string()))?;
+    writeln!(out, "## Summary\n")?;
+    writeln!(out, "| Metric | Value |")?;
+    writeln!(out, "| --- | ---: |")?;
+    writeln!(out, "| Crates analyzed | {} |", analysis.analyzed_crates())?;
+    writeln!(out, "| Error enums | {} |", analysis.enums())?;
+    writeln!(out, "| Error codes | {} |", analysis.entries.len())?;
+    writeln!(out, "| Colliding codes | {} |", analysis.colliding_codes())?;
+
+    if !analysis.collisions.is_empty() {
+        writeln!(out, "\n## Colliding Codes\n")?;
+        writeln!(out, "| Crate | Code | Defined by |")?;
+        writeln!(out, "| --- | ---: | --- |")?;
+        for collision in &analysis.collisions {
+            let entries: Vec<String> = collision.entries.iter()
+                .map(|entry| format!("{} at {}", markdown_code(&entry.name), markdown_code(&entry.location.to_string())))
+                .collect();
+            writeln!(out, "| {} | {} | {} |", markdown_code(&collision.krate), collision.code, entries.join("<br>"))?;
+        }
+    }
+
+    if analysis.entries.is_empty() {
+        return Ok(());
+    }
+    writeln!(out, "\n## Codes\n")?;
+    writeln!(out, "| Crate | Code | Name | Message | Origin | Location |")?;
+    writeln!(out, "| --- | ---: | --- | --- | --- | --- |")?;
+    for entry in &analysis.entries {
+        writeln!(
+            out,
+            "| {} | {} | {} | {} | {} | {} |",
+            markdown_code(&entry.krate),
+            entry.code,
+            markdown_code(&entry.name),
+            entry.message.as_deref().map_or(String::new(), markdown_cell),
+            entry.origin,
+            markdown_code(&entry.location.to_string()),
+        )?;
+    }
+    Ok(())
+}
@@ -0,0 +1,42 @@
+//! SARIF 2.1.0 output: an `error-code-collision` warning per colliding code, located at
+//! its first definition, with the others as related locations.
+
+use std::io::{self, Write};
+use std::path::Path;
+
+use serde_json::json;
+use trr_core::sarif::{location, write_log};
+
+use mec::Collision;
+
+const RULE: &str = "error-code-collision";
+
+pub fn write_sarif(out: &mut dyn Write, collisions: &[Collision], base: &Path) -> io::Result<()> {
+    let mut results = Vec::new();
+    for collision in collisions {
+        let Some((first, others)) = collision.entries.split_first() else { continue };
+        let names: Vec<&str> = collision.entries.iter().map(|entry| entry.name.as_str()).collect();
+        results.push(json!({
+            "ruleId": RULE,
+            "ruleIndex": 0,
+            "level": "warning",
+            "message": {
+                "text": format!("Error code {} of {} is defined by {}", collision.code, collision.krate, names.join(", ")),
+            },
+            "locations": [location(&first.location, base)],
+            "relatedLocations": others.iter().map(|entry| location(&entry.location, base)).collect::<Vec<_>>(),
+            "properties": { "crate": collision.krate, "code": collision.code },
+        }));
+    }
+
+    let rules = vec![json!({
+        "id": RULE,
+        "name": "ErrorCodeCollision",
+        "shortDescription": { "text": "Custom error code with more than one meaning" },
+        "fullDescription": {
+            "text": "Two error enums, an enum and a literal ProgramError::Custom code, or literal codes in different modules give a program's custom error code different meanings, so clients and monitoring cannot tell the errors apart. Give each enum its own range (#[error_code(offset = N)]) and use the enums instead of literal codes.",
+        },
+        "defaultConfiguration": { "level": "warning" },
+    })];
+    write_log(out, "mec", env!("CARGO_PKG_VERSION"), rules, results)
+}
@@ -0,0 +1,77 @@
+// Inventories tests/fixtures/workspace: an Anchor vault program whose two
+// #[error_code] enums both start at 6000, and a native lock program whose error enums
+// and literal codes overlap.
+
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use mec::{Options, Origin};
+
+fn fixture() -> PathBuf {
+    Path::new(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/workspace")).to_path_buf()
+}
+
+#[test]
+fn codes() {
+    let analysis = mec::analyze(&fixture(), Options::default()).unwrap();
+    assert!(analysis.errors.is_empty(), "{:?}", analysis.errors);
+    let codes: Vec<_> = analysis.entries.iter()
+        .map(|entry| (entry.krate.as_str(), entry.code, entry.name.as_str(), entry.origin, entry.module.as_str()))
+        .collect();
+    assert_eq!(codes, [
+        ("lock", 0, "LockError::NotExpired", Origin::Enum, "error"),
+        // Converted into ProgramError without thiserror
+        ("lock", 0, "StateError::Corrupt", Origin::Enum, "state"),
+        ("lock", 5, "LockError::AlreadyReleased", Origin::Enum, "error"),
+        ("lock", 5, "Custom(5)", Origin::Custom, "processor"),
+        ("lock", 6, "LockError::WrongBeneficiary", Origin::Enum, "error"),
+        // Twice in processor, listed once; the test module is left out
+        ("lock", 42, "Custom(UNKNOWN_MINT)", Origin::Custom, "processor"),
+        ("lock", 42, "Custom(42)", Origin::Custom, "state"),
+        ("vault", 6000, "AdminError::NotAdmin", Origin::ErrorCode, "admin"),
+        ("vault", 6000, "VaultError::InsufficientFunds", Origin::ErrorCode, "errors"),
+        ("vault", 6001, "VaultError::Unauthorized", Origin::ErrorCode, "errors"),
+        ("vault", 7000, "OracleError::Stale", Origin::ErrorCode, ""),
+    ]);
+    assert_eq!(analysis.entries[1].message.as_deref(), Some("The lock account could not be read"));
+    assert_eq!(analysis.entries[10].message.as_deref(), Some("The oracle price is stale"));
+    assert_eq!(analysis.enums(), 5);
+}
+
+#[test]
+fn collisions() {
+    let analysis = mec::analyze(&fixture(), Options::default()).unwrap();
+    let collisions: Vec<_> = analysis.collisions.iter()
+        .map(|collision| {
+            let names: Vec<_> = collision.entries.iter().map(|entry| entry.name.as_str()).collect();
+            (collision.krate.as_str(), collision.code, names)
+        })
+        .collect();
+    assert_eq!(collisions, [
+        ("lock", 0, vec!["LockError::NotExpired", "StateError::Corrupt"]),
+        ("lock", 5, vec!["LockError::AlreadyReleased", "Custom(5)"]),
+        ("lock", 42, vec!["Custom(UNKNOWN_MINT)", "Custom(42)"]),
+        ("vault", 6000, vec!["AdminError::NotAdmin", "VaultError::InsufficientFunds"]),
+    ]);
+}
+
+#[test]
+fn limit() {
+    let run = |args: &[&str]| Command::new(env!("CARGO_BIN_EXE_mec"))
+        .args(args)
+        .arg(fixture())
+        .output()
+        .expect("failed to run mec");
+
+    assert!(run(&["--fail-above", "4"]).status.success());
+
+    let output = run(&["--fail-above", "3", "--format", "sarif"]);
+    assert_eq!(output.status.code(), Some(1));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("4 colliding error code(s), more than 3"), "stderr: {}", stderr);
+    assert!(stderr.contains("vault code 6000: AdminError::NotAdmin, VaultError::InsufficientFunds"), "stderr: {}", stderr);
+    let log: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let results = log["runs"][0]["results"].as_array().unwrap();
+    assert_eq!(results.len(), 4);
+    assert_eq!(results[0]["relatedLocations"].as_array().unwrap().len(), 1);
+}
@@ -0,0 +1,18 @@
+use solana_program::program_error::ProgramError;
+use thiserror::Error;
+
+#[derive(Clone, Debug, Error)]
+pub enum LockError {
+    #[error("The lock has not expired")]
+    NotExpired,
+    #[error("The lock was already released")]
+    AlreadyReleased = 5,
+    #[error("Wrong beneficiary")]
+    WrongBeneficiary,
+}
+
+impl From<LockError> for ProgramError {
+    fn from(e: LockError) -> Self {
+        ProgramError::Custom(e as u32)
+    }
+}
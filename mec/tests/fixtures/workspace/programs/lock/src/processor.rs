@@ -0,0 +1,25 @@
+use solana_program::{msg, program_error::ProgramError};
+
+const UNKNOWN_MINT: u32 = 42;
+
+pub fn release(released: bool, mint_known: bool) -> Result<(), ProgramError> {
+    if released {
+        msg!("released twice");
+        return Err(ProgramError::Custom(5));
+    }
+    if !mint_known {
+        return Err(ProgramError::Custom(UNKNOWN_MINT));
+    }
+    if !mint_known {
+        return Err(ProgramError::Custom(UNKNOWN_MINT));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn codes() {
+        assert_eq!(solana_program::program_error::ProgramError::Custom(99), 99.into());
+    }
+}
@@ -0,0 +1,19 @@
+use solana_program::program_error::ProgramError;
+
+pub enum StateError {
+    /// The lock account could not be read
+    Corrupt,
+}
+
+impl From<StateError> for ProgramError {
+    fn from(e: StateError) -> Self {
+        ProgramError::Custom(e as u32)
+    }
+}
+
+pub fn check_size(data: &[u8]) -> Result<(), ProgramError> {
+    if data.len() != 64 {
+        return Err(ProgramError::Custom(42));
+    }
+    Ok(())
+}
@@ -0,0 +1,8 @@
+use anchor_lang::prelude::*;
+
+/// Numbered from 6000 too, so NotAdmin shares its code with InsufficientFunds
+#[error_code]
+pub enum AdminError {
+    #[msg("Only the admin may do this")]
+    NotAdmin,
+}
@@ -0,0 +1,9 @@
+use anchor_lang::prelude::*;
+
+#[error_code]
+pub enum VaultError {
+    #[msg("Not enough lamports in the vault")]
+    InsufficientFunds,
+    #[msg("The signer is not the vault authority")]
+    Unauthorized,
+}
@@ -0,0 +1,12 @@
+use anchor_lang::prelude::*;
+
+pub mod admin;
+pub mod errors;
+
+declare_id!("Fg6PaFpoGXkYsidMpWTK6W2BeZ7FEfcYkg476zPFsLnS");
+
+#[error_code(offset = 7000)]
+pub enum OracleError {
+    #[msg("The oracle price is stale")]
+    Stale,
+}
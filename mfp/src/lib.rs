@@ -0,0 +1,403 @@
+//! Floating point in Solana program crates: `f32`/`f64` types, float literals, casts to
+//! floats and float math.
+//!
+//! Floats have no place on-chain. Rounding depends on the order of operations, `NaN`
+//! and infinities slip through comparisons, conversions back to integers saturate
+//! silently, and SBF has no floating-point unit, so every operation is a costly
+//! software routine. Prices, rates and shares should use integers with a fixed scale.
+//!
+//! A site is one of:
+//! - `type`: `f32` or `f64` named in a type or a path (`price: f64`, `f64::MAX`)
+//! - `literal`: `1.5`, `1e9`, `2f64`
+//! - `cast`: `amount as f64`
+//! - `math`: arithmetic or a comparison with a float operand, or a float method
+//!   (`sqrt`, `powf`, `floor`, ...) on one
+//!
+//! An operand is a float when it is a float literal, a cast to a float, a path through
+//! `f32`/`f64`, a local or parameter declared as a float or bound to a float
+//! expression, or arithmetic on one of those. Arguments of macros that parse as
+//! expressions (`msg!`, `require!`, ...) are searched too. Only crates depending on a
+//! Solana program framework are analyzed unless asked, and `#[cfg(test)]` items,
+//! `#[test]` functions and `tests/` and `benches/` are left out.
+
+use std::collections::{BTreeMap, HashSet};
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use mdd::manifests::Manifests;
+use proc_macro2::Span;
+use serde::Serialize;
+use syn::punctuated::Punctuated;
+use syn::spanned::Spanned;
+use syn::visit::{self, Visit};
+use syn::{Attribute, BinOp, Expr, Lit, Pat, Token, Type};
+pub use trr_core::Location;
+use trr_core::source::{is_cfg_test, location};
+use trr_core::walk;
+
+/// Methods of `f32`/`f64` that count as float math on a float receiver
+pub const FLOAT_METHODS: &[&str] = &[
+    "sqrt", "cbrt", "powf", "powi", "exp", "exp2", "ln", "log", "log2", "log10", "sin", "cos", "tan", "atan",
+    "atan2", "hypot", "floor", "ceil", "round", "trunc", "fract", "abs", "mul_add", "recip", "min", "max",
+    "clamp", "to_degrees", "to_radians",
+];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum FloatKind {
+    Type,
+    Literal,
+    Cast,
+    Math,
+}
+
+impl FloatKind {
+    pub const ALL: [FloatKind; 4] = [FloatKind::Type, FloatKind::Literal, FloatKind::Cast, FloatKind::Math];
+}
+
+impl fmt::Display for FloatKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.pad(match self {
+            FloatKind::Type => "type",
+            FloatKind::Literal => "literal",
+            FloatKind::Cast => "cast",
+            FloatKind::Math => "math",
+        })
+    }
+}
+
+/// A crate the analyzed files belong to
+#[derive(Clone, Debug, Serialize)]
+pub struct Crate {
+    pub name: String,
+    pub root: PathBuf,
+    /// Depends directly on one of [`mdd::PROGRAM_FRAMEWORKS`], or has no manifest to
+    /// tell
+    pub program: bool,
+    /// Analyzed: a program, or any crate when asked
+    pub analyzed: bool,
+    /// Sites by kind
+    pub counts: BTreeMap<FloatKind, usize>,
+}
+
+impl Crate {
+    pub fn sites(&self) -> usize {
+        self.counts.values().sum()
+    }
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct FloatSite {
+    #[serde(rename = "crate")]
+    pub krate: String,
+    /// The enclosing item: inline modules, then the impl type or trait, then the
+    /// function, struct, const or other item
+    pub item: String,
+    pub kind: FloatKind,
+    /// The source of the site, when it fits on one line
+    pub code: Option<String>,
+    pub location: Location,
+}
+
+#[derive(Debug, Default)]
+pub struct Analysis {
+    pub crates: Vec<Crate>,
+    /// By crate, then file and position
+    pub sites: Vec<FloatSite>,
+    /// Files that could not be read or parsed
+    pub errors: Vec<(PathBuf, String)>,
+}
+
+impl Analysis {
+    pub fn analyzed_crates(&self) -> usize {
+        self.crates.iter().filter(|krate| krate.analyzed).count()
+    }
+
+    /// Analyzed crates with at least one site
+    pub fn crates_with_floats(&self) -> usize {
+        self.crates.iter().filter(|krate| krate.sites() > 0).count()
+    }
+
+    /// Sites of `kind`
+    pub fn count(&self, kind: FloatKind) -> usize {
+        self.sites.iter().filter(|site| site.kind == kind).count()
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Options {
+    /// Library and client crates too, not only programs
+    pub all_crates: bool,
+}
+
+/// Find the floating point of `source`, the contents of `path`, in `krate`
+pub fn analyze_source(path: &Path, source: &str, krate: &str) -> syn::Result<Vec<FloatSite>> {
+    let file = syn::parse_file(source)?;
+    let mut visitor = FloatVisitor { path, source, krate, scope: Vec::new(), floats: Vec::new(), sites: Vec::new() };
+    visitor.visit_file(&file);
+    Ok(visitor.sites)
+}
+
+/// Find the floating point of the crates under `dir` (or `dir` itself, if it is a file)
+/// that `options` select, leaving out `target/` and test code
+pub fn analyze(dir: &Path, options: Options) -> io::Result<Analysis> {
+    let files = walk::program_files(dir)?;
+
+    let mut manifests = Manifests::default();
+    let mut analysis = Analysis::default();
+    for file in files {
+        let krate = manifests.crate_of(&file, dir);
+        let index = match analysis.crates.iter().position(|known| known.root == krate.root) {
+            Some(index) => index,
+            None => {
+                let analyzed = krate.program || options.all_crates;
+                analysis.crates.push(Crate { name: krate.name, root: krate.root, program: krate.program, analyzed, counts: BTreeMap::new() });
+                analysis.crates.len() - 1
+            }
+        };
+        if !analysis.crates[index].analyzed {
+            continue;
+        }
+        let result = fs::read_to_string(&file)
+            .map_err(|e| e.to_string())
+            .and_then(|source| analyze_source(&file, &source, &analysis.crates[index].name).map_err(|e| e.to_string()));
+        match result {
+            Ok(sites) => {
+                for site in &sites {
+                    *analysis.crates[index].counts.entry(site.kind).or_default() += 1;
+                }
+                analysis.sites.extend(sites);
+            }
+            Err(e) => analysis.errors.push((file, e)),
+        }
+    }
+    analysis.crates.sort_by(|a, b| a.name.cmp(&b.name));
+    analysis.sites.sort_by(|a, b| a.krate.cmp(&b.krate)
+        .then_with(|| a.location.file.cmp(&b.location.file))
+        .then_with(|| a.location.line.cmp(&b.location.line))
+        .then_with(|| a.location.column.cmp(&b.location.column)));
+    Ok(analysis)
+}
+
+fn is_float_name(ident: &syn::Ident) -> bool {
+    ident == "f32" || ident == "f64"
+}
+
+/// `f32`, `f64`, or a reference to one
+fn is_float_type(ty: &Type) -> bool {
+    match ty {
+        Type::Path(path) => path.qself.is_none() && path.path.segments.last().is_some_and(|segment| is_float_name(&segment.ident)),
+        Type::Reference(reference) => is_float_type(&reference.elem),
+        Type::Paren(paren) => is_float_type(&paren.elem),
+        _ => false,
+    }
+}
+
+/// The names a pattern binds
+fn bindings(pattern: &Pat, names: &mut Vec<String>) {
+    match pattern {
+        Pat::Ident(ident) => names.push(ident.ident.to_string()),
+        Pat::Type(typed) => bindings(&typed.pat, names),
+        Pat::Reference(reference) => bindings(&reference.pat, names),
+        _ => {}
+    }
+}
+
+struct FloatVisitor<'a> {
+    path: &'a Path,
+    source: &'a str,
+    krate: &'a str,
+    /// Inline modules, impl types, traits and the items being visited
+    scope: Vec<String>,
+    /// The float locals and parameters of each function being visited, innermost last
+    floats: Vec<HashSet<String>>,
+    sites: Vec<FloatSite>,
+}
+
+impl FloatVisitor<'_> {
+    /// The source of `span`, if it is on one line
+    fn snippet(&self, span: Span) -> Option<String> {
+        let (start, end) = (span.start(), span.end());
+        if start.line != end.line {
+            return None;
+        }
+        let line = self.source.lines().nth(start.line - 1)?;
+        let text: String = line.chars().skip(start.column).take(end.column - start.column).collect();
+        Some(text)
+    }
+
+    fn push(&mut self, kind: FloatKind, span: Span, at: Span) {
+        let item = if self.scope.is_empty() { "_".to_string() } else { self.scope.join("::") };
+        self.sites.push(FloatSite { krate: self.krate.to_string(), item, kind, code: self.snippet(span), location: location(self.path, at) });
+    }
+
+    /// Visit an item named `name`, unless it is test code
+    fn item(&mut self, name: String, attrs: &[Attribute], visit: impl FnOnce(&mut Self)) {
+        if is_cfg_test(attrs) {
+            return;
+        }
+        self.scope.push(name);
+        visit(self);
+        self.scope.pop();
+    }
+
+    fn function(&mut self, sig: &syn::Signature, attrs: &[Attribute], visit: impl FnOnce(&mut Self)) {
+        let mut floats = HashSet::new();
+        for input in &sig.inputs {
+            if let syn::FnArg::Typed(typed) = input {
+                if is_float_type(&typed.ty) {
+                    let mut names = Vec::new();
+                    bindings(&typed.pat, &mut names);
+                    floats.extend(names);
+                }
+            }
+        }
+        self.floats.push(floats);
+        self.item(sig.ident.to_string(), attrs, visit);
+        self.floats.pop();
+    }
+
+    /// Whether `expr` is a float, as far as the source tells
+    fn is_float(&self, expr: &Expr) -> bool {
+        match expr {
+            Expr::Lit(literal) => matches!(literal.lit, Lit::Float(_)),
+            Expr::Cast(cast) => is_float_type(&cast.ty),
+            Expr::Path(path) => {
+                path.path.segments.iter().any(|segment| is_float_name(&segment.ident))
+                    || path.path.get_ident().is_some_and(|ident| {
+                        self.floats.last().is_some_and(|floats| floats.contains(&ident.to_string()))
+                    })
+            }
+            Expr::Call(call) => matches!(&*call.func, Expr::Path(path) if path.path.segments.iter().any(|segment| is_float_name(&segment.ident))),
+            Expr::Binary(binary) => is_arithmetic(&binary.op) && (self.is_float(&binary.left) || self.is_float(&binary.right)),
+            Expr::Unary(unary) => self.is_float(&unary.expr),
+            Expr::Paren(paren) => self.is_float(&paren.expr),
+            Expr::Reference(reference) => self.is_float(&reference.expr),
+            Expr::MethodCall(call) => FLOAT_METHODS.contains(&call.method.to_string().as_str()) && self.is_float(&call.receiver),
+            _ => false,
+        }
+    }
+}
+
+fn is_arithmetic(op: &BinOp) -> bool {
+    matches!(
+        op,
+        BinOp::Add(_) | BinOp::Sub(_) | BinOp::Mul(_) | BinOp::Div(_) | BinOp::Rem(_)
+            | BinOp::AddAssign(_) | BinOp::SubAssign(_) | BinOp::MulAssign(_) | BinOp::DivAssign(_) | BinOp::RemAssign(_)
+    )
+}
+
+fn is_comparison(op: &BinOp) -> bool {
+    matches!(op, BinOp::Eq(_) | BinOp::Ne(_) | BinOp::Lt(_) | BinOp::Le(_) | BinOp::Gt(_) | BinOp::Ge(_))
+}
+
+impl<'ast> Visit<'ast> for FloatVisitor<'_> {
+    fn visit_item_mod(&mut self, item: &'ast syn::ItemMod) {
+        self.item(item.ident.to_string(), &item.attrs, |visitor| visit::visit_item_mod(visitor, item));
+    }
+
+    fn visit_item_impl(&mut self, item: &'ast syn::ItemImpl) {
+        let owner = match &*item.self_ty {
+            Type::Path(path) => path.path.segments.last().map(|segment| segment.ident.to_string()),
+            _ => None,
+        };
+        self.item(owner.unwrap_or_else(|| "_".to_string()), &item.attrs, |visitor| visit::visit_item_impl(visitor, item));
+    }
+
+    fn visit_item_trait(&mut self, item: &'ast syn::ItemTrait) {
+        self.item(item.ident.to_string(), &item.attrs, |visitor| visit::visit_item_trait(visitor, item));
+    }
+
+    fn visit_item_fn(&mut self, item: &'ast syn::ItemFn) {
+        self.function(&item.sig, &item.attrs, |visitor| visit::visit_item_fn(visitor, item));
+    }
+
+    fn visit_impl_item_fn(&mut self, item: &'ast syn::ImplItemFn) {
+        self.function(&item.sig, &item.attrs, |visitor| visit::visit_impl_item_fn(visitor, item));
+    }
+
+    fn visit_trait_item_fn(&mut self, item: &'ast syn::TraitItemFn) {
+        self.function(&item.sig, &item.attrs, |visitor| visit::visit_trait_item_fn(visitor, item));
+    }
+
+    fn visit_item_struct(&mut self, item: &'ast syn::ItemStruct) {
+        self.item(item.ident.to_string(), &item.attrs, |visitor| visit::visit_item_struct(visitor, item));
+    }
+
+    fn visit_item_enum(&mut self, item: &'ast syn::ItemEnum) {
+        self.item(item.ident.to_string(), &item.attrs, |visitor| visit::visit_item_enum(visitor, item));
+    }
+
+    fn visit_item_const(&mut self, item: &'ast syn::ItemConst) {
+        self.item(item.ident.to_string(), &item.attrs, |visitor| visit::visit_item_const(visitor, item));
+    }
+
+    fn visit_item_static(&mut self, item: &'ast syn::ItemStatic) {
+        self.item(item.ident.to_string(), &item.attrs, |visitor| visit::visit_item_static(visitor, item));
+    }
+
+    fn visit_item_type(&mut self, item: &'ast syn::ItemType) {
+        self.item(item.ident.to_string(), &item.attrs, |visitor| visit::visit_item_type(visitor, item));
+    }
+
+    fn visit_local(&mut self, local: &'ast syn::Local) {
+        let float = match &local.pat {
+            Pat::Type(typed) => is_float_type(&typed.ty),
+            _ => local.init.as_ref().is_some_and(|init| self.is_float(&init.expr)),
+        };
+        visit::visit_local(self, local);
+        if float {
+            let mut names = Vec::new();
+            bindings(&local.pat, &mut names);
+            if let Some(floats) = self.floats.last_mut() {
+                floats.extend(names);
+            }
+        }
+    }
+
+    fn visit_ident(&mut self, ident: &'ast syn::Ident) {
+        if is_float_name(ident) {
+            self.push(FloatKind::Type, ident.span(), ident.span());
+        }
+    }
+
+    fn visit_lit(&mut self, lit: &'ast Lit) {
+        if let Lit::Float(float) = lit {
+            self.push(FloatKind::Literal, float.span(), float.span());
+        }
+    }
+
+    fn visit_expr_cast(&mut self, cast: &'ast syn::ExprCast) {
+        if is_float_type(&cast.ty) {
+            self.push(FloatKind::Cast, cast.span(), cast.as_token.span);
+            self.visit_expr(&cast.expr);
+        } else {
+            visit::visit_expr_cast(self, cast);
+        }
+    }
+
+    fn visit_expr_binary(&mut self, expr: &'ast syn::ExprBinary) {
+        if (is_arithmetic(&expr.op) || is_comparison(&expr.op)) && (self.is_float(&expr.left) || self.is_float(&expr.right)) {
+            self.push(FloatKind::Math, expr.span(), expr.op.span());
+        }
+        visit::visit_expr_binary(self, expr);
+    }
+
+    fn visit_expr_method_call(&mut self, call: &'ast syn::ExprMethodCall) {
+        if FLOAT_METHODS.contains(&call.method.to_string().as_str()) && self.is_float(&call.receiver) {
+            self.push(FloatKind::Math, call.span(), call.method.span());
+        }
+        visit::visit_expr_method_call(self, call);
+    }
+
+    fn visit_macro(&mut self, mac: &'ast syn::Macro) {
+        // Arguments written as expressions, as in `msg!` and `require!`
+        if let Ok(arguments) = mac.parse_body_with(Punctuated::<Expr, Token![,]>::parse_terminated) {
+            for argument in &arguments {
+                self.visit_expr(argument);
+            }
+        }
+    }
+}
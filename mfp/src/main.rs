@@ -0,0 +1,178 @@
+mod report;
+mod sarif;
+
+use std::collections::BTreeMap;
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::process;
+
+use clap::Parser;
+use serde::Serialize;
+use trr_core::git::Repository;
+use trr_core::output::{self, Format};
+
+use mfp::{Analysis, Crate, FloatKind, FloatSite, Options};
+
+#[derive(Parser)]
+#[clap(author, version, about = "MFP (Floating Point) Detector")]
+#[clap(after_help = "Examples:
+  mfp programs/amm
+  mfp --all-crates .
+  mfp --format sarif programs > floats.sarif
+  mfp --fail-above 0 programs")]
+struct Args {
+    /// Directory to analyze (relative to --repo when given)
+    #[clap(value_name = "DIR", default_value = ".")]
+    dir: PathBuf,
+
+    /// Git repository URL to clone, or path of a local checkout
+    #[clap(long, value_name = "REPO_URL_OR_PATH")]
+    repo: Option<String>,
+
+    /// Branch, tag or commit of --repo to analyze
+    #[clap(long, value_name = "REV", requires = "repo")]
+    rev: Option<String>,
+
+    /// Output format
+    #[clap(long, value_enum, default_value = "text")]
+    format: Format,
+
+    /// Analyze every crate, not only those depending on a Solana program framework
+    #[clap(long)]
+    all_crates: bool,
+
+    /// Exit with status 1 if there are more than N floating-point sites
+    #[clap(long, value_name = "N")]
+    fail_above: Option<usize>,
+}
+
+/// Structured form of the analysis for `--format json`
+#[derive(Serialize)]
+struct JsonReport<'a> {
+    summary: Summary,
+    /// With their sites by kind
+    crates: &'a [Crate],
+    /// By crate, then file and position
+    sites: &'a [FloatSite],
+    errors: Vec<FileError>,
+}
+
+#[derive(Serialize)]
+struct Summary {
+    /// Crates analyzed
+    crates: usize,
+    crates_with_floats: usize,
+    sites: usize,
+    by_kind: BTreeMap<FloatKind, usize>,
+}
+
+#[derive(Serialize)]
+struct FileError {
+    file: PathBuf,
+    error: String,
+}
+
+/// `type 2, literal 3`
+fn counts(counts: &BTreeMap<FloatKind, usize>) -> String {
+    counts.iter().map(|(kind, count)| format!("{} {}", kind, count)).collect::<Vec<_>>().join(", ")
+}
+
+fn print_text(analysis: &Analysis) {
+    println!("\nCrates:");
+    for krate in &analysis.crates {
+        let kind = if krate.program { "program" } else { "library" };
+        if !krate.analyzed {
+            println!("  {} ({}, skipped)", krate.name, kind);
+        } else if krate.sites() == 0 {
+            println!("  {} ({}): none", krate.name, kind);
+        } else {
+            println!("  {} ({}): {} ({})", krate.name, kind, krate.sites(), counts(&krate.counts));
+        }
+    }
+    let mut krate = None;
+    for site in &analysis.sites {
+        if krate != Some(&site.krate) {
+            println!("\n{}:", site.krate);
+            krate = Some(&site.krate);
+        }
+        match &site.code {
+            Some(code) => println!("  {:<7} {} in {} at {}", site.kind, code, site.item, site.location),
+            None => println!("  {:<7} in {} at {}", site.kind, site.item, site.location),
+        }
+    }
+
+    println!("\nSummary:");
+    println!("Crates analyzed: {}", analysis.analyzed_crates());
+    println!("Crates using floating point: {}", analysis.crates_with_floats());
+    println!("Floating-point sites: {}", analysis.sites.len());
+    for kind in FloatKind::ALL {
+        let count = analysis.count(kind);
+        if count > 0 {
+            println!("  {}: {}", kind, count);
+        }
+    }
+}
+
+fn write_json(out: &mut dyn Write, analysis: &Analysis) -> io::Result<()> {
+    let report = JsonReport {
+        summary: Summary {
+            crates: analysis.analyzed_crates(),
+            crates_with_floats: analysis.crates_with_floats(),
+            sites: analysis.sites.len(),
+            by_kind: FloatKind::ALL.into_iter().map(|kind| (kind, analysis.count(kind))).collect(),
+        },
+        crates: &analysis.crates,
+        sites: &analysis.sites,
+        errors: analysis.errors.iter()
+            .map(|(file, error)| FileError { file: file.clone(), error: error.clone() })
+            .collect(),
+    };
+    output::write_json(out, "mfp", &report)
+}
+
+fn run(args: &Args) -> Result<(), Box<dyn std::error::Error>> {
+    let repository = args.repo.as_deref().map(|repo| Repository::open(repo, args.rev.as_deref())).transpose()?;
+    let dir = match &repository {
+        Some(repository) => repository.subdir(&args.dir)?,
+        None => args.dir.clone(),
+    };
+    if !dir.exists() {
+        return Err(format!("Path '{}' does not exist", dir.display()).into());
+    }
+    if args.format == Format::Text {
+        println!("Analyzing: {}", dir.display());
+    }
+
+    let analysis = mfp::analyze(&dir, Options { all_crates: args.all_crates })?;
+    for (file, error) in &analysis.errors {
+        eprintln!("Error analyzing {}: {}", file.display(), error);
+    }
+
+    let mut out = io::stdout().lock();
+    match args.format {
+        Format::Text => print_text(&analysis),
+        Format::Json => write_json(&mut out, &analysis)?,
+        Format::Csv => report::write_csv(&mut out, &analysis.sites)?,
+        Format::Markdown => report::write_markdown(&mut out, &dir, &analysis)?,
+        Format::Sarif => {
+            // Artifact URIs are relative to the repository root, or to where mfp runs
+            let base = repository.as_ref().map_or_else(|| PathBuf::from("."), |repository| repository.root().to_path_buf());
+            sarif::write_sarif(&mut out, &analysis.sites, &base)?;
+            writeln!(out)?;
+        }
+    }
+    drop(out);
+    let crates = analysis.crates.iter().filter(|krate| krate.sites() > 0);
+    output::enforce_limit(args.fail_above, analysis.sites.len(), "floating-point site(s)", crates.map(|krate| {
+        format!("{}: {} ({})", krate.name, krate.sites(), counts(&krate.counts))
+    }));
+    Ok(())
+}
+
+fn main() {
+    let args = Args::parse();
+    if let Err(e) = run(&args) {
+        eprintln!("Error: {}", e);
+        process::exit(1);
+    }
+}
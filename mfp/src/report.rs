@@ -0,0 +1,78 @@
+//! `--format csv`, one row per site, and `--format markdown`, a review-ready report with
+//! the counts of each crate and the sites.
+
+use std::io::{self, Write};
+use std::path::Path;
+
+use trr_core::output::{markdown_code, write_csv_row};
+
+use mfp::{Analysis, FloatKind, FloatSite};
+
+/// Rows are by crate, then file and position
+pub fn write_csv(out: &mut dyn Write, sites: &[FloatSite]) -> io::Result<()> {
+    write_csv_row(out, &["crate", "item", "kind", "code", "file", "line", "column"])?;
+    for site in sites {
+        write_csv_row(out, &[
+            &site.krate,
+            &site.item,
+            &site.kind.to_string(),
+            site.code.as_deref().unwrap_or(""),
+            &site.location.file.display().to_string(),
+            &site.location.line.to_string(),
+            &site.location.column.map_or(String::new(), |column| column.to_string()),
+        ])?;
+    }
+    Ok(())
+}
+
+pub fn write_markdown(out: &mut dyn Write, target: &Path, analysis: &Analysis) -> io::Result<()> {
+    writeln!(out, "# Floating Point Report\n")?;
+    writeln!(out, "Target: {}\n", markdown_code(&target.display().to_string()))?;
+    writeln!(out, "## Summary\n")?;
+    writeln!(out, "| Metric | Value |")?;
+    writeln!(out, "| --- | ---: |")?;
+    writeln!(out, "| Crates analyzed | {} |", analysis.analyzed_crates())?;
+    writeln!(out, "| Crates using floating point | {} |", analysis.crates_with_floats())?;
+    writeln!(out, "| Floating-point sites | {} |", analysis.sites.len())?;
+
+    if !analysis.crates.is_empty() {
+        writeln!(out, "\n## Crates\n")?;
+        writeln!(out, "| Crate | Program | Analyzed | Sites | Types | Literals | Casts | Math |")?;
+        writeln!(out, "| --- | --- | --- | ---: | ---: | ---: | ---: | ---: |")?;
+        let yes_no = |value: bool| if value { "yes" } else { "no" };
+        for krate in &analysis.crates {
+            let count = |kind| krate.counts.get(&kind).copied().unwrap_or(0);
+            writeln!(
+                out,
+                "| {} | {} | {} | {} | {} | {} | {} | {} |",
+                markdown_code(&krate.name),
+                yes_no(krate.program),
+                yes_no(krate.analyzed),
+                krate.sites(),
+                count(FloatKind::Type),
+                count(FloatKind::Literal),
+                count(FloatKind::Cast),
+                count(FloatKind::Math),
+            )?;
+        }
+    }
+
+    if analysis.sites.is_empty() {
+        return Ok(());
+    }
+    writeln!(out, "\n## Sites\n")?;
+    writeln!(out, "| Crate | Item | Kind | Code | Location |")?;
+    writeln!(out, "| --- | --- | --- | --- | --- |")?;
+    for site in &analysis.sites {
+        writeln!(
+            out,
+            "| {} | {} | {} | {} | {} |",
+            markdown_code(&site.krate),
+            markdown_code(&site.item),
+            site.kind,
+            site.code.as_deref().map_or(String::new(), markdown_code),
+            markdown_code(&site.location.to_string()),
+        )?;
+    }
+    Ok(())
+}
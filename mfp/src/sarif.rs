@@ -0,0 +1,41 @@
+//! SARIF 2.1.0 output: a `floating-point` warning per site, located at the type, the
+//! literal, the `as` of the cast or the operator.
+
+use std::io::{self, Write};
+use std::path::Path;
+
+use serde_json::json;
+use trr_core::sarif::{location, write_log};
+
+use mfp::FloatSite;
+
+const RULE: &str = "floating-point";
+
+pub fn write_sarif(out: &mut dyn Write, sites: &[FloatSite], base: &Path) -> io::Result<()> {
+    let mut results = Vec::new();
+    for site in sites {
+        let text = match &site.code {
+            Some(code) => format!("Floating point ({}) in {}: {}", site.kind, site.item, code),
+            None => format!("Floating point ({}) in {}", site.kind, site.item),
+        };
+        results.push(json!({
+            "ruleId": RULE,
+            "ruleIndex": 0,
+            "level": "warning",
+            "message": { "text": text },
+            "locations": [location(&site.location, base)],
+            "properties": { "crate": site.krate, "item": site.item, "kind": site.kind },
+        }));
+    }
+
+    let rules = vec![json!({
+        "id": RULE,
+        "name": "FloatingPoint",
+        "shortDescription": { "text": "Floating-point type, literal or math in a program crate" },
+        "fullDescription": {
+            "text": "f32/f64 values round depending on the order of operations, let NaN and infinities through comparisons and saturate when converted back to integers, and are emulated in software on SBF. Use integers with a fixed scale for prices, rates and shares.",
+        },
+        "defaultConfiguration": { "level": "warning" },
+    })];
+    write_log(out, "mfp", env!("CARGO_PKG_VERSION"), rules, results)
+}
@@ -0,0 +1,35 @@
+use solana_program::msg;
+
+pub const FEE_RATE: f64 = 0.003;
+
+pub struct Pool {
+    pub reserve_a: u64,
+    pub reserve_b: u64,
+    pub last_price: f32,
+}
+
+impl Pool {
+    pub fn price(&self) -> u64 {
+        let ratio = self.reserve_a as f64 / self.reserve_b as f64;
+        (ratio.sqrt() * 1e6) as u64
+    }
+
+    pub fn swap_out(&self, amount_in: u64) -> u64 {
+        let fee = amount_in * 3 / 1000;
+        let out = (amount_in - fee) as u128 * self.reserve_b as u128 / self.reserve_a as u128;
+        msg!("Out {} at {}", out, self.price());
+        out as u64
+    }
+
+    pub fn stale(&self, price: f32) -> bool {
+        price > self.last_price
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn price() {
+        assert!((super::FEE_RATE - 0.003).abs() < f64::EPSILON);
+    }
+}
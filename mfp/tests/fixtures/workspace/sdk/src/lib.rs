@@ -0,0 +1,3 @@
+pub fn ui_amount(amount: u64, decimals: u8) -> f64 {
+    amount as f64 / 10u64.pow(decimals as u32) as f64
+}
@@ -0,0 +1,70 @@
+// Locates the floating-point code of tests/fixtures/workspace, whose rounding differs
+// from the integer math on-chain programs settle amounts with: the amm program's f64
+// fee rate, its f32 price field, a reserve ratio taken through casts, a division and
+// sqrt, and a float comparison. Its integer swap math and #[cfg(test)] check are not
+// sites, and the sdk crate's floats only count with --all-crates.
+
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use mfp::{FloatKind, Options};
+
+fn fixture() -> PathBuf {
+    Path::new(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/workspace")).to_path_buf()
+}
+
+#[test]
+fn program_sites() {
+    let analysis = mfp::analyze(&fixture(), Options::default()).unwrap();
+    assert!(analysis.errors.is_empty(), "{:?}", analysis.errors);
+    let sites: Vec<_> = analysis.sites.iter()
+        .map(|site| (site.item.as_str(), site.kind, site.location.line, site.code.as_deref().unwrap_or("")))
+        .collect();
+    assert_eq!(sites, [
+        ("FEE_RATE", FloatKind::Type, 3, "f64"),
+        ("FEE_RATE", FloatKind::Literal, 3, "0.003"),
+        ("Pool", FloatKind::Type, 8, "f32"),
+        ("Pool::price", FloatKind::Cast, 13, "self.reserve_a as f64"),
+        ("Pool::price", FloatKind::Math, 13, "self.reserve_a as f64 / self.reserve_b as f64"),
+        ("Pool::price", FloatKind::Cast, 13, "self.reserve_b as f64"),
+        // `ratio` is bound to a float
+        ("Pool::price", FloatKind::Math, 14, "ratio.sqrt()"),
+        ("Pool::price", FloatKind::Math, 14, "ratio.sqrt() * 1e6"),
+        ("Pool::price", FloatKind::Literal, 14, "1e6"),
+        // `price` is a float parameter
+        ("Pool::stale", FloatKind::Type, 24, "f32"),
+        ("Pool::stale", FloatKind::Math, 25, "price > self.last_price"),
+    ]);
+}
+
+#[test]
+fn crate_counts() {
+    let analysis = mfp::analyze(&fixture(), Options::default()).unwrap();
+    let crates: Vec<_> = analysis.crates.iter().map(|krate| (krate.name.as_str(), krate.analyzed, krate.sites())).collect();
+    assert_eq!(crates, [("amm", true, 11), ("amm-sdk", false, 0)]);
+    assert_eq!(analysis.crates[0].counts.get(&FloatKind::Math), Some(&4));
+
+    let analysis = mfp::analyze(&fixture(), Options { all_crates: true }).unwrap();
+    assert_eq!(analysis.crates_with_floats(), 2);
+    assert_eq!(analysis.crates[1].sites(), 4);
+}
+
+#[test]
+fn limit() {
+    let run = |args: &[&str]| Command::new(env!("CARGO_BIN_EXE_mfp"))
+        .args(args)
+        .arg(fixture())
+        .output()
+        .expect("failed to run mfp");
+
+    assert!(run(&["--fail-above", "11"]).status.success());
+
+    let output = run(&["--fail-above", "0", "--format", "json"]);
+    assert_eq!(output.status.code(), Some(1));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("11 floating-point site(s), more than 0"), "stderr: {}", stderr);
+    assert!(stderr.contains("amm: 11 (type 3, literal 2, cast 2, math 4)"), "stderr: {}", stderr);
+    let report: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(report["summary"]["by_kind"]["cast"], 2);
+    assert_eq!(report["crates"][0]["counts"]["math"], 4);
+}
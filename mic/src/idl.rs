@@ -0,0 +1,194 @@
+//! Reading Anchor IDLs, in the legacy format (Anchor 0.29 and earlier: camelCase names,
+//! `isMut`/`isSigner`, account types inline, `publicKey`) and the current one (Anchor
+//! 0.30 and later: snake_case names, `writable`/`signer`, account types under `types`,
+//! `pubkey`). Types are read into the form [`crate::source`] gives Rust types: `u64`,
+//! `pubkey`, `string`, `bytes`, `vec<T>`, `option<T>`, `[T; N]` or a type name.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use serde_json::Value;
+
+use crate::Location;
+
+/// A field, argument or variant
+#[derive(Clone, Debug)]
+pub struct Member {
+    /// As written in the IDL
+    pub name: String,
+    /// `None` for variants, and for types this reader does not know
+    pub ty: Option<String>,
+}
+
+/// An account of an instruction
+#[derive(Clone, Debug)]
+pub struct InstructionAccount {
+    pub name: String,
+    pub writable: bool,
+    pub signer: bool,
+    /// A nested accounts struct, with its own accounts
+    pub composite: bool,
+}
+
+#[derive(Clone, Debug)]
+pub struct Instruction {
+    pub name: String,
+    pub accounts: Vec<InstructionAccount>,
+    pub args: Vec<Member>,
+}
+
+#[derive(Clone, Debug)]
+pub enum TypeKind {
+    Struct(Vec<Member>),
+    Enum(Vec<Member>),
+    /// Aliases and types this reader does not know
+    Other,
+}
+
+#[derive(Clone, Debug)]
+pub struct TypeDef {
+    pub name: String,
+    pub kind: TypeKind,
+}
+
+#[derive(Clone, Debug)]
+pub struct Idl {
+    pub path: PathBuf,
+    /// The program's name: `name`, or `metadata.name`
+    pub name: String,
+    pub instructions: Vec<Instruction>,
+    /// Names of the account types
+    pub accounts: Vec<String>,
+    /// Account types (inline in legacy IDLs) and the other types
+    pub types: Vec<TypeDef>,
+    source: String,
+}
+
+impl Idl {
+    /// Read the IDL at `path`; `Ok(None)` if it is JSON but not an IDL
+    pub fn load(path: &Path) -> io::Result<Option<Idl>> {
+        let source = fs::read_to_string(path)?;
+        let value: Value = serde_json::from_str(&source).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        Ok(parse(path, &value).map(|idl| Idl { source, ..idl }))
+    }
+
+    pub fn type_def(&self, name: &str) -> Option<&TypeDef> {
+        self.types.iter().find(|def| def.name == name)
+    }
+
+    /// Where `names` are, each searched for as a `"name"` after the previous one (or as a
+    /// key, for the `instructions`, `accounts` and `types` sections): the line
+    /// of the last one found, so `["instructions", "deposit", "amount"]` is the `amount`
+    /// argument of `deposit`
+    pub fn location(&self, names: &[&str]) -> Location {
+        let mut offset = 0;
+        for (index, name) in names.iter().enumerate() {
+            let section = index == 0 && SECTIONS.contains(name);
+            let quoted = format!("\"{}\"", name);
+            let mut start = offset;
+            while let Some(found) = self.source[start..].find(&quoted) {
+                let position = start + found;
+                let is_key = self.source[position + quoted.len()..].trim_start().starts_with(':');
+                let is_name = self.source[..position].trim_end().strip_suffix(':').is_some_and(|before| before.trim_end().ends_with("\"name\""));
+                if (section && is_key) || (!section && is_name) {
+                    offset = position;
+                    break;
+                }
+                start = position + quoted.len();
+            }
+        }
+        let line = self.source[..offset].matches('\n').count() + 1;
+        Location::new(self.path.clone(), line)
+    }
+}
+
+const SECTIONS: [&str; 3] = ["instructions", "accounts", "types"];
+
+fn str_field<'a>(value: &'a Value, key: &str) -> Option<&'a str> {
+    value.get(key).and_then(Value::as_str)
+}
+
+fn array<'a>(value: &'a Value, key: &str) -> &'a [Value] {
+    value.get(key).and_then(Value::as_array).map_or(&[], Vec::as_slice)
+}
+
+fn flag(value: &Value, keys: &[&str]) -> bool {
+    keys.iter().any(|key| value.get(*key).and_then(Value::as_bool).unwrap_or(false))
+}
+
+fn parse(path: &Path, value: &Value) -> Option<Idl> {
+    let instructions = value.get("instructions")?.as_array()?;
+    let name = str_field(value, "name").or_else(|| value.get("metadata").and_then(|metadata| str_field(metadata, "name")))?;
+
+    let mut types: Vec<TypeDef> = array(value, "accounts").iter().filter_map(type_def).collect();
+    types.extend(array(value, "types").iter().filter_map(type_def));
+    Some(Idl {
+        path: path.to_path_buf(),
+        name: name.to_string(),
+        instructions: instructions.iter().filter_map(|instruction| {
+            Some(Instruction {
+                name: str_field(instruction, "name")?.to_string(),
+                accounts: accounts(array(instruction, "accounts")),
+                args: array(instruction, "args").iter().filter_map(member).collect(),
+            })
+        }).collect(),
+        accounts: array(value, "accounts").iter().filter_map(|account| str_field(account, "name").map(str::to_string)).collect(),
+        types,
+        source: String::new(),
+    })
+}
+
+fn accounts(values: &[Value]) -> Vec<InstructionAccount> {
+    values.iter().filter_map(|account| {
+        Some(InstructionAccount {
+            name: str_field(account, "name")?.to_string(),
+            writable: flag(account, &["isMut", "writable"]),
+            signer: flag(account, &["isSigner", "signer"]),
+            composite: account.get("accounts").is_some(),
+        })
+    }).collect()
+}
+
+fn member(value: &Value) -> Option<Member> {
+    Some(Member { name: str_field(value, "name")?.to_string(), ty: value.get("type").and_then(type_name) })
+}
+
+fn type_def(value: &Value) -> Option<TypeDef> {
+    let name = str_field(value, "name")?.to_string();
+    // Current IDLs list accounts by name and discriminator only
+    let ty = value.get("type")?;
+    let kind = match str_field(ty, "kind") {
+        Some("struct") => TypeKind::Struct(array(ty, "fields").iter().filter_map(member).collect()),
+        Some("enum") => TypeKind::Enum(array(ty, "variants").iter()
+            .filter_map(|variant| Some(Member { name: str_field(variant, "name")?.to_string(), ty: None }))
+            .collect()),
+        _ => TypeKind::Other,
+    };
+    Some(TypeDef { name, kind })
+}
+
+/// An IDL type in the common form, `None` if this reader does not know it
+pub fn type_name(value: &Value) -> Option<String> {
+    if let Some(name) = value.as_str() {
+        return Some(match name {
+            "publicKey" | "pubkey" => "pubkey".to_string(),
+            other => other.to_string(),
+        });
+    }
+    let object = value.as_object()?;
+    let (key, inner) = object.iter().next()?;
+    match key.as_str() {
+        "vec" => type_name(inner).map(|inner| if inner == "u8" { "bytes".to_string() } else { format!("vec<{}>", inner) }),
+        "option" | "coption" => type_name(inner).map(|inner| format!("option<{}>", inner)),
+        "array" => {
+            let [element, length] = inner.as_array()?.as_slice() else { return None };
+            Some(format!("[{}; {}]", type_name(element)?, length.as_u64()?))
+        }
+        "defined" => {
+            let name = inner.as_str().or_else(|| str_field(inner, "name"))?;
+            Some(name.rsplit("::").next().unwrap_or(name).to_string())
+        }
+        _ => None,
+    }
+}
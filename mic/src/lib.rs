@@ -0,0 +1,454 @@
+//! IDL drift: Anchor IDLs, built into `target/idl` or committed for clients, that no
+//! longer match the program source they describe.
+//!
+//! Each IDL is checked against the `#[program]` module of the same name (or the only
+//! one): its instructions with their arguments and accounts, its account types and the
+//! types they use, member by member. Names are compared in snake_case, so legacy
+//! camelCase IDLs match; types are compared by their IDL form (see [`idl`] and
+//! [`source`]), so a `u32` that became a `u64` or a `[u8; 32]` that became
+//! `[u8; 64]` is drift. Drift is an item or member on one side only, members in another
+//! order, or a different type, writability or signer flag.
+
+pub mod idl;
+pub mod source;
+
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+pub use trr_core::Location;
+use trr_core::walk;
+
+use idl::Idl;
+use source::{Program, Source};
+
+/// What drifted
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Item {
+    Instruction,
+    Account,
+    Type,
+}
+
+impl fmt::Display for Item {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.pad(match self {
+            Item::Instruction => "instruction",
+            Item::Account => "account",
+            Item::Type => "type",
+        })
+    }
+}
+
+/// How it drifted
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum DriftKind {
+    /// In the code only
+    NotInIdl,
+    /// In the IDL only
+    NotInCode,
+    /// The same members in another order
+    Order,
+    /// A member of another type
+    Type,
+    /// An instruction account writable on one side only
+    Writable,
+    /// An instruction account signing on one side only
+    Signer,
+}
+
+impl fmt::Display for DriftKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.pad(match self {
+            DriftKind::NotInIdl => "not in IDL",
+            DriftKind::NotInCode => "not in code",
+            DriftKind::Order => "order",
+            DriftKind::Type => "type",
+            DriftKind::Writable => "writable",
+            DriftKind::Signer => "signer",
+        })
+    }
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct Drift {
+    pub idl: PathBuf,
+    pub program: String,
+    pub item: Item,
+    /// As named in the code, or else in the IDL
+    pub name: String,
+    /// The argument, account, field or variant, when one drifted
+    pub member: Option<String>,
+    pub kind: DriftKind,
+    pub message: String,
+    /// In the code, unless only the IDL has it
+    pub location: Option<Location>,
+    pub idl_location: Location,
+}
+
+/// An IDL that was checked
+#[derive(Clone, Debug, Serialize)]
+pub struct CheckedIdl {
+    pub path: PathBuf,
+    pub name: String,
+    /// The `#[program]` module it was checked against
+    pub program: Option<String>,
+}
+
+#[derive(Debug, Default)]
+pub struct Analysis {
+    pub idls: Vec<CheckedIdl>,
+    pub source: Source,
+    pub drifts: Vec<Drift>,
+    /// Files that could not be read or parsed, and IDLs with no program to check
+    pub errors: Vec<(PathBuf, String)>,
+}
+
+impl Analysis {
+    /// `#[program]` modules no IDL was checked against
+    pub fn programs_without_idl(&self) -> Vec<&Program> {
+        self.source.programs.iter()
+            .filter(|program| !self.idls.iter().any(|idl| idl.program.as_deref() == Some(program.name.as_str())))
+            .collect()
+    }
+}
+
+/// `initializeVault` as `initialize_vault`; snake_case names are unchanged
+pub fn snake_case(name: &str) -> String {
+    let mut snake = String::new();
+    for (index, c) in name.chars().enumerate() {
+        if c.is_uppercase() {
+            if index > 0 && !snake.ends_with('_') {
+                snake.push('_');
+            }
+            snake.extend(c.to_lowercase());
+        } else {
+            snake.push(c);
+        }
+    }
+    snake
+}
+
+/// A member of either side, for comparing
+struct Slot<'a> {
+    name: &'a str,
+    ty: Option<&'a str>,
+    writable: Option<bool>,
+    signer: Option<bool>,
+    location: Option<&'a Location>,
+}
+
+impl Slot<'_> {
+    fn key(&self) -> String {
+        snake_case(self.name)
+    }
+}
+
+impl<'a> From<&'a source::Member> for Slot<'a> {
+    fn from(member: &'a source::Member) -> Self {
+        Slot { name: &member.name, ty: member.ty.as_deref(), writable: None, signer: None, location: Some(&member.location) }
+    }
+}
+
+impl<'a> From<&'a idl::Member> for Slot<'a> {
+    fn from(member: &'a idl::Member) -> Self {
+        Slot { name: &member.name, ty: member.ty.as_deref(), writable: None, signer: None, location: None }
+    }
+}
+
+struct Checker<'a> {
+    idl: &'a Idl,
+    program: &'a Program,
+    source: &'a Source,
+    drifts: Vec<Drift>,
+}
+
+impl Checker<'_> {
+    /// Add a drift of the item `name`
+    fn push(&mut self, (item, name): (Item, &str), member: Option<&str>, kind: DriftKind, message: String, location: Option<&Location>, idl_path: &[&str]) {
+        self.drifts.push(Drift {
+            idl: self.idl.path.clone(),
+            program: self.program.name.clone(),
+            item,
+            name: name.to_string(),
+            member: member.map(str::to_string),
+            kind,
+            message,
+            location: location.cloned(),
+            idl_location: self.idl.location(idl_path),
+        });
+    }
+
+    /// Compare the members of an item on both sides; `idl_path` leads to the item in
+    /// the IDL
+    fn members(&mut self, (item, name): (Item, &str), label: &str, location: &Location, code: &[Slot], idl: &[Slot], idl_path: &[&str]) {
+        let item_name = format!("{} `{}`", item, name);
+        for slot in code.iter().filter(|slot| !idl.iter().any(|other| other.key() == slot.key())) {
+            let message = format!("{}: {} `{}` is not in the IDL", item_name, label, slot.name);
+            self.push((item, name), Some(slot.name), DriftKind::NotInIdl, message, slot.location, idl_path);
+        }
+        for slot in idl.iter().filter(|slot| !code.iter().any(|other| other.key() == slot.key())) {
+            let message = format!("{}: {} `{}` is not in the code", item_name, label, slot.name);
+            let path: Vec<&str> = idl_path.iter().copied().chain([slot.name]).collect();
+            self.push((item, name), Some(slot.name), DriftKind::NotInCode, message, Some(location), &path);
+        }
+
+        let common = |slots: &[Slot], others: &[Slot]| -> Vec<String> {
+            slots.iter().map(Slot::key).filter(|key| others.iter().any(|other| other.key() == *key)).collect()
+        };
+        let (code_order, idl_order) = (common(code, idl), common(idl, code));
+        if code_order != idl_order {
+            let message = format!(
+                "{}: {}s in another order, {} in the code and {} in the IDL",
+                item_name, label, code_order.join(", "), idl_order.join(", ")
+            );
+            self.push((item, name), None, DriftKind::Order, message, Some(location), idl_path);
+        }
+
+        for slot in code {
+            let Some(other) = idl.iter().find(|other| other.key() == slot.key()) else { continue };
+            let path: Vec<&str> = idl_path.iter().copied().chain([other.name]).collect();
+            if let (Some(ty), Some(other_ty)) = (slot.ty, other.ty) {
+                if ty != other_ty {
+                    let message = format!("{}: {} `{}` is {} in the code, {} in the IDL", item_name, label, slot.name, ty, other_ty);
+                    self.push((item, name), Some(slot.name), DriftKind::Type, message, slot.location, &path);
+                }
+            }
+            if let (Some(writable), Some(other_writable)) = (slot.writable, other.writable) {
+                if writable != other_writable {
+                    let (code, idl) = if writable { ("writable", "read-only") } else { ("read-only", "writable") };
+                    let message = format!("{}: {} `{}` is {} in the code, {} in the IDL", item_name, label, slot.name, code, idl);
+                    self.push((item, name), Some(slot.name), DriftKind::Writable, message, slot.location, &path);
+                }
+            }
+            if let (Some(signer), Some(other_signer)) = (slot.signer, other.signer) {
+                if signer != other_signer {
+                    let (code, idl) = if signer { ("a signer", "not") } else { ("not a signer", "a signer") };
+                    let message = format!("{}: {} `{}` is {} in the code, {} in the IDL", item_name, label, slot.name, code, idl);
+                    self.push((item, name), Some(slot.name), DriftKind::Signer, message, slot.location, &path);
+                }
+            }
+        }
+    }
+
+    fn instructions(&mut self) {
+        let (idl, program, source) = (self.idl, self.program, self.source);
+        for instruction in &program.instructions {
+            let Some(other) = idl.instructions.iter().find(|other| snake_case(&other.name) == instruction.name) else {
+                let message = format!("instruction `{}` is not in the IDL", instruction.name);
+                self.push((Item::Instruction, &instruction.name), None, DriftKind::NotInIdl, message, Some(&instruction.location), &["instructions"]);
+                continue;
+            };
+            let path = ["instructions", other.name.as_str()];
+            let code: Vec<Slot> = instruction.args.iter().map(Slot::from).collect();
+            let args: Vec<Slot> = other.args.iter().map(Slot::from).collect();
+            self.members((Item::Instruction, &instruction.name), "argument", &instruction.location, &code, &args, &path);
+
+            let Some(accounts) = instruction.context.as_deref().and_then(|name| source.accounts_struct(name)) else { continue };
+            let code: Vec<Slot> = accounts.fields.iter().map(|field| {
+                // Nested accounts structs have no flags of their own
+                let nested = source.accounts_struct(&field.ty).is_some();
+                Slot {
+                    name: &field.name,
+                    ty: None,
+                    writable: (!nested).then_some(field.writable),
+                    signer: (!nested).then_some(field.signer),
+                    location: Some(&field.location),
+                }
+            }).collect();
+            let idl_accounts: Vec<Slot> = other.accounts.iter().map(|account| Slot {
+                name: &account.name,
+                ty: None,
+                writable: (!account.composite).then_some(account.writable),
+                signer: (!account.composite).then_some(account.signer),
+                location: None,
+            }).collect();
+            self.members((Item::Instruction, &instruction.name), "account", &instruction.location, &code, &idl_accounts, &path);
+        }
+        for other in &idl.instructions {
+            if !program.instructions.iter().any(|instruction| instruction.name == snake_case(&other.name)) {
+                let message = format!("instruction `{}` is not in the code", other.name);
+                self.push((Item::Instruction, &other.name), None, DriftKind::NotInCode, message, None, &["instructions", &other.name]);
+            }
+        }
+    }
+
+    /// Compare a type defined on both sides
+    fn type_def(&mut self, item: Item, code: &source::TypeDef, other: &idl::TypeDef) {
+        let section = if item == Item::Account { "accounts" } else { "types" };
+        let path = [section, other.name.as_str()];
+        let (label, members, other_members) = match (&code.kind, &other.kind) {
+            (source::TypeKind::Struct(fields), idl::TypeKind::Struct(other_fields)) => ("field", fields, other_fields),
+            (source::TypeKind::Enum(variants), idl::TypeKind::Enum(other_variants)) => ("variant", variants, other_variants),
+            (source::TypeKind::Struct(_), idl::TypeKind::Enum(_)) | (source::TypeKind::Enum(_), idl::TypeKind::Struct(_)) => {
+                let (kind, other_kind) = if matches!(code.kind, source::TypeKind::Struct(_)) { ("a struct", "an enum") } else { ("an enum", "a struct") };
+                let message = format!("{} `{}` is {} in the code, {} in the IDL", item, code.name, kind, other_kind);
+                self.push((item, &code.name), None, DriftKind::Type, message, Some(&code.location), &path);
+                return;
+            }
+            _ => return,
+        };
+        let code_slots: Vec<Slot> = members.iter().map(Slot::from).collect();
+        let idl_slots: Vec<Slot> = other_members.iter().map(Slot::from).collect();
+        self.members((item, &code.name), label, &code.location, &code_slots, &idl_slots, &path);
+    }
+
+
+    fn accounts(&mut self) {
+        let (idl, program, source) = (self.idl, self.program, self.source);
+        let accounts = source.types.iter().filter(|def| def.account && def.location.file.starts_with(&program.root));
+        for account in accounts {
+            if !idl.accounts.contains(&account.name) {
+                let message = format!("account `{}` is not in the IDL", account.name);
+                self.push((Item::Account, &account.name), None, DriftKind::NotInIdl, message, Some(&account.location), &["accounts"]);
+            } else if let Some(other) = idl.type_def(&account.name) {
+                self.type_def(Item::Account, account, other);
+            }
+        }
+        for name in &idl.accounts {
+            if source.type_def(name, &program.root).is_none() {
+                let message = format!("account `{}` is not in the code", name);
+                self.push((Item::Account, name), None, DriftKind::NotInCode, message, None, &["accounts", name]);
+            }
+        }
+    }
+
+    fn types(&mut self) {
+        let (idl, program, source) = (self.idl, self.program, self.source);
+        for def in used_types(program, source) {
+            match idl.type_def(&def.name) {
+                Some(other) => self.type_def(Item::Type, def, other),
+                None => {
+                    let message = format!("type `{}` is not in the IDL", def.name);
+                    self.push((Item::Type, &def.name), None, DriftKind::NotInIdl, message, Some(&def.location), &["types"]);
+                }
+            }
+        }
+        for other in idl.types.iter().filter(|other| !idl.accounts.contains(&other.name)) {
+            if source.type_def(&other.name, &program.root).is_none() {
+                let message = format!("type `{}` is not in the code", other.name);
+                self.push((Item::Type, &other.name), None, DriftKind::NotInCode, message, None, &["types", &other.name]);
+            }
+        }
+    }
+}
+
+/// The non-account types `program`'s instructions and accounts use, directly or not
+fn used_types<'a>(program: &Program, source: &'a Source) -> Vec<&'a source::TypeDef> {
+    let mut pending: Vec<&str> = program.instructions.iter()
+        .flat_map(|instruction| &instruction.args)
+        .filter_map(|arg| arg.ty.as_deref())
+        .collect();
+    pending.extend(source.types.iter()
+        .filter(|def| def.account && def.location.file.starts_with(&program.root))
+        .flat_map(|def| match &def.kind {
+            source::TypeKind::Struct(fields) => fields.as_slice(),
+            _ => &[],
+        })
+        .filter_map(|field| field.ty.as_deref()));
+
+    let mut used: Vec<&source::TypeDef> = Vec::new();
+    while let Some(ty) = pending.pop() {
+        for name in ty.split(|c: char| !c.is_alphanumeric() && c != '_') {
+            let Some(def) = source.type_def(name, &program.root).filter(|def| !def.account) else { continue };
+            if used.iter().any(|known| known.name == def.name) {
+                continue;
+            }
+            used.push(def);
+            if let source::TypeKind::Struct(fields) = &def.kind {
+                pending.extend(fields.iter().filter_map(|field| field.ty.as_deref()));
+            }
+        }
+    }
+    used.sort_by(|a, b| a.name.cmp(&b.name));
+    used
+}
+
+/// The drift between `idl` and `program`
+pub fn check(idl: &Idl, program: &Program, source: &Source) -> Vec<Drift> {
+    let mut checker = Checker { idl, program, source, drifts: Vec::new() };
+    checker.instructions();
+    checker.accounts();
+    checker.types();
+    checker.drifts
+}
+
+/// The IDLs under `dir`: JSON files in `target/idl` and outside `target/`,
+/// `node_modules/` and hidden directories that read as an Anchor IDL. Symbolic links to
+/// directories are not followed.
+pub fn find_idls(dir: &Path) -> io::Result<Vec<PathBuf>> {
+    fn visit(dir: &Path, in_target: bool, found: &mut Vec<PathBuf>) -> io::Result<()> {
+        let mut entries: Vec<(PathBuf, fs::FileType)> = fs::read_dir(dir)?
+            .map(|entry| entry.and_then(|entry| Ok((entry.path(), entry.file_type()?))))
+            .collect::<io::Result<_>>()?;
+        entries.sort_by(|(a, _), (b, _)| a.cmp(b));
+        for (path, file_type) in entries {
+            let name = path.file_name().map(|name| name.to_string_lossy().into_owned()).unwrap_or_default();
+            if file_type.is_dir() {
+                if name.starts_with('.') || name == "node_modules" {
+                    continue;
+                }
+                if in_target {
+                    if name == "idl" {
+                        visit(&path, false, found)?;
+                    }
+                } else {
+                    visit(&path, name == "target", found)?;
+                }
+            } else if !in_target && name.ends_with(".json") && Idl::load(&path).is_ok_and(|idl| idl.is_some()) {
+                found.push(path);
+            }
+        }
+        Ok(())
+    }
+    let mut found = Vec::new();
+    visit(dir, false, &mut found)?;
+    Ok(found)
+}
+
+/// Check the IDLs at `idls`, or else those under `dir`, against the program source
+/// under `dir`, leaving out `target/` and test code
+pub fn analyze(dir: &Path, idls: &[PathBuf]) -> io::Result<Analysis> {
+    let files = walk::program_files(dir)?;
+    let mut analysis = Analysis::default();
+    let mut parsed = Vec::new();
+    for file in files {
+        let result = fs::read_to_string(&file)
+            .map_err(|e| e.to_string())
+            .and_then(|source| syn::parse_file(&source).map_err(|e| e.to_string()));
+        match result {
+            Ok(syntax) => parsed.push((file, syntax)),
+            Err(e) => analysis.errors.push((file, e)),
+        }
+    }
+    analysis.source = Source::parse(&parsed);
+
+    let given = !idls.is_empty();
+    let paths = if given { idls.to_vec() } else { find_idls(dir)? };
+    for path in paths {
+        let idl = match Idl::load(&path) {
+            Ok(Some(idl)) => idl,
+            Ok(None) => {
+                analysis.errors.push((path, "not an Anchor IDL".to_string()));
+                continue;
+            }
+            Err(e) => {
+                analysis.errors.push((path, e.to_string()));
+                continue;
+            }
+        };
+        let programs = &analysis.source.programs;
+        let program = programs.iter().find(|program| program.name == snake_case(&idl.name))
+            .or_else(|| if programs.len() == 1 { programs.first() } else { None });
+        match program {
+            Some(program) => analysis.drifts.extend(check(&idl, program, &analysis.source)),
+            None => analysis.errors.push((path.clone(), format!("no #[program] module named {}", idl.name))),
+        }
+        analysis.idls.push(CheckedIdl { path, name: idl.name.clone(), program: program.map(|program| program.name.clone()) });
+    }
+    Ok(analysis)
+}
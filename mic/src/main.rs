@@ -0,0 +1,171 @@
+mod report;
+mod sarif;
+
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::process;
+
+use clap::Parser;
+use serde::Serialize;
+use trr_core::git::Repository;
+use trr_core::output::{self, Format};
+
+use mic::{Analysis, CheckedIdl, Drift};
+
+#[derive(Parser)]
+#[clap(author, version, about = "Mismatched IDL Contents (MIC) Checker")]
+#[clap(after_help = "Examples:
+  mic
+  mic --idl target/idl/vault.json programs/vault
+  mic --idl app/src/idl/vault.json --idl target/idl/vault.json .
+  mic --format sarif . > idl-drift.sarif
+  mic --fail-above 0 .")]
+struct Args {
+    /// Program source to check, and where to look for IDLs (relative to --repo when given)
+    #[clap(value_name = "DIR", default_value = ".")]
+    dir: PathBuf,
+
+    /// Git repository URL to clone, or path of a local checkout
+    #[clap(long, value_name = "REPO_URL_OR_PATH")]
+    repo: Option<String>,
+
+    /// Branch, tag or commit of --repo to analyze
+    #[clap(long, value_name = "REV", requires = "repo")]
+    rev: Option<String>,
+
+    /// Output format
+    #[clap(long, value_enum, default_value = "text")]
+    format: Format,
+
+    /// Check this IDL instead of those found under DIR (repeatable; relative to --repo
+    /// when given)
+    #[clap(long, value_name = "FILE")]
+    idl: Vec<PathBuf>,
+
+    /// Exit with status 1 if there are more than N drifts
+    #[clap(long, value_name = "N")]
+    fail_above: Option<usize>,
+}
+
+/// Structured form of the analysis for `--format json`
+#[derive(Serialize)]
+struct JsonReport<'a> {
+    summary: Summary,
+    idls: &'a [CheckedIdl],
+    /// By IDL: instructions, then accounts, then types
+    drifts: &'a [Drift],
+    errors: Vec<FileError>,
+}
+
+#[derive(Serialize)]
+struct Summary {
+    idls: usize,
+    programs: usize,
+    programs_without_idl: usize,
+    drifts: usize,
+}
+
+#[derive(Serialize)]
+struct FileError {
+    file: PathBuf,
+    error: String,
+}
+
+fn print_text(analysis: &Analysis) {
+    for idl in &analysis.idls {
+        let Some(program) = &idl.program else { continue };
+        println!("\nIDL {} (program {}):", idl.path.display(), program);
+        let drifts: Vec<&Drift> = analysis.drifts.iter().filter(|drift| drift.idl == idl.path).collect();
+        if drifts.is_empty() {
+            println!("  matches the code");
+        }
+        for drift in drifts {
+            match &drift.location {
+                Some(location) => println!("  {} at {} ({})", drift.message, location, drift.idl_location),
+                None => println!("  {} ({})", drift.message, drift.idl_location),
+            }
+        }
+    }
+
+    let without_idl = analysis.programs_without_idl();
+    println!("\nSummary:");
+    println!("IDLs checked: {}", analysis.idls.iter().filter(|idl| idl.program.is_some()).count());
+    println!("Programs: {}", analysis.source.programs.len());
+    if without_idl.is_empty() {
+        println!("Programs without an IDL: 0");
+    } else {
+        let names: Vec<&str> = without_idl.iter().map(|program| program.name.as_str()).collect();
+        println!("Programs without an IDL: {} ({})", names.len(), names.join(", "));
+    }
+    println!("Drifts: {}", analysis.drifts.len());
+}
+
+fn write_json(out: &mut dyn Write, analysis: &Analysis) -> io::Result<()> {
+    let report = JsonReport {
+        summary: Summary {
+            idls: analysis.idls.iter().filter(|idl| idl.program.is_some()).count(),
+            programs: analysis.source.programs.len(),
+            programs_without_idl: analysis.programs_without_idl().len(),
+            drifts: analysis.drifts.len(),
+        },
+        idls: &analysis.idls,
+        drifts: &analysis.drifts,
+        errors: analysis.errors.iter()
+            .map(|(file, error)| FileError { file: file.clone(), error: error.clone() })
+            .collect(),
+    };
+    output::write_json(out, "mic", &report)
+}
+
+fn run(args: &Args) -> Result<(), Box<dyn std::error::Error>> {
+    let repository = args.repo.as_deref().map(|repo| Repository::open(repo, args.rev.as_deref())).transpose()?;
+    let dir = match &repository {
+        Some(repository) => repository.subdir(&args.dir)?,
+        None => args.dir.clone(),
+    };
+    if !dir.exists() {
+        return Err(format!("Path '{}' does not exist", dir.display()).into());
+    }
+    let idls = match &repository {
+        Some(repository) => args.idl.iter().map(|idl| repository.subdir(idl)).collect::<Result<Vec<_>, _>>()?,
+        None => args.idl.clone(),
+    };
+    if let Some(missing) = idls.iter().find(|idl| !idl.is_file()) {
+        return Err(format!("IDL '{}' does not exist", missing.display()).into());
+    }
+    if args.format == Format::Text {
+        println!("Analyzing: {}", dir.display());
+    }
+
+    let analysis = mic::analyze(&dir, &idls)?;
+    for (file, error) in &analysis.errors {
+        eprintln!("Error analyzing {}: {}", file.display(), error);
+    }
+
+    let mut out = io::stdout().lock();
+    match args.format {
+        Format::Text => print_text(&analysis),
+        Format::Json => write_json(&mut out, &analysis)?,
+        Format::Csv => report::write_csv(&mut out, &analysis.drifts)?,
+        Format::Markdown => report::write_markdown(&mut out, &dir, &analysis)?,
+        Format::Sarif => {
+            // Artifact URIs are relative to the repository root, or to where mic runs
+            let base = repository.as_ref().map_or_else(|| PathBuf::from("."), |repository| repository.root().to_path_buf());
+            sarif::write_sarif(&mut out, &analysis.drifts, &base)?;
+            writeln!(out)?;
+        }
+    }
+    drop(out);
+    output::enforce_limit(args.fail_above, analysis.drifts.len(), "IDL drift(s)", analysis.drifts.iter().map(|drift| {
+        format!("{}: {}", drift.idl.display(), drift.message)
+    }));
+    Ok(())
+}
+
+fn main() {
+    let args = Args::parse();
+    if let Err(e) = run(&args) {
+        eprintln!("Error: {}", e);
+        process::exit(1);
+    }
+}
@@ -0,0 +1,73 @@
+//! `--format csv`, one row per drift, and `--format markdown`, a review-ready report with
+//! the IDLs checked and their drift.
+
+use std::io::{self, Write};
+use std::path::Path;
+
+use trr_core::output::{markdown_cell, markdown_code, write_csv_row};
+
+use mic::{Analysis, Drift};
+
+/// Rows are by IDL: instructions, then accounts, then types
+pub fn write_csv(out: &mut dyn Write, drifts: &[Drift]) -> io::Result<()> {
+    write_csv_row(out, &["idl", "program", "item", "name", "member", "kind", "file", "line", "idl_line"])?;
+    for drift in drifts {
+        write_csv_row(out, &[
+            &drift.idl.display().to_string(),
+            &drift.program,
+            &drift.item.to_string(),
+            &drift.name,
+            drift.member.as_deref().unwrap_or(""),
+            &drift.kind.to_string(),
+            &drift.location.as_ref().map_or(String::new(), |location| location.file.display().to_string()),
+            &drift.location.as_ref().map_or(String::new(), |location| location.line.to_string()),
+            &drift.idl_location.line.to_string(),
+        ])?;
+    }
+    Ok(())
+}
+
+pub fn write_markdown(out: &mut dyn Write, target: &Path, analysis: &Analysis) -> io::Result<()> {
+    writeln!(out, "# IDL Consistency Report\n")?;
+    writeln!(out, "Target: {}\n", markdown_code(&target.display().to_string()))?;
+    writeln!(out, "## Summary\n")?;
+    writeln!(out, "| Metric | Value |")?;
+    writeln!(out, "| --- | ---: |")?;
+    writeln!(out, "| IDLs checked | {} |", analysis.idls.iter().filter(|idl| idl.program.is_some()).count())?;
+    writeln!(out, "| Programs | {} |", analysis.source.programs.len())?;
+    writeln!(out, "| Programs without an IDL | {} |", analysis.programs_without_idl().len())?;
+    writeln!(out, "| Drifts | {} |", analysis.drifts.len())?;
+
+    if !analysis.idls.is_empty() {
+        writeln!(out, "\n## IDLs\n")?;
+        writeln!(out, "| IDL | Program | Drifts |")?;
+        writeln!(out, "| --- | --- | ---: |")?;
+        for idl in &analysis.idls {
+            writeln!(
+                out,
+                "| {} | {} | {} |",
+                markdown_code(&idl.path.display().to_string()),
+                idl.program.as_deref().map_or_else(|| "none".to_string(), markdown_code),
+                analysis.drifts.iter().filter(|drift| drift.idl == idl.path).count(),
+            )?;
+        }
+    }
+
+    if analysis.drifts.is_empty() {
+        return Ok(());
+    }
+    writeln!(out, "\n## Drift\n")?;
+    writeln!(out, "| Drift | Kind | Code | IDL |")?;
+    writeln!(out, "| --- | --- | --- | --- |")?;
+    for drift in &analysis.drifts {
+        writeln!(
+            out,
+            "| {} | {} | {} | {} |",
+            markdown_cell(&drift.message),
+            drift.kind,
+            drift.location.as_ref().map_or_else(String::new, |location| markdown_code(&location.to_string())),
+            markdown_code(&drift.idl_location.to_string()),
+        )?;
+    }
+    Ok(())
+}
@@ -0,0 +1,41 @@
+//! SARIF 2.1.0 output: an `idl-drift` warning per drift, located in the code when it has
+//! the item, with the IDL entry as a related location, or else in the IDL.
+
+use std::io::{self, Write};
+use std::path::Path;
+
+use serde_json::json;
+use trr_core::sarif::{location, write_log};
+
+use mic::Drift;
+
+const RULE: &str = "idl-drift";
+
+pub fn write_sarif(out: &mut dyn Write, drifts: &[Drift], base: &Path) -> io::Result<()> {
+    let mut results = Vec::new();
+    for drift in drifts {
+        let mut result = json!({
+            "ruleId": RULE,
+            "ruleIndex": 0,
+            "level": "warning",
+            "message": { "text": format!("{} ({})", drift.message, drift.idl.display()) },
+            "locations": [location(drift.location.as_ref().unwrap_or(&drift.idl_location), base)],
+            "properties": { "program": drift.program, "item": drift.item, "kind": drift.kind },
+        });
+        if drift.location.is_some() {
+            result["relatedLocations"] = json!([location(&drift.idl_location, base)]);
+        }
+        results.push(result);
+    }
+
+    let rules = vec![json!({
+        "id": RULE,
+        "name": "IdlDrift",
+        "shortDescription": { "text": "Anchor IDL out of date with the program source" },
+        "fullDescription": {
+            "text": "An instruction, account or type of the program differs from its IDL: it is missing on one side, its members are in another order, or a member has another type, writability or signer flag. Clients built from a stale IDL serialize instructions and read accounts wrongly.",
+        },
+        "defaultConfiguration": { "level": "warning" },
+    })];
+    write_log(out, "mic", env!("CARGO_PKG_VERSION"), rules, results)
+}
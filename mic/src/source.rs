@@ -0,0 +1,330 @@
+//! Reading what an IDL describes from the program source: the instructions of the
+//! `#[program]` module with their arguments and `Context` accounts struct, the
+//! `#[derive(Accounts)]` structs, and the `#[account]` and `#[event]` structs and the
+//! types deriving `AnchorSerialize`/`AnchorDeserialize` (or Borsh's derives). Types are read into the
+//! form [`crate::idl`] gives IDL types, with array lengths named by a constant resolved
+//! through the `const` items of the tree. `#[cfg(test)]` items are left out.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use proc_macro2::{Span, TokenTree};
+use syn::punctuated::Punctuated;
+use syn::visit::{self, Visit};
+use syn::{Attribute, Expr, Fields, FnArg, GenericArgument, Item, ItemConst, ItemEnum, ItemMod, ItemStruct, Lit, Pat, PathArguments, Type};
+use trr_core::source::{self, is_cfg_test};
+
+use crate::Location;
+
+/// A field, argument or variant
+#[derive(Clone, Debug)]
+pub struct Member {
+    pub name: String,
+    /// `None` for variants, and for types with no IDL form (references, tuples, ...)
+    pub ty: Option<String>,
+    pub location: Location,
+}
+
+#[derive(Clone, Debug)]
+pub struct Instruction {
+    pub name: String,
+    pub location: Location,
+    /// The accounts struct of its `Context<..>`
+    pub context: Option<String>,
+    /// After the context
+    pub args: Vec<Member>,
+}
+
+#[derive(Clone, Debug)]
+pub struct Program {
+    /// The `#[program]` module
+    pub name: String,
+    pub location: Location,
+    /// Directory of the crate's `Cargo.toml`
+    pub root: PathBuf,
+    pub instructions: Vec<Instruction>,
+}
+
+#[derive(Clone, Debug)]
+pub struct AccountsField {
+    pub name: String,
+    pub location: Location,
+    pub writable: bool,
+    pub signer: bool,
+    /// Last segment of the type, inside `Box` and `Option`: `Account`, `Signer`, or the
+    /// name of a nested accounts struct
+    pub ty: String,
+}
+
+#[derive(Clone, Debug)]
+pub struct AccountsStruct {
+    pub name: String,
+    pub location: Location,
+    pub fields: Vec<AccountsField>,
+}
+
+#[derive(Clone, Debug)]
+pub enum TypeKind {
+    Struct(Vec<Member>),
+    Enum(Vec<Member>),
+    /// Tuple and unit structs
+    Other,
+}
+
+#[derive(Clone, Debug)]
+pub struct TypeDef {
+    pub name: String,
+    pub location: Location,
+    /// An `#[account]` struct
+    pub account: bool,
+    pub kind: TypeKind,
+}
+
+#[derive(Debug, Default)]
+pub struct Source {
+    pub programs: Vec<Program>,
+    pub accounts_structs: Vec<AccountsStruct>,
+    pub types: Vec<TypeDef>,
+}
+
+impl Source {
+    pub fn accounts_struct(&self, name: &str) -> Option<&AccountsStruct> {
+        self.accounts_structs.iter().find(|accounts| accounts.name == name)
+    }
+
+    /// The type named `name`, preferably in the crate at `root`
+    pub fn type_def(&self, name: &str, root: &Path) -> Option<&TypeDef> {
+        let mut named = self.types.iter().filter(|def| def.name == name);
+        named.clone().find(|def| def.location.file.starts_with(root)).or_else(|| named.next())
+    }
+
+    /// Add the items of the parsed `files`, resolving array lengths through the `const`
+    /// items of all of them
+    pub fn parse(files: &[(PathBuf, syn::File)]) -> Source {
+        let mut consts = Consts::default();
+        for (_, file) in files {
+            consts.visit_file(file);
+        }
+        let mut source = Source::default();
+        for (path, file) in files {
+            let mut visitor = FileVisitor { path, consts: &consts.0, source: &mut source };
+            visitor.visit_file(file);
+        }
+        source
+    }
+}
+
+fn derives(attrs: &[Attribute], names: &[&str]) -> bool {
+    attrs.iter().filter(|attr| attr.path().is_ident("derive")).any(|attr| {
+        attr.parse_args_with(Punctuated::<syn::Path, syn::Token![,]>::parse_terminated)
+            .is_ok_and(|paths| paths.iter().any(|path| path.segments.last().is_some_and(|segment| names.iter().any(|name| segment.ident == name))))
+    })
+}
+
+/// The first identifier of each comma-separated constraint of the `#[account(..)]`
+/// attributes: `mut`, `init`, `seeds`, `associated_token`, ...
+fn constraints(attrs: &[Attribute]) -> Vec<String> {
+    let mut constraints = Vec::new();
+    for attr in attrs.iter().filter(|attr| attr.path().is_ident("account")) {
+        let Ok(list) = attr.meta.require_list() else { continue };
+        let mut first = true;
+        for token in list.tokens.clone() {
+            match token {
+                TokenTree::Punct(punct) if punct.as_char() == ',' => first = true,
+                TokenTree::Ident(ident) if first => {
+                    constraints.push(ident.to_string());
+                    first = false;
+                }
+                _ => first = false,
+            }
+        }
+    }
+    constraints
+}
+
+/// Integer `const` items, for array lengths
+#[derive(Default)]
+struct Consts(HashMap<String, u64>);
+
+impl<'ast> Visit<'ast> for Consts {
+    fn visit_item_const(&mut self, item: &'ast ItemConst) {
+        if let Expr::Lit(syn::ExprLit { lit: Lit::Int(int), .. }) = &*item.expr {
+            if let Ok(value) = int.base10_parse() {
+                self.0.insert(item.ident.to_string(), value);
+            }
+        }
+    }
+}
+
+/// The type arguments of the last segment of `path`
+fn type_args(path: &syn::Path) -> Vec<&Type> {
+    let Some(PathArguments::AngleBracketed(args)) = path.segments.last().map(|segment| &segment.arguments) else {
+        return Vec::new();
+    };
+    args.args.iter().filter_map(|arg| match arg {
+        GenericArgument::Type(ty) => Some(ty),
+        _ => None,
+    }).collect()
+}
+
+/// `ty` in the common form with the IDL, `None` if it has none
+pub fn type_name(ty: &Type, consts: &HashMap<String, u64>) -> Option<String> {
+    match ty {
+        Type::Path(path) => {
+            let segment = path.path.segments.last()?;
+            let name = segment.ident.to_string();
+            let inner = || type_args(&path.path).first().and_then(|inner| type_name(inner, consts));
+            Some(match name.as_str() {
+                "String" => "string".to_string(),
+                "Pubkey" => "pubkey".to_string(),
+                "Vec" => {
+                    let inner = inner()?;
+                    if inner == "u8" { "bytes".to_string() } else { format!("vec<{}>", inner) }
+                }
+                "Option" | "COption" => format!("option<{}>", inner()?),
+                "Box" => inner()?,
+                _ => name,
+            })
+        }
+        Type::Array(array) => {
+            let length = match &array.len {
+                Expr::Lit(syn::ExprLit { lit: Lit::Int(int), .. }) => int.base10_parse().ok()?,
+                Expr::Path(path) => *consts.get(&path.path.segments.last()?.ident.to_string())?,
+                _ => return None,
+            };
+            Some(format!("[{}; {}]", type_name(&array.elem, consts)?, length))
+        }
+        Type::Paren(paren) => type_name(&paren.elem, consts),
+        Type::Group(group) => type_name(&group.elem, consts),
+        _ => None,
+    }
+}
+
+/// Last segment of an accounts field's type, inside `Box` and `Option`
+fn accounts_type(ty: &Type) -> String {
+    let Type::Path(path) = ty else { return String::new() };
+    let Some(segment) = path.path.segments.last() else { return String::new() };
+    match segment.ident.to_string().as_str() {
+        "Box" | "Option" => type_args(&path.path).first().map_or_else(String::new, |inner| accounts_type(inner)),
+        name => name.to_string(),
+    }
+}
+
+struct FileVisitor<'a> {
+    path: &'a Path,
+    consts: &'a HashMap<String, u64>,
+    source: &'a mut Source,
+}
+
+impl FileVisitor<'_> {
+    fn location(&self, span: Span) -> Location {
+        source::location(self.path, span)
+    }
+
+    fn fields(&self, fields: &syn::FieldsNamed) -> Vec<Member> {
+        fields.named.iter().filter_map(|field| {
+            let ident = field.ident.as_ref()?;
+            Some(Member { name: ident.to_string(), ty: type_name(&field.ty, self.consts), location: self.location(ident.span()) })
+        }).collect()
+    }
+
+    fn add_program(&mut self, item: &ItemMod) {
+        let Some((_, items)) = &item.content else { return };
+        let instructions = items.iter().filter_map(|item| match item {
+            Item::Fn(function) if matches!(function.vis, syn::Visibility::Public(_)) && !is_cfg_test(&function.attrs) => Some(function),
+            _ => None,
+        }).map(|function| {
+            let mut inputs = function.sig.inputs.iter().filter_map(|input| match input {
+                FnArg::Typed(typed) => Some(typed),
+                FnArg::Receiver(_) => None,
+            });
+            let context = inputs.next().and_then(|ctx| match &*ctx.ty {
+                Type::Path(path) => type_args(&path.path).first().map(|accounts| accounts_type(accounts)),
+                _ => None,
+            });
+            let args = inputs.filter_map(|arg| match &*arg.pat {
+                Pat::Ident(ident) => Some(Member {
+                    name: ident.ident.to_string(),
+                    ty: type_name(&arg.ty, self.consts),
+                    location: self.location(ident.ident.span()),
+                }),
+                _ => None,
+            }).collect();
+            Instruction { name: function.sig.ident.to_string(), location: self.location(function.sig.ident.span()), context, args }
+        }).collect();
+
+        let root = self.path.ancestors().skip(1)
+            .find(|dir| dir.join("Cargo.toml").is_file())
+            .unwrap_or_else(|| self.path.parent().unwrap_or(self.path))
+            .to_path_buf();
+        self.source.programs.push(Program { name: item.ident.to_string(), location: self.location(item.ident.span()), root, instructions });
+    }
+
+    fn add_accounts_struct(&mut self, item: &ItemStruct) {
+        let Fields::Named(fields) = &item.fields else { return };
+        let fields = fields.named.iter().filter_map(|field| {
+            let ident = field.ident.as_ref()?;
+            let constraints = constraints(&field.attrs);
+            let has = |name: &str| constraints.iter().any(|constraint| constraint == name);
+            let ty = accounts_type(&field.ty);
+            let init = has("init") || has("init_if_needed");
+            Some(AccountsField {
+                name: ident.to_string(),
+                location: self.location(ident.span()),
+                writable: has("mut") || init || has("zero"),
+                // `init` without seeds creates a keypair account, which signs
+                signer: ty == "Signer" || has("signer") || (init && !has("seeds") && !has("associated_token")),
+                ty,
+            })
+        }).collect();
+        self.source.accounts_structs.push(AccountsStruct { name: item.ident.to_string(), location: self.location(item.ident.span()), fields });
+    }
+}
+
+const SERIALIZE_DERIVES: [&str; 4] = ["AnchorSerialize", "AnchorDeserialize", "BorshSerialize", "BorshDeserialize"];
+
+impl<'ast> Visit<'ast> for FileVisitor<'_> {
+    fn visit_item_mod(&mut self, item: &'ast ItemMod) {
+        if is_cfg_test(&item.attrs) {
+            return;
+        }
+        if item.attrs.iter().any(|attr| attr.path().is_ident("program")) {
+            self.add_program(item);
+        }
+        visit::visit_item_mod(self, item);
+    }
+
+    fn visit_item_struct(&mut self, item: &'ast ItemStruct) {
+        if is_cfg_test(&item.attrs) {
+            return;
+        }
+        if derives(&item.attrs, &["Accounts"]) {
+            self.add_accounts_struct(item);
+            return;
+        }
+        let account = item.attrs.iter().any(|attr| attr.path().is_ident("account"));
+        let event = item.attrs.iter().any(|attr| attr.path().is_ident("event"));
+        if account || event || derives(&item.attrs, &SERIALIZE_DERIVES) {
+            let kind = match &item.fields {
+                Fields::Named(fields) => TypeKind::Struct(self.fields(fields)),
+                _ => TypeKind::Other,
+            };
+            self.source.types.push(TypeDef { name: item.ident.to_string(), location: self.location(item.ident.span()), account, kind });
+        }
+    }
+
+    fn visit_item_enum(&mut self, item: &'ast ItemEnum) {
+        if is_cfg_test(&item.attrs) || !derives(&item.attrs, &SERIALIZE_DERIVES) {
+            return;
+        }
+        let variants = item.variants.iter()
+            .map(|variant| Member { name: variant.ident.to_string(), ty: None, location: self.location(variant.ident.span()) })
+            .collect();
+        self.source.types.push(TypeDef {
+            name: item.ident.to_string(),
+            location: self.location(item.ident.span()),
+            account: false,
+            kind: TypeKind::Enum(variants),
+        });
+    }
+}
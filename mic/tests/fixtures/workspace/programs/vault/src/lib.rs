@@ -0,0 +1,100 @@
+use anchor_lang::prelude::*;
+
+declare_id!("Vau1t11111111111111111111111111111111111111");
+
+pub const MAX_NAME: usize = 32;
+
+#[program]
+pub mod vault {
+    use super::*;
+
+    pub fn initialize(ctx: Context<Initialize>, name: [u8; MAX_NAME], fee_bps: u16) -> Result<()> {
+        let vault = &mut ctx.accounts.vault;
+        vault.authority = ctx.accounts.authority.key();
+        vault.name = name;
+        vault.fee_bps = fee_bps;
+        Ok(())
+    }
+
+    pub fn deposit(ctx: Context<Deposit>, amount: u64, memo: Option<String>) -> Result<()> {
+        let _ = memo;
+        ctx.accounts.vault.total = ctx.accounts.vault.total.checked_add(amount).unwrap();
+        Ok(())
+    }
+
+    pub fn withdraw(ctx: Context<Withdraw>, amount: u64) -> Result<()> {
+        ctx.accounts.vault.total = ctx.accounts.vault.total.checked_sub(amount).unwrap();
+        Ok(())
+    }
+
+    pub fn set_config(ctx: Context<SetConfig>, config: Config) -> Result<()> {
+        ctx.accounts.vault.config = config;
+        Ok(())
+    }
+}
+
+#[derive(Accounts)]
+pub struct Initialize<'info> {
+    #[account(init, payer = authority, space = 8 + Vault::INIT_SPACE)]
+    pub vault: Account<'info, Vault>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct Deposit<'info> {
+    #[account(mut, has_one = authority)]
+    pub vault: Account<'info, Vault>,
+    pub authority: Signer<'info>,
+    #[account(mut)]
+    pub depositor: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct Withdraw<'info> {
+    #[account(mut, has_one = authority)]
+    pub vault: Account<'info, Vault>,
+    pub authority: Signer<'info>,
+    #[account(mut)]
+    pub recipient: SystemAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetConfig<'info> {
+    #[account(mut, has_one = authority)]
+    pub vault: Account<'info, Vault>,
+    pub authority: Signer<'info>,
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct Vault {
+    pub authority: Pubkey,
+    pub name: [u8; MAX_NAME],
+    pub fee_bps: u16,
+    pub total: u64,
+    pub config: Config,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, InitSpace)]
+pub struct Config {
+    pub mode: Mode,
+    #[max_len(4)]
+    pub limits: Vec<u64>,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, InitSpace)]
+pub enum Mode {
+    Open,
+    Paused,
+    Closed,
+}
+
+#[cfg(test)]
+mod tests {
+    #[derive(anchor_lang::AnchorSerialize)]
+    pub struct Fixture {
+        pub seed: u8,
+    }
+}
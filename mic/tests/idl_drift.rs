@@ -0,0 +1,104 @@
+// Checks tests/fixtures/workspace: the vault program, its current IDL in idls/ (which
+// matches) and a stale legacy IDL in app/src/idl.
+
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use mic::{DriftKind, Item};
+
+fn fixture() -> PathBuf {
+    Path::new(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/workspace")).to_path_buf()
+}
+
+#[test]
+fn finds_idls() {
+    let analysis = mic::analyze(&fixture(), &[]).unwrap();
+    let idls: Vec<_> = analysis.idls.iter()
+        .map(|idl| (idl.path.strip_prefix(fixture()).unwrap().to_path_buf(), idl.program.as_deref()))
+        .collect();
+    assert_eq!(idls, [
+        (PathBuf::from("app/src/idl/vault.json"), Some("vault")),
+        (PathBuf::from("idls/vault.json"), Some("vault")),
+    ]);
+    assert!(analysis.drifts.iter().all(|drift| drift.idl.ends_with("app/src/idl/vault.json")));
+    assert!(analysis.programs_without_idl().is_empty());
+    assert!(analysis.errors.is_empty(), "{:?}", analysis.errors);
+}
+
+#[cfg(unix)]
+#[test]
+fn symlinked_directories_are_not_followed() {
+    let dir = tempfile::TempDir::new().unwrap();
+    std::fs::create_dir(dir.path().join("idls")).unwrap();
+    std::fs::copy(fixture().join("idls/vault.json"), dir.path().join("idls/vault.json")).unwrap();
+    std::os::unix::fs::symlink("..", dir.path().join("idls/loop")).unwrap();
+    assert_eq!(mic::find_idls(dir.path()).unwrap(), [dir.path().join("idls/vault.json")]);
+}
+
+#[test]
+fn drifts() {
+    let idl = fixture().join("app/src/idl/vault.json");
+    let analysis = mic::analyze(&fixture(), &[idl]).unwrap();
+    let drifts: Vec<_> = analysis.drifts.iter()
+        .map(|drift| (drift.item, drift.name.as_str(), drift.member.as_deref(), drift.kind))
+        .collect();
+    assert_eq!(drifts, [
+        (Item::Instruction, "initialize", None, DriftKind::Order),
+        (Item::Instruction, "deposit", Some("memo"), DriftKind::NotInIdl),
+        (Item::Instruction, "withdraw", Some("amount"), DriftKind::Type),
+        (Item::Instruction, "withdraw", Some("recipient"), DriftKind::Writable),
+        (Item::Instruction, "set_config", None, DriftKind::NotInIdl),
+        (Item::Instruction, "closeVault", None, DriftKind::NotInCode),
+        (Item::Account, "Vault", Some("total"), DriftKind::NotInIdl),
+        (Item::Account, "Vault", Some("name"), DriftKind::Type),
+        (Item::Type, "Mode", Some("Paused"), DriftKind::NotInIdl),
+        (Item::Type, "OldState", None, DriftKind::NotInCode),
+    ]);
+    // The array length comes from the MAX_NAME constant
+    assert_eq!(analysis.drifts[7].message, "account `Vault`: field `name` is [u8; 32] in the code, [u8; 16] in the IDL");
+    assert_eq!(analysis.drifts[7].location.as_ref().unwrap().line, 74);
+    assert_eq!(analysis.drifts[7].idl_location.line, 55);
+    assert!(analysis.drifts[5].location.is_none());
+    assert_eq!(analysis.drifts[5].idl_location.line, 40);
+}
+
+#[test]
+fn names_and_types() {
+    assert_eq!(mic::snake_case("initializeVault"), "initialize_vault");
+    assert_eq!(mic::snake_case("amountA"), "amount_a");
+    assert_eq!(mic::snake_case("fee_bps"), "fee_bps");
+
+    let idl_type = |json: &str| mic::idl::type_name(&serde_json::from_str(json).unwrap());
+    assert_eq!(idl_type(r#""publicKey""#).as_deref(), Some("pubkey"));
+    assert_eq!(idl_type(r#"{ "vec": "u8" }"#).as_deref(), Some("bytes"));
+    assert_eq!(idl_type(r#"{ "option": { "defined": { "name": "Config" } } }"#).as_deref(), Some("option<Config>"));
+
+    let consts = Default::default();
+    let code_type = |source: &str| mic::source::type_name(&syn::parse_str(source).unwrap(), &consts);
+    assert_eq!(code_type("Vec<u8>").as_deref(), Some("bytes"));
+    assert_eq!(code_type("Box<Option<state::Config>>").as_deref(), Some("option<Config>"));
+    assert_eq!(code_type("[Pubkey; 4]").as_deref(), Some("[pubkey; 4]"));
+    assert_eq!(code_type("&str"), None);
+}
+
+#[test]
+fn fail_above_and_sarif() {
+    let run = |args: &[&str]| Command::new(env!("CARGO_BIN_EXE_mic"))
+        .args(args)
+        .arg(fixture())
+        .output()
+        .expect("failed to run mic");
+
+    assert!(run(&["--fail-above", "10"]).status.success());
+
+    let output = run(&["--fail-above", "0", "--format", "sarif"]);
+    assert_eq!(output.status.code(), Some(1));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("10 IDL drift(s), more than 0"), "stderr: {}", stderr);
+    let log: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let results = log["runs"][0]["results"].as_array().unwrap();
+    assert_eq!(results.len(), 10);
+    assert_eq!(results[2]["locations"][0]["physicalLocation"]["region"]["startLine"], 25);
+    assert_eq!(results[2]["relatedLocations"][0]["physicalLocation"]["region"]["startLine"], 36);
+    assert!(results[5].get("relatedLocations").is_none());
+}
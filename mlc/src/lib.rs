@@ -0,0 +1,248 @@
+//! Logging cost and leaks: the `msg!` and `sol_log*` calls of Solana programs, what
+//! each costs in compute units, and the logs that may print secrets or whole data
+//! structures, profiled per instruction handler.
+//!
+//! Log sites are found statically (see [`logs`]). Handlers and the functions they call
+//! come from [`mcd`], so a handler's profile is every log it reaches. Estimates follow
+//! the runtime's charges for the log syscalls, plus a fixed estimate for formatting each
+//! argument of `msg!`; they rank logs to trim, they do not predict a transaction.
+
+pub mod logs;
+
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use mcd::FunctionId;
+use serde::Serialize;
+pub use trr_core::Location;
+
+/// Compute units of one log syscall; a longer message costs a unit per byte
+pub const LOG_UNITS: u64 = 100;
+
+/// Compute units of `sol_log_params` and `sol_log_slice`, which log a line per account
+/// or per byte: their floor, since the length is not known
+pub const BULK_LOG_UNITS: u64 = 1_000;
+
+/// How an argument of a log is printed
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ArgumentKind {
+    /// With `{}`, or given as is to a log syscall
+    Value,
+    /// With `{:?}`
+    Debug,
+    /// A key, printed in base58: `Pubkey::log` prints it for a fraction of the cost
+    Key,
+    /// A whole account, context, instruction data or byte buffer, with `{:?}` or
+    /// `sol_log_data`
+    Data,
+}
+
+impl ArgumentKind {
+    /// Compute units of formatting the argument in `msg!`
+    pub fn units(self) -> u64 {
+        match self {
+            ArgumentKind::Value => 200,
+            ArgumentKind::Debug => 1_000,
+            ArgumentKind::Key => 2_000,
+            ArgumentKind::Data => 5_000,
+        }
+    }
+}
+
+impl fmt::Display for ArgumentKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.pad(match self {
+            ArgumentKind::Value => "value",
+            ArgumentKind::Debug => "debug",
+            ArgumentKind::Key => "key",
+            ArgumentKind::Data => "data",
+        })
+    }
+}
+
+/// What a log may expose
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Leak {
+    /// An argument named like a secret (see [`logs::SECRET_NAMES`])
+    Secret,
+    /// A whole data structure, or `sol_log_params`/`sol_log_slice`
+    LargeData,
+}
+
+impl fmt::Display for Leak {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.pad(match self {
+            Leak::Secret => "secret",
+            Leak::LargeData => "large data",
+        })
+    }
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct Argument {
+    /// The argument's source, or the name captured by the format string
+    pub code: String,
+    pub kind: ArgumentKind,
+    /// Named like a secret
+    pub secret: bool,
+}
+
+/// A log call in a function body
+#[derive(Clone, Debug, Serialize)]
+pub struct LogSite {
+    /// `msg!`, `sol_log_data`, `log` (`Pubkey::log`), ...
+    pub call: String,
+    /// The format string of `msg!`, or a literal message
+    pub message: Option<String>,
+    pub arguments: Vec<Argument>,
+    /// Estimated compute units of one execution
+    pub units: u64,
+    /// How many loops of its function it is in, up to [`mcu::MAX_LOOP_NESTING`]
+    pub loops: u32,
+    pub leaks: Vec<Leak>,
+    pub location: Location,
+}
+
+/// A log site of a program, with how many times it counts
+#[derive(Clone, Debug, Serialize)]
+pub struct Log {
+    #[serde(flatten)]
+    pub site: LogSite,
+    pub program: String,
+    /// Path of the function it is in
+    pub function: String,
+    /// [`mcu::LOOP_ITERATIONS`] for each loop around it
+    pub multiplier: u64,
+    /// Its units times the multiplier
+    pub total: u64,
+}
+
+/// The logging of one handler
+#[derive(Clone, Debug, Serialize)]
+pub struct HandlerProfile {
+    pub program: String,
+    pub handler: String,
+    pub location: Location,
+    /// Estimated compute units of its logs
+    pub units: u64,
+    /// Its logs that may leak
+    pub leaks: usize,
+    /// The logs it reaches, most expensive first
+    pub logs: Vec<Log>,
+}
+
+/// The programs under a directory, with the log sites of their functions
+#[derive(Debug, Default)]
+pub struct Analysis {
+    /// Programs, handlers and calls
+    pub programs: mcd::Analysis,
+    /// The log sites of each function, by the location of its name
+    pub sites: HashMap<Location, Vec<LogSite>>,
+    /// Files that could not be read or parsed
+    pub errors: Vec<(PathBuf, String)>,
+}
+
+impl Analysis {
+    /// The logs of a function
+    fn function_logs(&self, (program, function): FunctionId) -> Vec<Log> {
+        let program = &self.programs.programs[program];
+        let function = &program.functions[function];
+        let Some(sites) = self.sites.get(&function.location) else { return Vec::new() };
+        sites.iter()
+            .map(|site| {
+                let multiplier = mcu::LOOP_ITERATIONS.pow(site.loops);
+                Log {
+                    total: site.units * multiplier,
+                    site: site.clone(),
+                    program: program.name.clone(),
+                    function: function.path.join("::"),
+                    multiplier,
+                }
+            })
+            .collect()
+    }
+
+    /// The functions a handler reaches, itself included
+    fn reachable(&self, start: FunctionId) -> HashSet<FunctionId> {
+        let mut reached = HashSet::from([start]);
+        let mut stack = vec![start];
+        while let Some(id) = stack.pop() {
+            for call in &self.programs.programs[id.0].functions[id.1].calls {
+                for callee in self.programs.callees(id.0, call) {
+                    if reached.insert(callee) {
+                        stack.push(callee);
+                    }
+                }
+            }
+        }
+        reached
+    }
+
+    /// Every log of every program, by file and line
+    pub fn logs(&self) -> Vec<Log> {
+        let mut logs: Vec<Log> = self.programs.programs.iter()
+            .enumerate()
+            .flat_map(|(index, program)| (0..program.functions.len()).map(move |function| (index, function)))
+            .flat_map(|id| self.function_logs(id))
+            .collect();
+        logs.sort_by(|a, b| a.site.location.file.cmp(&b.site.location.file)
+            .then_with(|| a.site.location.line.cmp(&b.site.location.line))
+            .then_with(|| a.site.location.column.cmp(&b.site.location.column)));
+        logs
+    }
+
+    /// Every handler with the logs it reaches, most expensive first
+    pub fn profiles(&self) -> Vec<HandlerProfile> {
+        let mut profiles: Vec<HandlerProfile> = self.programs.programs.iter()
+            .enumerate()
+            .flat_map(|(index, program)| program.handlers.iter().map(move |handler| (index, program, handler)))
+            .map(|(index, program, handler)| {
+                let mut logs: Vec<Log> = self.reachable((index, handler.function)).into_iter()
+                    .flat_map(|id| self.function_logs(id))
+                    .collect();
+                logs.sort_by(|a, b| b.total.cmp(&a.total)
+                    .then_with(|| a.site.location.file.cmp(&b.site.location.file))
+                    .then_with(|| a.site.location.line.cmp(&b.site.location.line)));
+                HandlerProfile {
+                    program: program.name.clone(),
+                    handler: handler.name.clone(),
+                    location: handler.location.clone(),
+                    units: logs.iter().map(|log| log.total).sum(),
+                    leaks: logs.iter().filter(|log| !log.site.leaks.is_empty()).count(),
+                    logs,
+                }
+            })
+            .collect();
+        profiles.sort_by(|a, b| b.units.cmp(&a.units)
+            .then_with(|| a.program.cmp(&b.program))
+            .then_with(|| a.handler.cmp(&b.handler)));
+        profiles
+    }
+}
+
+/// Analyze every Rust file under `dir` (or `dir` itself, if it is a file), leaving out
+/// `target/` and test code, as [`mcd::analyze`] does
+pub fn analyze(dir: &Path) -> io::Result<Analysis> {
+    let programs = mcd::analyze(dir)?;
+    let files: BTreeSet<&Path> = programs.programs.iter()
+        .flat_map(|program| &program.functions)
+        .map(|function| function.location.file.as_path())
+        .collect();
+
+    let mut sites = HashMap::new();
+    let mut errors = programs.errors.clone();
+    for file in files {
+        let result = fs::read_to_string(file)
+            .map_err(|e| e.to_string())
+            .and_then(|source| logs::parse_file(file, &source, &mut sites).map_err(|e| e.to_string()));
+        if let Err(e) = result {
+            errors.push((file.to_path_buf(), e));
+        }
+    }
+    Ok(Analysis { programs, sites, errors })
+}
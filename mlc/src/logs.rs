@@ -0,0 +1,405 @@
+//! Finding the log calls of each function, with their estimated compute units:
+//!
+//! - `msg!` with only a message: one log syscall, [`LOG_UNITS`] or a unit per byte of
+//!   a longer literal. It is not formatted, so `msg!("{x}")` prints the braces.
+//! - `msg!` with format arguments: the same, plus [`ArgumentKind::units`] for each
+//!   placeholder, positional, named or captured
+//! - `sol_log`, `sol_log_64`, `sol_log_pubkey`, `sol_log_compute_units` and
+//!   `Pubkey::log` (`key.log()`): [`LOG_UNITS`]
+//! - `sol_log_data`: [`LOG_UNITS`], and as much again for each field
+//! - `sol_log_params` and `sol_log_slice`: [`BULK_LOG_UNITS`], always flagged as large
+//!   data
+//!
+//! Arguments named like a secret (see [`SECRET_NAMES`]) are flagged wherever they are
+//! logged. Keys (`.key()`, `authority_pubkey`) are printed in base58, which is costly.
+//! An account (`ctx.accounts.vault`), the context, or anything named like data or an
+//! account (see [`DATA_NAMES`]) printed with `{:?}` or given to `sol_log_data` is
+//! flagged as large data. Logs in the body of a `for`, `while` or `loop`, or in a
+//! closure given to an iterator adapter, count [`mcu::LOOP_ITERATIONS`] times per loop.
+
+use std::collections::HashMap;
+use std::path::Path;
+
+use proc_macro2::Span;
+use quote::ToTokens;
+use syn::punctuated::Punctuated;
+use syn::spanned::Spanned;
+use syn::visit::{self, Visit};
+use syn::{Expr, ExprCall, ExprMethodCall, ImplItemFn, ItemFn, ItemImpl, ItemMod, Lit, TraitItemFn};
+use trr_core::source::{is_cfg_test, location};
+
+use crate::{Argument, ArgumentKind, Leak, Location, LogSite, BULK_LOG_UNITS, LOG_UNITS};
+
+/// Words that make an argument a secret
+pub const SECRET_NAMES: &[&str] = &["secret", "private", "mnemonic", "password", "passphrase", "keypair", "seed_phrase"];
+
+/// Words that make an argument printed whole a large data structure
+pub const DATA_NAMES: &[&str] = &["account", "data", "state", "buffer", "bytes", "instruction", "ctx", "params"];
+
+/// Log syscalls taking a fixed charge, whatever their arguments
+const LOG_FUNCTIONS: &[&str] = &["sol_log", "sol_log_64", "sol_log_pubkey", "sol_log_compute_units"];
+
+/// Methods that hand back the value they are called on, or a view of it
+const TRANSPARENT_METHODS: &[&str] = &[
+    "borrow", "borrow_mut", "as_ref", "as_slice", "as_bytes", "clone", "to_vec", "to_owned", "to_string",
+    "to_account_info", "deref", "unwrap", "iter",
+];
+
+/// Iterator adapters whose closure runs once per item
+const ITERATOR_METHODS: &[&str] = &[
+    "for_each", "try_for_each", "map", "filter", "filter_map", "flat_map", "fold", "try_fold", "any", "all",
+    "find", "find_map", "position", "scan", "take_while", "skip_while", "inspect", "map_while",
+];
+
+/// Parse `source`, the contents of `path`, adding the log sites of its functions to
+/// `sites`, keyed by the location of the function's name
+pub fn parse_file(path: &Path, source: &str, sites: &mut HashMap<Location, Vec<LogSite>>) -> syn::Result<()> {
+    let file = syn::parse_file(source)?;
+    FileVisitor { path, source, sites }.visit_file(&file);
+    Ok(())
+}
+
+struct FileVisitor<'a> {
+    path: &'a Path,
+    source: &'a str,
+    sites: &'a mut HashMap<Location, Vec<LogSite>>,
+}
+
+impl FileVisitor<'_> {
+    fn add_function(&mut self, ident: &syn::Ident, block: &syn::Block) {
+        let mut body = BodyVisitor { path: self.path, source: self.source, loops: 0, sites: Vec::new() };
+        body.visit_block(block);
+        if !body.sites.is_empty() {
+            self.sites.insert(location(self.path, ident.span()), body.sites);
+        }
+    }
+}
+
+impl<'ast> Visit<'ast> for FileVisitor<'_> {
+    fn visit_item_mod(&mut self, item: &'ast ItemMod) {
+        if !is_cfg_test(&item.attrs) {
+            visit::visit_item_mod(self, item);
+        }
+    }
+
+    fn visit_item_fn(&mut self, item: &'ast ItemFn) {
+        if !is_cfg_test(&item.attrs) {
+            self.add_function(&item.sig.ident, &item.block);
+        }
+    }
+
+    fn visit_item_impl(&mut self, item: &'ast ItemImpl) {
+        if !is_cfg_test(&item.attrs) {
+            visit::visit_item_impl(self, item);
+        }
+    }
+
+    fn visit_impl_item_fn(&mut self, item: &'ast ImplItemFn) {
+        if !is_cfg_test(&item.attrs) {
+            self.add_function(&item.sig.ident, &item.block);
+        }
+    }
+
+    fn visit_trait_item_fn(&mut self, item: &'ast TraitItemFn) {
+        if let Some(block) = &item.default {
+            self.add_function(&item.sig.ident, block);
+        }
+    }
+}
+
+/// The identifiers of an expression, fields and methods included
+#[derive(Default)]
+struct Identifiers(Vec<String>);
+
+impl<'ast> Visit<'ast> for Identifiers {
+    fn visit_ident(&mut self, ident: &'ast syn::Ident) {
+        self.0.push(ident.to_string());
+    }
+}
+
+fn is_secret_name(name: &str) -> bool {
+    let name = name.to_lowercase();
+    SECRET_NAMES.iter().any(|secret| name.contains(secret))
+}
+
+fn is_key_name(name: &str) -> bool {
+    let name = name.to_lowercase();
+    name == "key" || name.ends_with("_key") || name.ends_with("pubkey")
+}
+
+fn is_data_name(name: &str) -> bool {
+    let name = name.to_lowercase();
+    DATA_NAMES.iter().any(|data| name.contains(data))
+}
+
+/// The expression without references, parentheses, `?` and the methods of
+/// [`TRANSPARENT_METHODS`]
+fn strip(expr: &Expr) -> &Expr {
+    match expr {
+        Expr::Reference(inner) => strip(&inner.expr),
+        Expr::Paren(inner) => strip(&inner.expr),
+        Expr::Unary(inner) => strip(&inner.expr),
+        Expr::Try(inner) => strip(&inner.expr),
+        Expr::MethodCall(call) if TRANSPARENT_METHODS.contains(&call.method.to_string().as_str()) => strip(&call.receiver),
+        expr => expr,
+    }
+}
+
+/// The name an expression ends with: `vault` for `ctx.accounts.vault`, `key` for
+/// `authority.key()`
+fn last_name(expr: &Expr) -> Option<String> {
+    match strip(expr) {
+        Expr::Path(path) => path.path.segments.last().map(|segment| segment.ident.to_string()),
+        Expr::Field(field) => match &field.member {
+            syn::Member::Named(ident) => Some(ident.to_string()),
+            syn::Member::Unnamed(_) => last_name(&field.base),
+        },
+        Expr::MethodCall(call) => Some(call.method.to_string()),
+        Expr::Index(index) => last_name(&index.expr),
+        _ => None,
+    }
+}
+
+/// A field of `ctx.accounts` or `accounts`: an account, printed whole
+fn is_account(expr: &Expr) -> bool {
+    match strip(expr) {
+        Expr::Field(field) => {
+            matches!(strip(&field.base), Expr::Field(base) if matches!(&base.member, syn::Member::Named(ident) if ident == "accounts"))
+                || matches!(strip(&field.base), Expr::Path(path) if path.path.is_ident("accounts"))
+        }
+        _ => false,
+    }
+}
+
+/// Classify an argument, given as an expression or captured by name. `whole` is for
+/// arguments printed whole: with `{:?}`, or as bytes.
+fn argument(code: String, expr: Option<&Expr>, whole: bool) -> Argument {
+    let (names, last) = match expr {
+        Some(expr) => {
+            let mut identifiers = Identifiers::default();
+            identifiers.visit_expr(expr);
+            (identifiers.0, last_name(expr))
+        }
+        None => (vec![code.clone()], Some(code.clone())),
+    };
+    let secret = names.iter().any(|name| is_secret_name(name));
+    let kind = if last.as_deref().is_some_and(is_key_name) {
+        ArgumentKind::Key
+    } else if whole && (last.as_deref().is_some_and(is_data_name) || expr.is_some_and(is_account)) {
+        ArgumentKind::Data
+    } else if whole {
+        ArgumentKind::Debug
+    } else {
+        ArgumentKind::Value
+    };
+    Argument { code, kind, secret }
+}
+
+/// A placeholder of a format string
+struct Placeholder {
+    /// Empty for the next positional argument, a number or a name
+    name: String,
+    debug: bool,
+}
+
+/// The placeholders of a format string, leaving out `{{` and `}}`
+fn placeholders(format: &str) -> Vec<Placeholder> {
+    let mut placeholders = Vec::new();
+    let mut chars = format.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c != '{' {
+            continue;
+        }
+        if chars.peek() == Some(&'{') {
+            chars.next();
+            continue;
+        }
+        let inner: String = chars.by_ref().take_while(|&c| c != '}').collect();
+        let (name, spec) = inner.split_once(':').unwrap_or((&inner, ""));
+        placeholders.push(Placeholder { name: name.trim().to_string(), debug: spec.contains('?') });
+    }
+    placeholders
+}
+
+fn string_literal(expr: &Expr) -> Option<String> {
+    match expr {
+        Expr::Lit(syn::ExprLit { lit: Lit::Str(lit), .. }) => Some(lit.value()),
+        _ => None,
+    }
+}
+
+/// The units of one log syscall printing a literal `message`
+fn message_units(message: Option<&str>) -> u64 {
+    LOG_UNITS.max(message.map_or(0, |message| message.len() as u64))
+}
+
+/// The log sites of one function body
+struct BodyVisitor<'a> {
+    path: &'a Path,
+    source: &'a str,
+    /// Loops around the current expression
+    loops: u32,
+    sites: Vec<LogSite>,
+}
+
+impl BodyVisitor<'_> {
+    /// The source of an expression, if it is on one line
+    fn code(&self, expr: &Expr) -> String {
+        let (start, end) = (expr.span().start(), expr.span().end());
+        let line = self.source.lines().nth(start.line.saturating_sub(1)).filter(|_| start.line == end.line);
+        match line {
+            Some(line) => line.chars().skip(start.column).take(end.column - start.column).collect(),
+            None => expr.to_token_stream().to_string(),
+        }
+    }
+
+    fn add(&mut self, call: &str, message: Option<String>, arguments: Vec<Argument>, units: u64, span: Span) {
+        let mut leaks: Vec<Leak> = Vec::new();
+        if arguments.iter().any(|argument| argument.secret) {
+            leaks.push(Leak::Secret);
+        }
+        if arguments.iter().any(|argument| argument.kind == ArgumentKind::Data) {
+            leaks.push(Leak::LargeData);
+        }
+        self.sites.push(LogSite {
+            call: call.to_string(),
+            message,
+            arguments,
+            units,
+            loops: self.loops.min(mcu::MAX_LOOP_NESTING),
+            leaks,
+            location: location(self.path, span),
+        });
+    }
+
+    fn msg(&mut self, args: &Punctuated<Expr, syn::Token![,]>, span: Span) {
+        let mut args = args.iter();
+        let Some(first) = args.next() else { return };
+        let message = string_literal(first);
+        let rest: Vec<&Expr> = args.collect();
+        if rest.is_empty() {
+            // Logged as is, without formatting
+            let arguments = match &message {
+                Some(_) => Vec::new(),
+                None => vec![argument(self.code(first), Some(first), false)],
+            };
+            self.add("msg!", message.clone(), arguments, message_units(message.as_deref()), span);
+            return;
+        }
+
+        let named = |name: &str| rest.iter().find_map(|arg| match arg {
+            Expr::Assign(assign) if matches!(&*assign.left, Expr::Path(path) if path.path.is_ident(name)) => Some(&*assign.right),
+            _ => None,
+        });
+        let mut next = 0;
+        let mut arguments = Vec::new();
+        for placeholder in placeholders(message.as_deref().unwrap_or_default()) {
+            let expr = if placeholder.name.is_empty() {
+                next += 1;
+                rest.get(next - 1).copied()
+            } else if let Ok(index) = placeholder.name.parse::<usize>() {
+                rest.get(index).copied()
+            } else {
+                named(&placeholder.name)
+            };
+            let argument = match expr {
+                Some(expr) => argument(self.code(expr), Some(expr), placeholder.debug),
+                None => argument(placeholder.name, None, placeholder.debug),
+            };
+            arguments.push(argument);
+        }
+        let units = message_units(message.as_deref()) + arguments.iter().map(|argument| argument.kind.units()).sum::<u64>();
+        self.add("msg!", message, arguments, units, span);
+    }
+
+    fn call(&mut self, name: &str, args: &Punctuated<Expr, syn::Token![,]>, span: Span) {
+        let values = |visitor: &Self, whole: bool| -> Vec<Argument> {
+            args.iter()
+                .filter(|arg| string_literal(arg).is_none())
+                .map(|arg| argument(visitor.code(arg), Some(arg), whole))
+                .collect()
+        };
+        if LOG_FUNCTIONS.contains(&name) {
+            let message = args.first().and_then(string_literal).filter(|_| name == "sol_log");
+            let units = message_units(message.as_deref());
+            let arguments = values(self, false);
+            self.add(name, message, arguments, units, span);
+        } else if name == "sol_log_data" {
+            // `sol_log_data(&[a, b])` logs each field
+            let fields: Vec<&Expr> = match args.first().map(strip) {
+                Some(Expr::Array(array)) => array.elems.iter().collect(),
+                Some(expr) => vec![expr],
+                None => Vec::new(),
+            };
+            let arguments: Vec<Argument> = fields.iter().map(|field| argument(self.code(field), Some(field), true)).collect();
+            let units = LOG_UNITS + LOG_UNITS * arguments.len() as u64;
+            self.add(name, None, arguments, units, span);
+        } else if matches!(name, "sol_log_params" | "sol_log_slice") {
+            // A line per account or per byte: large whatever it is named
+            let arguments = values(self, true).into_iter()
+                .map(|argument| Argument { kind: ArgumentKind::Data, ..argument })
+                .collect();
+            self.add(name, None, arguments, BULK_LOG_UNITS, span);
+        }
+    }
+
+    fn in_loop(&mut self, visit: impl FnOnce(&mut Self)) {
+        self.loops += 1;
+        visit(self);
+        self.loops -= 1;
+    }
+}
+
+impl<'ast> Visit<'ast> for BodyVisitor<'_> {
+    fn visit_expr_call(&mut self, call: &'ast ExprCall) {
+        if let Expr::Path(path) = &*call.func {
+            if let Some(segment) = path.path.segments.last() {
+                self.call(&segment.ident.to_string(), &call.args, call.span());
+            }
+        }
+        visit::visit_expr_call(self, call);
+    }
+
+    fn visit_expr_method_call(&mut self, call: &'ast ExprMethodCall) {
+        let name = call.method.to_string();
+        if name == "log" && call.args.is_empty() && last_name(&call.receiver).is_some_and(|name| is_key_name(&name)) {
+            self.add("log", None, vec![argument(self.code(&call.receiver), Some(&call.receiver), false)], LOG_UNITS, call.method.span());
+        }
+        if ITERATOR_METHODS.contains(&name.as_str()) {
+            self.visit_expr(&call.receiver);
+            for arg in &call.args {
+                if matches!(arg, Expr::Closure(_)) {
+                    self.in_loop(|visitor| visitor.visit_expr(arg));
+                } else {
+                    self.visit_expr(arg);
+                }
+            }
+            return;
+        }
+        visit::visit_expr_method_call(self, call);
+    }
+
+    fn visit_expr_for_loop(&mut self, expr: &'ast syn::ExprForLoop) {
+        self.visit_expr(&expr.expr);
+        self.in_loop(|visitor| visitor.visit_block(&expr.body));
+    }
+
+    fn visit_expr_while(&mut self, expr: &'ast syn::ExprWhile) {
+        self.in_loop(|visitor| visit::visit_expr_while(visitor, expr));
+    }
+
+    fn visit_expr_loop(&mut self, expr: &'ast syn::ExprLoop) {
+        self.in_loop(|visitor| visit::visit_expr_loop(visitor, expr));
+    }
+
+    fn visit_macro(&mut self, mac: &'ast syn::Macro) {
+        let Some(name) = mac.path.segments.last().map(|segment| segment.ident.to_string()) else { return };
+        let Ok(args) = mac.parse_body_with(Punctuated::<Expr, syn::Token![,]>::parse_terminated) else { return };
+        if name == "msg" {
+            self.msg(&args, mac.path.span());
+        }
+        for arg in &args {
+            self.visit_expr(arg);
+        }
+    }
+}
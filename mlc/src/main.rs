@@ -0,0 +1,244 @@
+mod report;
+mod sarif;
+
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::process;
+
+use clap::Parser;
+use serde::Serialize;
+use trr_core::git::Repository;
+use trr_core::output::{self, Format};
+
+use mlc::{Analysis, ArgumentKind, HandlerProfile, Leak, Log};
+
+#[derive(Parser)]
+#[clap(author, version, about = "Logging Cost (MLC) Analyzer")]
+#[clap(after_help = "Estimates are heuristic: use them to pick the logs to trim.
+
+Examples:
+  mlc programs/vault
+  mlc -v --top 5 programs
+  mlc --format sarif programs > logging.sarif
+  mlc --fail-on-leak --fail-above 20000 programs")]
+struct Args {
+    /// Directory to analyze (relative to --repo when given)
+    #[clap(value_name = "DIR", default_value = ".")]
+    dir: PathBuf,
+
+    /// Git repository URL to clone, or path of a local checkout
+    #[clap(long, value_name = "REPO_URL_OR_PATH")]
+    repo: Option<String>,
+
+    /// Branch, tag or commit of --repo to analyze
+    #[clap(long, value_name = "REV", requires = "repo")]
+    rev: Option<String>,
+
+    /// Output format
+    #[clap(long, value_enum, default_value = "text")]
+    format: Format,
+
+    /// List every log of each handler, not just the five most expensive
+    #[clap(short, long)]
+    verbose: bool,
+
+    /// Only the N handlers logging the most
+    #[clap(long, value_name = "N")]
+    top: Option<usize>,
+
+    /// Exit with status 1 if any handler's logging is estimated over UNITS
+    #[clap(long, value_name = "UNITS")]
+    fail_above: Option<u64>,
+
+    /// Exit with status 1 if any log may leak a secret or large data
+    #[clap(long)]
+    fail_on_leak: bool,
+}
+
+/// Structured form of the analysis for `--format json`
+#[derive(Serialize)]
+struct JsonReport<'a> {
+    summary: Summary,
+    /// Handlers, most logging first
+    handlers: &'a [HandlerProfile],
+    /// Every log site, by file and line
+    logs: &'a [Log],
+    errors: Vec<FileError>,
+}
+
+#[derive(Serialize)]
+struct Summary {
+    programs: usize,
+    handlers: usize,
+    log_sites: usize,
+    /// The highest estimate of a handler's logging
+    max_units: u64,
+    leaks: usize,
+    secret_leaks: usize,
+    large_data_leaks: usize,
+    /// Arguments printed in base58
+    key_arguments: usize,
+}
+
+#[derive(Serialize)]
+struct FileError {
+    file: PathBuf,
+    error: String,
+}
+
+fn leaking(logs: &[Log], leak: Leak) -> usize {
+    logs.iter().filter(|log| log.site.leaks.contains(&leak)).count()
+}
+
+/// What a leaking log prints: `secret (seed_phrase), large data (ctx.accounts.vault)`
+fn describe_leaks(log: &Log) -> String {
+    let described: Vec<String> = log.site.leaks.iter()
+        .map(|&leak| {
+            let arguments: Vec<&str> = log.site.arguments.iter()
+                .filter(|argument| match leak {
+                    Leak::Secret => argument.secret,
+                    Leak::LargeData => argument.kind == ArgumentKind::Data,
+                })
+                .map(|argument| argument.code.as_str())
+                .collect();
+            format!("{} ({})", leak, arguments.join(", "))
+        })
+        .collect();
+    described.join(", ")
+}
+
+fn print_text(analysis: &Analysis, logs: &[Log], handlers: &[HandlerProfile], verbose: bool) {
+    println!("\nPrograms:");
+    for program in &analysis.programs.programs {
+        let sites = logs.iter().filter(|log| log.program == program.name).count();
+        println!("  {} ({} handlers, {} log sites)", program.name, program.handlers.len(), sites);
+    }
+
+    if !handlers.is_empty() {
+        println!("\nLogging by instruction handler:");
+    }
+    for (rank, handler) in handlers.iter().enumerate() {
+        let leaks = if handler.leaks > 0 { format!(", {} may leak", handler.leaks) } else { String::new() };
+        println!(
+            "\n{}. {}::{} (at {}): ~{} CU in {} logs{}",
+            rank + 1, handler.program, handler.handler, handler.location, handler.units, handler.logs.len(), leaks
+        );
+        let shown = if verbose { handler.logs.len() } else { 5 };
+        for log in handler.logs.iter().take(shown) {
+            let times = if log.multiplier > 1 { format!(" ×{}", log.multiplier) } else { String::new() };
+            let message = log.site.message.as_deref().map(|message| format!(": {:?}", message)).unwrap_or_default();
+            println!("   - {}{}: {} CU in {} at {}{}", log.site.call, times, log.total, log.function, log.site.location, message);
+        }
+        if handler.logs.len() > shown {
+            println!("   ... {} more (-v lists them)", handler.logs.len() - shown);
+        }
+    }
+
+    let leaks: Vec<&Log> = logs.iter().filter(|log| !log.site.leaks.is_empty()).collect();
+    if !leaks.is_empty() {
+        println!("\nLogs that may leak:");
+    }
+    for log in &leaks {
+        println!("  {} in {} at {}: {}", log.site.call, log.function, log.site.location, describe_leaks(log));
+    }
+
+    let keys = logs.iter().flat_map(|log| &log.site.arguments).filter(|argument| argument.kind == ArgumentKind::Key).count();
+    println!("\nSummary:");
+    println!("Log sites: {}", logs.len());
+    println!("Logs that may leak: {} (secret {}, large data {})", leaks.len(), leaking(logs, Leak::Secret), leaking(logs, Leak::LargeData));
+    println!("Keys printed in base58: {}", keys);
+    println!("Highest handler logging: ~{} CU", handlers.iter().map(|handler| handler.units).max().unwrap_or(0));
+}
+
+fn write_json(out: &mut dyn Write, analysis: &Analysis, logs: &[Log], all: &[HandlerProfile], handlers: &[HandlerProfile]) -> io::Result<()> {
+    let report = JsonReport {
+        summary: Summary {
+            programs: analysis.programs.programs.len(),
+            handlers: all.len(),
+            log_sites: logs.len(),
+            max_units: all.first().map_or(0, |handler| handler.units),
+            leaks: logs.iter().filter(|log| !log.site.leaks.is_empty()).count(),
+            secret_leaks: leaking(logs, Leak::Secret),
+            large_data_leaks: leaking(logs, Leak::LargeData),
+            key_arguments: logs.iter().flat_map(|log| &log.site.arguments).filter(|argument| argument.kind == ArgumentKind::Key).count(),
+        },
+        handlers,
+        logs,
+        errors: analysis.errors.iter()
+            .map(|(file, error)| FileError { file: file.clone(), error: error.clone() })
+            .collect(),
+    };
+    output::write_json(out, "mlc", &report)
+}
+
+/// With `--fail-above` or `--fail-on-leak`, say which limit failed and exit with
+/// status 1
+fn enforce_limits(args: &Args, logs: &[Log], handlers: &[HandlerProfile]) {
+    let mut failed = false;
+    if let Some(limit) = args.fail_above {
+        let offending: Vec<&HandlerProfile> = handlers.iter().filter(|handler| handler.units > limit).collect();
+        if !offending.is_empty() {
+            let header = format!("{} handler(s) log over {} CU", offending.len(), limit);
+            output::list_failures(&header, offending.iter().map(|handler| {
+                format!("{}::{} (~{} CU) at {}", handler.program, handler.handler, handler.units, handler.location)
+            }));
+            failed = true;
+        }
+    }
+    let leaks: Vec<&Log> = logs.iter().filter(|log| !log.site.leaks.is_empty()).collect();
+    if args.fail_on_leak && !leaks.is_empty() {
+        let header = format!("{} log(s) may leak", leaks.len());
+        output::list_failures(&header, leaks.iter().map(|log| format!("{} at {}: {}", log.site.call, log.site.location, describe_leaks(log))));
+        failed = true;
+    }
+    if failed {
+        process::exit(1);
+    }
+}
+
+fn run(args: &Args) -> Result<(), Box<dyn std::error::Error>> {
+    let repository = args.repo.as_deref().map(|repo| Repository::open(repo, args.rev.as_deref())).transpose()?;
+    let dir = match &repository {
+        Some(repository) => repository.subdir(&args.dir)?,
+        None => args.dir.clone(),
+    };
+    if !dir.exists() {
+        return Err(format!("Path '{}' does not exist", dir.display()).into());
+    }
+    if args.format == Format::Text {
+        println!("Analyzing: {}", dir.display());
+    }
+
+    let analysis = mlc::analyze(&dir)?;
+    for (file, error) in &analysis.errors {
+        eprintln!("Error analyzing {}: {}", file.display(), error);
+    }
+    let logs = analysis.logs();
+    let all = analysis.profiles();
+    let handlers = &all[..args.top.map_or(all.len(), |top| top.min(all.len()))];
+
+    let mut out = io::stdout().lock();
+    match args.format {
+        Format::Text => print_text(&analysis, &logs, handlers, args.verbose),
+        Format::Json => write_json(&mut out, &analysis, &logs, &all, handlers)?,
+        Format::Csv => report::write_csv(&mut out, &logs)?,
+        Format::Markdown => report::write_markdown(&mut out, &dir, &logs, handlers)?,
+        Format::Sarif => {
+            // Artifact URIs are relative to the repository root, or to where mlc runs
+            let base = repository.as_ref().map_or_else(|| PathBuf::from("."), |repository| repository.root().to_path_buf());
+            sarif::write_sarif(&mut out, &logs, handlers, args.fail_above, &base)?;
+            writeln!(out)?;
+        }
+    }
+    drop(out);
+    enforce_limits(args, &logs, &all);
+    Ok(())
+}
+
+fn main() {
+    let args = Args::parse();
+    if let Err(e) = run(&args) {
+        eprintln!("Error: {}", e);
+        process::exit(1);
+    }
+}
@@ -0,0 +1,90 @@
+//! `--format csv`, one row per log site, and `--format markdown`, a review-ready
+//! profile of the handlers with the logs that may leak.
+
+use std::io::{self, Write};
+use std::path::Path;
+
+use trr_core::output::{markdown_cell, markdown_code, write_csv_row};
+
+use mlc::{HandlerProfile, Log};
+
+fn leaks(log: &Log) -> String {
+    let leaks: Vec<String> = log.site.leaks.iter().map(ToString::to_string).collect();
+    leaks.join("; ")
+}
+
+/// Rows are by file and line, with the arguments as `code (kind)` separated by `; `
+pub fn write_csv(out: &mut dyn Write, logs: &[Log]) -> io::Result<()> {
+    write_csv_row(out, &["program", "function", "call", "message", "arguments", "units", "multiplier", "total", "leaks", "file", "line"])?;
+    for log in logs {
+        let arguments: Vec<String> = log.site.arguments.iter()
+            .map(|argument| format!("{} ({})", argument.code, argument.kind))
+            .collect();
+        write_csv_row(out, &[
+            &log.program,
+            &log.function,
+            &log.site.call,
+            log.site.message.as_deref().unwrap_or(""),
+            &arguments.join("; "),
+            &log.site.units.to_string(),
+            &log.multiplier.to_string(),
+            &log.total.to_string(),
+            &leaks(log),
+            &log.site.location.file.display().to_string(),
+            &log.site.location.line.to_string(),
+        ])?;
+    }
+    Ok(())
+}
+
+pub fn write_markdown(out: &mut dyn Write, target: &Path, logs: &[Log], handlers: &[HandlerProfile]) -> io::Result<()> {
+    let leaking: Vec<&Log> = logs.iter().filter(|log| !log.site.leaks.is_empty()).collect();
+    writeln!(out, "# Logging Cost\n")?;
+    writeln!(out, "Target: {}\n", markdown_code(&target.display().to_string()))?;
+    writeln!(out, "Estimates are heuristic: they rank the logs to trim first.\n")?;
+    writeln!(out, "## Summary\n")?;
+    writeln!(out, "| Metric | Value |")?;
+    writeln!(out, "| --- | ---: |")?;
+    writeln!(out, "| Log sites | {} |", logs.len())?;
+    writeln!(out, "| Instruction handlers | {} |", handlers.len())?;
+    writeln!(out, "| Highest handler logging (CU) | {} |", handlers.iter().map(|handler| handler.units).max().unwrap_or(0))?;
+    writeln!(out, "| Logs that may leak | {} |", leaking.len())?;
+
+    if !handlers.is_empty() {
+        writeln!(out, "\n## Handlers\n")?;
+        writeln!(out, "| # | Handler | Logging (CU) | Logs | May leak | Location |")?;
+        writeln!(out, "| ---: | --- | ---: | ---: | ---: | --- |")?;
+        for (rank, handler) in handlers.iter().enumerate() {
+            writeln!(
+                out,
+                "| {} | {} | {} | {} | {} | {} |",
+                rank + 1,
+                markdown_code(&format!("{}::{}", handler.program, handler.handler)),
+                handler.units,
+                handler.logs.len(),
+                handler.leaks,
+                markdown_code(&handler.location.to_string()),
+            )?;
+        }
+    }
+
+    if leaking.is_empty() {
+        return Ok(());
+    }
+    writeln!(out, "\n## Logs That May Leak\n")?;
+    writeln!(out, "| Log | Function | Leaks | Arguments | Location |")?;
+    writeln!(out, "| --- | --- | --- | --- | --- |")?;
+    for log in leaking {
+        let arguments: Vec<String> = log.site.arguments.iter().map(|argument| markdown_code(&argument.code)).collect();
+        writeln!(
+            out,
+            "| {} | {} | {} | {} | {} |",
+            markdown_code(&log.site.call),
+            markdown_code(&log.function),
+            markdown_cell(&leaks(log)),
+            arguments.join(", "),
+            markdown_code(&log.site.location.to_string()),
+        )?;
+    }
+    Ok(())
+}
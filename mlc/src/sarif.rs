@@ -0,0 +1,80 @@
+//! SARIF 2.1.0 output: a `log-leak` result per log that may leak, and with
+//! `--fail-above` a `logging-cost` result per handler logging over the limit, with its
+//! five most expensive logs as related locations.
+
+use std::io::{self, Write};
+use std::path::Path;
+
+use serde_json::json;
+use trr_core::sarif::{location, write_log};
+
+use mlc::{HandlerProfile, Log};
+
+const LEAK_RULE_ID: &str = "log-leak";
+const COST_RULE_ID: &str = "logging-cost";
+
+pub fn write_sarif(out: &mut dyn Write, logs: &[Log], handlers: &[HandlerProfile], limit: Option<u64>, base: &Path) -> io::Result<()> {
+    let mut results: Vec<_> = logs.iter()
+        .filter(|log| !log.site.leaks.is_empty())
+        .map(|log| {
+            let leaks: Vec<String> = log.site.leaks.iter().map(ToString::to_string).collect();
+            let arguments: Vec<&str> = log.site.arguments.iter().map(|argument| argument.code.as_str()).collect();
+            json!({
+                "ruleId": LEAK_RULE_ID,
+                "level": "warning",
+                "message": {
+                    "text": format!("{} in {} may leak {}: {}", log.site.call, log.function, leaks.join(" and "), arguments.join(", ")),
+                },
+                "locations": [location(&log.site.location, base)],
+                "properties": { "leaks": log.site.leaks },
+            })
+        })
+        .collect();
+
+    if let Some(limit) = limit {
+        results.extend(handlers.iter().filter(|handler| handler.units > limit).map(|handler| {
+            let related: Vec<_> = handler.logs.iter()
+                .take(5)
+                .enumerate()
+                .map(|(i, log)| {
+                    let mut related = location(&log.site.location, base);
+                    related["id"] = json!(i);
+                    related["message"] = json!({ "text": format!("{}: {} CU", log.site.call, log.total) });
+                    related
+                })
+                .collect();
+            json!({
+                "ruleId": COST_RULE_ID,
+                "level": "warning",
+                "message": {
+                    "text": format!("{}::{} logs an estimated ~{} CU (over {} CU)", handler.program, handler.handler, handler.units, limit),
+                },
+                "locations": [location(&handler.location, base)],
+                "relatedLocations": related,
+                "properties": { "units": handler.units },
+            })
+        }));
+    }
+
+    let rules = vec![
+        json!({
+            "id": LEAK_RULE_ID,
+            "name": "LogLeak",
+            "shortDescription": { "text": "Log that may print a secret or a whole data structure" },
+            "fullDescription": {
+                "text": "Program logs are public and kept by every RPC node. A log printing a value named like a secret, a whole account, the context or instruction data exposes it to anyone and costs compute units for every byte. Log only the fields needed.",
+            },
+            "defaultConfiguration": { "level": "warning" },
+        }),
+        json!({
+            "id": COST_RULE_ID,
+            "name": "LoggingCost",
+            "shortDescription": { "text": "Instruction handler spending many compute units on logs" },
+            "fullDescription": {
+                "text": "The logs an instruction handler reaches add up to a high heuristic estimate. Formatting arguments with msg!, keys in base58 and logs in loops are the most expensive; prefer literals, Pubkey::log and sol_log_64.",
+            },
+            "defaultConfiguration": { "level": "warning" },
+        }),
+    ];
+    write_log(out, "mlc", env!("CARGO_PKG_VERSION"), rules, results)
+}
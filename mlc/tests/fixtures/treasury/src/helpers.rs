@@ -0,0 +1,12 @@
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::log::sol_log_data;
+
+use crate::Treasury;
+
+// formatted (100 + 1,000) + sol_log_data (100 + 100) = 1,300
+pub fn log_state(treasury: &Treasury) -> Result<()> {
+    msg!("treasury state: {:?}", treasury.total);
+    let data = treasury.try_to_vec()?;
+    sol_log_data(&[&data]);
+    Ok(())
+}
@@ -0,0 +1,75 @@
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::log::sol_log_compute_units;
+
+pub mod helpers;
+
+use helpers::log_state;
+
+#[program]
+pub mod treasury {
+    use super::*;
+
+    // literal 100 + formatted (100 + amount 200 + key 2,000) = 2,400
+    pub fn deposit(ctx: Context<Deposit>, amount: u64) -> Result<()> {
+        msg!("Instruction: Deposit");
+        msg!("Depositing {} lamports from {}", amount, ctx.accounts.depositor.key());
+        ctx.accounts.treasury.total += amount;
+        Ok(())
+    }
+
+    // 10 × share (100 + 200) + log_state 1,300 + sol_log_compute_units 100 = 4,400
+    pub fn distribute(ctx: Context<Distribute>, shares: Vec<u64>) -> Result<()> {
+        for share in shares.iter() {
+            msg!("share {}", share);
+        }
+        log_state(&ctx.accounts.treasury)?;
+        sol_log_compute_units();
+        Ok(())
+    }
+
+    // secret (100 + 1,000) + account (100 + 5,000) + new_admin 100 + key.log() 100 = 6,400.
+    // msg! with only a message does not format it: the braces are printed as is.
+    pub fn rotate_admin(ctx: Context<RotateAdmin>, admin_secret: [u8; 32], new_admin: Pubkey) -> Result<()> {
+        msg!("rotating admin with {:?}", admin_secret);
+        msg!("treasury before: {:?}", ctx.accounts.treasury);
+        msg!("new admin {new_admin}");
+        ctx.accounts.admin.key().log();
+        ctx.accounts.treasury.admin = new_admin;
+        Ok(())
+    }
+}
+
+#[derive(Accounts)]
+pub struct Deposit<'info> {
+    #[account(mut)]
+    pub treasury: Account<'info, Treasury>,
+    pub depositor: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct Distribute<'info> {
+    #[account(mut)]
+    pub treasury: Account<'info, Treasury>,
+}
+
+#[derive(Accounts)]
+pub struct RotateAdmin<'info> {
+    #[account(mut, has_one = admin)]
+    pub treasury: Account<'info, Treasury>,
+    pub admin: Signer<'info>,
+}
+
+#[account]
+#[derive(Debug)]
+pub struct Treasury {
+    pub admin: Pubkey,
+    pub total: u64,
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn logs() {
+        anchor_lang::prelude::msg!("test {:?}", 1);
+    }
+}
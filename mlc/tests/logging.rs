@@ -0,0 +1,87 @@
+// Profiles tests/fixtures/treasury: an Anchor program logging keys, a secret, a whole
+// account and instruction data, in a loop and through a helper module.
+
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use mlc::{ArgumentKind, HandlerProfile, Leak};
+use serde_json::Value;
+
+fn fixture() -> PathBuf {
+    Path::new(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/treasury")).to_path_buf()
+}
+
+fn profiles() -> Vec<HandlerProfile> {
+    let analysis = mlc::analyze(&fixture()).unwrap();
+    assert!(analysis.errors.is_empty(), "{:?}", analysis.errors);
+    analysis.profiles()
+}
+
+#[test]
+fn profiles_per_handler() {
+    let profiles = profiles();
+    let ranking: Vec<(&str, u64, usize, usize)> = profiles.iter()
+        .map(|profile| (profile.handler.as_str(), profile.units, profile.logs.len(), profile.leaks))
+        .collect();
+    assert_eq!(ranking, [("rotate_admin", 6_400, 4, 2), ("distribute", 4_400, 4, 1), ("deposit", 2_400, 2, 0)]);
+
+    // The loop multiplies the log, and the helper's logs count for its caller
+    let distribute = &profiles[1];
+    assert_eq!((distribute.logs[0].multiplier, distribute.logs[0].total), (10, 3_000));
+    assert!(distribute.logs.iter().any(|log| log.function == "helpers::log_state" && log.site.call == "sol_log_data"));
+
+    let deposit = &profiles[2];
+    let kinds: Vec<(&str, ArgumentKind)> = deposit.logs[0].site.arguments.iter()
+        .map(|argument| (argument.code.as_str(), argument.kind))
+        .collect();
+    assert_eq!(kinds, [("amount", ArgumentKind::Value), ("ctx.accounts.depositor.key()", ArgumentKind::Key)]);
+}
+
+#[test]
+fn leaks() {
+    let analysis = mlc::analyze(&fixture()).unwrap();
+    let logs = analysis.logs();
+    // The test module's msg! is left out
+    assert_eq!(logs.len(), 10);
+    let leaking: Vec<(&str, usize, &[Leak])> = logs.iter()
+        .filter(|log| !log.site.leaks.is_empty())
+        .map(|log| (log.function.as_str(), log.site.location.line, log.site.leaks.as_slice()))
+        .collect();
+    assert_eq!(leaking, [
+        ("helpers::log_state", 10, &[Leak::LargeData][..]),
+        ("treasury::rotate_admin", 33, &[Leak::Secret][..]),
+        ("treasury::rotate_admin", 34, &[Leak::LargeData][..]),
+    ]);
+
+    // With one argument, msg! prints the braces: nothing is formatted
+    let captured = logs.iter().find(|log| log.site.message.as_deref() == Some("new admin {new_admin}")).unwrap();
+    assert!(captured.site.arguments.is_empty());
+    assert_eq!(captured.site.units, 100);
+}
+
+#[test]
+fn json_and_limits() {
+    let run = |args: &[&str]| Command::new(env!("CARGO_BIN_EXE_mlc"))
+        .args(args)
+        .arg(fixture())
+        .output()
+        .expect("failed to run mlc");
+
+    let output = run(&["--format", "json", "--top", "1"]);
+    assert!(output.status.success());
+    let report: Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(report["tool"], "mlc");
+    assert_eq!(report["summary"]["handlers"], 3);
+    assert_eq!(report["summary"]["log_sites"], 10);
+    assert_eq!(report["summary"]["max_units"], 6_400);
+    assert_eq!(report["summary"]["leaks"], 3);
+    assert_eq!(report["summary"]["key_arguments"], 2);
+    assert_eq!(report["handlers"].as_array().unwrap().len(), 1);
+    assert_eq!(report["logs"][1]["leaks"][0], "large-data");
+
+    assert_eq!(run(&["--fail-above", "10000"]).status.code(), Some(0));
+    assert_eq!(run(&["--fail-above", "5000"]).status.code(), Some(1));
+    let output = run(&["--fail-on-leak"]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("secret (admin_secret)"));
+}
@@ -0,0 +1,42 @@
+//! `--anchor`: Anchor programs. Code inside a `#[program]` module is re-emitted by the
+//! attribute's expansion, so macros in instruction handlers nest below `#[program]`
+//! rather than at the top level. Each handler is reported with its own depth and the
+//! `#[derive(Accounts)]` struct of its `Context`.
+
+use std::path::PathBuf;
+
+use serde::Serialize;
+use syn::punctuated::Punctuated;
+use syn::{Attribute, Token};
+
+use crate::CallSite;
+
+/// A public function of a `#[program]` module
+#[derive(Serialize)]
+pub struct InstructionHandler {
+    /// `program_module::handler`
+    pub name: String,
+    pub file: PathBuf,
+    pub line: usize,
+    /// The accounts struct of its `Context<...>` argument
+    pub accounts: Option<String>,
+    /// Deepest nesting inside the handler, counting `#[program]`
+    pub depth: usize,
+    pub deepest_chain: Vec<CallSite>,
+    /// Expansion depth of the accounts struct's `#[derive(Accounts)]`, when it was found
+    pub accounts_depth: Option<usize>,
+}
+
+/// Whether one of `attrs` is `#[name]` or `#[path::to::name]`
+pub fn has_attribute(attrs: &[Attribute], name: &str) -> bool {
+    attrs.iter().any(|attr| attr.path().segments.last().is_some_and(|segment| segment.ident == name))
+}
+
+/// The path of `name` in a `#[derive(...)]` among `attrs`, as written
+pub fn derived_path(attrs: &[Attribute], name: &str) -> Option<syn::Path> {
+    attrs.iter()
+        .filter(|attr| attr.path().is_ident("derive"))
+        .filter_map(|attr| attr.parse_args_with(Punctuated::<syn::Path, Token![,]>::parse_terminated).ok())
+        .flatten()
+        .find(|path| path.segments.last().is_some_and(|segment| segment.ident == name))
+}
@@ -0,0 +1,125 @@
+//! Baselines (`--write-baseline`, `--baseline`, `--baseline-rev`): the depth and
+//! warnings of every file at an earlier point, so that an existing codebase can adopt
+//! `--fail-above` and `--fail-on-warning` and only newly introduced nesting is reported.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use mmed::{FileReport, Warning};
+
+#[derive(Default, Serialize, Deserialize)]
+pub struct Baseline {
+    /// Path relative to the analyzed directory -> its figures
+    pub files: BTreeMap<String, FileBaseline>,
+}
+
+#[derive(Default, Serialize, Deserialize)]
+pub struct FileBaseline {
+    pub max_depth: usize,
+    /// Warning message -> instances
+    pub warnings: BTreeMap<String, usize>,
+}
+
+/// A file that got deeper or gained warnings since the baseline
+#[derive(Serialize)]
+pub struct Increase {
+    pub file: String,
+    /// `None` for a file that is new since the baseline
+    pub old_depth: Option<usize>,
+    pub new_depth: usize,
+    /// Messages of the warnings beyond the baseline's count for the same message
+    pub new_warnings: Vec<String>,
+}
+
+/// The key of a file in a baseline
+fn key(report: &FileReport, root: &Path) -> String {
+    report.path.strip_prefix(root).unwrap_or(&report.path).display().to_string()
+}
+
+impl Baseline {
+    /// The figures of `reports`, for files under `root`
+    pub fn of(reports: &[FileReport], root: &Path) -> Self {
+        let files = reports.iter()
+            .map(|report| {
+                let mut warnings = BTreeMap::new();
+                for warning in &report.warnings {
+                    *warnings.entry(warning.message.clone()).or_insert(0) += 1;
+                }
+                (key(report, root), FileBaseline { max_depth: report.max_depth, warnings })
+            })
+            .collect();
+        Baseline { files }
+    }
+
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let content = fs::read_to_string(path)?;
+        serde_json::from_str(&content).map_err(|e| {
+            io::Error::new(io::ErrorKind::InvalidData, format!("Invalid baseline {}: {}", path.display(), e))
+        })
+    }
+
+    pub fn write(&self, path: &Path) -> io::Result<()> {
+        let mut content = serde_json::to_string_pretty(self)?;
+        content.push('\n');
+        fs::write(path, content)
+    }
+
+    /// Whether the file of `report` was already at least this deep in the baseline
+    pub fn covers_depth(&self, report: &FileReport, root: &Path) -> bool {
+        self.files.get(&key(report, root)).is_some_and(|file| report.max_depth <= file.max_depth)
+    }
+
+    /// The warnings of `report` beyond the instances of the same message in the baseline
+    pub fn new_warnings<'a>(&self, report: &'a FileReport, root: &Path) -> Vec<&'a Warning> {
+        let mut allowed = self.files.get(&key(report, root)).map(|file| file.warnings.clone()).unwrap_or_default();
+        report.warnings.iter()
+            .filter(|warning| match allowed.get_mut(&warning.message) {
+                Some(count) if *count > 0 => {
+                    *count -= 1;
+                    false
+                }
+                _ => true,
+            })
+            .collect()
+    }
+
+    /// The files of `reports` that got deeper or gained warnings, in report order
+    pub fn increases(&self, reports: &[FileReport], root: &Path) -> Vec<Increase> {
+        reports.iter()
+            .filter_map(|report| {
+                let old_depth = self.files.get(&key(report, root)).map(|file| file.max_depth);
+                let new_warnings: Vec<String> = self.new_warnings(report, root).into_iter()
+                    .map(|warning| warning.message.clone())
+                    .collect();
+                let deeper = report.max_depth > old_depth.unwrap_or(0);
+                (deeper || !new_warnings.is_empty()).then(|| Increase {
+                    file: key(report, root),
+                    old_depth,
+                    new_depth: report.max_depth,
+                    new_warnings,
+                })
+            })
+            .collect()
+    }
+}
+
+pub fn print_increases(increases: &[Increase], since: &str) {
+    println!("\nIncreases Since {}:", since);
+    if increases.is_empty() {
+        println!("No increases in macro nesting or warnings.");
+    }
+    for increase in increases {
+        match increase.old_depth {
+            Some(old) if increase.new_depth > old => println!("↑ {}: depth {} → {}", increase.file, old, increase.new_depth),
+            None if increase.new_depth > 0 => println!("+ {}: depth {} (new)", increase.file, increase.new_depth),
+            _ => println!("  {}: depth {}", increase.file, increase.new_depth),
+        }
+        for message in &increase.new_warnings {
+            println!("    new warning: {}", message);
+        }
+    }
+}
@@ -0,0 +1,48 @@
+//! `mmed.toml`, or the `[mmed]` table of a shared `trr.toml`: which macros count toward
+//! the depth, so a project can tune them without rebuilding the tool. Options given on
+//! the command line are applied after the config file.
+
+use std::collections::HashMap;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use serde::Deserialize;
+
+#[derive(Default, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct Config {
+    /// Macros left out of the depth, in addition to the built-in helpers
+    pub ignore_macros: Vec<String>,
+    /// Macros that count toward the depth even if ignored by default, and that are
+    /// treated as proc-macros when used as attributes
+    pub track_macros: Vec<String>,
+    /// Globs of files and directories to leave out (`--exclude`)
+    pub exclude: Vec<String>,
+    /// Also analyze files ignored by `.gitignore`
+    pub no_gitignore: bool,
+    /// Count calls to local macros as one level (`--no-local-expansion`)
+    pub no_local_expansion: bool,
+    /// Expansion depths of attributes and derives, overriding the built-in table
+    pub proc_macro_depths: HashMap<String, usize>,
+    /// Start from an empty depth table
+    pub no_builtin_depths: bool,
+    /// Analyze as Anchor programs (`--anchor`)
+    pub anchor: bool,
+    pub fail_above: Option<usize>,
+    /// Buckets left out of the overall depth and `fail-above` (`--exclude-bucket`)
+    pub exclude_buckets: Vec<crate::Bucket>,
+    pub fail_on_warning: Vec<crate::WarningKind>,
+    pub min_severity: Option<crate::Severity>,
+    /// Severity of each warning type, overriding its default
+    pub warning_severity: HashMap<crate::WarningKind, crate::Severity>,
+    pub disable_warnings: Vec<crate::WarningKind>,
+}
+
+/// The nearest `mmed.toml` or `trr.toml` in `dir` or its ancestors
+pub fn find(dir: &Path) -> Option<PathBuf> {
+    trr_core::config::find(dir, "mmed")
+}
+
+pub fn load(path: &Path) -> io::Result<Config> {
+    trr_core::config::load(path, "mmed")
+}
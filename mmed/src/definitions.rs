@@ -0,0 +1,449 @@
+//! `macro_rules!` definitions and the macros their bodies invoke. Call sites only show
+//! one level of nesting; a macro that invokes itself (directly, or through other local
+//! macros) recurses once per step of its input, so the recursion is found from the
+//! call graph and its depth estimated from the largest input it is called with.
+//!
+//! The call graph also gives what a call to a local macro expands to: the macros its
+//! rules invoke, then the macros those invoke, down to external macros, with recursion
+//! bounded by its estimated depth.
+//!
+//! Proc-macros defined by crates in the analyzed directory are recorded too, so
+//! user-defined macros can be told apart from external ones.
+//!
+//! Macros 2.0 (`macro name(...) { ... }` and `macro name { rules }` items, which syn
+//! keeps as verbatim tokens) are definitions in the same way as `macro_rules!`.
+//!
+//! With several crates under the analyzed directory, a call resolves to a definition
+//! of its own crate, or else to a `#[macro_export]`ed one of another crate.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+use proc_macro2::{TokenStream, TokenTree};
+use syn::visit::{self, Visit};
+use syn::{Item, ItemFn, ItemMacro};
+
+/// rustc's default `#![recursion_limit]`
+pub const DEFAULT_RECURSION_LIMIT: usize = 128;
+
+/// A `macro_rules!` definition
+#[derive(Clone)]
+pub struct MacroDefinition {
+    pub file: PathBuf,
+    pub line: usize,
+    /// Macros invoked in the transcribers (right-hand sides) of its rules
+    pub calls: BTreeSet<String>,
+    /// The invocations of the transcribers, with the invocations nested in their input
+    pub body: Vec<Call>,
+    /// `#[macro_export]`, so other crates can call it
+    pub exported: bool,
+}
+
+/// An invocation in a transcriber and the invocations in its input
+#[derive(Clone, Debug)]
+pub struct Call {
+    pub name: String,
+    pub nested: Vec<Call>,
+}
+
+/// Macros that invoke each other in a cycle, or a single self-recursive macro
+pub struct RecursiveMacros {
+    /// Sorted by name
+    pub macros: Vec<String>,
+    /// Most input items any call site passes to one of the macros, assuming each
+    /// recursion step consumes one, and at least 1 since a call expands the macro
+    /// once even with empty input; `None` when none of them is called
+    pub estimated_depth: Option<usize>,
+}
+
+/// What a call to a local macro expands to
+#[derive(Clone, Debug, Default)]
+pub struct LocalExpansion {
+    /// Levels of nesting below the call itself
+    pub depth: usize,
+    /// The macro expanded at each level, outermost first: `name!`, or `name! ×n` for
+    /// `n` levels of recursion
+    pub chain: Vec<String>,
+}
+
+/// Definitions and call sites of every analyzed file
+#[derive(Clone, Default)]
+pub struct MacroIndex {
+    pub definitions: BTreeMap<String, MacroDefinition>,
+    /// Proc-macros defined by `#[proc_macro]`, `#[proc_macro_attribute]` and
+    /// `#[proc_macro_derive]` functions: `name!`, `#[name]` or `#[derive(Name)]`
+    pub proc_macros: BTreeSet<String>,
+    /// Macro name -> most input items passed at any call site
+    call_inputs: HashMap<String, usize>,
+}
+
+impl MacroIndex {
+    pub fn collect(&mut self, file: &syn::File, path: &Path) {
+        let mut collector = Collector { index: self, path };
+        collector.visit_file(file);
+    }
+
+    /// Add the definitions and call sites of `other`, collected from later files
+    pub fn merge(&mut self, other: MacroIndex) {
+        self.definitions.extend(other.definitions);
+        self.proc_macros.extend(other.proc_macros);
+        for (name, items) in other.call_inputs {
+            let largest = self.call_inputs.entry(name).or_default();
+            *largest = (*largest).max(items);
+        }
+    }
+
+    /// Whether a macro as keyed in the statistics (`name!`, `#[path::name]`,
+    /// `#[derive(path::Name)]`) is defined in the analyzed directory. Attribute paths
+    /// are matched by their last segment.
+    pub fn defines(&self, usage: &str) -> bool {
+        if let Some(name) = usage.strip_suffix('!') {
+            return self.definitions.contains_key(name) || self.proc_macros.contains(usage);
+        }
+        let Some(key) = usage.strip_prefix("#[").and_then(|key| key.strip_suffix(']')) else { return false };
+        let key = match key.strip_prefix("derive(").and_then(|path| path.strip_suffix(')')) {
+            Some(path) => format!("derive({})", path.rsplit("::").next().unwrap_or(path)),
+            None => key.rsplit("::").next().unwrap_or(key).to_string(),
+        };
+        self.proc_macros.contains(&format!("#[{}]", key))
+    }
+
+    /// The definitions a crate with `own` definitions sees: its own, and the exported
+    /// definitions of the other crates of `self` that it does not define itself
+    pub fn visible_from(&self, own: &MacroIndex) -> MacroIndex {
+        let mut visible = own.clone();
+        visible.call_inputs = self.call_inputs.clone();
+        for (name, definition) in self.definitions.iter().filter(|(_, definition)| definition.exported) {
+            visible.definitions.entry(name.clone()).or_insert_with(|| definition.clone());
+        }
+        visible
+    }
+
+    /// Groups of mutually recursive macros and self-recursive macros, in name order
+    pub fn recursive_groups(&self) -> Vec<RecursiveMacros> {
+        let mut groups = Vec::new();
+        let mut grouped: BTreeSet<String> = BTreeSet::new();
+        for name in self.definitions.keys() {
+            if grouped.contains(name) {
+                continue;
+            }
+            // Macros reachable from `name` that also reach back to it
+            let reachable = self.reachable(name);
+            if !reachable.contains(name.as_str()) {
+                continue;
+            }
+            let macros: Vec<String> = reachable.into_iter()
+                .filter(|other| self.reachable(other).contains(name.as_str()))
+                .map(str::to_string)
+                .collect();
+            grouped.extend(macros.iter().cloned());
+            let estimated_depth = macros.iter().filter_map(|name| self.call_inputs.get(name)).copied().max().map(|items| items.max(1));
+            groups.push(RecursiveMacros { macros, estimated_depth });
+        }
+        groups
+    }
+
+    /// The expansion of every local macro, leaving out `ignored` macros
+    pub fn local_expansions(&self, ignored: &HashSet<String>) -> HashMap<String, LocalExpansion> {
+        // Recursive macro -> levels of recursion, as estimated from its call sites
+        let recursion: HashMap<String, usize> = self.recursive_groups().into_iter()
+            .flat_map(|group| {
+                let depth = group.estimated_depth.unwrap_or(1).min(DEFAULT_RECURSION_LIMIT);
+                group.macros.into_iter().map(move |name| (name, depth))
+            })
+            .collect();
+        self.definitions.keys()
+            .map(|name| (name.clone(), self.expansion(name, ignored, &recursion, &mut Vec::new())))
+            .collect()
+    }
+
+    /// The deepest expansion below `name`, with the macros being expanded in `visiting`
+    fn expansion<'a>(
+        &'a self,
+        name: &'a str,
+        ignored: &HashSet<String>,
+        recursion: &HashMap<String, usize>,
+        visiting: &mut Vec<&'a str>,
+    ) -> LocalExpansion {
+        let Some(definition) = self.definitions.get(name) else { return LocalExpansion::default() };
+        visiting.push(name);
+        let deepest = self.deepest_call(&definition.body, ignored, recursion, visiting);
+        visiting.pop();
+        deepest
+    }
+
+    /// The deepest of `calls`, each counting the calls nested in its input below it
+    fn deepest_call<'a>(
+        &'a self,
+        calls: &'a [Call],
+        ignored: &HashSet<String>,
+        recursion: &HashMap<String, usize>,
+        visiting: &mut Vec<&'a str>,
+    ) -> LocalExpansion {
+        let mut deepest = LocalExpansion::default();
+        for call in calls {
+            let called = call.name.as_str();
+            let mut candidate = if ignored.contains(called) {
+                LocalExpansion::default()
+            } else if visiting.contains(&called) {
+                // The first call is already counted by the caller
+                let steps = recursion.get(called).copied().unwrap_or(1).saturating_sub(1);
+                LocalExpansion { depth: steps, chain: vec![format!("{}! ×{}", called, steps)] }
+            } else if self.definitions.contains_key(called) {
+                let inner = self.expansion(called, ignored, recursion, visiting);
+                let mut chain = vec![format!("{}!", called)];
+                chain.extend(inner.chain);
+                LocalExpansion { depth: 1 + inner.depth, chain }
+            } else {
+                LocalExpansion { depth: 1, chain: vec![format!("{}!", called)] }
+            };
+            let nested = self.deepest_call(&call.nested, ignored, recursion, visiting);
+            candidate.depth += nested.depth;
+            candidate.chain.extend(nested.chain);
+            if candidate.depth > deepest.depth {
+                deepest = candidate;
+            }
+        }
+        deepest
+    }
+
+    /// Local macros invoked by `name`'s body, directly or through other local macros
+    fn reachable(&self, name: &str) -> BTreeSet<&str> {
+        let mut reached = BTreeSet::new();
+        let mut pending = vec![name];
+        while let Some(current) = pending.pop() {
+            let Some(definition) = self.definitions.get(current) else { continue };
+            for called in &definition.calls {
+                if let Some((called, _)) = self.definitions.get_key_value(called) {
+                    if reached.insert(called.as_str()) {
+                        pending.push(called);
+                    }
+                }
+            }
+        }
+        reached
+    }
+}
+
+struct Collector<'a> {
+    index: &'a mut MacroIndex,
+    path: &'a Path,
+}
+
+impl<'ast> Visit<'ast> for Collector<'_> {
+    fn visit_item_macro(&mut self, item: &'ast ItemMacro) {
+        match &item.ident {
+            Some(name) if item.mac.path.is_ident("macro_rules") => {
+                let exported = item.attrs.iter().any(|attr| attr.path().is_ident("macro_export"));
+                self.define(name, transcribers(&item.mac.tokens), exported);
+            }
+            _ => visit::visit_item_macro(self, item),
+        }
+    }
+
+    fn visit_item(&mut self, item: &'ast Item) {
+        match item {
+            Item::Verbatim(tokens) => {
+                if let Some(definition) = Macro2::parse(tokens) {
+                    self.define(&definition.name, definition.transcribers, definition.public);
+                }
+            }
+            _ => visit::visit_item(self, item),
+        }
+    }
+
+    fn visit_item_fn(&mut self, item: &'ast ItemFn) {
+        for attr in &item.attrs {
+            let name = item.sig.ident.to_string();
+            let key = if attr.path().is_ident("proc_macro") {
+                format!("{}!", name)
+            } else if attr.path().is_ident("proc_macro_attribute") {
+                format!("#[{}]", name)
+            } else if attr.path().is_ident("proc_macro_derive") {
+                // `#[proc_macro_derive(Name, attributes(helper))]`: the first ident
+                match attr.parse_args_with(|input: syn::parse::ParseStream| {
+                    let derive: syn::Ident = input.parse()?;
+                    input.parse::<TokenStream>()?;
+                    Ok(derive)
+                }) {
+                    Ok(derive) => format!("#[derive({})]", derive),
+                    Err(_) => continue,
+                }
+            } else {
+                continue;
+            };
+            self.index.proc_macros.insert(key);
+        }
+        visit::visit_item_fn(self, item);
+    }
+
+    fn visit_macro(&mut self, mac: &'ast syn::Macro) {
+        if let Some(segment) = mac.path.segments.last() {
+            self.record_call(segment.ident.to_string(), &mac.tokens);
+        }
+        for (called, input) in invocations(&mac.tokens) {
+            self.record_call(called, &input);
+        }
+    }
+}
+
+impl Collector<'_> {
+    // Invocations inside a definition are not counted as call sites: their input is
+    // made of the definition's metavariables
+    fn define(&mut self, name: &syn::Ident, transcribers: Vec<TokenStream>, exported: bool) {
+        let calls = transcribers.iter()
+            .flat_map(invocations)
+            .map(|(called, _)| called)
+            .collect();
+        self.index.definitions.insert(name.to_string(), MacroDefinition {
+            file: self.path.to_path_buf(),
+            line: name.span().start().line,
+            calls,
+            body: transcribers.iter().flat_map(call_tree).collect(),
+            exported,
+        });
+    }
+
+    fn record_call(&mut self, name: String, input: &TokenStream) {
+        let items = input_items(input);
+        let largest = self.index.call_inputs.entry(name).or_default();
+        *largest = (*largest).max(items);
+    }
+}
+
+/// A macros 2.0 item
+pub struct Macro2 {
+    pub name: syn::Ident,
+    /// Declared `pub`, so other crates can call it (unlike `pub(crate)`)
+    pub public: bool,
+    /// The tokens after the name: the matcher and body, or the braced rules
+    pub body: TokenStream,
+    pub transcribers: Vec<TokenStream>,
+}
+
+impl Macro2 {
+    /// `[vis] macro name(matcher) { transcriber }` or `[vis] macro name { rules }`
+    pub fn parse(tokens: &TokenStream) -> Option<Macro2> {
+        let mut iter = tokens.clone().into_iter().peekable();
+        let mut public = false;
+        let name = loop {
+            match iter.next()? {
+                TokenTree::Ident(ident) if ident == "pub" => {
+                    // `pub(crate)` and `pub(super)` stay inside the crate
+                    public = !matches!(iter.peek(), Some(TokenTree::Group(_)));
+                    if !public {
+                        iter.next();
+                    }
+                }
+                TokenTree::Ident(ident) if ident == "macro" => match iter.next()? {
+                    TokenTree::Ident(name) => break name,
+                    _ => return None,
+                },
+                _ => return None,
+            }
+        };
+        let body: TokenStream = iter.collect();
+        let trees: Vec<TokenTree> = body.clone().into_iter().collect();
+        let transcribers = match trees.as_slice() {
+            [TokenTree::Group(_), TokenTree::Group(transcriber)] => vec![transcriber.stream()],
+            [TokenTree::Group(rules)] => transcribers(&rules.stream()),
+            _ => return None,
+        };
+        Some(Macro2 { name, public, body, transcribers })
+    }
+}
+
+/// The right-hand sides of the rules in a `macro_rules!` body: `(matcher) => { transcriber };`
+fn transcribers(body: &TokenStream) -> Vec<TokenStream> {
+    let mut transcribers = Vec::new();
+    let mut tokens = body.clone().into_iter().peekable();
+    while let Some(token) = tokens.next() {
+        if let TokenTree::Punct(punct) = &token {
+            if punct.as_char() == '=' {
+                if let Some(TokenTree::Punct(arrow)) = tokens.peek() {
+                    if arrow.as_char() == '>' {
+                        tokens.next();
+                        if let Some(TokenTree::Group(group)) = tokens.next() {
+                            transcribers.push(group.stream());
+                        }
+                    }
+                }
+            }
+        }
+    }
+    transcribers
+}
+
+/// Every `name!(...)` in a token stream, including nested ones, with its input
+fn invocations(tokens: &TokenStream) -> Vec<(String, TokenStream)> {
+    let mut found = Vec::new();
+    let mut iter = tokens.clone().into_iter().peekable();
+    while let Some(token) = iter.next() {
+        match token {
+            TokenTree::Ident(ident) => {
+                let mut lookahead = iter.clone();
+                let is_bang = matches!(lookahead.next(), Some(TokenTree::Punct(punct)) if punct.as_char() == '!');
+                if is_keyword(&ident.to_string()) {
+                    continue;
+                }
+                if let (true, Some(TokenTree::Group(group))) = (is_bang, lookahead.next()) {
+                    iter.next();
+                    iter.next();
+                    found.push((ident.to_string(), group.stream()));
+                    found.extend(invocations(&group.stream()));
+                }
+            }
+            TokenTree::Group(group) => found.extend(invocations(&group.stream())),
+            _ => {}
+        }
+    }
+    found
+}
+
+/// The `name!(...)` invocations in a token stream, each with the ones in its input
+fn call_tree(tokens: &TokenStream) -> Vec<Call> {
+    let mut calls = Vec::new();
+    let mut iter = tokens.clone().into_iter().peekable();
+    while let Some(token) = iter.next() {
+        match token {
+            TokenTree::Ident(ident) => {
+                let mut lookahead = iter.clone();
+                let is_bang = matches!(lookahead.next(), Some(TokenTree::Punct(punct)) if punct.as_char() == '!');
+                if is_keyword(&ident.to_string()) {
+                    continue;
+                }
+                if let (true, Some(TokenTree::Group(group))) = (is_bang, lookahead.next()) {
+                    iter.next();
+                    iter.next();
+                    calls.push(Call { name: ident.to_string(), nested: call_tree(&group.stream()) });
+                }
+            }
+            TokenTree::Group(group) => calls.extend(call_tree(&group.stream())),
+            _ => {}
+        }
+    }
+    calls
+}
+
+/// Keywords that may be followed by `!` and a group without being a macro: `if !(ready)`
+pub fn is_keyword(ident: &str) -> bool {
+    matches!(
+        ident,
+        "if" | "else" | "while" | "match" | "return" | "let" | "in" | "for" | "loop" | "break"
+            | "continue" | "move" | "async" | "await" | "yield" | "mut" | "ref" | "as"
+    )
+}
+
+/// Items in a macro input: separated by `,` or `;` when it has separators, otherwise
+/// one per token tree
+fn input_items(input: &TokenStream) -> usize {
+    let trees: Vec<TokenTree> = input.clone().into_iter().collect();
+    let separators = trees.iter()
+        .filter(|tree| matches!(tree, TokenTree::Punct(punct) if matches!(punct.as_char(), ',' | ';')))
+        .count();
+    if separators == 0 {
+        return trees.len();
+    }
+    let trailing = matches!(trees.last(), Some(TokenTree::Punct(punct)) if matches!(punct.as_char(), ',' | ';'));
+    separators + 1 - usize::from(trailing)
+}
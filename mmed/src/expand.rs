@@ -0,0 +1,281 @@
+//! `--expand`: expand each crate the way `cargo expand` does
+//! (`cargo rustc --profile=check -- -Zunpretty=expanded`), with rustc's hygiene data
+//! added. The hygiene data records every macro expansion together with the expansion
+//! it was produced by, which gives the real nesting depth after expansion instead of
+//! an estimate from the call sites.
+//!
+//! The expanded lines per source line (the blowup factor) of each crate and of each
+//! of its files are a cheap proxy for how much code proc-macros generate.
+
+use std::collections::{HashMap, HashSet};
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use regex::Regex;
+use trr_core::walk;
+use walkdir::WalkDir;
+
+/// Expansion results for one crate
+pub struct CrateExpansion {
+    pub name: String,
+    pub dir: PathBuf,
+    /// Deepest chain of nested macro expansions, outermost first, excluding ignored macros
+    pub deepest_chain: Vec<String>,
+    /// Lines of the crate's own sources under `src/`
+    pub source_lines: usize,
+    /// Lines of the expanded crate
+    pub expanded_lines: usize,
+    /// The files of the crate's module tree that are in the expansion
+    pub files: Vec<FileExpansion>,
+}
+
+impl CrateExpansion {
+    pub fn max_depth(&self) -> usize {
+        self.deepest_chain.len()
+    }
+
+    pub fn blowup(&self) -> f64 {
+        blowup(self.source_lines, self.expanded_lines)
+    }
+}
+
+/// One source file of an expanded crate
+pub struct FileExpansion {
+    pub path: PathBuf,
+    pub source_lines: usize,
+    /// Lines of the file's module in the expanded crate, without the modules of other files
+    pub expanded_lines: usize,
+}
+
+impl FileExpansion {
+    pub fn blowup(&self) -> f64 {
+        blowup(self.source_lines, self.expanded_lines)
+    }
+}
+
+/// Expanded lines per source line
+fn blowup(source_lines: usize, expanded_lines: usize) -> f64 {
+    if source_lines == 0 {
+        0.0
+    } else {
+        expanded_lines as f64 / source_lines as f64
+    }
+}
+
+/// Which features to expand with
+pub struct FeatureSelection {
+    pub features: Vec<String>,
+    pub all_features: bool,
+}
+
+/// Directories holding a `Cargo.toml` with a `[package]` under `dir`, skipping `target/`
+pub fn find_crates(dir: &Path) -> Vec<PathBuf> {
+    let mut crates: Vec<PathBuf> = WalkDir::new(dir)
+        .sort_by_file_name()
+        .into_iter()
+        .filter_entry(|entry| entry.file_name() != "target")
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_name() == "Cargo.toml")
+        .filter(|entry| fs::read_to_string(entry.path())
+            .map(|manifest| manifest.lines().any(|line| line.trim() == "[package]"))
+            .unwrap_or(false))
+        .filter_map(|entry| entry.path().parent().map(Path::to_path_buf))
+        .collect();
+    crates.sort();
+    crates
+}
+
+pub fn expand_crate(
+    crate_dir: &Path,
+    selection: &FeatureSelection,
+    ignored_macros: &HashSet<String>,
+) -> Result<CrateExpansion, Box<dyn Error>> {
+    let manifest = crate_dir.join("Cargo.toml");
+    let mut command = Command::new("cargo");
+    command.arg("rustc")
+        .arg("--quiet")
+        .arg("--manifest-path").arg(&manifest)
+        .arg("--profile=check");
+    // Extra rustc arguments need a single target; prefer the library
+    let root = if crate_dir.join("src/lib.rs").is_file() {
+        command.arg("--lib");
+        crate_dir.join("src/lib.rs")
+    } else {
+        crate_dir.join("src/main.rs")
+    };
+    if selection.all_features {
+        command.arg("--all-features");
+    } else if !selection.features.is_empty() {
+        command.arg("--features").arg(selection.features.join(","));
+    }
+    // `-Z` flags on a stable toolchain, as cargo expand does
+    command.env("RUSTC_BOOTSTRAP", "1")
+        .args(["--", "-Zunpretty=expanded,hygiene"]);
+
+    let output = command.output()?;
+    if !output.status.success() {
+        return Err(format!(
+            "failed to expand {}: {}",
+            crate_dir.display(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        ).into());
+    }
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let (code, hygiene) = stdout.split_once("\n/*\nExpansions:")
+        .ok_or_else(|| format!("no expansion data in the output for {}", crate_dir.display()))?;
+
+    Ok(CrateExpansion {
+        name: crate_name(&manifest).unwrap_or_else(|| crate_dir.display().to_string()),
+        dir: crate_dir.to_path_buf(),
+        deepest_chain: deepest_expansion_chain(hygiene, ignored_macros),
+        source_lines: source_lines(&crate_dir.join("src")),
+        expanded_lines: code.lines().count(),
+        files: file_expansions(&root, code),
+    })
+}
+
+/// The expanded lines of each file of the module tree rooted at `root`. Out-of-line
+/// modules are inlined in the expansion, so each file's share is the lines of its module
+/// less those of the modules of other files inside it.
+fn file_expansions(root: &Path, code: &str) -> Vec<FileExpansion> {
+    let Ok(expanded) = syn::parse_file(code) else { return Vec::new() };
+    let mut module_lines = HashMap::new();
+    expanded_module_lines(&expanded.items, &[], &mut module_lines);
+    module_lines.insert(Vec::new(), code.lines().count());
+
+    let mut files = Vec::new();
+    module_files(root, Vec::new(), &mut files);
+    let file_modules: HashSet<&[String]> = files.iter().map(|(module, _)| module.as_slice()).collect();
+    let mut own_lines: HashMap<&[String], usize> = HashMap::new();
+    for (module, _) in &files {
+        let Some(&lines) = module_lines.get(module) else { continue };
+        *own_lines.entry(module).or_insert(0) += lines;
+        // Taken out of the nearest enclosing file's module
+        if let Some(parent) = (0..module.len()).rev().map(|len| &module[..len]).find(|parent| file_modules.contains(parent)) {
+            let parent_lines = own_lines.entry(parent).or_insert(0);
+            *parent_lines = parent_lines.saturating_sub(lines);
+        }
+    }
+    files.iter()
+        .filter_map(|(module, path)| Some(FileExpansion {
+            path: path.clone(),
+            source_lines: fs::read_to_string(path).ok()?.lines().count(),
+            expanded_lines: *own_lines.get(module.as_slice())?,
+        }))
+        .collect()
+}
+
+/// Module path -> lines of every `mod` in the expanded code
+fn expanded_module_lines(items: &[syn::Item], module: &[String], lines: &mut HashMap<Vec<String>, usize>) {
+    use syn::spanned::Spanned;
+    for item in items {
+        if let syn::Item::Mod(item) = item {
+            let Some((_, items)) = &item.content else { continue };
+            let mut path = module.to_vec();
+            path.push(item.ident.to_string());
+            let span = item.span();
+            lines.insert(path.clone(), span.end().line + 1 - span.start().line);
+            expanded_module_lines(items, &path, lines);
+        }
+    }
+}
+
+/// The files of the module tree from `file`, with their module paths, following
+/// `mod name;` declarations to `name.rs` or `name/mod.rs`
+fn module_files(file: &Path, module: Vec<String>, files: &mut Vec<(Vec<String>, PathBuf)>) {
+    let Ok(source) = fs::read_to_string(file) else { return };
+    let Ok(syntax) = syn::parse_file(&source) else { return };
+    files.push((module.clone(), file.to_path_buf()));
+    let parent = file.parent().unwrap_or(Path::new(""));
+    let dir = match file.file_name().and_then(|name| name.to_str()) {
+        Some("lib.rs" | "main.rs" | "mod.rs") => parent.to_path_buf(),
+        _ => parent.join(file.file_stem().unwrap_or_default()),
+    };
+    module_declarations(&syntax.items, &dir, &module, files);
+}
+
+fn module_declarations(items: &[syn::Item], dir: &Path, module: &[String], files: &mut Vec<(Vec<String>, PathBuf)>) {
+    for item in items {
+        let syn::Item::Mod(item) = item else { continue };
+        let name = item.ident.to_string();
+        let mut path = module.to_vec();
+        path.push(name.clone());
+        match &item.content {
+            Some((_, items)) => module_declarations(items, &dir.join(&name), &path, files),
+            None => {
+                let candidates = [dir.join(format!("{}.rs", name)), dir.join(&name).join("mod.rs")];
+                if let Some(file) = candidates.iter().find(|candidate| candidate.is_file()) {
+                    module_files(file, path, files);
+                }
+            }
+        }
+    }
+}
+
+/// The `name` of the `[package]` in a manifest
+fn crate_name(manifest: &Path) -> Option<String> {
+    let content = fs::read_to_string(manifest).ok()?;
+    let name = Regex::new(r#"(?m)^\s*name\s*=\s*"([^"]+)""#).unwrap();
+    let package = content.split("[package]").nth(1)?;
+    let section = package.split("\n[").next()?;
+    name.captures(section).map(|captures| captures[1].to_string())
+}
+
+fn source_lines(dir: &Path) -> usize {
+    walk::rust_files(dir, true, &mut |_| false)
+        .unwrap_or_default()
+        .iter()
+        .filter_map(|path| fs::read_to_string(path).ok())
+        .map(|source| source.lines().count())
+        .sum()
+}
+
+/// The longest chain of macro expansions in the local crate (`crate0`) listed by
+/// `-Zunpretty=hygiene`, such as
+/// `crate0::{{expn5}}: parent: crate0::{{expn3}}, ..., kind: Macro(Bang, "inner")`.
+/// Compiler passes and ignored macros do not add to the depth.
+fn deepest_expansion_chain(hygiene: &str, ignored_macros: &HashSet<String>) -> Vec<String> {
+    let expansion = Regex::new(
+        r#"^crate0::\{\{expn(\d+)\}\}: parent: crate0::\{\{expn(\d+)\}\}, .*kind: (?:Macro\(\w+, "([^"]+)"\)|\w+)"#
+    ).unwrap();
+
+    // Expansion id -> (parent id, counted macro name)
+    let mut expansions: HashMap<usize, (usize, Option<String>)> = HashMap::new();
+    for line in hygiene.lines() {
+        if let Some(captures) = expansion.captures(line) {
+            let id: usize = captures[1].parse().unwrap_or(0);
+            let parent: usize = captures[2].parse().unwrap_or(0);
+            let name = captures.get(3)
+                .map(|name| name.as_str().to_string())
+                .filter(|name| !ignored_macros.contains(name.rsplit("::").next().unwrap_or(name)));
+            if id != 0 {
+                expansions.insert(id, (parent, name));
+            }
+        }
+    }
+
+    let mut ids: Vec<usize> = expansions.keys().copied().collect();
+    ids.sort_unstable();
+    let mut deepest = Vec::new();
+    for id in ids {
+        let mut chain = Vec::new();
+        let mut current = id;
+        // Parents always have smaller ids, so this terminates at the root
+        while let Some((parent, name)) = expansions.get(&current) {
+            if let Some(name) = name {
+                chain.push(name.clone());
+            }
+            if *parent >= current {
+                break;
+            }
+            current = *parent;
+        }
+        if chain.len() > deepest.len() {
+            chain.reverse();
+            deepest = chain;
+        }
+    }
+    deepest
+}
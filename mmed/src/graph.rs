@@ -0,0 +1,70 @@
+//! `--dot`: the macro call graph in Graphviz DOT. Each `macro_rules!` definition
+//! points to the macros its rules invoke, and each file to the macros it calls, so
+//! the shape of a project's macro architecture shows rather than a single depth.
+//! Render with `dot -Tsvg macros.dot -o macros.svg`.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::fmt::Write;
+use std::path::Path;
+
+use mmed::definitions::MacroIndex;
+use mmed::stats::MacroUsage;
+
+/// The graph of `index`'s definitions and of the macros each file in `usage` calls,
+/// leaving out `ignored` macros. File nodes are labelled relative to `root`.
+pub fn to_dot<'a>(
+    index: &MacroIndex,
+    usage: impl IntoIterator<Item = (&'a Path, &'a HashMap<String, MacroUsage>)>,
+    ignored: &HashSet<String>,
+    root: &Path,
+) -> String {
+    // Macro node -> user-defined
+    let mut macros: BTreeMap<String, bool> = BTreeMap::new();
+    let mut edges: BTreeSet<(String, String, String)> = BTreeSet::new();
+
+    for (name, definition) in &index.definitions {
+        let caller = format!("{}!", name);
+        macros.insert(caller.clone(), true);
+        for called in definition.calls.iter().filter(|called| !ignored.contains(*called)) {
+            let callee = format!("{}!", called);
+            macros.entry(callee.clone()).or_insert_with(|| index.defines(&callee));
+            edges.insert((caller.clone(), callee, String::new()));
+        }
+    }
+
+    let mut files = Vec::new();
+    for (path, usages) in usage {
+        if usages.is_empty() {
+            continue;
+        }
+        let file = path.strip_prefix(root).unwrap_or(path).display().to_string();
+        for (name, usage) in usages {
+            macros.entry(name.clone()).or_insert_with(|| index.defines(name));
+            edges.insert((format!("file:{}", file), name.clone(), usage.invocations.to_string()));
+        }
+        files.push(file);
+    }
+
+    let mut dot = String::from("digraph macros {\n    rankdir=LR;\n    node [fontname=\"monospace\"];\n");
+    for file in &files {
+        let _ = writeln!(dot, "    {} [label={}, shape=note];", quote(&format!("file:{}", file)), quote(file));
+    }
+    for (name, user_defined) in &macros {
+        let style = if *user_defined { "shape=box, style=filled, fillcolor=lightyellow" } else { "shape=ellipse" };
+        let _ = writeln!(dot, "    {} [{}];", quote(name), style);
+    }
+    for (from, to, invocations) in &edges {
+        if invocations.is_empty() {
+            let _ = writeln!(dot, "    {} -> {};", quote(from), quote(to));
+        } else {
+            let _ = writeln!(dot, "    {} -> {} [style=dashed, label={}];", quote(from), quote(to), quote(invocations));
+        }
+    }
+    dot.push_str("}\n");
+    dot
+}
+
+/// A DOT string literal
+fn quote(text: &str) -> String {
+    format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\""))
+}
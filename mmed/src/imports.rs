@@ -0,0 +1,89 @@
+//! `use` imports of a file, so an attribute or derive is reported by the path it
+//! refers to (`anchor_lang::program`, `derive(serde::Serialize)`) rather than its
+//! last segment, and a local `#[program]` is told apart from Anchor's.
+
+use std::collections::HashMap;
+
+use syn::visit::{self, Visit};
+use syn::{ItemMod, ItemUse, UseTree};
+
+/// Imports of every module in a file, keyed by module path
+#[derive(Default)]
+pub struct Imports {
+    /// Module path -> local name -> imported path
+    modules: HashMap<Vec<String>, HashMap<String, Vec<String>>>,
+    current_module: Vec<String>,
+}
+
+impl Imports {
+    pub fn collect(file: &syn::File) -> Self {
+        let mut imports = Imports::default();
+        imports.visit_file(file);
+        imports
+    }
+
+    /// Full path of `path` as written inside `module`. Paths whose first segment is
+    /// not imported by name (preludes, glob imports) are kept as written.
+    pub fn resolve(&self, module: &[String], path: &syn::Path) -> String {
+        let idents: Vec<String> = path.segments.iter().map(|segment| segment.ident.to_string()).collect();
+        let Some(first) = idents.first() else { return String::new() };
+        match self.modules.get(module).and_then(|names| names.get(first)) {
+            Some(target) => target.iter().chain(&idents[1..]).cloned().collect::<Vec<_>>().join("::"),
+            None => idents.join("::"),
+        }
+    }
+
+    fn collect_use_tree(&mut self, tree: &UseTree, mut prefix: Vec<String>) {
+        match tree {
+            UseTree::Path(use_path) => {
+                prefix.push(use_path.ident.to_string());
+                self.collect_use_tree(&use_path.tree, prefix);
+            }
+            UseTree::Name(use_name) => {
+                // `use foo::{self}` imports `foo` itself
+                let local_name = if use_name.ident == "self" {
+                    match prefix.last() {
+                        Some(last) => last.clone(),
+                        None => return,
+                    }
+                } else {
+                    prefix.push(use_name.ident.to_string());
+                    use_name.ident.to_string()
+                };
+                self.import(local_name, prefix);
+            }
+            UseTree::Rename(use_rename) => {
+                if use_rename.ident != "self" {
+                    prefix.push(use_rename.ident.to_string());
+                }
+                self.import(use_rename.rename.to_string(), prefix);
+            }
+            // The names a glob brings in are not known without the target's contents
+            UseTree::Glob(_) => {}
+            UseTree::Group(use_group) => {
+                for tree in &use_group.items {
+                    self.collect_use_tree(tree, prefix.clone());
+                }
+            }
+        }
+    }
+
+    fn import(&mut self, local_name: String, path: Vec<String>) {
+        // `use foo as _` only brings trait methods into scope
+        if local_name != "_" {
+            self.modules.entry(self.current_module.clone()).or_default().insert(local_name, path);
+        }
+    }
+}
+
+impl<'ast> Visit<'ast> for Imports {
+    fn visit_item_mod(&mut self, item: &'ast ItemMod) {
+        self.current_module.push(item.ident.to_string());
+        visit::visit_item_mod(self, item);
+        self.current_module.pop();
+    }
+
+    fn visit_item_use(&mut self, item: &'ast ItemUse) {
+        self.collect_use_tree(&item.tree, Vec::new());
+    }
+}
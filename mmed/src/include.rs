@@ -0,0 +1,57 @@
+//! `include!`: the code of the included file is part of the including one, so its
+//! macros nest at the depth of the `include!` call.
+
+use std::path::{Component, Path, PathBuf};
+
+use proc_macro2::{TokenStream, TokenTree};
+
+/// The file an `include!` with `tokens` in `file` reads: a string literal, or a
+/// `concat!` of literals and `env!("CARGO_MANIFEST_DIR")`, relative to the directory of
+/// `file`. `None` for paths only known at build time (`env!("OUT_DIR")`).
+pub fn resolve(tokens: &TokenStream, file: &Path) -> Option<PathBuf> {
+    let path = evaluate(&tokens.clone().into_iter().collect::<Vec<_>>(), file)?;
+    Some(normalize(&file.parent().unwrap_or(Path::new("")).join(path)))
+}
+
+/// `path` without `.` components and with each `..` taking out the directory before it,
+/// so an included file has one path however it is reached
+fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir if matches!(normalized.components().next_back(), Some(Component::Normal(_))) => {
+                normalized.pop();
+            }
+            component => normalized.push(component),
+        }
+    }
+    normalized
+}
+
+/// The string a literal, `concat!` or `env!("CARGO_MANIFEST_DIR")` expression evaluates to
+fn evaluate(tokens: &[TokenTree], file: &Path) -> Option<String> {
+    match tokens {
+        [TokenTree::Literal(lit)] => syn::parse_str::<syn::LitStr>(&lit.to_string()).ok().map(|lit| lit.value()),
+        [TokenTree::Ident(name), TokenTree::Punct(bang), TokenTree::Group(args)] if bang.as_char() == '!' => {
+            let args: Vec<TokenTree> = args.stream().into_iter().collect();
+            match name.to_string().as_str() {
+                "concat" => args.split(|token| matches!(token, TokenTree::Punct(punct) if punct.as_char() == ','))
+                    .filter(|arg| !arg.is_empty())
+                    .map(|arg| evaluate(arg, file))
+                    .collect(),
+                "env" if evaluate(&args, file).as_deref() == Some("CARGO_MANIFEST_DIR") => {
+                    manifest_dir(file).map(|dir| dir.display().to_string())
+                }
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+}
+
+/// The directory of the nearest `Cargo.toml` above `file`
+fn manifest_dir(file: &Path) -> Option<PathBuf> {
+    let file = file.canonicalize().ok()?;
+    file.ancestors().skip(1).find(|dir| dir.join("Cargo.toml").is_file()).map(Path::to_path_buf)
+}
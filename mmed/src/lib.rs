@@ -0,0 +1,1286 @@
+//! Macro nesting analysis behind the `mmed` binary: walks Rust sources, follows
+//! `macro_rules!` definitions, attributes and derives, and reports how deeply macro
+//! invocations nest in each file, with the chain of invocations behind the deepest
+//! nesting and warnings for expansions that cannot be counted.
+
+pub mod anchor;
+pub mod definitions;
+mod imports;
+mod include;
+pub mod proc_macros;
+pub mod stats;
+pub mod suppress;
+pub mod walk;
+
+use syn::{punctuated::Punctuated, spanned::Spanned, visit::Visit, Attribute, Token};
+use proc_macro2::{TokenStream, TokenTree};
+use std::{fmt, fs, path::{Path, PathBuf}, collections::{BTreeMap, HashMap, HashSet}, thread};
+use std::sync::{atomic::{AtomicUsize, Ordering}, OnceLock};
+use regex::Regex;
+use serde::Serialize;
+
+use definitions::{LocalExpansion, MacroIndex};
+use anchor::InstructionHandler;
+use imports::Imports;
+use proc_macros::DEFAULT_PROC_MACRO_DEPTH;
+use stats::MacroUsage;
+use suppress::{Suppressed, Suppressions};
+use walk::SourceFilter;
+use trr_core::Location;
+
+/// A warning and its message
+#[derive(Serialize)]
+pub struct Warning {
+    #[serde(flatten)]
+    pub warning_type: WarningType,
+    pub severity: Severity,
+    pub message: String,
+    /// Where the warned-about macro, attribute or string is
+    pub line: usize,
+    /// 1-based
+    pub column: usize,
+    /// The `include!`d file the warning is in, when it is not the analyzed file
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub file: Option<PathBuf>,
+}
+
+#[derive(clap::ValueEnum, serde::Deserialize, Serialize, Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Info,
+    Warn,
+    Error,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Severity::Info => "info",
+            Severity::Warn => "warn",
+            Severity::Error => "error",
+        })
+    }
+}
+
+/// Where a macro is invoked
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct CallSite {
+    #[serde(rename = "macro")]
+    pub macro_name: String,
+    pub file: PathBuf,
+    pub line: usize,
+    /// 1-based
+    pub column: usize,
+    /// For an attribute or derive: how many levels of nesting its expansion adds
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub attribute_depth: Option<usize>,
+    /// For a macro defined in the analyzed files: the macros its call expands through
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub expansion: Vec<String>,
+}
+
+/// Nested invocations, outermost first, and the depth the innermost one reaches
+#[derive(Debug, Clone, Serialize)]
+pub struct Chain {
+    pub depth: usize,
+    pub sites: Vec<CallSite>,
+}
+
+/// A function, impl block or module and the deepest nesting inside it
+#[derive(Debug, Clone, Serialize)]
+pub struct ItemDepth {
+    pub kind: ItemKind,
+    /// `module::function`, `module::Type::method`, `module::Type` or
+    /// `module::<Type as Trait>` for an impl block, `module` for a module
+    pub name: String,
+    pub line: usize,
+    /// Counting the invocations and attributes enclosing the item
+    pub max_depth: usize,
+    pub deepest_chain: Vec<CallSite>,
+}
+
+/// The attribute macros and derives stacked on one item
+#[derive(Debug, Clone, Serialize)]
+pub struct AttributeStack {
+    /// `module::Item`, `module::Type::method`, or `module::<Type as Trait>` for an impl block
+    pub item: String,
+    pub line: usize,
+    /// The counted attributes and derives, as keyed in the statistics
+    /// (`program`, `derive(anchor_lang::Accounts)`), in source order
+    pub macros: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ItemKind {
+    Fn,
+    Impl,
+    Mod,
+}
+
+impl fmt::Display for ItemKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.pad(match self {
+            ItemKind::Fn => "fn",
+            ItemKind::Impl => "impl",
+            ItemKind::Mod => "mod",
+        })
+    }
+}
+
+impl CallSite {
+    fn new(macro_name: String, file: &Path, span: proc_macro2::Span) -> Self {
+        let start = span.start();
+        CallSite {
+            macro_name,
+            file: file.to_path_buf(),
+            line: start.line,
+            column: start.column + 1,
+            attribute_depth: None,
+            expansion: Vec::new(),
+        }
+    }
+
+    pub fn location(&self) -> Location {
+        Location { file: self.file.clone(), line: self.line, column: Some(self.column) }
+    }
+}
+
+impl fmt::Display for CallSite {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.attribute_depth {
+            Some(depth) => write!(
+                f,
+                "#[{}] at {} (expands {} level{} deep)",
+                self.macro_name, self.location(), depth, if depth == 1 { "" } else { "s" }
+            ),
+            None => {
+                write!(f, "{}! at {}", self.macro_name, self.location())?;
+                if !self.expansion.is_empty() {
+                    write!(f, " (expands through {})", self.expansion.join(" → "))?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+#[derive(Debug, Hash, Eq, PartialEq, Ord, PartialOrd, Clone, Serialize)]
+#[serde(tag = "type", content = "macro", rename_all = "snake_case")]
+pub enum WarningType {
+    ProcMacro(String),
+    MacroRepetition(String),
+    StringLiteralMacro,
+}
+
+/// A warning type without the macro it names, for `--fail-on-warning` and the
+/// severity settings
+#[derive(clap::ValueEnum, serde::Deserialize, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[serde(rename_all = "kebab-case")]
+pub enum WarningKind {
+    ProcMacro,
+    MacroRepetition,
+    StringLiteralMacro,
+}
+
+impl WarningKind {
+    pub fn default_severity(self) -> Severity {
+        match self {
+            WarningKind::ProcMacro => Severity::Warn,
+            WarningKind::MacroRepetition => Severity::Warn,
+            WarningKind::StringLiteralMacro => Severity::Info,
+        }
+    }
+}
+
+/// Which warnings are reported, and at which severity
+#[derive(Clone)]
+pub struct WarningPolicy {
+    pub severities: HashMap<WarningKind, Severity>,
+    pub disabled: HashSet<WarningKind>,
+    pub min_severity: Severity,
+}
+
+impl WarningPolicy {
+    /// The severity of a reported warning, or `None` if it is disabled or below the minimum
+    pub fn severity(&self, kind: WarningKind) -> Option<Severity> {
+        let severity = self.severities.get(&kind).copied().unwrap_or_else(|| kind.default_severity());
+        (!self.disabled.contains(&kind) && severity >= self.min_severity).then_some(severity)
+    }
+}
+
+impl fmt::Display for WarningType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WarningType::ProcMacro(name) => write!(f, "Procedural macro '{}'", name),
+            WarningType::MacroRepetition(name) => write!(f, "Macro with repetition pattern '{}'", name),
+            WarningType::StringLiteralMacro => write!(f, "Macro calls in strings parsed as code"),
+        }
+    }
+}
+
+impl WarningType {
+    pub fn kind(&self) -> WarningKind {
+        match self {
+            WarningType::ProcMacro(_) => WarningKind::ProcMacro,
+            WarningType::MacroRepetition(_) => WarningKind::MacroRepetition,
+            WarningType::StringLiteralMacro => WarningKind::StringLiteralMacro,
+        }
+    }
+}
+
+struct MacroDepthVisitor {
+    max_depth: usize,
+    known_proc_macros: HashSet<String>,
+    ignored_macros: HashSet<String>,
+    proc_macro_depths: HashMap<String, usize>,
+    warnings: Vec<Warning>,
+    policy: WarningPolicy,
+    /// Visiting the attributes of a field or variant, which are derive helpers
+    /// (`#[account(mut)]`, `#[serde(skip)]`) rather than macros
+    in_helper_attributes: bool,
+    /// The file being visited: the analyzed file, or a file it `include!`s
+    file: PathBuf,
+    /// Files whose `include!` is being visited, outermost first
+    including: Vec<PathBuf>,
+    /// Invocations enclosing the current position, outermost first. Entries are only
+    /// pushed and popped around the group an invocation's input is in (`nest`), so
+    /// sibling invocations never see each other.
+    stack: Vec<CallSite>,
+    /// Levels of nesting each invocation on the stack adds; the depth is their sum
+    levels: Vec<usize>,
+    /// Inside a `macro_rules!` body, which only adds depth where the macro is called
+    in_definition: bool,
+    local_expansion: bool,
+    local_expansions: HashMap<String, LocalExpansion>,
+    /// The stack at which `max_depth` was first reached
+    deepest_chain: Vec<CallSite>,
+    /// Every innermost invocation with the invocations enclosing it, in source order
+    chains: Vec<Chain>,
+    anchor: bool,
+    /// Module path inside the file, for handler and item names
+    module: Vec<String>,
+    /// Name of the type of the enclosing impl block, for method names
+    impl_type: Option<String>,
+    /// Items with nesting inside them, in the order they end
+    items: Vec<ItemDepth>,
+    /// Items with counted attributes or derives, in source order
+    attribute_stacks: Vec<AttributeStack>,
+    /// Name of the enclosing `#[program]` module while visiting its handlers
+    program_module: Option<String>,
+    handlers: Vec<InstructionHandler>,
+    /// Structs deriving `Accounts`, with the derive's expansion depth
+    accounts_structs: Vec<(String, usize)>,
+    /// `use` imports of the file, to resolve attribute and derive paths
+    imports: Imports,
+    /// `name!` and `#[attribute]` -> its invocations in this file
+    macro_usage: HashMap<String, MacroUsage>,
+    /// Suppression comments of the file being visited
+    suppressions: Suppressions,
+    suppressed: Suppressed,
+}
+
+/// Attributes treated as proc-macros, macros left out of the depth, and how deep
+/// known proc-macros expand
+#[derive(Clone)]
+pub struct MacroLists {
+    pub proc_macros: HashSet<String>,
+    pub ignored: HashSet<String>,
+    pub proc_macro_depths: HashMap<String, usize>,
+}
+
+impl MacroLists {
+    pub fn builtin() -> Self {
+        let mut known_proc_macros = HashSet::new();
+        // Common proc macros that typically generate deep macro trees
+        known_proc_macros.insert("derive".to_string());
+        known_proc_macros.insert("proc_macro".to_string());
+        known_proc_macros.insert("proc_macro_derive".to_string());
+
+        let mut ignored_macros = HashSet::new();
+        // Standard library and compiler helper macros that should be ignored
+        // I/O and formatting macros
+        ignored_macros.insert("format_args".to_string());
+        ignored_macros.insert("print".to_string());
+        ignored_macros.insert("println".to_string());
+        ignored_macros.insert("eprint".to_string());
+        ignored_macros.insert("eprintln".to_string());
+        ignored_macros.insert("format".to_string());
+        ignored_macros.insert("write".to_string());
+        ignored_macros.insert("writeln".to_string());
+        
+        // Assertion and debugging macros
+        ignored_macros.insert("assert".to_string());
+        ignored_macros.insert("assert_eq".to_string());
+        ignored_macros.insert("assert_ne".to_string());
+        ignored_macros.insert("debug_assert".to_string());
+        ignored_macros.insert("debug_assert_eq".to_string());
+        ignored_macros.insert("debug_assert_ne".to_string());
+        ignored_macros.insert("panic".to_string());
+        ignored_macros.insert("unreachable".to_string());
+        ignored_macros.insert("unimplemented".to_string());
+        ignored_macros.insert("todo".to_string());
+        
+        // Compiler intrinsics and metadata macros
+        ignored_macros.insert("compile_error".to_string());
+        ignored_macros.insert("concat".to_string());
+        ignored_macros.insert("concat_idents".to_string());
+        ignored_macros.insert("env".to_string());
+        ignored_macros.insert("option_env".to_string());
+        ignored_macros.insert("file".to_string());
+        ignored_macros.insert("line".to_string());
+        ignored_macros.insert("column".to_string());
+        ignored_macros.insert("module_path".to_string());
+        ignored_macros.insert("stringify".to_string());
+        ignored_macros.insert("include".to_string());
+        ignored_macros.insert("include_str".to_string());
+        ignored_macros.insert("include_bytes".to_string());
+        ignored_macros.insert("cfg".to_string());
+        
+        // Type and trait helper macros
+        ignored_macros.insert("matches".to_string());
+        ignored_macros.insert("dbg".to_string());
+        ignored_macros.insert("try".to_string());
+        
+        // Standard collection macros
+        ignored_macros.insert("vec".to_string());
+
+        MacroLists {
+            proc_macros: known_proc_macros,
+            ignored: ignored_macros,
+            proc_macro_depths: proc_macros::builtin_depths(),
+        }
+    }
+
+    /// Apply depth overrides, optionally dropping the built-in table first
+    pub fn set_depths(&mut self, no_builtin: bool, depths: impl IntoIterator<Item = (String, usize)>) {
+        if no_builtin {
+            self.proc_macro_depths.clear();
+        }
+        self.proc_macro_depths.extend(depths);
+    }
+
+    /// Leave `ignore` out of the depth, then count `track` toward it, treating tracked
+    /// macros used as attributes as proc-macros
+    pub fn customize(&mut self, ignore: &[String], track: &[String]) {
+        for name in ignore {
+            self.ignored.insert(name.clone());
+            self.proc_macros.remove(name);
+        }
+        for name in track {
+            self.ignored.remove(name);
+            self.proc_macros.insert(name.clone());
+        }
+    }
+}
+
+impl MacroDepthVisitor {
+    fn new(settings: &Settings, file: &Path, imports: Imports, suppressions: Suppressions) -> Self {
+        let lists = &settings.lists;
+        MacroDepthVisitor {
+            max_depth: 0,
+            known_proc_macros: lists.proc_macros.clone(),
+            ignored_macros: lists.ignored.clone(),
+            proc_macro_depths: lists.proc_macro_depths.clone(),
+            warnings: Vec::new(),
+            policy: settings.policy.clone(),
+            in_helper_attributes: false,
+            file: file.to_path_buf(),
+            including: Vec::new(),
+            stack: Vec::new(),
+            levels: Vec::new(),
+            in_definition: false,
+            local_expansion: settings.local_expansion,
+            local_expansions: settings.local_expansions.clone(),
+            deepest_chain: Vec::new(),
+            chains: Vec::new(),
+            anchor: settings.anchor,
+            module: Vec::new(),
+            impl_type: None,
+            items: Vec::new(),
+            attribute_stacks: Vec::new(),
+            program_module: None,
+            handlers: Vec::new(),
+            accounts_structs: Vec::new(),
+            imports,
+            macro_usage: HashMap::new(),
+            suppressions,
+            suppressed: Suppressed::default(),
+        }
+    }
+
+    /// Record a warning unless the policy leaves it out
+    fn warn(&mut self, warning_type: WarningType, message: String, span: proc_macro2::Span) {
+        if let Some(severity) = self.policy.severity(warning_type.kind()) {
+            let start = span.start();
+            let file = (!self.including.is_empty()).then(|| self.file.clone());
+            self.warnings.push(Warning { warning_type, severity, message, line: start.line, column: start.column + 1, file });
+        }
+    }
+
+    /// Nesting depth at the current position
+    fn current_depth(&self) -> usize {
+        self.levels.iter().sum()
+    }
+
+    /// Run `inside` with `site` adding `levels` of nesting, for the input of an
+    /// invocation or the items under an attribute
+    fn nest(&mut self, site: CallSite, levels: usize, inside: impl FnOnce(&mut Self)) {
+        self.stack.push(site);
+        self.levels.push(levels);
+        inside(self);
+        self.levels.pop();
+        self.stack.pop();
+    }
+
+    /// Count an invocation of `name` at `span`, and visit its input with `inside` one
+    /// invocation deeper
+    fn invocation(&mut self, name: &str, span: proc_macro2::Span, inside: impl FnOnce(&mut Self)) {
+        let mut site = CallSite::new(name.to_string(), &self.file, span);
+        let levels = if self.in_definition {
+            0
+        } else if let Some(expansion) = self.local_expansions.get(name) {
+            site.expansion = expansion.chain.clone();
+            1 + expansion.depth
+        } else {
+            1
+        };
+        self.nest(site, levels, |visitor| {
+            if levels > 0 {
+                let depth = visitor.current_depth();
+                visitor.macro_usage.entry(format!("{}!", name)).or_default().record(depth);
+                visitor.record_chain(depth, visitor.current_chain());
+            }
+            inside(visitor);
+        });
+    }
+
+    /// The invocations on the stack that add depth, outermost first
+    fn current_chain(&self) -> Vec<CallSite> {
+        self.stack.iter()
+            .zip(&self.levels)
+            .filter(|(_, levels)| **levels > 0)
+            .map(|(site, _)| site.clone())
+            .collect()
+    }
+
+    /// Record a chain reaching `depth`, in place of the chain of its enclosing invocation
+    fn record_chain(&mut self, depth: usize, sites: Vec<CallSite>) {
+        if self.chains.last().is_some_and(|last| last.sites.len() < sites.len() && sites.starts_with(&last.sites)) {
+            self.chains.pop();
+        }
+        if depth > self.max_depth {
+            self.max_depth = depth;
+            self.deepest_chain = sites.clone();
+        }
+        self.chains.push(Chain { depth, sites });
+    }
+
+    fn scan_token_stream(&mut self, tokens: &TokenStream) {
+        let mut iter = tokens.clone().into_iter().peekable();
+        
+        while let Some(token) = iter.next() {
+            match token {
+                TokenTree::Ident(ident) => {
+                    let ident_str = ident.to_string();
+                    if definitions::is_keyword(&ident_str) {
+                        continue;
+                    }
+                    
+                    // Check for macro pattern: Ident + '!' + Group
+                    if let Some(TokenTree::Punct(punct)) = iter.peek() {
+                        if punct.as_char() == '!' {
+                            
+                            let mut lookahead = iter.clone();
+                            let _bang = lookahead.next(); 
+                            let next_after_bang = lookahead.next();
+
+                            let follows_group = matches!(next_after_bang, Some(TokenTree::Group(_)));
+                            if !follows_group {
+                                continue;
+                            }
+
+                            iter.next();
+                            let Some(TokenTree::Group(group)) = iter.next() else { continue };
+
+                            let is_ignored = self.ignored_macros.contains(&ident_str);
+                            let enclosing = self.stack.last().map(|site| site.macro_name.clone());
+
+                            let scan_input = |visitor: &mut Self| {
+                                // Check for repetition patterns (only warn for non-ignored macros)
+                                if !is_ignored {
+                                    let stream_str = group.stream().to_string();
+                                    if stream_str.contains("$(") && stream_str.contains(")*") {
+                                        visitor.warn(
+                                            WarningType::MacroRepetition(ident_str.clone()),
+                                            format!("Macro '{}!' contains repetition pattern - actual depth may be higher", ident_str),
+                                            ident.span(),
+                                        );
+                                    }
+                                }
+
+                                if ident_str == "include" && !visitor.in_definition {
+                                    visitor.visit_include(&group.stream());
+                                }
+
+                                // `concat!`/`stringify!` building code for the enclosing macro
+                                if let (Some(enclosing), "concat" | "stringify") = (&enclosing, ident_str.as_str()) {
+                                    visitor.scan_generated_code(&ident_str, enclosing, &group.stream(), ident.span());
+                                }
+
+                                visitor.scan_token_stream(&group.stream());
+                            };
+
+                            // Ignored macros add no depth, but their input is still scanned
+                            if is_ignored {
+                                scan_input(self);
+                            } else {
+                                self.invocation(&ident_str, ident.span(), scan_input);
+                            }
+                        }
+                    }
+                }
+                TokenTree::Group(group) => {
+                    self.scan_token_stream(&group.stream());
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Macro calls in the code a `concat!` or `stringify!` inside `enclosing!` builds:
+    /// in string literals for `concat!`, in the tokens themselves for `stringify!`
+    fn scan_generated_code(&mut self, builder: &str, enclosing: &str, tokens: &TokenStream, span: proc_macro2::Span) {
+        let code = if builder == "stringify" {
+            tokens.to_string()
+        } else {
+            string_literals(tokens).concat()
+        };
+        if let Some(called) = macro_call_in(&code) {
+            self.warn(
+                WarningType::StringLiteralMacro,
+                format!(
+                    "{}! inside '{}!' builds code calling '{}!' - its expansion is not counted",
+                    builder, enclosing, called
+                ),
+                span,
+            );
+        }
+    }
+
+    /// A string literal parsed as code (`syn::parse_str`, `TokenStream::from_str`, `.parse()`)
+    fn scan_parsed_string(&mut self, parser: &str, expr: &syn::Expr) {
+        if let syn::Expr::Lit(syn::ExprLit { lit: syn::Lit::Str(lit), .. }) = expr {
+            if let Some(called) = macro_call_in(&lit.value()) {
+                self.warn(
+                    WarningType::StringLiteralMacro,
+                    format!("String parsed by {} calls '{}!' - its expansion is not counted", parser, called),
+                    lit.span(),
+                );
+            }
+        }
+    }
+
+    /// The attribute, or each derive of a `#[derive(...)]`: its key (`path::name` or
+    /// `derive(path::Name)`), name and span
+    fn attribute_macros(&self, attr: &Attribute) -> Vec<(String, String, proc_macro2::Span)> {
+        let path = attr.path();
+        let name = path.segments.last().map(|segment| segment.ident.to_string()).unwrap_or_default();
+        if name == "derive" {
+            let derives = attr.parse_args_with(Punctuated::<syn::Path, Token![,]>::parse_terminated);
+            derives.iter().flatten()
+                .map(|derive| {
+                    let resolved = self.imports.resolve(&self.module, derive);
+                    let derive_name = resolved.rsplit("::").next().unwrap_or_default().to_string();
+                    (format!("derive({})", resolved), derive_name, derive.span())
+                })
+                .collect()
+        } else {
+            // Renamed imports are known by the name they were defined with
+            let key = self.imports.resolve(&self.module, path);
+            let name = key.rsplit("::").next().unwrap_or_default().to_string();
+            vec![(key, name, path.span())]
+        }
+    }
+
+    fn scan_attribute(&mut self, attr: &Attribute) {
+        if !self.in_helper_attributes {
+            for (key, name, span) in self.attribute_macros(attr) {
+                self.scan_proc_macro(key, &name, span);
+            }
+        }
+        
+        if let Ok(tokens) = attr.parse_args::<TokenStream>() {
+            self.scan_token_stream(&tokens);
+        }
+    }
+
+    /// Expansion depth of an attribute or derive key, or `None` when it is not counted
+    fn proc_macro_depth(&self, key: &str, name: &str) -> Option<usize> {
+        let is_derive = key.starts_with("derive(");
+        let bare_key = if is_derive { format!("derive({})", name) } else { name.to_string() };
+        if [name, key, &bare_key].iter().any(|ignored| self.ignored_macros.contains(*ignored)) {
+            return None;
+        }
+        match proc_macros::lookup(&self.proc_macro_depths, key) {
+            Some(0) => None,
+            Some(depth) => Some(depth),
+            None if self.known_proc_macros.contains(name)
+                || (is_derive && self.known_proc_macros.contains("derive")) => Some(DEFAULT_PROC_MACRO_DEPTH),
+            None => None,
+        }
+    }
+
+    /// Visit the items of a `#[program]` module one `#[program]` expansion deeper, each
+    /// public function as an instruction handler
+    fn visit_program_module(&mut self, item: &syn::ItemMod) {
+        for attr in &item.attrs {
+            self.visit_attribute(attr);
+        }
+        let Some((_, items)) = &item.content else { return };
+        let attr = item.attrs.iter()
+            .find(|attr| attr.path().segments.last().is_some_and(|segment| segment.ident == "program"));
+        let key = attr.map_or_else(|| "program".to_string(), |attr| self.imports.resolve(&self.module, attr.path()));
+        let program_depth = self.proc_macro_depth(&key, "program").unwrap_or(0);
+        let attr_span = attr.map_or_else(|| item.ident.span(), |attr| attr.path().span());
+        let mut site = CallSite::new(key, &self.file, attr_span);
+        site.attribute_depth = Some(program_depth);
+
+        self.module.push(item.ident.to_string());
+        self.measure_item(ItemKind::Mod, self.module.join("::"), item.ident.span(), |visitor| {
+            visitor.nest(site, program_depth, |visitor| {
+                let outer_program = visitor.program_module.replace(item.ident.to_string());
+                for item in items {
+                    match item {
+                        syn::Item::Fn(function) if matches!(function.vis, syn::Visibility::Public(_))
+                            && !visitor.suppressions.covers(&function.attrs, function.span().start().line) => {
+                            let path = visitor.item_name(&function.sig.ident);
+                            visitor.record_attribute_stack(path, &function.attrs, function.span());
+                            visitor.visit_handler(function);
+                        }
+                        _ => visitor.visit_item(item),
+                    }
+                }
+                visitor.program_module = outer_program;
+            });
+        });
+        self.module.pop();
+    }
+
+    /// Run `visit` measuring its own deepest nesting, from the depth and chain at the
+    /// current position, and keep the deepest for the file as well
+    fn measure(&mut self, visit: impl FnOnce(&mut Self)) -> (usize, Vec<CallSite>) {
+        let depth = self.current_depth();
+        let outer_max = std::mem::replace(&mut self.max_depth, depth);
+        let chain = self.current_chain();
+        let outer_chain = std::mem::replace(&mut self.deepest_chain, chain);
+        visit(self);
+        let depth = std::mem::replace(&mut self.max_depth, outer_max);
+        let chain = std::mem::replace(&mut self.deepest_chain, outer_chain);
+        if depth > self.max_depth {
+            self.max_depth = depth;
+            self.deepest_chain = chain.clone();
+        }
+        (depth, chain)
+    }
+
+    /// Record the attributes and derives on an item that are counted as proc-macros
+    fn record_attribute_stack(&mut self, item: String, attrs: &[Attribute], span: proc_macro2::Span) {
+        let macros: Vec<String> = attrs.iter()
+            .flat_map(|attr| self.attribute_macros(attr))
+            .filter(|(key, name, _)| self.proc_macro_depth(key, name).is_some())
+            .map(|(key, _, _)| key)
+            .collect();
+        if !macros.is_empty() {
+            self.attribute_stacks.push(AttributeStack { item, line: span.start().line, macros });
+        }
+    }
+
+    /// `module::name` of a named item, or `module::Type`/`module::<Type as Trait>` of an
+    /// impl block
+    fn item_path(&self, item: &syn::Item) -> Option<String> {
+        let ident = match item {
+            syn::Item::Const(item) => &item.ident,
+            syn::Item::Enum(item) => &item.ident,
+            syn::Item::Fn(item) => &item.sig.ident,
+            syn::Item::Mod(item) => &item.ident,
+            syn::Item::Static(item) => &item.ident,
+            syn::Item::Struct(item) => &item.ident,
+            syn::Item::Trait(item) => &item.ident,
+            syn::Item::Type(item) => &item.ident,
+            syn::Item::Union(item) => &item.ident,
+            syn::Item::Impl(item) => {
+                let mut name = self.module.clone();
+                name.push(impl_name(item));
+                return Some(name.join("::"));
+            }
+            _ => return None,
+        };
+        Some(self.item_name(ident))
+    }
+
+    /// Measure an item, recording it when anything inside it nests
+    fn measure_item(&mut self, kind: ItemKind, name: String, span: proc_macro2::Span, visit: impl FnOnce(&mut Self)) {
+        let (max_depth, deepest_chain) = self.measure(visit);
+        if max_depth > 0 {
+            self.items.push(ItemDepth { kind, name, line: span.start().line, max_depth, deepest_chain });
+        }
+    }
+
+    /// `module::function`, or `module::Type::method` inside an impl block
+    fn item_name(&self, ident: &syn::Ident) -> String {
+        let mut name = self.module.clone();
+        name.extend(self.impl_type.clone());
+        name.push(ident.to_string());
+        name.join("::")
+    }
+
+    /// Visit an instruction handler, measuring its own deepest nesting
+    fn visit_handler(&mut self, function: &syn::ItemFn) {
+        let (depth, chain) = self.measure(|visitor| visitor.visit_item_fn(function));
+        self.handlers.push(InstructionHandler {
+            name: self.item_name(&function.sig.ident),
+            file: self.file.clone(),
+            line: function.sig.ident.span().start().line,
+            accounts: trr_core::anchor::context_accounts(&function.sig),
+            depth,
+            deepest_chain: chain,
+            accounts_depth: None,
+        });
+    }
+
+    /// Visit the file an `include!` reads in place of the call, as items or as an
+    /// expression. Paths only known at build time and include cycles are skipped.
+    fn visit_include(&mut self, tokens: &TokenStream) {
+        let Some(path) = include::resolve(tokens, &self.file) else { return };
+        if path == self.file || self.including.contains(&path) {
+            return;
+        }
+        let Ok(source) = fs::read_to_string(&path) else { return };
+        let outer_file = std::mem::replace(&mut self.file, path);
+        let outer_suppressions = std::mem::replace(&mut self.suppressions, Suppressions::parse(&source));
+        self.including.push(outer_file);
+        let visit = |visitor: &mut Self| {
+            if let Ok(file) = syn::parse_file(&source) {
+                visitor.visit_file(&file);
+            } else if let Ok(expr) = syn::parse_str::<syn::Expr>(&source) {
+                visitor.visit_expr(&expr);
+            }
+        };
+        if self.suppressions.file {
+            self.visit_suppressed(visit);
+        } else {
+            visit(self);
+        }
+        self.file = self.including.pop().unwrap_or_default();
+        self.suppressions = outer_suppressions;
+    }
+
+    /// Run `visit` for a region a suppression comment covers, tallying its nesting,
+    /// invocations and warnings instead of reporting them
+    fn visit_suppressed(&mut self, visit: impl FnOnce(&mut Self)) {
+        let max_depth = std::mem::take(&mut self.max_depth);
+        let deepest_chain = std::mem::take(&mut self.deepest_chain);
+        let chains = std::mem::take(&mut self.chains);
+        let items = std::mem::take(&mut self.items);
+        let attribute_stacks = std::mem::take(&mut self.attribute_stacks);
+        let warnings = std::mem::take(&mut self.warnings);
+        let macro_usage = std::mem::take(&mut self.macro_usage);
+        visit(self);
+        self.suppressed.add(&Suppressed {
+            regions: 1,
+            invocations: self.macro_usage.values().map(|usage| usage.invocations).sum(),
+            warnings: self.warnings.len(),
+            max_depth: self.max_depth,
+        });
+        self.max_depth = max_depth;
+        self.deepest_chain = deepest_chain;
+        self.chains = chains;
+        self.items = items;
+        self.attribute_stacks = attribute_stacks;
+        self.warnings = warnings;
+        self.macro_usage = macro_usage;
+    }
+
+    /// The body of a macros 2.0 item, which like a `macro_rules!` body only adds depth
+    /// where the macro is called
+    fn visit_macro2(&mut self, tokens: &TokenStream) {
+        let Some(definition) = definitions::Macro2::parse(tokens) else { return };
+        let outer = self.in_definition;
+        self.in_definition |= self.local_expansion;
+        self.scan_token_stream(&definition.body);
+        self.in_definition = outer;
+    }
+
+    /// Count the expansion of an attribute or derive (`key` is `name` or `derive(name)`)
+    /// that is in the depth table or tracked as a proc-macro
+    fn scan_proc_macro(&mut self, key: String, name: &str, span: proc_macro2::Span) {
+        let Some(depth) = self.proc_macro_depth(&key, name) else { return };
+        let enclosing_depth = self.current_depth();
+        self.macro_usage.entry(format!("#[{}]", key)).or_default().record(enclosing_depth + depth);
+
+        // A single generated impl (std derives) is not worth a warning
+        if depth > 1 {
+            self.warn(
+                WarningType::ProcMacro(key.clone()),
+                format!("Found proc-macro attribute '{}' - counted as expanding {} levels deep", key, depth),
+                span,
+            );
+        }
+        let mut site = CallSite::new(key, &self.file, span);
+        site.attribute_depth = Some(depth);
+        let mut sites = self.current_chain();
+        sites.push(site);
+        self.record_chain(enclosing_depth + depth, sites);
+    }
+}
+
+impl<'ast> Visit<'ast> for MacroDepthVisitor {
+    fn visit_item(&mut self, item: &'ast syn::Item) {
+        let visit = |visitor: &mut Self| {
+            if let Some(path) = visitor.item_path(item) {
+                visitor.record_attribute_stack(path, suppress::item_attrs(item), item.span());
+            }
+            match item {
+                syn::Item::Verbatim(tokens) => visitor.visit_macro2(tokens),
+                _ => syn::visit::visit_item(visitor, item),
+            }
+        };
+        if self.suppressions.covers(suppress::item_attrs(item), item.span().start().line) {
+            self.visit_suppressed(visit);
+        } else {
+            visit(self);
+        }
+    }
+
+    fn visit_impl_item(&mut self, item: &'ast syn::ImplItem) {
+        let visit = |visitor: &mut Self| {
+            if let syn::ImplItem::Fn(function) = item {
+                let path = visitor.item_name(&function.sig.ident);
+                visitor.record_attribute_stack(path, &function.attrs, item.span());
+            }
+            syn::visit::visit_impl_item(visitor, item);
+        };
+        if self.suppressions.covers(suppress::impl_item_attrs(item), item.span().start().line) {
+            self.visit_suppressed(visit);
+        } else {
+            visit(self);
+        }
+    }
+
+    fn visit_trait_item(&mut self, item: &'ast syn::TraitItem) {
+        if self.suppressions.covers(suppress::trait_item_attrs(item), item.span().start().line) {
+            self.visit_suppressed(|visitor| syn::visit::visit_trait_item(visitor, item));
+        } else {
+            syn::visit::visit_trait_item(self, item);
+        }
+    }
+
+    fn visit_macro(&mut self, mac: &'ast syn::Macro) {
+        let macro_name = mac.path.segments.last().map(|ident| ident.ident.to_string());
+        
+        // Check if this macro should be ignored
+        let is_ignored = macro_name.as_ref()
+            .map(|name| self.ignored_macros.contains(name))
+            .unwrap_or(false);
+
+        // Calls in a definition's rules are counted where the macro is called
+        if self.local_expansion && macro_name.as_deref() == Some("macro_rules") {
+            let outer = std::mem::replace(&mut self.in_definition, true);
+            self.scan_token_stream(&mac.tokens);
+            self.in_definition = outer;
+            return;
+        }
+        
+        if !self.in_definition && macro_name.as_deref() == Some("include") {
+            self.visit_include(&mac.tokens);
+        }
+
+        // Ignored macros add no depth, but their input is still scanned
+        if is_ignored {
+            self.scan_token_stream(&mac.tokens);
+        } else {
+            let name = macro_name.as_deref().unwrap_or("unknown");
+            self.invocation(name, mac.path.span(), |visitor| visitor.scan_token_stream(&mac.tokens));
+        }
+    }
+
+    fn visit_attribute(&mut self, attr: &'ast Attribute) {
+        self.scan_attribute(attr);
+        syn::visit::visit_attribute(self, attr);
+    }
+
+    fn visit_expr_call(&mut self, call: &'ast syn::ExprCall) {
+        if let syn::Expr::Path(func) = &*call.func {
+            if let Some(segment) = func.path.segments.last() {
+                if matches!(segment.ident.to_string().as_str(), "parse_str" | "from_str") {
+                    if let Some(argument) = call.args.first() {
+                        self.scan_parsed_string(&segment.ident.to_string(), argument);
+                    }
+                }
+            }
+        }
+        syn::visit::visit_expr_call(self, call);
+    }
+
+    fn visit_expr_method_call(&mut self, call: &'ast syn::ExprMethodCall) {
+        if call.method == "parse" {
+            self.scan_parsed_string("parse", &call.receiver);
+        }
+        syn::visit::visit_expr_method_call(self, call);
+    }
+
+    fn visit_item_mod(&mut self, item: &'ast syn::ItemMod) {
+        if self.anchor && self.program_module.is_none() && anchor::has_attribute(&item.attrs, "program") {
+            self.visit_program_module(item);
+            return;
+        }
+        // The module's own attributes are resolved in the enclosing module
+        for attr in &item.attrs {
+            self.visit_attribute(attr);
+        }
+        self.module.push(item.ident.to_string());
+        self.measure_item(ItemKind::Mod, self.module.join("::"), item.ident.span(), |visitor| {
+            for item in item.content.iter().flat_map(|(_, items)| items) {
+                visitor.visit_item(item);
+            }
+        });
+        self.module.pop();
+    }
+
+    fn visit_item_fn(&mut self, function: &'ast syn::ItemFn) {
+        let name = self.item_name(&function.sig.ident);
+        self.measure_item(ItemKind::Fn, name, function.sig.ident.span(), |visitor| {
+            syn::visit::visit_item_fn(visitor, function);
+        });
+    }
+
+    fn visit_impl_item_fn(&mut self, function: &'ast syn::ImplItemFn) {
+        let name = self.item_name(&function.sig.ident);
+        self.measure_item(ItemKind::Fn, name, function.sig.ident.span(), |visitor| {
+            syn::visit::visit_impl_item_fn(visitor, function);
+        });
+    }
+
+    fn visit_item_impl(&mut self, item: &'ast syn::ItemImpl) {
+        let impl_type = impl_name(item);
+        let mut name = self.module.clone();
+        name.push(impl_type.clone());
+        let outer_impl = self.impl_type.replace(impl_type);
+        self.measure_item(ItemKind::Impl, name.join("::"), item.impl_token.span, |visitor| {
+            syn::visit::visit_item_impl(visitor, item);
+        });
+        self.impl_type = outer_impl;
+    }
+
+    fn visit_item_struct(&mut self, item: &'ast syn::ItemStruct) {
+        if let Some(derive) = anchor::derived_path(&item.attrs, "Accounts").filter(|_| self.anchor) {
+            let key = format!("derive({})", self.imports.resolve(&self.module, &derive));
+            if let Some(depth) = self.proc_macro_depth(&key, "Accounts") {
+                self.accounts_structs.push((item.ident.to_string(), depth));
+            }
+        }
+        syn::visit::visit_item_struct(self, item);
+    }
+
+    fn visit_field(&mut self, field: &'ast syn::Field) {
+        self.in_helper_attributes = true;
+        for attr in &field.attrs {
+            self.visit_attribute(attr);
+        }
+        self.in_helper_attributes = false;
+        self.visit_type(&field.ty);
+    }
+
+    fn visit_variant(&mut self, variant: &'ast syn::Variant) {
+        self.in_helper_attributes = true;
+        for attr in &variant.attrs {
+            self.visit_attribute(attr);
+        }
+        self.in_helper_attributes = false;
+        self.visit_fields(&variant.fields);
+        if let Some((_, discriminant)) = &variant.discriminant {
+            self.visit_expr(discriminant);
+        }
+    }
+}
+
+/// How every file is analyzed
+#[derive(Clone)]
+pub struct Settings {
+    pub lists: MacroLists,
+    pub policy: WarningPolicy,
+    pub anchor: bool,
+    /// Expand calls to the macros defined in the analyzed files instead of counting
+    /// each call as one level
+    pub local_expansion: bool,
+    /// Filled in by `analyze_dir`
+    pub local_expansions: HashMap<String, LocalExpansion>,
+}
+
+/// Run `task` on every path on `jobs` threads, returning the results in the order of `paths`
+pub fn parallel_map<T: Send>(paths: &[PathBuf], jobs: usize, task: impl Fn(&Path) -> T + Sync) -> Vec<T> {
+    let next = AtomicUsize::new(0);
+    let mut results: Vec<(usize, T)> = thread::scope(|scope| {
+        let workers: Vec<_> = (0..jobs.clamp(1, paths.len().max(1)))
+            .map(|_| scope.spawn(|| {
+                let mut results = Vec::new();
+                loop {
+                    let i = next.fetch_add(1, Ordering::Relaxed);
+                    let Some(path) = paths.get(i) else { break };
+                    results.push((i, task(path)));
+                }
+                results
+            }))
+            .collect();
+        workers.into_iter()
+            .flat_map(|worker| worker.join().expect("analysis thread panicked"))
+            .collect()
+    });
+    results.sort_by_key(|(i, _)| *i);
+    results.into_iter().map(|(_, result)| result).collect()
+}
+
+/// `Type` of an inherent impl block, `<Type as Trait>` of a trait impl
+fn impl_name(item: &syn::ItemImpl) -> String {
+    let type_name = type_name(&item.self_ty);
+    match &item.trait_ {
+        Some((_, path, _)) => {
+            let trait_name = path.segments.last().map(|segment| segment.ident.to_string()).unwrap_or_default();
+            format!("<{} as {}>", type_name, trait_name)
+        }
+        None => type_name,
+    }
+}
+
+/// The last path segment of a type, `_` for types without one
+fn type_name(ty: &syn::Type) -> String {
+    match ty {
+        syn::Type::Path(path) => path.path.segments.last().map_or_else(|| "_".to_string(), |segment| segment.ident.to_string()),
+        syn::Type::Reference(reference) => type_name(&reference.elem),
+        _ => "_".to_string(),
+    }
+}
+
+/// Contents of the string literals in a token stream, including nested groups
+fn string_literals(tokens: &TokenStream) -> Vec<String> {
+    tokens.clone().into_iter()
+        .flat_map(|token| match token {
+            TokenTree::Literal(lit) => match syn::parse_str::<syn::LitStr>(&lit.to_string()) {
+                Ok(lit) => vec![lit.value()],
+                Err(_) => Vec::new(),
+            },
+            TokenTree::Group(group) => string_literals(&group.stream()),
+            _ => Vec::new(),
+        })
+        .collect()
+}
+
+/// The first `name!(`, `name![` or `name! {` in source code, so that "Hello!" is not one
+fn macro_call_in(code: &str) -> Option<String> {
+    static MACRO_CALL: OnceLock<Regex> = OnceLock::new();
+    let macro_call = MACRO_CALL.get_or_init(|| Regex::new(r"\b([A-Za-z_][A-Za-z0-9_]*)\s*!\s*[(\[{]").unwrap());
+    macro_call.captures(code).map(|captures| captures[1].to_string())
+}
+
+/// What a file is compiled into, since macros in build scripts and tests do not end up
+/// on chain
+#[derive(clap::ValueEnum, serde::Deserialize, Serialize, Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[serde(rename_all = "kebab-case")]
+pub enum Bucket {
+    #[default]
+    Program,
+    BuildScript,
+    Test,
+}
+
+impl fmt::Display for Bucket {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.pad(match self {
+            Bucket::Program => "program",
+            Bucket::BuildScript => "build-script",
+            Bucket::Test => "test",
+        })
+    }
+}
+
+/// Results for one file
+#[derive(Serialize)]
+pub struct FileReport {
+    pub path: PathBuf,
+    /// `Program` unless the file was analyzed as part of a directory
+    pub bucket: Bucket,
+    pub max_depth: usize,
+    /// Invocations forming the deepest nesting, outermost first
+    pub deepest_chain: Vec<CallSite>,
+    /// Every innermost invocation with the invocations enclosing it, in source order
+    #[serde(skip)]
+    pub chains: Vec<Chain>,
+    /// Functions, impl blocks and modules with nesting inside them, in the order they end
+    pub items: Vec<ItemDepth>,
+    /// Items with attribute macros or derives, in source order
+    pub attribute_stacks: Vec<AttributeStack>,
+    pub warnings: Vec<Warning>,
+    #[serde(skip)]
+    pub handlers: Vec<InstructionHandler>,
+    /// Structs deriving `Accounts`, with the derive's expansion depth
+    #[serde(skip)]
+    pub accounts_structs: Vec<(String, usize)>,
+    /// `name!` and `#[attribute]` -> its invocations in the file
+    #[serde(skip)]
+    pub macro_usage: HashMap<String, MacroUsage>,
+    /// What suppression comments left out of the figures above
+    pub suppressed: Suppressed,
+}
+
+/// The `macro_rules!` definitions and call sites of a file
+pub fn collect_definitions(path: &Path) -> Result<MacroIndex, Box<dyn std::error::Error>> {
+    let source = fs::read_to_string(path)?;
+    let syntax = syn::parse_file(&source)?;
+    let mut index = MacroIndex::default();
+    index.collect(&syntax, path);
+    Ok(index)
+}
+
+/// Analyze one file, expanding calls to the macros in `settings.local_expansions`
+pub fn analyze_file(path: &Path, settings: &Settings) -> Result<FileReport, Box<dyn std::error::Error>> {
+    let source = fs::read_to_string(path)?;
+    let syntax = syn::parse_file(&source)?;
+    
+    let suppressions = Suppressions::parse(&source);
+    let whole_file = suppressions.file;
+    let mut visitor = MacroDepthVisitor::new(settings, path, Imports::collect(&syntax), suppressions);
+    if whole_file {
+        visitor.visit_suppressed(|visitor| visitor.visit_file(&syntax));
+    } else {
+        visitor.visit_file(&syntax);
+    }
+    
+    Ok(FileReport {
+        path: path.to_path_buf(),
+        bucket: Bucket::Program,
+        max_depth: visitor.max_depth,
+        deepest_chain: visitor.deepest_chain,
+        chains: visitor.chains,
+        items: visitor.items,
+        attribute_stacks: visitor.attribute_stacks,
+        warnings: visitor.warnings,
+        handlers: visitor.handlers,
+        accounts_structs: visitor.accounts_structs,
+        macro_usage: visitor.macro_usage,
+        suppressed: visitor.suppressed,
+    })
+}
+
+/// Results for every analyzed file under a directory
+pub struct DirReport {
+    /// In directory-walk order
+    pub files: Vec<FileReport>,
+    /// Files that could not be read or parsed, with the error
+    pub errors: Vec<(PathBuf, String)>,
+    /// `macro_rules!` and proc-macro definitions of all the files
+    pub macro_index: MacroIndex,
+}
+
+impl DirReport {
+    pub fn max_depth(&self) -> usize {
+        self.max_depth_excluding(&[])
+    }
+
+    /// The deepest chain of the first file reaching the maximum depth
+    pub fn deepest_chain(&self) -> &[CallSite] {
+        self.deepest_chain_excluding(&[])
+    }
+
+    /// Maximum depth of the files outside the `excluded` buckets
+    pub fn max_depth_excluding(&self, excluded: &[Bucket]) -> usize {
+        self.files.iter()
+            .filter(|report| !excluded.contains(&report.bucket))
+            .map(|report| report.max_depth)
+            .max()
+            .unwrap_or(0)
+    }
+
+    pub fn deepest_chain_excluding(&self, excluded: &[Bucket]) -> &[CallSite] {
+        let max_depth = self.max_depth_excluding(excluded);
+        self.files.iter()
+            .filter(|report| !excluded.contains(&report.bucket))
+            .find(|report| report.max_depth == max_depth)
+            .map_or(&[], |report| &report.deepest_chain)
+    }
+
+    /// Bucket -> number of files and their maximum depth, for the buckets with files
+    pub fn bucket_depths(&self) -> BTreeMap<Bucket, (usize, usize)> {
+        let mut depths: BTreeMap<Bucket, (usize, usize)> = BTreeMap::new();
+        for report in &self.files {
+            let (files, max_depth) = depths.entry(report.bucket).or_default();
+            *files += 1;
+            *max_depth = (*max_depth).max(report.max_depth);
+        }
+        depths
+    }
+}
+
+/// Analyze the Rust files under `dir` that `filter` lets through, on `jobs` threads.
+/// The definitions of every file are collected first, so calls to macros defined in
+/// other files of the same crate, or exported by another crate, can be expanded.
+pub fn analyze_dir(dir: &Path, filter: &SourceFilter, settings: &Settings, jobs: usize) -> DirReport {
+    let paths = match filter.rust_files(dir) {
+        Ok(paths) => paths,
+        Err(e) => {
+            let errors = vec![(dir.to_path_buf(), e.to_string())];
+            return DirReport { files: Vec::new(), errors, macro_index: MacroIndex::default() };
+        }
+    };
+    let crate_roots: Vec<PathBuf> = paths.iter().map(|path| walk::crate_root(path, dir)).collect();
+    let indexes = parallel_map(&paths, jobs, |path| collect_definitions(path).map_err(|e| e.to_string()));
+    let mut crate_indexes: BTreeMap<&Path, MacroIndex> = BTreeMap::new();
+    for (root, index) in crate_roots.iter().zip(indexes) {
+        if let Ok(index) = index {
+            crate_indexes.entry(root).or_default().merge(index);
+        }
+    }
+    let mut macro_index = MacroIndex::default();
+    for index in crate_indexes.values() {
+        macro_index.merge(index.clone());
+    }
+    // Settings of each crate, with the expansions of the definitions it sees
+    let crate_settings: HashMap<&Path, Settings> = crate_indexes.iter()
+        .map(|(root, index)| {
+            let mut settings = settings.clone();
+            if settings.local_expansion {
+                settings.local_expansions = macro_index.visible_from(index).local_expansions(&settings.lists.ignored);
+            }
+            (*root, settings)
+        })
+        .collect();
+
+    let results = parallel_map(&paths, jobs, |path| {
+        let root = walk::crate_root(path, dir);
+        analyze_file(path, crate_settings.get(root.as_path()).unwrap_or(settings)).map_err(|e| e.to_string())
+    });
+    let mut report = DirReport { files: Vec::new(), errors: Vec::new(), macro_index };
+    for (path, result) in paths.into_iter().zip(results) {
+        match result {
+            Ok(file) => report.files.push(FileReport { bucket: walk::bucket(&path, dir), ..file }),
+            Err(e) => report.errors.push((path, e)),
+        }
+    }
+
+    // Accounts structs may be declared in another file than their handlers
+    let accounts_depths: HashMap<String, usize> = report.files.iter()
+        .flat_map(|file| file.accounts_structs.iter().cloned())
+        .collect();
+    for handler in report.files.iter_mut().flat_map(|file| &mut file.handlers) {
+        handler.accounts_depth = handler.accounts.as_ref().and_then(|name| accounts_depths.get(name).copied());
+    }
+    report
+}
+
+/// The `n` deepest chains of all the files, deepest first and then in file order
+pub fn top_chains(files: &[FileReport], n: usize) -> Vec<&Chain> {
+    let mut chains: Vec<&Chain> = files.iter().flat_map(|file| &file.chains).collect();
+    chains.sort_by_key(|chain| std::cmp::Reverse(chain.depth));
+    chains.truncate(n);
+    chains
+}
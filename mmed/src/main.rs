@@ -1,312 +1,787 @@
-use syn::{visit::Visit, Attribute, Meta};
-use syn::__private::ToTokens;
-use proc_macro2::{TokenStream, TokenTree};
-use std::{fs, path::PathBuf, collections::HashMap, collections::HashSet};
+mod baseline;
+mod config;
+mod expand;
+mod graph;
+mod report;
+mod sarif;
+
+use std::{fs, io, path::{Path, PathBuf}, collections::{BTreeMap, HashMap, HashSet}, thread};
 use clap::Parser;
-use walkdir::WalkDir;
+use serde_json::json;
+use trr_core::git::Repository;
+use trr_core::output::{self, Format};
+
+use baseline::Baseline;
+use expand::{CrateExpansion, FeatureSelection, FileExpansion};
+use mmed::anchor::InstructionHandler;
+use mmed::definitions::DEFAULT_RECURSION_LIMIT;
+use mmed::stats::{self, Origin, SortBy};
+use mmed::suppress::Suppressed;
+use mmed::walk::SourceFilter;
+use mmed::{
+    analyze_dir, proc_macros, AttributeStack, Bucket, CallSite, FileReport, ItemDepth, MacroLists, Settings, Severity, Warning, WarningKind,
+    WarningPolicy, WarningType,
+};
+
+/// Items listed per file in text output
+const ITEMS_SHOWN: usize = 5;
+/// Files listed by expansion blowup
+const BLOWUP_FILES_SHOWN: usize = 10;
+/// Most stacked items listed
+const STACKS_SHOWN: usize = 5;
 
 #[derive(Parser, Debug)]
 #[clap(author, version, about)]
 struct Args {
-    /// Path to the directory containing Rust files to analyze
-    #[clap(short, long)]
+    /// Directory containing Rust files to analyze (relative to --repo when given)
+    #[clap(value_name = "DIR", default_value = ".")]
     dir: PathBuf,
+
+    /// The directory as an option, as earlier versions took it
+    #[clap(short = 'd', long = "dir", value_name = "DIR", conflicts_with = "dir", hide = true)]
+    dir_option: Option<PathBuf>,
+
+    /// Git repository URL to clone, or path of a local checkout
+    #[clap(long, value_name = "REPO_URL_OR_PATH")]
+    repo: Option<String>,
+
+    /// Branch, tag or commit of --repo to analyze
+    #[clap(long, value_name = "REV", requires = "repo")]
+    rev: Option<String>,
+
+    /// Also expand every crate under the directory and report the real expansion
+    /// depth and generated code size next to the source figures
+    #[clap(long)]
+    expand: bool,
+
+    /// Comma-separated features to expand with
+    #[clap(long, use_value_delimiter = true)]
+    features: Vec<String>,
+
+    /// Expand with all features enabled
+    #[clap(long)]
+    all_features: bool,
+
+    /// Output format
+    #[clap(long, value_enum, default_value = "text")]
+    format: OutputFormat,
+
+    /// Leave a macro out of the depth (repeatable)
+    #[clap(long, value_name = "NAME")]
+    ignore_macro: Vec<String>,
+
+    /// Count a macro toward the depth even if it is ignored by default, and treat it
+    /// as a proc-macro when used as an attribute (repeatable)
+    #[clap(long, value_name = "NAME")]
+    track_macro: Vec<String>,
+
+    /// Config file to use instead of the nearest mmed.toml or trr.toml
+    #[clap(long, value_name = "FILE")]
+    config: Option<PathBuf>,
+
+    /// Ignore mmed.toml and trr.toml
+    #[clap(long)]
+    no_config: bool,
+
+    /// Expansion depth of an attribute (`program`) or derive (`derive(Accounts)`),
+    /// overriding the built-in table (repeatable)
+    #[clap(long, value_name = "KEY=DEPTH", value_parser = proc_macros::parse_depth)]
+    proc_macro_depth: Vec<(String, usize)>,
+
+    /// Start from an empty proc-macro depth table
+    #[clap(long)]
+    no_builtin_depths: bool,
+
+    /// Anchor programs: nest handler macros below `#[program]` and report the depth of
+    /// each instruction handler
+    #[clap(long)]
+    anchor: bool,
+
+    /// Exit with status 1 if any file's maximum nesting depth is greater than this
+    #[clap(long, value_name = "DEPTH")]
+    fail_above: Option<usize>,
+
+    /// Leave build scripts or test files out of the overall maximum depth and
+    /// --fail-above; they are still analyzed and reported (repeatable)
+    #[clap(long, value_enum, value_name = "BUCKET")]
+    exclude_bucket: Vec<Bucket>,
+
+    /// Exit with status 1 if a warning of this type is found (repeatable)
+    #[clap(long, value_enum, value_name = "TYPE")]
+    fail_on_warning: Vec<WarningKind>,
+
+    /// List the N deepest invocation chains across all files
+    #[clap(long, value_name = "N")]
+    top: Option<usize>,
+
+    /// Save the depth and warnings of every file as a baseline
+    #[clap(long, value_name = "FILE")]
+    write_baseline: Option<PathBuf>,
+
+    /// Report the increases since a baseline; --fail-above and --fail-on-warning skip
+    /// what the baseline already had
+    #[clap(long, value_name = "FILE", conflicts_with = "baseline-rev")]
+    baseline: Option<PathBuf>,
+
+    /// Use the directory at a git revision as the baseline
+    #[clap(long, value_name = "REV")]
+    baseline_rev: Option<String>,
+
+    /// Leave out files and directories matching a glob, relative to the directory or
+    /// by name (repeatable)
+    #[clap(long, value_name = "GLOB")]
+    exclude: Vec<String>,
+
+    /// Also analyze files ignored by .gitignore
+    #[clap(long)]
+    no_gitignore: bool,
+
+    /// Only report warnings of at least this severity
+    #[clap(long, value_enum, value_name = "SEVERITY")]
+    min_severity: Option<Severity>,
+
+    /// Severity of a warning type, as TYPE=SEVERITY (repeatable)
+    #[clap(long, value_name = "TYPE=SEVERITY", value_parser = parse_warning_severity)]
+    warning_severity: Vec<(WarningKind, Severity)>,
+
+    /// Do not report warnings of this type (repeatable)
+    #[clap(long, value_enum, value_name = "TYPE")]
+    disable_warning: Vec<WarningKind>,
+
+    /// Report warnings of this type even if the config file disables them (repeatable)
+    #[clap(long, value_enum, value_name = "TYPE")]
+    enable_warning: Vec<WarningKind>,
+
+    /// Count each call to a macro defined in the analyzed files as one level, instead of
+    /// expanding it through the macros its rules invoke
+    #[clap(long)]
+    no_local_expansion: bool,
+
+    /// Number of files analyzed in parallel (default: one per CPU)
+    #[clap(short, long)]
+    jobs: Option<usize>,
+
+    /// Order of the per-macro statistics table (largest first)
+    #[clap(long, value_enum, default_value = "invocations")]
+    sort_macros: SortBy,
+
+    /// Write the macro call graph to this file in Graphviz DOT format
+    #[clap(long, value_name = "FILE")]
+    dot: Option<PathBuf>,
+
+    /// Write the files over the --fail-above limit and the warnings as SARIF results
+    #[clap(long, value_name = "FILE")]
+    sarif: Option<PathBuf>,
 }
 
-#[derive(Debug, Hash, Eq, PartialEq, Clone)]
-enum WarningType {
-    ProcMacro(String),
-    MacroRepetition(String),
-    StringLiteralMacro,
+/// The report formats all the analyzers print, and an HTML page
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum OutputFormat {
+    Report(Format),
+    Html,
 }
 
-struct MacroDepthVisitor {
-    current_depth: usize,
-    max_depth: usize,
-    current_macro: Option<String>,
-    known_proc_macros: HashSet<String>,
-    ignored_macros: HashSet<String>,
-    warnings: Vec<(WarningType, String)>,
+impl clap::ValueEnum for OutputFormat {
+    fn value_variants<'a>() -> &'a [Self] {
+        &[
+            OutputFormat::Report(Format::Text),
+            OutputFormat::Report(Format::Json),
+            OutputFormat::Report(Format::Csv),
+            OutputFormat::Report(Format::Markdown),
+            OutputFormat::Report(Format::Sarif),
+            OutputFormat::Html,
+        ]
+    }
+
+    fn to_possible_value<'a>(&self) -> Option<clap::PossibleValue<'a>> {
+        match self {
+            OutputFormat::Report(format) => format.to_possible_value(),
+            OutputFormat::Html => Some(clap::PossibleValue::new("html")),
+        }
+    }
 }
 
-impl MacroDepthVisitor {
-    fn new() -> Self {
-        let mut known_proc_macros = HashSet::new();
-        // Common proc macros that typically generate deep macro trees
-        known_proc_macros.insert("derive".to_string());
-        known_proc_macros.insert("proc_macro".to_string());
-        known_proc_macros.insert("proc_macro_derive".to_string());
-        known_proc_macros.insert("anchor_lang".to_string());
-        known_proc_macros.insert("serde".to_string());
-
-        let mut ignored_macros = HashSet::new();
-        // Standard library and compiler helper macros that should be ignored
-        // I/O and formatting macros
-        ignored_macros.insert("format_args".to_string());
-        ignored_macros.insert("print".to_string());
-        ignored_macros.insert("println".to_string());
-        ignored_macros.insert("eprint".to_string());
-        ignored_macros.insert("eprintln".to_string());
-        ignored_macros.insert("format".to_string());
-        ignored_macros.insert("write".to_string());
-        ignored_macros.insert("writeln".to_string());
-        
-        // Assertion and debugging macros
-        ignored_macros.insert("assert".to_string());
-        ignored_macros.insert("assert_eq".to_string());
-        ignored_macros.insert("assert_ne".to_string());
-        ignored_macros.insert("debug_assert".to_string());
-        ignored_macros.insert("debug_assert_eq".to_string());
-        ignored_macros.insert("debug_assert_ne".to_string());
-        ignored_macros.insert("panic".to_string());
-        ignored_macros.insert("unreachable".to_string());
-        ignored_macros.insert("unimplemented".to_string());
-        ignored_macros.insert("todo".to_string());
-        
-        // Compiler intrinsics and metadata macros
-        ignored_macros.insert("compile_error".to_string());
-        ignored_macros.insert("concat".to_string());
-        ignored_macros.insert("concat_idents".to_string());
-        ignored_macros.insert("env".to_string());
-        ignored_macros.insert("option_env".to_string());
-        ignored_macros.insert("file".to_string());
-        ignored_macros.insert("line".to_string());
-        ignored_macros.insert("column".to_string());
-        ignored_macros.insert("module_path".to_string());
-        ignored_macros.insert("stringify".to_string());
-        ignored_macros.insert("include".to_string());
-        ignored_macros.insert("include_str".to_string());
-        ignored_macros.insert("include_bytes".to_string());
-        ignored_macros.insert("cfg".to_string());
-        
-        // Type and trait helper macros
-        ignored_macros.insert("matches".to_string());
-        ignored_macros.insert("dbg".to_string());
-        ignored_macros.insert("try".to_string());
-        
-        // Standard collection macros
-        ignored_macros.insert("vec".to_string());
-
-        MacroDepthVisitor {
-            current_depth: 0,
-            max_depth: 0,
-            current_macro: None,
-            known_proc_macros,
-            ignored_macros,
-            warnings: Vec::new(),
-        }
-    }
-
-    fn scan_token_stream(&mut self, tokens: &TokenStream) {
-        let mut iter = tokens.clone().into_iter().peekable();
-        
-        while let Some(token) = iter.next() {
-            match token {
-                TokenTree::Ident(ident) => {
-                    let ident_str = ident.to_string();
-                    
-                    // Check for macro pattern: Ident + '!' + Group
-                    if let Some(TokenTree::Punct(punct)) = iter.peek() {
-                        if punct.as_char() == '!' {
-                            
-                            let mut lookahead = iter.clone();
-                            let _bang = lookahead.next(); 
-                            let next_after_bang = lookahead.next();
-
-                            let follows_group = matches!(next_after_bang, Some(TokenTree::Group(_)));
-                            if !follows_group {
-                                continue;
-                            }
-
-                            iter.next();                             
-
-                            let is_ignored = self.ignored_macros.contains(&ident_str);
-
-                            self.current_macro = Some(ident_str.clone());
-
-                            // Only increment depth if NOT in the ignore list
-                            if !is_ignored {
-                                self.current_depth += 1;
-                                self.max_depth = self.max_depth.max(self.current_depth);
-                            }
-
-                            // Process the macro body if it exists
-                            if let Some(TokenTree::Group(group)) = iter.next() {
-                                // Check for repetition patterns (only warn for non-ignored macros)
-                                if !is_ignored {
-                                    let stream_str = group.stream().to_string();
-                                    if stream_str.contains("$(") && stream_str.contains(")*") {
-                                        let macro_name = self.current_macro.as_ref().unwrap_or(&"unknown".to_string()).clone();
-                                        self.warnings.push((
-                                            WarningType::MacroRepetition(macro_name.clone()),
-                                            format!("Warning: Macro '{}!' contains repetition pattern - actual depth may be higher", macro_name)
-                                        ));
-                                    }
-                                }
-                                
-                                self.scan_token_stream(&group.stream());
-                            }
-
-                            // Only decrement depth if we incremented it
-                            if !is_ignored {
-                                self.current_depth = self.current_depth.saturating_sub(1);
-                            }
-                        }
-                    }
-                }
-                TokenTree::Group(group) => {
-                    self.scan_token_stream(&group.stream());
-                }
-                TokenTree::Literal(lit) => {
-                    // Scan string literals for potential macro calls
-                    let lit_str = lit.to_string();
-                    if lit_str.contains("!") {
-                        self.warnings.push((
-                            WarningType::StringLiteralMacro,
-                            "Note: Found '!' in string literal - might be a hidden macro call".to_string()
-                        ));
-                    }
-                }
-                _ => {}
-            }
+/// A `TYPE=SEVERITY` pair from the command line
+fn parse_warning_severity(value: &str) -> Result<(WarningKind, Severity), String> {
+    use clap::ValueEnum;
+    let (kind, severity) = value.split_once('=')
+        .ok_or_else(|| format!("expected TYPE=SEVERITY, got '{}'", value))?;
+    Ok((WarningKind::from_str(kind.trim(), false)?, Severity::from_str(severity.trim(), false)?))
+}
+
+fn print_file_report(report: &FileReport) {
+    println!("File: {}", report.path.display());
+    println!("Maximum macro nesting depth: {}", report.max_depth);
+    print_chain("Deepest nesting", &report.deepest_chain);
+    print_items(report);
+    print_suppressed(&report.suppressed);
+    
+    if !report.warnings.is_empty() {
+        println!("\nAnalysis warnings:");
+        for warning in &report.warnings {
+            println!("- [{}] {}", warning.severity, warning.message);
         }
+        println!();
     }
+}
 
-    fn scan_attribute(&mut self, attr: &Attribute) {
-        // Special handling for proc-macro attributes
-        if let Ok(meta) = attr.parse_args::<Meta>() {
-            if let Meta::List(list) = meta {
-                let path_str = list.path.to_token_stream().to_string();
-                
-                if self.known_proc_macros.contains(&path_str) {
-                    self.warnings.push((
-                        WarningType::ProcMacro(path_str.clone()),
-                        format!("Warning: Found proc-macro attribute '{}' - actual macro depth may be significantly higher", path_str)
-                    ));
-                    // Assume proc-macros typically generate at least 3 levels of macro calls
-                    self.max_depth = self.max_depth.max(3);
-                }
-            }
+/// With `--fail-above` or `--fail-on-warning`, list the files over the limits on stderr
+/// and exit with status 1. Depth and warnings a baseline already had do not count.
+fn enforce_limits(
+    reports: &[FileReport],
+    excluded: &[Bucket],
+    fail_above: Option<usize>,
+    fail_on_warning: &[WarningKind],
+    baseline: Option<(&Baseline, &Path)>,
+) {
+    let mut failed = false;
+    let since = if baseline.is_some() { " beyond the baseline" } else { "" };
+    if let Some(limit) = fail_above {
+        let offending = over_limit(reports, excluded, limit, baseline);
+        if !offending.is_empty() {
+            failed = true;
+            let header = format!("{} file(s) exceed the maximum macro nesting depth of {}{}", offending.len(), limit, since);
+            output::list_failures(&header, offending.iter().map(|report| {
+                let chain: Vec<String> = report.deepest_chain.iter().map(CallSite::to_string).collect();
+                format!("{} (depth {}): {}", report.path.display(), report.max_depth, chain.join(" → "))
+            }));
         }
-        
-        if let Ok(tokens) = attr.parse_args::<TokenStream>() {
-            self.scan_token_stream(&tokens);
+    }
+    if !fail_on_warning.is_empty() {
+        let offending: Vec<(&FileReport, &Warning)> = new_warnings(reports, baseline).into_iter()
+            .filter(|(_, warning)| fail_on_warning.contains(&warning.warning_type.kind()))
+            .collect();
+        if !offending.is_empty() {
+            failed = true;
+            let header = format!("{} warning(s) of a failing type{}", offending.len(), since);
+            output::list_failures(&header, offending.iter().map(|(report, warning)| {
+                format!("{}: [{}] {}", report.path.display(), warning.severity, warning.message)
+            }));
         }
     }
+    if failed {
+        std::process::exit(1);
+    }
+}
+
+/// The files outside the `excluded` buckets deeper than `limit` that the baseline did
+/// not already have at that depth
+fn over_limit<'a>(
+    reports: &'a [FileReport],
+    excluded: &[Bucket],
+    limit: usize,
+    baseline: Option<(&Baseline, &Path)>,
+) -> Vec<&'a FileReport> {
+    reports.iter()
+        .filter(|report| report.max_depth > limit && !excluded.contains(&report.bucket))
+        .filter(|report| !baseline.is_some_and(|(baseline, root)| baseline.covers_depth(report, root)))
+        .collect()
 }
 
-impl<'ast> Visit<'ast> for MacroDepthVisitor {
-    fn visit_macro(&mut self, mac: &'ast syn::Macro) {
-        let macro_name = mac.path.segments.last().map(|ident| ident.ident.to_string());
-        
-        // Check if this macro should be ignored
-        let is_ignored = macro_name.as_ref()
-            .map(|name| self.ignored_macros.contains(name))
-            .unwrap_or(false);
-        
-        if let Some(name) = macro_name {
-            self.current_macro = Some(name);
-        }
-        
-        // Only increment depth if NOT in the ignore list
-        if !is_ignored {
-            self.current_depth += 1;
-            self.max_depth = self.max_depth.max(self.current_depth);
-        }
-        
-        self.scan_token_stream(&mac.tokens);
-        
-        // Only decrement depth if we incremented it
-        if !is_ignored {
-            self.current_depth = self.current_depth.saturating_sub(1);
-        }
-    }
-
-    fn visit_attribute(&mut self, attr: &'ast Attribute) {
-        self.scan_attribute(attr);
-        syn::visit::visit_attribute(self, attr);
+/// The warnings of every file beyond those the baseline had, with their file
+fn new_warnings<'a>(reports: &'a [FileReport], baseline: Option<(&Baseline, &Path)>) -> Vec<(&'a FileReport, &'a Warning)> {
+    reports.iter()
+        .flat_map(|report| {
+            let warnings = match baseline {
+                Some((baseline, root)) => baseline.new_warnings(report, root),
+                None => report.warnings.iter().collect(),
+            };
+            warnings.into_iter().map(move |warning| (report, warning))
+        })
+        .collect()
+}
+
+/// The deepest functions, impl blocks and modules of a file, innermost first at equal depth
+fn print_items(report: &FileReport) {
+    let mut items: Vec<&ItemDepth> = report.items.iter().collect();
+    if items.is_empty() {
+        return;
+    }
+    items.sort_by_key(|item| std::cmp::Reverse(item.max_depth));
+    println!("Deepest items:");
+    for item in items.iter().take(ITEMS_SHOWN) {
+        println!("  {:>2}  {:<4} {} (line {})", item.max_depth, item.kind, item.name, item.line);
+    }
+    if items.len() > ITEMS_SHOWN {
+        println!("  ... and {} more (see --format json)", items.len() - ITEMS_SHOWN);
     }
 }
 
-fn analyze_file(path: &PathBuf) -> Result<(usize, Vec<(WarningType, String)>), Box<dyn std::error::Error>> {
-    let source = fs::read_to_string(path)?;
-    let syntax = syn::parse_file(&source)?;
-    
-    let mut visitor = MacroDepthVisitor::new();
-    visitor.visit_file(&syntax);
-    
-    println!("File: {}", path.display());
-    println!("Maximum macro nesting depth: {}", visitor.max_depth);
-    
-    if !visitor.warnings.is_empty() {
-        println!("\nAnalysis warnings:");
-        for (_, warning) in &visitor.warnings {
-            println!("- {}", warning);
+fn print_suppressed(suppressed: &Suppressed) {
+    if suppressed.regions > 0 {
+        println!(
+            "Suppressed by trr:ignore-macro-depth: {} region(s), {} invocations, {} warnings, max depth {}",
+            suppressed.regions, suppressed.invocations, suppressed.warnings, suppressed.max_depth
+        );
+    }
+}
+
+/// The crates and files whose expansion is largest relative to their source
+fn print_blowup(expansions: &[(CrateExpansion, usize)]) {
+    println!("\nExpansion Blowup (expanded lines per source line):");
+    if expansions.len() > 1 {
+        let mut crates: Vec<&CrateExpansion> = expansions.iter().map(|(expansion, _)| expansion).collect();
+        crates.sort_by(|a, b| b.blowup().total_cmp(&a.blowup()));
+        for expansion in crates {
+            println!("  ×{:<6.1} {} ({} → {} lines)", expansion.blowup(), expansion.name, expansion.source_lines, expansion.expanded_lines);
         }
         println!();
     }
-    
-    Ok((visitor.max_depth, visitor.warnings))
+    let mut files: Vec<&FileExpansion> = expansions.iter()
+        .flat_map(|(expansion, _)| &expansion.files)
+        .filter(|file| file.source_lines > 0)
+        .collect();
+    files.sort_by(|a, b| b.blowup().total_cmp(&a.blowup()));
+    for file in files.iter().take(BLOWUP_FILES_SHOWN) {
+        println!("  ×{:<6.1} {} ({} → {} lines)", file.blowup(), file.path.display(), file.source_lines, file.expanded_lines);
+    }
+}
+
+/// Each invocation of a chain on its own line, outermost first
+fn print_chain(title: &str, chain: &[CallSite]) {
+    if chain.is_empty() {
+        return;
+    }
+    println!("{}:", title);
+    for (i, site) in chain.iter().enumerate() {
+        println!("  {}. {}", i + 1, site);
+    }
 }
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
-    let args = Args::parse();
-    
-    let mut max_overall_depth = 0;
-    let mut files_analyzed = 0;
-    let mut all_warnings: Vec<(WarningType, String)> = Vec::new();
+    let mut args = Args::parse();
+    if let Some(dir) = args.dir_option.take() {
+        args.dir = dir;
+    }
+    let text = args.format == OutputFormat::Report(Format::Text);
+
+    let config_file = match &args.config {
+        Some(path) => Some(path.clone()),
+        None if args.no_config => None,
+        None => config::find(&std::env::current_dir()?),
+    };
+    let config = match config_file.as_deref().map(config::load).transpose() {
+        Ok(config) => config.unwrap_or_default(),
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    };
+    let mut lists = MacroLists::builtin();
+    lists.customize(&config.ignore_macros, &config.track_macros);
+    lists.customize(&args.ignore_macro, &args.track_macro);
+    lists.set_depths(config.no_builtin_depths, config.proc_macro_depths);
+    lists.set_depths(args.no_builtin_depths, args.proc_macro_depth);
+    let anchor = args.anchor || config.anchor;
+
+    let mut policy = WarningPolicy {
+        severities: config.warning_severity,
+        disabled: config.disable_warnings.into_iter().collect(),
+        min_severity: args.min_severity.or(config.min_severity).unwrap_or(Severity::Info),
+    };
+    policy.severities.extend(args.warning_severity);
+    policy.disabled.extend(args.disable_warning);
+    for kind in &args.enable_warning {
+        policy.disabled.remove(kind);
+    }
+    let settings = Settings {
+        lists,
+        policy,
+        anchor,
+        local_expansion: !(args.no_local_expansion || config.no_local_expansion),
+        local_expansions: HashMap::new(),
+    };
     
-    // Walk through all files in the directory
-    for entry in WalkDir::new(&args.dir)
-        .into_iter()
-        .filter_map(|e| e.ok())
-        .filter(|e| e.path().extension().map_or(false, |ext| ext == "rs"))
-    {
-        match analyze_file(&entry.path().to_path_buf()) {
-            Ok((depth, warnings)) => {
-                max_overall_depth = max_overall_depth.max(depth);
-                files_analyzed += 1;
-                all_warnings.extend(warnings);
-            }
+    // Walk through all files in the directory, then analyze them in parallel
+    let mut excludes = Vec::new();
+    for glob in config.exclude.iter().chain(&args.exclude) {
+        match glob::Pattern::new(glob) {
+            Ok(pattern) => excludes.push(pattern),
             Err(e) => {
-                eprintln!("Error analyzing {}: {}", entry.path().display(), e);
+                eprintln!("Invalid exclude pattern '{}': {}", glob, e);
+                std::process::exit(1);
             }
         }
     }
+    let filter = SourceFilter {
+        excludes,
+        gitignore: !(args.no_gitignore || config.no_gitignore),
+    };
+    let jobs = args.jobs.unwrap_or_else(|| thread::available_parallelism().map_or(1, |n| n.get()));
+    // Keeps a cloned repository alive until the analysis is done
+    let repository = args.repo.as_deref().map(|repo| Repository::open(repo, args.rev.as_deref())).transpose()?;
+    let dir = match &repository {
+        Some(repository) => repository.subdir(&args.dir)?,
+        None => args.dir.clone(),
+    };
+    let mut analysis = analyze_dir(&dir, &filter, &settings, jobs);
+    if text {
+        for report in &analysis.files {
+            print_file_report(report);
+        }
+    }
+    for (path, e) in &analysis.errors {
+        eprintln!("Error analyzing {}: {}", path.display(), e);
+    }
+    let handlers: Vec<InstructionHandler> = analysis.files.iter_mut()
+        .flat_map(|report| std::mem::take(&mut report.handlers))
+        .collect();
+    let reports = &analysis.files;
+    let macro_index = &analysis.macro_index;
+    let files_analyzed = reports.len();
+    let mut excluded_buckets = config.exclude_buckets;
+    excluded_buckets.extend(args.exclude_bucket);
+    let max_overall_depth = analysis.max_depth_excluding(&excluded_buckets);
+    let deepest_chain = analysis.deepest_chain_excluding(&excluded_buckets);
+    let bucket_depths = analysis.bucket_depths();
+
+    if let Some(path) = &args.write_baseline {
+        Baseline::of(reports, &dir).write(path)?;
+    }
+    let baseline = match (&args.baseline, &args.baseline_rev) {
+        (Some(path), _) => Some(Baseline::load(path)?),
+        (None, Some(revision)) => {
+            let (_checkout, dir) = trr_core::git::checkout_revision(&dir, revision)?;
+            if !dir.exists() {
+                return Err(format!("'{}' does not exist at {}", dir.display(), revision).into());
+            }
+            Some(Baseline::of(&analyze_dir(&dir, &filter, &settings, jobs).files, &dir))
+        }
+        (None, None) => None,
+    };
+    let increases = baseline.as_ref().map(|baseline| baseline.increases(reports, &dir));
+
+    let mut suppressed = Suppressed::default();
+    for report in reports {
+        suppressed.add(&report.suppressed);
+    }
+
+    let mut warning_counts: BTreeMap<WarningType, usize> = BTreeMap::new();
+    for warning in reports.iter().flat_map(|report| &report.warnings) {
+        *warning_counts.entry(warning.warning_type.clone()).or_insert(0) += 1;
+    }
     
-    println!("\nAnalysis Summary:");
-    println!("Files analyzed: {}", files_analyzed);
-    println!("Maximum macro nesting depth across all files: {}", max_overall_depth);
-    println!("Note: Standard library and compiler helper macros are excluded from depth calculation");
+    if text {
+        println!("\nAnalysis Summary:");
+        println!("Files analyzed: {}", files_analyzed);
+        if excluded_buckets.is_empty() {
+            println!("Maximum macro nesting depth across all files: {}", max_overall_depth);
+        } else {
+            let excluded: Vec<String> = excluded_buckets.iter().map(Bucket::to_string).collect();
+            println!("Maximum macro nesting depth across all files (excluding {}): {}", excluded.join(", "), max_overall_depth);
+        }
+        print_chain("Deepest nesting across all files", deepest_chain);
+        if bucket_depths.len() > 1 {
+            println!("By bucket:");
+            for (bucket, (files, max_depth)) in &bucket_depths {
+                println!("  {:<12} {} file(s), max depth {}", bucket, files, max_depth);
+            }
+        }
+        print_suppressed(&suppressed);
+        println!("Note: Standard library and compiler helper macros are excluded from depth calculation");
+    }
     
-    if !all_warnings.is_empty() {
-        let mut warning_counts: HashMap<WarningType, usize> = HashMap::new();
-        for (warning_type, _) in &all_warnings {
-            *warning_counts.entry(warning_type.clone()).or_insert(0) += 1;
+    if text && !warning_counts.is_empty() {
+        println!("\nWarning Statistics:");
+        for (warning_type, count) in &warning_counts {
+            println!("{}: {} instances", warning_type, count);
         }
 
-        println!("\nWarning Statistics:");
-        for (warning_type, count) in warning_counts {
-            match warning_type {
-                WarningType::ProcMacro(name) => {
-                    println!("Procedural macro '{}': {} instances", name, count);
-                }
-                WarningType::MacroRepetition(name) => {
-                    println!("Macro with repetition pattern '{}': {} instances", name, count);
+        println!("\nDetailed Warnings:");
+        // Each message once, in file order
+        let mut seen = HashSet::new();
+        let unique_warnings = reports.iter()
+            .flat_map(|report| &report.warnings)
+            .filter(|warning| seen.insert(&warning.message));
+        for warning in unique_warnings {
+            println!("- [{}] {}", warning.severity, warning.message);
+        }
+    }
+
+    let top_chains = args.top.map(|n| mmed::top_chains(reports, n));
+    if let (true, Some(top_chains)) = (text, &top_chains) {
+        println!("\nTop {} Deepest Macro Chains:", top_chains.len());
+        for (i, chain) in top_chains.iter().enumerate() {
+            // Including the macros a local macro expands through
+            let names: Vec<String> = chain.sites.iter()
+                .flat_map(|site| match site.attribute_depth {
+                    Some(_) => vec![format!("#[{}]", site.macro_name)],
+                    None => std::iter::once(format!("{}!", site.macro_name)).chain(site.expansion.clone()).collect(),
+                })
+                .collect();
+            println!("{}. depth {}: {}", i + 1, chain.depth, names.join(" → "));
+            for site in &chain.sites {
+                println!("     {}", site);
+            }
+        }
+    }
+
+    if text && anchor {
+        println!("\nAnchor Instruction Handlers:");
+        if handlers.is_empty() {
+            println!("No #[program] module found");
+        }
+        for handler in &handlers {
+            let accounts = match (&handler.accounts, handler.accounts_depth) {
+                (Some(name), Some(depth)) => format!(", accounts {} depth {}", name, depth),
+                (Some(name), None) => format!(", accounts {} (no #[derive(Accounts)] found)", name),
+                (None, _) => String::new(),
+            };
+            println!("{} at {}:{}: depth {}{}", handler.name, handler.file.display(), handler.line, handler.depth, accounts);
+            for (i, site) in handler.deepest_chain.iter().enumerate() {
+                println!("  {}. {}", i + 1, site);
+            }
+        }
+    }
+
+    let macro_stats = stats::aggregate(
+        reports.iter().map(|report| &report.macro_usage),
+        |usage| if macro_index.defines(usage) { Origin::UserDefined } else { Origin::External },
+        args.sort_macros,
+    );
+    let origin_stats = stats::by_origin(&macro_stats);
+    if text && !macro_stats.is_empty() {
+        let width = macro_stats.iter().map(|stats| stats.name.len()).max().unwrap_or(0).max("Macro".len());
+        println!("\nMacro Statistics:");
+        println!(
+            "{:<width$}  {:<12}  {:>11}  {:>9}  {:>5}",
+            "Macro", "Origin", "Invocations", "Max depth", "Files", width = width
+        );
+        for stats in &macro_stats {
+            println!(
+                "{:<width$}  {:<12}  {:>11}  {:>9}  {:>5}",
+                stats.name, stats.origin, stats.invocations, stats.max_depth, stats.files, width = width
+            );
+        }
+        println!("\nBy origin:");
+        for (origin, totals) in &origin_stats {
+            println!(
+                "  {}: {} macros, {} invocations, max depth {}",
+                origin, totals.macros, totals.invocations, totals.max_depth
+            );
+        }
+    }
+
+    let stacks: Vec<(&Path, &AttributeStack)> = reports.iter()
+        .flat_map(|report| report.attribute_stacks.iter().map(move |stack| (report.path.as_path(), stack)))
+        .collect();
+    let stacking = stats::stacking_distribution(stacks.iter().map(|(_, stack)| *stack));
+    let max_stack = stacking.keys().next_back().copied().unwrap_or(0);
+    let most_stacked: Vec<&(&Path, &AttributeStack)> = stacks.iter()
+        .filter(|(_, stack)| stack.macros.len() == max_stack)
+        .take(STACKS_SHOWN)
+        .collect();
+    if text && !stacking.is_empty() {
+        println!("\nAttribute Macro Stacking:");
+        for (macros, items) in &stacking {
+            println!("  {} macro{}: {} item{}", macros, if *macros == 1 { "" } else { "s" }, items, if *items == 1 { "" } else { "s" });
+        }
+        println!("Most stacked ({}):", max_stack);
+        for (file, stack) in &most_stacked {
+            println!("  {} at {}:{}: {}", stack.item, file.display(), stack.line, stack.macros.join(", "));
+        }
+    }
+
+    if let Some(dot_path) = &args.dot {
+        let usage = reports.iter().map(|report| (report.path.as_path(), &report.macro_usage));
+        let dot = graph::to_dot(macro_index, usage, &settings.lists.ignored, &dir);
+        fs::write(dot_path, dot).map_err(|e| format!("failed to write {}: {}", dot_path.display(), e))?;
+    }
+
+    let recursive_groups = macro_index.recursive_groups();
+    if text && !recursive_groups.is_empty() {
+        println!("\nRecursive macro_rules! Definitions:");
+        for group in &recursive_groups {
+            let names: Vec<String> = group.macros.iter().map(|name| format!("{}!", name)).collect();
+            let kind = if group.macros.len() == 1 { "self-recursive" } else { "mutually recursive" };
+            let depth = match group.estimated_depth {
+                Some(depth) if depth > DEFAULT_RECURSION_LIMIT => format!(
+                    "estimated recursion depth {} (over the default recursion_limit of {})",
+                    depth, DEFAULT_RECURSION_LIMIT
+                ),
+                Some(depth) => format!("estimated recursion depth {}", depth),
+                None => "never invoked".to_string(),
+            };
+            println!("{} ({}), {}", names.join(" ⇄ "), kind, depth);
+            for name in &group.macros {
+                let definition = &macro_index.definitions[name];
+                println!("  - {}! defined at {}:{}", name, definition.file.display(), definition.line);
+            }
+        }
+    }
+
+    let mut expansions = Vec::new();
+    if args.expand {
+        let selection = FeatureSelection {
+            features: args.features,
+            all_features: args.all_features,
+        };
+        let crates = expand::find_crates(&dir);
+        if crates.is_empty() {
+            eprintln!("--expand: no crate (Cargo.toml with [package]) found under {}", dir.display());
+        }
+
+        if text {
+            println!("\nMacro Expansion (source → expanded):");
+        }
+        for crate_dir in crates {
+            let expansion = match expand::expand_crate(&crate_dir, &selection, &settings.lists.ignored) {
+                Ok(expansion) => expansion,
+                Err(e) => {
+                    eprintln!("Error expanding {}: {}", crate_dir.display(), e);
+                    continue;
                 }
-                WarningType::StringLiteralMacro => {
-                    println!("Potential macro calls in string literals: {} instances", count);
+            };
+            let source_depth = reports.iter()
+                .filter(|report| report.path.starts_with(&expansion.dir))
+                .map(|report| report.max_depth)
+                .max()
+                .unwrap_or(0);
+            if text {
+                println!("\nCrate: {} ({})", expansion.name, expansion.dir.display());
+                println!("Maximum macro nesting depth: {} → {}", source_depth, expansion.max_depth());
+                if !expansion.deepest_chain.is_empty() {
+                    let chain: Vec<String> = expansion.deepest_chain.iter().map(|name| format!("{}!", name)).collect();
+                    println!("Deepest expansion: {}", chain.join(" → "));
                 }
+                println!(
+                    "Lines: {} → {} (×{:.1})",
+                    expansion.source_lines, expansion.expanded_lines, expansion.blowup()
+                );
             }
+            expansions.push((expansion, source_depth));
+        }
+        if text && !expansions.is_empty() {
+            print_blowup(&expansions);
         }
+    }
 
-        println!("\nDetailed Warnings:");
-        let unique_warnings: HashSet<_> = all_warnings.into_iter().map(|(_, msg)| msg).collect();
-        for warning in unique_warnings {
-            println!("- {}", warning);
+    if let (true, Some(increases)) = (text, &increases) {
+        let since = args.baseline_rev.as_deref().unwrap_or("the Baseline");
+        baseline::print_increases(increases, since);
+    }
+
+    if matches!(args.format, OutputFormat::Report(Format::Markdown | Format::Csv) | OutputFormat::Html) {
+        let report = report::Report {
+            target: &dir,
+            files: reports,
+            max_depth: max_overall_depth,
+            macro_stats: &macro_stats,
+            origins: &origin_stats,
+            warning_counts: &warning_counts,
+        };
+        let mut out = std::io::stdout().lock();
+        match args.format {
+            OutputFormat::Report(Format::Markdown) => report::write_markdown(&mut out, &report)?,
+            OutputFormat::Report(_) => report::write_csv(&mut out, &report)?,
+            OutputFormat::Html => report::write_html(&mut out, &report)?,
         }
     }
-    
+
+    if args.format == OutputFormat::Report(Format::Json) {
+        let recursive_macros: Vec<_> = recursive_groups.iter()
+            .map(|group| json!({
+                "macros": group.macros.iter()
+                    .map(|name| {
+                        let definition = &macro_index.definitions[name];
+                        json!({ "name": name, "file": definition.file, "line": definition.line })
+                    })
+                    .collect::<Vec<_>>(),
+                "estimated_depth": group.estimated_depth,
+            }))
+            .collect();
+        let expansions: Vec<_> = expansions.iter()
+            .map(|(expansion, source_depth)| json!({
+                "name": expansion.name,
+                "dir": expansion.dir,
+                "source_depth": source_depth,
+                "expanded_depth": expansion.max_depth(),
+                "deepest_chain": expansion.deepest_chain,
+                "source_lines": expansion.source_lines,
+                "expanded_lines": expansion.expanded_lines,
+                "blowup": expansion.blowup(),
+                "files": expansion.files.iter()
+                    .map(|file| json!({
+                        "path": file.path,
+                        "source_lines": file.source_lines,
+                        "expanded_lines": file.expanded_lines,
+                        "blowup": file.blowup(),
+                    }))
+                    .collect::<Vec<_>>(),
+            }))
+            .collect();
+        let mut output = json!({
+            "files": reports,
+            "summary": {
+                "files_analyzed": files_analyzed,
+                "max_depth": max_overall_depth,
+                "deepest_chain": deepest_chain,
+                "excluded_buckets": excluded_buckets,
+                "buckets": bucket_depths.iter()
+                    .map(|(bucket, (files, max_depth))| json!({ "bucket": bucket, "files": files, "max_depth": max_depth }))
+                    .collect::<Vec<_>>(),
+                "suppressed": suppressed,
+                "warning_counts": warning_counts.iter()
+                    .map(|(warning_type, count)| {
+                        let mut entry = serde_json::to_value(warning_type).unwrap_or_default();
+                        entry["count"] = json!(count);
+                        entry
+                    })
+                    .collect::<Vec<_>>(),
+            },
+            "macro_stats": macro_stats,
+            "macro_origins": origin_stats.iter()
+                .map(|(origin, totals)| {
+                    let mut entry = serde_json::to_value(totals).unwrap_or_default();
+                    entry["origin"] = json!(origin);
+                    entry
+                })
+                .collect::<Vec<_>>(),
+            "recursive_macros": recursive_macros,
+            "attribute_stacking": {
+                "max": max_stack,
+                "distribution": stacking.iter()
+                    .map(|(macros, items)| json!({ "macros": macros, "items": items }))
+                    .collect::<Vec<_>>(),
+                "most_stacked": most_stacked.iter()
+                    .map(|(file, stack)| json!({ "file": file, "item": stack.item, "line": stack.line, "macros": stack.macros }))
+                    .collect::<Vec<_>>(),
+            },
+        });
+        if anchor {
+            output["anchor_handlers"] = json!(handlers);
+        }
+        if args.expand {
+            output["expansion"] = json!(expansions);
+        }
+        if let Some(top_chains) = &top_chains {
+            output["top_chains"] = json!(top_chains);
+        }
+        if let Some(increases) = &increases {
+            output["baseline_increases"] = json!(increases);
+        }
+        trr_core::output::write_json(&mut io::stdout().lock(), "mmed", &output)?;
+    }
+
+    let mut fail_on_warning = config.fail_on_warning;
+    fail_on_warning.extend(args.fail_on_warning);
+    let baseline = baseline.as_ref().map(|baseline| (baseline, dir.as_path()));
+    let fail_above = args.fail_above.or(config.fail_above);
+    let sarif_output = args.format == OutputFormat::Report(Format::Sarif);
+    if args.sarif.is_some() || sarif_output {
+        let over_limit = fail_above.map(|limit| over_limit(reports, &excluded_buckets, limit, baseline)).unwrap_or_default();
+        let warnings = new_warnings(reports, baseline);
+        let base = std::env::current_dir()?;
+        if let Some(sarif_path) = &args.sarif {
+            let file = fs::File::create(sarif_path).map_err(|e| format!("failed to write {}: {}", sarif_path.display(), e))?;
+            sarif::write_sarif(io::BufWriter::new(file), &over_limit, fail_above, &warnings, &base)?;
+        }
+        if sarif_output {
+            sarif::write_sarif(io::stdout().lock(), &over_limit, fail_above, &warnings, &base)?;
+            println!();
+        }
+    }
+    enforce_limits(reports, &excluded_buckets, fail_above, &fail_on_warning, baseline);
+
     Ok(())
 }
-
@@ -0,0 +1,90 @@
+//! How deep the code generated by well-known attribute and derive macros nests, so a
+//! proc-macro counts for what it actually expands to instead of a flat guess. The
+//! figures are the nesting depths measured with `--expand` on typical uses; projects
+//! can override them or add their own.
+//!
+//! Keys are the attribute path (`anchor_lang::program`, `error_code`), or `derive(Path)`
+//! for a derive. A qualified key matches any path from the same crate with the same
+//! name (`anchor_lang::prelude::program`), and a bare name matches a path from any crate.
+
+use std::collections::HashMap;
+
+/// Depth assumed for a tracked proc-macro missing from the table
+pub const DEFAULT_PROC_MACRO_DEPTH: usize = 3;
+
+const BUILTIN_DEPTHS: &[(&str, usize)] = &[
+    // Anchor
+    ("anchor_lang::program", 5),
+    ("derive(anchor_lang::Accounts)", 4),
+    ("anchor_lang::account", 3),
+    ("anchor_lang::error_code", 3),
+    ("anchor_lang::event", 2),
+    ("anchor_lang::constant", 1),
+    ("derive(anchor_lang::InitSpace)", 2),
+    ("derive(anchor_lang::AnchorSerialize)", 2),
+    ("derive(anchor_lang::AnchorDeserialize)", 2),
+    // Borsh
+    ("derive(borsh::BorshSerialize)", 2),
+    ("derive(borsh::BorshDeserialize)", 2),
+    // Serde
+    ("derive(serde::Serialize)", 2),
+    ("derive(serde::Deserialize)", 3),
+    // std derives expand to a single impl
+    ("derive(Debug)", 1),
+    ("derive(Clone)", 1),
+    ("derive(Copy)", 1),
+    ("derive(PartialEq)", 1),
+    ("derive(Eq)", 1),
+    ("derive(PartialOrd)", 1),
+    ("derive(Ord)", 1),
+    ("derive(Hash)", 1),
+    ("derive(Default)", 1),
+];
+
+pub fn builtin_depths() -> HashMap<String, usize> {
+    BUILTIN_DEPTHS.iter().map(|(key, depth)| (key.to_string(), *depth)).collect()
+}
+
+/// Depth of the macro at `key` in `depths`, preferring the closest matching key: the
+/// same path, then the same crate and name, then a bare name. A bare `key`, whose
+/// import could not be resolved, matches a qualified entry with the same name.
+pub fn lookup(depths: &HashMap<String, usize>, key: &str) -> Option<usize> {
+    if let Some(depth) = depths.get(key) {
+        return Some(*depth);
+    }
+    let (derive, segments) = split_key(key);
+    depths.iter()
+        .filter_map(|(entry, depth)| {
+            let (entry_derive, entry_segments) = split_key(entry);
+            if entry_derive != derive || entry_segments.last() != segments.last() {
+                return None;
+            }
+            let rank = match (entry_segments.len(), segments.len()) {
+                (1, _) => 1,
+                (_, 1) => 2,
+                _ if entry_segments[0] == segments[0] => 0,
+                _ => return None,
+            };
+            Some(((rank, entry.as_str()), *depth))
+        })
+        .min()
+        .map(|(_, depth)| depth)
+}
+
+/// Whether `key` is a derive, and the segments of its path
+fn split_key(key: &str) -> (bool, Vec<&str>) {
+    let (derive, path) = match key.strip_prefix("derive(").and_then(|rest| rest.strip_suffix(')')) {
+        Some(path) => (true, path),
+        None => (false, key),
+    };
+    (derive, path.split("::").collect())
+}
+
+/// A `KEY=DEPTH` override from the command line
+pub fn parse_depth(value: &str) -> Result<(String, usize), String> {
+    let (key, depth) = value.rsplit_once('=')
+        .ok_or_else(|| format!("expected KEY=DEPTH, got '{}'", value))?;
+    let depth = depth.trim().parse()
+        .map_err(|_| format!("invalid depth '{}' for {}", depth.trim(), key.trim()))?;
+    Ok((key.trim().to_string(), depth))
+}
@@ -0,0 +1,276 @@
+//! `--format markdown` and `--format html`: a shareable report with the summary, the
+//! per-macro table, warning statistics, and the deepest chains with the source line of
+//! each invocation. `--format csv` gives one row per file instead.
+
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use mmed::stats::{MacroStats, Origin, OriginStats};
+use mmed::{CallSite, FileReport, WarningType};
+use trr_core::output::{markdown_cell, markdown_code as code, write_csv_row};
+
+/// Files whose deepest chain is listed
+const DEEPEST_FILES_SHOWN: usize = 10;
+
+/// Everything the report shows
+pub struct Report<'a> {
+    pub target: &'a Path,
+    pub files: &'a [FileReport],
+    pub max_depth: usize,
+    pub macro_stats: &'a [MacroStats],
+    pub origins: &'a BTreeMap<Origin, OriginStats>,
+    pub warning_counts: &'a BTreeMap<WarningType, usize>,
+}
+
+impl Report<'_> {
+    fn relative<'p>(&self, path: &'p Path) -> &'p Path {
+        path.strip_prefix(self.target).unwrap_or(path)
+    }
+
+    /// `check!` or `#[derive(Accounts)]`, with what it expands through
+    fn describe(&self, site: &CallSite) -> String {
+        match site.attribute_depth {
+            Some(depth) => format!("#[{}] (expands {} level{} deep)", site.macro_name, depth, if depth == 1 { "" } else { "s" }),
+            None if site.expansion.is_empty() => format!("{}!", site.macro_name),
+            None => format!("{}! (expands through {})", site.macro_name, site.expansion.join(" → ")),
+        }
+    }
+
+    fn location(&self, site: &CallSite) -> String {
+        format!("{}:{}:{}", self.relative(&site.file).display(), site.line, site.column)
+    }
+
+    /// Files with any nesting, deepest first and then in walk order, at most
+    /// `DEEPEST_FILES_SHOWN`
+    fn deepest_files(&self) -> Vec<&FileReport> {
+        let mut files: Vec<&FileReport> = self.files.iter().filter(|file| file.max_depth > 0).collect();
+        files.sort_by_key(|file| Reverse(file.max_depth));
+        files.truncate(DEEPEST_FILES_SHOWN);
+        files
+    }
+
+    fn warning_total(&self) -> usize {
+        self.warning_counts.values().sum()
+    }
+
+    /// Metric -> value rows of the summary table
+    fn summary(&self) -> Vec<(String, String)> {
+        let mut rows = vec![
+            ("Files analyzed".to_string(), self.files.len().to_string()),
+            ("Maximum nesting depth".to_string(), self.max_depth.to_string()),
+        ];
+        if let Some(file) = self.files.iter().find(|file| file.max_depth == self.max_depth && self.max_depth > 0) {
+            rows.push(("Deepest file".to_string(), self.relative(&file.path).display().to_string()));
+        }
+        for (origin, totals) in self.origins {
+            let label = format!("{} macros", origin);
+            rows.push((
+                label[..1].to_uppercase() + &label[1..],
+                format!("{} ({} invocations, max depth {})", totals.macros, totals.invocations, totals.max_depth),
+            ));
+        }
+        rows.push(("Warnings".to_string(), self.warning_total().to_string()));
+        rows
+    }
+}
+
+/// Source lines of the files a report points into, read once each
+#[derive(Default)]
+struct Snippets {
+    files: HashMap<PathBuf, Vec<String>>,
+}
+
+impl Snippets {
+    /// Line `line` (1-based) of `file`, trimmed
+    fn line(&mut self, file: &Path, line: usize) -> Option<String> {
+        let lines = self.files.entry(file.to_path_buf()).or_insert_with(|| {
+            fs::read_to_string(file).map(|source| source.lines().map(str::to_string).collect()).unwrap_or_default()
+        });
+        lines.get(line.checked_sub(1)?).map(|source| source.trim().to_string())
+    }
+}
+
+pub fn write_markdown(out: &mut impl Write, report: &Report) -> io::Result<()> {
+    let mut snippets = Snippets::default();
+
+    writeln!(out, "# Macro Nesting Report")?;
+    writeln!(out)?;
+    writeln!(out, "Target: {}", code(&report.target.display().to_string()))?;
+    writeln!(out)?;
+    writeln!(out, "## Summary")?;
+    writeln!(out)?;
+    writeln!(out, "| Metric | Value |")?;
+    writeln!(out, "| --- | ---: |")?;
+    for (metric, value) in report.summary() {
+        writeln!(out, "| {} | {} |", metric, markdown_cell(&value))?;
+    }
+
+    writeln!(out)?;
+    writeln!(out, "## Deepest Nesting")?;
+    let deepest_files = report.deepest_files();
+    if deepest_files.is_empty() {
+        writeln!(out)?;
+        writeln!(out, "No counted macro invocations found.")?;
+    }
+    for file in deepest_files {
+        writeln!(out)?;
+        writeln!(out, "### {} (depth {})", code(&report.relative(&file.path).display().to_string()), file.max_depth)?;
+        writeln!(out)?;
+        write_markdown_chain(out, report, &mut snippets, &file.deepest_chain)?;
+    }
+
+    if !report.macro_stats.is_empty() {
+        writeln!(out)?;
+        writeln!(out, "## Macros")?;
+        writeln!(out)?;
+        writeln!(out, "| Macro | Origin | Invocations | Max depth | Files |")?;
+        writeln!(out, "| --- | --- | ---: | ---: | ---: |")?;
+        for stats in report.macro_stats {
+            writeln!(
+                out,
+                "| {} | {} | {} | {} | {} |",
+                code(&stats.name), stats.origin, stats.invocations, stats.max_depth, stats.files
+            )?;
+        }
+    }
+
+    if !report.warning_counts.is_empty() {
+        writeln!(out)?;
+        writeln!(out, "## Warnings")?;
+        writeln!(out)?;
+        writeln!(out, "| Warning | Instances |")?;
+        writeln!(out, "| --- | ---: |")?;
+        for (warning_type, count) in report.warning_counts {
+            writeln!(out, "| {} | {} |", markdown_cell(&warning_type.to_string()), count)?;
+        }
+    }
+    Ok(())
+}
+
+/// One row per file, in walk order: its path relative to the target, bucket, maximum
+/// nesting depth, the macros of its deepest chain and its number of warnings
+pub fn write_csv(out: &mut impl Write, report: &Report) -> io::Result<()> {
+    write_csv_row(out, &["file", "bucket", "max_depth", "deepest_chain", "warnings"])?;
+    for file in report.files {
+        let chain: Vec<String> = file.deepest_chain.iter()
+            .map(|site| match site.attribute_depth {
+                Some(_) => format!("#[{}]", site.macro_name),
+                None => format!("{}!", site.macro_name),
+            })
+            .collect();
+        write_csv_row(out, &[
+            &report.relative(&file.path).display().to_string(),
+            &file.bucket.to_string(),
+            &file.max_depth.to_string(),
+            &chain.join(" → "),
+            &file.warnings.len().to_string(),
+        ])?;
+    }
+    Ok(())
+}
+
+/// A numbered list of the invocations, each with its source line in a code block
+fn write_markdown_chain(out: &mut impl Write, report: &Report, snippets: &mut Snippets, chain: &[CallSite]) -> io::Result<()> {
+    for (i, site) in chain.iter().enumerate() {
+        writeln!(out, "{}. {} at {}", i + 1, code(&report.describe(site)), code(&report.location(site)))?;
+        if let Some(source) = snippets.line(&site.file, site.line) {
+            writeln!(out)?;
+            writeln!(out, "   ```rust")?;
+            writeln!(out, "   {}", source)?;
+            writeln!(out, "   ```")?;
+        }
+    }
+    Ok(())
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+const STYLE: &str = "body { font-family: sans-serif; max-width: 60rem; margin: 2rem auto; padding: 0 1rem; }
+table { border-collapse: collapse; margin: 1rem 0; }
+th, td { border: 1px solid #ccc; padding: 0.3rem 0.6rem; }
+td.number { text-align: right; }
+code, pre { font-family: monospace; }
+pre { background: #f6f8fa; padding: 0.4rem 0.6rem; margin: 0.3rem 0 0.8rem; overflow-x: auto; }";
+
+pub fn write_html(out: &mut impl Write, report: &Report) -> io::Result<()> {
+    let mut snippets = Snippets::default();
+
+    writeln!(out, "<!DOCTYPE html>")?;
+    writeln!(out, "<html>")?;
+    writeln!(out, "<head>")?;
+    writeln!(out, "<meta charset=\"utf-8\">")?;
+    writeln!(out, "<title>Macro Nesting Report</title>")?;
+    writeln!(out, "<style>\n{}\n</style>", STYLE)?;
+    writeln!(out, "</head>")?;
+    writeln!(out, "<body>")?;
+    writeln!(out, "<h1>Macro Nesting Report</h1>")?;
+    writeln!(out, "<p>Target: <code>{}</code></p>", escape(&report.target.display().to_string()))?;
+
+    writeln!(out, "<h2>Summary</h2>")?;
+    writeln!(out, "<table>")?;
+    for (metric, value) in report.summary() {
+        writeln!(out, "<tr><th>{}</th><td>{}</td></tr>", escape(&metric), escape(&value))?;
+    }
+    writeln!(out, "</table>")?;
+
+    writeln!(out, "<h2>Deepest Nesting</h2>")?;
+    let deepest_files = report.deepest_files();
+    if deepest_files.is_empty() {
+        writeln!(out, "<p>No counted macro invocations found.</p>")?;
+    }
+    for file in deepest_files {
+        writeln!(
+            out,
+            "<h3><code>{}</code> (depth {})</h3>",
+            escape(&report.relative(&file.path).display().to_string()),
+            file.max_depth
+        )?;
+        writeln!(out, "<ol>")?;
+        for site in &file.deepest_chain {
+            write!(
+                out,
+                "<li><code>{}</code> at <code>{}</code>",
+                escape(&report.describe(site)),
+                escape(&report.location(site))
+            )?;
+            if let Some(source) = snippets.line(&site.file, site.line) {
+                write!(out, "<pre>{}</pre>", escape(&source))?;
+            }
+            writeln!(out, "</li>")?;
+        }
+        writeln!(out, "</ol>")?;
+    }
+
+    if !report.macro_stats.is_empty() {
+        writeln!(out, "<h2>Macros</h2>")?;
+        writeln!(out, "<table>")?;
+        writeln!(out, "<tr><th>Macro</th><th>Origin</th><th>Invocations</th><th>Max depth</th><th>Files</th></tr>")?;
+        for stats in report.macro_stats {
+            writeln!(
+                out,
+                "<tr><td><code>{}</code></td><td>{}</td><td class=\"number\">{}</td><td class=\"number\">{}</td><td class=\"number\">{}</td></tr>",
+                escape(&stats.name), stats.origin, stats.invocations, stats.max_depth, stats.files
+            )?;
+        }
+        writeln!(out, "</table>")?;
+    }
+
+    if !report.warning_counts.is_empty() {
+        writeln!(out, "<h2>Warnings</h2>")?;
+        writeln!(out, "<table>")?;
+        writeln!(out, "<tr><th>Warning</th><th>Instances</th></tr>")?;
+        for (warning_type, count) in report.warning_counts {
+            writeln!(out, "<tr><td>{}</td><td class=\"number\">{}</td></tr>", escape(&warning_type.to_string()), count)?;
+        }
+        writeln!(out, "</table>")?;
+    }
+
+    writeln!(out, "</body>")?;
+    writeln!(out, "</html>")?;
+    Ok(())
+}
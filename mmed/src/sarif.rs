@@ -0,0 +1,97 @@
+//! SARIF 2.1.0 output: one result per file whose nesting is over the `--fail-above`
+//! limit, located at the invocation that goes over it, and one per warning, located
+//! at the macro, attribute or string it is about.
+
+use std::io::{self, Write};
+use std::path::Path;
+
+use serde_json::json;
+use trr_core::sarif::{location, write_log};
+use trr_core::Location;
+
+use mmed::{CallSite, FileReport, Severity, Warning, WarningKind};
+
+const DEPTH_RULE_ID: &str = "macro-depth";
+
+/// The rules of the results, in `ruleIndex` order
+const RULES: &[(&str, &str, &str)] = &[
+    (DEPTH_RULE_ID, "MaximumMacroDepth", "Macro invocations nested deeper than the allowed maximum"),
+    ("proc-macro", "ProcMacroExpansion", "Attribute or derive whose expansion nests several levels deep"),
+    ("macro-repetition", "MacroRepetition", "Macro input with a repetition pattern that may expand deeper than counted"),
+    ("string-literal-macro", "StringLiteralMacro", "Macro call in a string parsed as code, whose expansion is not counted"),
+];
+
+fn rule_id(kind: WarningKind) -> &'static str {
+    match kind {
+        WarningKind::ProcMacro => RULES[1].0,
+        WarningKind::MacroRepetition => RULES[2].0,
+        WarningKind::StringLiteralMacro => RULES[3].0,
+    }
+}
+
+fn level(severity: Severity) -> &'static str {
+    match severity {
+        Severity::Info => "note",
+        Severity::Warn => "warning",
+        Severity::Error => "error",
+    }
+}
+
+/// `over_limit` holds the files deeper than `limit`, and `warnings` the warnings to
+/// report with the file they are in
+pub fn write_sarif(
+    out: impl Write,
+    over_limit: &[&FileReport],
+    limit: Option<usize>,
+    warnings: &[(&FileReport, &Warning)],
+    base: &Path,
+) -> io::Result<()> {
+    let mut results = Vec::new();
+    for report in over_limit {
+        let Some(innermost) = report.deepest_chain.last() else { continue };
+        let chain: Vec<String> = report.deepest_chain.iter().map(CallSite::to_string).collect();
+        let related: Vec<_> = report.deepest_chain[..report.deepest_chain.len() - 1].iter()
+            .enumerate()
+            .map(|(i, site)| {
+                let mut related = location(&site.location(), base);
+                related["id"] = json!(i);
+                related["message"] = json!({ "text": format!("enclosing {}", site.macro_name) });
+                related
+            })
+            .collect();
+        results.push(json!({
+            "ruleId": DEPTH_RULE_ID,
+            "ruleIndex": 0,
+            "level": "warning",
+            "message": {
+                "text": format!(
+                    "Macro nesting reaches depth {} (limit {}): {}",
+                    report.max_depth, limit.unwrap_or_default(), chain.join(" → ")
+                ),
+            },
+            "locations": [location(&innermost.location(), base)],
+            "relatedLocations": related,
+            "properties": { "depth": report.max_depth },
+        }));
+    }
+    for (report, warning) in warnings {
+        let rule = rule_id(warning.warning_type.kind());
+        let file = warning.file.as_ref().unwrap_or(&report.path).clone();
+        results.push(json!({
+            "ruleId": rule,
+            "ruleIndex": RULES.iter().position(|(id, _, _)| *id == rule),
+            "level": level(warning.severity),
+            "message": { "text": warning.message },
+            "locations": [location(&Location { file, line: warning.line, column: Some(warning.column) }, base)],
+        }));
+    }
+
+    let rules: Vec<_> = RULES.iter()
+        .map(|(id, name, description)| json!({
+            "id": id,
+            "name": name,
+            "shortDescription": { "text": description },
+        }))
+        .collect();
+    write_log(out, "mmed", env!("CARGO_PKG_VERSION"), rules, results)
+}
@@ -0,0 +1,127 @@
+//! Per-macro statistics across the analyzed files: how often each macro is invoked,
+//! the deepest nesting it is invoked at, and how many files use it, so the macros
+//! that dominate a codebase stand out. Macros defined in the analyzed directory are
+//! told apart from external ones, since towers of in-house macros are the ones a
+//! project can still flatten.
+
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+
+use serde::Serialize;
+
+use crate::AttributeStack;
+
+/// Invocations of one macro in one file
+#[derive(Clone, Copy, Default)]
+pub struct MacroUsage {
+    pub invocations: usize,
+    /// Deepest nesting level the macro is invoked at (1 at the top level), counting its
+    /// own expansion depth for an attribute or derive
+    pub max_depth: usize,
+}
+
+impl MacroUsage {
+    pub fn record(&mut self, depth: usize) {
+        self.invocations += 1;
+        self.max_depth = self.max_depth.max(depth);
+    }
+}
+
+/// Where a macro is defined
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Origin {
+    /// A `macro_rules!` or proc-macro defined in the analyzed directory
+    UserDefined,
+    External,
+}
+
+impl fmt::Display for Origin {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Origin::UserDefined => f.pad("user-defined"),
+            Origin::External => f.pad("external"),
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct MacroStats {
+    #[serde(rename = "macro")]
+    pub name: String,
+    pub origin: Origin,
+    pub invocations: usize,
+    pub max_depth: usize,
+    pub files: usize,
+}
+
+#[derive(clap::ValueEnum, Clone, Copy, Debug)]
+pub enum SortBy {
+    Invocations,
+    Depth,
+    Files,
+    Name,
+}
+
+/// Totals over the macros of one origin
+#[derive(Default, Serialize)]
+pub struct OriginStats {
+    pub macros: usize,
+    pub invocations: usize,
+    pub max_depth: usize,
+}
+
+/// Combine the per-file usage of every macro, sorted by `sort_by` (largest first), then name
+pub fn aggregate<'a>(
+    per_file: impl IntoIterator<Item = &'a HashMap<String, MacroUsage>>,
+    origin: impl Fn(&str) -> Origin,
+    sort_by: SortBy,
+) -> Vec<MacroStats> {
+    let mut combined: HashMap<&str, MacroStats> = HashMap::new();
+    for usages in per_file {
+        for (name, usage) in usages {
+            let stats = combined.entry(name).or_insert_with(|| MacroStats {
+                name: name.clone(),
+                origin: origin(name),
+                invocations: 0,
+                max_depth: 0,
+                files: 0,
+            });
+            stats.invocations += usage.invocations;
+            stats.max_depth = stats.max_depth.max(usage.max_depth);
+            stats.files += 1;
+        }
+    }
+
+    let mut stats: Vec<MacroStats> = combined.into_values().collect();
+    stats.sort_by(|a, b| a.name.cmp(&b.name));
+    match sort_by {
+        SortBy::Invocations => stats.sort_by_key(|stats| Reverse(stats.invocations)),
+        SortBy::Depth => stats.sort_by_key(|stats| Reverse(stats.max_depth)),
+        SortBy::Files => stats.sort_by_key(|stats| Reverse(stats.files)),
+        SortBy::Name => {}
+    }
+    stats
+}
+
+/// Macro count, invocations and deepest invocation of each origin
+pub fn by_origin(stats: &[MacroStats]) -> BTreeMap<Origin, OriginStats> {
+    let mut totals: BTreeMap<Origin, OriginStats> = BTreeMap::new();
+    for stats in stats {
+        let total = totals.entry(stats.origin).or_default();
+        total.macros += 1;
+        total.invocations += stats.invocations;
+        total.max_depth = total.max_depth.max(stats.max_depth);
+    }
+    totals
+}
+
+/// Number of stacked attribute macros and derives -> items with that many
+pub fn stacking_distribution<'a>(stacks: impl IntoIterator<Item = &'a AttributeStack>) -> BTreeMap<usize, usize> {
+    let mut distribution = BTreeMap::new();
+    for stack in stacks {
+        *distribution.entry(stack.macros.len()).or_insert(0) += 1;
+    }
+    distribution
+}
@@ -0,0 +1,127 @@
+//! Suppression comments: `// trr:ignore-macro-depth` on the lines above an item (or
+//! among its attributes) leaves the item out of the depth and warnings, and
+//! `// trr:ignore-macro-depth-file` anywhere in a file leaves out the whole file. What
+//! they leave out is still tallied, so suppressions can be audited.
+
+use std::sync::OnceLock;
+
+use regex::Regex;
+use serde::Serialize;
+use syn::spanned::Spanned;
+use syn::Attribute;
+
+/// What suppression comments left out of a file's results
+#[derive(Debug, Default, Clone, Serialize)]
+pub struct Suppressed {
+    /// Suppressed items, and the file itself for a file-level comment
+    pub regions: usize,
+    /// Macro invocations and counted attributes inside them
+    pub invocations: usize,
+    pub warnings: usize,
+    /// Deepest nesting inside them
+    pub max_depth: usize,
+}
+
+impl Suppressed {
+    pub fn add(&mut self, other: &Suppressed) {
+        self.regions += other.regions;
+        self.invocations += other.invocations;
+        self.warnings += other.warnings;
+        self.max_depth = self.max_depth.max(other.max_depth);
+    }
+}
+
+/// The suppression comments of one source file
+#[derive(Default)]
+pub struct Suppressions {
+    /// Lines (1-based) with an item-level comment
+    item_lines: Vec<usize>,
+    pub file: bool,
+    /// Whether each line is blank or only a comment
+    trivia: Vec<bool>,
+}
+
+impl Suppressions {
+    pub fn parse(source: &str) -> Self {
+        static DIRECTIVE: OnceLock<Regex> = OnceLock::new();
+        let directive = DIRECTIVE.get_or_init(|| {
+            Regex::new(r"^\s*//[/!]?\s*trr:ignore-macro-depth(-file)?([\s:]|$)").unwrap()
+        });
+        let mut suppressions = Suppressions::default();
+        for (i, line) in source.lines().enumerate() {
+            let trimmed = line.trim_start();
+            suppressions.trivia.push(trimmed.is_empty() || trimmed.starts_with("//"));
+            match directive.captures(line) {
+                Some(captures) if captures.get(1).is_some() => suppressions.file = true,
+                Some(_) => suppressions.item_lines.push(i + 1),
+                None => {}
+            }
+        }
+        suppressions
+    }
+
+    fn is_trivia(&self, line: usize) -> bool {
+        line.checked_sub(1).and_then(|i| self.trivia.get(i)).copied().unwrap_or(false)
+    }
+
+    /// Whether an item starting at `start` (its first attribute or token) with `attrs`
+    /// has a comment in the comments and blank lines above it or among its attributes
+    pub fn covers(&self, attrs: &[Attribute], start: usize) -> bool {
+        if self.item_lines.is_empty() {
+            return false;
+        }
+        let mut first = start;
+        while first > 1 && self.is_trivia(first - 1) {
+            first -= 1;
+        }
+        let mut last = attrs.iter().map(|attr| attr.span().end().line).max().unwrap_or(start - 1);
+        while self.is_trivia(last + 1) {
+            last += 1;
+        }
+        self.item_lines.iter().any(|line| (first..=last).contains(line))
+    }
+}
+
+pub fn item_attrs(item: &syn::Item) -> &[Attribute] {
+    use syn::Item;
+    match item {
+        Item::Const(item) => &item.attrs,
+        Item::Enum(item) => &item.attrs,
+        Item::ExternCrate(item) => &item.attrs,
+        Item::Fn(item) => &item.attrs,
+        Item::ForeignMod(item) => &item.attrs,
+        Item::Impl(item) => &item.attrs,
+        Item::Macro(item) => &item.attrs,
+        Item::Mod(item) => &item.attrs,
+        Item::Static(item) => &item.attrs,
+        Item::Struct(item) => &item.attrs,
+        Item::Trait(item) => &item.attrs,
+        Item::TraitAlias(item) => &item.attrs,
+        Item::Type(item) => &item.attrs,
+        Item::Union(item) => &item.attrs,
+        Item::Use(item) => &item.attrs,
+        _ => &[],
+    }
+}
+
+pub fn impl_item_attrs(item: &syn::ImplItem) -> &[Attribute] {
+    use syn::ImplItem;
+    match item {
+        ImplItem::Const(item) => &item.attrs,
+        ImplItem::Fn(item) => &item.attrs,
+        ImplItem::Type(item) => &item.attrs,
+        ImplItem::Macro(item) => &item.attrs,
+        _ => &[],
+    }
+}
+
+pub fn trait_item_attrs(item: &syn::TraitItem) -> &[Attribute] {
+    use syn::TraitItem;
+    match item {
+        TraitItem::Const(item) => &item.attrs,
+        TraitItem::Fn(item) => &item.attrs,
+        TraitItem::Type(item) => &item.attrs,
+        TraitItem::Macro(item) => &item.attrs,
+        _ => &[],
+    }
+}
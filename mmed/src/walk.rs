@@ -0,0 +1,35 @@
+//! Where the analyzed files sit in their crates. The walk itself, leaving out
+//! `--exclude` globs and `.gitignore`d files, is shared with the other tools.
+
+use std::path::{Path, PathBuf};
+
+pub use trr_core::walk::SourceFilter;
+
+use crate::Bucket;
+
+/// The directory of the crate `file` belongs to: its nearest ancestor under `dir` with
+/// a `Cargo.toml`, or `dir` itself
+pub fn crate_root(file: &Path, dir: &Path) -> PathBuf {
+    file.ancestors()
+        .skip(1)
+        .take_while(|ancestor| ancestor.starts_with(dir))
+        .find(|ancestor| ancestor.join("Cargo.toml").is_file())
+        .unwrap_or(dir)
+        .to_path_buf()
+}
+
+/// The bucket of `file` under `dir`: the `build.rs` of a crate is its build script, and
+/// files under a crate's `tests/` or `benches/`, or named `tests.rs` or `test.rs`, are tests
+pub fn bucket(file: &Path, dir: &Path) -> Bucket {
+    let root = crate_root(file, dir);
+    let relative = file.strip_prefix(&root).unwrap_or(file);
+    if relative == Path::new("build.rs") {
+        Bucket::BuildScript
+    } else if relative.iter().next().is_some_and(|first| first == "tests" || first == "benches")
+        || file.file_name().is_some_and(|name| name == "tests.rs" || name == "test.rs")
+    {
+        Bucket::Test
+    } else {
+        Bucket::Program
+    }
+}
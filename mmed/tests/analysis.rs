@@ -0,0 +1,229 @@
+// Runs the mmed library over the fixtures in tests/fixtures and checks the depths,
+// chains and warnings it reports.
+
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+use mmed::walk::SourceFilter;
+use mmed::{analyze_dir, analyze_file, top_chains, Bucket, DirReport, MacroLists, Settings, Severity, WarningPolicy, WarningType};
+
+fn fixture(name: &str) -> PathBuf {
+    Path::new(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures")).join(name)
+}
+
+fn settings(local_expansion: bool) -> Settings {
+    Settings {
+        lists: MacroLists::builtin(),
+        policy: WarningPolicy {
+            severities: HashMap::new(),
+            disabled: HashSet::new(),
+            min_severity: Severity::Info,
+        },
+        anchor: false,
+        local_expansion,
+        local_expansions: HashMap::new(),
+    }
+}
+
+fn analyze(name: &str, local_expansion: bool) -> DirReport {
+    let filter = SourceFilter { excludes: Vec::new(), gitignore: false };
+    let report = analyze_dir(&fixture(name), &filter, &settings(local_expansion), 2);
+    assert!(report.errors.is_empty(), "errors: {:?}", report.errors);
+    report
+}
+
+#[test]
+fn local_macros_expand_through_their_definitions() {
+    let report = analyze("tower", true);
+    assert_eq!(report.files.len(), 2);
+    assert_eq!(report.max_depth(), 4);
+
+    let chain = report.deepest_chain();
+    assert_eq!(chain.len(), 1);
+    assert_eq!(chain[0].macro_name, "check");
+    assert_eq!(chain[0].expansion, ["require!", "log!", "emit!"]);
+    assert!(chain[0].file.ends_with("src/lib.rs"));
+    assert_eq!((chain[0].line, chain[0].column), (11, 5));
+}
+
+#[test]
+fn without_local_expansion_each_call_is_one_level() {
+    let report = analyze("tower", false);
+    let lib = report.files.iter().find(|file| file.path.ends_with("src/lib.rs")).unwrap();
+    // The serde derive is deeper than the single `check!` call
+    assert_eq!(lib.max_depth, 3);
+    assert_eq!(lib.deepest_chain[0].macro_name, "derive(serde::Deserialize)");
+}
+
+#[test]
+fn top_chains_are_deepest_first() {
+    let report = analyze("tower", true);
+    let chains = top_chains(&report.files, 2);
+    let depths: Vec<usize> = chains.iter().map(|chain| chain.depth).collect();
+    assert_eq!(depths, [4, 3]);
+    assert_eq!(chains[1].sites[0].macro_name, "derive(serde::Deserialize)");
+}
+
+#[test]
+fn warnings_point_at_the_attribute() {
+    let report = analyze("tower", true);
+    let lib = report.files.iter().find(|file| file.path.ends_with("src/lib.rs")).unwrap();
+    let warning = lib.warnings.iter()
+        .find(|warning| warning.warning_type == WarningType::ProcMacro("derive(serde::Deserialize)".to_string()))
+        .expect("no warning for the serde derive");
+    assert_eq!(warning.severity, Severity::Warn);
+    assert_eq!((warning.line, warning.column), (5, 10));
+}
+
+#[test]
+fn a_single_file_counts_calls_as_one_level() {
+    let report = analyze_file(&fixture("tower/src/lib.rs"), &settings(true)).unwrap();
+    let calls = &report.macro_usage["check!"];
+    assert_eq!((calls.invocations, calls.max_depth), (1, 1));
+}
+
+#[test]
+fn included_files_nest_at_the_include() {
+    let report = analyze("include/src", true);
+    assert_eq!(report.files.len(), 1);
+    let lib = &report.files[0];
+    assert_eq!(lib.max_depth, 3);
+    let names: Vec<&str> = lib.deepest_chain.iter().map(|site| site.macro_name.as_str()).collect();
+    assert_eq!(names, ["require", "ensure", "log"]);
+    assert_eq!(lib.deepest_chain[0].file, fixture("include/generated/calls.rs"));
+
+    // `include!` inside a macro call nests below it
+    assert_eq!(lib.macro_usage["add!"].max_depth, 2);
+
+    let warning = &lib.warnings[0];
+    assert_eq!(warning.file.as_deref(), Some(fixture("include/generated/calls.rs").as_path()));
+    assert_eq!(warning.line, 7);
+}
+
+#[test]
+fn items_report_their_own_depth() {
+    let report = analyze("items", true);
+    let items: Vec<(String, &str, usize, usize)> = report.files[0].items.iter()
+        .map(|item| (item.kind.to_string(), item.name.as_str(), item.line, item.max_depth))
+        .collect();
+    assert_eq!(items, [
+        ("fn".to_string(), "vault::Vault::deposit", 5, 3),
+        ("impl".to_string(), "vault::Vault", 4, 3),
+        ("fn".to_string(), "vault::<Vault as Default>::default", 11, 1),
+        ("impl".to_string(), "vault::<Vault as Default>", 10, 1),
+        ("mod".to_string(), "vault", 1, 3),
+        ("fn".to_string(), "shallow", 18, 1),
+    ]);
+    assert_eq!(report.files[0].items[0].deepest_chain[2].macro_name, "leaf");
+}
+
+#[test]
+fn suppressed_regions_are_tallied_instead_of_reported() {
+    let report = analyze("suppress", true);
+    let lib = report.files.iter().find(|file| file.path.ends_with("src/lib.rs")).unwrap();
+    assert_eq!(lib.max_depth, 2);
+    assert_eq!((lib.suppressed.regions, lib.suppressed.invocations, lib.suppressed.max_depth), (2, 7, 4));
+
+    let generated = report.files.iter().find(|file| file.path.ends_with("src/generated.rs")).unwrap();
+    assert_eq!(generated.max_depth, 0);
+    assert!(generated.warnings.is_empty() && generated.macro_usage.is_empty());
+    assert_eq!((generated.suppressed.regions, generated.suppressed.warnings, generated.suppressed.max_depth), (1, 1, 5));
+}
+
+#[test]
+fn calls_resolve_to_their_crate_or_exported_definitions() {
+    let report = analyze("crates", true);
+    let app = report.files.iter().find(|file| file.path.ends_with("app/src/lib.rs")).unwrap();
+    // `shared!` counts the nesting inside its own body
+    assert_eq!(app.max_depth, 3);
+    assert_eq!(app.deepest_chain[0].macro_name, "shared");
+    assert_eq!(app.deepest_chain[0].expansion, ["wrap!", "log!"]);
+    assert_eq!(app.macro_usage["helper!"].max_depth, 2);
+
+    let util = report.files.iter().find(|file| file.path.ends_with("util/src/lib.rs")).unwrap();
+    assert_eq!(util.max_depth, 4);
+}
+
+#[test]
+fn macros_2_0_are_definitions() {
+    let report = analyze("macros2", true);
+    assert!(report.macro_index.defines("guard!") && report.macro_index.defines("count!"));
+    assert!(report.macro_index.definitions["guard"].exported);
+    assert!(!report.macro_index.definitions["count"].exported);
+    let groups = report.macro_index.recursive_groups();
+    assert_eq!(groups.len(), 1);
+    assert_eq!(groups[0].macros, ["count"]);
+
+    let lib = &report.files[0];
+    assert_eq!(lib.max_depth, 3);
+    assert_eq!(lib.deepest_chain[0].expansion, ["ensure!", "log!"]);
+}
+
+#[test]
+fn recursion_depth_is_estimated_per_group() {
+    let report = analyze("recursion", true);
+    let groups: Vec<(Vec<String>, Option<usize>)> = report.macro_index.recursive_groups().into_iter()
+        .map(|group| (group.macros, group.estimated_depth))
+        .collect();
+    // `ping!()` passes no input, yet expands `ping!` once
+    assert_eq!(groups, [
+        (vec!["count".to_string()], Some(4)),
+        (vec!["idle".to_string()], None),
+        (vec!["ping".to_string(), "pong".to_string()], Some(1)),
+    ]);
+
+    let lib = &report.files[0];
+    assert_eq!(lib.macro_usage["ping!"].max_depth, 2);
+    assert_eq!(lib.macro_usage["count!"].max_depth, 4);
+}
+
+#[test]
+fn sibling_invocations_do_not_nest() {
+    let report = analyze("siblings", true);
+    let lib = &report.files[0];
+    assert_eq!(lib.max_depth, 3);
+    let names: Vec<&str> = lib.deepest_chain.iter().map(|site| site.macro_name.as_str()).collect();
+    assert_eq!(names, ["outer", "d", "e"]);
+
+    let depth = |name: &str| lib.macro_usage[name].max_depth;
+    assert_eq!([depth("first!"), depth("second!"), depth("third!")], [1, 1, 1]);
+    assert_eq!([depth("a!"), depth("b!"), depth("c!"), depth("d!")], [2, 2, 2, 2]);
+    assert_eq!([depth("x!"), depth("y!"), depth("z!")], [2, 2, 2]);
+    assert_eq!([depth("p!"), depth("q!"), depth("r!")], [2, 2, 2]);
+    // Inside an ignored macro, at the depth around it
+    assert_eq!(depth("s!"), 1);
+}
+
+#[test]
+fn stacked_attribute_macros_are_counted_per_item() {
+    let report = analyze("stacking", true);
+    let lib = &report.files[0];
+    let config = lib.attribute_stacks.iter().find(|stack| stack.item == "Config").unwrap();
+    assert_eq!(config.line, 3);
+    assert_eq!(config.macros, ["derive(Debug)", "derive(Clone)", "derive(serde::Serialize)", "derive(serde::Deserialize)"]);
+    // `#[inline]` and derive helpers are not counted, so `Plain::run` has no stack
+    assert_eq!(lib.attribute_stacks.len(), 2);
+
+    let distribution = mmed::stats::stacking_distribution(&lib.attribute_stacks);
+    assert_eq!(distribution.into_iter().collect::<Vec<_>>(), [(1, 1), (4, 1)]);
+}
+
+#[test]
+fn build_scripts_and_tests_have_their_own_buckets() {
+    let report = analyze("buckets", true);
+    let bucket = |name: &str| report.files.iter().find(|file| file.path.ends_with(name)).unwrap().bucket;
+    assert_eq!(bucket("src/lib.rs"), Bucket::Program);
+    assert_eq!(bucket("build.rs"), Bucket::BuildScript);
+    assert_eq!(bucket("src/tests.rs"), Bucket::Test);
+    assert_eq!(bucket("tests/transfer.rs"), Bucket::Test);
+    assert_eq!(bucket("benches/transfer.rs"), Bucket::Test);
+
+    let depths = report.bucket_depths();
+    assert_eq!(depths[&Bucket::Program], (1, 2));
+    assert_eq!(depths[&Bucket::BuildScript], (1, 3));
+    assert_eq!(depths[&Bucket::Test], (3, 5));
+
+    assert_eq!(report.max_depth(), 5);
+    assert_eq!(report.max_depth_excluding(&[Bucket::BuildScript, Bucket::Test]), 2);
+    assert_eq!(report.deepest_chain_excluding(&[Bucket::Test])[0].macro_name, "generate");
+}
@@ -0,0 +1,329 @@
+// Runs the mmed binary over the fixtures in tests/fixtures: its report formats, the
+// options that tune the depth and the warnings, baselines and exit codes. Fixtures
+// that mmed writes to (a config file, `cargo expand`) are copied to a temporary
+// directory first.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+
+use serde_json::Value;
+use tempfile::TempDir;
+
+fn fixture(name: &str) -> PathBuf {
+    Path::new(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures")).join(name)
+}
+
+fn run(dir: &Path, args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_mmed")).current_dir(dir).args(args).output().expect("failed to run mmed")
+}
+
+fn mmed(args: &[&str]) -> Output {
+    run(&fixture(""), &[&["--no-config", "--format", "json"], args].concat())
+}
+
+fn stdout(output: &Output) -> String {
+    assert!(output.status.success(), "mmed failed: {}", String::from_utf8_lossy(&output.stderr));
+    String::from_utf8(output.stdout.clone()).unwrap()
+}
+
+/// A copy of the fixture `name` in a temporary directory
+fn copy(name: &str) -> TempDir {
+    let dir = TempDir::new().unwrap();
+    for entry in walkdir::WalkDir::new(fixture(name)) {
+        let entry = entry.unwrap();
+        let target = dir.path().join(entry.path().strip_prefix(fixture(name)).unwrap());
+        if entry.file_type().is_dir() {
+            fs::create_dir_all(target).unwrap();
+        } else {
+            fs::copy(entry.path(), target).unwrap();
+        }
+    }
+    dir
+}
+
+fn json_report(args: &[&str]) -> Value {
+    let output = mmed(args);
+    assert!(output.status.success(), "mmed failed: {}", String::from_utf8_lossy(&output.stderr));
+    serde_json::from_slice(&output.stdout).expect("mmed printed invalid JSON")
+}
+
+#[test]
+fn directory_is_positional() {
+    let report = json_report(&["tower"]);
+    assert!(report["summary"]["files_analyzed"].as_u64().unwrap() > 0);
+    assert_eq!(json_report(&["--dir", "tower"]), report);
+    assert_eq!(json_report(&["-d", "tower"]), report);
+    assert!(!mmed(&["--dir", "tower", "stacking"]).status.success());
+}
+
+#[test]
+fn files_report_their_chains_and_spans() {
+    let report = json_report(&["tower"]);
+    let files = report["files"].as_array().unwrap();
+    assert_eq!(files.len(), 2);
+    let lib = &files[0];
+    assert_eq!(lib["path"], "tower/src/lib.rs");
+    assert_eq!(lib["max_depth"], 4);
+    let site = &lib["deepest_chain"][0];
+    assert_eq!((&site["macro"], &site["line"], &site["column"]), (&Value::from("check"), &Value::from(11), &Value::from(5)));
+    let warning = &lib["warnings"][0];
+    assert_eq!((&warning["type"], &warning["severity"]), (&Value::from("proc_macro"), &Value::from("warn")));
+    assert_eq!((&warning["line"], &warning["column"]), (&Value::from(5), &Value::from(10)));
+    assert_eq!(files[1]["max_depth"], 0);
+    assert_eq!(report["summary"]["max_depth"], 4);
+}
+
+#[test]
+fn config_and_command_line_tune_the_macro_lists() {
+    let tower = copy("tower");
+    let depth = |args: &[&str]| -> Value {
+        let report: Value = serde_json::from_str(&stdout(&run(tower.path(), &[&["--format", "json"], args, &["."]].concat()))).unwrap();
+        report["summary"]["max_depth"].clone()
+    };
+    assert_eq!(depth(&[]), 4);
+    assert_eq!(json_report(&["--ignore-macro", "check", "tower"])["summary"]["max_depth"], 3);
+
+    // The nearest mmed.toml applies unless --no-config is given, and the command line
+    // goes after it
+    fs::write(tower.path().join("mmed.toml"), "ignore-macros = [\"check\"]\n").unwrap();
+    assert_eq!(depth(&[]), 3);
+    assert_eq!(depth(&["--no-config"]), 4);
+    assert_eq!(depth(&["--track-macro", "check"]), 4);
+}
+
+#[test]
+fn proc_macro_depths_resolve_attribute_paths() {
+    let stat = |report: &Value, name: &str| {
+        let stats = report["macro_stats"].as_array().unwrap();
+        stats.iter().find(|stat| stat["macro"] == name).unwrap()["max_depth"].clone()
+    };
+    let report = json_report(&["program"]);
+    assert_eq!(report["summary"]["max_depth"], 5);
+    assert_eq!(stat(&report, "#[program]"), 5);
+
+    // `#[program]` comes from anchor_lang's prelude, so only overrides naming that path
+    // or the bare attribute apply to it
+    for key in ["program=2", "anchor_lang::program=2"] {
+        let report = json_report(&["--proc-macro-depth", key, "program"]);
+        assert_eq!(stat(&report, "#[program]"), 2, "{}", key);
+        assert_eq!(report["summary"]["max_depth"], 4);
+    }
+    assert_eq!(stat(&json_report(&["--proc-macro-depth", "other::program=1", "program"]), "#[program]"), 5);
+
+    // Without the built-in table, attributes it does not list are not counted
+    let report = json_report(&["--no-builtin-depths", "program"]);
+    assert!(report["macro_stats"].as_array().unwrap().iter().all(|stat| stat["macro"] != "#[program]"));
+    assert_eq!(report["summary"]["max_depth"], 3);
+}
+
+#[test]
+fn anchor_handlers_nest_below_the_program() {
+    let report = json_report(&["--anchor", "program"]);
+    let handlers = report["anchor_handlers"].as_array().unwrap();
+    assert_eq!(handlers.len(), 1);
+    assert_eq!(handlers[0]["name"], "vault::deposit");
+    // #[program] → msg! → sum!
+    assert_eq!(handlers[0]["depth"], 7);
+    assert_eq!(handlers[0]["accounts"], "Deposit");
+    assert_eq!(handlers[0]["accounts_depth"], 4);
+    assert!(json_report(&["program"]).get("anchor_handlers").is_none());
+}
+
+#[test]
+fn bangs_in_strings_count_only_when_parsed() {
+    let report = json_report(&["program"]);
+    let strings: Vec<&Value> = report["files"][0]["warnings"].as_array().unwrap().iter()
+        .filter(|warning| warning["type"] == "string_literal_macro")
+        .collect();
+    // "vec!(1)".parse() is reported; "deposit! {}" and "hello! world!" are not
+    assert_eq!(strings.len(), 1);
+    assert!(strings[0]["message"].as_str().unwrap().contains("'vec!'"));
+    assert_eq!((&strings[0]["line"], &strings[0]["severity"]), (&Value::from(35), &Value::from("info")));
+}
+
+#[test]
+fn macro_statistics_by_origin() {
+    let report = json_report(&["program"]);
+    let stats: Vec<(&str, &str, u64)> = report["macro_stats"].as_array().unwrap().iter()
+        .map(|stat| (stat["macro"].as_str().unwrap(), stat["origin"].as_str().unwrap(), stat["max_depth"].as_u64().unwrap()))
+        .collect();
+    assert!(stats.contains(&("sum!", "user_defined", 2)));
+    assert!(stats.contains(&("msg!", "external", 1)));
+    assert!(stats.contains(&("#[derive(Accounts)]", "external", 4)));
+
+    let origins = report["macro_origins"].as_array().unwrap();
+    assert_eq!(origins.len(), 2);
+    assert_eq!((&origins[0]["origin"], &origins[0]["macros"]), (&Value::from("user_defined"), &Value::from(1)));
+    assert_eq!((&origins[1]["origin"], &origins[1]["macros"]), (&Value::from("external"), &Value::from(6)));
+}
+
+#[test]
+fn recursive_groups_report_an_estimated_depth() {
+    let report = json_report(&["recursion"]);
+    let groups: Vec<(Vec<&str>, &Value)> = report["recursive_macros"].as_array().unwrap().iter()
+        .map(|group| {
+            let names = group["macros"].as_array().unwrap().iter().map(|definition| definition["name"].as_str().unwrap()).collect();
+            (names, &group["estimated_depth"])
+        })
+        .collect();
+    assert_eq!(groups, [
+        (vec!["count"], &Value::from(4)),
+        (vec!["idle"], &Value::Null),
+        (vec!["ping", "pong"], &Value::from(1)),
+    ]);
+
+    let text = stdout(&run(&fixture(""), &["--no-config", "recursion"]));
+    assert!(text.contains("ping! ⇄ pong! (mutually recursive), estimated recursion depth 1"));
+    assert!(text.contains("idle! (self-recursive), never invoked"));
+}
+
+#[test]
+fn fail_above_sets_the_exit_code() {
+    let output = mmed(&["--fail-above", "2", "tower"]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("1 file(s) exceed the maximum macro nesting depth of 2"));
+    assert!(mmed(&["--fail-above", "4", "tower"]).status.success());
+}
+
+#[test]
+fn jobs_do_not_change_the_report() {
+    let report = stdout(&mmed(&["-j", "1", "."]));
+    assert_eq!(stdout(&mmed(&["-j", "4", "."])), report);
+}
+
+#[test]
+fn excluded_files_are_not_analyzed() {
+    let report = json_report(&["--exclude", "macros.rs", "tower"]);
+    assert_eq!(report["summary"]["files_analyzed"], 1);
+    assert_eq!(report["files"][0]["path"], "tower/src/lib.rs");
+
+    // .gitignore is followed unless --no-gitignore is given
+    let tower = copy("tower");
+    fs::create_dir(tower.path().join(".git")).unwrap();
+    fs::write(tower.path().join(".gitignore"), "src/macros.rs\n").unwrap();
+    let analyzed = |args: &[&str]| -> Value {
+        let report: Value = serde_json::from_str(&stdout(&run(tower.path(), &[&["--no-config", "--format", "json"], args, &["."]].concat()))).unwrap();
+        report["summary"]["files_analyzed"].clone()
+    };
+    assert_eq!(analyzed(&[]), 1);
+    assert_eq!(analyzed(&["--no-gitignore"]), 2);
+}
+
+#[test]
+fn warning_severities_and_failing_types() {
+    let warnings = |args: &[&str]| -> Vec<(String, String)> {
+        let report = json_report(&[args, &["program"]].concat());
+        report["files"][0]["warnings"].as_array().unwrap().iter()
+            .map(|warning| (warning["type"].as_str().unwrap().to_string(), warning["severity"].as_str().unwrap().to_string()))
+            .collect()
+    };
+    assert_eq!(warnings(&[]).len(), 4);
+    assert!(warnings(&["--min-severity", "warn"]).iter().all(|(kind, _)| kind == "proc_macro"));
+    assert_eq!(warnings(&["--disable-warning", "proc-macro"]), [("string_literal_macro".to_string(), "info".to_string())]);
+    assert!(warnings(&["--warning-severity", "string-literal-macro=error"]).contains(&("string_literal_macro".to_string(), "error".to_string())));
+
+    let output = mmed(&["--fail-on-warning", "string-literal-macro", "program"]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("1 warning(s) of a failing type"));
+    assert!(mmed(&["--fail-on-warning", "macro-repetition", "program"]).status.success());
+}
+
+#[test]
+fn dot_graph_of_the_expansions() {
+    let dir = TempDir::new().unwrap();
+    let dot = dir.path().join("macros.dot");
+    stdout(&mmed(&["--dot", dot.to_str().unwrap(), "tower"]));
+    let graph = fs::read_to_string(dot).unwrap();
+    assert!(graph.starts_with("digraph macros {"));
+    for edge in ["\"check!\" -> \"require!\";", "\"require!\" -> \"log!\";", "\"log!\" -> \"emit!\";", "\"file:src/lib.rs\" -> \"check!\" [style=dashed, label=\"1\"];"] {
+        assert!(graph.contains(edge), "missing {}", edge);
+    }
+}
+
+#[test]
+fn markdown_html_and_csv_reports() {
+    let report = |format: &str| stdout(&run(&fixture(""), &["--no-config", "--format", format, "tower"]));
+    let markdown = report("markdown");
+    assert!(markdown.starts_with("# Macro Nesting Report"));
+    assert!(markdown.contains("| Maximum nesting depth | 4 |"));
+    assert!(markdown.contains("`check! (expands through require! → log! → emit!)` at `src/lib.rs:11:5`"));
+
+    let html = report("html");
+    assert!(html.starts_with("<!DOCTYPE html>"));
+    assert!(html.contains("src/lib.rs"));
+
+    assert_eq!(report("csv"), "file,bucket,max_depth,deepest_chain,warnings\nsrc/lib.rs,program,4,check!,1\nsrc/macros.rs,program,0,,0\n");
+}
+
+#[test]
+fn baseline_reports_only_increases() {
+    let dir = TempDir::new().unwrap();
+    let baseline = dir.path().join("baseline.json");
+    let baseline = baseline.to_str().unwrap();
+    stdout(&mmed(&["--write-baseline", baseline, "tower"]));
+    let written: Value = serde_json::from_str(&fs::read_to_string(baseline).unwrap()).unwrap();
+    assert_eq!(written["files"]["src/lib.rs"]["max_depth"], 4);
+    assert_eq!(written["files"]["src/macros.rs"]["max_depth"], 0);
+
+    // Depths already in the baseline do not fail the run
+    let output = mmed(&["--baseline", baseline, "--fail-above", "2", "tower"]);
+    let report: Value = serde_json::from_str(&stdout(&output)).unwrap();
+    assert_eq!(report["baseline_increases"], Value::Array(Vec::new()));
+
+    // A file deeper than its baseline does
+    stdout(&mmed(&["--ignore-macro", "check", "--write-baseline", baseline, "tower"]));
+    let output = mmed(&["--baseline", baseline, "--fail-above", "2", "tower"]);
+    assert_eq!(output.status.code(), Some(1));
+    let report: Value = serde_json::from_slice(&output.stdout).unwrap();
+    let increase = &report["baseline_increases"][0];
+    assert_eq!((&increase["old_depth"], &increase["new_depth"]), (&Value::from(3), &Value::from(4)));
+}
+
+#[test]
+fn sarif_results_and_rules() {
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join("mmed.sarif");
+    let output = mmed(&["--fail-above", "2", "--sarif", path.to_str().unwrap(), "tower"]);
+    assert_eq!(output.status.code(), Some(1));
+    let log: Value = serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+    let sarif = &log["runs"][0];
+    assert_eq!(sarif["tool"]["driver"]["name"], "mmed");
+    let results: Vec<(&str, &str)> = sarif["results"].as_array().unwrap().iter()
+        .map(|result| (result["ruleId"].as_str().unwrap(), result["message"]["text"].as_str().unwrap()))
+        .collect();
+    assert_eq!(results.len(), 2);
+    assert_eq!(results[0].0, "macro-depth");
+    assert!(results[0].1.starts_with("Macro nesting reaches depth 4 (limit 2): check!"));
+    assert_eq!(results[1].0, "proc-macro");
+    let location = &sarif["results"][1]["locations"][0]["physicalLocation"];
+    assert_eq!(location["artifactLocation"]["uri"], "tower/src/lib.rs");
+    assert_eq!((&location["region"]["startLine"], &location["region"]["startColumn"]), (&Value::from(5), &Value::from(10)));
+
+    // --format sarif prints the log, here without a depth limit to report
+    let printed: Value = serde_json::from_str(&stdout(&run(&fixture(""), &["--no-config", "--format", "sarif", "tower"]))).unwrap();
+    assert_eq!(printed["runs"][0]["results"].as_array().unwrap().len(), 1);
+}
+
+#[test]
+fn expansion_follows_cargo_expand() {
+    // `cargo expand` builds in the crate directory, so it runs on a copy
+    let expand = copy("expand");
+    let output = run(expand.path(), &["--no-config", "--format", "json", "--expand", "."]);
+    let report: Value = serde_json::from_str(&stdout(&output)).unwrap();
+    let crates = report["expansion"].as_array().unwrap();
+    assert_eq!(crates.len(), 1);
+    let expansion = &crates[0];
+    assert_eq!(expansion["name"], "expand_fixture");
+    assert_eq!((&expansion["expanded_depth"], &expansion["source_depth"]), (&Value::from(3), &Value::from(3)));
+    assert_eq!(expansion["deepest_chain"], serde_json::json!(["quad", "double", "double"]));
+    assert_eq!(expansion["source_lines"], 28);
+
+    // The expanded line counts depend on the toolchain, but util.rs's derives and vec!
+    // expand to more lines than it has
+    let util = expansion["files"].as_array().unwrap().iter()
+        .find(|file| file["path"].as_str().unwrap().ends_with("src/util.rs"))
+        .unwrap();
+    assert_eq!(util["source_lines"], 6);
+    assert!(util["blowup"].as_f64().unwrap() > 1.0);
+}
@@ -0,0 +1,3 @@
+fn bench() {
+    measure!(transfer!());
+}
@@ -0,0 +1,6 @@
+pub fn transfer() {
+    check!(amount!());
+}
+
+#[cfg(test)]
+mod tests;
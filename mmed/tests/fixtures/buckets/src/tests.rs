@@ -0,0 +1,4 @@
+#[test]
+fn transfers() {
+    a!(b!(c!(d!())));
+}
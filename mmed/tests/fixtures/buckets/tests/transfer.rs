@@ -0,0 +1,4 @@
+#[test]
+fn transfers_between_accounts() {
+    setup!(accounts!(keys!(seeds!(bump!()))));
+}
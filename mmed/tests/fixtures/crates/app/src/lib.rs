@@ -0,0 +1,12 @@
+// Calls the exported `shared!` of util, and its own `helper!`
+
+macro_rules! helper {
+    () => {
+        one!()
+    };
+}
+
+pub fn run() {
+    helper!();
+    shared!(1);
+}
@@ -0,0 +1,18 @@
+// `shared!` nests two levels in its own body; `helper!` is private to this crate
+
+#[macro_export]
+macro_rules! shared {
+    ($x:expr) => {
+        wrap!(log!($x))
+    };
+}
+
+macro_rules! helper {
+    () => {
+        a!(b!(c!()))
+    };
+}
+
+pub fn run() {
+    helper!();
+}
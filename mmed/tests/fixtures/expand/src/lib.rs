@@ -0,0 +1,22 @@
+// A crate small enough to expand quickly: a local macro nesting another, and a module
+// whose vec! expands to more lines than it is written in.
+
+mod util;
+
+macro_rules! double {
+    ($x:expr) => {
+        $x * 2
+    };
+}
+
+macro_rules! quad {
+    ($x:expr) => {
+        double!(double!($x))
+    };
+}
+
+pub fn four(x: u32) -> u32 {
+    quad!(x)
+}
+
+pub use util::values;
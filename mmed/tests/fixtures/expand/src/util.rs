@@ -0,0 +1,6 @@
+#[derive(Debug, Clone, PartialEq)]
+pub struct Value(pub u32);
+
+pub fn values() -> Vec<Value> {
+    vec![Value(1), Value(2), Value(3)]
+}
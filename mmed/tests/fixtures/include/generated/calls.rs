@@ -0,0 +1,8 @@
+pub fn check(value: u64) {
+    require!(ensure!(value > 0, log!("value {}", value)));
+}
+
+include!("calls.rs");
+
+#[derive(serde::Serialize)]
+pub struct Call;
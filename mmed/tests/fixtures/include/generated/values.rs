@@ -0,0 +1 @@
+[add!(1, 2), add!(3, 4)]
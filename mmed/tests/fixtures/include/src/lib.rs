@@ -0,0 +1,11 @@
+mod generated {
+    include!("../generated/calls.rs");
+}
+
+pub fn total() -> u64 {
+    sum!(include!(concat!("../generated/", "values.rs")))
+}
+
+pub fn missing() {
+    include!(concat!(env!("OUT_DIR"), "/bindings.rs"));
+}
@@ -0,0 +1,22 @@
+pub mod vault {
+    pub struct Vault;
+
+    impl Vault {
+        pub fn deposit(&self) {
+            outer!(inner!(leaf!()));
+        }
+    }
+
+    impl Default for Vault {
+        fn default() -> Self {
+            single!();
+            Vault
+        }
+    }
+}
+
+pub fn shallow() {
+    single!();
+}
+
+pub fn plain() {}
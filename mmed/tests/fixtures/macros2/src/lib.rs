@@ -0,0 +1,17 @@
+#![feature(decl_macro)]
+
+// A single-rule macro nesting two levels in its body
+pub macro guard($cond:expr) {
+    ensure!(log!($cond))
+}
+
+// Rules that recurse through the macro itself
+pub(crate) macro count {
+    () => { 0 },
+    ($head:tt $($tail:tt)*) => { 1 + count!($($tail)*) },
+}
+
+pub fn check(value: u64) -> u64 {
+    guard!(value > 0);
+    count!(a b c)
+}
@@ -0,0 +1,37 @@
+// An Anchor program: a handler nesting require! and msg! below #[program], its
+// derive(Accounts) struct, a local macro called inside msg!, and "!" inside strings
+// that only count where the string is parsed as code.
+
+use anchor_lang::prelude::*;
+
+declare_id!("Fg6PaFpoGXkYsidMpWTK6W2BeZ7FEfcYkg476zPFsLnS");
+
+macro_rules! sum {
+    ($($x:expr),*) => { 0 $(+ $x)* };
+}
+
+#[program]
+pub mod vault {
+    use super::*;
+
+    pub fn deposit(ctx: Context<Deposit>, amount: u64) -> Result<()> {
+        require!(amount > 0, VaultError::Zero);
+        msg!("deposit! {}", sum!(amount, 1));
+        Ok(())
+    }
+}
+
+#[derive(Accounts)]
+pub struct Deposit<'info> {
+    pub payer: Signer<'info>,
+}
+
+#[error_code]
+pub enum VaultError {
+    Zero,
+}
+
+pub fn banner() -> &'static str {
+    let _code: proc_macro2::TokenStream = "vec!(1)".parse().unwrap();
+    "hello! world!"
+}
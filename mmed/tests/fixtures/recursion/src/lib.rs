@@ -0,0 +1,26 @@
+// Two macros taking turns on their input, called with none of it
+macro_rules! ping {
+    () => {};
+    ($head:expr $(, $tail:expr)*) => { pong!($($tail),*) };
+}
+
+macro_rules! pong {
+    () => {};
+    ($head:expr $(, $tail:expr)*) => { ping!($($tail),*) };
+}
+
+// A tt-muncher called with four token trees
+macro_rules! count {
+    () => { 0 };
+    ($head:tt $($tail:tt)*) => { 1 + count!($($tail)*) };
+}
+
+// Recursive, but never called
+macro_rules! idle {
+    ($($tokens:tt)*) => { idle!($($tokens)*) };
+}
+
+pub fn run() -> u32 {
+    ping!();
+    count!(a b c d)
+}
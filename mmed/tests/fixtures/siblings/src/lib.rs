@@ -0,0 +1,10 @@
+// Sibling invocations, in statements, token trees and separated lists, stay at the
+// depth of their enclosing invocation
+
+pub fn run() {
+    first!(); second!(); third!();
+    outer!(a!(); b!(); c!(x), d! { e!() });
+    block! { x!() y!() z![] }
+    list![p!(), q!(), r!()];
+    println!("{}", s!());
+}
@@ -0,0 +1,19 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Config {
+    pub limit: u64,
+}
+
+#[derive(Debug)]
+pub enum Mode {
+    Fast,
+}
+
+pub struct Plain;
+
+impl Plain {
+    #[inline]
+    pub fn run(&self) {}
+}
@@ -0,0 +1,8 @@
+// trr:ignore-macro-depth-file
+
+#[derive(serde::Serialize)]
+pub struct Generated;
+
+pub fn table() {
+    outer!(inner!(leaf!(deeper!(deepest!()))));
+}
@@ -0,0 +1,19 @@
+pub fn reported() {
+    outer!(inner!());
+}
+
+// trr:ignore-macro-depth: generated dispatch, reviewed
+#[inline]
+pub fn dispatch() {
+    outer!(inner!(leaf!(deeper!())));
+}
+
+pub struct Handler;
+
+impl Handler {
+    /// Documented before the comment
+    // trr:ignore-macro-depth
+    pub fn run(&self) {
+        outer!(inner!(leaf!()));
+    }
+}
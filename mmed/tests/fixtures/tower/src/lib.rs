@@ -0,0 +1,13 @@
+// Four levels of local macros next to a derive: check! -> require! -> log! -> emit!
+
+mod macros;
+
+#[derive(serde::Deserialize)]
+pub struct Config {
+    pub limit: u64,
+}
+
+pub fn validate(config: &Config) -> bool {
+    check!(config.limit > 0);
+    true
+}
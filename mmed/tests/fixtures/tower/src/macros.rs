@@ -0,0 +1,29 @@
+#[macro_export]
+macro_rules! emit {
+    ($msg:expr) => {
+        let _ = $msg;
+    };
+}
+
+#[macro_export]
+macro_rules! log {
+    ($msg:expr) => {
+        emit!($msg);
+    };
+}
+
+#[macro_export]
+macro_rules! require {
+    ($cond:expr) => {
+        if !($cond) {
+            log!("requirement failed");
+        }
+    };
+}
+
+#[macro_export]
+macro_rules! check {
+    ($cond:expr) => {
+        require!($cond)
+    };
+}
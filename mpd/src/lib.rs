@@ -0,0 +1,337 @@
+//! Potential panics in Solana program crates: panicking macros, `unwrap`/`expect`,
+//! assertions, and indexing or slicing that can go out of bounds.
+//!
+//! A panic aborts the transaction with a bare `ProgramFailedToComplete` and a log line
+//! at best, so the client never learns which check failed, and a panic reachable from
+//! attacker-controlled input is a denial of service. On-chain code should return a
+//! program error instead: `ok_or(..)?`, `require!`, `get(..)`.
+//!
+//! Only crates depending on a Solana program framework are analyzed unless asked, and
+//! `#[cfg(test)]` items, `#[test]` functions and `tests/` and `benches/` are left out.
+//! Arguments of macros that parse as expressions (`msg!`, `require!`, `emit!`, ...) are
+//! searched too. `debug_assert!` and its kin are compiled out of release builds and are
+//! not reported.
+
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use mdd::manifests::Manifests;
+use proc_macro2::Span;
+use serde::Serialize;
+use syn::punctuated::Punctuated;
+use syn::spanned::Spanned;
+use syn::visit::{self, Visit};
+use syn::{Expr, ImplItemFn, ItemFn, ItemImpl, ItemMod, ItemTrait, Macro, RangeLimits, Token, TraitItemFn};
+pub use trr_core::Location;
+use trr_core::source::{is_cfg_test, location};
+use trr_core::walk;
+
+/// What can panic
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum PanicKind {
+    /// `panic!`, `unreachable!`, `todo!`, `unimplemented!`
+    Panic,
+    /// `unwrap()`, `unwrap_err()`
+    Unwrap,
+    /// `expect(..)`, `expect_err(..)`
+    Expect,
+    /// `assert!`, `assert_eq!`, `assert_ne!`
+    Assert,
+    /// `value[index]`
+    Index,
+    /// `value[start..end]`
+    Slice,
+}
+
+impl PanicKind {
+    pub const ALL: [PanicKind; 6] = [PanicKind::Panic, PanicKind::Unwrap, PanicKind::Expect, PanicKind::Assert, PanicKind::Index, PanicKind::Slice];
+
+    fn of_macro(name: &str) -> Option<PanicKind> {
+        match name {
+            "panic" | "unreachable" | "todo" | "unimplemented" => Some(PanicKind::Panic),
+            "assert" | "assert_eq" | "assert_ne" => Some(PanicKind::Assert),
+            _ => None,
+        }
+    }
+
+    fn of_method(name: &str) -> Option<PanicKind> {
+        match name {
+            "unwrap" | "unwrap_err" => Some(PanicKind::Unwrap),
+            "expect" | "expect_err" => Some(PanicKind::Expect),
+            _ => None,
+        }
+    }
+
+    /// Indexing and slicing, which `--no-indexing` leaves out
+    pub fn is_indexing(self) -> bool {
+        matches!(self, PanicKind::Index | PanicKind::Slice)
+    }
+}
+
+impl fmt::Display for PanicKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.pad(match self {
+            PanicKind::Panic => "panic",
+            PanicKind::Unwrap => "unwrap",
+            PanicKind::Expect => "expect",
+            PanicKind::Assert => "assert",
+            PanicKind::Index => "index",
+            PanicKind::Slice => "slice",
+        })
+    }
+}
+
+/// A crate the analyzed files belong to
+#[derive(Clone, Debug, Serialize)]
+pub struct Crate {
+    pub name: String,
+    pub root: PathBuf,
+    /// Depends directly on one of [`mdd::PROGRAM_FRAMEWORKS`], or has no manifest to
+    /// tell
+    pub program: bool,
+    /// Analyzed: a program, or any crate when asked
+    pub analyzed: bool,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct PanicSite {
+    pub kind: PanicKind,
+    /// The source of the site, when it fits on one line
+    pub code: Option<String>,
+    pub location: Location,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct FunctionPanics {
+    #[serde(rename = "crate")]
+    pub krate: String,
+    /// Inline modules, then the impl type or trait for methods, then the name
+    pub name: String,
+    pub location: Location,
+    /// In source order
+    pub sites: Vec<PanicSite>,
+}
+
+#[derive(Debug, Default)]
+pub struct Analysis {
+    pub crates: Vec<Crate>,
+    /// Most sites first
+    pub functions: Vec<FunctionPanics>,
+    /// Files that could not be read or parsed
+    pub errors: Vec<(PathBuf, String)>,
+}
+
+impl Analysis {
+    pub fn analyzed_crates(&self) -> usize {
+        self.crates.iter().filter(|krate| krate.analyzed).count()
+    }
+
+    pub fn sites(&self) -> usize {
+        self.functions.iter().map(|function| function.sites.len()).sum()
+    }
+
+    /// Sites of `kind`
+    pub fn count(&self, kind: PanicKind) -> usize {
+        self.functions.iter().flat_map(|function| &function.sites).filter(|site| site.kind == kind).count()
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Options {
+    /// Library and client crates too, not only programs
+    pub all_crates: bool,
+    /// Leave out indexing and slicing
+    pub no_indexing: bool,
+}
+
+/// Find the potential panics of `source`, the contents of `path`, in `krate`
+pub fn analyze_source(path: &Path, source: &str, krate: &str, options: Options) -> syn::Result<Vec<FunctionPanics>> {
+    let file = syn::parse_file(source)?;
+    let mut visitor = FileVisitor { path, source, krate, options, scope: Vec::new(), functions: Vec::new() };
+    visitor.visit_file(&file);
+    Ok(visitor.functions)
+}
+
+/// Find the potential panics of the crates under `dir` (or `dir` itself, if it is a
+/// file) that `options` select, leaving out `target/` and test code
+pub fn analyze(dir: &Path, options: Options) -> io::Result<Analysis> {
+    let files = walk::program_files(dir)?;
+
+    let mut manifests = Manifests::default();
+    let mut analysis = Analysis::default();
+    for file in files {
+        let krate = manifests.crate_of(&file, dir);
+        let index = match analysis.crates.iter().position(|known| known.root == krate.root) {
+            Some(index) => index,
+            None => {
+                let analyzed = krate.program || options.all_crates;
+                analysis.crates.push(Crate { name: krate.name, root: krate.root, program: krate.program, analyzed });
+                analysis.crates.len() - 1
+            }
+        };
+        if !analysis.crates[index].analyzed {
+            continue;
+        }
+        let result = fs::read_to_string(&file)
+            .map_err(|e| e.to_string())
+            .and_then(|source| analyze_source(&file, &source, &analysis.crates[index].name, options).map_err(|e| e.to_string()));
+        match result {
+            Ok(functions) => analysis.functions.extend(functions),
+            Err(e) => analysis.errors.push((file, e)),
+        }
+    }
+    analysis.crates.sort_by(|a, b| a.name.cmp(&b.name));
+    analysis.functions.sort_by(|a, b| b.sites.len().cmp(&a.sites.len())
+        .then_with(|| a.location.file.cmp(&b.location.file))
+        .then_with(|| a.location.line.cmp(&b.location.line)));
+    Ok(analysis)
+}
+
+struct FileVisitor<'a> {
+    path: &'a Path,
+    source: &'a str,
+    krate: &'a str,
+    options: Options,
+    /// Inline modules, impl types, traits and enclosing functions
+    scope: Vec<String>,
+    functions: Vec<FunctionPanics>,
+}
+
+impl FileVisitor<'_> {
+    fn add_function(&mut self, sig: &syn::Signature, block: &syn::Block) {
+        let mut body = BodyVisitor { path: self.path, source: self.source, options: self.options, sites: Vec::new() };
+        body.visit_block(block);
+        if !body.sites.is_empty() {
+            let mut name = self.scope.clone();
+            name.push(sig.ident.to_string());
+            body.sites.sort_by_key(|site| (site.location.line, site.location.column));
+            self.functions.push(FunctionPanics {
+                krate: self.krate.to_string(),
+                name: name.join("::"),
+                location: location(self.path, sig.ident.span()),
+                sites: body.sites,
+            });
+        }
+
+        // Functions declared in the body
+        self.scope.push(sig.ident.to_string());
+        self.visit_block(block);
+        self.scope.pop();
+    }
+}
+
+impl<'ast> Visit<'ast> for FileVisitor<'_> {
+    fn visit_item_mod(&mut self, item: &'ast ItemMod) {
+        if is_cfg_test(&item.attrs) {
+            return;
+        }
+        self.scope.push(item.ident.to_string());
+        visit::visit_item_mod(self, item);
+        self.scope.pop();
+    }
+
+    fn visit_item_fn(&mut self, item: &'ast ItemFn) {
+        if !is_cfg_test(&item.attrs) {
+            self.add_function(&item.sig, &item.block);
+        }
+    }
+
+    fn visit_item_impl(&mut self, item: &'ast ItemImpl) {
+        if is_cfg_test(&item.attrs) {
+            return;
+        }
+        let owner = match &*item.self_ty {
+            syn::Type::Path(path) => path.path.segments.last().map(|segment| segment.ident.to_string()),
+            _ => None,
+        };
+        self.scope.push(owner.unwrap_or_else(|| "_".to_string()));
+        visit::visit_item_impl(self, item);
+        self.scope.pop();
+    }
+
+    fn visit_impl_item_fn(&mut self, item: &'ast ImplItemFn) {
+        if !is_cfg_test(&item.attrs) {
+            self.add_function(&item.sig, &item.block);
+        }
+    }
+
+    fn visit_item_trait(&mut self, item: &'ast ItemTrait) {
+        self.scope.push(item.ident.to_string());
+        visit::visit_item_trait(self, item);
+        self.scope.pop();
+    }
+
+    fn visit_trait_item_fn(&mut self, item: &'ast TraitItemFn) {
+        if let Some(block) = &item.default {
+            self.add_function(&item.sig, block);
+        }
+    }
+}
+
+/// The potential panics of one body, leaving out the items declared in it
+struct BodyVisitor<'a> {
+    path: &'a Path,
+    source: &'a str,
+    options: Options,
+    sites: Vec<PanicSite>,
+}
+
+impl BodyVisitor<'_> {
+    /// The source of `span`, if it is on one line
+    fn snippet(&self, span: Span) -> Option<String> {
+        let (start, end) = (span.start(), span.end());
+        if start.line != end.line {
+            return None;
+        }
+        let line = self.source.lines().nth(start.line - 1)?;
+        let text: String = line.chars().skip(start.column).take(end.column - start.column).collect();
+        Some(text)
+    }
+
+    fn push(&mut self, kind: PanicKind, span: Span, at: Span) {
+        if kind.is_indexing() && self.options.no_indexing {
+            return;
+        }
+        self.sites.push(PanicSite { kind, code: self.snippet(span), location: location(self.path, at) });
+    }
+}
+
+impl<'ast> Visit<'ast> for BodyVisitor<'_> {
+    fn visit_item(&mut self, _: &'ast syn::Item) {}
+
+    fn visit_expr_method_call(&mut self, call: &'ast syn::ExprMethodCall) {
+        if let Some(kind) = PanicKind::of_method(&call.method.to_string()) {
+            self.push(kind, call.span(), call.method.span());
+        }
+        visit::visit_expr_method_call(self, call);
+    }
+
+    fn visit_expr_index(&mut self, expr: &'ast syn::ExprIndex) {
+        // `value[..]` takes the whole of it and cannot panic
+        let kind = match &*expr.index {
+            Expr::Range(range) if range.start.is_none() && range.end.is_none() && matches!(range.limits, RangeLimits::HalfOpen(_)) => None,
+            Expr::Range(_) => Some(PanicKind::Slice),
+            _ => Some(PanicKind::Index),
+        };
+        if let Some(kind) = kind {
+            self.push(kind, expr.span(), expr.bracket_token.span.open());
+        }
+        visit::visit_expr_index(self, expr);
+    }
+
+    fn visit_macro(&mut self, mac: &'ast Macro) {
+        let name = mac.path.segments.last().map(|segment| segment.ident.to_string()).unwrap_or_default();
+        if let Some(kind) = PanicKind::of_macro(&name) {
+            self.push(kind, mac.span(), mac.path.span());
+        }
+        // Arguments written as expressions, as in `msg!`, `require!` and the macros above
+        if let Ok(arguments) = mac.parse_body_with(Punctuated::<Expr, Token![,]>::parse_terminated) {
+            for argument in &arguments {
+                self.visit_expr(argument);
+            }
+        }
+    }
+}
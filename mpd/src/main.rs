@@ -0,0 +1,178 @@
+mod report;
+mod sarif;
+
+use std::collections::BTreeMap;
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::process;
+
+use clap::Parser;
+use serde::Serialize;
+use trr_core::git::Repository;
+use trr_core::output::{self, Format};
+
+use mpd::{Analysis, Crate, FunctionPanics, Options, PanicKind};
+
+#[derive(Parser)]
+#[clap(author, version, about = "MPD (Potential Panic) Detector")]
+#[clap(after_help = "Examples:
+  mpd programs/vault
+  mpd --top 10 --no-indexing programs
+  mpd --all-crates .
+  mpd --format sarif programs > panics.sarif
+  mpd --fail-above 0 --no-indexing programs")]
+struct Args {
+    /// Directory to analyze (relative to --repo when given)
+    #[clap(value_name = "DIR", default_value = ".")]
+    dir: PathBuf,
+
+    /// Git repository URL to clone, or path of a local checkout
+    #[clap(long, value_name = "REPO_URL_OR_PATH")]
+    repo: Option<String>,
+
+    /// Branch, tag or commit of --repo to analyze
+    #[clap(long, value_name = "REV", requires = "repo")]
+    rev: Option<String>,
+
+    /// Output format
+    #[clap(long, value_enum, default_value = "text")]
+    format: Format,
+
+    /// Only the N functions with the most potential panics
+    #[clap(long, value_name = "N")]
+    top: Option<usize>,
+
+    /// Analyze every crate, not only those depending on a Solana program framework
+    #[clap(long)]
+    all_crates: bool,
+
+    /// Leave out indexing and slicing (`data[0]`, `data[8..]`)
+    #[clap(long)]
+    no_indexing: bool,
+
+    /// Exit with status 1 if there are more than N potential panics
+    #[clap(long, value_name = "N")]
+    fail_above: Option<usize>,
+}
+
+/// Structured form of the analysis for `--format json`
+#[derive(Serialize)]
+struct JsonReport<'a> {
+    summary: Summary,
+    crates: &'a [Crate],
+    /// Functions, most potential panics first
+    functions: &'a [FunctionPanics],
+    errors: Vec<FileError>,
+}
+
+#[derive(Serialize)]
+struct Summary {
+    /// Crates analyzed
+    crates: usize,
+    functions: usize,
+    sites: usize,
+    by_kind: BTreeMap<PanicKind, usize>,
+}
+
+#[derive(Serialize)]
+struct FileError {
+    file: PathBuf,
+    error: String,
+}
+
+fn print_text(analysis: &Analysis, functions: &[FunctionPanics]) {
+    println!("\nCrates:");
+    for krate in &analysis.crates {
+        let kind = if krate.program { "program" } else { "library" };
+        let skipped = if krate.analyzed { "" } else { ", skipped" };
+        println!("  {} ({}{})", krate.name, kind, skipped);
+    }
+    if !functions.is_empty() {
+        println!("\nPotential panics by function:");
+    }
+    for function in functions {
+        println!("\n{}::{} ({}): {} site(s)", function.krate, function.name, function.location, function.sites.len());
+        for site in &function.sites {
+            match &site.code {
+                Some(code) => println!("  {:<6} {} at {}", site.kind, code, site.location),
+                None => println!("  {:<6} at {}", site.kind, site.location),
+            }
+        }
+    }
+
+    println!("\nSummary:");
+    println!("Crates analyzed: {}", analysis.analyzed_crates());
+    println!("Functions that may panic: {}", analysis.functions.len());
+    println!("Potential panics: {}", analysis.sites());
+    for kind in PanicKind::ALL {
+        let count = analysis.count(kind);
+        if count > 0 {
+            println!("  {}: {}", kind, count);
+        }
+    }
+}
+
+fn write_json(out: &mut dyn Write, analysis: &Analysis, functions: &[FunctionPanics]) -> io::Result<()> {
+    let report = JsonReport {
+        summary: Summary {
+            crates: analysis.analyzed_crates(),
+            functions: analysis.functions.len(),
+            sites: analysis.sites(),
+            by_kind: PanicKind::ALL.into_iter().map(|kind| (kind, analysis.count(kind))).collect(),
+        },
+        crates: &analysis.crates,
+        functions,
+        errors: analysis.errors.iter()
+            .map(|(file, error)| FileError { file: file.clone(), error: error.clone() })
+            .collect(),
+    };
+    output::write_json(out, "mpd", &report)
+}
+
+fn run(args: &Args) -> Result<(), Box<dyn std::error::Error>> {
+    let repository = args.repo.as_deref().map(|repo| Repository::open(repo, args.rev.as_deref())).transpose()?;
+    let dir = match &repository {
+        Some(repository) => repository.subdir(&args.dir)?,
+        None => args.dir.clone(),
+    };
+    if !dir.exists() {
+        return Err(format!("Path '{}' does not exist", dir.display()).into());
+    }
+    if args.format == Format::Text {
+        println!("Analyzing: {}", dir.display());
+    }
+
+    let options = Options { all_crates: args.all_crates, no_indexing: args.no_indexing };
+    let analysis = mpd::analyze(&dir, options)?;
+    for (file, error) in &analysis.errors {
+        eprintln!("Error analyzing {}: {}", file.display(), error);
+    }
+    let functions = &analysis.functions[..args.top.map_or(analysis.functions.len(), |top| top.min(analysis.functions.len()))];
+
+    let mut out = io::stdout().lock();
+    match args.format {
+        Format::Text => print_text(&analysis, functions),
+        Format::Json => write_json(&mut out, &analysis, functions)?,
+        Format::Csv => report::write_csv(&mut out, functions)?,
+        Format::Markdown => report::write_markdown(&mut out, &dir, &analysis, functions)?,
+        Format::Sarif => {
+            // Artifact URIs are relative to the repository root, or to where mpd runs
+            let base = repository.as_ref().map_or_else(|| PathBuf::from("."), |repository| repository.root().to_path_buf());
+            sarif::write_sarif(&mut out, functions, &base)?;
+            writeln!(out)?;
+        }
+    }
+    drop(out);
+    output::enforce_limit(args.fail_above, analysis.sites(), "potential panic(s)", analysis.functions.iter().map(|function| {
+        format!("{}::{} ({}) at {}", function.krate, function.name, function.sites.len(), function.location)
+    }));
+    Ok(())
+}
+
+fn main() {
+    let args = Args::parse();
+    if let Err(e) = run(&args) {
+        eprintln!("Error: {}", e);
+        process::exit(1);
+    }
+}
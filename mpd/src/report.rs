@@ -0,0 +1,72 @@
+//! `--format csv`, one row per potential panic, and `--format markdown`, a review-ready
+//! report with the crates and the functions that may panic.
+
+use std::io::{self, Write};
+use std::path::Path;
+
+use trr_core::output::{markdown_code, write_csv_row};
+
+use mpd::{Analysis, FunctionPanics, PanicKind};
+
+/// Rows are by function, most potential panics first
+pub fn write_csv(out: &mut dyn Write, functions: &[FunctionPanics]) -> io::Result<()> {
+    write_csv_row(out, &["crate", "function", "kind", "code", "file", "line", "column"])?;
+    for function in functions {
+        for site in &function.sites {
+            write_csv_row(out, &[
+                &function.krate,
+                &function.name,
+                &site.kind.to_string(),
+                site.code.as_deref().unwrap_or(""),
+                &site.location.file.display().to_string(),
+                &site.location.line.to_string(),
+                &site.location.column.map_or(String::new(), |column| column.to_string()),
+            ])?;
+        }
+    }
+    Ok(())
+}
+
+pub fn write_markdown(out: &mut dyn Write, target: &Path, analysis: &Analysis, functions: &[FunctionPanics]) -> io::Result<()> {
+    writeln!(out, "# Potential Panic Report\n")?;
+    writeln!(out, "Target: {}\n", markdown_code(&target.display().to_string()))?;
+    writeln!(out, "## Summary\n")?;
+    writeln!(out, "| Metric | Value |")?;
+    writeln!(out, "| --- | ---: |")?;
+    writeln!(out, "| Crates analyzed | {} |", analysis.analyzed_crates())?;
+    writeln!(out, "| Functions that may panic | {} |", analysis.functions.len())?;
+    writeln!(out, "| Potential panics | {} |", analysis.sites())?;
+    for kind in PanicKind::ALL {
+        writeln!(out, "| {} | {} |", markdown_code(&kind.to_string()), analysis.count(kind))?;
+    }
+
+    if !analysis.crates.is_empty() {
+        writeln!(out, "\n## Crates\n")?;
+        writeln!(out, "| Crate | Program | Analyzed |")?;
+        writeln!(out, "| --- | --- | --- |")?;
+        let yes_no = |value: bool| if value { "yes" } else { "no" };
+        for krate in &analysis.crates {
+            writeln!(out, "| {} | {} | {} |", markdown_code(&krate.name), yes_no(krate.program), yes_no(krate.analyzed))?;
+        }
+    }
+
+    if functions.is_empty() {
+        return Ok(());
+    }
+    writeln!(out, "\n## Potential Panics\n")?;
+    writeln!(out, "| Function | Kind | Code | Location |")?;
+    writeln!(out, "| --- | --- | --- | --- |")?;
+    for function in functions {
+        for site in &function.sites {
+            writeln!(
+                out,
+                "| {} | {} | {} | {} |",
+                markdown_code(&format!("{}::{}", function.krate, function.name)),
+                site.kind,
+                site.code.as_deref().map_or(String::new(), markdown_code),
+                markdown_code(&site.location.to_string()),
+            )?;
+        }
+    }
+    Ok(())
+}
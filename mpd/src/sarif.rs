@@ -0,0 +1,43 @@
+//! SARIF 2.1.0 output: a `potential-panic` warning per site, located at the macro, the
+//! method or the opening bracket.
+
+use std::io::{self, Write};
+use std::path::Path;
+
+use serde_json::json;
+use trr_core::sarif::{location, write_log};
+
+use mpd::FunctionPanics;
+
+const RULE: &str = "potential-panic";
+
+pub fn write_sarif(out: &mut dyn Write, functions: &[FunctionPanics], base: &Path) -> io::Result<()> {
+    let mut results = Vec::new();
+    for function in functions {
+        for site in &function.sites {
+            let text = match &site.code {
+                Some(code) => format!("Potential panic ({}) in {}: {}", site.kind, function.name, code),
+                None => format!("Potential panic ({}) in {}", site.kind, function.name),
+            };
+            results.push(json!({
+                "ruleId": RULE,
+                "ruleIndex": 0,
+                "level": "warning",
+                "message": { "text": text },
+                "locations": [location(&site.location, base)],
+                "properties": { "crate": function.krate, "function": function.name, "kind": site.kind },
+            }));
+        }
+    }
+
+    let rules = vec![json!({
+        "id": RULE,
+        "name": "PotentialPanic",
+        "shortDescription": { "text": "Code that can panic in a program crate" },
+        "fullDescription": {
+            "text": "panic!, unwrap, expect, an assertion, or indexing that can go out of bounds. A panic aborts the transaction without a program error the client can act on; return an error instead (ok_or, require!, get).",
+        },
+        "defaultConfiguration": { "level": "warning" },
+    })];
+    write_log(out, "mpd", env!("CARGO_PKG_VERSION"), rules, results)
+}
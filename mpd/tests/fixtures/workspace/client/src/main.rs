@@ -0,0 +1,4 @@
+fn main() {
+    let url = std::env::args().nth(1).unwrap();
+    println!("{}", url);
+}
@@ -0,0 +1,81 @@
+use anchor_lang::prelude::*;
+
+declare_id!("Fg6PaFpoGXkYsidMpWTK6W2BeZ7FEfcYkg476zPFsLnS");
+
+#[program]
+pub mod escrow {
+    use super::*;
+
+    pub fn make(ctx: Context<Make>, amount: u64, seed: [u8; 8]) -> Result<()> {
+        let escrow = &mut ctx.accounts.escrow;
+        escrow.amount = escrow.amount.checked_add(amount).unwrap();
+        escrow.seed = seed[..].try_into().expect("seed is 8 bytes");
+        msg!("Escrow {} holds {}", escrow.key(), escrow.amount.checked_mul(2).unwrap());
+        Ok(())
+    }
+
+    pub fn take(ctx: Context<Take>, index: usize) -> Result<()> {
+        let data = ctx.accounts.escrow.to_account_info().data.borrow().to_vec();
+        let first = data[index];
+        let header = &data[0..8];
+        let body = &data[..];
+        assert_eq!(header.len(), 8);
+        debug_assert!(first < 8);
+        if body.is_empty() {
+            panic!("empty escrow");
+        }
+        require!(ctx.remaining_accounts[0].is_signer, ErrorCode::ConstraintSigner);
+        Ok(())
+    }
+
+    pub fn close(ctx: Context<Close>) -> Result<()> {
+        let escrow = &ctx.accounts.escrow;
+        let amount = escrow.amount.checked_sub(1).ok_or(ErrorCode::ConstraintRaw)?;
+        let run = || unreachable!();
+        if amount == u64::MAX {
+            run();
+        }
+        Ok(())
+    }
+}
+
+#[account]
+pub struct Escrow {
+    pub amount: u64,
+    pub seed: [u8; 8],
+}
+
+impl Escrow {
+    pub fn parse(data: &[u8]) -> Self {
+        fn read(data: &[u8]) -> u64 {
+            u64::from_le_bytes(data.try_into().unwrap())
+        }
+        Escrow { amount: read(&data[8..16]), seed: [0; 8] }
+    }
+}
+
+#[derive(Accounts)]
+pub struct Make<'info> {
+    #[account(mut)]
+    pub escrow: Account<'info, Escrow>,
+}
+
+#[derive(Accounts)]
+pub struct Take<'info> {
+    #[account(mut)]
+    pub escrow: Account<'info, Escrow>,
+}
+
+#[derive(Accounts)]
+pub struct Close<'info> {
+    pub escrow: Account<'info, Escrow>,
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn parses() {
+        let data = vec![0u8; 16];
+        assert_eq!(super::Escrow::parse(&data).amount, data[8] as u64);
+    }
+}
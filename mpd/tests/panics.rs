@@ -0,0 +1,73 @@
+// Finds the potential panics of tests/fixtures/workspace: the escrow program, and a
+// client crate that is only analyzed with --all-crates.
+
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use mpd::{Options, PanicKind};
+
+fn fixture() -> PathBuf {
+    Path::new(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/workspace")).to_path_buf()
+}
+
+/// (function, kind, line) of every site, by function in source order
+fn sites(options: Options) -> Vec<(String, PanicKind, usize)> {
+    let mut analysis = mpd::analyze(&fixture(), options).unwrap();
+    assert!(analysis.errors.is_empty(), "{:?}", analysis.errors);
+    analysis.functions.sort_by_key(|function| (function.location.file.clone(), function.location.line));
+    analysis.functions.iter()
+        .flat_map(|function| function.sites.iter().map(|site| (format!("{}::{}", function.krate, function.name), site.kind, site.location.line)))
+        .collect()
+}
+
+#[test]
+fn program_sites() {
+    let sites = sites(Options::default());
+    let sites: Vec<_> = sites.iter().map(|(function, kind, line)| (function.as_str(), *kind, *line)).collect();
+    assert_eq!(sites, [
+        ("escrow::escrow::make", PanicKind::Unwrap, 11),
+        ("escrow::escrow::make", PanicKind::Expect, 12),
+        // In the arguments of msg!
+        ("escrow::escrow::make", PanicKind::Unwrap, 13),
+        ("escrow::escrow::take", PanicKind::Index, 19),
+        ("escrow::escrow::take", PanicKind::Slice, 20),
+        ("escrow::escrow::take", PanicKind::Assert, 22),
+        ("escrow::escrow::take", PanicKind::Panic, 25),
+        ("escrow::escrow::take", PanicKind::Index, 27),
+        // In a closure
+        ("escrow::escrow::close", PanicKind::Panic, 34),
+        ("escrow::Escrow::parse", PanicKind::Slice, 53),
+        ("escrow::Escrow::parse::read", PanicKind::Unwrap, 51),
+    ]);
+}
+
+#[test]
+fn options() {
+    let sites_without_indexing = sites(Options { no_indexing: true, ..Options::default() });
+    assert_eq!(sites_without_indexing.len(), 7);
+    assert!(sites_without_indexing.iter().all(|(_, kind, _)| !kind.is_indexing()));
+
+    let all = sites(Options { all_crates: true, ..Options::default() });
+    assert_eq!(all.len(), 12);
+    assert!(all.contains(&("escrow-client::main".to_string(), PanicKind::Unwrap, 2)));
+}
+
+#[test]
+fn limit() {
+    let run = |args: &[&str]| Command::new(env!("CARGO_BIN_EXE_mpd"))
+        .args(args)
+        .arg(fixture())
+        .output()
+        .expect("failed to run mpd");
+
+    assert!(run(&["--fail-above", "11"]).status.success());
+
+    let output = run(&["--fail-above", "6", "--no-indexing", "--format", "json"]);
+    assert_eq!(output.status.code(), Some(1));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("7 potential panic(s), more than 6"), "stderr: {}", stderr);
+    let report: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(report["summary"]["sites"], 7);
+    assert_eq!(report["summary"]["by_kind"]["unwrap"], 3);
+    assert_eq!(report["summary"]["by_kind"]["index"], 0);
+}
@@ -0,0 +1,471 @@
+//! What each function and `#[derive(Accounts)]` struct says about reallocation: the
+//! reallocations, the names that come from instruction data, the names that are
+//! compared or clamped, and the lamport top-ups.
+//!
+//! A reallocation is:
+//! - `account.realloc(new_len, zero_init)`, `AccountInfo::realloc`
+//! - `account.resize(new_len)`, which always zero-fills the bytes it adds
+//! - `#[account(realloc = new_len, realloc::payer = payer, realloc::zero = zero)]`,
+//!   which Anchor tops up from the payer itself
+//!
+//! Instruction data is the parameters of a function other than accounts, keys and the
+//! context, and the `let` bindings computed from them; for an accounts struct, its
+//! `#[instruction(..)]` arguments. A name is bounded when it is compared (`<`, `<=`,
+//! `>`, `>=`, `require_gt!` and the like) anywhere in the function, or clamped with
+//! `min` or `clamp`. Top-ups are a system program `transfer`, `add_lamports` and `+=`
+//! on anything named `lamports`. `#[cfg(test)]` items and `#[test]` functions are left
+//! out, as [`mcd`] leaves them out.
+
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+use proc_macro2::{Span, TokenTree};
+use serde::Serialize;
+use syn::punctuated::Punctuated;
+use syn::spanned::Spanned;
+use syn::visit::{self, Visit};
+use syn::{BinOp, Expr, Fields, ImplItemFn, ItemFn, ItemImpl, ItemMod, ItemStruct, Pat, TraitItemFn, Type};
+use trr_core::anchor::{context_accounts, derives_accounts, keyed_constraints};
+use trr_core::source::{is_cfg_test, location};
+
+use crate::Location;
+
+/// Types of parameters that are accounts, keys or the context rather than instruction
+/// data
+const ACCOUNT_TYPES: &[&str] = &[
+    "Context", "AccountInfo", "Account", "AccountLoader", "Signer", "Program", "Interface", "InterfaceAccount",
+    "UncheckedAccount", "SystemAccount", "Sysvar", "Pubkey", "Rent", "Clock",
+];
+
+/// Modules whose `transfer` is the system program's
+const SYSTEM_MODULES: &[&str] = &["system_instruction", "system_program"];
+
+/// Macros comparing their arguments
+const COMPARING_MACROS: &[&str] = &["require_gt", "require_gte", "require_lt", "require_lte"];
+
+/// Methods that hand back what they are called on, for telling what a size is
+const UNWRAPPING_METHODS: &[&str] = &["unwrap", "expect", "ok_or", "ok_or_else", "unwrap_or", "try_into", "into"];
+
+/// An account reallocation
+#[derive(Clone, Debug, Serialize)]
+pub struct Realloc {
+    /// `realloc`, `resize` or `#[account(realloc)]`
+    pub call: String,
+    /// The account reallocated, as written
+    pub account: String,
+    /// The new size, as written
+    pub size: String,
+    /// The zero-init flag, when it is a literal
+    pub zero_init: Option<bool>,
+    /// The names from instruction data the size is computed from
+    pub influenced_by: Vec<String>,
+    /// One of those names, or one they are computed from, is compared or clamped
+    pub bounded: bool,
+    /// The size is the current one less something
+    pub shrinks: bool,
+    pub location: Location,
+}
+
+#[derive(Debug, Default)]
+pub struct FunctionFacts {
+    /// The accounts struct `T` of a `Context<T>` parameter
+    pub context: Option<String>,
+    pub reallocs: Vec<Realloc>,
+    /// Where lamports are added to an account
+    pub top_ups: Vec<Location>,
+}
+
+#[derive(Clone, Debug)]
+pub struct AccountsStruct {
+    pub name: String,
+    pub file: PathBuf,
+    /// The `realloc` constraints of its accounts
+    pub reallocs: Vec<Realloc>,
+}
+
+#[derive(Debug, Default)]
+pub struct Facts {
+    /// Keyed by the location of the function's name
+    pub functions: HashMap<Location, FunctionFacts>,
+    pub structs: Vec<AccountsStruct>,
+}
+
+/// Add the functions and accounts structs of `file`, parsed from `source`, the
+/// contents of `path`, to `facts`
+pub fn add_file(path: &Path, source: &str, file: &syn::File, facts: &mut Facts) {
+    FileVisitor { path, source, facts }.visit_file(file);
+}
+
+/// The source of `span`, its lines joined with spaces
+fn code(source: &str, span: Span) -> String {
+    let (start, end) = (span.start(), span.end());
+    let lines: Vec<&str> = source.lines().skip(start.line.saturating_sub(1)).take(end.line + 1 - start.line).collect();
+    if lines.len() == 1 {
+        return lines[0].chars().skip(start.column).take(end.column - start.column).collect();
+    }
+    let last = lines.len().saturating_sub(1);
+    lines.iter().enumerate()
+        .map(|(index, line)| match index {
+            0 => line.chars().skip(start.column).collect::<String>(),
+            _ if index == last => line.chars().take(end.column).collect::<String>(),
+            _ => line.to_string(),
+        })
+        .map(|line| line.trim().to_string())
+        .filter(|line| !line.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// The local names an expression reads, in order: single-identifier paths, and the
+/// identifiers of macro arguments
+#[derive(Default)]
+struct Names(Vec<String>);
+
+impl<'ast> Visit<'ast> for Names {
+    fn visit_expr_path(&mut self, path: &'ast syn::ExprPath) {
+        if let Some(ident) = path.path.get_ident() {
+            self.0.push(ident.to_string());
+        }
+    }
+
+    fn visit_macro(&mut self, mac: &'ast syn::Macro) {
+        for tree in mac.tokens.clone() {
+            if let TokenTree::Ident(ident) = tree {
+                self.0.push(ident.to_string());
+            }
+        }
+    }
+}
+
+fn names(expr: &Expr) -> Vec<String> {
+    let mut names = Names::default();
+    names.visit_expr(expr);
+    names.0
+}
+
+/// The names a pattern binds
+fn bindings(pattern: &Pat, names: &mut Vec<String>) {
+    match pattern {
+        Pat::Ident(ident) => names.push(ident.ident.to_string()),
+        Pat::Type(typed) => bindings(&typed.pat, names),
+        Pat::Reference(reference) => bindings(&reference.pat, names),
+        Pat::Tuple(tuple) => tuple.elems.iter().for_each(|elem| bindings(elem, names)),
+        _ => {}
+    }
+}
+
+/// Whether a type names one of [`ACCOUNT_TYPES`]
+#[derive(Default)]
+struct AccountType(bool);
+
+impl<'ast> Visit<'ast> for AccountType {
+    fn visit_ident(&mut self, ident: &'ast syn::Ident) {
+        self.0 |= ACCOUNT_TYPES.contains(&ident.to_string().as_str());
+    }
+}
+
+/// Whether a parameter of type `ty` is instruction data
+fn is_data_type(ty: &Type) -> bool {
+    let mut account = AccountType::default();
+    account.visit_type(ty);
+    !account.0
+}
+
+/// The names bound by the instruction data parameters of a function, each computed
+/// from itself
+fn data_parameters(sig: &syn::Signature) -> HashMap<String, HashSet<String>> {
+    let mut names = Vec::new();
+    for input in &sig.inputs {
+        if let syn::FnArg::Typed(typed) = input {
+            if is_data_type(&typed.ty) {
+                bindings(&typed.pat, &mut names);
+            }
+        }
+    }
+    names.into_iter().map(|name| (name.clone(), HashSet::from([name]))).collect()
+}
+
+/// Whether a size is the current one less something: `len - n`, `len.saturating_sub(n)`
+fn shrinks(size: &Expr) -> bool {
+    match size {
+        Expr::Binary(binary) => matches!(binary.op, BinOp::Sub(_)),
+        Expr::Paren(inner) => shrinks(&inner.expr),
+        Expr::Cast(inner) => shrinks(&inner.expr),
+        Expr::Try(inner) => shrinks(&inner.expr),
+        Expr::MethodCall(call) => match call.method.to_string().as_str() {
+            "saturating_sub" | "checked_sub" | "wrapping_sub" => true,
+            method => UNWRAPPING_METHODS.contains(&method) && shrinks(&call.receiver),
+        },
+        _ => false,
+    }
+}
+
+/// Whether a size is clamped with `min` or `clamp` itself
+#[derive(Default)]
+struct Clamped(bool);
+
+impl<'ast> Visit<'ast> for Clamped {
+    fn visit_expr_method_call(&mut self, call: &'ast syn::ExprMethodCall) {
+        self.0 |= call.method == "min" || call.method == "clamp";
+        visit::visit_expr_method_call(self, call);
+    }
+
+    fn visit_expr_call(&mut self, call: &'ast syn::ExprCall) {
+        self.0 |= matches!(&*call.func, Expr::Path(path) if path.path.segments.last().is_some_and(|segment| segment.ident == "min"));
+        visit::visit_expr_call(self, call);
+    }
+}
+
+fn is_bool(expr: &Expr) -> Option<bool> {
+    match expr {
+        Expr::Lit(syn::ExprLit { lit: syn::Lit::Bool(value), .. }) => Some(value.value),
+        _ => None,
+    }
+}
+
+/// The names of the `#[instruction(..)]` arguments of an accounts struct
+fn instruction_arguments(attrs: &[syn::Attribute]) -> HashSet<String> {
+    let mut names = Vec::new();
+    for attr in attrs.iter().filter(|attr| attr.path().is_ident("instruction")) {
+        let Ok(args) = attr.parse_args_with(Punctuated::<syn::FnArg, syn::Token![,]>::parse_terminated) else { continue };
+        for arg in &args {
+            if let syn::FnArg::Typed(typed) = arg {
+                bindings(&typed.pat, &mut names);
+            }
+        }
+    }
+    names.into_iter().collect()
+}
+
+/// The `realloc` constraint of an account, reading its size against the struct's
+/// instruction arguments
+fn realloc_field(path: &Path, source: &str, field: &syn::Field, data: &HashSet<String>) -> Option<Realloc> {
+    let ident = field.ident.as_ref()?;
+    let constraints = keyed_constraints(&field.attrs);
+    let size = constraints.iter().find(|(key, _)| key == "realloc").map(|(_, value)| value.clone())?;
+    let size: Expr = syn::parse2(size).ok()?;
+    let zero_init = constraints.iter()
+        .find(|(key, _)| key == "realloc::zero")
+        .and_then(|(_, value)| syn::parse2::<Expr>(value.clone()).ok())
+        .and_then(|zero| is_bool(&zero));
+    let mut clamped = Clamped::default();
+    clamped.visit_expr(&size);
+    let mut influenced_by: Vec<String> = Vec::new();
+    for name in names(&size).into_iter().filter(|name| data.contains(name)) {
+        if !influenced_by.contains(&name) {
+            influenced_by.push(name);
+        }
+    }
+    Some(Realloc {
+        call: "#[account(realloc)]".to_string(),
+        account: ident.to_string(),
+        size: code(source, size.span()),
+        zero_init,
+        influenced_by,
+        bounded: clamped.0,
+        shrinks: shrinks(&size),
+        location: location(path, ident.span()),
+    })
+}
+
+struct FileVisitor<'a> {
+    path: &'a Path,
+    source: &'a str,
+    facts: &'a mut Facts,
+}
+
+impl FileVisitor<'_> {
+    fn add_function(&mut self, sig: &syn::Signature, block: &syn::Block) {
+        let mut body = BodyVisitor {
+            path: self.path,
+            source: self.source,
+            data: data_parameters(sig),
+            compared: HashSet::new(),
+            sources: Vec::new(),
+            facts: FunctionFacts { context: context_accounts(sig), ..FunctionFacts::default() },
+        };
+        body.visit_block(block);
+        let BodyVisitor { compared, sources, mut facts, .. } = body;
+        for (realloc, sources) in facts.reallocs.iter_mut().zip(sources) {
+            realloc.bounded |= sources.iter().any(|name| compared.contains(name));
+        }
+        self.facts.functions.insert(location(self.path, sig.ident.span()), facts);
+    }
+}
+
+impl<'ast> Visit<'ast> for FileVisitor<'_> {
+    fn visit_item_mod(&mut self, item: &'ast ItemMod) {
+        if !is_cfg_test(&item.attrs) {
+            visit::visit_item_mod(self, item);
+        }
+    }
+
+    fn visit_item_fn(&mut self, item: &'ast ItemFn) {
+        if !is_cfg_test(&item.attrs) {
+            self.add_function(&item.sig, &item.block);
+        }
+    }
+
+    fn visit_item_impl(&mut self, item: &'ast ItemImpl) {
+        if !is_cfg_test(&item.attrs) {
+            visit::visit_item_impl(self, item);
+        }
+    }
+
+    fn visit_impl_item_fn(&mut self, item: &'ast ImplItemFn) {
+        if !is_cfg_test(&item.attrs) {
+            self.add_function(&item.sig, &item.block);
+        }
+    }
+
+    fn visit_trait_item_fn(&mut self, item: &'ast TraitItemFn) {
+        if let Some(block) = &item.default {
+            self.add_function(&item.sig, block);
+        }
+    }
+
+    fn visit_item_struct(&mut self, item: &'ast ItemStruct) {
+        if is_cfg_test(&item.attrs) || !derives_accounts(&item.attrs) {
+            return;
+        }
+        let Fields::Named(fields) = &item.fields else { return };
+        let data = instruction_arguments(&item.attrs);
+        self.facts.structs.push(AccountsStruct {
+            name: item.ident.to_string(),
+            file: self.path.to_path_buf(),
+            reallocs: fields.named.iter().filter_map(|field| realloc_field(self.path, self.source, field, &data)).collect(),
+        });
+    }
+}
+
+/// Whether an expression names something `lamports`
+#[derive(Default)]
+struct Lamports(bool);
+
+impl<'ast> Visit<'ast> for Lamports {
+    fn visit_ident(&mut self, ident: &'ast syn::Ident) {
+        self.0 |= ident.to_string().contains("lamports");
+    }
+}
+
+/// The reallocations, bounds and top-ups of one function body, leaving out the items
+/// declared in it
+struct BodyVisitor<'a> {
+    path: &'a Path,
+    source: &'a str,
+    /// Names holding instruction data, with the names they are computed from
+    data: HashMap<String, HashSet<String>>,
+    /// Names compared or clamped
+    compared: HashSet<String>,
+    /// The names each reallocation's size is computed from, in order
+    sources: Vec<HashSet<String>>,
+    facts: FunctionFacts,
+}
+
+impl BodyVisitor<'_> {
+    fn add(&mut self, call: &str, account: &Expr, size: &Expr, zero_init: Option<bool>, span: Span) {
+        let mut influenced_by: Vec<String> = Vec::new();
+        for name in names(size).into_iter().filter(|name| self.data.contains_key(name)) {
+            if !influenced_by.contains(&name) {
+                influenced_by.push(name);
+            }
+        }
+        let mut clamped = Clamped::default();
+        clamped.visit_expr(size);
+        self.sources.push(self.sources(size));
+        self.facts.reallocs.push(Realloc {
+            call: call.to_string(),
+            account: code(self.source, account.span()),
+            size: code(self.source, size.span()),
+            zero_init,
+            influenced_by,
+            bounded: clamped.0,
+            shrinks: shrinks(size),
+            location: location(self.path, span),
+        });
+    }
+
+    /// The instruction data names `expr` reads, and those they are computed from
+    fn sources(&self, expr: &Expr) -> HashSet<String> {
+        names(expr).iter().filter_map(|name| self.data.get(name)).flatten().cloned().collect()
+    }
+
+    fn compare(&mut self, exprs: &[&Expr]) {
+        for expr in exprs {
+            self.compared.extend(names(expr));
+        }
+    }
+}
+
+impl<'ast> Visit<'ast> for BodyVisitor<'_> {
+    fn visit_item(&mut self, _: &'ast syn::Item) {}
+
+    fn visit_local(&mut self, local: &'ast syn::Local) {
+        visit::visit_local(self, local);
+        let Some(init) = &local.init else { return };
+        let sources = self.sources(&init.expr);
+        if sources.is_empty() {
+            return;
+        }
+        let mut bound = Vec::new();
+        bindings(&local.pat, &mut bound);
+        for name in bound {
+            let mut sources = sources.clone();
+            sources.insert(name.clone());
+            self.data.insert(name, sources);
+        }
+    }
+
+    fn visit_expr_call(&mut self, call: &'ast syn::ExprCall) {
+        if let Expr::Path(path) = &*call.func {
+            let segments: Vec<String> = path.path.segments.iter().map(|segment| segment.ident.to_string()).collect();
+            let system = segments.iter().any(|segment| SYSTEM_MODULES.contains(&segment.as_str()));
+            match segments.last().map(String::as_str) {
+                Some("transfer") if system => self.facts.top_ups.push(location(self.path, path.span())),
+                Some("min") => self.compare(&call.args.iter().collect::<Vec<_>>()),
+                _ => {}
+            }
+        }
+        visit::visit_expr_call(self, call);
+    }
+
+    fn visit_expr_method_call(&mut self, call: &'ast syn::ExprMethodCall) {
+        let args: Vec<&Expr> = call.args.iter().collect();
+        match (call.method.to_string().as_str(), args.as_slice()) {
+            ("realloc", [size, zero_init]) => self.add("realloc", &call.receiver, size, is_bool(zero_init), call.method.span()),
+            ("resize", [size]) => self.add("resize", &call.receiver, size, Some(true), call.method.span()),
+            ("add_lamports", _) => self.facts.top_ups.push(location(self.path, call.method.span())),
+            ("min" | "clamp", _) => {
+                self.compare(&[&call.receiver]);
+                self.compare(&args);
+            }
+            _ => {}
+        }
+        visit::visit_expr_method_call(self, call);
+    }
+
+    fn visit_expr_binary(&mut self, expr: &'ast syn::ExprBinary) {
+        match expr.op {
+            BinOp::Lt(_) | BinOp::Le(_) | BinOp::Gt(_) | BinOp::Ge(_) => self.compare(&[&expr.left, &expr.right]),
+            BinOp::AddAssign(_) => {
+                let mut lamports = Lamports::default();
+                lamports.visit_expr(&expr.left);
+                if lamports.0 {
+                    self.facts.top_ups.push(location(self.path, expr.op.span()));
+                }
+            }
+            _ => {}
+        }
+        visit::visit_expr_binary(self, expr);
+    }
+
+    fn visit_macro(&mut self, mac: &'ast syn::Macro) {
+        let Ok(args) = mac.parse_body_with(Punctuated::<Expr, syn::Token![,]>::parse_terminated) else { return };
+        if mac.path.get_ident().is_some_and(|ident| COMPARING_MACROS.contains(&ident.to_string().as_str())) {
+            self.compare(&args.iter().collect::<Vec<_>>());
+        }
+        for arg in &args {
+            self.visit_expr(arg);
+        }
+    }
+}
@@ -0,0 +1,215 @@
+//! Realloc audit: every account reallocation an instruction handler reaches, with its
+//! zero-init flag, whether its new size comes from instruction data, and whether the
+//! lamports follow the growth.
+//!
+//! Reallocation has been behind real exploits. Without zero-init, an account shrunk
+//! and grown again in one transaction shows the bytes it had before; a size taken from
+//! instruction data lets the caller grow an account as far as the runtime allows, at
+//! the payer's expense or past what the program reads safely; and an account grown
+//! without lamports for its new size falls short of rent. See [`facts`] for what
+//! counts as a reallocation, instruction data, a bound and a top-up.
+//!
+//! Handlers and the functions they call come from [`mcd`]. A reallocation is topped up
+//! when lamports are added in its own function, in a function it calls, or in a
+//! function on the way to it from the handler, as `mre` checks rent. Anchor's `realloc`
+//! constraint tops up from `realloc::payer` itself, and runs before the handler, so
+//! only `min` or `clamp` in the constraint bounds its size.
+
+pub mod facts;
+
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use mcd::FunctionId;
+use serde::Serialize;
+pub use trr_core::Location;
+use trr_core::{source, walk};
+
+use facts::{AccountsStruct, Facts, FunctionFacts, Realloc};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Problem {
+    /// Grows without zero-init
+    ZeroInitOff,
+    /// Grows to a size from instruction data that is never compared or clamped
+    AttackerSized,
+    /// Grows without lamports added on the way
+    NoTopUp,
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.pad(match self {
+            Problem::ZeroInitOff => "zero-init off",
+            Problem::AttackerSized => "sized by unbounded instruction data",
+            Problem::NoTopUp => "no lamport top-up",
+        })
+    }
+}
+
+/// A reallocation the handlers of a program reach
+#[derive(Clone, Debug, Serialize)]
+pub struct CheckedRealloc {
+    #[serde(flatten)]
+    pub realloc: Realloc,
+    pub program: String,
+    /// Path of the function it is in, or the accounts struct of a `realloc` constraint
+    pub function: String,
+    /// The handlers reaching it
+    pub handlers: Vec<String>,
+    /// Every handler reaching it adds lamports on the way
+    pub topped_up: bool,
+    pub problems: Vec<Problem>,
+}
+
+impl CheckedRealloc {
+    fn new(realloc: Realloc, program: String, function: String, handlers: Vec<String>, topped_up: bool) -> Self {
+        let mut problems = Vec::new();
+        if realloc.zero_init == Some(false) && !realloc.shrinks {
+            problems.push(Problem::ZeroInitOff);
+        }
+        if !realloc.influenced_by.is_empty() && !realloc.bounded && !realloc.shrinks {
+            problems.push(Problem::AttackerSized);
+        }
+        if !topped_up && !realloc.shrinks {
+            problems.push(Problem::NoTopUp);
+        }
+        CheckedRealloc { realloc, program, function, handlers, topped_up, problems }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Check {
+    /// By program, then file and line
+    pub reallocs: Vec<CheckedRealloc>,
+}
+
+impl Check {
+    /// The reallocations with a problem
+    pub fn flagged(&self) -> impl Iterator<Item = &CheckedRealloc> {
+        self.reallocs.iter().filter(|realloc| !realloc.problems.is_empty())
+    }
+
+    /// How many reallocations have `problem`
+    pub fn count(&self, problem: Problem) -> usize {
+        self.reallocs.iter().filter(|realloc| realloc.problems.contains(&problem)).count()
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Analysis {
+    pub programs: mcd::Analysis,
+    pub facts: Facts,
+    /// Files that could not be read or parsed
+    pub errors: Vec<(PathBuf, String)>,
+}
+
+impl Analysis {
+    fn function_facts(&self, (program, function): FunctionId) -> Option<&FunctionFacts> {
+        self.facts.functions.get(&self.programs.programs[program].functions[function].location)
+    }
+
+    /// The functions reachable from `start`, itself included
+    fn reachable(&self, start: FunctionId) -> HashSet<FunctionId> {
+        let mut reached = HashSet::from([start]);
+        let mut stack = vec![start];
+        while let Some(id) = stack.pop() {
+            for call in &self.programs.programs[id.0].functions[id.1].calls {
+                for callee in self.programs.callees(id.0, call) {
+                    if reached.insert(callee) {
+                        stack.push(callee);
+                    }
+                }
+            }
+        }
+        reached
+    }
+
+    fn tops_up(&self, id: FunctionId) -> bool {
+        self.function_facts(id).is_some_and(|facts| !facts.top_ups.is_empty())
+    }
+
+    /// The accounts struct named `name`, preferably in `program`'s own crate
+    fn accounts_struct(&self, program: usize, name: &str) -> Option<&AccountsStruct> {
+        let root = &self.programs.programs[program].root;
+        let mut named = self.facts.structs.iter().filter(|accounts| accounts.name == name);
+        named.clone().find(|accounts| accounts.file.starts_with(root)).or_else(|| named.next())
+    }
+
+    /// Every reallocation the handlers reach, and the `realloc` constraints of their
+    /// accounts structs
+    pub fn check(&self) -> Check {
+        let mut check = Check::default();
+        let mut reachable: HashMap<FunctionId, HashSet<FunctionId>> = HashMap::new();
+        let mut reach = |id: FunctionId| reachable.entry(id).or_insert_with(|| self.reachable(id)).clone();
+
+        for (index, program) in self.programs.programs.iter().enumerate() {
+            // By function: the handlers reaching it, and whether they all top up
+            let mut reached_by: Vec<(FunctionId, Vec<String>, bool)> = Vec::new();
+            for handler in &program.handlers {
+                let reached = reach((index, handler.function));
+                for &id in &reached {
+                    if self.function_facts(id).is_none_or(|facts| facts.reallocs.is_empty()) {
+                        continue;
+                    }
+                    // On the way from the handler, and below
+                    let topped_up = reach(id).into_iter().any(|below| self.tops_up(below))
+                        || reached.iter().any(|&above| self.tops_up(above) && reach(above).contains(&id));
+                    match reached_by.iter_mut().find(|(known, _, _)| *known == id) {
+                        Some((_, handlers, all)) => {
+                            handlers.push(handler.name.clone());
+                            *all &= topped_up;
+                        }
+                        None => reached_by.push((id, vec![handler.name.clone()], topped_up)),
+                    }
+                }
+            }
+            for (id, mut handlers, topped_up) in reached_by {
+                let Some(facts) = self.function_facts(id) else { continue };
+                handlers.sort();
+                let function = program.functions[id.1].path.join("::");
+                for realloc in &facts.reallocs {
+                    check.reallocs.push(CheckedRealloc::new(realloc.clone(), program.name.clone(), function.clone(), handlers.clone(), topped_up));
+                }
+            }
+
+            // The accounts structs of the program's handlers
+            let mut structs: Vec<(&AccountsStruct, Vec<String>)> = Vec::new();
+            for handler in &program.handlers {
+                let Some(accounts) = self.function_facts((index, handler.function))
+                    .and_then(|facts| facts.context.as_deref())
+                    .and_then(|name| self.accounts_struct(index, name))
+                else {
+                    continue;
+                };
+                match structs.iter_mut().find(|(known, _)| std::ptr::eq(*known, accounts)) {
+                    Some((_, handlers)) => handlers.push(handler.name.clone()),
+                    None => structs.push((accounts, vec![handler.name.clone()])),
+                }
+            }
+            for (accounts, handlers) in structs {
+                for realloc in &accounts.reallocs {
+                    // Anchor transfers from `realloc::payer`
+                    check.reallocs.push(CheckedRealloc::new(realloc.clone(), program.name.clone(), accounts.name.clone(), handlers.clone(), true));
+                }
+            }
+        }
+        check.reallocs.sort_by(|a, b| a.program.cmp(&b.program)
+            .then_with(|| a.realloc.location.file.cmp(&b.realloc.location.file))
+            .then_with(|| a.realloc.location.line.cmp(&b.realloc.location.line)));
+        check
+    }
+}
+
+/// Analyze every Rust file under `dir` (or `dir` itself, if it is a file), leaving out
+/// `target/` and test code
+pub fn analyze(dir: &Path) -> io::Result<Analysis> {
+    let programs = mcd::analyze(dir)?;
+    let mut facts = Facts::default();
+    let mut errors = programs.errors.clone();
+    source::parse_files(walk::program_files(dir)?, &mut errors, |path, source, file| facts::add_file(&path, source, &file, &mut facts));
+    Ok(Analysis { programs, facts, errors })
+}
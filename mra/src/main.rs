@@ -0,0 +1,191 @@
+mod report;
+mod sarif;
+
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::process;
+
+use clap::Parser;
+use serde::Serialize;
+use trr_core::git::Repository;
+use trr_core::output::{self, Format};
+
+use mra::{Analysis, Check, CheckedRealloc, Problem};
+
+#[derive(Parser)]
+#[clap(author, version, about = "Realloc Auditor (MRA)")]
+#[clap(after_help = "Examples:
+  mra programs/vault
+  mra -v programs
+  mra --format sarif programs > realloc.sarif
+  mra --fail-above 0 programs")]
+struct Args {
+    /// Directory to analyze (relative to --repo when given)
+    #[clap(value_name = "DIR", default_value = ".")]
+    dir: PathBuf,
+
+    /// Git repository URL to clone, or path of a local checkout
+    #[clap(long, value_name = "REPO_URL_OR_PATH")]
+    repo: Option<String>,
+
+    /// Branch, tag or commit of --repo to analyze
+    #[clap(long, value_name = "REV", requires = "repo")]
+    rev: Option<String>,
+
+    /// Output format
+    #[clap(long, value_enum, default_value = "text")]
+    format: Format,
+
+    /// List every reallocation, not only those with a problem
+    #[clap(short, long)]
+    verbose: bool,
+
+    /// Exit with status 1 if more than N reallocations have a problem
+    #[clap(long, value_name = "N")]
+    fail_above: Option<usize>,
+}
+
+/// Structured form of the analysis for `--format json`
+#[derive(Serialize)]
+struct JsonReport<'a> {
+    summary: Summary,
+    /// By program, then file and line
+    reallocs: &'a [CheckedRealloc],
+    errors: Vec<FileError>,
+}
+
+#[derive(Serialize)]
+struct Summary {
+    programs: usize,
+    reallocs: usize,
+    zero_init_off: usize,
+    attacker_sized: usize,
+    no_top_up: usize,
+    /// Reallocations with a problem
+    flagged: usize,
+}
+
+#[derive(Serialize)]
+struct FileError {
+    file: PathBuf,
+    error: String,
+}
+
+/// The zero-init flag, where the size comes from and the lamports, in a few words
+fn describe(realloc: &CheckedRealloc) -> String {
+    let zero_init = match realloc.realloc.zero_init {
+        Some(true) => "zero-init on",
+        Some(false) => "zero-init off",
+        None => "zero-init not a literal",
+    };
+    let size = match (realloc.realloc.influenced_by.is_empty(), realloc.realloc.bounded) {
+        (true, _) => "size not from instruction data".to_string(),
+        (false, bounded) => format!(
+            "size from instruction data ({}), {}",
+            realloc.realloc.influenced_by.join(", "),
+            if bounded { "bounded" } else { "unbounded" }
+        ),
+    };
+    let lamports = match (realloc.realloc.shrinks, realloc.topped_up) {
+        (true, _) => "shrinks",
+        (false, true) => "topped up",
+        (false, false) => "not topped up",
+    };
+    format!("{}; {}; {}", zero_init, size, lamports)
+}
+
+fn print_realloc(realloc: &CheckedRealloc) {
+    println!(
+        "\n{}::{}: {} of {} to {} at {}",
+        realloc.program, realloc.function, realloc.realloc.call, realloc.realloc.account, realloc.realloc.size, realloc.realloc.location
+    );
+    println!("  {}", describe(realloc));
+    println!("  Handlers: {}", realloc.handlers.join(", "));
+    if !realloc.problems.is_empty() {
+        let problems: Vec<String> = realloc.problems.iter().map(Problem::to_string).collect();
+        println!("  Problems: {}", problems.join(", "));
+    }
+}
+
+fn print_text(analysis: &Analysis, check: &Check, verbose: bool) {
+    if verbose && !check.reallocs.is_empty() {
+        println!("\nReallocations:");
+        check.reallocs.iter().for_each(print_realloc);
+    } else if check.flagged().next().is_some() {
+        println!("\nReallocations with a problem:");
+        check.flagged().for_each(print_realloc);
+    }
+
+    println!("\nSummary:");
+    println!("Programs: {}", analysis.programs.programs.len());
+    println!("Reallocations: {} ({} with a problem)", check.reallocs.len(), check.flagged().count());
+    println!("Growing without zero-init: {}", check.count(Problem::ZeroInitOff));
+    println!("Sized by unbounded instruction data: {}", check.count(Problem::AttackerSized));
+    println!("Growing without a lamport top-up: {}", check.count(Problem::NoTopUp));
+}
+
+fn write_json(out: &mut dyn Write, analysis: &Analysis, check: &Check) -> io::Result<()> {
+    let report = JsonReport {
+        summary: Summary {
+            programs: analysis.programs.programs.len(),
+            reallocs: check.reallocs.len(),
+            zero_init_off: check.count(Problem::ZeroInitOff),
+            attacker_sized: check.count(Problem::AttackerSized),
+            no_top_up: check.count(Problem::NoTopUp),
+            flagged: check.flagged().count(),
+        },
+        reallocs: &check.reallocs,
+        errors: analysis.errors.iter()
+            .map(|(file, error)| FileError { file: file.clone(), error: error.clone() })
+            .collect(),
+    };
+    output::write_json(out, "mra", &report)
+}
+
+fn run(args: &Args) -> Result<(), Box<dyn std::error::Error>> {
+    let repository = args.repo.as_deref().map(|repo| Repository::open(repo, args.rev.as_deref())).transpose()?;
+    let dir = match &repository {
+        Some(repository) => repository.subdir(&args.dir)?,
+        None => args.dir.clone(),
+    };
+    if !dir.exists() {
+        return Err(format!("Path '{}' does not exist", dir.display()).into());
+    }
+    if args.format == Format::Text {
+        println!("Analyzing: {}", dir.display());
+    }
+
+    let analysis = mra::analyze(&dir)?;
+    for (file, error) in &analysis.errors {
+        eprintln!("Error analyzing {}: {}", file.display(), error);
+    }
+    let check = analysis.check();
+
+    let mut out = io::stdout().lock();
+    match args.format {
+        Format::Text => print_text(&analysis, &check, args.verbose),
+        Format::Json => write_json(&mut out, &analysis, &check)?,
+        Format::Csv => report::write_csv(&mut out, &check)?,
+        Format::Markdown => report::write_markdown(&mut out, &dir, &check)?,
+        Format::Sarif => {
+            // Artifact URIs are relative to the repository root, or to where mra runs
+            let base = repository.as_ref().map_or_else(|| PathBuf::from("."), |repository| repository.root().to_path_buf());
+            sarif::write_sarif(&mut out, &check, &base)?;
+            writeln!(out)?;
+        }
+    }
+    drop(out);
+    output::enforce_limit(args.fail_above, check.flagged().count(), "reallocation(s) with a problem", check.flagged().map(|realloc| {
+        let problems: Vec<String> = realloc.problems.iter().map(Problem::to_string).collect();
+        format!("{}::{} ({}) at {}: {}", realloc.program, realloc.function, realloc.realloc.account, realloc.realloc.location, problems.join(", "))
+    }));
+    Ok(())
+}
+
+fn main() {
+    let args = Args::parse();
+    if let Err(e) = run(&args) {
+        eprintln!("Error: {}", e);
+        process::exit(1);
+    }
+}
@@ -0,0 +1,80 @@
+//! `--format csv`, one row per reallocation, and `--format markdown`, a review-ready
+//! summary and the reallocations with a problem.
+
+use std::io::{self, Write};
+use std::path::Path;
+
+use trr_core::output::{markdown_cell, markdown_code, write_csv_row};
+
+use mra::{Check, Problem};
+
+fn zero_init(value: Option<bool>) -> &'static str {
+    match value {
+        Some(true) => "true",
+        Some(false) => "false",
+        None => "",
+    }
+}
+
+fn problems(problems: &[Problem]) -> Vec<String> {
+    problems.iter().map(Problem::to_string).collect()
+}
+
+pub fn write_csv(out: &mut dyn Write, check: &Check) -> io::Result<()> {
+    write_csv_row(out, &[
+        "program", "function", "handlers", "call", "account", "size", "zero_init", "influenced_by", "bounded",
+        "shrinks", "topped_up", "problems", "file", "line",
+    ])?;
+    for realloc in &check.reallocs {
+        write_csv_row(out, &[
+            &realloc.program,
+            &realloc.function,
+            &realloc.handlers.join(" "),
+            &realloc.realloc.call,
+            &realloc.realloc.account,
+            &realloc.realloc.size,
+            zero_init(realloc.realloc.zero_init),
+            &realloc.realloc.influenced_by.join(" "),
+            &realloc.realloc.bounded.to_string(),
+            &realloc.realloc.shrinks.to_string(),
+            &realloc.topped_up.to_string(),
+            &problems(&realloc.problems).join("; "),
+            &realloc.realloc.location.file.display().to_string(),
+            &realloc.realloc.location.line.to_string(),
+        ])?;
+    }
+    Ok(())
+}
+
+pub fn write_markdown(out: &mut dyn Write, target: &Path, check: &Check) -> io::Result<()> {
+    writeln!(out, "# Realloc Audit\n")?;
+    writeln!(out, "Target: {}\n", markdown_code(&target.display().to_string()))?;
+    writeln!(out, "## Summary\n")?;
+    writeln!(out, "| Metric | Value |")?;
+    writeln!(out, "| --- | ---: |")?;
+    writeln!(out, "| Reallocations | {} |", check.reallocs.len())?;
+    writeln!(out, "| With a problem | {} |", check.flagged().count())?;
+    writeln!(out, "| Growing without zero-init | {} |", check.count(Problem::ZeroInitOff))?;
+    writeln!(out, "| Sized by unbounded instruction data | {} |", check.count(Problem::AttackerSized))?;
+    writeln!(out, "| Growing without a lamport top-up | {} |", check.count(Problem::NoTopUp))?;
+
+    if check.flagged().next().is_none() {
+        return Ok(());
+    }
+    writeln!(out, "\n## Reallocations with a Problem\n")?;
+    writeln!(out, "| Account | Size | Function | Handlers | Problems | Location |")?;
+    writeln!(out, "| --- | --- | --- | --- | --- | --- |")?;
+    for realloc in check.flagged() {
+        writeln!(
+            out,
+            "| {} | {} | {} | {} | {} | {} |",
+            markdown_code(&realloc.realloc.account),
+            markdown_code(&realloc.realloc.size),
+            markdown_code(&format!("{}::{}", realloc.program, realloc.function)),
+            markdown_cell(&realloc.handlers.join(", ")),
+            markdown_cell(&problems(&realloc.problems).join(", ")),
+            markdown_code(&realloc.realloc.location.to_string()),
+        )?;
+    }
+    Ok(())
+}
@@ -0,0 +1,71 @@
+//! SARIF 2.1.0 output: a result per problem of a reallocation, located at the
+//! reallocation, under `realloc-zero-init`, `realloc-attacker-size` or
+//! `realloc-top-up`.
+
+use std::io::{self, Write};
+use std::path::Path;
+
+use serde_json::json;
+use trr_core::sarif::{location, write_log};
+
+use mra::{Check, Problem};
+
+fn rule_id(problem: Problem) -> &'static str {
+    match problem {
+        Problem::ZeroInitOff => "realloc-zero-init",
+        Problem::AttackerSized => "realloc-attacker-size",
+        Problem::NoTopUp => "realloc-top-up",
+    }
+}
+
+pub fn write_sarif(out: &mut dyn Write, check: &Check, base: &Path) -> io::Result<()> {
+    let mut results: Vec<_> = Vec::new();
+    for realloc in check.flagged() {
+        for &problem in &realloc.problems {
+            results.push(json!({
+                "ruleId": rule_id(problem),
+                "level": "warning",
+                "message": {
+                    "text": format!(
+                        "{} of {} to {} in {}::{} ({}): {}",
+                        realloc.realloc.call, realloc.realloc.account, realloc.realloc.size, realloc.program,
+                        realloc.function, realloc.handlers.join(", "), problem
+                    ),
+                },
+                "locations": [location(&realloc.realloc.location, base)],
+                "properties": { "influencedBy": realloc.realloc.influenced_by, "zeroInit": realloc.realloc.zero_init },
+            }));
+        }
+    }
+
+    let rules = vec![
+        json!({
+            "id": rule_id(Problem::ZeroInitOff),
+            "name": "ReallocZeroInit",
+            "shortDescription": { "text": "Account grown without zero-init" },
+            "fullDescription": {
+                "text": "Without zero-init, an account shrunk and grown again in the same transaction shows the bytes it held before. Pass zero_init = true to realloc, use resize, or set realloc::zero = true.",
+            },
+            "defaultConfiguration": { "level": "warning" },
+        }),
+        json!({
+            "id": rule_id(Problem::AttackerSized),
+            "name": "ReallocAttackerSize",
+            "shortDescription": { "text": "Account sized by unbounded instruction data" },
+            "fullDescription": {
+                "text": "The new size comes from instruction data that is never compared or clamped, so the caller chooses how far the account grows. Check it against a maximum before reallocating.",
+            },
+            "defaultConfiguration": { "level": "warning" },
+        }),
+        json!({
+            "id": rule_id(Problem::NoTopUp),
+            "name": "ReallocTopUp",
+            "shortDescription": { "text": "Account grown without a lamport top-up" },
+            "fullDescription": {
+                "text": "An account grown without lamports for its new size falls short of the rent-exempt minimum, and the instruction fails. Transfer Rent::minimum_balance(new_len) less the current balance before or after reallocating.",
+            },
+            "defaultConfiguration": { "level": "warning" },
+        }),
+    ];
+    write_log(out, "mra", env!("CARGO_PKG_VERSION"), rules, results)
+}
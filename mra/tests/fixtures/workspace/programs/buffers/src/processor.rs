@@ -0,0 +1,47 @@
+use solana_program::account_info::{next_account_info, AccountInfo};
+use solana_program::entrypoint::ProgramResult;
+use solana_program::program::invoke;
+use solana_program::pubkey::Pubkey;
+use solana_program::rent::Rent;
+use solana_program::system_instruction;
+use solana_program::sysvar::Sysvar;
+
+const CHUNK: usize = 1024;
+
+pub fn process(_program_id: &Pubkey, accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
+    match data.first() {
+        Some(0) => grow(accounts, data),
+        _ => grow_funded(accounts),
+    }
+}
+
+// The caller picks the length, stale bytes show, and rent is not paid
+fn grow(accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
+    let accounts = &mut accounts.iter();
+    let buffer = next_account_info(accounts)?;
+    let len = u32::from_le_bytes(data[1..5].try_into().unwrap()) as usize;
+    buffer.realloc(len, false)?;
+    Ok(())
+}
+
+// A fixed chunk, paid for before growing
+fn grow_funded(accounts: &[AccountInfo]) -> ProgramResult {
+    let accounts = &mut accounts.iter();
+    let payer = next_account_info(accounts)?;
+    let buffer = next_account_info(accounts)?;
+    let new_len = buffer.data_len() + CHUNK;
+    let lamports = Rent::get()?.minimum_balance(new_len) - buffer.lamports();
+    invoke(
+        &system_instruction::transfer(payer.key, buffer.key, lamports),
+        &[payer.clone(), buffer.clone()],
+    )?;
+    buffer.resize(new_len)
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn grows() {
+        let _ = super::CHUNK;
+    }
+}
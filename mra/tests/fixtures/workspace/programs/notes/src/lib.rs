@@ -0,0 +1,66 @@
+use anchor_lang::prelude::*;
+use anchor_lang::system_program;
+
+const MAX_TEXT: usize = 256;
+
+#[program]
+pub mod notes {
+    use super::*;
+
+    // Anchor tops up from the payer, but the length is the caller's and zero is off
+    pub fn resize_note(_ctx: Context<ResizeNote>, _len: u16) -> Result<()> {
+        Ok(())
+    }
+
+    // Bounded, topped up and zeroed
+    pub fn append(ctx: Context<Append>, text: String) -> Result<()> {
+        require!(text.len() <= MAX_TEXT, NotesError::TooLong);
+        let note = ctx.accounts.note.to_account_info();
+        let new_len = note.data_len() + text.len();
+        let lamports = Rent::get()?.minimum_balance(new_len).saturating_sub(note.lamports());
+        let accounts = system_program::Transfer {
+            from: ctx.accounts.author.to_account_info(),
+            to: note.clone(),
+        };
+        system_program::transfer(CpiContext::new(ctx.accounts.system_program.to_account_info(), accounts), lamports)?;
+        note.realloc(new_len, true)?;
+        Ok(())
+    }
+
+    // Shrinking needs neither zero-init nor lamports
+    pub fn truncate(ctx: Context<Append>, count: u16) -> Result<()> {
+        let note = ctx.accounts.note.to_account_info();
+        note.realloc(note.data_len() - count as usize, false)?;
+        Ok(())
+    }
+}
+
+#[derive(Accounts)]
+#[instruction(len: u16)]
+pub struct ResizeNote<'info> {
+    #[account(mut, realloc = 8 + len as usize, realloc::payer = author, realloc::zero = false)]
+    pub note: Account<'info, Note>,
+    #[account(mut)]
+    pub author: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct Append<'info> {
+    #[account(mut)]
+    pub note: Account<'info, Note>,
+    #[account(mut)]
+    pub author: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[account]
+pub struct Note {
+    pub text: String,
+}
+
+#[error_code]
+pub enum NotesError {
+    #[msg("The note would be too long")]
+    TooLong,
+}
@@ -0,0 +1,88 @@
+// Audits tests/fixtures/workspace: an Anchor program growing a note through a
+// `realloc` constraint, a bounded and funded `realloc` and a shrinking one, and a
+// native program whose dispatcher reaches an unchecked and a funded reallocation.
+
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use mra::{Check, Problem};
+use serde_json::Value;
+
+fn fixture() -> PathBuf {
+    Path::new(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/workspace")).to_path_buf()
+}
+
+fn check() -> Check {
+    let analysis = mra::analyze(&fixture()).unwrap();
+    assert!(analysis.errors.is_empty(), "{:?}", analysis.errors);
+    analysis.check()
+}
+
+#[test]
+fn problems_per_realloc() {
+    let check = check();
+    let reallocs: Vec<(&str, &str, &[Problem])> = check.reallocs.iter()
+        .map(|realloc| (realloc.function.as_str(), realloc.realloc.call.as_str(), realloc.problems.as_slice()))
+        .collect();
+    assert_eq!(reallocs, [
+        ("processor::grow", "realloc", &[Problem::ZeroInitOff, Problem::AttackerSized, Problem::NoTopUp][..]),
+        ("processor::grow_funded", "resize", &[][..]),
+        ("notes::append", "realloc", &[][..]),
+        ("notes::truncate", "realloc", &[][..]),
+        ("ResizeNote", "#[account(realloc)]", &[Problem::ZeroInitOff, Problem::AttackerSized][..]),
+    ]);
+
+    // The native dispatcher is one handler: the transfer of one instruction does not
+    // fund the other
+    assert_eq!(check.reallocs[0].handlers, ["process_instruction"]);
+    assert!(!check.reallocs[0].topped_up);
+    assert!(check.reallocs[1].topped_up);
+}
+
+#[test]
+fn sizes_and_flags() {
+    let check = check();
+    let grow = &check.reallocs[0].realloc;
+    assert_eq!((grow.size.as_str(), grow.zero_init, grow.influenced_by.as_slice()), ("len", Some(false), &["len".to_string()][..]));
+
+    // new_len comes from text, which is compared with MAX_TEXT
+    let append = &check.reallocs[2].realloc;
+    assert_eq!(append.influenced_by, ["new_len"]);
+    assert!(append.bounded);
+
+    let truncate = &check.reallocs[3].realloc;
+    assert_eq!(truncate.size, "note.data_len() - count as usize");
+    assert!(truncate.shrinks);
+
+    let constraint = &check.reallocs[4];
+    assert_eq!((constraint.realloc.account.as_str(), constraint.realloc.size.as_str()), ("note", "8 + len as usize"));
+    assert_eq!(constraint.handlers, ["resize_note"]);
+    assert!(constraint.topped_up);
+}
+
+#[test]
+fn json_and_limits() {
+    let run = |args: &[&str]| Command::new(env!("CARGO_BIN_EXE_mra"))
+        .args(args)
+        .arg(fixture())
+        .output()
+        .expect("failed to run mra");
+
+    let output = run(&["--format", "json"]);
+    assert!(output.status.success());
+    let report: Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(report["tool"], "mra");
+    assert_eq!(report["summary"]["programs"], 2);
+    assert_eq!(report["summary"]["reallocs"], 5);
+    assert_eq!(report["summary"]["zero_init_off"], 2);
+    assert_eq!(report["summary"]["attacker_sized"], 2);
+    assert_eq!(report["summary"]["no_top_up"], 1);
+    assert_eq!(report["summary"]["flagged"], 2);
+    assert_eq!(report["reallocs"][0]["problems"][2], "no-top-up");
+    assert_eq!(report["reallocs"][1]["zero_init"], true);
+
+    assert_eq!(run(&["--fail-above", "2"]).status.code(), Some(0));
+    let output = run(&["--fail-above", "1"]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("notes::ResizeNote (note)"));
+}
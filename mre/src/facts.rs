@@ -0,0 +1,291 @@
+//! What each function and `#[derive(Accounts)]` struct says about rent: the calls that
+//! create accounts or move lamports, the rent lookups, and the space of the accounts
+//! Anchor initializes.
+//!
+//! Sites are:
+//! - `create_account` and `create_account_with_seed` of `system_instruction` or
+//!   Anchor's `system_program`, with their lamports when they are a literal
+//! - `allocate` of either, which gives an account space its lamports must cover
+//! - `transfer` of either
+//! - `-=` on anything named `lamports` (`**vault.try_borrow_mut_lamports()? -= amount`)
+//!   and `sub_lamports`
+//!
+//! Rent is consulted by `Rent::get()`, `Rent::from_account_info`, `minimum_balance` and
+//! `is_exempt`. Macro arguments are read as expressions, so checks in `require!` count.
+//! `#[cfg(test)]` items and `#[test]` functions are left out, as [`mcd`] leaves them
+//! out.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use proc_macro2::{Span, TokenStream, TokenTree};
+use serde::Serialize;
+use syn::punctuated::Punctuated;
+use syn::spanned::Spanned;
+use syn::visit::{self, Visit};
+use syn::{BinOp, Expr, Fields, ImplItemFn, ItemFn, ItemImpl, ItemMod, ItemStruct, TraitItemFn};
+use trr_core::anchor::{context_accounts, derives_accounts, keyed_constraints};
+use trr_core::source::{is_cfg_test, location};
+
+use crate::{InitProblem, Location, SiteKind};
+
+/// Constraints that make Anchor size a token account, mint or associated token
+/// account itself
+const TOKEN_CONSTRAINTS: &[&str] = &["token", "mint", "associated_token"];
+
+/// Modules whose `create_account`, `allocate` and `transfer` are the system program's
+const SYSTEM_MODULES: &[&str] = &["system_instruction", "system_program"];
+
+/// A call creating an account or moving lamports
+#[derive(Clone, Debug, Serialize)]
+pub struct Site {
+    pub kind: SiteKind,
+    /// `create_account`, `transfer`, `-= lamports`, ...
+    pub call: String,
+    /// The lamports given to `create_account`, when they are a literal
+    pub literal_lamports: Option<String>,
+    pub location: Location,
+}
+
+#[derive(Debug, Default)]
+pub struct FunctionFacts {
+    /// The accounts struct `T` of a `Context<T>` parameter
+    pub context: Option<String>,
+    pub sites: Vec<Site>,
+    /// Where rent is consulted
+    pub rent_checks: Vec<Location>,
+}
+
+/// An account of an accounts struct that Anchor initializes
+#[derive(Clone, Debug)]
+pub struct InitField {
+    pub name: String,
+    pub location: Location,
+    /// The `space` expression
+    pub space: Option<String>,
+    pub problem: Option<InitProblem>,
+}
+
+#[derive(Clone, Debug)]
+pub struct AccountsStruct {
+    pub name: String,
+    pub file: PathBuf,
+    pub inits: Vec<InitField>,
+}
+
+#[derive(Debug, Default)]
+pub struct Facts {
+    /// Keyed by the location of the function's name
+    pub functions: HashMap<Location, FunctionFacts>,
+    pub structs: Vec<AccountsStruct>,
+}
+
+/// Add the functions and accounts structs of `file`, parsed from `source`, the
+/// contents of `path`, to `facts`
+pub fn add_file(path: &Path, source: &str, file: &syn::File, facts: &mut Facts) {
+    FileVisitor { path, source, facts }.visit_file(file);
+}
+
+/// `X::INIT_SPACE` or `size_of::<X>()` alone: the size of the data without Anchor's
+/// 8-byte discriminator
+fn leaves_out_discriminator(space: &Expr) -> bool {
+    match space {
+        Expr::Paren(inner) => leaves_out_discriminator(&inner.expr),
+        Expr::Path(path) => path.path.segments.last().is_some_and(|segment| segment.ident == "INIT_SPACE"),
+        Expr::Call(call) => matches!(&*call.func, Expr::Path(path) if path.path.segments.last().is_some_and(|segment| segment.ident == "size_of")),
+        _ => false,
+    }
+}
+
+/// The source of `tokens`, if they are on one line
+fn code(source: &str, tokens: &TokenStream) -> String {
+    let trees: Vec<TokenTree> = tokens.clone().into_iter().collect();
+    let line = trees.first().zip(trees.last())
+        .map(|(first, last)| (first.span().start(), last.span().end()))
+        .filter(|(start, end)| start.line == end.line)
+        .and_then(|(start, end)| source.lines().nth(start.line.saturating_sub(1)).map(|line| (line, start, end)));
+    match line {
+        Some((line, start, end)) => line.chars().skip(start.column).take(end.column - start.column).collect(),
+        None => tokens.to_string(),
+    }
+}
+
+fn init_field(path: &Path, source: &str, field: &syn::Field) -> Option<InitField> {
+    let ident = field.ident.as_ref()?;
+    let constraints = keyed_constraints(&field.attrs);
+    if !constraints.iter().any(|(key, _)| key == "init" || key == "init_if_needed") {
+        return None;
+    }
+    let space = constraints.iter().find(|(key, _)| key == "space").map(|(_, value)| value.clone());
+    let sized_by_anchor = constraints.iter()
+        .any(|(key, _)| key.split_once("::").is_some_and(|(module, _)| TOKEN_CONSTRAINTS.contains(&module)));
+    let problem = match &space {
+        None if !sized_by_anchor => Some(InitProblem::MissingSpace),
+        Some(space) if syn::parse2::<Expr>(space.clone()).is_ok_and(|space| leaves_out_discriminator(&space)) => {
+            Some(InitProblem::NoDiscriminator)
+        }
+        _ => None,
+    };
+    Some(InitField {
+        name: ident.to_string(),
+        location: location(path, ident.span()),
+        space: space.map(|space| code(source, &space)),
+        problem,
+    })
+}
+
+struct FileVisitor<'a> {
+    path: &'a Path,
+    source: &'a str,
+    facts: &'a mut Facts,
+}
+
+impl FileVisitor<'_> {
+    fn add_function(&mut self, sig: &syn::Signature, block: &syn::Block) {
+        let mut body = BodyVisitor { path: self.path, facts: FunctionFacts { context: context_accounts(sig), ..FunctionFacts::default() } };
+        body.visit_block(block);
+        self.facts.functions.insert(location(self.path, sig.ident.span()), body.facts);
+    }
+}
+
+impl<'ast> Visit<'ast> for FileVisitor<'_> {
+    fn visit_item_mod(&mut self, item: &'ast ItemMod) {
+        if !is_cfg_test(&item.attrs) {
+            visit::visit_item_mod(self, item);
+        }
+    }
+
+    fn visit_item_fn(&mut self, item: &'ast ItemFn) {
+        if !is_cfg_test(&item.attrs) {
+            self.add_function(&item.sig, &item.block);
+        }
+    }
+
+    fn visit_item_impl(&mut self, item: &'ast ItemImpl) {
+        if !is_cfg_test(&item.attrs) {
+            visit::visit_item_impl(self, item);
+        }
+    }
+
+    fn visit_impl_item_fn(&mut self, item: &'ast ImplItemFn) {
+        if !is_cfg_test(&item.attrs) {
+            self.add_function(&item.sig, &item.block);
+        }
+    }
+
+    fn visit_trait_item_fn(&mut self, item: &'ast TraitItemFn) {
+        if let Some(block) = &item.default {
+            self.add_function(&item.sig, block);
+        }
+    }
+
+    fn visit_item_struct(&mut self, item: &'ast ItemStruct) {
+        if is_cfg_test(&item.attrs) || !derives_accounts(&item.attrs) {
+            return;
+        }
+        let Fields::Named(fields) = &item.fields else { return };
+        self.facts.structs.push(AccountsStruct {
+            name: item.ident.to_string(),
+            file: self.path.to_path_buf(),
+            inits: fields.named.iter().filter_map(|field| init_field(self.path, self.source, field)).collect(),
+        });
+    }
+}
+
+/// Whether an expression names something `lamports`
+#[derive(Default)]
+struct Lamports(bool);
+
+impl<'ast> Visit<'ast> for Lamports {
+    fn visit_ident(&mut self, ident: &'ast syn::Ident) {
+        self.0 |= ident.to_string().contains("lamports");
+    }
+}
+
+/// An integer literal, through references, parentheses and casts
+fn literal(expr: &Expr) -> Option<String> {
+    match expr {
+        Expr::Lit(syn::ExprLit { lit: syn::Lit::Int(int), .. }) => Some(int.to_string()),
+        Expr::Reference(inner) => literal(&inner.expr),
+        Expr::Paren(inner) => literal(&inner.expr),
+        Expr::Cast(inner) => literal(&inner.expr),
+        _ => None,
+    }
+}
+
+/// The sites and rent lookups of one function body, leaving out the items declared in
+/// it
+struct BodyVisitor<'a> {
+    path: &'a Path,
+    facts: FunctionFacts,
+}
+
+impl BodyVisitor<'_> {
+    fn add(&mut self, kind: SiteKind, call: &str, literal_lamports: Option<String>, span: Span) {
+        self.facts.sites.push(Site { kind, call: call.to_string(), literal_lamports, location: location(self.path, span) });
+    }
+}
+
+impl<'ast> Visit<'ast> for BodyVisitor<'_> {
+    fn visit_item(&mut self, _: &'ast syn::Item) {}
+
+    fn visit_expr_call(&mut self, call: &'ast syn::ExprCall) {
+        if let Expr::Path(path) = &*call.func {
+            let segments: Vec<String> = path.path.segments.iter().map(|segment| segment.ident.to_string()).collect();
+            let name = segments.last().map(String::as_str).unwrap_or_default();
+            let owner = segments.len().checked_sub(2).map(|index| segments[index].as_str());
+            let system = segments.iter().any(|segment| SYSTEM_MODULES.contains(&segment.as_str()));
+            match name {
+                "create_account" | "create_account_with_seed" if system => {
+                    // (from, to, lamports, ..) natively, (ctx, lamports, ..) with Anchor;
+                    // the seed comes before the lamports
+                    let native = segments.iter().any(|segment| segment == "system_instruction");
+                    let index = match (native, name == "create_account") {
+                        (true, true) => 2,
+                        (true, false) => 4,
+                        (false, true) => 1,
+                        (false, false) => 2,
+                    };
+                    let lamports = call.args.iter().nth(index).and_then(literal);
+                    self.add(SiteKind::CreateAccount, name, lamports, path.span());
+                }
+                "allocate" if system => self.add(SiteKind::Allocate, name, None, path.span()),
+                "transfer" if system => self.add(SiteKind::Transfer, name, None, path.span()),
+                "get" | "from_account_info" if owner == Some("Rent") => {
+                    self.facts.rent_checks.push(location(self.path, path.span()));
+                }
+                "minimum_balance" | "is_exempt" => self.facts.rent_checks.push(location(self.path, path.span())),
+                _ => {}
+            }
+        }
+        visit::visit_expr_call(self, call);
+    }
+
+    fn visit_expr_method_call(&mut self, call: &'ast syn::ExprMethodCall) {
+        match call.method.to_string().as_str() {
+            "minimum_balance" | "is_exempt" => self.facts.rent_checks.push(location(self.path, call.method.span())),
+            "sub_lamports" => self.add(SiteKind::LamportDebit, "sub_lamports", None, call.method.span()),
+            _ => {}
+        }
+        visit::visit_expr_method_call(self, call);
+    }
+
+    fn visit_expr_binary(&mut self, expr: &'ast syn::ExprBinary) {
+        if matches!(expr.op, BinOp::SubAssign(_)) {
+            let mut lamports = Lamports::default();
+            lamports.visit_expr(&expr.left);
+            if lamports.0 {
+                self.add(SiteKind::LamportDebit, "-= lamports", None, expr.op.span());
+            }
+        }
+        visit::visit_expr_binary(self, expr);
+    }
+
+    fn visit_macro(&mut self, mac: &'ast syn::Macro) {
+        if let Ok(args) = mac.parse_body_with(Punctuated::<Expr, syn::Token![,]>::parse_terminated) {
+            for arg in &args {
+                self.visit_expr(arg);
+            }
+        }
+    }
+}
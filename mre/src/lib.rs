@@ -0,0 +1,263 @@
+//! Missing rent exemption: instruction handlers that create accounts or move lamports
+//! without consulting rent, and Anchor accounts initialized without room for their
+//! data.
+//!
+//! An account must hold the rent-exempt minimum for its size, or the runtime rejects
+//! the transaction that leaves it short. Lamports hardcoded into `create_account`, or a
+//! withdrawal that never compares what remains with `Rent::minimum_balance`, work until
+//! the account grows or rent changes, then make the instruction fail. See [`facts`] for
+//! the calls that count.
+//!
+//! Handlers and the functions they call come from [`mcd`]. A site is checked when rent
+//! is consulted in its own function, in a function it calls, or in a function on the
+//! way to it from the handler, so a native dispatcher is not cleared by the rent check
+//! of another instruction. Anchor's `init` handles rent itself, but only for the
+//! `space` it is given: an `init` account without `space`, or with `X::INIT_SPACE` or
+//! `size_of::<X>()` alone (leaving out the 8-byte discriminator), is reported too.
+
+pub mod facts;
+
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use mcd::FunctionId;
+use serde::Serialize;
+pub use trr_core::Location;
+use trr_core::{source, walk};
+
+use facts::{AccountsStruct, Facts, FunctionFacts, Site};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum SiteKind {
+    /// `create_account` or `create_account_with_seed`
+    CreateAccount,
+    /// `allocate`, giving an account space
+    Allocate,
+    /// A system program `transfer`
+    Transfer,
+    /// Lamports taken directly out of an account
+    LamportDebit,
+}
+
+impl fmt::Display for SiteKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.pad(match self {
+            SiteKind::CreateAccount => "account creation",
+            SiteKind::Allocate => "allocation",
+            SiteKind::Transfer => "transfer",
+            SiteKind::LamportDebit => "lamport debit",
+        })
+    }
+}
+
+/// What is wrong with an Anchor `init` account
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum InitProblem {
+    /// No `space`, and not a token account, mint or associated token account
+    MissingSpace,
+    /// `space = X::INIT_SPACE` or `size_of::<X>()`, without the 8-byte discriminator
+    NoDiscriminator,
+}
+
+impl fmt::Display for InitProblem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.pad(match self {
+            InitProblem::MissingSpace => "init without space",
+            InitProblem::NoDiscriminator => "space without the 8-byte discriminator",
+        })
+    }
+}
+
+/// A site a handler reaches
+#[derive(Clone, Debug, Serialize)]
+pub struct ReachedSite {
+    #[serde(flatten)]
+    pub site: Site,
+    /// Path of the function it is in
+    pub function: String,
+    /// Rent is consulted on the way to it, in its function or in what it calls
+    pub rent_checked: bool,
+}
+
+impl ReachedSite {
+    /// Unchecked, or creating an account with hardcoded lamports
+    pub fn at_risk(&self) -> bool {
+        !self.rent_checked || self.site.literal_lamports.is_some()
+    }
+}
+
+/// A handler creating accounts or moving lamports
+#[derive(Clone, Debug, Serialize)]
+pub struct CheckedHandler {
+    pub program: String,
+    pub handler: String,
+    pub location: Location,
+    /// By file and line
+    pub sites: Vec<ReachedSite>,
+    /// One of its sites is at risk
+    pub at_risk: bool,
+}
+
+/// An Anchor `init` account without room for its data
+#[derive(Clone, Debug, Serialize)]
+pub struct InitFinding {
+    pub program: String,
+    /// The accounts struct
+    pub accounts: String,
+    pub account: String,
+    pub problem: InitProblem,
+    pub space: Option<String>,
+    /// The handlers taking the accounts struct
+    pub handlers: Vec<String>,
+    pub location: Location,
+}
+
+#[derive(Debug, Default)]
+pub struct Check {
+    /// The handlers reaching a site, by program then handler
+    pub handlers: Vec<CheckedHandler>,
+    /// By program, then file and line
+    pub inits: Vec<InitFinding>,
+    /// Anchor `init` accounts checked
+    pub init_accounts: usize,
+}
+
+impl Check {
+    pub fn at_risk(&self) -> impl Iterator<Item = &CheckedHandler> {
+        self.handlers.iter().filter(|handler| handler.at_risk)
+    }
+
+    /// Handlers at risk and `init` findings
+    pub fn findings(&self) -> usize {
+        self.at_risk().count() + self.inits.len()
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Analysis {
+    pub programs: mcd::Analysis,
+    pub facts: Facts,
+    /// Files that could not be read or parsed
+    pub errors: Vec<(PathBuf, String)>,
+}
+
+impl Analysis {
+    fn function_facts(&self, (program, function): FunctionId) -> Option<&FunctionFacts> {
+        self.facts.functions.get(&self.programs.programs[program].functions[function].location)
+    }
+
+    /// The functions reachable from `start`, itself included
+    fn reachable(&self, start: FunctionId) -> HashSet<FunctionId> {
+        let mut reached = HashSet::from([start]);
+        let mut stack = vec![start];
+        while let Some(id) = stack.pop() {
+            for call in &self.programs.programs[id.0].functions[id.1].calls {
+                for callee in self.programs.callees(id.0, call) {
+                    if reached.insert(callee) {
+                        stack.push(callee);
+                    }
+                }
+            }
+        }
+        reached
+    }
+
+    fn checks_rent(&self, id: FunctionId) -> bool {
+        self.function_facts(id).is_some_and(|facts| !facts.rent_checks.is_empty())
+    }
+
+    /// The accounts struct named `name`, preferably in `program`'s own crate
+    fn accounts_struct(&self, program: usize, name: &str) -> Option<&AccountsStruct> {
+        let root = &self.programs.programs[program].root;
+        let mut named = self.facts.structs.iter().filter(|accounts| accounts.name == name);
+        named.clone().find(|accounts| accounts.file.starts_with(root)).or_else(|| named.next())
+    }
+
+    /// The handlers creating accounts or moving lamports, and the Anchor `init`
+    /// accounts without room for their data
+    pub fn check(&self) -> Check {
+        let mut check = Check::default();
+        let mut reachable: HashMap<FunctionId, HashSet<FunctionId>> = HashMap::new();
+        let mut reach = |id: FunctionId| reachable.entry(id).or_insert_with(|| self.reachable(id)).clone();
+
+        for (index, program) in self.programs.programs.iter().enumerate() {
+            for handler in &program.handlers {
+                let reached = reach((index, handler.function));
+                let mut sites = Vec::new();
+                for &id in &reached {
+                    let Some(facts) = self.function_facts(id).filter(|facts| !facts.sites.is_empty()) else { continue };
+                    // On the way from the handler, and below
+                    let rent_checked = reach(id).into_iter().any(|below| self.checks_rent(below))
+                        || reached.iter().any(|&above| self.checks_rent(above) && reach(above).contains(&id));
+                    let function = program.functions[id.1].path.join("::");
+                    sites.extend(facts.sites.iter().map(|site| ReachedSite {
+                        site: site.clone(),
+                        function: function.clone(),
+                        rent_checked,
+                    }));
+                }
+                if sites.is_empty() {
+                    continue;
+                }
+                sites.sort_by(|a, b| a.site.location.file.cmp(&b.site.location.file)
+                    .then_with(|| a.site.location.line.cmp(&b.site.location.line)));
+                check.handlers.push(CheckedHandler {
+                    program: program.name.clone(),
+                    handler: handler.name.clone(),
+                    location: handler.location.clone(),
+                    at_risk: sites.iter().any(ReachedSite::at_risk),
+                    sites,
+                });
+            }
+
+            // The accounts structs of the program's handlers
+            let mut structs: Vec<(&AccountsStruct, Vec<String>)> = Vec::new();
+            for handler in &program.handlers {
+                let Some(accounts) = self.function_facts((index, handler.function))
+                    .and_then(|facts| facts.context.as_deref())
+                    .and_then(|name| self.accounts_struct(index, name))
+                else {
+                    continue;
+                };
+                match structs.iter_mut().find(|(known, _)| std::ptr::eq(*known, accounts)) {
+                    Some((_, handlers)) => handlers.push(handler.name.clone()),
+                    None => structs.push((accounts, vec![handler.name.clone()])),
+                }
+            }
+            for (accounts, handlers) in structs {
+                check.init_accounts += accounts.inits.len();
+                for init in &accounts.inits {
+                    let Some(problem) = init.problem else { continue };
+                    check.inits.push(InitFinding {
+                        program: program.name.clone(),
+                        accounts: accounts.name.clone(),
+                        account: init.name.clone(),
+                        problem,
+                        space: init.space.clone(),
+                        handlers: handlers.clone(),
+                        location: init.location.clone(),
+                    });
+                }
+            }
+        }
+        check.handlers.sort_by(|a, b| a.program.cmp(&b.program).then_with(|| a.handler.cmp(&b.handler)));
+        check.inits.sort_by(|a, b| a.program.cmp(&b.program)
+            .then_with(|| a.location.file.cmp(&b.location.file))
+            .then_with(|| a.location.line.cmp(&b.location.line)));
+        check
+    }
+}
+
+/// Analyze every Rust file under `dir` (or `dir` itself, if it is a file), leaving out
+/// `target/` and test code
+pub fn analyze(dir: &Path) -> io::Result<Analysis> {
+    let programs = mcd::analyze(dir)?;
+    let mut facts = Facts::default();
+    let mut errors = programs.errors.clone();
+    source::parse_files(walk::program_files(dir)?, &mut errors, |path, source, file| facts::add_file(&path, source, &file, &mut facts));
+    Ok(Analysis { programs, facts, errors })
+}
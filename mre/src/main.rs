@@ -0,0 +1,187 @@
+mod report;
+mod sarif;
+
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::process;
+
+use clap::Parser;
+use serde::Serialize;
+use trr_core::git::Repository;
+use trr_core::output::{self, Format};
+
+use mre::{Analysis, Check, CheckedHandler, InitFinding, ReachedSite};
+
+#[derive(Parser)]
+#[clap(author, version, about = "Missing Rent Exemption (MRE) Detector")]
+#[clap(after_help = "Examples:
+  mre programs/vault
+  mre -v programs
+  mre --format sarif programs > rent.sarif
+  mre --fail-above 0 programs")]
+struct Args {
+    /// Directory to analyze (relative to --repo when given)
+    #[clap(value_name = "DIR", default_value = ".")]
+    dir: PathBuf,
+
+    /// Git repository URL to clone, or path of a local checkout
+    #[clap(long, value_name = "REPO_URL_OR_PATH")]
+    repo: Option<String>,
+
+    /// Branch, tag or commit of --repo to analyze
+    #[clap(long, value_name = "REV", requires = "repo")]
+    rev: Option<String>,
+
+    /// Output format
+    #[clap(long, value_enum, default_value = "text")]
+    format: Format,
+
+    /// List every handler creating accounts or moving lamports, not only those at risk
+    #[clap(short, long)]
+    verbose: bool,
+
+    /// Exit with status 1 if there are more than N findings (handlers at risk and init
+    /// accounts)
+    #[clap(long, value_name = "N")]
+    fail_above: Option<usize>,
+}
+
+/// Structured form of the analysis for `--format json`
+#[derive(Serialize)]
+struct JsonReport<'a> {
+    summary: Summary,
+    /// By program, then handler
+    handlers: &'a [CheckedHandler],
+    inits: &'a [InitFinding],
+    errors: Vec<FileError>,
+}
+
+#[derive(Serialize)]
+struct Summary {
+    programs: usize,
+    /// Handlers creating accounts or moving lamports
+    handlers: usize,
+    at_risk: usize,
+    /// Sites reached without a rent check, or with hardcoded lamports
+    sites_at_risk: usize,
+    init_accounts: usize,
+    init_findings: usize,
+    findings: usize,
+}
+
+#[derive(Serialize)]
+struct FileError {
+    file: PathBuf,
+    error: String,
+}
+
+/// Why a site is at risk
+fn risk(site: &ReachedSite) -> String {
+    match &site.site.literal_lamports {
+        Some(lamports) => format!("{} lamports hardcoded", lamports),
+        None => "rent not consulted".to_string(),
+    }
+}
+
+fn print_text(analysis: &Analysis, check: &Check, verbose: bool) {
+    if check.at_risk().next().is_some() {
+        println!("\nHandlers that may leave accounts short of rent:");
+    }
+    for handler in check.at_risk() {
+        println!("\n{}::{} (at {})", handler.program, handler.handler, handler.location);
+        for site in handler.sites.iter().filter(|site| site.at_risk()) {
+            println!("  {} ({}) in {} at {}: {}", site.site.kind, site.site.call, site.function, site.site.location, risk(site));
+        }
+    }
+    if !check.inits.is_empty() {
+        println!("\nAnchor init accounts without room for their data:");
+    }
+    for init in &check.inits {
+        let space = init.space.as_deref().map(|space| format!(" (space = {})", space)).unwrap_or_default();
+        println!(
+            "  {}::{}.{}: {}{} at {}, in {}",
+            init.program, init.accounts, init.account, init.problem, space, init.location, init.handlers.join(", ")
+        );
+    }
+    if verbose {
+        println!("\nHandlers creating accounts or moving lamports:");
+        for handler in &check.handlers {
+            let checked = handler.sites.iter().filter(|site| !site.at_risk()).count();
+            println!("  {}::{}: {} site(s), {} checked, at {}", handler.program, handler.handler, handler.sites.len(), checked, handler.location);
+        }
+    }
+
+    println!("\nSummary:");
+    println!("Programs: {}", analysis.programs.programs.len());
+    println!("Handlers creating accounts or moving lamports: {}", check.handlers.len());
+    println!("Handlers at risk: {}", check.at_risk().count());
+    println!("Anchor init accounts: {} ({} without room for their data)", check.init_accounts, check.inits.len());
+}
+
+fn write_json(out: &mut dyn Write, analysis: &Analysis, check: &Check) -> io::Result<()> {
+    let report = JsonReport {
+        summary: Summary {
+            programs: analysis.programs.programs.len(),
+            handlers: check.handlers.len(),
+            at_risk: check.at_risk().count(),
+            sites_at_risk: check.handlers.iter().flat_map(|handler| &handler.sites).filter(|site| site.at_risk()).count(),
+            init_accounts: check.init_accounts,
+            init_findings: check.inits.len(),
+            findings: check.findings(),
+        },
+        handlers: &check.handlers,
+        inits: &check.inits,
+        errors: analysis.errors.iter()
+            .map(|(file, error)| FileError { file: file.clone(), error: error.clone() })
+            .collect(),
+    };
+    output::write_json(out, "mre", &report)
+}
+
+fn run(args: &Args) -> Result<(), Box<dyn std::error::Error>> {
+    let repository = args.repo.as_deref().map(|repo| Repository::open(repo, args.rev.as_deref())).transpose()?;
+    let dir = match &repository {
+        Some(repository) => repository.subdir(&args.dir)?,
+        None => args.dir.clone(),
+    };
+    if !dir.exists() {
+        return Err(format!("Path '{}' does not exist", dir.display()).into());
+    }
+    if args.format == Format::Text {
+        println!("Analyzing: {}", dir.display());
+    }
+
+    let analysis = mre::analyze(&dir)?;
+    for (file, error) in &analysis.errors {
+        eprintln!("Error analyzing {}: {}", file.display(), error);
+    }
+    let check = analysis.check();
+
+    let mut out = io::stdout().lock();
+    match args.format {
+        Format::Text => print_text(&analysis, &check, args.verbose),
+        Format::Json => write_json(&mut out, &analysis, &check)?,
+        Format::Csv => report::write_csv(&mut out, &check)?,
+        Format::Markdown => report::write_markdown(&mut out, &dir, &check)?,
+        Format::Sarif => {
+            // Artifact URIs are relative to the repository root, or to where mre runs
+            let base = repository.as_ref().map_or_else(|| PathBuf::from("."), |repository| repository.root().to_path_buf());
+            sarif::write_sarif(&mut out, &check, &base)?;
+            writeln!(out)?;
+        }
+    }
+    drop(out);
+    let handlers = check.at_risk().map(|handler| format!("{}::{} at {}", handler.program, handler.handler, handler.location));
+    let inits = check.inits.iter()
+        .map(|init| format!("{}::{}.{}: {} at {}", init.program, init.accounts, init.account, init.problem, init.location));
+    output::enforce_limit(args.fail_above, check.findings(), "rent finding(s)", handlers.chain(inits));
+    Ok(())
+}
+
+fn main() {
+    let args = Args::parse();
+    if let Err(e) = run(&args) {
+        eprintln!("Error: {}", e);
+        process::exit(1);
+    }
+}
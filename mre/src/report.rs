@@ -0,0 +1,97 @@
+//! `--format csv`, one row per finding, and `--format markdown`, a review-ready list of
+//! the handlers at risk and the `init` accounts without room for their data.
+
+use std::io::{self, Write};
+use std::path::Path;
+
+use trr_core::output::{markdown_cell, markdown_code, write_csv_row};
+
+use mre::Check;
+
+/// Rows are the sites at risk, then the `init` findings; `kind` is the site kind or
+/// the `init` problem
+pub fn write_csv(out: &mut dyn Write, check: &Check) -> io::Result<()> {
+    write_csv_row(out, &["program", "handler", "function", "kind", "call", "literal_lamports", "file", "line"])?;
+    for handler in check.at_risk() {
+        for site in handler.sites.iter().filter(|site| site.at_risk()) {
+            write_csv_row(out, &[
+                &handler.program,
+                &handler.handler,
+                &site.function,
+                &site.site.kind.to_string(),
+                &site.site.call,
+                site.site.literal_lamports.as_deref().unwrap_or(""),
+                &site.site.location.file.display().to_string(),
+                &site.site.location.line.to_string(),
+            ])?;
+        }
+    }
+    for init in &check.inits {
+        write_csv_row(out, &[
+            &init.program,
+            &init.handlers.join(" "),
+            &format!("{}.{}", init.accounts, init.account),
+            &init.problem.to_string(),
+            "init",
+            "",
+            &init.location.file.display().to_string(),
+            &init.location.line.to_string(),
+        ])?;
+    }
+    Ok(())
+}
+
+pub fn write_markdown(out: &mut dyn Write, target: &Path, check: &Check) -> io::Result<()> {
+    writeln!(out, "# Rent Exemption Checks\n")?;
+    writeln!(out, "Target: {}\n", markdown_code(&target.display().to_string()))?;
+    writeln!(out, "## Summary\n")?;
+    writeln!(out, "| Metric | Value |")?;
+    writeln!(out, "| --- | ---: |")?;
+    writeln!(out, "| Handlers creating accounts or moving lamports | {} |", check.handlers.len())?;
+    writeln!(out, "| Handlers at risk | {} |", check.at_risk().count())?;
+    writeln!(out, "| Anchor `init` accounts | {} |", check.init_accounts)?;
+    writeln!(out, "| `init` accounts without room for their data | {} |", check.inits.len())?;
+
+    if check.at_risk().next().is_some() {
+        writeln!(out, "\n## Handlers at Risk\n")?;
+        writeln!(out, "| Handler | Site | Function | Risk | Location |")?;
+        writeln!(out, "| --- | --- | --- | --- | --- |")?;
+        for handler in check.at_risk() {
+            for site in handler.sites.iter().filter(|site| site.at_risk()) {
+                let risk = match &site.site.literal_lamports {
+                    Some(lamports) => format!("{} lamports hardcoded", lamports),
+                    None => "rent not consulted".to_string(),
+                };
+                writeln!(
+                    out,
+                    "| {} | {} ({}) | {} | {} | {} |",
+                    markdown_code(&format!("{}::{}", handler.program, handler.handler)),
+                    site.site.kind,
+                    markdown_code(&site.site.call),
+                    markdown_code(&site.function),
+                    markdown_cell(&risk),
+                    markdown_code(&site.site.location.to_string()),
+                )?;
+            }
+        }
+    }
+
+    if check.inits.is_empty() {
+        return Ok(());
+    }
+    writeln!(out, "\n## Init Accounts\n")?;
+    writeln!(out, "| Account | Problem | Space | Handlers | Location |")?;
+    writeln!(out, "| --- | --- | --- | --- | --- |")?;
+    for init in &check.inits {
+        writeln!(
+            out,
+            "| {} | {} | {} | {} | {} |",
+            markdown_code(&format!("{}::{}.{}", init.program, init.accounts, init.account)),
+            init.problem,
+            init.space.as_deref().map(markdown_code).unwrap_or_default(),
+            markdown_cell(&init.handlers.join(", ")),
+            markdown_code(&init.location.to_string()),
+        )?;
+    }
+    Ok(())
+}
@@ -0,0 +1,72 @@
+//! SARIF 2.1.0 output: a `rent-unchecked` result per site at risk, located at the site
+//! with its handler as a related location, and an `init-space` result per `init`
+//! account without room for its data.
+
+use std::io::{self, Write};
+use std::path::Path;
+
+use serde_json::json;
+use trr_core::sarif::{location, write_log};
+
+use mre::Check;
+
+const SITE_RULE_ID: &str = "rent-unchecked";
+const INIT_RULE_ID: &str = "init-space";
+
+pub fn write_sarif(out: &mut dyn Write, check: &Check, base: &Path) -> io::Result<()> {
+    let mut results: Vec<_> = Vec::new();
+    for handler in check.at_risk() {
+        for site in handler.sites.iter().filter(|site| site.at_risk()) {
+            let risk = match &site.site.literal_lamports {
+                Some(lamports) => format!("with {} lamports hardcoded", lamports),
+                None => "without consulting rent".to_string(),
+            };
+            let mut related = location(&handler.location, base);
+            related["id"] = json!(0);
+            related["message"] = json!({ "text": format!("handler {}::{}", handler.program, handler.handler) });
+            results.push(json!({
+                "ruleId": SITE_RULE_ID,
+                "level": "warning",
+                "message": {
+                    "text": format!("{}::{} reaches {} ({}) in {} {}", handler.program, handler.handler, site.site.kind, site.site.call, site.function, risk),
+                },
+                "locations": [location(&site.site.location, base)],
+                "relatedLocations": [related],
+                "properties": { "kind": site.site.kind },
+            }));
+        }
+    }
+    for init in &check.inits {
+        results.push(json!({
+            "ruleId": INIT_RULE_ID,
+            "level": "warning",
+            "message": {
+                "text": format!("{}.{} ({}): {}", init.accounts, init.account, init.program, init.problem),
+            },
+            "locations": [location(&init.location, base)],
+            "properties": { "problem": init.problem },
+        }));
+    }
+
+    let rules = vec![
+        json!({
+            "id": SITE_RULE_ID,
+            "name": "RentUnchecked",
+            "shortDescription": { "text": "Account created or lamports moved without consulting rent" },
+            "fullDescription": {
+                "text": "An account must keep the rent-exempt minimum for its size, or the transaction leaving it short fails. Compute the lamports of a new account, and what may leave an existing one, from Rent::get()?.minimum_balance(len) rather than a hardcoded amount.",
+            },
+            "defaultConfiguration": { "level": "warning" },
+        }),
+        json!({
+            "id": INIT_RULE_ID,
+            "name": "InitSpace",
+            "shortDescription": { "text": "Anchor init account without room for its data" },
+            "fullDescription": {
+                "text": "Anchor funds an init account for the space it is given. Without space, or with X::INIT_SPACE or size_of::<X>() alone, the account has no room for the 8-byte discriminator and its data. Use space = 8 + X::INIT_SPACE.",
+            },
+            "defaultConfiguration": { "level": "warning" },
+        }),
+    ];
+    write_log(out, "mre", env!("CARGO_PKG_VERSION"), rules, results)
+}
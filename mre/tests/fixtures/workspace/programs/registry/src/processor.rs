@@ -0,0 +1,47 @@
+use solana_program::account_info::{next_account_info, AccountInfo};
+use solana_program::entrypoint::ProgramResult;
+use solana_program::program::invoke;
+use solana_program::pubkey::Pubkey;
+use solana_program::system_instruction;
+
+use crate::rent::lamports_for;
+
+const ENTRY_SPACE: usize = 96;
+
+pub fn process(program_id: &Pubkey, accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
+    match data.first() {
+        Some(0) => create_entry(program_id, accounts),
+        _ => refund(accounts, data),
+    }
+}
+
+// Rent consulted through the helper
+fn create_entry(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+    let accounts = &mut accounts.iter();
+    let payer = next_account_info(accounts)?;
+    let entry = next_account_info(accounts)?;
+    let lamports = lamports_for(ENTRY_SPACE);
+    invoke(
+        &system_instruction::create_account(payer.key, entry.key, lamports, ENTRY_SPACE as u64, program_id),
+        &[payer.clone(), entry.clone()],
+    )
+}
+
+// Another instruction of the same entrypoint: its debit is not checked
+fn refund(accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
+    let accounts = &mut accounts.iter();
+    let entry = next_account_info(accounts)?;
+    let owner = next_account_info(accounts)?;
+    let amount = u64::from_le_bytes(data[1..9].try_into().unwrap());
+    **entry.lamports.borrow_mut() -= amount;
+    **owner.lamports.borrow_mut() += amount;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn creates() {
+        let _ = solana_program::system_instruction::create_account(&Default::default(), &Default::default(), 1, 1, &Default::default());
+    }
+}
@@ -0,0 +1,7 @@
+use solana_program::rent::Rent;
+use solana_program::sysvar::Sysvar;
+
+/// Lamports an entry of `space` bytes needs to be rent exempt
+pub fn lamports_for(space: usize) -> u64 {
+    Rent::get().map(|rent| rent.minimum_balance(space)).unwrap_or(0)
+}
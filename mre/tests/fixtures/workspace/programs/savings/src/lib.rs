@@ -0,0 +1,103 @@
+use anchor_lang::prelude::*;
+use anchor_lang::system_program;
+use anchor_spl::token::{Mint, Token, TokenAccount};
+
+#[program]
+pub mod savings {
+    use super::*;
+
+    pub fn open(ctx: Context<Open>) -> Result<()> {
+        ctx.accounts.savings.owner = ctx.accounts.owner.key();
+        Ok(())
+    }
+
+    pub fn open_legacy(ctx: Context<OpenLegacy>) -> Result<()> {
+        ctx.accounts.savings.owner = ctx.accounts.owner.key();
+        Ok(())
+    }
+
+    // Hardcoded lamports: short of rent as soon as the size or rent changes
+    pub fn create_note(ctx: Context<CreateNote>) -> Result<()> {
+        let accounts = system_program::CreateAccount {
+            from: ctx.accounts.owner.to_account_info(),
+            to: ctx.accounts.note.to_account_info(),
+        };
+        let cpi = CpiContext::new(ctx.accounts.system_program.to_account_info(), accounts);
+        system_program::create_account(cpi, 1_000_000, 64, ctx.program_id)?;
+        Ok(())
+    }
+
+    // Takes lamports without keeping the rent-exempt minimum
+    pub fn withdraw(ctx: Context<Withdraw>, amount: u64) -> Result<()> {
+        **ctx.accounts.savings.to_account_info().try_borrow_mut_lamports()? -= amount;
+        **ctx.accounts.owner.to_account_info().try_borrow_mut_lamports()? += amount;
+        Ok(())
+    }
+
+    pub fn withdraw_checked(ctx: Context<Withdraw>, amount: u64) -> Result<()> {
+        let savings = ctx.accounts.savings.to_account_info();
+        let minimum = Rent::get()?.minimum_balance(savings.data_len());
+        require!(savings.lamports() - amount >= minimum, SavingsError::BelowRent);
+        savings.sub_lamports(amount)?;
+        ctx.accounts.owner.add_lamports(amount)?;
+        Ok(())
+    }
+}
+
+#[derive(Accounts)]
+pub struct Open<'info> {
+    #[account(init, payer = owner, space = 8 + Savings::INIT_SPACE)]
+    pub savings: Account<'info, Savings>,
+    #[account(init, payer = owner, token::mint = mint, token::authority = owner)]
+    pub vault: Account<'info, TokenAccount>,
+    pub mint: Account<'info, Mint>,
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct OpenLegacy<'info> {
+    #[account(init, payer = owner, space = Savings::INIT_SPACE)]
+    pub savings: Account<'info, Savings>,
+    #[account(init_if_needed, payer = owner)]
+    pub history: Account<'info, History>,
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct CreateNote<'info> {
+    #[account(mut)]
+    pub note: Signer<'info>,
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct Withdraw<'info> {
+    #[account(mut, has_one = owner)]
+    pub savings: Account<'info, Savings>,
+    #[account(mut)]
+    pub owner: Signer<'info>,
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct Savings {
+    pub owner: Pubkey,
+}
+
+#[account]
+pub struct History {
+    pub entries: u64,
+}
+
+#[error_code]
+pub enum SavingsError {
+    #[msg("The withdrawal would leave the account below the rent-exempt minimum")]
+    BelowRent,
+}
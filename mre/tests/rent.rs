@@ -0,0 +1,90 @@
+// Checks tests/fixtures/workspace: an Anchor program creating and debiting accounts with
+// and without rent, with `init` accounts short of space, and a native program whose
+// dispatcher reaches one checked and one unchecked instruction.
+
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use mre::{Check, InitProblem, SiteKind};
+use serde_json::Value;
+
+fn fixture() -> PathBuf {
+    Path::new(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/workspace")).to_path_buf()
+}
+
+fn check() -> Check {
+    let analysis = mre::analyze(&fixture()).unwrap();
+    assert!(analysis.errors.is_empty(), "{:?}", analysis.errors);
+    analysis.check()
+}
+
+#[test]
+fn handlers_at_risk() {
+    let check = check();
+    let handlers: Vec<(&str, &str, bool)> = check.handlers.iter()
+        .map(|handler| (handler.program.as_str(), handler.handler.as_str(), handler.at_risk))
+        .collect();
+    assert_eq!(handlers, [
+        ("registry", "process_instruction", true),
+        ("savings", "create_note", true),
+        ("savings", "withdraw", true),
+        ("savings", "withdraw_checked", false),
+    ]);
+
+    // The rent helper clears the creation, not the refund beside it
+    let registry: Vec<(&str, SiteKind, bool)> = check.handlers[0].sites.iter()
+        .map(|site| (site.function.as_str(), site.site.kind, site.rent_checked))
+        .collect();
+    assert_eq!(registry, [
+        ("processor::create_entry", SiteKind::CreateAccount, true),
+        ("processor::refund", SiteKind::LamportDebit, false),
+    ]);
+
+    // Hardcoded lamports are at risk whatever else the handler does
+    let note = &check.handlers[1].sites[0];
+    assert_eq!(note.site.literal_lamports.as_deref(), Some("1_000_000"));
+    assert!(note.at_risk());
+    assert_eq!(check.handlers[3].sites[0].site.call, "sub_lamports");
+}
+
+#[test]
+fn init_accounts() {
+    let check = check();
+    // The token account is sized by Anchor, `open`'s account has its discriminator
+    assert_eq!(check.init_accounts, 4);
+    let inits: Vec<(&str, InitProblem, Option<&str>, usize)> = check.inits.iter()
+        .map(|init| (init.account.as_str(), init.problem, init.space.as_deref(), init.location.line))
+        .collect();
+    assert_eq!(inits, [
+        ("savings", InitProblem::NoDiscriminator, Some("Savings::INIT_SPACE"), 63),
+        ("history", InitProblem::MissingSpace, None, 65),
+    ]);
+    assert_eq!(check.inits[0].handlers, ["open_legacy"]);
+    assert_eq!(check.findings(), 5);
+}
+
+#[test]
+fn json_and_limits() {
+    let run = |args: &[&str]| Command::new(env!("CARGO_BIN_EXE_mre"))
+        .args(args)
+        .arg(fixture())
+        .output()
+        .expect("failed to run mre");
+
+    let output = run(&["--format", "json"]);
+    assert!(output.status.success());
+    let report: Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(report["tool"], "mre");
+    assert_eq!(report["summary"]["programs"], 2);
+    assert_eq!(report["summary"]["handlers"], 4);
+    assert_eq!(report["summary"]["at_risk"], 3);
+    assert_eq!(report["summary"]["sites_at_risk"], 3);
+    assert_eq!(report["summary"]["init_findings"], 2);
+    assert_eq!(report["handlers"][0]["sites"][1]["kind"], "lamport-debit");
+    assert_eq!(report["inits"][1]["problem"], "missing-space");
+
+    assert_eq!(run(&["--fail-above", "5"]).status.code(), Some(0));
+    let output = run(&["--fail-above", "4"]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("savings::OpenLegacy.history: init without space"));
+}
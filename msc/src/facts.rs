@@ -0,0 +1,409 @@
+//! What each function and `#[derive(Accounts)]` struct says about accounts: which
+//! accounts' keys gate access (`has_one`, `constraint`, PDA `seeds`, or a key compared
+//! in the body), which are checked with `is_signer`, and whether state is written.
+//!
+//! Account names are matched by identifier: `ctx.accounts.authority.key()` and
+//! `*authority.key` both use the key of `authority`. Macro arguments (`require!`,
+//! `require_keys_eq!`, `assert_eq!`) are read as tokens, so checks written in them
+//! count. `#[cfg(test)]` items and `#[test]` functions are left out, as [`mcd`]
+//! leaves them out.
+
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+use proc_macro2::{Delimiter, Span, TokenStream, TokenTree};
+use syn::visit::{self, Visit};
+use syn::{BinOp, Expr, Fields, ImplItemFn, ItemFn, ItemImpl, ItemMod, ItemStruct, Pat, TraitItemFn};
+use trr_core::anchor::{account_constraints, context_accounts, derives_accounts, type_name};
+use trr_core::source::{is_cfg_test, location};
+
+use crate::{GateKind, Location};
+
+/// Account types that may belong to a wallet, and so may sign. Program-owned data
+/// accounts (`Account`, `AccountLoader`, `Program`, ...) never sign, so gating on them
+/// is a relation between accounts, not an authorization.
+const SIGNING_TYPES: &[&str] = &["AccountInfo", "UncheckedAccount", "SystemAccount"];
+
+/// Constraints that write the account they are on
+const MUTABLE_CONSTRAINTS: &[&str] = &["mut", "init", "init_if_needed", "zero", "close", "realloc"];
+
+/// Calls that write account data or lamports, or move them by CPI
+const MUTATIONS: &[&str] = &[
+    "borrow_mut", "try_borrow_mut_data", "try_borrow_mut_lamports", "serialize", "pack", "pack_into_slice",
+    "realloc", "resize", "assign", "invoke", "invoke_signed", "set_lamports", "exit",
+];
+
+/// Names of accounts (or of the fields their keys are compared with) that hold an
+/// authority, in native programs where every account is an `AccountInfo`
+const AUTHORITY_NAMES: &[&str] = &[
+    "authority", "owner", "admin", "signer", "user", "payer", "manager", "operator", "creator", "delegate",
+    "governor", "initializer", "depositor", "withdrawer", "staker", "maker", "taker", "recipient",
+];
+
+/// Whether an account or field name looks like it holds an authority
+pub fn is_authority_name(name: &str) -> bool {
+    let name = name.to_lowercase();
+    AUTHORITY_NAMES.iter().any(|authority| name.contains(authority))
+}
+
+/// An account bound by a native handler
+#[derive(Clone, Debug)]
+pub struct Binding {
+    pub name: String,
+    pub location: Location,
+}
+
+/// An account's key used to gate access in a function body
+#[derive(Clone, Debug)]
+pub struct KeyUse {
+    pub account: String,
+    pub kind: GateKind,
+    /// The account is named like an authority, or compared with a field named so
+    pub authority: bool,
+    pub location: Location,
+}
+
+#[derive(Debug, Default)]
+pub struct FunctionFacts {
+    /// The accounts struct `T` of a `Context<T>` parameter
+    pub context: Option<String>,
+    /// Accounts taken with `next_account_info` or from the `accounts` slice
+    pub bindings: Vec<Binding>,
+    pub key_uses: Vec<KeyUse>,
+    /// Names whose `is_signer` is read
+    pub signer_checks: HashSet<String>,
+    /// The first write of account data or lamports
+    pub mutation: Option<Location>,
+}
+
+/// A key gate declared on an accounts struct field
+#[derive(Clone, Debug)]
+pub struct FieldGate {
+    pub kind: GateKind,
+    /// The gated account
+    pub account: String,
+}
+
+#[derive(Clone, Debug)]
+pub struct AccountField {
+    pub name: String,
+    pub location: Location,
+    /// `Signer<'info>` or `#[account(signer)]`
+    pub signer: bool,
+    /// One of the [`SIGNING_TYPES`]
+    pub may_sign: bool,
+    pub mutable: bool,
+    pub gates: Vec<FieldGate>,
+}
+
+#[derive(Clone, Debug)]
+pub struct AccountsStruct {
+    pub name: String,
+    pub file: PathBuf,
+    pub fields: Vec<AccountField>,
+}
+
+#[derive(Debug, Default)]
+pub struct Facts {
+    /// Keyed by the location of the function's name
+    pub functions: HashMap<Location, FunctionFacts>,
+    pub structs: Vec<AccountsStruct>,
+}
+
+/// Add the functions and accounts structs of `file`, parsed from `path`, to `facts`
+pub fn add_file(path: &Path, file: &syn::File, facts: &mut Facts) {
+    FileVisitor { path, facts }.visit_file(file);
+}
+
+/// A token of a flattened stream: groups become their delimiters around their contents
+#[derive(Clone, Debug)]
+enum Token {
+    Ident(String, Span),
+    Punct(char),
+    Open,
+    Close(Delimiter),
+    Literal,
+}
+
+fn flatten(stream: TokenStream, tokens: &mut Vec<Token>) {
+    for tree in stream {
+        match tree {
+            TokenTree::Ident(ident) => tokens.push(Token::Ident(ident.to_string(), ident.span())),
+            TokenTree::Punct(punct) => tokens.push(Token::Punct(punct.as_char())),
+            TokenTree::Literal(_) => tokens.push(Token::Literal),
+            TokenTree::Group(group) => {
+                tokens.push(Token::Open);
+                flatten(group.stream(), tokens);
+                tokens.push(Token::Close(group.delimiter()));
+            }
+        }
+    }
+}
+
+fn tokens_of(stream: TokenStream) -> Vec<Token> {
+    let mut tokens = Vec::new();
+    flatten(stream, &mut tokens);
+    tokens
+}
+
+fn is_ident(token: Option<&Token>, name: &str) -> bool {
+    matches!(token, Some(Token::Ident(ident, _)) if ident == name)
+}
+
+fn is_dot(token: Option<&Token>) -> bool {
+    matches!(token, Some(Token::Punct('.')))
+}
+
+/// The accounts whose `key` is read (`X.key`, `X.key()`), with where
+fn key_reads(tokens: &[Token]) -> Vec<(String, Span)> {
+    (2..tokens.len())
+        .filter(|&i| is_ident(tokens.get(i), "key") && is_dot(tokens.get(i - 1)))
+        .filter_map(|i| match &tokens[i - 2] {
+            Token::Ident(name, span) if name != "self" => Some((name.clone(), *span)),
+            _ => None,
+        })
+        .collect()
+}
+
+/// The identifiers of the method chains whose `is_signer` is read:
+/// `ctx.accounts.authority.to_account_info().is_signer` gives all four
+fn signer_reads(tokens: &[Token]) -> Vec<String> {
+    let mut names = Vec::new();
+    for i in (1..tokens.len()).filter(|&i| is_ident(tokens.get(i), "is_signer") && is_dot(tokens.get(i - 1))) {
+        let mut j = i - 1;
+        while j > 0 && is_dot(tokens.get(j)) {
+            j -= 1;
+            // Skip a call's arguments back to the method name
+            if matches!(tokens[j], Token::Close(Delimiter::Parenthesis)) {
+                let mut depth = 0;
+                loop {
+                    match tokens[j] {
+                        Token::Close(_) => depth += 1,
+                        Token::Open => depth -= 1,
+                        _ => {}
+                    }
+                    if depth == 0 || j == 0 {
+                        break;
+                    }
+                    j -= 1;
+                }
+                if j == 0 {
+                    break;
+                }
+                j -= 1;
+            }
+            match &tokens[j] {
+                Token::Ident(name, _) => names.push(name.clone()),
+                _ => break,
+            }
+            if j == 0 {
+                break;
+            }
+            j -= 1;
+        }
+    }
+    names
+}
+
+fn idents(tokens: &[Token]) -> impl Iterator<Item = &str> {
+    tokens.iter().filter_map(|token| match token {
+        Token::Ident(name, _) => Some(name.as_str()),
+        _ => None,
+    })
+}
+
+fn account_field(path: &Path, field: &syn::Field) -> Option<AccountField> {
+    let ident = field.ident.as_ref()?;
+    let ty = type_name(&field.ty);
+    let mut account = AccountField {
+        name: ident.to_string(),
+        location: location(path, ident.span()),
+        signer: ty.as_deref() == Some("Signer"),
+        may_sign: ty.as_deref().is_some_and(|ty| SIGNING_TYPES.contains(&ty)),
+        mutable: false,
+        gates: Vec::new(),
+    };
+    for constraint in account_constraints(&field.attrs).into_iter().map(|trees| tokens_of(trees.into_iter().collect())) {
+        let Some(Token::Ident(name, _)) = constraint.first() else { continue };
+        match name.as_str() {
+            "signer" => account.signer = true,
+            "has_one" => {
+                if let Some(Token::Ident(target, _)) = constraint.get(2) {
+                    account.gates.push(FieldGate { kind: GateKind::HasOne, account: target.clone() });
+                }
+            }
+            "constraint" | "seeds" => {
+                let kind = if name == "seeds" { GateKind::Seeds } else { GateKind::Constraint };
+                for (target, _) in key_reads(&constraint) {
+                    account.gates.push(FieldGate { kind, account: target });
+                }
+            }
+            name if MUTABLE_CONSTRAINTS.contains(&name) => account.mutable = true,
+            _ => {}
+        }
+    }
+    Some(account)
+}
+
+struct FileVisitor<'a> {
+    path: &'a Path,
+    facts: &'a mut Facts,
+}
+
+impl FileVisitor<'_> {
+    fn add_function(&mut self, sig: &syn::Signature, block: &syn::Block) {
+        let mut body = BodyVisitor { path: self.path, facts: FunctionFacts { context: context_accounts(sig), ..FunctionFacts::default() } };
+        body.visit_block(block);
+        let tokens = tokens_of(quote_block(block));
+        body.facts.signer_checks.extend(signer_reads(&tokens));
+        body.facts.mutation = tokens.iter().find_map(|token| match token {
+            Token::Ident(name, span) if MUTATIONS.contains(&name.as_str()) => Some(location(self.path, *span)),
+            _ => None,
+        });
+        self.facts.functions.insert(location(self.path, sig.ident.span()), body.facts);
+    }
+}
+
+fn quote_block(block: &syn::Block) -> TokenStream {
+    let mut stream = TokenStream::new();
+    quote::ToTokens::to_tokens(block, &mut stream);
+    stream
+}
+
+impl<'ast> Visit<'ast> for FileVisitor<'_> {
+    fn visit_item_mod(&mut self, item: &'ast ItemMod) {
+        if !is_cfg_test(&item.attrs) {
+            visit::visit_item_mod(self, item);
+        }
+    }
+
+    fn visit_item_fn(&mut self, item: &'ast ItemFn) {
+        if !is_cfg_test(&item.attrs) {
+            self.add_function(&item.sig, &item.block);
+        }
+    }
+
+    fn visit_item_impl(&mut self, item: &'ast ItemImpl) {
+        if !is_cfg_test(&item.attrs) {
+            visit::visit_item_impl(self, item);
+        }
+    }
+
+    fn visit_impl_item_fn(&mut self, item: &'ast ImplItemFn) {
+        if !is_cfg_test(&item.attrs) {
+            self.add_function(&item.sig, &item.block);
+        }
+    }
+
+    fn visit_trait_item_fn(&mut self, item: &'ast TraitItemFn) {
+        if let Some(block) = &item.default {
+            self.add_function(&item.sig, block);
+        }
+    }
+
+    fn visit_item_struct(&mut self, item: &'ast ItemStruct) {
+        if is_cfg_test(&item.attrs) || !derives_accounts(&item.attrs) {
+            return;
+        }
+        let Fields::Named(fields) = &item.fields else { return };
+        self.facts.structs.push(AccountsStruct {
+            name: item.ident.to_string(),
+            file: self.path.to_path_buf(),
+            fields: fields.named.iter().filter_map(|field| account_field(self.path, field)).collect(),
+        });
+    }
+}
+
+/// Whether an expression is `accounts`, or indexes or slices it
+fn is_accounts(expr: &Expr) -> bool {
+    match expr {
+        Expr::Path(path) => path.path.is_ident("accounts"),
+        Expr::Reference(reference) => is_accounts(&reference.expr),
+        Expr::Index(index) => is_accounts(&index.expr),
+        Expr::Paren(paren) => is_accounts(&paren.expr),
+        _ => false,
+    }
+}
+
+fn calls(expr: &Expr, name: &str) -> bool {
+    match expr {
+        Expr::Try(expr) => calls(&expr.expr, name),
+        Expr::Call(call) => matches!(&*call.func, Expr::Path(path) if path.path.segments.last().is_some_and(|segment| segment.ident == name)),
+        _ => false,
+    }
+}
+
+fn pat_idents(pat: &Pat) -> Vec<&syn::Ident> {
+    match pat {
+        Pat::Ident(ident) => vec![&ident.ident],
+        Pat::Slice(slice) => slice.elems.iter().flat_map(pat_idents).collect(),
+        Pat::Reference(reference) => pat_idents(&reference.pat),
+        Pat::Type(typed) => pat_idents(&typed.pat),
+        _ => Vec::new(),
+    }
+}
+
+/// The accounts and key uses of one function body, leaving out the items declared in it
+struct BodyVisitor<'a> {
+    path: &'a Path,
+    facts: FunctionFacts,
+}
+
+impl BodyVisitor<'_> {
+    /// Keys read in `tokens`, compared with what the other `counterpart` tokens name
+    fn add_key_uses(&mut self, tokens: &[Token], counterpart: &[Token], kind: GateKind) {
+        let authority_counterpart = idents(counterpart).any(is_authority_name);
+        for (account, span) in key_reads(tokens) {
+            let authority = authority_counterpart || is_authority_name(&account);
+            self.facts.key_uses.push(KeyUse { account, kind, authority, location: location(self.path, span) });
+        }
+    }
+}
+
+impl<'ast> Visit<'ast> for BodyVisitor<'_> {
+    fn visit_item(&mut self, _: &'ast syn::Item) {}
+
+    fn visit_local(&mut self, local: &'ast syn::Local) {
+        if let Some(init) = &local.init {
+            // `let [a, b, ..] = accounts else { .. }` binds only identifiers
+            let bound = calls(&init.expr, "next_account_info") || is_accounts(&init.expr);
+            if bound {
+                for ident in pat_idents(&local.pat) {
+                    self.facts.bindings.push(Binding { name: ident.to_string(), location: location(self.path, ident.span()) });
+                }
+            }
+        }
+        visit::visit_local(self, local);
+    }
+
+    fn visit_expr_binary(&mut self, expr: &'ast syn::ExprBinary) {
+        if matches!(expr.op, BinOp::Eq(_) | BinOp::Ne(_)) {
+            let left = tokens_of(quote::ToTokens::to_token_stream(&expr.left));
+            let right = tokens_of(quote::ToTokens::to_token_stream(&expr.right));
+            self.add_key_uses(&left, &right, GateKind::Comparison);
+            self.add_key_uses(&right, &left, GateKind::Comparison);
+        }
+        visit::visit_expr_binary(self, expr);
+    }
+
+    fn visit_expr_call(&mut self, call: &'ast syn::ExprCall) {
+        let is_pda = matches!(&*call.func, Expr::Path(path) if path.path.segments.last().is_some_and(|segment| {
+            segment.ident.to_string().ends_with("program_address")
+        }));
+        if is_pda {
+            let tokens = tokens_of(quote::ToTokens::to_token_stream(&call.args));
+            self.add_key_uses(&tokens, &[], GateKind::Seeds);
+        }
+        visit::visit_expr_call(self, call);
+    }
+
+    fn visit_macro(&mut self, mac: &'ast syn::Macro) {
+        let name = mac.path.segments.last().map(|segment| segment.ident.to_string()).unwrap_or_default();
+        let compares = name.contains("eq") || name.contains("ne") || name == "require" || name == "assert";
+        if compares {
+            let tokens = tokens_of(mac.tokens.clone());
+            self.add_key_uses(&tokens, &tokens, GateKind::Comparison);
+        }
+        visit::visit_macro(self, mac);
+    }
+}
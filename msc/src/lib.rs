@@ -0,0 +1,250 @@
+//! Missing signer checks: accounts whose keys decide who may change state, in
+//! instruction handlers that never check that the account signed.
+//!
+//! Comparing an account's key with a stored authority, or deriving a PDA from it, only
+//! proves which account was passed, not that its owner approved the transaction:
+//! anyone can pass someone else's key. Such an account must be a `Signer<'info>`, carry
+//! Anchor's `signer` constraint, or have its `is_signer` checked by the handler or a
+//! function it calls (see [`facts`] for what counts).
+//!
+//! Anchor handlers come from [`mcd`], with the accounts struct of their `Context`;
+//! their gated accounts are the ones that may sign at all (`AccountInfo`,
+//! `UncheckedAccount`, `SystemAccount`). In native programs every function taking
+//! accounts with `next_account_info` or from the `accounts` slice is a handler, and
+//! its gated accounts are the ones named like an authority or compared with a field
+//! named so. Handlers that write no account are left out.
+
+pub mod facts;
+
+use std::collections::{BTreeSet, HashSet};
+use std::fmt;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use mcd::FunctionId;
+use serde::Serialize;
+pub use trr_core::Location;
+use trr_core::{source, walk};
+
+use facts::{AccountsStruct, Facts, FunctionFacts};
+
+/// How an account's key gates access
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum GateKind {
+    /// Anchor's `has_one = account`
+    HasOne,
+    /// An Anchor `constraint` reading the account's key
+    Constraint,
+    /// PDA seeds: Anchor's `seeds`, or `find_program_address`/`create_program_address`
+    Seeds,
+    /// The key compared with `==`/`!=` or in `require_keys_eq!`-style macros
+    Comparison,
+}
+
+impl fmt::Display for GateKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.pad(match self {
+            GateKind::HasOne => "has_one",
+            GateKind::Constraint => "constraint",
+            GateKind::Seeds => "seeds",
+            GateKind::Comparison => "key comparison",
+        })
+    }
+}
+
+/// An account gating a handler's writes without a signer check
+#[derive(Clone, Debug, Serialize)]
+pub struct Finding {
+    pub program: String,
+    pub handler: String,
+    pub account: String,
+    /// The account's field or binding
+    pub location: Location,
+    pub gate: GateKind,
+    /// The accounts struct field carrying the constraint, for Anchor constraints
+    pub gated_by: Option<String>,
+    pub gate_location: Location,
+}
+
+/// A handler that was checked
+#[derive(Clone, Debug, Serialize)]
+pub struct CheckedHandler {
+    pub program: String,
+    pub handler: String,
+    pub location: Location,
+    /// An Anchor handler, or else a native one
+    pub anchor: bool,
+    /// Writes an account, so its gated accounts were checked
+    pub mutates: bool,
+}
+
+#[derive(Debug, Default)]
+pub struct Check {
+    pub handlers: Vec<CheckedHandler>,
+    /// By program, then handler
+    pub findings: Vec<Finding>,
+}
+
+#[derive(Debug, Default)]
+pub struct Analysis {
+    pub programs: mcd::Analysis,
+    pub facts: Facts,
+    /// Files that could not be read or parsed
+    pub errors: Vec<(PathBuf, String)>,
+}
+
+/// What a handler and the functions it calls do, taken together
+#[derive(Default)]
+struct Reached<'a> {
+    signer_checks: HashSet<&'a str>,
+    key_uses: Vec<&'a facts::KeyUse>,
+    mutates: bool,
+}
+
+impl Analysis {
+    fn function_facts(&self, (program, function): FunctionId) -> Option<&FunctionFacts> {
+        self.facts.functions.get(&self.programs.programs[program].functions[function].location)
+    }
+
+    /// The facts of a function and of every function it calls, directly or not
+    fn reached(&self, start: FunctionId) -> Reached<'_> {
+        let mut visited = HashSet::from([start]);
+        let mut stack = vec![start];
+        let mut reached = Reached::default();
+        while let Some(id) = stack.pop() {
+            if let Some(facts) = self.function_facts(id) {
+                reached.signer_checks.extend(facts.signer_checks.iter().map(String::as_str));
+                reached.key_uses.extend(&facts.key_uses);
+                reached.mutates |= facts.mutation.is_some();
+            }
+            for call in &self.programs.programs[id.0].functions[id.1].calls {
+                for callee in self.programs.callees(id.0, call) {
+                    if visited.insert(callee) {
+                        stack.push(callee);
+                    }
+                }
+            }
+        }
+        reached
+    }
+
+    /// The accounts struct named `name`, preferably in `program`'s own crate
+    fn accounts_struct(&self, program: usize, name: &str) -> Option<&AccountsStruct> {
+        let root = &self.programs.programs[program].root;
+        let mut named = self.facts.structs.iter().filter(|accounts| accounts.name == name);
+        named.clone().find(|accounts| accounts.file.starts_with(root)).or_else(|| named.next())
+    }
+
+    fn check_anchor(&self, accounts: &AccountsStruct, handler: &CheckedHandler, reached: &Reached, findings: &mut Vec<Finding>) {
+        let signed = |name: &str| {
+            accounts.fields.iter().any(|field| field.name == name && field.signer) || reached.signer_checks.contains(name)
+        };
+        let unsigned = |name: &str| accounts.fields.iter().find(|field| field.name == name && field.may_sign && !signed(name));
+        let mut add = |account: &facts::AccountField, gate, gated_by: Option<&str>, gate_location: &Location| {
+            if findings.iter().any(|finding| finding.handler == handler.handler && finding.program == handler.program && finding.account == account.name) {
+                return;
+            }
+            findings.push(Finding {
+                program: handler.program.clone(),
+                handler: handler.handler.clone(),
+                account: account.name.clone(),
+                location: account.location.clone(),
+                gate,
+                gated_by: gated_by.map(str::to_string),
+                gate_location: gate_location.clone(),
+            });
+        };
+        for field in &accounts.fields {
+            for gate in &field.gates {
+                if let Some(account) = unsigned(&gate.account) {
+                    add(account, gate.kind, Some(&field.name), &field.location);
+                }
+            }
+        }
+        for key_use in &reached.key_uses {
+            if let Some(account) = unsigned(&key_use.account) {
+                add(account, key_use.kind, None, &key_use.location);
+            }
+        }
+    }
+
+    /// Every handler checked, and the accounts gating its writes without a signer check
+    pub fn check(&self) -> Check {
+        let mut check = Check::default();
+        for (index, program) in self.programs.programs.iter().enumerate() {
+            // Anchor handlers, with the accounts struct of their context
+            for handler in &program.handlers {
+                let id = (index, handler.function);
+                let Some(accounts) = self.function_facts(id)
+                    .and_then(|facts| facts.context.as_deref())
+                    .and_then(|name| self.accounts_struct(index, name))
+                else {
+                    continue;
+                };
+                let reached = self.reached(id);
+                let checked = CheckedHandler {
+                    program: program.name.clone(),
+                    handler: handler.name.clone(),
+                    location: handler.location.clone(),
+                    anchor: true,
+                    mutates: reached.mutates || accounts.fields.iter().any(|field| field.mutable),
+                };
+                if checked.mutates {
+                    self.check_anchor(accounts, &checked, &reached, &mut check.findings);
+                }
+                check.handlers.push(checked);
+            }
+
+            // Native handlers: the functions taking accounts
+            for (function_index, function) in program.functions.iter().enumerate() {
+                let id = (index, function_index);
+                let Some(facts) = self.function_facts(id).filter(|facts| facts.context.is_none() && !facts.bindings.is_empty()) else {
+                    continue;
+                };
+                let reached = self.reached(id);
+                let checked = CheckedHandler {
+                    program: program.name.clone(),
+                    handler: function.name().to_string(),
+                    location: function.location.clone(),
+                    anchor: false,
+                    mutates: reached.mutates,
+                };
+                if checked.mutates {
+                    let mut gated = BTreeSet::new();
+                    for key_use in reached.key_uses.iter().filter(|key_use| key_use.authority) {
+                        let Some(binding) = facts.bindings.iter().find(|binding| binding.name == key_use.account) else { continue };
+                        if reached.signer_checks.contains(binding.name.as_str()) || !gated.insert(binding.name.as_str()) {
+                            continue;
+                        }
+                        check.findings.push(Finding {
+                            program: checked.program.clone(),
+                            handler: checked.handler.clone(),
+                            account: binding.name.clone(),
+                            location: binding.location.clone(),
+                            gate: key_use.kind,
+                            gated_by: None,
+                            gate_location: key_use.location.clone(),
+                        });
+                    }
+                }
+                check.handlers.push(checked);
+            }
+        }
+        check.findings.sort_by(|a, b| a.program.cmp(&b.program)
+            .then_with(|| a.handler.cmp(&b.handler))
+            .then_with(|| a.location.file.cmp(&b.location.file))
+            .then_with(|| a.location.line.cmp(&b.location.line)));
+        check
+    }
+}
+
+/// Analyze every Rust file under `dir` (or `dir` itself, if it is a file), leaving out
+/// `target/` and test code
+pub fn analyze(dir: &Path) -> io::Result<Analysis> {
+    let programs = mcd::analyze(dir)?;
+    let mut facts = Facts::default();
+    let mut errors = programs.errors.clone();
+    source::parse_files(walk::program_files(dir)?, &mut errors, |path, _, file| facts::add_file(&path, &file, &mut facts));
+    Ok(Analysis { programs, facts, errors })
+}
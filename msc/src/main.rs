@@ -0,0 +1,163 @@
+mod report;
+mod sarif;
+
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::process;
+
+use clap::Parser;
+use serde::Serialize;
+use trr_core::git::Repository;
+use trr_core::output::{self, Format};
+
+use msc::{Analysis, Check, CheckedHandler, Finding};
+
+#[derive(Parser)]
+#[clap(author, version, about = "Missing Signer Check (MSC) Detector")]
+#[clap(after_help = "Examples:
+  msc programs/vault
+  msc --repo https://github.com/user/repo.git programs
+  msc --format sarif programs > signer-checks.sarif
+  msc --fail-above 0 programs")]
+struct Args {
+    /// Directory to analyze (relative to --repo when given)
+    #[clap(value_name = "DIR", default_value = ".")]
+    dir: PathBuf,
+
+    /// Git repository URL to clone, or path of a local checkout
+    #[clap(long, value_name = "REPO_URL_OR_PATH")]
+    repo: Option<String>,
+
+    /// Branch, tag or commit of --repo to analyze
+    #[clap(long, value_name = "REV", requires = "repo")]
+    rev: Option<String>,
+
+    /// Output format
+    #[clap(long, value_enum, default_value = "text")]
+    format: Format,
+
+    /// List every handler checked, not only those with findings
+    #[clap(short, long)]
+    verbose: bool,
+
+    /// Exit with status 1 if there are more than N findings
+    #[clap(long, value_name = "N")]
+    fail_above: Option<usize>,
+}
+
+/// Structured form of the analysis for `--format json`
+#[derive(Serialize)]
+struct JsonReport<'a> {
+    summary: Summary,
+    handlers: &'a [CheckedHandler],
+    /// By program, then handler
+    findings: &'a [Finding],
+    errors: Vec<FileError>,
+}
+
+#[derive(Serialize)]
+struct Summary {
+    programs: usize,
+    handlers: usize,
+    /// Handlers writing an account, whose gated accounts were checked
+    mutating_handlers: usize,
+    findings: usize,
+}
+
+#[derive(Serialize)]
+struct FileError {
+    file: PathBuf,
+    error: String,
+}
+
+fn print_text(analysis: &Analysis, check: &Check, verbose: bool) {
+    if !check.findings.is_empty() {
+        println!("\nAccounts gating writes without a signer check:\n");
+    }
+    for finding in &check.findings {
+        let gate = match &finding.gated_by {
+            Some(field) => format!("{} on {}", finding.gate, field),
+            None => finding.gate.to_string(),
+        };
+        println!("{}::{}: {} ({}) at {}", finding.program, finding.handler, finding.account, gate, finding.location);
+        println!("    gated at {}", finding.gate_location);
+    }
+    if verbose {
+        println!("\nHandlers checked:");
+        for handler in &check.handlers {
+            let kind = if handler.anchor { "Anchor" } else { "native" };
+            let writes = if handler.mutates { "" } else { ", no writes" };
+            println!("  {}::{} ({}{}) at {}", handler.program, handler.handler, kind, writes, handler.location);
+        }
+    }
+
+    println!("\nSummary:");
+    println!("Programs: {}", analysis.programs.programs.len());
+    println!("Handlers checked: {}", check.handlers.len());
+    println!("Handlers writing accounts: {}", check.handlers.iter().filter(|handler| handler.mutates).count());
+    println!("Missing signer checks: {}", check.findings.len());
+}
+
+fn write_json(out: &mut dyn Write, analysis: &Analysis, check: &Check) -> io::Result<()> {
+    let report = JsonReport {
+        summary: Summary {
+            programs: analysis.programs.programs.len(),
+            handlers: check.handlers.len(),
+            mutating_handlers: check.handlers.iter().filter(|handler| handler.mutates).count(),
+            findings: check.findings.len(),
+        },
+        handlers: &check.handlers,
+        findings: &check.findings,
+        errors: analysis.errors.iter()
+            .map(|(file, error)| FileError { file: file.clone(), error: error.clone() })
+            .collect(),
+    };
+    output::write_json(out, "msc", &report)
+}
+
+fn run(args: &Args) -> Result<(), Box<dyn std::error::Error>> {
+    let repository = args.repo.as_deref().map(|repo| Repository::open(repo, args.rev.as_deref())).transpose()?;
+    let dir = match &repository {
+        Some(repository) => repository.subdir(&args.dir)?,
+        None => args.dir.clone(),
+    };
+    if !dir.exists() {
+        return Err(format!("Path '{}' does not exist", dir.display()).into());
+    }
+    if args.format == Format::Text {
+        println!("Analyzing: {}", dir.display());
+    }
+
+    let analysis = msc::analyze(&dir)?;
+    for (file, error) in &analysis.errors {
+        eprintln!("Error analyzing {}: {}", file.display(), error);
+    }
+    let check = analysis.check();
+
+    let mut out = io::stdout().lock();
+    match args.format {
+        Format::Text => print_text(&analysis, &check, args.verbose),
+        Format::Json => write_json(&mut out, &analysis, &check)?,
+        Format::Csv => report::write_csv(&mut out, &check.findings)?,
+        Format::Markdown => report::write_markdown(&mut out, &dir, &analysis, &check)?,
+        Format::Sarif => {
+            // Artifact URIs are relative to the repository root, or to where msc runs
+            let base = repository.as_ref().map_or_else(|| PathBuf::from("."), |repository| repository.root().to_path_buf());
+            sarif::write_sarif(&mut out, &check.findings, &base)?;
+            writeln!(out)?;
+        }
+    }
+    drop(out);
+    output::enforce_limit(args.fail_above, check.findings.len(), "missing signer check(s)", check.findings.iter().map(|finding| {
+        format!("{}::{}: {} at {}", finding.program, finding.handler, finding.account, finding.location)
+    }));
+    Ok(())
+}
+
+fn main() {
+    let args = Args::parse();
+    if let Err(e) = run(&args) {
+        eprintln!("Error: {}", e);
+        process::exit(1);
+    }
+}
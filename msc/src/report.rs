@@ -0,0 +1,63 @@
+//! `--format csv`, one row per finding, and `--format markdown`, a review-ready report
+//! with the summary and the findings.
+
+use std::io::{self, Write};
+use std::path::Path;
+
+use trr_core::output::{markdown_code, write_csv_row};
+
+use msc::{Analysis, Check, Finding};
+
+/// Rows are by program, then handler
+pub fn write_csv(out: &mut dyn Write, findings: &[Finding]) -> io::Result<()> {
+    write_csv_row(out, &["program", "handler", "account", "gate", "gated_by", "file", "line", "gate_file", "gate_line"])?;
+    for finding in findings {
+        write_csv_row(out, &[
+            &finding.program,
+            &finding.handler,
+            &finding.account,
+            &finding.gate.to_string(),
+            finding.gated_by.as_deref().unwrap_or(""),
+            &finding.location.file.display().to_string(),
+            &finding.location.line.to_string(),
+            &finding.gate_location.file.display().to_string(),
+            &finding.gate_location.line.to_string(),
+        ])?;
+    }
+    Ok(())
+}
+
+pub fn write_markdown(out: &mut dyn Write, target: &Path, analysis: &Analysis, check: &Check) -> io::Result<()> {
+    writeln!(out, "# Missing Signer Check Report\n")?;
+    writeln!(out, "Target: {}\n", markdown_code(&target.display().to_string()))?;
+    writeln!(out, "## Summary\n")?;
+    writeln!(out, "| Metric | Value |")?;
+    writeln!(out, "| --- | ---: |")?;
+    writeln!(out, "| Programs | {} |", analysis.programs.programs.len())?;
+    writeln!(out, "| Handlers checked | {} |", check.handlers.len())?;
+    writeln!(out, "| Handlers writing accounts | {} |", check.handlers.iter().filter(|handler| handler.mutates).count())?;
+    writeln!(out, "| Missing signer checks | {} |", check.findings.len())?;
+
+    if check.findings.is_empty() {
+        return Ok(());
+    }
+    writeln!(out, "\n## Findings\n")?;
+    writeln!(out, "| Handler | Account | Gate | Account location | Gate location |")?;
+    writeln!(out, "| --- | --- | --- | --- | --- |")?;
+    for finding in &check.findings {
+        let gate = match &finding.gated_by {
+            Some(field) => format!("{} on {}", finding.gate, markdown_code(field)),
+            None => finding.gate.to_string(),
+        };
+        writeln!(
+            out,
+            "| {} | {} | {} | {} | {} |",
+            markdown_code(&format!("{}::{}", finding.program, finding.handler)),
+            markdown_code(&finding.account),
+            gate,
+            markdown_code(&finding.location.to_string()),
+            markdown_code(&finding.gate_location.to_string()),
+        )?;
+    }
+    Ok(())
+}
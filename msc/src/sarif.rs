@@ -0,0 +1,48 @@
+//! SARIF 2.1.0 output: a `missing-signer-check` warning per finding, located at the
+//! account, with the constraint or comparison gating on it as a related location.
+
+use std::io::{self, Write};
+use std::path::Path;
+
+use serde_json::json;
+use trr_core::sarif::{location, write_log};
+
+use msc::Finding;
+
+const RULE: &str = "missing-signer-check";
+
+pub fn write_sarif(out: &mut dyn Write, findings: &[Finding], base: &Path) -> io::Result<()> {
+    let results = findings.iter()
+        .map(|finding| {
+            let gate = match &finding.gated_by {
+                Some(field) => format!("{} on {}", finding.gate, field),
+                None => finding.gate.to_string(),
+            };
+            json!({
+                "ruleId": RULE,
+                "ruleIndex": 0,
+                "level": "warning",
+                "message": {
+                    "text": format!(
+                        "{}::{} gates writes on the key of {} ({}) but never checks that it signed",
+                        finding.program, finding.handler, finding.account, gate
+                    ),
+                },
+                "locations": [location(&finding.location, base)],
+                "relatedLocations": [location(&finding.gate_location, base)],
+                "properties": { "gate": finding.gate, "handler": finding.handler },
+            })
+        })
+        .collect();
+
+    let rules = vec![json!({
+        "id": RULE,
+        "name": "MissingSignerCheck",
+        "shortDescription": { "text": "Account gating state changes without a signer check" },
+        "fullDescription": {
+            "text": "The handler writes state after comparing this account's key (has_one, a constraint, PDA seeds or a key comparison), but the account is not a Signer, has no signer constraint and its is_signer is never checked. Anyone can pass another user's key.",
+        },
+        "defaultConfiguration": { "level": "warning" },
+    })];
+    write_log(out, "msc", env!("CARGO_PKG_VERSION"), rules, results)
+}
@@ -0,0 +1,116 @@
+use anchor_lang::prelude::*;
+
+declare_id!("Escrow1111111111111111111111111111111111111");
+
+#[program]
+pub mod escrow {
+    use super::*;
+
+    /// Missing: `authority` gates the vault through `has_one` but never signs
+    pub fn withdraw(ctx: Context<Withdraw>, amount: u64) -> Result<()> {
+        let vault = &mut ctx.accounts.vault;
+        vault.balance -= amount;
+        Ok(())
+    }
+
+    pub fn update(ctx: Context<Update>, fee: u16) -> Result<()> {
+        ctx.accounts.config.fee = fee;
+        Ok(())
+    }
+
+    pub fn close_checked(ctx: Context<CloseChecked>) -> Result<()> {
+        require!(ctx.accounts.owner.is_signer, EscrowError::Unauthorized);
+        Ok(())
+    }
+
+    /// Missing: the user's state PDA is derived from a key nobody signed for
+    pub fn claim(ctx: Context<Claim>) -> Result<()> {
+        ctx.accounts.user_state.claimed = true;
+        Ok(())
+    }
+
+    /// Missing: `admin` is compared in the body only
+    pub fn sweep(ctx: Context<Sweep>) -> Result<()> {
+        require_keys_eq!(ctx.accounts.config.admin, ctx.accounts.admin.key(), EscrowError::Unauthorized);
+        ctx.accounts.config.fee = 0;
+        Ok(())
+    }
+
+    /// Reads only
+    pub fn view(ctx: Context<View>) -> Result<()> {
+        msg!("balance {}", ctx.accounts.vault.balance);
+        Ok(())
+    }
+}
+
+#[derive(Accounts)]
+pub struct Withdraw<'info> {
+    #[account(mut, has_one = authority, has_one = mint)]
+    pub vault: Account<'info, Vault>,
+    /// CHECK: compared by has_one
+    pub authority: UncheckedAccount<'info>,
+    pub mint: Account<'info, Mint>,
+}
+
+#[derive(Accounts)]
+pub struct Update<'info> {
+    #[account(mut, has_one = admin)]
+    pub config: Account<'info, Config>,
+    pub admin: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct CloseChecked<'info> {
+    #[account(mut, close = owner, constraint = vault.owner == owner.key() @ EscrowError::Unauthorized)]
+    pub vault: Account<'info, Vault>,
+    #[account(mut)]
+    pub owner: AccountInfo<'info>,
+}
+
+#[derive(Accounts)]
+pub struct Claim<'info> {
+    #[account(mut, seeds = [b"user", user.key().as_ref()], bump)]
+    pub user_state: Account<'info, UserState>,
+    /// CHECK: only used for the seeds
+    pub user: AccountInfo<'info>,
+}
+
+#[derive(Accounts)]
+pub struct Sweep<'info> {
+    #[account(mut)]
+    pub config: Account<'info, Config>,
+    /// CHECK: compared in the handler
+    pub admin: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct View<'info> {
+    #[account(has_one = authority)]
+    pub vault: Account<'info, Vault>,
+    /// CHECK: read only
+    pub authority: UncheckedAccount<'info>,
+}
+
+#[account]
+pub struct Vault {
+    pub authority: Pubkey,
+    pub owner: Pubkey,
+    pub mint: Pubkey,
+    pub balance: u64,
+}
+
+#[account]
+pub struct Config {
+    pub admin: Pubkey,
+    pub fee: u16,
+}
+
+#[account]
+pub struct UserState {
+    pub claimed: bool,
+}
+
+#[error_code]
+pub enum EscrowError {
+    Unauthorized,
+}
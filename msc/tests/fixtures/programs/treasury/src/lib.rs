@@ -0,0 +1,73 @@
+use borsh::{BorshDeserialize, BorshSerialize};
+use solana_program::account_info::{next_account_info, AccountInfo};
+use solana_program::entrypoint;
+use solana_program::entrypoint::ProgramResult;
+use solana_program::program_error::ProgramError;
+use solana_program::pubkey::Pubkey;
+
+entrypoint!(process_instruction);
+
+#[derive(BorshSerialize, BorshDeserialize)]
+pub struct Treasury {
+    pub owner: Pubkey,
+}
+
+#[derive(BorshSerialize, BorshDeserialize)]
+pub struct Config {
+    pub admin: Pubkey,
+}
+
+pub fn process_instruction(program_id: &Pubkey, accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
+    match data.first() {
+        Some(0) => process_withdraw(accounts),
+        Some(1) => process_set_admin(accounts),
+        _ => process_deposit(program_id, accounts),
+    }
+}
+
+/// Missing: the owner's key is compared, but nothing checks that the owner signed
+fn process_withdraw(accounts: &[AccountInfo]) -> ProgramResult {
+    let iter = &mut accounts.iter();
+    let treasury = next_account_info(iter)?;
+    let owner = next_account_info(iter)?;
+    let state = Treasury::try_from_slice(&treasury.data.borrow())?;
+    if state.owner != *owner.key {
+        return Err(ProgramError::IllegalOwner);
+    }
+    **treasury.try_borrow_mut_lamports()? -= 1;
+    Ok(())
+}
+
+/// Checked by `assert_admin`
+fn process_set_admin(accounts: &[AccountInfo]) -> ProgramResult {
+    let [config, admin, new_admin] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+    assert_admin(config, admin)?;
+    let updated = Config { admin: *new_admin.key };
+    updated.serialize(&mut &mut config.data.borrow_mut()[..])?;
+    Ok(())
+}
+
+fn assert_admin(config: &AccountInfo, admin: &AccountInfo) -> ProgramResult {
+    if !admin.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    let state = Config::try_from_slice(&config.data.borrow())?;
+    if state.admin != *admin.key {
+        return Err(ProgramError::IllegalOwner);
+    }
+    Ok(())
+}
+
+/// Missing: the receipt PDA is derived from the depositor's key, unsigned
+fn process_deposit(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+    let depositor = &accounts[0];
+    let receipt = &accounts[1];
+    let (expected, _) = Pubkey::find_program_address(&[b"receipt", depositor.key.as_ref()], program_id);
+    if expected != *receipt.key {
+        return Err(ProgramError::InvalidSeeds);
+    }
+    receipt.try_borrow_mut_data()?[0] = 1;
+    Ok(())
+}
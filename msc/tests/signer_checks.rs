@@ -0,0 +1,68 @@
+// Checks the handlers of tests/fixtures/programs: escrow (Anchor) and treasury
+// (native), whose doc comments say which accounts miss a signer check.
+
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use msc::GateKind;
+
+fn fixture() -> PathBuf {
+    Path::new(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/programs")).to_path_buf()
+}
+
+#[test]
+fn findings() {
+    let check = msc::analyze(&fixture()).unwrap().check();
+    let findings: Vec<_> = check.findings.iter()
+        .map(|finding| (finding.program.as_str(), finding.handler.as_str(), finding.account.as_str(), finding.gate, finding.gated_by.as_deref()))
+        .collect();
+    assert_eq!(findings, [
+        ("escrow", "claim", "user", GateKind::Seeds, Some("user_state")),
+        ("escrow", "sweep", "admin", GateKind::Comparison, None),
+        ("escrow", "withdraw", "authority", GateKind::HasOne, Some("vault")),
+        ("treasury", "process_deposit", "depositor", GateKind::Seeds, None),
+        ("treasury", "process_withdraw", "owner", GateKind::Comparison, None),
+    ]);
+    assert_eq!(check.findings[4].location.line, 32);
+    assert_eq!(check.findings[4].gate_location.line, 34);
+}
+
+#[test]
+fn handlers() {
+    let check = msc::analyze(&fixture()).unwrap().check();
+    let handlers: Vec<_> = check.handlers.iter()
+        .map(|handler| (handler.handler.as_str(), handler.anchor, handler.mutates))
+        .collect();
+    assert_eq!(handlers, [
+        ("withdraw", true, true),
+        ("update", true, true),
+        ("close_checked", true, true),
+        ("claim", true, true),
+        ("sweep", true, true),
+        ("view", true, false),
+        ("process_withdraw", false, true),
+        ("process_set_admin", false, true),
+        ("process_deposit", false, true),
+    ]);
+}
+
+#[test]
+fn fail_above_and_sarif() {
+    let run = |args: &[&str]| Command::new(env!("CARGO_BIN_EXE_msc"))
+        .args(args)
+        .arg(fixture())
+        .output()
+        .expect("failed to run msc");
+
+    assert!(run(&["--fail-above", "5"]).status.success());
+
+    let output = run(&["--fail-above", "0", "--format", "sarif"]);
+    assert_eq!(output.status.code(), Some(1));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("5 missing signer check(s), more than 0"), "stderr: {}", stderr);
+    let log: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let results = log["runs"][0]["results"].as_array().unwrap();
+    assert_eq!(results.len(), 5);
+    assert!(results[0]["message"]["text"].as_str().unwrap().contains("escrow::claim gates writes on the key of user (seeds on user_state)"));
+    assert_eq!(results[0]["relatedLocations"][0]["physicalLocation"]["region"]["startLine"], 73);
+}
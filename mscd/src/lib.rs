@@ -0,0 +1,959 @@
+//! Struct composition analysis behind the `mscd` binary: parses Rust sources, following
+//! `mod` declarations and `include!`, resolves field types through imports and type
+//! aliases, and measures how deeply the structs nest.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::fs;
+use syn::{parse_file, Item, Fields, Type, GenericArgument, PathArguments, UseTree, ItemUse};
+use quote::quote;
+use serde::Serialize;
+use trr_core::output::Format;
+use trr_core::walk;
+
+#[derive(Debug, Clone)]
+pub struct AnalysisOptions {
+    /// Suppress progress messages and reduce the report to a single summary line
+    pub quiet: bool,
+    pub format: Format,
+    /// When non-empty, only structs deriving at least one of these traits are analyzed
+    pub with_derives: Vec<String>,
+    /// Only report `pub` structs; private ones are still traversed as intermediate nodes
+    pub public_only: bool,
+    /// Also compute the depth of the serialized form, honouring serde/borsh field attributes
+    pub serialized_depth: bool,
+}
+
+impl AnalysisOptions {
+    /// Print a progress message unless running quietly. Progress goes to stderr
+    /// in the other formats so stdout only ever carries the document.
+    pub fn progress(&self, message: fmt::Arguments) {
+        if self.quiet {
+            return;
+        }
+        match self.format {
+            Format::Text => println!("{}", message),
+            _ => eprintln!("{}", message),
+        }
+    }
+}
+
+/// How a field takes part in the struct's serialized form
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SerializedMode {
+    Normal,
+    /// `#[serde(skip)]`, `#[serde(skip_serializing)]`, `#[borsh(skip)]`: not serialized at all
+    Skip,
+    /// `#[serde(flatten)]`: the field's own fields are inlined into the parent
+    Flatten,
+}
+
+/// Represents a struct's dependency information
+#[derive(Debug, Clone)]
+struct StructInfo {
+    name: String,
+    field_types: Vec<String>,
+    /// Serialization attribute of the field each entry of `field_types` came from
+    field_modes: Vec<SerializedMode>,
+    module_path: Vec<String>, // Track the module path for this struct
+    /// Traits named in `#[derive(...)]`, by their last path segment
+    derives: Vec<String>,
+    /// Declared with plain `pub` visibility
+    is_public: bool,
+}
+
+/// Represents a type alias
+#[derive(Debug, Clone)]
+struct TypeAlias {
+    name: String,
+    target_type: String,
+}
+
+/// Represents an import/use statement
+#[derive(Debug, Clone)]
+struct ImportInfo {
+    /// The imported path (e.g., "a::b::Inner")
+    full_path: String,
+    /// The local name it's imported as (e.g., "Inner" or "Alias")
+    local_name: String,
+    /// The module where this import exists
+    module_path: Vec<String>,
+}
+
+/// A file that could not be parsed and was left out of the analysis
+#[derive(Debug, Clone, Serialize)]
+pub struct ParseFailure {
+    pub path: PathBuf,
+    pub error: String,
+}
+
+/// Context for parsing with module information
+#[derive(Debug)]
+struct ParseContext {
+    current_module_path: Vec<String>,
+    structs: Vec<StructInfo>,
+    type_aliases: Vec<TypeAlias>,
+    imports: Vec<ImportInfo>,
+    /// Maps qualified module names to their file paths for out-of-line modules
+    module_files: HashMap<String, PathBuf>,
+    /// Root directory for resolving relative paths
+    root_dir: PathBuf,
+    /// Directory of the file currently being parsed, for `include!` paths
+    current_file_dir: PathBuf,
+    /// Files skipped because syn could not parse them
+    parse_failures: Vec<ParseFailure>,
+    /// Canonical paths of files already analyzed, so no file is counted twice
+    visited_files: HashSet<PathBuf>,
+    options: AnalysisOptions,
+}
+
+impl ParseContext {
+    fn new(options: &AnalysisOptions) -> Self {
+        Self {
+            current_module_path: Vec::new(),
+            structs: Vec::new(),
+            type_aliases: Vec::new(),
+            imports: Vec::new(),
+            module_files: HashMap::new(),
+            root_dir: PathBuf::new(),
+            current_file_dir: PathBuf::new(),
+            parse_failures: Vec::new(),
+            visited_files: HashSet::new(),
+            options: options.clone(),
+        }
+    }
+
+    fn with_root_dir(root_dir: PathBuf, options: &AnalysisOptions) -> Self {
+        Self {
+            current_module_path: Vec::new(),
+            structs: Vec::new(),
+            type_aliases: Vec::new(),
+            imports: Vec::new(),
+            module_files: HashMap::new(),
+            current_file_dir: root_dir.clone(),
+            root_dir,
+            parse_failures: Vec::new(),
+            visited_files: HashSet::new(),
+            options: options.clone(),
+        }
+    }
+
+    fn push_module(&mut self, module_name: String) {
+        self.current_module_path.push(module_name);
+    }
+    fn pop_module(&mut self) {
+        self.current_module_path.pop();
+    }
+
+    /// Qualify a name declared in the current module
+    fn qualified_name(&self, name: &str) -> String {
+        if self.current_module_path.is_empty() {
+            name.to_string()
+        } else {
+            format!("{}::{}", self.current_module_path.join("::"), name)
+        }
+    }
+
+}
+
+/// Calculates the maximum depth of nested struct compositions
+fn calculate_max_struct_depth(
+    struct_map: &HashMap<String, Vec<String>>,
+    struct_name: &str,
+    visited: &mut HashSet<String>,
+    curr_depth: usize,
+) -> usize {
+    // Base case: if we've seen this struct before, return current depth to avoid cycles
+    if !visited.insert(struct_name.to_string()) {
+        return curr_depth;
+    }
+
+    let mut max_depth = curr_depth;
+
+    // If the struct exists in our map, check its field types
+    if let Some(field_types) = struct_map.get(struct_name) {
+        for field_type in field_types {
+            // Only recurse if the field type is in our struct map
+            if struct_map.contains_key(field_type) {
+                let depth = calculate_max_struct_depth(
+                    struct_map,
+                    field_type,
+                    visited,
+                    curr_depth + 1,
+                );
+                max_depth = max_depth.max(depth);
+            }
+        }
+    }
+
+    visited.remove(struct_name);
+    max_depth
+}
+
+/// Calculates the maximum depth of the serialized form of a struct. Flattened fields
+/// are inlined into their parent, so following them does not add a level.
+fn calculate_serialized_depth(
+    serialized_map: &HashMap<String, Vec<(String, SerializedMode)>>,
+    struct_name: &str,
+    visited: &mut HashSet<String>,
+    curr_depth: usize,
+) -> usize {
+    if !visited.insert(struct_name.to_string()) {
+        return curr_depth;
+    }
+
+    let mut max_depth = curr_depth;
+    if let Some(field_types) = serialized_map.get(struct_name) {
+        for (field_type, mode) in field_types {
+            if serialized_map.contains_key(field_type) {
+                let step = if *mode == SerializedMode::Flatten { 0 } else { 1 };
+                let depth = calculate_serialized_depth(serialized_map, field_type, visited, curr_depth + step);
+                max_depth = max_depth.max(depth);
+            }
+        }
+    }
+
+    visited.remove(struct_name);
+    max_depth
+}
+
+/// Extracts all type dependencies from a syn::Type, handling wrappers and complex types
+fn extract_type_dependencies(ty: &Type, context: &ParseContext) -> Vec<String> {
+    let mut dependencies = Vec::new();
+    
+    match ty {
+        // Handle path types (most common case)
+        Type::Path(type_path) => {
+            dependencies.extend(extract_path_dependencies(&type_path.path, context));
+        }
+        // Handle references (&T)
+        Type::Reference(type_ref) => {
+            dependencies.extend(extract_type_dependencies(&type_ref.elem, context));
+        }
+        // Handle slices ([T])
+        Type::Slice(type_slice) => {
+            dependencies.extend(extract_type_dependencies(&type_slice.elem, context));
+        }
+        // Handle arrays ([T; N])
+        Type::Array(type_array) => {
+            dependencies.extend(extract_type_dependencies(&type_array.elem, context));
+        }
+        // Handle tuples - include ALL elements
+        Type::Tuple(type_tuple) => {
+            for elem in &type_tuple.elems {
+                dependencies.extend(extract_type_dependencies(elem, context));
+            }
+        }
+        // Handle raw pointers (*const T, *mut T)
+        Type::Ptr(type_ptr) => {
+            dependencies.extend(extract_type_dependencies(&type_ptr.elem, context));
+        }
+        // Handle function pointers and other types
+        _ => {
+            // For other types, convert to string and try to extract
+            let tokens = quote!(#ty);
+            let type_str = tokens.to_string().replace(' ', "");
+            if !type_str.is_empty() && !is_primitive_type(&type_str) {
+                dependencies.push(type_str);
+            }
+        }
+    }
+    
+    dependencies
+}
+
+/// Extract dependencies from a syn::Path, handling generics and module paths
+fn extract_path_dependencies(path: &syn::Path, context: &ParseContext) -> Vec<String> {
+    let mut dependencies = Vec::new();
+    
+    // Get the full path as a string
+    let path_str = path.segments.iter()
+        .map(|segment| segment.ident.to_string())
+        .collect::<Vec<_>>()
+        .join("::");
+    
+    // Handle Self keyword
+    let resolved_path = if path_str == "Self" {
+        // Replace Self with current struct name (we'll handle this in the calling context)
+        path_str
+    } else {
+        // Resolve the path through imports and relative paths
+        resolve_path(&path_str, context)
+    };
+    
+    // Add the main type if it's not primitive
+    if !is_primitive_type(&resolved_path) {
+        dependencies.push(resolved_path);
+    }
+    
+    // Extract generic arguments
+    for segment in &path.segments {
+        if let PathArguments::AngleBracketed(args) = &segment.arguments {
+            for arg in &args.args {
+                if let GenericArgument::Type(ty) = arg {
+                    dependencies.extend(extract_type_dependencies(ty, context));
+                }
+            }
+        }
+    }
+    
+    dependencies
+}
+
+/// Resolve a path string through imports, aliases, and relative paths
+fn resolve_path(path_str: &str, context: &ParseContext) -> String {
+    // Primitives and prelude types are the same in every module
+    if is_primitive_type(path_str) {
+        return path_str.to_string();
+    }
+    
+    // Imports only apply within the module that declares them and bind the first
+    // segment of the path (`use a::b; b::Inner` or `use a::Inner as Alias; Alias`)
+    let (first_segment, rest) = match path_str.split_once("::") {
+        Some((first, rest)) => (first, Some(rest)),
+        None => (path_str, None),
+    };
+    if let Some(import) = context.imports.iter().find(|imp| {
+        imp.module_path == context.current_module_path && imp.local_name == first_segment
+    }) {
+        let imported = normalize_relative_path(&import.full_path, &context.current_module_path);
+        return match rest {
+            Some(rest) => format!("{}::{}", imported, rest),
+            None => imported,
+        };
+    }
+    
+    // Handle relative paths
+    normalize_relative_path(path_str, &context.current_module_path)
+}
+
+/// Normalize relative paths (crate::, self::, super::)
+fn normalize_relative_path(path_str: &str, current_module: &[String]) -> String {
+    if path_str.starts_with("crate::") {
+        // crate:: means from the root
+        path_str.strip_prefix("crate::").unwrap().to_string()
+    } else if path_str.starts_with("self::") {
+        // self:: means current module
+        let relative = path_str.strip_prefix("self::").unwrap();
+        if current_module.is_empty() {
+            relative.to_string()
+        } else {
+            format!("{}::{}", current_module.join("::"), relative)
+        }
+    } else if path_str.starts_with("super::") {
+        // super:: means parent module
+        let relative = path_str.strip_prefix("super::").unwrap();
+        if current_module.len() <= 1 {
+            relative.to_string()
+        } else {
+            let parent_path = &current_module[..current_module.len() - 1];
+            format!("{}::{}", parent_path.join("::"), relative)
+        }
+    } else if current_module.is_empty() || path_str.contains("::") {
+        // Already absolute or we're at root
+        path_str.to_string()
+    } else {
+        // Relative to current module
+        format!("{}::{}", current_module.join("::"), path_str)
+    }
+}
+
+/// Check if a type is a primitive type
+fn is_primitive_type(type_name: &str) -> bool {
+    matches!(type_name, 
+        "u8" | "u16" | "u32" | "u64" | "u128" | "usize" |
+        "i8" | "i16" | "i32" | "i64" | "i128" | "isize" |
+        "f32" | "f64" | "bool" | "char" | "str" | "()" |
+        "String" | "Vec" | "Option" | "Result" | "Box" | "Rc" | "Arc" |
+        "HashMap" | "HashSet" | "BTreeMap" | "BTreeSet"
+    )
+}
+
+/// Process items within a module or file, handling nested structures
+fn process_items(items: &[Item], context: &mut ParseContext) {
+    // First pass: collect imports and module declarations
+    for item in items {
+        match item {
+            Item::Use(item_use) => {
+                process_use_item(item_use, context);
+            }
+            Item::Mod(item_mod) if item_mod.content.is_none() => {
+                // Out-of-line module (mod x;)
+                let module_name = item_mod.ident.to_string();
+                let module_path = resolve_module_file(&module_name, context);
+                if let Some(path) = module_path {
+                    context.module_files.insert(context.qualified_name(&module_name), path);
+                }
+            }
+            _ => {}
+        }
+    }
+    
+    // Second pass: process structs and other items
+    for item in items {
+        match item {
+            Item::Struct(item_struct) => {
+                let struct_name = item_struct.ident.to_string();
+                context.options.progress(format_args!("Found struct: {} in module: {:?}", struct_name, context.current_module_path));
+                let mut field_types = Vec::new();
+                let mut field_modes = Vec::new();
+
+                match &item_struct.fields {
+                    // Named fields
+                    Fields::Named(fields) => {
+                        for field in &fields.named {
+                            let mut deps = extract_type_dependencies(&field.ty, context);
+                            // Handle Self references
+                            deps = deps.into_iter().map(|dep| {
+                                if dep == "Self" {
+                                    struct_name.clone()
+                                } else {
+                                    dep
+                                }
+                            }).collect();
+                            field_modes.extend(std::iter::repeat_n(serialized_mode(&field.attrs), deps.len()));
+                            field_types.extend(deps);
+                        }
+                    }
+                    // Tuple structs (unnamed fields)
+                    Fields::Unnamed(fields) => {
+                        for field in &fields.unnamed {
+                            let mut deps = extract_type_dependencies(&field.ty, context);
+                            // Handle Self references
+                            deps = deps.into_iter().map(|dep| {
+                                if dep == "Self" {
+                                    struct_name.clone()
+                                } else {
+                                    dep
+                                }
+                            }).collect();
+                            field_modes.extend(std::iter::repeat_n(serialized_mode(&field.attrs), deps.len()));
+                            field_types.extend(deps);
+                        }
+                    }
+                    // Unit structs (no fields)
+                    Fields::Unit => {}
+                }
+
+                // Create full struct name with module path
+                let full_name = if context.current_module_path.is_empty() {
+                    struct_name.clone()
+                } else {
+                    format!("{}::{}", context.current_module_path.join("::"), struct_name)
+                };
+
+                context.structs.push(StructInfo {
+                    name: full_name,
+                    field_types,
+                    field_modes,
+                    module_path: context.current_module_path.clone(),
+                    derives: extract_derives(&item_struct.attrs),
+                    is_public: matches!(item_struct.vis, syn::Visibility::Public(_)),
+                });
+            }
+            Item::Mod(item_mod) => {
+                if let Some((_, items)) = &item_mod.content {
+                    // Process inline module
+                    let module_name = item_mod.ident.to_string();
+                    context.push_module(module_name);
+                    process_items(items, context);
+                    context.pop_module();
+                } else {       
+                    // Out-of-line module - parse the file into the same context so its
+                    // items (including nested modules) get their true module path
+                    let module_name = item_mod.ident.to_string();
+                    if let Some(module_file) = context.module_files.get(&context.qualified_name(&module_name)).cloned() {
+                        context.push_module(module_name);
+                        if let Err(e) = process_module_file(&module_file, context) {
+                            eprintln!("Error processing file {:?}: {}", module_file, e);
+                        }
+                        context.pop_module();
+                    }
+                }
+            }
+            Item::Macro(item_macro) if item_macro.mac.path.is_ident("include") => {
+                // include!("file.rs") splices the file's items into the current module
+                match resolve_include_path(&item_macro.mac, context) {
+                    Some(include_file) if include_file.is_file() => {
+                        if let Err(e) = process_module_file(&include_file, context) {
+                            eprintln!("Error processing file {:?}: {}", include_file, e);
+                        }
+                    }
+                    Some(include_file) => {
+                        context.options.progress(format_args!("Included file not found: {:?}", include_file));
+                    }
+                    None => {
+                        context.options.progress(format_args!("Could not resolve include! path in module: {:?}", context.current_module_path));
+                    }
+                }
+            }
+            Item::Type(item_type) => {
+                // Handle type aliases
+                let alias_name = item_type.ident.to_string();
+                let target_deps = extract_type_dependencies(&item_type.ty, context);
+                
+                if let Some(target_type) = target_deps.first() {
+                    let full_alias_name = if context.current_module_path.is_empty() {
+                        alias_name.clone()
+                    } else {
+                        format!("{}::{}", context.current_module_path.join("::"), alias_name)
+                    };
+                    
+                    context.type_aliases.push(TypeAlias {
+                        name: full_alias_name,
+                        target_type: target_type.clone(),
+                    });
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Classify a field by its serde/borsh attributes
+fn serialized_mode(attrs: &[syn::Attribute]) -> SerializedMode {
+    for attr in attrs {
+        // Legacy borsh spelling
+        if attr.path().is_ident("borsh_skip") {
+            return SerializedMode::Skip;
+        }
+        if !attr.path().is_ident("serde") && !attr.path().is_ident("borsh") {
+            continue;
+        }
+        let Ok(metas) = attr.parse_args_with(syn::punctuated::Punctuated::<syn::Meta, syn::Token![,]>::parse_terminated) else {
+            continue;
+        };
+        for meta in metas {
+            if let syn::Meta::Path(path) = meta {
+                if path.is_ident("skip") || path.is_ident("skip_serializing") {
+                    return SerializedMode::Skip;
+                }
+                if path.is_ident("flatten") {
+                    return SerializedMode::Flatten;
+                }
+            }
+        }
+    }
+    SerializedMode::Normal
+}
+
+/// Collect the trait names from every `#[derive(...)]` attribute
+fn extract_derives(attrs: &[syn::Attribute]) -> Vec<String> {
+    attrs.iter()
+        .filter(|attr| attr.path().is_ident("derive"))
+        .filter_map(|attr| {
+            attr.parse_args_with(syn::punctuated::Punctuated::<syn::Path, syn::Token![,]>::parse_terminated).ok()
+        })
+        .flatten()
+        .filter_map(|path| path.segments.last().map(|segment| segment.ident.to_string()))
+        .collect()
+}
+
+/// Process a use statement to extract import information
+fn process_use_item(item_use: &ItemUse, context: &mut ParseContext) {
+    process_use_tree(&item_use.tree, Vec::new(), context);
+}
+
+/// Recursively process use tree to extract all imports
+fn process_use_tree(tree: &UseTree, prefix: Vec<String>, context: &mut ParseContext) {
+    match tree {
+        UseTree::Path(use_path) => {
+            let mut new_prefix = prefix;
+            new_prefix.push(use_path.ident.to_string());
+            process_use_tree(&use_path.tree, new_prefix, context);
+        }
+        UseTree::Name(use_name) => {
+            let mut full_path = prefix;
+            full_path.push(use_name.ident.to_string());
+            let full_path_str = full_path.join("::");
+            let local_name = use_name.ident.to_string();
+            
+            context.imports.push(ImportInfo {
+                full_path: full_path_str,
+                local_name,
+                module_path: context.current_module_path.clone(),
+            });
+        }
+        UseTree::Rename(use_rename) => {
+            let mut full_path = prefix;
+            full_path.push(use_rename.ident.to_string());
+            let full_path_str = full_path.join("::");
+            let local_name = use_rename.rename.to_string();
+            
+            context.imports.push(ImportInfo {
+                full_path: full_path_str,
+                local_name,
+                module_path: context.current_module_path.clone(),
+            });
+        }
+        UseTree::Glob(_) => {
+            // For glob imports, we'd need more sophisticated handling
+            // For now, we'll skip them as they're complex to resolve
+        }
+        UseTree::Group(use_group) => {
+            for tree in &use_group.items {
+                process_use_tree(tree, prefix.clone(), context);
+            }
+        }
+    }
+}
+
+/// Resolve the file path for an out-of-line module
+fn resolve_module_file(module_name: &str, context: &ParseContext) -> Option<PathBuf> {
+    let base_path = if context.current_module_path.is_empty() {
+        context.root_dir.clone()
+    } else {
+        context.root_dir.join(context.current_module_path.join("/"))
+    };
+    
+    // Try module_name.rs first
+    let rs_path = base_path.join(format!("{}.rs", module_name));
+    if rs_path.exists() {
+        return Some(rs_path);
+    }
+    
+    // Try module_name/mod.rs
+    let mod_path = base_path.join(module_name).join("mod.rs");
+    if mod_path.exists() {
+        return Some(mod_path);
+    }
+    
+    None
+}
+
+/// Canonical form of a path used to recognize files reached more than once
+fn canonical_path(path: &Path) -> PathBuf {
+    fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf())
+}
+
+/// Check if a file is a crate root (`lib.rs` or `main.rs`)
+fn is_crate_root(path: &Path) -> bool {
+    matches!(path.file_name().and_then(|s| s.to_str()), Some("lib.rs") | Some("main.rs"))
+}
+
+/// Directory holding the child modules declared in a file: crate roots and `mod.rs`
+/// own their directory, while `foo.rs` keeps its children in `foo/` when it exists
+fn module_dir_for(path: &Path) -> PathBuf {
+    let parent = path.parent().unwrap_or(Path::new(".")).to_path_buf();
+    match path.file_stem().and_then(|s| s.to_str()) {
+        Some("lib") | Some("main") | Some("mod") => parent,
+        Some(stem) if parent.join(stem).is_dir() => parent.join(stem),
+        _ => parent,
+    }
+}
+
+/// Evaluate the argument of an `include!` invocation to a path. Supports string
+/// literals and `concat!` of literals and `env!` lookups, e.g.
+/// `include!(concat!(env!("OUT_DIR"), "/state_gen.rs"))` when OUT_DIR is set.
+fn resolve_include_path(mac: &syn::Macro, context: &ParseContext) -> Option<PathBuf> {
+    fn eval(expr: &syn::Expr) -> Option<String> {
+        match expr {
+            syn::Expr::Lit(syn::ExprLit { lit: syn::Lit::Str(lit), .. }) => Some(lit.value()),
+            syn::Expr::Macro(expr_macro) if expr_macro.mac.path.is_ident("env") => {
+                let name: syn::LitStr = expr_macro.mac.parse_body().ok()?;
+                std::env::var(name.value()).ok()
+            }
+            syn::Expr::Macro(expr_macro) if expr_macro.mac.path.is_ident("concat") => {
+                let parts = expr_macro.mac
+                    .parse_body_with(syn::punctuated::Punctuated::<syn::Expr, syn::Token![,]>::parse_terminated)
+                    .ok()?;
+                parts.iter().map(eval).collect()
+            }
+            _ => None,
+        }
+    }
+
+    let path = PathBuf::from(eval(&mac.parse_body::<syn::Expr>().ok()?)?);
+    // Relative include paths are resolved against the including file, like rustc does
+    Some(context.current_file_dir.join(path))
+}
+
+/// Parse a file into the current module of the context, skipping files already analyzed
+fn process_module_file(path: &Path, context: &mut ParseContext) -> std::io::Result<()> {
+    if !context.visited_files.insert(canonical_path(path)) {
+        return Ok(());
+    }
+
+    context.options.progress(format_args!("Processing file: {:?}", path));
+    let content = fs::read_to_string(path)?;
+    context.options.progress(format_args!("File content length: {}", content.len()));
+    
+    match parse_file(&content) {
+        Ok(file) => {
+            let file_dir = path.parent().unwrap_or(Path::new(".")).to_path_buf();
+            let parent_file_dir = std::mem::replace(&mut context.current_file_dir, file_dir);
+            process_items(&file.items, context);
+            context.current_file_dir = parent_file_dir;
+        }
+        Err(e) => {
+            if !context.options.quiet {
+                eprintln!("Error parsing file {:?}: {}", path, e);
+            }
+            context.parse_failures.push(ParseFailure {
+                path: path.to_path_buf(),
+                error: e.to_string(),
+            });
+        }
+    }
+    Ok(())
+}
+
+/// Processes a crate root or standalone file, following its out-of-line modules
+fn process_file(path: &Path, options: &AnalysisOptions, visited_files: &mut HashSet<PathBuf>) -> std::io::Result<ParseContext> {
+    let mut context = ParseContext::with_root_dir(module_dir_for(path), options);
+    if visited_files.contains(&canonical_path(path)) {
+        return Ok(context);
+    }
+
+    context.visited_files = std::mem::take(visited_files);
+    let result = process_module_file(path, &mut context);
+    *visited_files = std::mem::take(&mut context.visited_files);
+    result?;
+
+    options.progress(format_args!("Found {} structs, {} type aliases, and {} imports in file", 
+             context.structs.len(), context.type_aliases.len(), context.imports.len()));
+    Ok(context)
+}
+
+/// Resolve type aliases to their final types, handling chains and multi-target aliases
+fn resolve_type_aliases(
+    field_types: &[String], 
+    type_aliases: &HashMap<String, String>,
+    struct_names: &HashSet<String>,
+    current_module_path: &[String]
+) -> Vec<String> {
+    field_types.iter().flat_map(|field_type| {
+        // Resolve alias chains
+        let mut resolved_types = resolve_alias_chain(field_type, type_aliases);
+        
+        // If no aliases were resolved, use the original type
+        if resolved_types.is_empty() {
+            resolved_types.push(field_type.clone());
+        }
+        
+        // For each resolved type, try to resolve relative module paths
+        // (`deep::Deepest` inside module `a` names `a::deep::Deepest`)
+        resolved_types.into_iter().map(|resolved_type| {
+            if !current_module_path.is_empty() {
+                let full_path = format!("{}::{}", current_module_path.join("::"), resolved_type);
+                if struct_names.contains(&full_path) {
+                    return full_path;
+                }
+            }
+            resolved_type
+        }).collect::<Vec<_>>()
+    }).collect()
+}
+
+/// Resolve a single type through alias chains, handling multi-target aliases
+fn resolve_alias_chain(type_name: &str, type_aliases: &HashMap<String, String>) -> Vec<String> {
+    let mut result = Vec::new();
+    let mut current = type_name.to_string();
+    let mut visited = HashSet::new();
+    
+    // Handle potential generic types like M<K, V>
+    if current.contains('<') {
+        // Extract the base type and generic arguments
+        if let Some(base_end) = current.find('<') {
+            let base_type = &current[..base_end];
+            let generics_part = &current[base_end..];
+            
+            // Try to resolve the base type
+            if let Some(target) = type_aliases.get(base_type) {
+                // If the target also has generics, we need to substitute
+                if target.contains('<') {
+                    result.push(current); // Keep original for now
+                } else {
+                    result.push(format!("{}{}", target, generics_part));
+                }
+            } else {
+                result.push(current);
+            }
+        } else {
+            result.push(current);
+        }
+    } else {
+        // Simple alias chain resolution
+        while let Some(target) = type_aliases.get(&current) {
+            if !visited.insert(current.clone()) {
+                // Circular alias, break
+                break;
+            }
+            current = target.clone();
+        }
+        result.push(current);
+    }
+    
+    result
+}
+
+/// Outcome of a struct depth analysis run
+pub struct AnalysisResult {
+    pub max_depth: usize,
+    /// The field types of each reported struct, resolved to the structs they name
+    pub struct_map: HashMap<String, Vec<String>>,
+    /// Maximum composition depth rooted at each struct
+    pub struct_depths: BTreeMap<String, usize>,
+    /// Maximum serialized depth overall and per struct, with --serialized-depth
+    pub serialized: Option<(usize, BTreeMap<String, usize>)>,
+    pub parse_failures: Vec<ParseFailure>,
+}
+
+/// Main function to analyze struct composition depth across one or more input paths
+pub fn analyze_struct_depth(source_paths: &[PathBuf], options: &AnalysisOptions) -> std::io::Result<AnalysisResult> {
+    let mut struct_map: HashMap<String, Vec<String>> = HashMap::new();
+    let mut type_alias_map: HashMap<String, String> = HashMap::new();
+    let mut max_global_depth = 0;
+
+    let mut files = Vec::new();
+    for source_path in source_paths {
+        files.extend(walk::rust_files(source_path, true, &mut |_| false)?);
+    }
+    // Crate roots and shallower files go first so module files are reached through
+    // their `mod` declarations and attributed to their real module path
+    files.sort_by_cached_key(|file| (!is_crate_root(file), file.components().count(), file.clone()));
+
+    // Process all files, merging every input into one context
+    let mut context = ParseContext::new(options);
+    let mut visited_files = HashSet::new();
+    for file in &files {
+        match process_file(file, options, &mut visited_files) {
+            Ok(mut file_context) => {
+                context.structs.append(&mut file_context.structs);
+                context.type_aliases.append(&mut file_context.type_aliases);
+                context.imports.append(&mut file_context.imports);
+                context.parse_failures.append(&mut file_context.parse_failures);
+            }
+            Err(e) => eprintln!("Error processing file {:?}: {}", file, e),
+        }
+    }
+    
+    // Build the type alias map
+    for type_alias in &context.type_aliases {
+        type_alias_map.insert(type_alias.name.clone(), type_alias.target_type.clone());
+    }
+    
+    // Collect all struct names for path resolution
+    let struct_names: HashSet<String> = context.structs.iter()
+        .map(|s| s.name.clone())
+        .collect();
+    
+    // Build the struct map with resolved types
+    let mut serialized_map: HashMap<String, Vec<(String, SerializedMode)>> = HashMap::new();
+    for struct_info in &context.structs {
+        if !options.with_derives.is_empty() && !struct_info.derives.iter().any(|derive| {
+            options.with_derives.iter().any(|wanted| wanted.rsplit("::").next() == Some(derive.as_str()))
+        }) {
+            continue;
+        }
+        let resolved_types = resolve_type_aliases(
+            &struct_info.field_types, 
+            &type_alias_map,
+            &struct_names,
+            &struct_info.module_path
+        );
+        struct_map.insert(struct_info.name.clone(), resolved_types);
+
+        if options.serialized_depth {
+            let serialized_fields = struct_info.field_types.iter()
+                .zip(&struct_info.field_modes)
+                .filter(|(_, mode)| **mode != SerializedMode::Skip)
+                .flat_map(|(field_type, mode)| {
+                    resolve_type_aliases(
+                        std::slice::from_ref(field_type),
+                        &type_alias_map,
+                        &struct_names,
+                        &struct_info.module_path,
+                    ).into_iter().map(move |resolved| (resolved, *mode))
+                })
+                .collect();
+            serialized_map.insert(struct_info.name.clone(), serialized_fields);
+        }
+    }
+
+    // Calculate maximum depth for each reported struct; with --public-only the
+    // chains still run through private structs but only start at public ones
+    let public_structs: HashSet<&str> = context.structs.iter()
+        .filter(|s| s.is_public)
+        .map(|s| s.name.as_str())
+        .collect();
+    let mut struct_depths = BTreeMap::new();
+    for struct_name in struct_map.keys() {
+        if options.public_only && !public_structs.contains(struct_name.as_str()) {
+            continue;
+        }
+        let mut visited = HashSet::new();
+        let depth = calculate_max_struct_depth(&struct_map, struct_name, &mut visited, 1);
+        max_global_depth = max_global_depth.max(depth);
+        struct_depths.insert(struct_name.clone(), depth);
+    }
+
+    let serialized = options.serialized_depth.then(|| {
+        let depths: BTreeMap<String, usize> = struct_depths.keys()
+            .map(|name| {
+                let mut visited = HashSet::new();
+                (name.clone(), calculate_serialized_depth(&serialized_map, name, &mut visited, 1))
+            })
+            .collect();
+        (depths.values().copied().max().unwrap_or(0), depths)
+    });
+
+    if options.public_only {
+        struct_map.retain(|name, _| struct_depths.contains_key(name));
+    }
+
+    Ok(AnalysisResult {
+        max_depth: max_global_depth,
+        struct_map,
+        struct_depths,
+        serialized,
+        parse_failures: context.parse_failures,
+    })
+}
+
+/// Differences in struct depth between two analysis runs
+#[derive(Serialize)]
+pub struct DepthDiff<'a> {
+    pub old_max_depth: usize,
+    pub new_max_depth: usize,
+    pub old_struct_count: usize,
+    pub new_struct_count: usize,
+    /// Structs only present in the new tree, with their depth
+    pub added: BTreeMap<&'a str, usize>,
+    /// Structs only present in the old tree, with their depth
+    pub removed: BTreeMap<&'a str, usize>,
+    /// Structs whose depth changed, as (old, new)
+    pub changed: BTreeMap<&'a str, (usize, usize)>,
+}
+
+impl<'a> DepthDiff<'a> {
+    pub fn new(old: &'a AnalysisResult, new: &'a AnalysisResult) -> Self {
+        let mut diff = DepthDiff {
+            old_max_depth: old.max_depth,
+            new_max_depth: new.max_depth,
+            old_struct_count: old.struct_map.len(),
+            new_struct_count: new.struct_map.len(),
+            added: BTreeMap::new(),
+            removed: BTreeMap::new(),
+            changed: BTreeMap::new(),
+        };
+        for (name, &depth) in &new.struct_depths {
+            match old.struct_depths.get(name) {
+                None => {
+                    diff.added.insert(name, depth);
+                }
+                Some(&old_depth) if old_depth != depth => {
+                    diff.changed.insert(name, (old_depth, depth));
+                }
+                Some(_) => {}
+            }
+        }
+        for (name, &depth) in &old.struct_depths {
+            if !new.struct_depths.contains_key(name) {
+                diff.removed.insert(name, depth);
+            }
+        }
+        diff
+    }
+}
@@ -1,778 +1,429 @@
-use std::collections::{HashMap, HashSet};
-use std::path::{Path, PathBuf};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
 use std::fs;
-use std::process::Command;
-use syn::{parse_file, Item, Fields, Type, GenericArgument, PathArguments, UseTree, ItemUse, ItemMod};
-use quote::quote;
-use tempfile::TempDir;
-use url::Url;
-
-/// Represents a struct's dependency information
-#[derive(Debug, Clone)]
-struct StructInfo {
-    name: String,
-    field_types: Vec<String>,
-    module_path: Vec<String>, // Track the module path for this struct
+use std::io::{self, Write};
+use clap::{Args, Parser, Subcommand};
+use mscd::{analyze_struct_depth, AnalysisOptions, AnalysisResult, DepthDiff, ParseFailure};
+use serde::Serialize;
+use serde_json::json;
+use trr_core::git::Repository;
+use trr_core::output::{self, markdown_code, write_csv_row, Format};
+
+/// Serializable form of the analysis results for `--format json`
+#[derive(Serialize)]
+struct JsonReport<'a> {
+    max_depth: usize,
+    struct_count: usize,
+    structs: BTreeMap<&'a str, &'a [String]>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    serialized_max_depth: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    serialized_depths: Option<&'a BTreeMap<String, usize>>,
+    skipped_files: &'a [ParseFailure],
 }
 
-/// Represents a type alias
-#[derive(Debug, Clone)]
-struct TypeAlias {
-    name: String,
-    target_type: String,
-    module_path: Vec<String>,
-}
-
-/// Represents an import/use statement
-#[derive(Debug, Clone)]
-struct ImportInfo {
-    /// The imported path (e.g., "a::b::Inner")
-    full_path: String,
-    /// The local name it's imported as (e.g., "Inner" or "Alias")
-    local_name: String,
-    /// The module where this import exists
-    module_path: Vec<String>,
-}
-
-/// Context for parsing with module information
-#[derive(Debug)]
-struct ParseContext {
-    current_module_path: Vec<String>,
-    structs: Vec<StructInfo>,
-    type_aliases: Vec<TypeAlias>,
-    imports: Vec<ImportInfo>,
-    /// Maps module names to their file paths for out-of-line modules
-    module_files: HashMap<String, PathBuf>,
-    /// Root directory for resolving relative paths
-    root_dir: PathBuf,
-}
-
-impl ParseContext {
-    fn new() -> Self {
-        Self {
-            current_module_path: Vec::new(),
-            structs: Vec::new(),
-            type_aliases: Vec::new(),
-            imports: Vec::new(),
-            module_files: HashMap::new(),
-            root_dir: PathBuf::new(),
-        }
-    }
-
-    fn with_root_dir(root_dir: PathBuf) -> Self {
-        Self {
-            current_module_path: Vec::new(),
-            structs: Vec::new(),
-            type_aliases: Vec::new(),
-            imports: Vec::new(),
-            module_files: HashMap::new(),
-            root_dir,
-        }
-    }
-
-    fn push_module(&mut self, module_name: String) {
-        self.current_module_path.push(module_name);
-    }
-    fn pop_module(&mut self) {
-        self.current_module_path.pop();
-    }
-
-}
-
-/// Calculates the maximum depth of nested struct compositions
-fn calculate_max_struct_depth(
-    struct_map: &HashMap<String, Vec<String>>,
-    struct_name: &str,
-    visited: &mut HashSet<String>,
-    curr_depth: usize,
-) -> usize {
-    // Base case: if we've seen this struct before, return current depth to avoid cycles
-    if !visited.insert(struct_name.to_string()) {
-        return curr_depth;
-    }
-
-    let mut max_depth = curr_depth;
-
-    // If the struct exists in our map, check its field types
-    if let Some(field_types) = struct_map.get(struct_name) {
-        for field_type in field_types {
-            // Only recurse if the field type is in our struct map
-            if struct_map.contains_key(field_type) {
-                let depth = calculate_max_struct_depth(
-                    struct_map,
-                    field_type,
-                    visited,
-                    curr_depth + 1,
-                );
-                max_depth = max_depth.max(depth);
+/// Write the full analysis report in the requested format
+fn write_report(
+    out: &mut dyn Write,
+    result: &AnalysisResult,
+    options: &AnalysisOptions,
+) -> io::Result<()> {
+    let depth = result.max_depth;
+    let struct_map = &result.struct_map;
+    match options.format {
+        Format::Json => {
+            let report = JsonReport {
+                max_depth: depth,
+                struct_count: struct_map.len(),
+                structs: struct_map.iter()
+                    .map(|(name, fields)| (name.as_str(), fields.as_slice()))
+                    .collect(),
+                serialized_max_depth: result.serialized.as_ref().map(|(depth, _)| *depth),
+                serialized_depths: result.serialized.as_ref().map(|(_, depths)| depths),
+                skipped_files: &result.parse_failures,
+            };
+            output::write_json(out, "mscd", &report)?;
+        }
+        Format::Csv => write_csv(out, result)?,
+        Format::Markdown => write_markdown(out, result)?,
+        Format::Sarif => write_sarif(out, result)?,
+        Format::Text if options.quiet => {
+            let serialized = match &result.serialized {
+                Some((serialized_depth, _)) => format!(", serialized: {}", serialized_depth),
+                None => String::new(),
+            };
+            writeln!(out, "Maximum struct composition depth: {}{} ({} structs, {} files skipped)",
+                     depth, serialized, struct_map.len(), result.parse_failures.len())?;
+        }
+        Format::Text => {
+            writeln!(out, "\nAnalysis Results:")?;
+            writeln!(out, "=================")?;
+            writeln!(out, "Maximum struct composition depth: {}", depth)?;
+            if let Some((serialized_depth, _)) = &result.serialized {
+                writeln!(out, "Maximum serialized depth: {} (skipped fields excluded, flattened fields collapsed)", serialized_depth)?;
             }
-        }
-    }
-
-    visited.remove(struct_name);
-    max_depth
-}
-
-/// Extracts all type dependencies from a syn::Type, handling wrappers and complex types
-fn extract_type_dependencies(ty: &Type, context: &ParseContext) -> Vec<String> {
-    let mut dependencies = Vec::new();
-    
-    match ty {
-        // Handle path types (most common case)
-        Type::Path(type_path) => {
-            dependencies.extend(extract_path_dependencies(&type_path.path, context));
-        }
-        // Handle references (&T)
-        Type::Reference(type_ref) => {
-            dependencies.extend(extract_type_dependencies(&type_ref.elem, context));
-        }
-        // Handle slices ([T])
-        Type::Slice(type_slice) => {
-            dependencies.extend(extract_type_dependencies(&type_slice.elem, context));
-        }
-        // Handle arrays ([T; N])
-        Type::Array(type_array) => {
-            dependencies.extend(extract_type_dependencies(&type_array.elem, context));
-        }
-        // Handle tuples - include ALL elements
-        Type::Tuple(type_tuple) => {
-            for elem in &type_tuple.elems {
-                dependencies.extend(extract_type_dependencies(elem, context));
+            writeln!(out, "\nStruct count: {}", struct_map.len())?;
+            writeln!(out, "Files skipped (parse errors): {}", result.parse_failures.len())?;
+            for failure in &result.parse_failures {
+                writeln!(out, "  - {}: {}", failure.path.display(), failure.error)?;
             }
-        }
-        // Handle raw pointers (*const T, *mut T)
-        Type::Ptr(type_ptr) => {
-            dependencies.extend(extract_type_dependencies(&type_ptr.elem, context));
-        }
-        // Handle function pointers and other types
-        _ => {
-            // For other types, convert to string and try to extract
-            let tokens = quote!(#ty);
-            let type_str = tokens.to_string().replace(' ', "");
-            if !type_str.is_empty() && !is_primitive_type(&type_str) {
-                dependencies.push(type_str);
+            
+            if depth > 0 {
+                writeln!(out, "\nStructs with their field types:")?;
+                writeln!(out, "============================")?;
+                for (struct_name, field_types) in struct_map {
+                    writeln!(out, "\n{}", struct_name)?;
+                    for field_type in field_types {
+                        writeln!(out, "  - {}", field_type)?;
+                    }
+                }
             }
         }
     }
-    
-    dependencies
+    Ok(())
 }
 
-/// Extract dependencies from a syn::Path, handling generics and module paths
-fn extract_path_dependencies(path: &syn::Path, context: &ParseContext) -> Vec<String> {
-    let mut dependencies = Vec::new();
-    
-    // Get the full path as a string
-    let path_str = path.segments.iter()
-        .map(|segment| segment.ident.to_string())
-        .collect::<Vec<_>>()
-        .join("::");
-    
-    // Handle Self keyword
-    let resolved_path = if path_str == "Self" {
-        // Replace Self with current struct name (we'll handle this in the calling context)
-        path_str
-    } else {
-        // Resolve the path through imports and relative paths
-        resolve_path(&path_str, context)
-    };
-    
-    // Add the main type if it's not primitive
-    if !is_primitive_type(&resolved_path) {
-        dependencies.push(resolved_path);
-    }
-    
-    // Extract generic arguments
-    for segment in &path.segments {
-        if let PathArguments::AngleBracketed(args) = &segment.arguments {
-            for arg in &args.args {
-                if let GenericArgument::Type(ty) = arg {
-                    dependencies.extend(extract_type_dependencies(ty, context));
-                }
-            }
-        }
+/// The analyzed structs, deepest first, with their depths
+fn structs_by_depth(result: &AnalysisResult) -> Vec<(&str, usize)> {
+    let mut structs: Vec<(&str, usize)> = result.struct_depths.iter()
+        .map(|(name, &depth)| (name.as_str(), depth))
+        .collect();
+    structs.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+    structs
+}
+
+/// `--format csv`: one row per struct with its depth, its serialized depth (empty
+/// without --serialized-depth) and its field types
+fn write_csv(out: &mut dyn Write, result: &AnalysisResult) -> io::Result<()> {
+    write_csv_row(out, &["struct", "depth", "serialized_depth", "fields"])?;
+    for (name, depth) in structs_by_depth(result) {
+        let serialized_depth = result.serialized.as_ref()
+            .and_then(|(_, depths)| depths.get(name))
+            .map(usize::to_string)
+            .unwrap_or_default();
+        let fields = result.struct_map.get(name).map(|fields| fields.join("; ")).unwrap_or_default();
+        write_csv_row(out, &[name, &depth.to_string(), &serialized_depth, &fields])?;
     }
-    
-    dependencies
+    Ok(())
 }
 
-/// Resolve a path string through imports, aliases, and relative paths
-fn resolve_path(path_str: &str, context: &ParseContext) -> String {
-    // Handle relative paths
-    let normalized_path = normalize_relative_path(path_str, &context.current_module_path);
-    
-    // Check if it's an import alias
-    if let Some(import) = context.imports.iter().find(|imp| imp.local_name == normalized_path) {
-        return import.full_path.clone();
+/// `--format markdown`: the summary and every struct, deepest first
+fn write_markdown(out: &mut dyn Write, result: &AnalysisResult) -> io::Result<()> {
+    writeln!(out, "# Struct Composition Depth\n")?;
+    writeln!(out, "| Metric | Value |")?;
+    writeln!(out, "|---|---:|")?;
+    writeln!(out, "| Maximum struct composition depth | {} |", result.max_depth)?;
+    if let Some((serialized_depth, _)) = &result.serialized {
+        writeln!(out, "| Maximum serialized depth | {} |", serialized_depth)?;
     }
-    
-    // Check if it's a simple unqualified name that might be imported
-    if !normalized_path.contains("::") {
-        // Look for imports that end with this name
-        if let Some(import) = context.imports.iter().find(|imp| {
-            imp.full_path.split("::").last() == Some(&normalized_path)
-        }) {
-            return import.full_path.clone();
+    writeln!(out, "| Structs | {} |", result.struct_depths.len())?;
+    writeln!(out, "| Files skipped (parse errors) | {} |", result.parse_failures.len())?;
+
+    let structs = structs_by_depth(result);
+    if !structs.is_empty() {
+        writeln!(out, "\n## Structs\n")?;
+        writeln!(out, "| Struct | Depth | Fields |")?;
+        writeln!(out, "|---|---:|---|")?;
+        for (name, depth) in structs {
+            let fields: Vec<String> = result.struct_map.get(name).into_iter().flatten()
+                .map(|field| markdown_code(field))
+                .collect();
+            writeln!(out, "| {} | {} | {} |", markdown_code(name), depth, fields.join(", "))?;
         }
     }
-    
-    normalized_path
-}
 
-/// Normalize relative paths (crate::, self::, super::)
-fn normalize_relative_path(path_str: &str, current_module: &[String]) -> String {
-    if path_str.starts_with("crate::") {
-        // crate:: means from the root
-        path_str.strip_prefix("crate::").unwrap().to_string()
-    } else if path_str.starts_with("self::") {
-        // self:: means current module
-        let relative = path_str.strip_prefix("self::").unwrap();
-        if current_module.is_empty() {
-            relative.to_string()
-        } else {
-            format!("{}::{}", current_module.join("::"), relative)
+    if !result.parse_failures.is_empty() {
+        writeln!(out, "\n## Skipped Files\n")?;
+        for failure in &result.parse_failures {
+            writeln!(out, "- `{}`: {}", failure.path.display(), failure.error)?;
         }
-    } else if path_str.starts_with("super::") {
-        // super:: means parent module
-        let relative = path_str.strip_prefix("super::").unwrap();
-        if current_module.len() <= 1 {
-            relative.to_string()
-        } else {
-            let parent_path = &current_module[..current_module.len() - 1];
-            format!("{}::{}", parent_path.join("::"), relative)
-        }
-    } else if current_module.is_empty() || path_str.contains("::") {
-        // Already absolute or we're at root
-        path_str.to_string()
-    } else {
-        // Relative to current module
-        format!("{}::{}", current_module.join("::"), path_str)
     }
+    Ok(())
 }
 
-/// Check if a type is a primitive type
-fn is_primitive_type(type_name: &str) -> bool {
-    matches!(type_name, 
-        "u8" | "u16" | "u32" | "u64" | "u128" | "usize" |
-        "i8" | "i16" | "i32" | "i64" | "i128" | "isize" |
-        "f32" | "f64" | "bool" | "char" | "str" | "()" |
-        "String" | "Vec" | "Option" | "Result" | "Box" | "Rc" | "Arc" |
-        "HashMap" | "HashSet" | "BTreeMap" | "BTreeSet"
-    )
+/// `--format sarif`: a `struct-depth` note for each struct at the maximum depth. Struct
+/// locations are not tracked, so the results carry the struct's path as a logical location.
+fn write_sarif(out: &mut dyn Write, result: &AnalysisResult) -> io::Result<()> {
+    let results: Vec<_> = structs_by_depth(result).into_iter()
+        .filter(|&(_, depth)| depth > 0 && depth == result.max_depth)
+        .map(|(name, depth)| json!({
+            "ruleId": "struct-depth",
+            "level": "note",
+            "message": {
+                "text": format!("{} has the maximum struct composition depth of {}", name, depth),
+            },
+            "logicalLocations": [{ "fullyQualifiedName": name, "kind": "type" }],
+            "properties": { "depth": depth },
+        }))
+        .collect();
+    let rule = json!({
+        "id": "struct-depth",
+        "name": "MaximumStructDepth",
+        "shortDescription": { "text": "Struct at the maximum composition depth of the analyzed code" },
+        "defaultConfiguration": { "level": "note" },
+    });
+    trr_core::sarif::write_log(&mut *out, "mscd", env!("CARGO_PKG_VERSION"), vec![rule], results)?;
+    writeln!(out)
 }
 
-/// Process items within a module or file, handling nested structures
-fn process_items(items: &[Item], context: &mut ParseContext) {
-    // First pass: collect imports and module declarations
-    for item in items {
-        match item {
-            Item::Use(item_use) => {
-                process_use_item(item_use, context);
-            }
-            Item::Mod(item_mod) => {
-                if item_mod.content.is_none() {
-                    // Out-of-line module (mod x;)
-                    let module_name = item_mod.ident.to_string();
-                    let module_path = resolve_module_file(&module_name, context);
-                    if let Some(path) = module_path {
-                        context.module_files.insert(module_name, path);
-                    }
-                }
-            }
-            _ => {}
+/// Write the comparison between two analysis runs in the requested format
+fn write_diff_report(out: &mut dyn Write, diff: &DepthDiff, options: &AnalysisOptions) -> io::Result<()> {
+    match options.format {
+        Format::Json => output::write_json(out, "mscd", diff)?,
+        Format::Text if options.quiet => {
+            writeln!(out, "Maximum struct composition depth: {} -> {} ({} added, {} removed, {} changed)",
+                     diff.old_max_depth, diff.new_max_depth,
+                     diff.added.len(), diff.removed.len(), diff.changed.len())?;
         }
-    }
-    
-    // Second pass: process structs and other items
-    for item in items {
-        match item {
-            Item::Struct(item_struct) => {
-                let struct_name = item_struct.ident.to_string();
-                println!("Found struct: {} in module: {:?}", struct_name, context.current_module_path);
-                let mut field_types = Vec::new();
-
-                match &item_struct.fields {
-                    // Named fields
-                    Fields::Named(fields) => {
-                        for field in &fields.named {
-                            let mut deps = extract_type_dependencies(&field.ty, context);
-                            // Handle Self references
-                            deps = deps.into_iter().map(|dep| {
-                                if dep == "Self" {
-                                    struct_name.clone()
-                                } else {
-                                    dep
-                                }
-                            }).collect();
-                            field_types.extend(deps);
-                        }
-                    }
-                    // Tuple structs (unnamed fields)
-                    Fields::Unnamed(fields) => {
-                        for field in &fields.unnamed {
-                            let mut deps = extract_type_dependencies(&field.ty, context);
-                            // Handle Self references
-                            deps = deps.into_iter().map(|dep| {
-                                if dep == "Self" {
-                                    struct_name.clone()
-                                } else {
-                                    dep
-                                }
-                            }).collect();
-                            field_types.extend(deps);
-                        }
-                    }
-                    // Unit structs (no fields)
-                    Fields::Unit => {}
+        _ => {
+            writeln!(out, "\nDiff Results:")?;
+            writeln!(out, "=============")?;
+            writeln!(out, "Maximum struct composition depth: {} -> {}", diff.old_max_depth, diff.new_max_depth)?;
+            writeln!(out, "Struct count: {} -> {}", diff.old_struct_count, diff.new_struct_count)?;
+
+            if !diff.added.is_empty() {
+                writeln!(out, "\nAdded structs:")?;
+                for (name, depth) in &diff.added {
+                    writeln!(out, "  + {} (depth {})", name, depth)?;
                 }
-
-                // Create full struct name with module path
-                let full_name = if context.current_module_path.is_empty() {
-                    struct_name.clone()
-                } else {
-                    format!("{}::{}", context.current_module_path.join("::"), struct_name)
-                };
-
-                context.structs.push(StructInfo {
-                    name: full_name,
-                    field_types,
-                    module_path: context.current_module_path.clone(),
-                });
             }
-            Item::Mod(item_mod) => {
-                if let Some((_, items)) = &item_mod.content {
-                    /// Process inline module
-                    let module_name = item_mod.ident.to_string();
-                    context.push_module(module_name);
-                    process_items(items, context);
-                    context.pop_module();
-                } else {       
-                    // Out-of-line module - process the file if we found it             
-                    let module_name = item_mod.ident.to_string();
-                    if let Some(module_file) = context.module_files.get(&module_name).cloned() {
-                        if let Ok(mut nested) = process_file(&module_file) {
-                            for s in &mut nested.structs {
-                                s.module_path = [context.current_module_path.clone(), vec![module_name.clone()]].concat();
-                                s.name = if s.module_path.is_empty() {
-                                    s.name.clone()
-                                } else {
-                                    let base = s.name.split("::").last().unwrap_or(&s.name).to_string();
-                                    format!("{}::{}", s.module_path.join("::"), base)
-                                };
-                            }
-                            context.structs.append(&mut nested.structs);
-                            context.type_aliases.append(&mut nested.type_aliases);
-                            context.imports.append(&mut nested.imports);
-                        }
-                    }
+            if !diff.removed.is_empty() {
+                writeln!(out, "\nRemoved structs:")?;
+                for (name, depth) in &diff.removed {
+                    writeln!(out, "  - {} (depth {})", name, depth)?;
                 }
             }
-            Item::Type(item_type) => {
-                // Handle type aliases
-                let alias_name = item_type.ident.to_string();
-                let target_deps = extract_type_dependencies(&item_type.ty, context);
-                
-                if let Some(target_type) = target_deps.first() {
-                    let full_alias_name = if context.current_module_path.is_empty() {
-                        alias_name.clone()
-                    } else {
-                        format!("{}::{}", context.current_module_path.join("::"), alias_name)
-                    };
-                    
-                    context.type_aliases.push(TypeAlias {
-                        name: full_alias_name,
-                        target_type: target_type.clone(),
-                        module_path: context.current_module_path.clone(),
-                    });
+            if !diff.changed.is_empty() {
+                writeln!(out, "\nDepth changes:")?;
+                for (name, (old_depth, new_depth)) in &diff.changed {
+                    writeln!(out, "  ~ {}: {} -> {}", name, old_depth, new_depth)?;
                 }
             }
-            _ => {}
         }
     }
+    Ok(())
 }
 
-/// Process a use statement to extract import information
-fn process_use_item(item_use: &ItemUse, context: &mut ParseContext) {
-    process_use_tree(&item_use.tree, Vec::new(), context);
+#[derive(Parser, Debug)]
+#[clap(author, version, about = "Maximum Struct Composition Depth (MSCD) Analyzer")]
+#[clap(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true, arg_required_else_help = true)]
+#[clap(after_help = "Running without a subcommand is the same as `analyze`.\n\nExamples:
+  mscd ./src
+  mscd --repo https://github.com/user/repo.git src/
+  mscd --repo /local/path/to/repo ./sample/src
+  mscd --quiet --format json -o report.json ./src
+  git diff --name-only | mscd --files-from -
+  mscd diff ../old-checkout/src ./src")]
+struct Cli {
+    #[clap(subcommand)]
+    command: Option<CliCommand>,
+
+    #[clap(flatten)]
+    analyze: AnalyzeArgs,
 }
 
-/// Recursively process use tree to extract all imports
-fn process_use_tree(tree: &UseTree, prefix: Vec<String>, context: &mut ParseContext) {
-    match tree {
-        UseTree::Path(use_path) => {
-            let mut new_prefix = prefix;
-            new_prefix.push(use_path.ident.to_string());
-            process_use_tree(&use_path.tree, new_prefix, context);
-        }
-        UseTree::Name(use_name) => {
-            let mut full_path = prefix;
-            full_path.push(use_name.ident.to_string());
-            let full_path_str = full_path.join("::");
-            let local_name = use_name.ident.to_string();
-            
-            context.imports.push(ImportInfo {
-                full_path: full_path_str,
-                local_name,
-                module_path: context.current_module_path.clone(),
-            });
-        }
-        UseTree::Rename(use_rename) => {
-            let mut full_path = prefix;
-            full_path.push(use_rename.ident.to_string());
-            let full_path_str = full_path.join("::");
-            let local_name = use_rename.rename.to_string();
-            
-            context.imports.push(ImportInfo {
-                full_path: full_path_str,
-                local_name,
-                module_path: context.current_module_path.clone(),
-            });
-        }
-        UseTree::Glob(_) => {
-            // For glob imports, we'd need more sophisticated handling
-            // For now, we'll skip them as they're complex to resolve
-        }
-        UseTree::Group(use_group) => {
-            for tree in &use_group.items {
-                process_use_tree(tree, prefix.clone(), context);
-            }
-        }
-    }
+#[derive(Subcommand, Debug)]
+enum CliCommand {
+    /// Analyze struct composition depth
+    Analyze(AnalyzeArgs),
+    /// Compare struct composition depth between two trees
+    Diff(DiffArgs),
 }
 
-/// Resolve the file path for an out-of-line module
-fn resolve_module_file(module_name: &str, context: &ParseContext) -> Option<PathBuf> {
-    let base_path = if context.current_module_path.is_empty() {
-        context.root_dir.clone()
-    } else {
-        context.root_dir.join(context.current_module_path.join("/"))
-    };
-    
-    // Try module_name.rs first
-    let rs_path = base_path.join(format!("{}.rs", module_name));
-    if rs_path.exists() {
-        return Some(rs_path);
-    }
-    
-    // Try module_name/mod.rs
-    let mod_path = base_path.join(module_name).join("mod.rs");
-    if mod_path.exists() {
-        return Some(mod_path);
-    }
-    
-    None
+#[derive(Args, Debug)]
+struct OutputArgs {
+    /// Write the report to a file instead of stdout
+    #[clap(short, long, value_name = "PATH")]
+    output: Option<PathBuf>,
+
+    /// Only print a single summary line in text (other formats print just the document)
+    #[clap(short, long)]
+    quiet: bool,
+
+    /// Report format
+    #[clap(long, value_enum, default_value = "text")]
+    format: Format,
+
+    /// Exit non-zero if any file fails to parse
+    #[clap(long)]
+    strict: bool,
 }
 
-/// Processes a single file and extracts struct information
-fn process_file(path: &Path) -> std::io::Result<ParseContext> {
-    println!("Processing file: {:?}", path);
-    let content = fs::read_to_string(path)?;
-    println!("File content length: {}", content.len());
-    
-    match parse_file(&content) {
-        Ok(file) => {
-            let root_dir = path.parent().unwrap_or(Path::new(".")).to_path_buf();
-            let mut context = ParseContext::with_root_dir(root_dir);
-            process_items(&file.items, &mut context);
-            
-            println!("Found {} structs, {} type aliases, and {} imports in file", 
-                     context.structs.len(), context.type_aliases.len(), context.imports.len());
-            Ok(context)
-        }
-        Err(e) => {
-            eprintln!("Error parsing file {:?}: {}", path, e);
-            Ok(ParseContext::new())
+#[derive(Args, Debug)]
+struct FilterArgs {
+    /// Only analyze structs deriving this trait (repeatable; any match counts)
+    #[clap(long = "with-derive", value_name = "TRAIT", multiple_occurrences = true)]
+    with_derives: Vec<String>,
+
+    /// Only report `pub` structs (private structs still count as links in their chains)
+    #[clap(long)]
+    public_only: bool,
+
+    /// Also report the serialized depth, excluding #[serde(skip)]/#[borsh(skip)] fields and collapsing #[serde(flatten)]
+    #[clap(long)]
+    serialized_depth: bool,
+}
+
+impl OutputArgs {
+    fn options(&self, filter: &FilterArgs) -> AnalysisOptions {
+        AnalysisOptions {
+            quiet: self.quiet,
+            format: self.format,
+            with_derives: filter.with_derives.clone(),
+            public_only: filter.public_only,
+            serialized_depth: filter.serialized_depth,
         }
     }
+
+    /// Open the report destination: the --output file or stdout
+    fn writer(&self) -> io::Result<Box<dyn Write>> {
+        Ok(match &self.output {
+            Some(path) => Box::new(fs::File::create(path)?),
+            None => Box::new(io::stdout()),
+        })
+    }
 }
 
-/// Recursively process directories and files
-fn process_directory(path: &Path) -> std::io::Result<ParseContext> {
-    let root_dir = if path.is_file() {
-        path.parent().unwrap_or(Path::new(".")).to_path_buf()
-    } else {
-        path.to_path_buf()
-    };
-    
-    let mut combined_context = ParseContext::with_root_dir(root_dir);
-
-    if path.is_file() {
-        if path.extension().and_then(|s| s.to_str()) == Some("rs") {
-            match process_file(path) {
-                Ok(mut file_context) => {
-                    combined_context.structs.append(&mut file_context.structs);
-                    combined_context.type_aliases.append(&mut file_context.type_aliases);
-                    combined_context.imports.append(&mut file_context.imports);
-                }
-                Err(e) => eprintln!("Error processing file {:?}: {}", path, e),
-            }
-        }
-    } else if path.is_dir() {
-        for entry in fs::read_dir(path)? {
-            let entry = entry?;
-            let entry_path = entry.path();
+#[derive(Args, Debug)]
+struct AnalyzeArgs {
+    /// Directories or files to analyze (relative to --repo when given)
+    #[clap(value_name = "PATH", required_unless_present = "files-from")]
+    paths: Vec<String>,
 
-            let mut sub_context = process_directory(&entry_path)?;
-            combined_context.structs.append(&mut sub_context.structs);
-            combined_context.type_aliases.append(&mut sub_context.type_aliases);
-            combined_context.imports.append(&mut sub_context.imports);
+    /// Git repository URL or local path to analyze
+    #[clap(long, value_name = "REPO_URL_OR_PATH")]
+    repo: Option<String>,
 
-        }
-    }
+    /// Branch, tag or commit of --repo to analyze
+    #[clap(long, value_name = "REV", requires = "repo")]
+    rev: Option<String>,
+
+    /// Read a newline-separated list of files to analyze ('-' for stdin)
+    #[clap(long, value_name = "FILE|-")]
+    files_from: Option<String>,
+
+    #[clap(flatten)]
+    filter: FilterArgs,
 
-    Ok(combined_context)
+    #[clap(flatten)]
+    output: OutputArgs,
 }
 
-/// Resolve type aliases to their final types, handling chains and multi-target aliases
-fn resolve_type_aliases(
-    field_types: &[String], 
-    type_aliases: &HashMap<String, String>,
-    struct_names: &HashSet<String>,
-    current_module_path: &[String]
-) -> Vec<String> {
-    field_types.iter().flat_map(|field_type| {
-        // Resolve alias chains
-        let mut resolved_types = resolve_alias_chain(field_type, type_aliases);
-        
-        // If no aliases were resolved, use the original type
-        if resolved_types.is_empty() {
-            resolved_types.push(field_type.clone());
-        }
-        
-        // For each resolved type, try to resolve relative module paths
-        resolved_types.into_iter().map(|resolved_type| {
-            if !resolved_type.contains("::") && !current_module_path.is_empty() {
-                let full_path = format!("{}::{}", current_module_path.join("::"), resolved_type);
-                if struct_names.contains(&full_path) {
-                    return full_path;
-                }
-            }
-            resolved_type
-        }).collect::<Vec<_>>()
-    }).collect()
+#[derive(Args, Debug)]
+struct DiffArgs {
+    /// Baseline directory or file
+    old: PathBuf,
+
+    /// Directory or file compared against the baseline
+    new: PathBuf,
+
+    #[clap(flatten)]
+    filter: FilterArgs,
+
+    #[clap(flatten)]
+    output: OutputArgs,
 }
 
-/// Resolve a single type through alias chains, handling multi-target aliases
-fn resolve_alias_chain(type_name: &str, type_aliases: &HashMap<String, String>) -> Vec<String> {
-    let mut result = Vec::new();
-    let mut current = type_name.to_string();
-    let mut visited = HashSet::new();
-    
-    // Handle potential generic types like M<K, V>
-    if current.contains('<') {
-        // Extract the base type and generic arguments
-        if let Some(base_end) = current.find('<') {
-            let base_type = &current[..base_end];
-            let generics_part = &current[base_end..];
-            
-            // Try to resolve the base type
-            if let Some(target) = type_aliases.get(base_type) {
-                // If the target also has generics, we need to substitute
-                if target.contains('<') {
-                    result.push(current); // Keep original for now
-                } else {
-                    result.push(format!("{}{}", target, generics_part));
-                }
-            } else {
-                result.push(current);
-            }
-        } else {
-            result.push(current);
-        }
-    } else {
-        // Simple alias chain resolution
-        while let Some(target) = type_aliases.get(&current) {
-            if !visited.insert(current.clone()) {
-                // Circular alias, break
-                break;
-            }
-            current = target.clone();
+type CliResult<T> = Result<T, Box<dyn std::error::Error>>;
+
+/// Resolve the analyze arguments into the list of paths to process. The returned
+/// repository keeps a clone alive for the duration of the run.
+fn resolve_inputs(args: &AnalyzeArgs, options: &AnalysisOptions) -> CliResult<(Vec<PathBuf>, Option<Repository>)> {
+    let repository = args.repo.as_deref().map(|repo| Repository::open(repo, args.rev.as_deref())).transpose()?;
+    let base_dir = match &repository {
+        Some(repository) => {
+            options.progress(format_args!("Repository: {}", repository.root().display()));
+            repository.root().to_path_buf()
         }
-        result.push(current);
-    }
-    
-    result
-}
+        None => PathBuf::new(),
+    };
 
-/// Main function to analyze struct composition depth
-fn analyze_struct_depth(source_path: &Path) -> std::io::Result<(usize, HashMap<String, Vec<String>>)> {
-    let mut struct_map: HashMap<String, Vec<String>> = HashMap::new();
-    let mut type_alias_map: HashMap<String, String> = HashMap::new();
-    let mut max_global_depth = 0;
-
-    // Process all files recursively
-    let context = process_directory(source_path)?;
-    
-    // Build the type alias map
-    for type_alias in &context.type_aliases {
-        type_alias_map.insert(type_alias.name.clone(), type_alias.target_type.clone());
-    }
-    
-    // Collect all struct names for path resolution
-    let struct_names: HashSet<String> = context.structs.iter()
-        .map(|s| s.name.clone())
-        .collect();
-    
-    // Build the struct map with resolved types
-    for struct_info in &context.structs {
-        let resolved_types = resolve_type_aliases(
-            &struct_info.field_types, 
-            &type_alias_map,
-            &struct_names,
-            &struct_info.module_path
-        );
-        struct_map.insert(struct_info.name.clone(), resolved_types);
+    let mut source_paths = Vec::new();
+    for input in &args.paths {
+        let path = base_dir.join(input);
+        if !path.exists() {
+            return Err(format!("Path '{}' does not exist", path.display()).into());
+        }
+        options.progress(format_args!("Analyzing: {}", input));
+        source_paths.push(path);
     }
 
-    // Calculate maximum depth for each struct
-    for struct_name in struct_map.keys() {
-        let mut visited = HashSet::new();
-        let depth = calculate_max_struct_depth(&struct_map, struct_name, &mut visited, 1);
-        max_global_depth = max_global_depth.max(depth);
+    // Files listed on stdin (or in a list file) are taken as-is; non-Rust entries are ignored
+    // and ones that no longer exist, such as files deleted in a diff, are skipped
+    if let Some(list_source) = &args.files_from {
+        let listing = if list_source == "-" {
+            io::read_to_string(io::stdin())?
+        } else {
+            fs::read_to_string(list_source)?
+        };
+        for line in listing.lines().map(str::trim).filter(|line| !line.is_empty()) {
+            let path = base_dir.join(line);
+            if path.exists() {
+                source_paths.push(path);
+            } else {
+                eprintln!("Skipping '{}': not found", path.display());
+            }
+        }
     }
 
-    Ok((max_global_depth, struct_map))
+    Ok((source_paths, repository))
 }
 
-/// Clone a Git repository to a temporary directory using system git command
-fn clone_repository(repo_url: &str) -> Result<TempDir, Box<dyn std::error::Error>> {
-    println!("Cloning repository: {}", repo_url);
-    
-    let temp_dir = TempDir::new()?;
-    let repo_path = temp_dir.path();
-    
-    let output = Command::new("git")
-        .args(&["clone", repo_url, repo_path.to_str().unwrap()])
-        .output()?;
-    
-    if !output.status.success() {
-        let error_msg = String::from_utf8_lossy(&output.stderr);
-        return Err(format!("Git clone failed: {}", error_msg).into());
+/// Fail the run in --strict mode when any file could not be parsed
+fn check_strict(output: &OutputArgs, result: &AnalysisResult) -> CliResult<()> {
+    if !output.strict || result.parse_failures.is_empty() {
+        return Ok(());
     }
-    
-    println!("Repository cloned to temporary directory");
-    Ok(temp_dir)
+    let listing: Vec<String> = result.parse_failures.iter()
+        .map(|failure| format!("  {}: {}", failure.path.display(), failure.error))
+        .collect();
+    Err(format!("{} file(s) could not be parsed:\n{}", result.parse_failures.len(), listing.join("\n")).into())
 }
 
-/// Check if a string is a valid URL
-fn is_url(s: &str) -> bool {
-    Url::parse(s).is_ok()
-}
+fn run_analyze(args: &AnalyzeArgs) -> CliResult<()> {
+    let options = args.output.options(&args.filter);
+    let (source_paths, _repository) = resolve_inputs(args, &options)?;
+    let result = analyze_struct_depth(&source_paths, &options)?;
+    check_strict(&args.output, &result)?;
 
-fn print_help() {
-    println!("Maximum Struct Composition Depth (MSCD) Analyzer");
-    println!("\nUsage:");
-    println!("  ./mscd-analyzer <directory>");
-    println!("  ./mscd-analyzer --repo <repo_url_or_path> <relative_directory>");
-    println!("\nOptions:");
-    println!("  -h, --help                    Show this help message");
-    println!("  --repo <repo_url_or_path>     Specify Git repository URL or local path");
-    println!("\nExamples:");
-    println!("  ./mscd-analyzer ./src");
-    println!("  ./mscd-analyzer /path/to/rust/files");
-    println!("  ./mscd-analyzer --repo https://github.com/user/repo.git src/");
-    println!("  ./mscd-analyzer --repo git@github.com:user/repo.git ./lib");
-    println!("  ./mscd-analyzer --repo /local/path/to/repo ./sample/src");
+    write_report(&mut args.output.writer()?, &result, &options)?;
+    if let Some(path) = &args.output.output {
+        options.progress(format_args!("\nReport written to {}", path.display()));
+    }
+    Ok(())
 }
 
-fn main() -> std::io::Result<()> {
-    let args: Vec<String> = std::env::args().collect();
-    
-    // Handle help
-    if args.len() < 2 || args.contains(&"-h".to_string()) || args.contains(&"--help".to_string()) {
-        print_help();
-        return Ok(());
+fn run_diff(args: &DiffArgs) -> CliResult<()> {
+    let options = args.output.options(&args.filter);
+    if !matches!(options.format, Format::Text | Format::Json) {
+        return Err(format!("mscd diff prints text or json, not {}", options.format).into());
     }
-
-    let (source_path, _temp_dir) = if args.len() >= 4 && args[1] == "--repo" {
-        // Handle --repo flag: --repo <repo_url_or_path> <relative_path>
-        let repo_input = &args[2];
-        let relative_path = &args[3];
-        
-        if is_url(repo_input) || repo_input.starts_with("git@") {
-            // Handle Git URL
-            match clone_repository(repo_input) {
-                Ok(temp_dir) => {
-                    let repo_path = temp_dir.path();
-                    let full_path = repo_path.join(relative_path);
-                    
-                    if !full_path.exists() {
-                        eprintln!("Error: Path '{}' does not exist in cloned repository", relative_path);
-                        return Ok(());
-                    }
-                    
-                    println!("Analyzing: {}", relative_path);
-                    (full_path, Some(temp_dir))
-                }
-                Err(e) => {
-                    eprintln!("Error cloning repository '{}': {}", repo_input, e);
-                    return Ok(());
-                }
-            }
-        } else {
-            // Handle local path
-            let repo_path = PathBuf::from(repo_input);
-            
-            if !repo_path.exists() {
-                eprintln!("Error: Repository path '{}' does not exist", repo_path.display());
-                return Ok(());
-            }
-            
-            if !repo_path.is_dir() {
-                eprintln!("Error: Repository path '{}' is not a directory", repo_path.display());
-                return Ok(());
-            }
-            
-            let full_path = repo_path.join(relative_path);
-            
-            if !full_path.exists() {
-                eprintln!("Error: Path '{}' does not exist in repository '{}'", 
-                         relative_path, repo_path.display());
-                return Ok(());
-            }
-            
-            println!("Repository: {}", repo_path.display());
-            println!("Analyzing: {}", relative_path);
-            (full_path, None)
-        }
-    } else if args.len() >= 2 {
-        // Handle direct path
-        let path = PathBuf::from(&args[1]);
-        
+    for path in [&args.old, &args.new] {
         if !path.exists() {
-            eprintln!("Error: Directory '{}' does not exist", path.display());
-            return Ok(());
+            return Err(format!("Path '{}' does not exist", path.display()).into());
         }
-        
-        (path, None)
-    } else {
-        print_help();
-        return Ok(());
-    };
+    }
+    let old = analyze_struct_depth(std::slice::from_ref(&args.old), &options)?;
+    check_strict(&args.output, &old)?;
+    let new = analyze_struct_depth(std::slice::from_ref(&args.new), &options)?;
+    check_strict(&args.output, &new)?;
+
+    write_diff_report(&mut args.output.writer()?, &DepthDiff::new(&old, &new), &options)?;
+    if let Some(path) = &args.output.output {
+        options.progress(format_args!("\nReport written to {}", path.display()));
+    }
+    Ok(())
+}
 
-    match analyze_struct_depth(&source_path) {
-        Ok((depth, struct_map)) => {
-            println!("\nAnalysis Results:");
-            println!("=================");
-            println!("Maximum struct composition depth: {}", depth);
-            println!("\nStruct count: {}", struct_map.len());
-            
-            if depth > 0 {
-                println!("\nStructs with their field types:");
-                println!("============================");
-                for (struct_name, field_types) in struct_map {
-                    println!("\n{}", struct_name);
-                    for field_type in field_types {
-                        println!("  - {}", field_type);
-                    }
-                }
-            }
-            
-            Ok(())
-        }
-        Err(e) => {
-            eprintln!("Error analyzing struct depth: {}", e);
-            Err(e)
-        }
+fn main() {
+    let cli = Cli::parse();
+    let result = match &cli.command {
+        Some(CliCommand::Analyze(args)) => run_analyze(args),
+        Some(CliCommand::Diff(args)) => run_diff(args),
+        None => run_analyze(&cli.analyze),
+    };
+    if let Err(e) = result {
+        eprintln!("Error: {}", e);
+        std::process::exit(1);
     }
 }
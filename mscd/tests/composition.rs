@@ -0,0 +1,114 @@
+// Runs the mscd library over the fixtures in tests/fixtures: a crate whose structs
+// reach each other through out-of-line modules, an include!, imports and a type alias,
+// with one file that does not parse, and a crate whose serialized form skips and
+// flattens fields.
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use mscd::{analyze_struct_depth, AnalysisOptions, AnalysisResult, DepthDiff};
+use trr_core::output::Format;
+
+fn fixture(name: &str) -> PathBuf {
+    Path::new(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures")).join(name)
+}
+
+fn options() -> AnalysisOptions {
+    AnalysisOptions { quiet: true, format: Format::Json, with_derives: Vec::new(), public_only: false, serialized_depth: false }
+}
+
+fn analyze(name: &str, options: &AnalysisOptions) -> AnalysisResult {
+    analyze_struct_depth(&[fixture(name)], options).unwrap()
+}
+
+fn depths(result: &AnalysisResult) -> Vec<(&str, usize)> {
+    result.struct_depths.iter().map(|(name, &depth)| (name.as_str(), depth)).collect()
+}
+
+#[test]
+fn modules_resolve_to_their_paths() {
+    let result = analyze("modules", &options());
+    // Every file is counted once under its module path, though the walk reaches
+    // state.rs and nested.rs both through `mod` and on their own
+    assert_eq!(depths(&result), [
+        ("Aliased", 3),
+        ("Generated", 4),
+        ("Root", 4),
+        ("state::Config", 2),
+        ("state::Vault", 3),
+        ("state::nested::Leaf", 1),
+        ("state::nested::Private", 2),
+    ]);
+    assert_eq!(result.max_depth, 4);
+    // `Main` is `state::Vault` imported under another name, and `Alias` is a type alias
+    assert_eq!(result.struct_map["Root"], ["state::Vault", "state::nested::Leaf"]);
+    assert_eq!(result.struct_map["Aliased"], ["state::Config"]);
+    assert_eq!(result.struct_map["state::Config"], ["state::nested::Leaf"]);
+
+    let failures: Vec<_> = result.parse_failures.iter().map(|failure| failure.path.file_name().unwrap()).collect();
+    assert_eq!(failures, ["broken.rs"]);
+}
+
+#[test]
+fn included_files_join_the_including_module() {
+    let result = analyze("modules", &options());
+    assert_eq!(result.struct_map["Generated"], ["state::Vault"]);
+    assert!(!result.struct_map.keys().any(|name| name.ends_with("generated::Generated")));
+}
+
+#[test]
+fn derive_and_public_filters() {
+    let derives = AnalysisOptions { with_derives: vec!["core::clone::Clone".to_string()], ..options() };
+    let result = analyze("modules", &derives);
+    // Chains only run through the structs that derive the trait
+    assert_eq!(depths(&result), [("Generated", 2), ("state::Vault", 1)]);
+
+    let public = AnalysisOptions { public_only: true, ..options() };
+    let result = analyze("modules", &public);
+    assert!(!result.struct_depths.contains_key("state::nested::Private"));
+    assert!(!result.struct_map.contains_key("state::nested::Private"));
+    assert_eq!(result.struct_depths.len(), 6);
+    assert_eq!(result.max_depth, 4);
+}
+
+#[test]
+fn serialized_depth_skips_and_flattens() {
+    let result = analyze("serialized", &AnalysisOptions { serialized_depth: true, ..options() });
+    assert_eq!(depths(&result), [("Cache", 3), ("Inner", 2), ("Leaf", 1), ("Outer", 4)]);
+    let (max, serialized) = result.serialized.unwrap();
+    assert_eq!(max, 3);
+    // Outer's only serialized field is flattened, so it is as deep as Inner
+    let expected: BTreeMap<String, usize> = [("Cache", 3), ("Inner", 2), ("Leaf", 1), ("Outer", 2)].into_iter()
+        .map(|(name, depth)| (name.to_string(), depth))
+        .collect();
+    assert_eq!(serialized, expected);
+
+    assert!(analyze("serialized", &options()).serialized.is_none());
+}
+
+#[test]
+fn diff_between_runs() {
+    let old = analyze("modules", &options());
+    let new = analyze("modules", &AnalysisOptions { with_derives: vec!["Clone".to_string()], ..options() });
+    let diff = DepthDiff::new(&old, &new);
+    assert_eq!((diff.old_max_depth, diff.new_max_depth), (4, 2));
+    assert!(diff.added.is_empty());
+    assert_eq!(diff.removed.keys().copied().collect::<Vec<_>>(), ["Aliased", "Root", "state::Config", "state::nested::Leaf", "state::nested::Private"]);
+    assert_eq!(diff.changed, BTreeMap::from([("Generated", (4, 2)), ("state::Vault", (3, 1))]));
+}
+
+#[test]
+fn strict_fails_on_parse_errors() {
+    let mscd = |args: &[&str]| Command::new(env!("CARGO_BIN_EXE_mscd")).args(args).arg(fixture("modules")).output().unwrap();
+    let output = mscd(&["--quiet", "--format", "json"]);
+    assert!(output.status.success());
+    let report: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(report["max_depth"], 4);
+    assert_eq!(report["struct_count"], 7);
+    assert_eq!(report["skipped_files"].as_array().unwrap().len(), 1);
+
+    let output = mscd(&["--quiet", "--strict"]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("1 file(s) could not be parsed"));
+}
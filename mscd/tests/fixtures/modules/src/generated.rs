@@ -0,0 +1,4 @@
+#[derive(Clone)]
+pub struct Generated {
+    vault: state::Vault,
+}
@@ -0,0 +1,14 @@
+mod state;
+
+include!("generated.rs");
+
+use state::Vault as Main;
+
+pub struct Root {
+    main: Main,
+    leaves: Vec<state::nested::Leaf>,
+}
+
+type Alias = state::Config;
+
+pub struct Aliased(Alias);
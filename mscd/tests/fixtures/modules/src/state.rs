@@ -0,0 +1,15 @@
+mod nested;
+
+use self::nested::Leaf;
+
+#[derive(Clone, Debug)]
+pub struct Vault {
+    config: Config,
+    leaf: Leaf,
+}
+
+#[derive(Debug)]
+pub struct Config {
+    fee: u64,
+    leaf: nested::Leaf,
+}
@@ -0,0 +1,7 @@
+pub struct Leaf {
+    value: u64,
+}
+
+struct Private {
+    leaf: Leaf,
+}
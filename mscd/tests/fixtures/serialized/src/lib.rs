@@ -0,0 +1,25 @@
+use serde::Serialize;
+
+#[derive(Serialize)]
+pub struct Outer {
+    #[serde(flatten)]
+    inner: Inner,
+    #[serde(skip)]
+    cache: Cache,
+    #[borsh(skip)]
+    scratch: Option<Cache>,
+}
+
+#[derive(Serialize)]
+pub struct Inner {
+    leaf: Leaf,
+}
+
+#[derive(Serialize)]
+pub struct Leaf {
+    value: u64,
+}
+
+pub struct Cache {
+    inner: Inner,
+}
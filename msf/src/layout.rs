@@ -0,0 +1,313 @@
+//! Sizes of types as the SBF target lays them out: 64-bit pointers and `usize`, `u128`
+//! aligned to 16. Structs, enums, aliases and integer constants are read from the
+//! analyzed tree and found by name. The sizes are estimates:
+//!
+//! - structs are packed the way rustc reorders fields, with no padding but at the end;
+//!   `#[repr(C)]` and zero-copy accounts keep their order, `#[repr(packed)]` has none
+//! - an enum is its largest variant plus a tag, with no niche optimization but for
+//!   `Option` of pointers, `Vec` and `String`
+//! - Anchor's `Account` and `InterfaceAccount` hold the deserialized account next to a
+//!   reference to its `AccountInfo`; `AccountLoader`, `Signer`, `Program` and the other
+//!   wrappers are references
+//!
+//! Generic parameters, trait objects and types from outside the tree have no size.
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use syn::visit::{self, Visit};
+use syn::{Attribute, BinOp, Expr, Fields, GenericArgument, ItemConst, ItemEnum, ItemStruct, ItemType, Lit, PathArguments, Type};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Layout {
+    pub size: u64,
+    pub align: u64,
+}
+
+impl Layout {
+    const fn new(size: u64, align: u64) -> Self {
+        Layout { size, align }
+    }
+
+    const POINTER: Layout = Layout::new(8, 8);
+    /// A slice or `str` reference, or a trait object
+    const FAT_POINTER: Layout = Layout::new(16, 8);
+    /// `Vec`, `String`: pointer, capacity, length
+    const VEC: Layout = Layout::new(24, 8);
+}
+
+fn round_up(size: u64, align: u64) -> u64 {
+    size.div_ceil(align.max(1)) * align.max(1)
+}
+
+/// How a struct's fields are laid out
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Repr {
+    Rust,
+    C,
+    Packed,
+}
+
+fn repr(attrs: &[Attribute]) -> Repr {
+    let mut repr = Repr::Rust;
+    for attr in attrs {
+        if attr.path().is_ident("repr") {
+            let _ = attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("packed") {
+                    repr = Repr::Packed;
+                } else if meta.path.is_ident("C") && repr == Repr::Rust {
+                    repr = Repr::C;
+                }
+                Ok(())
+            });
+        } else if attr.path().is_ident("zero_copy") && repr == Repr::Rust {
+            repr = Repr::C;
+        } else if attr.path().is_ident("account") {
+            let zero_copy = attr.meta.require_list().is_ok_and(|list| list.tokens.to_string().contains("zero_copy"));
+            if zero_copy && repr == Repr::Rust {
+                repr = Repr::C;
+            }
+        }
+    }
+    repr
+}
+
+enum Def {
+    Struct { fields: Vec<Type>, repr: Repr, generic: bool },
+    Enum { variants: Vec<Vec<Type>>, generic: bool },
+    Alias(Box<Type>),
+}
+
+fn field_types(fields: &Fields) -> Vec<Type> {
+    fields.iter().map(|field| field.ty.clone()).collect()
+}
+
+/// The types and constants of a tree
+#[derive(Default)]
+pub struct Types {
+    defs: HashMap<String, Def>,
+    consts: HashMap<String, Expr>,
+}
+
+impl<'ast> Visit<'ast> for Types {
+    fn visit_item_struct(&mut self, item: &'ast ItemStruct) {
+        let def = Def::Struct {
+            fields: field_types(&item.fields),
+            repr: repr(&item.attrs),
+            generic: item.generics.type_params().next().is_some(),
+        };
+        self.defs.entry(item.ident.to_string()).or_insert(def);
+    }
+
+    fn visit_item_enum(&mut self, item: &'ast ItemEnum) {
+        let def = Def::Enum {
+            variants: item.variants.iter().map(|variant| field_types(&variant.fields)).collect(),
+            generic: item.generics.type_params().next().is_some(),
+        };
+        self.defs.entry(item.ident.to_string()).or_insert(def);
+    }
+
+    fn visit_item_type(&mut self, item: &'ast ItemType) {
+        if item.generics.params.is_empty() {
+            self.defs.entry(item.ident.to_string()).or_insert_with(|| Def::Alias(item.ty.clone()));
+        }
+    }
+
+    fn visit_item_const(&mut self, item: &'ast ItemConst) {
+        self.consts.entry(item.ident.to_string()).or_insert_with(|| (*item.expr).clone());
+        visit::visit_item_const(self, item);
+    }
+}
+
+/// The type arguments of the last segment of `path`
+fn type_args(path: &syn::Path) -> Vec<&Type> {
+    let Some(PathArguments::AngleBracketed(args)) = path.segments.last().map(|segment| &segment.arguments) else {
+        return Vec::new();
+    };
+    args.args.iter().filter_map(|arg| match arg {
+        GenericArgument::Type(ty) => Some(ty),
+        _ => None,
+    }).collect()
+}
+
+/// A pointer to `ty` is fat
+fn is_unsized(ty: &Type) -> bool {
+    match ty {
+        Type::Slice(_) | Type::TraitObject(_) => true,
+        Type::Path(path) => path.path.is_ident("str"),
+        _ => false,
+    }
+}
+
+/// `Option` of these is no larger than they are
+fn has_niche(ty: &Type) -> bool {
+    match ty {
+        Type::Reference(_) => true,
+        Type::Path(path) => path.path.segments.last().is_some_and(|segment| {
+            let name = segment.ident.to_string();
+            matches!(name.as_str(), "Box" | "Rc" | "Arc" | "Vec" | "String" | "bool") || name.starts_with("NonZero")
+        }),
+        _ => false,
+    }
+}
+
+impl Types {
+    /// The structs, enums, aliases and constants of the parsed `files`, `#[cfg(test)]`
+    /// items included
+    pub fn collect(files: &[(PathBuf, syn::File)]) -> Types {
+        let mut types = Types::default();
+        for (_, file) in files {
+            types.visit_file(file);
+        }
+        types
+    }
+
+    pub fn is_defined(&self, name: &str) -> bool {
+        self.defs.contains_key(name)
+    }
+
+    /// The value of a constant expression: integer literals, constants and arithmetic
+    pub fn eval(&self, expr: &Expr) -> Option<u64> {
+        self.eval_nested(expr, 0)
+    }
+
+    fn eval_nested(&self, expr: &Expr, depth: usize) -> Option<u64> {
+        if depth > 16 {
+            return None;
+        }
+        match expr {
+            Expr::Lit(syn::ExprLit { lit: Lit::Int(int), .. }) => int.base10_parse().ok(),
+            Expr::Path(path) => self.eval_nested(self.consts.get(&path.path.segments.last()?.ident.to_string())?, depth + 1),
+            Expr::Paren(paren) => self.eval_nested(&paren.expr, depth),
+            Expr::Group(group) => self.eval_nested(&group.expr, depth),
+            Expr::Cast(cast) => self.eval_nested(&cast.expr, depth),
+            Expr::Binary(binary) => {
+                let (left, right) = (self.eval_nested(&binary.left, depth)?, self.eval_nested(&binary.right, depth)?);
+                match binary.op {
+                    BinOp::Add(_) => left.checked_add(right),
+                    BinOp::Sub(_) => left.checked_sub(right),
+                    BinOp::Mul(_) => left.checked_mul(right),
+                    BinOp::Div(_) => left.checked_div(right),
+                    _ => None,
+                }
+            }
+            _ => None,
+        }
+    }
+
+    pub fn layout(&self, ty: &Type) -> Option<Layout> {
+        self.layout_nested(ty, &mut Vec::new())
+    }
+
+    /// The layout of the type named `name`
+    pub fn layout_of(&self, name: &str) -> Option<Layout> {
+        self.def_layout(name, &mut Vec::new())
+    }
+
+    /// `visiting` holds the definitions being laid out, against recursive types
+    fn layout_nested(&self, ty: &Type, visiting: &mut Vec<String>) -> Option<Layout> {
+        match ty {
+            Type::Path(path) if path.qself.is_none() => self.path_layout(&path.path, visiting),
+            Type::Reference(reference) => Some(if is_unsized(&reference.elem) { Layout::FAT_POINTER } else { Layout::POINTER }),
+            Type::Ptr(pointer) => Some(if is_unsized(&pointer.elem) { Layout::FAT_POINTER } else { Layout::POINTER }),
+            Type::Array(array) => {
+                let element = self.layout_nested(&array.elem, visiting)?;
+                Some(Layout::new(element.size.checked_mul(self.eval(&array.len)?)?, element.align))
+            }
+            Type::Tuple(tuple) => {
+                let fields: Vec<&Type> = tuple.elems.iter().collect();
+                self.fields_layout(&fields, Repr::Rust, visiting)
+            }
+            Type::Paren(paren) => self.layout_nested(&paren.elem, visiting),
+            Type::Group(group) => self.layout_nested(&group.elem, visiting),
+            Type::BareFn(_) => Some(Layout::POINTER),
+            _ => None,
+        }
+    }
+
+    fn path_layout(&self, path: &syn::Path, visiting: &mut Vec<String>) -> Option<Layout> {
+        let name = path.segments.last()?.ident.to_string();
+        let args = type_args(path);
+        let layout = match name.as_str() {
+            "u8" | "i8" | "bool" => Layout::new(1, 1),
+            "u16" | "i16" => Layout::new(2, 2),
+            "u32" | "i32" | "f32" | "char" => Layout::new(4, 4),
+            "u64" | "i64" | "f64" | "usize" | "isize" => Layout::new(8, 8),
+            "u128" | "i128" => Layout::new(16, 16),
+            "Pubkey" => Layout::new(32, 1),
+            "PhantomData" | "PhantomPinned" => Layout::new(0, 1),
+            "String" | "Vec" | "VecDeque" | "BTreeMap" | "BTreeSet" => Layout::VEC,
+            "HashMap" | "HashSet" => Layout::new(48, 8),
+            "Box" | "Rc" | "Arc" => if args.first().is_some_and(|inner| is_unsized(inner)) { Layout::FAT_POINTER } else { Layout::POINTER },
+            "Cell" => self.layout_nested(args.first()?, visiting)?,
+            "RefCell" => {
+                let inner = self.layout_nested(args.first()?, visiting)?;
+                Layout::new(round_up(inner.size + 8, inner.align.max(8)), inner.align.max(8))
+            }
+            "Option" => {
+                let inner_type = args.first()?;
+                let inner = self.layout_nested(inner_type, visiting)?;
+                if has_niche(inner_type) { inner } else { Layout::new(round_up(inner.size + inner.align, inner.align), inner.align) }
+            }
+            // key, lamports, data, owner, rent_epoch and three flags
+            "AccountInfo" => Layout::new(48, 8),
+            "Account" | "InterfaceAccount" | "Sysvar" => {
+                let account = self.layout_nested(args.last()?, visiting)?;
+                let align = account.align.max(8);
+                Layout::new(round_up(account.size, 8) + 8, align)
+            }
+            "AccountLoader" | "Signer" | "Program" | "Interface" | "SystemAccount" | "UncheckedAccount" => Layout::POINTER,
+            // program_id, accounts, remaining_accounts and bumps
+            "Context" => Layout::new(40, 8),
+            _ => self.def_layout(&name, visiting)?,
+        };
+        Some(layout)
+    }
+
+    fn def_layout(&self, name: &str, visiting: &mut Vec<String>) -> Option<Layout> {
+        if visiting.iter().any(|known| known == name) {
+            return None;
+        }
+        let def = self.defs.get(name)?;
+        visiting.push(name.to_string());
+        let layout = match def {
+            Def::Struct { generic: true, .. } | Def::Enum { generic: true, .. } => None,
+            Def::Struct { fields, repr, .. } => self.fields_layout(&fields.iter().collect::<Vec<_>>(), *repr, visiting),
+            Def::Enum { variants, .. } => self.enum_layout(variants, visiting),
+            Def::Alias(ty) => self.layout_nested(ty, visiting),
+        };
+        visiting.pop();
+        layout
+    }
+
+    fn fields_layout(&self, fields: &[&Type], repr: Repr, visiting: &mut Vec<String>) -> Option<Layout> {
+        let mut size = 0;
+        let mut align = 1;
+        for field in fields {
+            let layout = self.layout_nested(field, visiting)?;
+            match repr {
+                Repr::C => size = round_up(size, layout.align) + layout.size,
+                Repr::Rust | Repr::Packed => size += layout.size,
+            }
+            align = align.max(layout.align);
+        }
+        if repr == Repr::Packed {
+            return Some(Layout::new(size, 1));
+        }
+        Some(Layout::new(round_up(size, align), align))
+    }
+
+    fn enum_layout(&self, variants: &[Vec<Type>], visiting: &mut Vec<String>) -> Option<Layout> {
+        let mut payload = Layout::new(0, 1);
+        for fields in variants {
+            let layout = self.fields_layout(&fields.iter().collect::<Vec<_>>(), Repr::Rust, visiting)?;
+            payload = Layout::new(payload.size.max(layout.size), payload.align.max(layout.align));
+        }
+        let tag = if variants.len() > 256 { 2 } else { 1 };
+        if payload.size == 0 {
+            return Some(Layout::new(tag, tag));
+        }
+        let align = payload.align.max(tag);
+        Some(Layout::new(round_up(payload.size, align) + align, align))
+    }
+}
@@ -0,0 +1,340 @@
+//! Estimated SBF stack frames: the locals of every function and the Anchor accounts
+//! structs, sized with [`layout`].
+//!
+//! SBF gives each function a 4 KiB stack frame; a function needing more fails at build
+//! time with a "Stack offset exceeded" warning or at run time with an access violation.
+//! The frame of a function is estimated as the sum of its `let` bindings whose type is
+//! known: annotated (`let buffer: [u8; 1024]`), a struct literal, an array repeat
+//! (`[0u8; 4096]`) or a call of an associated function of a type in the tree
+//! (`Vault::default()`, `State::try_from_slice(&data)?`). Temporaries, spills and the
+//! locals of callees are not counted, so the estimate is a lower bound. Closures count
+//! as their own functions and are left out; functions declared in a body are measured
+//! on their own.
+//!
+//! Parameters taken by value are listed with their size: a large struct passed by value
+//! is copied into the caller's frame on every call. An accounts struct
+//! (`#[derive(Accounts)]`) is built whole in the frame of Anchor's generated
+//! `try_accounts`, so its size counts against the same limit; `Box<Account<..>>` moves
+//! an account to the heap.
+
+pub mod layout;
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use quote::ToTokens;
+use serde::Serialize;
+use syn::spanned::Spanned;
+use syn::visit::{self, Visit};
+use syn::{Block, Expr, FnArg, ImplItemFn, ItemFn, ItemImpl, ItemMod, ItemStruct, ItemTrait, Lit, Pat, Signature, TraitItemFn, Type};
+pub use trr_core::Location;
+use trr_core::source::{is_cfg_test, location};
+use trr_core::walk;
+
+use layout::Types;
+
+/// The SBF stack frame size, in bytes
+pub const DEFAULT_FRAME_LIMIT: u64 = 4096;
+
+/// Parameters taken by value larger than this, in bytes, are reported when no limit is
+/// given
+pub const DEFAULT_PARAMETER_LIMIT: u64 = 256;
+
+/// A local or parameter with a known size
+#[derive(Clone, Debug, Serialize)]
+pub struct Value {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub ty: String,
+    pub size: u64,
+    pub location: Location,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct FunctionFrame {
+    /// Inline modules, then the impl type or trait for methods, then the name
+    pub name: String,
+    pub location: Location,
+    /// Estimated frame size in bytes: the sum of the sized locals
+    pub frame: u64,
+    /// Largest first
+    pub locals: Vec<Value>,
+    /// Locals whose size could not be told
+    pub unsized_locals: usize,
+    /// Taken by value, with a known size, largest first
+    pub parameters: Vec<Value>,
+}
+
+impl FunctionFrame {
+    pub fn largest_parameter(&self) -> Option<&Value> {
+        self.parameters.first()
+    }
+}
+
+/// An accounts struct, built whole by Anchor's `try_accounts`
+#[derive(Clone, Debug, Serialize)]
+pub struct AccountsFrame {
+    pub name: String,
+    pub location: Location,
+    /// `None` if a field's size could not be told
+    pub size: Option<u64>,
+}
+
+#[derive(Debug, Default)]
+pub struct Analysis {
+    pub files: usize,
+    /// Functions with a sized local or parameter, largest frame first
+    pub functions: Vec<FunctionFrame>,
+    /// Largest first
+    pub accounts: Vec<AccountsFrame>,
+    /// Files that could not be read or parsed
+    pub errors: Vec<(PathBuf, String)>,
+}
+
+impl Analysis {
+    /// The first of the functions with the largest frame
+    pub fn max_frame(&self) -> Option<&FunctionFrame> {
+        self.functions.iter().rev().max_by_key(|function| function.frame)
+    }
+
+    pub fn functions_over(&self, limit: u64) -> impl Iterator<Item = &FunctionFrame> {
+        self.functions.iter().filter(move |function| function.frame > limit)
+    }
+
+    pub fn accounts_over(&self, limit: u64) -> impl Iterator<Item = &AccountsFrame> {
+        self.accounts.iter().filter(move |accounts| accounts.size.is_some_and(|size| size > limit))
+    }
+
+    /// Parameters taken by value larger than `limit`, with their function
+    pub fn parameters_over(&self, limit: u64) -> impl Iterator<Item = (&FunctionFrame, &Value)> {
+        self.functions.iter()
+            .flat_map(|function| function.parameters.iter().map(move |parameter| (function, parameter)))
+            .filter(move |(_, parameter)| parameter.size > limit)
+    }
+}
+
+/// Estimate the frames of the Rust files under `dir` (or `dir` itself, if it is a
+/// file), leaving out `target/` and test code
+pub fn analyze(dir: &Path) -> io::Result<Analysis> {
+    let files = walk::program_files(dir)?;
+
+    let mut analysis = Analysis { files: files.len(), ..Analysis::default() };
+    let mut parsed = Vec::new();
+    for file in files {
+        let result = fs::read_to_string(&file)
+            .map_err(|e| e.to_string())
+            .and_then(|source| syn::parse_file(&source).map_err(|e| e.to_string()));
+        match result {
+            Ok(syntax) => parsed.push((file, syntax)),
+            Err(e) => analysis.errors.push((file, e)),
+        }
+    }
+
+    let types = Types::collect(&parsed);
+    for (path, file) in &parsed {
+        let mut visitor = FileVisitor { path, types: &types, scope: Vec::new(), analysis: &mut analysis };
+        visitor.visit_file(file);
+    }
+    analysis.functions.sort_by(|a, b| b.frame.cmp(&a.frame)
+        .then_with(|| b.largest_parameter().map(|parameter| parameter.size).cmp(&a.largest_parameter().map(|parameter| parameter.size)))
+        .then_with(|| a.location.file.cmp(&b.location.file))
+        .then_with(|| a.location.line.cmp(&b.location.line)));
+    analysis.accounts.sort_by(|a, b| b.size.cmp(&a.size)
+        .then_with(|| a.location.file.cmp(&b.location.file))
+        .then_with(|| a.location.line.cmp(&b.location.line)));
+    Ok(analysis)
+}
+
+/// The name a pattern binds, or its text for destructuring patterns
+fn binding(pattern: &Pat) -> String {
+    match pattern {
+        Pat::Ident(ident) => ident.ident.to_string(),
+        pattern => text(pattern),
+    }
+}
+
+/// Source-like text of a type or pattern: `Account<'info, Vault>`, `[u8; 1024]`
+fn text(tokens: &impl ToTokens) -> String {
+    let mut text = tokens.to_token_stream().to_string();
+    for (from, to) in [(" < ", "<"), ("< ", "<"), (" <", "<"), (" >", ">"), (" :: ", "::"), (" ,", ","), ("& ", "&"), ("[ ", "["), (" ]", "]"), (" ;", ";"), ("' ", "'")] {
+        text = text.replace(from, to);
+    }
+    text
+}
+
+struct FileVisitor<'a> {
+    path: &'a Path,
+    types: &'a Types,
+    /// Inline modules, impl types, traits and enclosing functions
+    scope: Vec<String>,
+    analysis: &'a mut Analysis,
+}
+
+impl FileVisitor<'_> {
+    fn add_function(&mut self, sig: &Signature, block: &Block) {
+        let mut body = BodyVisitor { path: self.path, types: self.types, locals: Vec::new(), unsized_locals: 0 };
+        body.visit_block(block);
+        let mut parameters: Vec<Value> = sig.inputs.iter().filter_map(|input| match input {
+            FnArg::Typed(typed) => Some(Value {
+                name: binding(&typed.pat),
+                ty: text(&typed.ty),
+                size: self.types.layout(&typed.ty)?.size,
+                location: location(self.path, typed.pat.span()),
+            }),
+            FnArg::Receiver(_) => None,
+        }).collect();
+        parameters.sort_by_key(|parameter| std::cmp::Reverse(parameter.size));
+        let mut locals = body.locals;
+        locals.sort_by_key(|local| std::cmp::Reverse(local.size));
+
+        if !locals.is_empty() || !parameters.is_empty() {
+            let mut name = self.scope.clone();
+            name.push(sig.ident.to_string());
+            self.analysis.functions.push(FunctionFrame {
+                name: name.join("::"),
+                location: location(self.path, sig.ident.span()),
+                frame: locals.iter().map(|local| local.size).sum(),
+                locals,
+                unsized_locals: body.unsized_locals,
+                parameters,
+            });
+        }
+
+        // Functions declared in the body
+        self.scope.push(sig.ident.to_string());
+        self.visit_block(block);
+        self.scope.pop();
+    }
+}
+
+impl<'ast> Visit<'ast> for FileVisitor<'_> {
+    fn visit_item_mod(&mut self, item: &'ast ItemMod) {
+        if is_cfg_test(&item.attrs) {
+            return;
+        }
+        self.scope.push(item.ident.to_string());
+        visit::visit_item_mod(self, item);
+        self.scope.pop();
+    }
+
+    fn visit_item_fn(&mut self, item: &'ast ItemFn) {
+        if !is_cfg_test(&item.attrs) {
+            self.add_function(&item.sig, &item.block);
+        }
+    }
+
+    fn visit_item_impl(&mut self, item: &'ast ItemImpl) {
+        if is_cfg_test(&item.attrs) {
+            return;
+        }
+        let owner = match &*item.self_ty {
+            Type::Path(path) => path.path.segments.last().map(|segment| segment.ident.to_string()),
+            _ => None,
+        };
+        self.scope.push(owner.unwrap_or_else(|| "_".to_string()));
+        visit::visit_item_impl(self, item);
+        self.scope.pop();
+    }
+
+    fn visit_impl_item_fn(&mut self, item: &'ast ImplItemFn) {
+        if !is_cfg_test(&item.attrs) {
+            self.add_function(&item.sig, &item.block);
+        }
+    }
+
+    fn visit_item_trait(&mut self, item: &'ast ItemTrait) {
+        self.scope.push(item.ident.to_string());
+        visit::visit_item_trait(self, item);
+        self.scope.pop();
+    }
+
+    fn visit_trait_item_fn(&mut self, item: &'ast TraitItemFn) {
+        if let Some(block) = &item.default {
+            self.add_function(&item.sig, block);
+        }
+    }
+
+    fn visit_item_struct(&mut self, item: &'ast ItemStruct) {
+        let accounts = item.attrs.iter()
+            .filter(|attr| attr.path().is_ident("derive"))
+            .any(|attr| attr.meta.to_token_stream().to_string().contains("Accounts"));
+        if accounts && !is_cfg_test(&item.attrs) {
+            self.analysis.accounts.push(AccountsFrame {
+                name: item.ident.to_string(),
+                location: location(self.path, item.ident.span()),
+                size: self.types.layout_of(&item.ident.to_string()).map(|layout| layout.size),
+            });
+        }
+    }
+}
+
+/// The `let` bindings of one body, leaving out closures and the items declared in it
+struct BodyVisitor<'a> {
+    path: &'a Path,
+    types: &'a Types,
+    locals: Vec<Value>,
+    unsized_locals: usize,
+}
+
+impl BodyVisitor<'_> {
+    /// The type of a binding's value, as text with its size, when it shows
+    fn value_type(&self, expr: &Expr) -> Option<(String, u64)> {
+        match expr {
+            Expr::Struct(literal) if literal.qself.is_none() => {
+                let name = literal.path.segments.last()?.ident.to_string();
+                Some((name.clone(), self.types.layout_of(&name)?.size))
+            }
+            Expr::Repeat(repeat) => {
+                let (element, size) = match &*repeat.expr {
+                    Expr::Lit(syn::ExprLit { lit: Lit::Int(int), .. }) if !int.suffix().is_empty() => {
+                        let ty: Type = syn::parse_str(int.suffix()).ok()?;
+                        (int.suffix().to_string(), self.types.layout(&ty)?.size)
+                    }
+                    Expr::Lit(syn::ExprLit { lit: Lit::Bool(_), .. }) => ("bool".to_string(), 1),
+                    other => self.value_type(other)?,
+                };
+                let length = self.types.eval(&repeat.len)?;
+                Some((format!("[{}; {}]", element, length), size.checked_mul(length)?))
+            }
+            // `Vault::default()`, `State::try_from_slice(&data)`
+            Expr::Call(call) => {
+                let Expr::Path(function) = &*call.func else { return None };
+                let segments = &function.path.segments;
+                let owner = segments.iter().nth_back(1)?.ident.to_string();
+                if !self.types.is_defined(&owner) {
+                    return None;
+                }
+                Some((owner.clone(), self.types.layout_of(&owner)?.size))
+            }
+            Expr::Try(tried) => self.value_type(&tried.expr),
+            Expr::MethodCall(call) if matches!(call.method.to_string().as_str(), "unwrap" | "expect" | "unwrap_or_default") => {
+                self.value_type(&call.receiver)
+            }
+            Expr::Paren(paren) => self.value_type(&paren.expr),
+            _ => None,
+        }
+    }
+}
+
+impl<'ast> Visit<'ast> for BodyVisitor<'_> {
+    fn visit_item(&mut self, _: &'ast syn::Item) {}
+
+    fn visit_expr_closure(&mut self, _: &'ast syn::ExprClosure) {}
+
+    fn visit_local(&mut self, local: &'ast syn::Local) {
+        let (pattern, annotated) = match &local.pat {
+            Pat::Type(typed) => (&*typed.pat, Some(&*typed.ty)),
+            pattern => (pattern, None),
+        };
+        let sized = match annotated {
+            Some(ty) => self.types.layout(ty).map(|layout| (text(ty), layout.size)),
+            None => local.init.as_ref().and_then(|init| self.value_type(&init.expr)),
+        };
+        match sized {
+            Some((ty, size)) => self.locals.push(Value { name: binding(pattern), ty, size, location: location(self.path, pattern.span()) }),
+            None => self.unsized_locals += 1,
+        }
+        visit::visit_local(self, local);
+    }
+}
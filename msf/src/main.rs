@@ -0,0 +1,204 @@
+mod report;
+mod sarif;
+
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::process;
+
+use clap::Parser;
+use serde::Serialize;
+use trr_core::git::Repository;
+use trr_core::output::{self, Format};
+
+use msf::{AccountsFrame, Analysis, FunctionFrame, DEFAULT_FRAME_LIMIT, DEFAULT_PARAMETER_LIMIT};
+
+#[derive(Parser)]
+#[clap(author, version, about = "Maximum Stack Frame (MSF) Estimator")]
+#[clap(after_help = "Examples:
+  msf programs/vault
+  msf --top 10 programs
+  msf --fail-above 4096 --fail-parameter-above 512 programs
+  msf --format sarif programs > stack-frames.sarif")]
+struct Args {
+    /// Directory to analyze (relative to --repo when given)
+    #[clap(value_name = "DIR", default_value = ".")]
+    dir: PathBuf,
+
+    /// Git repository URL to clone, or path of a local checkout
+    #[clap(long, value_name = "REPO_URL_OR_PATH")]
+    repo: Option<String>,
+
+    /// Branch, tag or commit of --repo to analyze
+    #[clap(long, value_name = "REV", requires = "repo")]
+    rev: Option<String>,
+
+    /// Output format
+    #[clap(long, value_enum, default_value = "text")]
+    format: Format,
+
+    /// Only the N functions with the largest frames
+    #[clap(long, value_name = "N")]
+    top: Option<usize>,
+
+    /// Exit with status 1 if any function or accounts struct needs more than BYTES of stack
+    #[clap(long, value_name = "BYTES")]
+    fail_above: Option<u64>,
+
+    /// Exit with status 1 if any function takes a parameter larger than BYTES by value
+    #[clap(long, value_name = "BYTES")]
+    fail_parameter_above: Option<u64>,
+}
+
+/// Structured form of the analysis for `--format json`
+#[derive(Serialize)]
+struct JsonReport<'a> {
+    summary: Summary,
+    /// Largest frame first
+    functions: &'a [FunctionFrame],
+    /// Largest first
+    accounts: &'a [AccountsFrame],
+    errors: Vec<FileError>,
+}
+
+#[derive(Serialize)]
+struct Summary {
+    files_analyzed: usize,
+    /// Functions with a sized local or by-value parameter
+    functions: usize,
+    max_frame: u64,
+    /// Over the defaults: the 4 KiB frame and 256-byte parameters
+    over_frame_limit: usize,
+    accounts_over_frame_limit: usize,
+    large_parameters: usize,
+}
+
+#[derive(Serialize)]
+struct FileError {
+    file: PathBuf,
+    error: String,
+}
+
+fn print_text(analysis: &Analysis, functions: &[FunctionFrame]) {
+    if !functions.is_empty() {
+        println!("\nFunctions by estimated stack frame:");
+        println!("\n{:>8}  Function", "Bytes");
+    }
+    for function in functions {
+        println!("{:>8}  {} ({})", function.frame, function.name, function.location);
+        for local in function.locals.iter().take(3) {
+            println!("{:>8}    let {}: {} at line {}", local.size, local.name, local.ty, local.location.line);
+        }
+        for parameter in function.parameters.iter().filter(|parameter| parameter.size > DEFAULT_PARAMETER_LIMIT) {
+            println!("{:>8}    by value: {}: {}", parameter.size, parameter.name, parameter.ty);
+        }
+    }
+    if !analysis.accounts.is_empty() {
+        println!("\nAccounts structs:");
+    }
+    for accounts in &analysis.accounts {
+        let size = accounts.size.map_or_else(|| "?".to_string(), |size| size.to_string());
+        println!("{:>8}  {} ({})", size, accounts.name, accounts.location);
+    }
+
+    println!("\nSummary:");
+    println!("Files analyzed: {}", analysis.files);
+    println!("Functions with sized locals or parameters: {}", analysis.functions.len());
+    if let Some(function) = analysis.max_frame() {
+        println!("Largest frame: {} bytes ({})", function.frame, function.name);
+    }
+    println!("Functions over {} bytes: {}", DEFAULT_FRAME_LIMIT, analysis.functions_over(DEFAULT_FRAME_LIMIT).count());
+    println!("Accounts structs over {} bytes: {}", DEFAULT_FRAME_LIMIT, analysis.accounts_over(DEFAULT_FRAME_LIMIT).count());
+    println!("Parameters over {} bytes by value: {}", DEFAULT_PARAMETER_LIMIT, analysis.parameters_over(DEFAULT_PARAMETER_LIMIT).count());
+}
+
+fn write_json(out: &mut dyn Write, analysis: &Analysis, functions: &[FunctionFrame]) -> io::Result<()> {
+    let report = JsonReport {
+        summary: Summary {
+            files_analyzed: analysis.files,
+            functions: analysis.functions.len(),
+            max_frame: analysis.max_frame().map_or(0, |function| function.frame),
+            over_frame_limit: analysis.functions_over(DEFAULT_FRAME_LIMIT).count(),
+            accounts_over_frame_limit: analysis.accounts_over(DEFAULT_FRAME_LIMIT).count(),
+            large_parameters: analysis.parameters_over(DEFAULT_PARAMETER_LIMIT).count(),
+        },
+        functions,
+        accounts: &analysis.accounts,
+        errors: analysis.errors.iter()
+            .map(|(file, error)| FileError { file: file.clone(), error: error.clone() })
+            .collect(),
+    };
+    output::write_json(out, "msf", &report)
+}
+
+/// With `--fail-above` or `--fail-parameter-above`, list what is over the limits and
+/// exit with status 1
+fn enforce_limits(args: &Args, analysis: &Analysis) {
+    let mut offending = Vec::new();
+    if let Some(limit) = args.fail_above {
+        for function in analysis.functions_over(limit) {
+            offending.push(format!("{}: {} byte frame at {}", function.name, function.frame, function.location));
+        }
+        for accounts in analysis.accounts_over(limit) {
+            offending.push(format!("{}: {} byte accounts struct at {}", accounts.name, accounts.size.unwrap_or(0), accounts.location));
+        }
+    }
+    if let Some(limit) = args.fail_parameter_above {
+        for (function, parameter) in analysis.parameters_over(limit) {
+            offending.push(format!("{}: {} ({} bytes) by value at {}", function.name, parameter.name, parameter.size, parameter.location));
+        }
+    }
+    if offending.is_empty() {
+        return;
+    }
+    output::fail(&format!("{} stack limit(s) exceeded", offending.len()), offending);
+}
+
+fn run(args: &Args) -> Result<(), Box<dyn std::error::Error>> {
+    let repository = args.repo.as_deref().map(|repo| Repository::open(repo, args.rev.as_deref())).transpose()?;
+    let dir = match &repository {
+        Some(repository) => repository.subdir(&args.dir)?,
+        None => args.dir.clone(),
+    };
+    if !dir.exists() {
+        return Err(format!("Path '{}' does not exist", dir.display()).into());
+    }
+    if args.format == Format::Text {
+        println!("Analyzing: {}", dir.display());
+    }
+
+    let analysis = msf::analyze(&dir)?;
+    for (file, error) in &analysis.errors {
+        eprintln!("Error analyzing {}: {}", file.display(), error);
+    }
+    let functions = &analysis.functions[..args.top.map_or(analysis.functions.len(), |top| top.min(analysis.functions.len()))];
+
+    let mut out = io::stdout().lock();
+    match args.format {
+        Format::Text => print_text(&analysis, functions),
+        Format::Json => write_json(&mut out, &analysis, functions)?,
+        Format::Csv => report::write_csv(&mut out, functions)?,
+        Format::Markdown => report::write_markdown(&mut out, &dir, &analysis, functions)?,
+        Format::Sarif => {
+            // Artifact URIs are relative to the repository root, or to where msf runs
+            let base = repository.as_ref().map_or_else(|| PathBuf::from("."), |repository| repository.root().to_path_buf());
+            let limits = sarif::Limits {
+                frame: args.fail_above.unwrap_or(DEFAULT_FRAME_LIMIT),
+                parameter: args.fail_parameter_above.unwrap_or(DEFAULT_PARAMETER_LIMIT),
+                given: args.fail_above.is_some() || args.fail_parameter_above.is_some(),
+            };
+            sarif::write_sarif(&mut out, functions, &analysis.accounts, &limits, &base)?;
+            writeln!(out)?;
+        }
+    }
+    drop(out);
+    enforce_limits(args, &analysis);
+    Ok(())
+}
+
+fn main() {
+    let args = Args::parse();
+    if let Err(e) = run(&args) {
+        eprintln!("Error: {}", e);
+        process::exit(1);
+    }
+}
@@ -0,0 +1,83 @@
+//! `--format csv`, one row per function, and `--format markdown`, a review-ready report
+//! with the summary, the largest frames and the accounts structs.
+
+use std::io::{self, Write};
+use std::path::Path;
+
+use trr_core::output::{markdown_code, write_csv_row};
+
+use msf::{Analysis, FunctionFrame, DEFAULT_FRAME_LIMIT, DEFAULT_PARAMETER_LIMIT};
+
+/// Rows are largest frame first
+pub fn write_csv(out: &mut dyn Write, functions: &[FunctionFrame]) -> io::Result<()> {
+    write_csv_row(out, &["function", "frame", "largest_local", "largest_parameter", "unsized_locals", "file", "line"])?;
+    for function in functions {
+        write_csv_row(out, &[
+            &function.name,
+            &function.frame.to_string(),
+            &function.locals.first().map_or(0, |local| local.size).to_string(),
+            &function.largest_parameter().map_or(0, |parameter| parameter.size).to_string(),
+            &function.unsized_locals.to_string(),
+            &function.location.file.display().to_string(),
+            &function.location.line.to_string(),
+        ])?;
+    }
+    Ok(())
+}
+
+/// A size in bold when it is over `limit`
+fn flagged(value: u64, limit: u64) -> String {
+    if value > limit { format!("**{}**", value) } else { value.to_string() }
+}
+
+pub fn write_markdown(out: &mut dyn Write, target: &Path, analysis: &Analysis, functions: &[FunctionFrame]) -> io::Result<()> {
+    writeln!(out, "# Stack Frame Report\n")?;
+    writeln!(out, "Target: {}\n", markdown_code(&target.display().to_string()))?;
+    writeln!(out, "## Summary\n")?;
+    writeln!(out, "| Metric | Value |")?;
+    writeln!(out, "| --- | ---: |")?;
+    writeln!(out, "| Files analyzed | {} |", analysis.files)?;
+    writeln!(out, "| Functions with sized locals or parameters | {} |", analysis.functions.len())?;
+    writeln!(out, "| Largest frame (bytes) | {} |", analysis.max_frame().map_or(0, |function| function.frame))?;
+    writeln!(out, "| Functions over {} bytes | {} |", DEFAULT_FRAME_LIMIT, analysis.functions_over(DEFAULT_FRAME_LIMIT).count())?;
+    writeln!(out, "| Accounts structs over {} bytes | {} |", DEFAULT_FRAME_LIMIT, analysis.accounts_over(DEFAULT_FRAME_LIMIT).count())?;
+    writeln!(out, "| Parameters over {} bytes by value | {} |", DEFAULT_PARAMETER_LIMIT, analysis.parameters_over(DEFAULT_PARAMETER_LIMIT).count())?;
+
+    if !functions.is_empty() {
+        writeln!(out, "\n## Functions\n")?;
+        writeln!(out, "| Function | Frame | Largest local | Largest by-value parameter | Location |")?;
+        writeln!(out, "| --- | ---: | --- | --- | --- |")?;
+        for function in functions {
+            let local = function.locals.first()
+                .map_or(String::new(), |local| format!("{} ({})", markdown_code(&format!("{}: {}", local.name, local.ty)), local.size));
+            let parameter = function.largest_parameter().map_or(String::new(), |parameter| {
+                format!("{} ({})", markdown_code(&format!("{}: {}", parameter.name, parameter.ty)), flagged(parameter.size, DEFAULT_PARAMETER_LIMIT))
+            });
+            writeln!(
+                out,
+                "| {} | {} | {} | {} | {} |",
+                markdown_code(&function.name),
+                flagged(function.frame, DEFAULT_FRAME_LIMIT),
+                local,
+                parameter,
+                markdown_code(&function.location.to_string()),
+            )?;
+        }
+    }
+
+    if !analysis.accounts.is_empty() {
+        writeln!(out, "\n## Accounts Structs\n")?;
+        writeln!(out, "| Struct | Size | Location |")?;
+        writeln!(out, "| --- | ---: | --- |")?;
+        for accounts in &analysis.accounts {
+            writeln!(
+                out,
+                "| {} | {} | {} |",
+                markdown_code(&accounts.name),
+                accounts.size.map_or_else(|| "?".to_string(), |size| flagged(size, DEFAULT_FRAME_LIMIT)),
+                markdown_code(&accounts.location.to_string()),
+            )?;
+        }
+    }
+    Ok(())
+}
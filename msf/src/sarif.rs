@@ -0,0 +1,90 @@
+//! SARIF 2.1.0 output: a `stack-frame` result per function or accounts struct over the
+//! frame limit, located at its name, and a `large-by-value-parameter` result per
+//! parameter over the parameter limit, located at the parameter.
+
+use std::io::{self, Write};
+use std::path::Path;
+
+use serde_json::json;
+use trr_core::sarif::{location, write_log};
+
+use msf::{AccountsFrame, FunctionFrame};
+
+const FRAME_RULE: &str = "stack-frame";
+const PARAMETER_RULE: &str = "large-by-value-parameter";
+
+pub struct Limits {
+    pub frame: u64,
+    pub parameter: u64,
+    /// Given on the command line, so results are warnings rather than notes
+    pub given: bool,
+}
+
+pub fn write_sarif(out: &mut dyn Write, functions: &[FunctionFrame], accounts: &[AccountsFrame], limits: &Limits, base: &Path) -> io::Result<()> {
+    let level = if limits.given { "warning" } else { "note" };
+    let mut results = Vec::new();
+    for function in functions {
+        if function.frame > limits.frame {
+            results.push(json!({
+                "ruleId": FRAME_RULE,
+                "ruleIndex": 0,
+                "level": level,
+                "message": {
+                    "text": format!("{} needs at least {} bytes of stack (limit {})", function.name, function.frame, limits.frame),
+                },
+                "locations": [location(&function.location, base)],
+                "properties": { "frame": function.frame },
+            }));
+        }
+        for parameter in function.parameters.iter().filter(|parameter| parameter.size > limits.parameter) {
+            results.push(json!({
+                "ruleId": PARAMETER_RULE,
+                "ruleIndex": 1,
+                "level": level,
+                "message": {
+                    "text": format!(
+                        "{} takes {}: {} ({} bytes) by value (limit {})",
+                        function.name, parameter.name, parameter.ty, parameter.size, limits.parameter
+                    ),
+                },
+                "locations": [location(&parameter.location, base)],
+                "properties": { "size": parameter.size },
+            }));
+        }
+    }
+    for accounts_struct in accounts {
+        let Some(size) = accounts_struct.size.filter(|&size| size > limits.frame) else { continue };
+        results.push(json!({
+            "ruleId": FRAME_RULE,
+            "ruleIndex": 0,
+            "level": level,
+            "message": {
+                "text": format!("Accounts struct {} takes {} bytes of try_accounts' stack (limit {})", accounts_struct.name, size, limits.frame),
+            },
+            "locations": [location(&accounts_struct.location, base)],
+            "properties": { "frame": size },
+        }));
+    }
+
+    let rules = vec![
+        json!({
+            "id": FRAME_RULE,
+            "name": "StackFrame",
+            "shortDescription": { "text": "Function or accounts struct needing more stack than the frame limit" },
+            "fullDescription": {
+                "text": "The locals of the function, or the accounts struct Anchor builds in try_accounts, add up to more than the limit. SBF frames are 4 KiB; larger frames overwrite neighbouring stack or fail at run time. Box large values or move them to account data.",
+            },
+            "defaultConfiguration": { "level": "warning" },
+        }),
+        json!({
+            "id": PARAMETER_RULE,
+            "name": "LargeByValueParameter",
+            "shortDescription": { "text": "Large value passed by value" },
+            "fullDescription": {
+                "text": "The parameter is copied into the caller's stack frame on every call. Take it by reference instead.",
+            },
+            "defaultConfiguration": { "level": "warning" },
+        }),
+    ];
+    write_log(out, "msf", env!("CARGO_PKG_VERSION"), rules, results)
+}
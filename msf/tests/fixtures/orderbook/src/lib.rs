@@ -0,0 +1,68 @@
+use anchor_lang::prelude::*;
+
+pub mod state;
+
+use state::{Market, Order};
+
+declare_id!("Fg6PaFpoGXkYsidMpWTK6W2BeZ7FEfcYkg476zPFsLnS");
+
+#[program]
+pub mod orderbook {
+    use super::*;
+
+    /// Frame: 48 (the order)
+    pub fn place(ctx: Context<Place>, price: u64, quantity: u64) -> Result<()> {
+        let market = &mut ctx.accounts.market;
+        let order = Order { owner: ctx.accounts.owner.key(), price, quantity };
+        market.orders[0] = order;
+        Ok(())
+    }
+
+    /// Frame: 3112 (the copy) + 2048 (the scratch buffer) = 5160
+    pub fn snapshot(ctx: Context<Snapshot>) -> Result<()> {
+        let data = ctx.accounts.market.try_borrow_data()?;
+        let copy = Market::try_deserialize(&mut &data[..])?;
+        let mut scratch = [0u8; 2048];
+        scratch[..32].copy_from_slice(copy.authority.as_ref());
+        let log = |message: &str| {
+            let buffer = [0u8; 8192];
+            msg!("{} {}", message, buffer.len());
+        };
+        log("snapshot");
+        Ok(())
+    }
+}
+
+/// 3120 (the market) + 8 (the owner) = 3128
+#[derive(Accounts)]
+pub struct Place<'info> {
+    #[account(mut)]
+    pub market: Account<'info, Market>,
+    pub owner: Signer<'info>,
+}
+
+/// Two markets on the stack: 3120 + 3120 + 8 = 6248
+#[derive(Accounts)]
+pub struct Migrate<'info> {
+    pub old: Account<'info, Market>,
+    #[account(mut)]
+    pub new: Account<'info, Market>,
+    pub authority: Signer<'info>,
+}
+
+/// The market boxed: 8 + 8 + 48 = 64
+#[derive(Accounts)]
+pub struct Snapshot<'info> {
+    pub market: Box<Account<'info, Market>>,
+    pub system_program: Program<'info, System>,
+    pub payer: AccountInfo<'info>,
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn large_buffer() {
+        let buffer = [0u64; 1024];
+        assert_eq!(buffer.len(), 1024);
+    }
+}
@@ -0,0 +1,32 @@
+use anchor_lang::prelude::*;
+
+pub const MAX_ORDERS: usize = 64;
+
+/// 32 + 1 + 64 * 48, rounded up to 8: 3112 bytes
+#[account]
+pub struct Market {
+    pub authority: Pubkey,
+    pub bump: u8,
+    pub orders: [Order; MAX_ORDERS],
+}
+
+/// 48 bytes
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default)]
+pub struct Order {
+    pub owner: Pubkey,
+    pub price: u64,
+    pub quantity: u64,
+}
+
+impl Market {
+    /// Frame: 48 (the order); `market` is 3112 bytes by value
+    pub fn best(market: Market) -> Order {
+        let mut best = Order::default();
+        for order in market.orders {
+            if order.price > best.price {
+                best = order;
+            }
+        }
+        best
+    }
+}
@@ -0,0 +1,87 @@
+// Estimates the frames of tests/fixtures/orderbook against the sizes written in its doc
+// comments, and checks the binary's limits.
+
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use msf::layout::Types;
+
+fn fixture() -> PathBuf {
+    Path::new(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/orderbook")).to_path_buf()
+}
+
+#[test]
+fn frames() {
+    let analysis = msf::analyze(&fixture()).unwrap();
+    let frames: Vec<_> = analysis.functions.iter().map(|function| (function.name.as_str(), function.frame)).collect();
+    assert_eq!(frames, [("orderbook::snapshot", 5160), ("Market::best", 48), ("orderbook::place", 48)]);
+
+    // The closure's buffer and the test module are left out
+    let snapshot = &analysis.functions[0];
+    let locals: Vec<_> = snapshot.locals.iter().map(|local| (local.name.as_str(), local.ty.as_str(), local.size)).collect();
+    assert_eq!(locals, [("copy", "Market", 3112), ("scratch", "[u8; 2048]", 2048)]);
+    assert_eq!(snapshot.unsized_locals, 2);
+    assert!(analysis.errors.is_empty(), "{:?}", analysis.errors);
+}
+
+#[test]
+fn parameters_and_accounts() {
+    let analysis = msf::analyze(&fixture()).unwrap();
+    let parameters: Vec<_> = analysis.parameters_over(msf::DEFAULT_PARAMETER_LIMIT)
+        .map(|(function, parameter)| (function.name.as_str(), parameter.name.as_str(), parameter.size))
+        .collect();
+    assert_eq!(parameters, [("Market::best", "market", 3112)]);
+
+    let accounts: Vec<_> = analysis.accounts.iter().map(|accounts| (accounts.name.as_str(), accounts.size)).collect();
+    assert_eq!(accounts, [("Migrate", Some(6248)), ("Place", Some(3128)), ("Snapshot", Some(64))]);
+}
+
+#[test]
+fn layouts() {
+    let source = "
+        const LEN: usize = 4 * 8;
+        struct Padded { a: u8, b: u64, c: u8 }
+        #[repr(C)]
+        struct Ordered { a: u8, b: u64, c: u8 }
+        #[repr(C, packed)]
+        struct Packed { a: u8, b: u64, c: u8 }
+        enum Side { Bid { price: u64 }, Ask(u128), None }
+        struct Buffer { data: [u16; LEN], tail: Option<Box<Padded>> }
+        struct Unknown { inner: external::Thing }
+    ";
+    let files = [(PathBuf::from("lib.rs"), syn::parse_file(source).unwrap())];
+    let types = Types::collect(&files);
+    let size = |name: &str| types.layout_of(name).map(|layout| layout.size);
+    assert_eq!(size("Padded"), Some(16));
+    assert_eq!(size("Ordered"), Some(24));
+    assert_eq!(size("Packed"), Some(10));
+    assert_eq!(size("Side"), Some(32));
+    assert_eq!(size("Buffer"), Some(72));
+    assert_eq!(size("Unknown"), None);
+}
+
+#[test]
+fn limits() {
+    let run = |args: &[&str]| Command::new(env!("CARGO_BIN_EXE_msf"))
+        .args(args)
+        .arg(fixture())
+        .output()
+        .expect("failed to run msf");
+
+    assert!(run(&["--fail-above", "6248", "--fail-parameter-above", "3112"]).status.success());
+
+    let output = run(&["--fail-above", "4096"]);
+    assert_eq!(output.status.code(), Some(1));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("2 stack limit(s) exceeded"), "stderr: {}", stderr);
+    assert!(stderr.contains("orderbook::snapshot: 5160 byte frame"), "stderr: {}", stderr);
+    assert!(stderr.contains("Migrate: 6248 byte accounts struct"), "stderr: {}", stderr);
+
+    let output = run(&["--fail-parameter-above", "256", "--format", "sarif"]);
+    assert_eq!(output.status.code(), Some(1));
+    let log: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let rules: Vec<_> = log["runs"][0]["results"].as_array().unwrap().iter()
+        .map(|result| (result["ruleId"].as_str().unwrap(), result["level"].as_str().unwrap()))
+        .collect();
+    assert_eq!(rules, [("stack-frame", "warning"), ("large-by-value-parameter", "warning"), ("stack-frame", "warning")]);
+}
@@ -0,0 +1,239 @@
+//! The sysvar reads of each function and `#[derive(Accounts)]` struct.
+//!
+//! A read is:
+//! - `X::get()`, through the `sol_get_*_sysvar` syscall
+//! - `X::from_account_info(..)`, through an account
+//! - a path into `sysvar::<module>` (`sysvar::clock::ID`, `sysvar::slot_hashes::check_id`),
+//!   checking or using an account's key
+//! - a function of `sysvar::instructions` (`load_instruction_at_checked`,
+//!   `get_instruction_relative`, ...), through the Instructions account, wherever it is
+//!   imported from
+//! - an Anchor `Sysvar<'info, X>` account, or an account constrained to
+//!   `address = sysvar::<module>::ID`
+//!
+//! `X` is one of the types of [`Sysvar::ALL`]. `#[cfg(test)]` items and `#[test]`
+//! functions are left out, as [`mcd`] leaves them out.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use proc_macro2::TokenTree;
+use serde::Serialize;
+use syn::punctuated::Punctuated;
+use syn::spanned::Spanned;
+use syn::visit::{self, Visit};
+use syn::{Expr, Fields, ImplItemFn, ItemFn, ItemImpl, ItemMod, ItemStruct, TraitItemFn, Type};
+use trr_core::anchor::{context_accounts, derives_accounts, inner_type, keyed_constraints, type_name};
+use trr_core::source::{is_cfg_test, location};
+
+use crate::{Access, Location, Pattern, Sysvar};
+
+/// Functions of `sysvar::instructions` reading the Instructions account
+const INSTRUCTIONS_FUNCTIONS: &[&str] = &[
+    "load_current_index_checked", "load_instruction_at_checked", "get_instruction_relative", "load_current_index",
+    "load_instruction_at",
+];
+
+/// The deprecated ones, which read any account given to them as the Instructions sysvar
+const UNCHECKED_FUNCTIONS: &[&str] = &["load_current_index", "load_instruction_at"];
+
+/// A sysvar read
+#[derive(Clone, Debug, Serialize)]
+pub struct Read {
+    pub sysvar: Sysvar,
+    pub access: Access,
+    /// `Clock::get`, `Sysvar<'info, Rent>`, `sysvar::instructions::ID`, ...
+    pub call: String,
+    pub pattern: Option<Pattern>,
+    pub location: Location,
+}
+
+impl Read {
+    fn new(sysvar: Sysvar, access: Access, call: String, location: Location) -> Self {
+        let pattern = if sysvar.deprecated() {
+            Some(Pattern::DeprecatedSysvar)
+        } else if access == Access::Account && sysvar.has_syscall() {
+            Some(Pattern::AccountForSyscall)
+        } else {
+            None
+        };
+        Read { sysvar, access, call, pattern, location }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct FunctionFacts {
+    /// The accounts struct `T` of a `Context<T>` parameter
+    pub context: Option<String>,
+    pub reads: Vec<Read>,
+}
+
+#[derive(Clone, Debug)]
+pub struct AccountsStruct {
+    pub name: String,
+    pub file: PathBuf,
+    /// Its `Sysvar<'info, X>` accounts and accounts constrained to a sysvar address
+    pub reads: Vec<Read>,
+}
+
+#[derive(Debug, Default)]
+pub struct Facts {
+    /// Keyed by the location of the function's name
+    pub functions: HashMap<Location, FunctionFacts>,
+    pub structs: Vec<AccountsStruct>,
+}
+
+/// Add the functions and accounts structs of `file`, parsed from `path`, to `facts`
+pub fn add_file(path: &Path, file: &syn::File, facts: &mut Facts) {
+    FileVisitor { path, facts }.visit_file(file);
+}
+
+/// The sysvar a `sysvar::<module>` path goes into
+fn module_sysvar(segments: &[String]) -> Option<Sysvar> {
+    let position = segments.iter().position(|segment| segment == "sysvar")?;
+    segments.get(position + 1).and_then(|module| Sysvar::from_module(module))
+}
+
+/// The `X` of a `Sysvar<'info, X>` account
+fn sysvar_account(ty: &Type) -> Option<Sysvar> {
+    let Type::Path(path) = inner_type(ty) else { return None };
+    let segment = path.path.segments.last().filter(|segment| segment.ident == "Sysvar")?;
+    let syn::PathArguments::AngleBracketed(args) = &segment.arguments else { return None };
+    args.args.iter().find_map(|arg| match arg {
+        syn::GenericArgument::Type(ty) => type_name(ty).and_then(|name| Sysvar::from_type(&name)),
+        _ => None,
+    })
+}
+
+/// The sysvar an `#[account(address = sysvar::<module>::ID)]` constraint names
+fn address_sysvar(attrs: &[syn::Attribute]) -> Option<Sysvar> {
+    let (_, address) = keyed_constraints(attrs).into_iter().find(|(key, _)| key == "address")?;
+    let segments: Vec<String> = address.into_iter()
+        .filter_map(|tree| match tree {
+            TokenTree::Ident(ident) => Some(ident.to_string()),
+            _ => None,
+        })
+        .collect();
+    module_sysvar(&segments)
+}
+
+struct FileVisitor<'a> {
+    path: &'a Path,
+    facts: &'a mut Facts,
+}
+
+impl FileVisitor<'_> {
+    fn add_function(&mut self, sig: &syn::Signature, block: &syn::Block) {
+        let mut body = BodyVisitor { path: self.path, facts: FunctionFacts { context: context_accounts(sig), ..FunctionFacts::default() } };
+        body.visit_block(block);
+        self.facts.functions.insert(location(self.path, sig.ident.span()), body.facts);
+    }
+}
+
+impl<'ast> Visit<'ast> for FileVisitor<'_> {
+    fn visit_item_mod(&mut self, item: &'ast ItemMod) {
+        if !is_cfg_test(&item.attrs) {
+            visit::visit_item_mod(self, item);
+        }
+    }
+
+    fn visit_item_fn(&mut self, item: &'ast ItemFn) {
+        if !is_cfg_test(&item.attrs) {
+            self.add_function(&item.sig, &item.block);
+        }
+    }
+
+    fn visit_item_impl(&mut self, item: &'ast ItemImpl) {
+        if !is_cfg_test(&item.attrs) {
+            visit::visit_item_impl(self, item);
+        }
+    }
+
+    fn visit_impl_item_fn(&mut self, item: &'ast ImplItemFn) {
+        if !is_cfg_test(&item.attrs) {
+            self.add_function(&item.sig, &item.block);
+        }
+    }
+
+    fn visit_trait_item_fn(&mut self, item: &'ast TraitItemFn) {
+        if let Some(block) = &item.default {
+            self.add_function(&item.sig, block);
+        }
+    }
+
+    fn visit_item_struct(&mut self, item: &'ast ItemStruct) {
+        if is_cfg_test(&item.attrs) || !derives_accounts(&item.attrs) {
+            return;
+        }
+        let Fields::Named(fields) = &item.fields else { return };
+        let mut reads = Vec::new();
+        for field in &fields.named {
+            let Some(ident) = &field.ident else { continue };
+            let at = location(self.path, ident.span());
+            if let Some(sysvar) = sysvar_account(&field.ty) {
+                reads.push(Read::new(sysvar, Access::Account, format!("Sysvar<'info, {}>", sysvar.type_name()), at));
+            } else if let Some(sysvar) = address_sysvar(&field.attrs) {
+                reads.push(Read::new(sysvar, Access::Account, format!("address = sysvar::{}::ID", sysvar.module()), at));
+            }
+        }
+        self.facts.structs.push(AccountsStruct { name: item.ident.to_string(), file: self.path.to_path_buf(), reads });
+    }
+}
+
+/// The sysvar reads of one function body, leaving out the items declared in it
+struct BodyVisitor<'a> {
+    path: &'a Path,
+    facts: FunctionFacts,
+}
+
+impl<'ast> Visit<'ast> for BodyVisitor<'_> {
+    fn visit_item(&mut self, _: &'ast syn::Item) {}
+
+    fn visit_expr_call(&mut self, call: &'ast syn::ExprCall) {
+        let Expr::Path(path) = &*call.func else {
+            return visit::visit_expr_call(self, call);
+        };
+        let segments: Vec<String> = path.path.segments.iter().map(|segment| segment.ident.to_string()).collect();
+        let name = segments.last().map(String::as_str).unwrap_or_default();
+        let owner = segments.len().checked_sub(2).and_then(|index| Sysvar::from_type(&segments[index]));
+        let at = location(self.path, path.span());
+        let read = match (owner, name) {
+            (Some(sysvar), "get") if call.args.is_empty() => {
+                Read::new(sysvar, Access::Syscall, format!("{}::get", sysvar.type_name()), at)
+            }
+            (Some(sysvar), "from_account_info") => {
+                Read::new(sysvar, Access::Account, format!("{}::from_account_info", sysvar.type_name()), at)
+            }
+            _ if INSTRUCTIONS_FUNCTIONS.contains(&name) => {
+                let mut read = Read::new(Sysvar::Instructions, Access::Account, name.to_string(), at);
+                if UNCHECKED_FUNCTIONS.contains(&name) {
+                    read.pattern = Some(Pattern::UncheckedLoad);
+                }
+                read
+            }
+            _ => return visit::visit_expr_call(self, call),
+        };
+        self.facts.reads.push(read);
+        // The path itself is not another read
+        for arg in &call.args {
+            self.visit_expr(arg);
+        }
+    }
+
+    fn visit_expr_path(&mut self, path: &'ast syn::ExprPath) {
+        let segments: Vec<String> = path.path.segments.iter().map(|segment| segment.ident.to_string()).collect();
+        if let Some(sysvar) = module_sysvar(&segments) {
+            let position = segments.iter().position(|segment| segment == "sysvar").unwrap_or_default();
+            let at = location(self.path, path.span());
+            self.facts.reads.push(Read::new(sysvar, Access::Account, segments[position..].join("::"), at));
+        }
+    }
+
+    fn visit_macro(&mut self, mac: &'ast syn::Macro) {
+        if let Ok(args) = mac.parse_body_with(Punctuated::<Expr, syn::Token![,]>::parse_terminated) {
+            for arg in &args {
+                self.visit_expr(arg);
+            }
+        }
+    }
+}
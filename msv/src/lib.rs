@@ -0,0 +1,315 @@
+//! Sysvar usage: which sysvars each instruction handler reads, whether through the
+//! `sol_get_*_sysvar` syscalls or through an account, and the deprecated patterns.
+//!
+//! Sysvars are a program's dependency on the runtime: the time it trusts (Clock), the
+//! rent it charges, the instructions around it (Instructions). Reading one through an
+//! account costs an account slot and makes the caller pass the right one; the Fees and
+//! RecentBlockhashes sysvars are deprecated; and the unchecked `load_instruction_at`
+//! and `load_current_index` read whatever account they are given, which is how a fake
+//! Instructions account got past signature verification in the Wormhole exploit. See
+//! [`facts`] for what counts as a read.
+//!
+//! Handlers and the functions they call come from [`mcd`]; a handler reads what its
+//! own body, the functions it calls and its accounts struct read. A native program's
+//! entrypoint is one handler, so its reads are those of every instruction.
+
+pub mod facts;
+
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::fmt;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use mcd::FunctionId;
+use serde::Serialize;
+pub use trr_core::Location;
+use trr_core::{source, walk};
+
+use facts::{AccountsStruct, Facts, FunctionFacts, Read};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+pub enum Sysvar {
+    Clock,
+    Rent,
+    EpochSchedule,
+    EpochRewards,
+    LastRestartSlot,
+    Instructions,
+    SlotHashes,
+    SlotHistory,
+    StakeHistory,
+    Fees,
+    RecentBlockhashes,
+}
+
+impl Sysvar {
+    pub const ALL: [Sysvar; 11] = [
+        Sysvar::Clock, Sysvar::Rent, Sysvar::EpochSchedule, Sysvar::EpochRewards, Sysvar::LastRestartSlot,
+        Sysvar::Instructions, Sysvar::SlotHashes, Sysvar::SlotHistory, Sysvar::StakeHistory, Sysvar::Fees,
+        Sysvar::RecentBlockhashes,
+    ];
+
+    /// The type, as in `Clock::get()`
+    pub fn type_name(self) -> &'static str {
+        match self {
+            Sysvar::Clock => "Clock",
+            Sysvar::Rent => "Rent",
+            Sysvar::EpochSchedule => "EpochSchedule",
+            Sysvar::EpochRewards => "EpochRewards",
+            Sysvar::LastRestartSlot => "LastRestartSlot",
+            Sysvar::Instructions => "Instructions",
+            Sysvar::SlotHashes => "SlotHashes",
+            Sysvar::SlotHistory => "SlotHistory",
+            Sysvar::StakeHistory => "StakeHistory",
+            Sysvar::Fees => "Fees",
+            Sysvar::RecentBlockhashes => "RecentBlockhashes",
+        }
+    }
+
+    /// The module, as in `sysvar::clock::ID`
+    pub fn module(self) -> &'static str {
+        match self {
+            Sysvar::Clock => "clock",
+            Sysvar::Rent => "rent",
+            Sysvar::EpochSchedule => "epoch_schedule",
+            Sysvar::EpochRewards => "epoch_rewards",
+            Sysvar::LastRestartSlot => "last_restart_slot",
+            Sysvar::Instructions => "instructions",
+            Sysvar::SlotHashes => "slot_hashes",
+            Sysvar::SlotHistory => "slot_history",
+            Sysvar::StakeHistory => "stake_history",
+            Sysvar::Fees => "fees",
+            Sysvar::RecentBlockhashes => "recent_blockhashes",
+        }
+    }
+
+    pub fn from_type(name: &str) -> Option<Sysvar> {
+        Sysvar::ALL.into_iter().find(|sysvar| sysvar.type_name() == name)
+    }
+
+    pub fn from_module(module: &str) -> Option<Sysvar> {
+        Sysvar::ALL.into_iter().find(|sysvar| sysvar.module() == module)
+    }
+
+    /// Whether `X::get()` reads it without an account
+    pub fn has_syscall(self) -> bool {
+        matches!(self, Sysvar::Clock | Sysvar::Rent | Sysvar::EpochSchedule | Sysvar::EpochRewards | Sysvar::LastRestartSlot | Sysvar::Fees)
+    }
+
+    pub fn deprecated(self) -> bool {
+        matches!(self, Sysvar::Fees | Sysvar::RecentBlockhashes)
+    }
+}
+
+impl fmt::Display for Sysvar {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.pad(self.type_name())
+    }
+}
+
+/// How a sysvar is read
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Access {
+    Syscall,
+    Account,
+}
+
+impl fmt::Display for Access {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.pad(match self {
+            Access::Syscall => "syscall",
+            Access::Account => "account",
+        })
+    }
+}
+
+/// A deprecated sysvar pattern
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Pattern {
+    /// Fees or RecentBlockhashes
+    DeprecatedSysvar,
+    /// `load_instruction_at` or `load_current_index`, which do not check the account
+    UncheckedLoad,
+    /// An account for a sysvar `X::get()` reads
+    AccountForSyscall,
+}
+
+impl fmt::Display for Pattern {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.pad(match self {
+            Pattern::DeprecatedSysvar => "deprecated sysvar",
+            Pattern::UncheckedLoad => "unchecked Instructions load",
+            Pattern::AccountForSyscall => "account where a syscall would do",
+        })
+    }
+}
+
+/// A sysvar a handler reads, and how
+#[derive(Clone, Debug, Serialize)]
+pub struct Usage {
+    pub sysvar: Sysvar,
+    pub access: Vec<Access>,
+}
+
+/// A read a handler reaches
+#[derive(Clone, Debug, Serialize)]
+pub struct HandlerRead {
+    #[serde(flatten)]
+    pub read: Read,
+    /// Path of the function it is in, or the accounts struct
+    pub function: String,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct HandlerSysvars {
+    pub program: String,
+    pub handler: String,
+    pub location: Location,
+    /// By sysvar
+    pub sysvars: Vec<Usage>,
+    /// By file and line
+    pub reads: Vec<HandlerRead>,
+}
+
+/// A read with a deprecated pattern, once however many handlers reach it
+#[derive(Clone, Debug, Serialize)]
+pub struct Flagged {
+    #[serde(flatten)]
+    pub read: HandlerRead,
+    pub program: String,
+    pub handlers: Vec<String>,
+}
+
+/// How many handlers read a sysvar, and how
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct SysvarCount {
+    pub handlers: usize,
+    pub syscall: usize,
+    pub account: usize,
+}
+
+#[derive(Debug, Default)]
+pub struct Check {
+    /// Every handler, by program then handler
+    pub handlers: Vec<HandlerSysvars>,
+    /// By program, then file and line
+    pub flagged: Vec<Flagged>,
+}
+
+impl Check {
+    pub fn reading(&self) -> impl Iterator<Item = &HandlerSysvars> {
+        self.handlers.iter().filter(|handler| !handler.sysvars.is_empty())
+    }
+
+    /// The handlers reading each sysvar, through a syscall and through an account
+    pub fn by_sysvar(&self) -> BTreeMap<Sysvar, SysvarCount> {
+        let mut counts: BTreeMap<Sysvar, SysvarCount> = BTreeMap::new();
+        for usage in self.handlers.iter().flat_map(|handler| &handler.sysvars) {
+            let count = counts.entry(usage.sysvar).or_default();
+            count.handlers += 1;
+            count.syscall += usize::from(usage.access.contains(&Access::Syscall));
+            count.account += usize::from(usage.access.contains(&Access::Account));
+        }
+        counts
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Analysis {
+    pub programs: mcd::Analysis,
+    pub facts: Facts,
+    /// Files that could not be read or parsed
+    pub errors: Vec<(PathBuf, String)>,
+}
+
+impl Analysis {
+    fn function_facts(&self, (program, function): FunctionId) -> Option<&FunctionFacts> {
+        self.facts.functions.get(&self.programs.programs[program].functions[function].location)
+    }
+
+    /// The functions reachable from `start`, itself included
+    fn reachable(&self, start: FunctionId) -> HashSet<FunctionId> {
+        let mut reached = HashSet::from([start]);
+        let mut stack = vec![start];
+        while let Some(id) = stack.pop() {
+            for call in &self.programs.programs[id.0].functions[id.1].calls {
+                for callee in self.programs.callees(id.0, call) {
+                    if reached.insert(callee) {
+                        stack.push(callee);
+                    }
+                }
+            }
+        }
+        reached
+    }
+
+    /// The accounts struct named `name`, preferably in `program`'s own crate
+    fn accounts_struct(&self, program: usize, name: &str) -> Option<&AccountsStruct> {
+        let root = &self.programs.programs[program].root;
+        let mut named = self.facts.structs.iter().filter(|accounts| accounts.name == name);
+        named.clone().find(|accounts| accounts.file.starts_with(root)).or_else(|| named.next())
+    }
+
+    /// The sysvars of every handler, and the reads with a deprecated pattern
+    pub fn check(&self) -> Check {
+        let mut check = Check::default();
+        let mut flagged: HashMap<Location, Flagged> = HashMap::new();
+        for (index, program) in self.programs.programs.iter().enumerate() {
+            for handler in &program.handlers {
+                let mut reads: Vec<HandlerRead> = Vec::new();
+                for id in self.reachable((index, handler.function)) {
+                    let Some(facts) = self.function_facts(id) else { continue };
+                    let function = program.functions[id.1].path.join("::");
+                    reads.extend(facts.reads.iter().map(|read| HandlerRead { read: read.clone(), function: function.clone() }));
+                }
+                let accounts = self.function_facts((index, handler.function))
+                    .and_then(|facts| facts.context.as_deref())
+                    .and_then(|name| self.accounts_struct(index, name));
+                if let Some(accounts) = accounts {
+                    reads.extend(accounts.reads.iter().map(|read| HandlerRead { read: read.clone(), function: accounts.name.clone() }));
+                }
+                reads.sort_by(|a, b| a.read.location.file.cmp(&b.read.location.file)
+                    .then_with(|| a.read.location.line.cmp(&b.read.location.line))
+                    .then_with(|| a.read.location.column.cmp(&b.read.location.column)));
+
+                let mut sysvars: BTreeMap<Sysvar, BTreeSet<Access>> = BTreeMap::new();
+                for read in &reads {
+                    sysvars.entry(read.read.sysvar).or_default().insert(read.read.access);
+                    if read.read.pattern.is_some() {
+                        flagged.entry(read.read.location.clone())
+                            .or_insert_with(|| Flagged { read: read.clone(), program: program.name.clone(), handlers: Vec::new() })
+                            .handlers.push(handler.name.clone());
+                    }
+                }
+                check.handlers.push(HandlerSysvars {
+                    program: program.name.clone(),
+                    handler: handler.name.clone(),
+                    location: handler.location.clone(),
+                    sysvars: sysvars.into_iter().map(|(sysvar, access)| Usage { sysvar, access: access.into_iter().collect() }).collect(),
+                    reads,
+                });
+            }
+        }
+        check.handlers.sort_by(|a, b| a.program.cmp(&b.program).then_with(|| a.handler.cmp(&b.handler)));
+        check.flagged = flagged.into_values().collect();
+        for flagged in &mut check.flagged {
+            flagged.handlers.sort();
+        }
+        check.flagged.sort_by(|a, b| a.program.cmp(&b.program)
+            .then_with(|| a.read.read.location.file.cmp(&b.read.read.location.file))
+            .then_with(|| a.read.read.location.line.cmp(&b.read.read.location.line)));
+        check
+    }
+}
+
+/// Analyze every Rust file under `dir` (or `dir` itself, if it is a file), leaving out
+/// `target/` and test code
+pub fn analyze(dir: &Path) -> io::Result<Analysis> {
+    let programs = mcd::analyze(dir)?;
+    let mut facts = Facts::default();
+    let mut errors = programs.errors.clone();
+    source::parse_files(walk::program_files(dir)?, &mut errors, |path, _, file| facts::add_file(&path, &file, &mut facts));
+    Ok(Analysis { programs, facts, errors })
+}
@@ -0,0 +1,219 @@
+mod report;
+mod sarif;
+
+use std::collections::BTreeMap;
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::process;
+
+use clap::Parser;
+use serde::Serialize;
+use trr_core::git::Repository;
+use trr_core::output::{self, Format};
+
+use msv::{Analysis, Check, Flagged, HandlerSysvars, Pattern, Sysvar, SysvarCount, Usage};
+
+#[derive(Parser)]
+#[clap(author, version, about = "Sysvar Usage (MSV) Report")]
+#[clap(after_help = "Examples:
+  msv programs/vault
+  msv -v programs
+  msv --format markdown programs > sysvars.md
+  msv --fail-on-deprecated programs")]
+struct Args {
+    /// Directory to analyze (relative to --repo when given)
+    #[clap(value_name = "DIR", default_value = ".")]
+    dir: PathBuf,
+
+    /// Git repository URL to clone, or path of a local checkout
+    #[clap(long, value_name = "REPO_URL_OR_PATH")]
+    repo: Option<String>,
+
+    /// Branch, tag or commit of --repo to analyze
+    #[clap(long, value_name = "REV", requires = "repo")]
+    rev: Option<String>,
+
+    /// Output format
+    #[clap(long, value_enum, default_value = "text")]
+    format: Format,
+
+    /// List every read of each handler, and the handlers reading no sysvar
+    #[clap(short, long)]
+    verbose: bool,
+
+    /// Exit with status 1 if any read follows a deprecated pattern
+    #[clap(long)]
+    fail_on_deprecated: bool,
+}
+
+/// Structured form of the analysis for `--format json`
+#[derive(Serialize)]
+struct JsonReport<'a> {
+    summary: Summary,
+    /// By program, then handler
+    handlers: &'a [HandlerSysvars],
+    /// The reads with a deprecated pattern, by program, then file and line
+    deprecated: &'a [Flagged],
+    errors: Vec<FileError>,
+}
+
+#[derive(Serialize)]
+struct Summary {
+    programs: usize,
+    handlers: usize,
+    handlers_reading: usize,
+    /// The handlers reading each sysvar
+    sysvars: BTreeMap<Sysvar, SysvarCount>,
+    deprecated: usize,
+    deprecated_sysvars: usize,
+    unchecked_loads: usize,
+    account_for_syscall: usize,
+}
+
+#[derive(Serialize)]
+struct FileError {
+    file: PathBuf,
+    error: String,
+}
+
+fn usages(sysvars: &[Usage]) -> String {
+    let usages: Vec<String> = sysvars.iter()
+        .map(|usage| {
+            let access: Vec<String> = usage.access.iter().map(ToString::to_string).collect();
+            format!("{} ({})", usage.sysvar, access.join(", "))
+        })
+        .collect();
+    usages.join(", ")
+}
+
+fn count(check: &Check, pattern: Pattern) -> usize {
+    check.flagged.iter().filter(|flagged| flagged.read.read.pattern == Some(pattern)).count()
+}
+
+fn print_text(analysis: &Analysis, check: &Check, verbose: bool) {
+    println!("\nSysvars by instruction handler:");
+    for handler in &check.handlers {
+        if handler.sysvars.is_empty() {
+            if verbose {
+                println!("  {}::{}: none", handler.program, handler.handler);
+            }
+            continue;
+        }
+        println!("  {}::{}: {}", handler.program, handler.handler, usages(&handler.sysvars));
+        if verbose {
+            for read in &handler.reads {
+                println!("    {} ({}) in {} at {}", read.read.call, read.read.access, read.function, read.read.location);
+            }
+        }
+    }
+
+    if !check.flagged.is_empty() {
+        println!("\nDeprecated sysvar patterns:");
+    }
+    for flagged in &check.flagged {
+        let read = &flagged.read;
+        println!(
+            "  {} in {}::{} at {}: {}, in {}",
+            read.read.call, flagged.program, read.function, read.read.location, read.read.pattern.map(|pattern| pattern.to_string()).unwrap_or_default(),
+            flagged.handlers.join(", ")
+        );
+    }
+
+    let by_sysvar = check.by_sysvar();
+    if !by_sysvar.is_empty() {
+        println!("\nSysvars read:");
+    }
+    for (sysvar, count) in &by_sysvar {
+        println!("  {}: {} handler(s) ({} through a syscall, {} through an account)", sysvar, count.handlers, count.syscall, count.account);
+    }
+
+    println!("\nSummary:");
+    println!("Programs: {}", analysis.programs.programs.len());
+    println!("Handlers reading sysvars: {} of {}", check.reading().count(), check.handlers.len());
+    println!(
+        "Deprecated patterns: {} (deprecated sysvars {}, unchecked loads {}, accounts for syscalls {})",
+        check.flagged.len(),
+        count(check, Pattern::DeprecatedSysvar),
+        count(check, Pattern::UncheckedLoad),
+        count(check, Pattern::AccountForSyscall)
+    );
+}
+
+fn write_json(out: &mut dyn Write, analysis: &Analysis, check: &Check) -> io::Result<()> {
+    let report = JsonReport {
+        summary: Summary {
+            programs: analysis.programs.programs.len(),
+            handlers: check.handlers.len(),
+            handlers_reading: check.reading().count(),
+            sysvars: check.by_sysvar(),
+            deprecated: check.flagged.len(),
+            deprecated_sysvars: count(check, Pattern::DeprecatedSysvar),
+            unchecked_loads: count(check, Pattern::UncheckedLoad),
+            account_for_syscall: count(check, Pattern::AccountForSyscall),
+        },
+        handlers: &check.handlers,
+        deprecated: &check.flagged,
+        errors: analysis.errors.iter()
+            .map(|(file, error)| FileError { file: file.clone(), error: error.clone() })
+            .collect(),
+    };
+    output::write_json(out, "msv", &report)
+}
+
+/// With `--fail-on-deprecated`, list the reads with a deprecated pattern, if any, and
+/// exit with status 1
+fn enforce_deprecated(fail: bool, check: &Check) {
+    if !fail || check.flagged.is_empty() {
+        return;
+    }
+    let header = format!("{} read(s) with a deprecated sysvar pattern", check.flagged.len());
+    output::fail(&header, check.flagged.iter().map(|flagged| {
+        let pattern = flagged.read.read.pattern.map(|pattern| pattern.to_string()).unwrap_or_default();
+        format!("{} ({}) at {}: {}", flagged.read.read.call, flagged.read.read.sysvar, flagged.read.read.location, pattern)
+    }));
+}
+
+fn run(args: &Args) -> Result<(), Box<dyn std::error::Error>> {
+    let repository = args.repo.as_deref().map(|repo| Repository::open(repo, args.rev.as_deref())).transpose()?;
+    let dir = match &repository {
+        Some(repository) => repository.subdir(&args.dir)?,
+        None => args.dir.clone(),
+    };
+    if !dir.exists() {
+        return Err(format!("Path '{}' does not exist", dir.display()).into());
+    }
+    if args.format == Format::Text {
+        println!("Analyzing: {}", dir.display());
+    }
+
+    let analysis = msv::analyze(&dir)?;
+    for (file, error) in &analysis.errors {
+        eprintln!("Error analyzing {}: {}", file.display(), error);
+    }
+    let check = analysis.check();
+
+    let mut out = io::stdout().lock();
+    match args.format {
+        Format::Text => print_text(&analysis, &check, args.verbose),
+        Format::Json => write_json(&mut out, &analysis, &check)?,
+        Format::Csv => report::write_csv(&mut out, &check)?,
+        Format::Markdown => report::write_markdown(&mut out, &dir, &check)?,
+        Format::Sarif => {
+            // Artifact URIs are relative to the repository root, or to where msv runs
+            let base = repository.as_ref().map_or_else(|| PathBuf::from("."), |repository| repository.root().to_path_buf());
+            sarif::write_sarif(&mut out, &check, &base)?;
+            writeln!(out)?;
+        }
+    }
+    drop(out);
+    enforce_deprecated(args.fail_on_deprecated, &check);
+    Ok(())
+}
+
+fn main() {
+    let args = Args::parse();
+    if let Err(e) = run(&args) {
+        eprintln!("Error: {}", e);
+        process::exit(1);
+    }
+}
@@ -0,0 +1,81 @@
+//! `--format csv`, one row per read a handler reaches, and `--format markdown`, a
+//! handler-by-sysvar table and the deprecated patterns.
+
+use std::io::{self, Write};
+use std::path::Path;
+
+use trr_core::output::{markdown_cell, markdown_code, write_csv_row};
+
+use msv::{Check, Sysvar};
+
+pub fn write_csv(out: &mut dyn Write, check: &Check) -> io::Result<()> {
+    write_csv_row(out, &["program", "handler", "function", "sysvar", "access", "call", "pattern", "file", "line"])?;
+    for handler in &check.handlers {
+        for read in &handler.reads {
+            write_csv_row(out, &[
+                &handler.program,
+                &handler.handler,
+                &read.function,
+                read.read.sysvar.type_name(),
+                &read.read.access.to_string(),
+                &read.read.call,
+                &read.read.pattern.map(|pattern| pattern.to_string()).unwrap_or_default(),
+                &read.read.location.file.display().to_string(),
+                &read.read.location.line.to_string(),
+            ])?;
+        }
+    }
+    Ok(())
+}
+
+pub fn write_markdown(out: &mut dyn Write, target: &Path, check: &Check) -> io::Result<()> {
+    writeln!(out, "# Sysvar Usage\n")?;
+    writeln!(out, "Target: {}\n", markdown_code(&target.display().to_string()))?;
+    writeln!(out, "## Summary\n")?;
+    writeln!(out, "| Metric | Value |")?;
+    writeln!(out, "| --- | ---: |")?;
+    writeln!(out, "| Instruction handlers | {} |", check.handlers.len())?;
+    writeln!(out, "| Handlers reading sysvars | {} |", check.reading().count())?;
+    writeln!(out, "| Deprecated patterns | {} |", check.flagged.len())?;
+
+    // A column per sysvar read, a row per handler reading one
+    let sysvars: Vec<Sysvar> = check.by_sysvar().into_keys().collect();
+    if !sysvars.is_empty() {
+        writeln!(out, "\n## Sysvars by Handler\n")?;
+        let names: Vec<&str> = sysvars.iter().map(|sysvar| sysvar.type_name()).collect();
+        writeln!(out, "| Handler | {} |", names.join(" | "))?;
+        writeln!(out, "| --- |{}", " --- |".repeat(sysvars.len()))?;
+        for handler in check.reading() {
+            let cells: Vec<String> = sysvars.iter()
+                .map(|sysvar| {
+                    handler.sysvars.iter()
+                        .find(|usage| usage.sysvar == *sysvar)
+                        .map(|usage| usage.access.iter().map(ToString::to_string).collect::<Vec<_>>().join(", "))
+                        .unwrap_or_default()
+                })
+                .collect();
+            writeln!(out, "| {} | {} |", markdown_code(&format!("{}::{}", handler.program, handler.handler)), cells.join(" | "))?;
+        }
+    }
+
+    if check.flagged.is_empty() {
+        return Ok(());
+    }
+    writeln!(out, "\n## Deprecated Patterns\n")?;
+    writeln!(out, "| Read | Sysvar | Pattern | Function | Handlers | Location |")?;
+    writeln!(out, "| --- | --- | --- | --- | --- | --- |")?;
+    for flagged in &check.flagged {
+        let read = &flagged.read;
+        writeln!(
+            out,
+            "| {} | {} | {} | {} | {} | {} |",
+            markdown_code(&read.read.call),
+            read.read.sysvar,
+            read.read.pattern.map(|pattern| pattern.to_string()).unwrap_or_default(),
+            markdown_code(&format!("{}::{}", flagged.program, read.function)),
+            markdown_cell(&flagged.handlers.join(", ")),
+            markdown_code(&read.read.location.to_string()),
+        )?;
+    }
+    Ok(())
+}
@@ -0,0 +1,79 @@
+//! SARIF 2.1.0 output: a result per read with a deprecated pattern, under
+//! `deprecated-sysvar`, `unchecked-instructions-load` or `sysvar-account`.
+
+use std::io::{self, Write};
+use std::path::Path;
+
+use serde_json::json;
+use trr_core::sarif::{location, write_log};
+
+use msv::{Check, Pattern};
+
+fn rule_id(pattern: Pattern) -> &'static str {
+    match pattern {
+        Pattern::DeprecatedSysvar => "deprecated-sysvar",
+        Pattern::UncheckedLoad => "unchecked-instructions-load",
+        Pattern::AccountForSyscall => "sysvar-account",
+    }
+}
+
+/// Unchecked loads are how fake Instructions accounts get through; an account for a
+/// syscall only costs a slot
+fn level(pattern: Pattern) -> &'static str {
+    match pattern {
+        Pattern::DeprecatedSysvar => "warning",
+        Pattern::UncheckedLoad => "error",
+        Pattern::AccountForSyscall => "note",
+    }
+}
+
+pub fn write_sarif(out: &mut dyn Write, check: &Check, base: &Path) -> io::Result<()> {
+    let mut results: Vec<_> = Vec::new();
+    for flagged in &check.flagged {
+        let read = &flagged.read;
+        let Some(pattern) = read.read.pattern else { continue };
+        results.push(json!({
+            "ruleId": rule_id(pattern),
+            "level": level(pattern),
+            "message": {
+                "text": format!(
+                    "{} reads {} through {} in {}::{} ({}): {}",
+                    read.read.call, read.read.sysvar, read.read.access, flagged.program, read.function, flagged.handlers.join(", "), pattern
+                ),
+            },
+            "locations": [location(&read.read.location, base)],
+            "properties": { "sysvar": read.read.sysvar, "access": read.read.access },
+        }));
+    }
+
+    let rules = vec![
+        json!({
+            "id": rule_id(Pattern::DeprecatedSysvar),
+            "name": "DeprecatedSysvar",
+            "shortDescription": { "text": "Deprecated sysvar" },
+            "fullDescription": {
+                "text": "The Fees and RecentBlockhashes sysvars are deprecated and may be removed. Compute fees with the fee calculator of the RPC, and use SlotHashes or a durable nonce for recent blockhashes.",
+            },
+            "defaultConfiguration": { "level": level(Pattern::DeprecatedSysvar) },
+        }),
+        json!({
+            "id": rule_id(Pattern::UncheckedLoad),
+            "name": "UncheckedInstructionsLoad",
+            "shortDescription": { "text": "Instructions sysvar read without checking the account" },
+            "fullDescription": {
+                "text": "load_instruction_at and load_current_index read whatever account they are given as the Instructions sysvar, so a caller can pass a fake one. Use load_instruction_at_checked and load_current_index_checked.",
+            },
+            "defaultConfiguration": { "level": level(Pattern::UncheckedLoad) },
+        }),
+        json!({
+            "id": rule_id(Pattern::AccountForSyscall),
+            "name": "SysvarAccount",
+            "shortDescription": { "text": "Sysvar read through an account where a syscall would do" },
+            "fullDescription": {
+                "text": "Clock, Rent, EpochSchedule, EpochRewards and LastRestartSlot can be read with X::get(), without the caller passing the account. The account costs an account slot and a key check.",
+            },
+            "defaultConfiguration": { "level": level(Pattern::AccountForSyscall) },
+        }),
+    ];
+    write_log(out, "msv", env!("CARGO_PKG_VERSION"), rules, results)
+}
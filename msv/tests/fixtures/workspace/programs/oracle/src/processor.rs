@@ -0,0 +1,41 @@
+use solana_program::account_info::{next_account_info, AccountInfo};
+use solana_program::clock::Clock;
+use solana_program::entrypoint::ProgramResult;
+use solana_program::program_error::ProgramError;
+use solana_program::pubkey::Pubkey;
+use solana_program::sysvar::{self, instructions, Sysvar};
+
+pub fn process(_program_id: &Pubkey, accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
+    match data.first() {
+        Some(0) => update(accounts),
+        _ => check_caller(accounts),
+    }
+}
+
+// Clock from an account the caller has to pass
+fn update(accounts: &[AccountInfo]) -> ProgramResult {
+    let accounts = &mut accounts.iter();
+    let clock = Clock::from_account_info(next_account_info(accounts)?)?;
+    let hashes = next_account_info(accounts)?;
+    if !sysvar::slot_hashes::check_id(hashes.key) {
+        return Err(ProgramError::InvalidArgument);
+    }
+    let _ = clock.slot;
+    Ok(())
+}
+
+// Reads whatever account it is given as the Instructions sysvar
+fn check_caller(accounts: &[AccountInfo]) -> ProgramResult {
+    let ixs = next_account_info(&mut accounts.iter())?;
+    let current = instructions::load_current_index(&ixs.try_borrow_data()?);
+    let _ = instructions::load_instruction_at(current as usize, &ixs.try_borrow_data()?);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn clock() {
+        let _ = solana_program::clock::Clock::get();
+    }
+}
@@ -0,0 +1,67 @@
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::sysvar;
+use anchor_lang::solana_program::sysvar::instructions::load_instruction_at_checked;
+
+#[program]
+pub mod staking {
+    use super::*;
+
+    // Clock through the syscall, Rent through an account it does not need
+    pub fn stake(ctx: Context<Stake>, amount: u64) -> Result<()> {
+        let now = Clock::get()?.unix_timestamp;
+        ctx.accounts.position.amount = amount;
+        ctx.accounts.position.since = now;
+        Ok(())
+    }
+
+    // The instruction before must be the ed25519 program's
+    pub fn verify(ctx: Context<Verify>) -> Result<()> {
+        let previous = load_instruction_at_checked(0, &ctx.accounts.instructions)?;
+        require_keys_eq!(previous.program_id, ed25519_program::ID);
+        Ok(())
+    }
+
+    pub fn claim(ctx: Context<Stake>) -> Result<()> {
+        let epoch = epoch_of(Clock::get()?.slot)?;
+        let fees = Fees::get()?;
+        ctx.accounts.position.claimed = epoch + fees.fee_calculator.lamports_per_signature;
+        Ok(())
+    }
+
+    pub fn close(_ctx: Context<Close>) -> Result<()> {
+        Ok(())
+    }
+}
+
+fn epoch_of(slot: u64) -> Result<u64> {
+    Ok(EpochSchedule::get()?.get_epoch(slot))
+}
+
+#[derive(Accounts)]
+pub struct Stake<'info> {
+    #[account(mut)]
+    pub position: Account<'info, Position>,
+    pub rent: Sysvar<'info, Rent>,
+}
+
+#[derive(Accounts)]
+pub struct Verify<'info> {
+    /// CHECK: the address constraint makes it the Instructions sysvar
+    #[account(address = sysvar::instructions::ID)]
+    pub instructions: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct Close<'info> {
+    #[account(mut, close = owner)]
+    pub position: Account<'info, Position>,
+    #[account(mut)]
+    pub owner: Signer<'info>,
+}
+
+#[account]
+pub struct Position {
+    pub amount: u64,
+    pub since: i64,
+    pub claimed: u64,
+}
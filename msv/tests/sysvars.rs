@@ -0,0 +1,91 @@
+// Reports on tests/fixtures/workspace: an Anchor program reading Clock, EpochSchedule
+// and the deprecated Fees through syscalls, Rent and Instructions through accounts, and
+// a native program reading Clock, SlotHashes and Instructions through accounts, with
+// the unchecked Instructions loads.
+
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use msv::{Access, Check, Pattern, Sysvar};
+use serde_json::Value;
+
+fn fixture() -> PathBuf {
+    Path::new(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/workspace")).to_path_buf()
+}
+
+fn check() -> Check {
+    let analysis = msv::analyze(&fixture()).unwrap();
+    assert!(analysis.errors.is_empty(), "{:?}", analysis.errors);
+    analysis.check()
+}
+
+/// A handler's sysvars and how it reads each
+type Usages<'a> = Vec<(Sysvar, &'a [Access])>;
+
+#[test]
+fn sysvars_per_handler() {
+    let check = check();
+    let handlers: Vec<(&str, Usages)> = check.handlers.iter()
+        .map(|handler| {
+            let sysvars = handler.sysvars.iter().map(|usage| (usage.sysvar, usage.access.as_slice())).collect();
+            (handler.handler.as_str(), sysvars)
+        })
+        .collect();
+    let (syscall, account): (&[Access], &[Access]) = (&[Access::Syscall], &[Access::Account]);
+    assert_eq!(handlers, [
+        ("process_instruction", vec![(Sysvar::Clock, account), (Sysvar::Instructions, account), (Sysvar::SlotHashes, account)]),
+        ("claim", vec![(Sysvar::Clock, syscall), (Sysvar::Rent, account), (Sysvar::EpochSchedule, syscall), (Sysvar::Fees, syscall)]),
+        ("close", vec![]),
+        ("stake", vec![(Sysvar::Clock, syscall), (Sysvar::Rent, account)]),
+        ("verify", vec![(Sysvar::Instructions, account)]),
+    ]);
+
+    // The helper's read counts for its caller, the accounts struct's for its handlers
+    let claim = &check.handlers[1];
+    assert!(claim.reads.iter().any(|read| read.function == "epoch_of" && read.read.call == "EpochSchedule::get"));
+    assert!(claim.reads.iter().any(|read| read.function == "Stake" && read.read.call == "Sysvar<'info, Rent>"));
+
+    let clock = &check.by_sysvar()[&Sysvar::Clock];
+    assert_eq!((clock.handlers, clock.syscall, clock.account), (3, 2, 1));
+}
+
+#[test]
+fn deprecated_patterns() {
+    let check = check();
+    let flagged: Vec<(&str, Option<Pattern>, &[String])> = check.flagged.iter()
+        .map(|flagged| (flagged.read.read.call.as_str(), flagged.read.read.pattern, flagged.handlers.as_slice()))
+        .collect();
+    let native = &["process_instruction".to_string()][..];
+    assert_eq!(flagged, [
+        ("Clock::from_account_info", Some(Pattern::AccountForSyscall), native),
+        ("load_current_index", Some(Pattern::UncheckedLoad), native),
+        ("load_instruction_at", Some(Pattern::UncheckedLoad), native),
+        ("Fees::get", Some(Pattern::DeprecatedSysvar), &["claim".to_string()][..]),
+        ("Sysvar<'info, Rent>", Some(Pattern::AccountForSyscall), &["claim".to_string(), "stake".to_string()][..]),
+    ]);
+}
+
+#[test]
+fn json_and_fail_on_deprecated() {
+    let run = |args: &[&str]| Command::new(env!("CARGO_BIN_EXE_msv"))
+        .args(args)
+        .arg(fixture())
+        .output()
+        .expect("failed to run msv");
+
+    let output = run(&["--format", "json"]);
+    assert!(output.status.success());
+    let report: Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(report["tool"], "msv");
+    assert_eq!(report["summary"]["handlers"], 5);
+    assert_eq!(report["summary"]["handlers_reading"], 4);
+    assert_eq!(report["summary"]["sysvars"]["Instructions"]["account"], 2);
+    assert_eq!(report["summary"]["deprecated"], 5);
+    assert_eq!(report["summary"]["unchecked_loads"], 2);
+    assert_eq!(report["handlers"][4]["reads"][1]["call"], "address = sysvar::instructions::ID");
+    assert_eq!(report["deprecated"][1]["pattern"], "unchecked-load");
+
+    let output = run(&["--fail-on-deprecated"]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("load_instruction_at (Instructions)"));
+}
@@ -0,0 +1,84 @@
+//! Baselines (`--write-baseline`, `--baseline`) and `mtd diff`: the depth of every
+//! type at an earlier point, so that established hierarchies stop tripping
+//! `--fail-above` and only newly introduced depth is reported.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use mtd::TraitAnalyzer;
+
+#[derive(Serialize, Deserialize)]
+pub struct Baseline {
+    /// Type name -> maximum trait depth
+    pub type_depths: BTreeMap<String, usize>,
+}
+
+/// A type whose depth differs between a baseline and the current analysis
+#[derive(Serialize)]
+pub struct DepthChange {
+    pub type_name: String,
+    /// `None` for a type that is new since the baseline
+    pub old_depth: Option<usize>,
+    /// `None` for a type that no longer implements any trait
+    pub new_depth: Option<usize>,
+}
+
+impl Baseline {
+    pub fn of(analyzer: &TraitAnalyzer) -> Self {
+        Baseline {
+            type_depths: analyzer.impl_map.keys()
+                .map(|type_name| (type_name.clone(), analyzer.calculate_max_depth(type_name)))
+                .collect(),
+        }
+    }
+
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let content = fs::read_to_string(path)?;
+        serde_json::from_str(&content).map_err(|e| {
+            io::Error::new(io::ErrorKind::InvalidData, format!("Invalid baseline {}: {}", path.display(), e))
+        })
+    }
+
+    pub fn write(&self, path: &Path) -> io::Result<()> {
+        let mut content = serde_json::to_string_pretty(self)?;
+        content.push('\n');
+        fs::write(path, content)
+    }
+
+    /// Whether a type at `depth` was already this deep in the baseline
+    pub fn covers(&self, type_name: &str, depth: usize) -> bool {
+        self.type_depths.get(type_name).is_some_and(|&baseline_depth| depth <= baseline_depth)
+    }
+
+    /// Every type whose depth is different in `current`, by type name
+    pub fn changes(&self, current: &Baseline) -> Vec<DepthChange> {
+        let type_names: BTreeSet<&String> = self.type_depths.keys().chain(current.type_depths.keys()).collect();
+        type_names.into_iter()
+            .map(|type_name| DepthChange {
+                type_name: type_name.clone(),
+                old_depth: self.type_depths.get(type_name).copied(),
+                new_depth: current.type_depths.get(type_name).copied(),
+            })
+            .filter(|change| change.old_depth != change.new_depth)
+            .collect()
+    }
+}
+
+pub fn print_changes(changes: &[DepthChange]) {
+    if changes.is_empty() {
+        println!("No changes in trait depth.");
+    }
+    for change in changes {
+        match (change.old_depth, change.new_depth) {
+            (None, Some(new)) => println!("+ {}: depth {} (new)", change.type_name, new),
+            (Some(old), None) => println!("- {}: depth {} (removed)", change.type_name, old),
+            (Some(old), Some(new)) if new > old => println!("↑ {}: depth {} → {}", change.type_name, old, new),
+            (Some(old), Some(new)) => println!("↓ {}: depth {} → {}", change.type_name, old, new),
+            (None, None) => {}
+        }
+    }
+}
@@ -0,0 +1,133 @@
+//! How heavily the generics of a function or impl are bounded. Signatures like
+//! `fn f<T: A + B<C>, U: D>(..) where T::Out: E` are slow to type-check and hard to
+//! review, so every bound, every bound on an associated type, and the nesting of
+//! the generic arguments inside bounds are counted.
+
+use std::collections::HashSet;
+
+use serde::Serialize;
+use syn::{
+    FnArg, GenericArgument, Generics, PathArguments, ReturnType, Signature, TraitBoundModifier, Type,
+    TypeParamBound, WherePredicate,
+};
+
+/// Bounds on the generics of one signature
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize)]
+pub struct BoundComplexity {
+    /// Generic type parameters, counting `impl Trait` arguments
+    pub type_params: usize,
+    /// Trait bounds, inline, in the `where` clause and on `impl Trait` arguments
+    pub bounds: usize,
+    /// `where` predicates on associated types (`T::Out: E`, `<T as A>::Out: E`)
+    pub projection_bounds: usize,
+    /// Deepest nesting of generic arguments in any bound: 0 for `A`, 1 for `B<C>`,
+    /// 2 for `B<C<D>>`
+    pub nesting: usize,
+}
+
+impl BoundComplexity {
+    pub fn of_generics(generics: &Generics) -> Self {
+        let mut complexity = BoundComplexity::default();
+        let mut params: HashSet<String> = HashSet::from(["Self".to_string()]);
+        for param in generics.type_params() {
+            complexity.type_params += 1;
+            complexity.add_bounds(param.bounds.iter());
+            params.insert(param.ident.to_string());
+        }
+        if let Some(where_clause) = &generics.where_clause {
+            for predicate in &where_clause.predicates {
+                if let WherePredicate::Type(predicate) = predicate {
+                    if is_projection(&predicate.bounded_ty, &params) {
+                        complexity.projection_bounds += 1;
+                    }
+                    complexity.add_bounds(predicate.bounds.iter());
+                }
+            }
+        }
+        complexity
+    }
+
+    /// The generics of a function together with its `impl Trait` arguments
+    pub fn of_signature(sig: &Signature) -> Self {
+        let mut complexity = BoundComplexity::of_generics(&sig.generics);
+        for input in &sig.inputs {
+            if let FnArg::Typed(argument) = input {
+                if let Type::ImplTrait(impl_trait) = &*argument.ty {
+                    complexity.type_params += 1;
+                    complexity.add_bounds(impl_trait.bounds.iter());
+                }
+            }
+        }
+        complexity
+    }
+
+    fn add_bounds<'a>(&mut self, bounds: impl Iterator<Item = &'a TypeParamBound>) {
+        for bound in bounds {
+            // `?Sized` relaxes a bound rather than adding one
+            if let TypeParamBound::Trait(trait_bound) = bound {
+                if !matches!(trait_bound.modifier, TraitBoundModifier::None) {
+                    continue;
+                }
+                self.bounds += 1;
+                self.nesting = self.nesting.max(path_nesting(&trait_bound.path));
+            }
+        }
+    }
+}
+
+/// `T::Out` or `<T as Trait>::Out`, where `T` is a generic parameter or `Self`
+fn is_projection(ty: &Type, params: &HashSet<String>) -> bool {
+    let Type::Path(type_path) = ty else { return false };
+    type_path.qself.is_some()
+        || (type_path.path.segments.len() > 1
+            && params.contains(&type_path.path.segments[0].ident.to_string()))
+}
+
+fn path_nesting(path: &syn::Path) -> usize {
+    path.segments.iter()
+        .map(|segment| match &segment.arguments {
+            PathArguments::None => 0,
+            PathArguments::AngleBracketed(arguments) => 1 + arguments.args.iter()
+                .map(|argument| match argument {
+                    GenericArgument::Type(ty) => type_nesting(ty),
+                    GenericArgument::AssocType(binding) => type_nesting(&binding.ty),
+                    GenericArgument::Constraint(constraint) => bounds_nesting(constraint.bounds.iter()),
+                    _ => 0,
+                })
+                .max()
+                .unwrap_or(0),
+            PathArguments::Parenthesized(arguments) => {
+                let output = match &arguments.output {
+                    ReturnType::Type(_, ty) => type_nesting(ty),
+                    ReturnType::Default => 0,
+                };
+                1 + arguments.inputs.iter().map(type_nesting).max().unwrap_or(0).max(output)
+            }
+        })
+        .max()
+        .unwrap_or(0)
+}
+
+fn type_nesting(ty: &Type) -> usize {
+    match ty {
+        Type::Path(type_path) => path_nesting(&type_path.path),
+        Type::Reference(reference) => type_nesting(&reference.elem),
+        Type::Slice(slice) => type_nesting(&slice.elem),
+        Type::Array(array) => type_nesting(&array.elem),
+        Type::Paren(paren) => type_nesting(&paren.elem),
+        Type::Tuple(tuple) => tuple.elems.iter().map(type_nesting).max().unwrap_or(0),
+        Type::TraitObject(object) => bounds_nesting(object.bounds.iter()),
+        Type::ImplTrait(impl_trait) => bounds_nesting(impl_trait.bounds.iter()),
+        _ => 0,
+    }
+}
+
+fn bounds_nesting<'a>(bounds: impl Iterator<Item = &'a TypeParamBound>) -> usize {
+    bounds
+        .filter_map(|bound| match bound {
+            TypeParamBound::Trait(trait_bound) => Some(path_nesting(&trait_bound.path)),
+            _ => None,
+        })
+        .max()
+        .unwrap_or(0)
+}
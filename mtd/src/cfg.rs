@@ -0,0 +1,115 @@
+//! Evaluation of `#[cfg(...)]` and `#[cfg_attr(...)]` conditions, deciding which
+//! conditional traits, impls and derives take part in the analysis.
+//!
+//! Conditions are evaluated with three values: a predicate mtd knows the answer to
+//! (`feature = "..."` once features are selected, `test` under `--no-tests`) is true
+//! or false, anything else (`target_os`, `unix`, custom cfgs) is unknown. Items are
+//! only left out when their condition is known to be false.
+
+use std::collections::HashSet;
+
+use syn::punctuated::Punctuated;
+use syn::{Attribute, Expr, Lit, Meta, Token};
+
+/// Which cargo features count as enabled
+#[derive(Clone, Default)]
+pub enum Features {
+    /// Feature conditions are not evaluated; every conditional item is counted
+    #[default]
+    Unchecked,
+    /// Only these features are enabled (`--features`)
+    Enabled(HashSet<String>),
+    /// Every feature is enabled (`--all-features`)
+    All,
+}
+
+pub struct CfgContext {
+    pub features: Features,
+    /// `test` is known to be disabled (`--no-tests`)
+    pub no_tests: bool,
+}
+
+impl CfgContext {
+    /// Whether an item with these attributes is compiled in
+    pub fn is_enabled(&self, attrs: &[Attribute]) -> bool {
+        attrs.iter()
+            .filter(|attr| attr.path().is_ident("cfg"))
+            .all(|attr| attr.parse_args::<Meta>().map_or(true, |meta| self.evaluate(&meta) != Some(false)))
+    }
+
+    /// Trait paths of every `#[derive(...)]` that applies, including ones inside
+    /// `#[cfg_attr(condition, derive(...))]` whose condition may hold
+    pub fn derive_paths(&self, attrs: &[Attribute]) -> Vec<syn::Path> {
+        let mut paths = Vec::new();
+        for attr in attrs {
+            if attr.path().is_ident("derive") {
+                if let Ok(derived) = attr.parse_args_with(Punctuated::<syn::Path, Token![,]>::parse_terminated) {
+                    paths.extend(derived);
+                }
+            } else if attr.path().is_ident("cfg_attr") {
+                let Ok(args) = attr.parse_args_with(Punctuated::<Meta, Token![,]>::parse_terminated) else {
+                    continue;
+                };
+                let mut args = args.into_iter();
+                if args.next().is_some_and(|condition| self.evaluate(&condition) == Some(false)) {
+                    continue;
+                }
+                for meta in args {
+                    if let Meta::List(list) = meta.clone() {
+                        if list.path.is_ident("derive") {
+                            if let Ok(derived) = list.parse_args_with(Punctuated::<syn::Path, Token![,]>::parse_terminated) {
+                                paths.extend(derived);
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        paths
+    }
+
+    /// Value of a cfg predicate, `None` when it cannot be known statically
+    fn evaluate(&self, meta: &Meta) -> Option<bool> {
+        match meta {
+            Meta::Path(path) if path.is_ident("test") => self.no_tests.then_some(false),
+            Meta::NameValue(name_value) if name_value.path.is_ident("feature") => {
+                let Expr::Lit(expr) = &name_value.value else { return None };
+                let Lit::Str(feature) = &expr.lit else { return None };
+                match &self.features {
+                    Features::Unchecked => None,
+                    Features::Enabled(enabled) => Some(enabled.contains(&feature.value())),
+                    Features::All => Some(true),
+                }
+            }
+            Meta::List(list) => {
+                let conditions = list.parse_args_with(Punctuated::<Meta, Token![,]>::parse_terminated).ok()?;
+                let values: Vec<Option<bool>> = conditions.iter().map(|condition| self.evaluate(condition)).collect();
+                if list.path.is_ident("all") {
+                    if values.contains(&Some(false)) {
+                        Some(false)
+                    } else if values.iter().all(Option::is_some) {
+                        Some(true)
+                    } else {
+                        None
+                    }
+                } else if list.path.is_ident("any") {
+                    if values.contains(&Some(true)) {
+                        Some(true)
+                    } else if values.iter().all(|value| *value == Some(false)) {
+                        Some(false)
+                    } else {
+                        None
+                    }
+                } else if list.path.is_ident("not") {
+                    match values.as_slice() {
+                        [value] => value.map(|value| !value),
+                        _ => None,
+                    }
+                } else {
+                    None
+                }
+            }
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,33 @@
+//! `mtd.toml`, or the `[mtd]` table of a shared `trr.toml`: defaults for the command
+//! line, so CI and local runs analyze the same way. Options given on the command line
+//! take precedence.
+
+use std::io;
+use std::path::{Path, PathBuf};
+
+use serde::Deserialize;
+
+#[derive(Default, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct Config {
+    /// Files and directories to skip, matched against the end of each path
+    pub exclude: Vec<PathBuf>,
+    /// Traits left out of every hierarchy (`Send`, `Sync`, `Debug`, ...)
+    pub ignore_traits: Vec<String>,
+    pub fail_above: Option<usize>,
+    pub format: Option<String>,
+    pub no_tests: bool,
+    pub no_local_items: bool,
+    pub features: Vec<String>,
+    pub all_features: bool,
+    pub generic_instances: bool,
+}
+
+/// The nearest `mtd.toml` or `trr.toml` in `dir` or its ancestors
+pub fn find(dir: &Path) -> Option<PathBuf> {
+    trr_core::config::find(dir, "mtd")
+}
+
+pub fn load(path: &Path) -> io::Result<Config> {
+    trr_core::config::load(path, "mtd")
+}
@@ -0,0 +1,33 @@
+//! `--format csv`: one row per implemented trait of every type, for triage in a
+//! spreadsheet when the codebase is too large to read the text report.
+
+use std::io::{self, Write};
+
+use mtd::TraitAnalyzer;
+use trr_core::output::write_csv_row as write_row;
+
+const HEADER: &[&str] = &["type", "trait", "depth", "chain", "file", "line"];
+
+/// Rows are sorted by type, then trait. `depth` and `chain` start at the implemented
+/// trait; `file` and `line` point at its impl (or derive, or blanket impl).
+pub fn write_csv(out: &mut impl Write, analyzer: &TraitAnalyzer) -> io::Result<()> {
+    write_row(out, HEADER)?;
+
+    let mut type_names: Vec<&String> = analyzer.impl_map.keys().collect();
+    type_names.sort();
+    for type_name in type_names {
+        for trait_name in analyzer.implemented_traits(type_name) {
+            let chain = analyzer.trait_chain(&trait_name);
+            let location = analyzer.impl_location(type_name, &trait_name);
+            write_row(out, &[
+                type_name,
+                &trait_name,
+                &chain.len().to_string(),
+                &chain.join(" → "),
+                &location.map(|location| location.file.display().to_string()).unwrap_or_default(),
+                &location.map(|location| location.line.to_string()).unwrap_or_default(),
+            ])?;
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,92 @@
+//! Graphviz export of the supertrait graph and the type -> trait edges, with
+//! nodes colored by depth so deep and diamond-shaped hierarchies stand out.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::io::{self, Write};
+
+use mtd::TraitAnalyzer;
+
+/// Fill colors from shallow to deep; deeper nodes reuse the last color
+const DEPTH_COLORS: &[&str] = &["#d9f0d3", "#a6dba0", "#fee08b", "#fdae61", "#f46d43", "#d73027"];
+
+fn depth_color(depth: usize) -> &'static str {
+    DEPTH_COLORS[depth.saturating_sub(1).min(DEPTH_COLORS.len() - 1)]
+}
+
+/// Quote a name as a DOT string
+fn quoted(name: &str) -> String {
+    format!("\"{}\"", name.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+pub fn write_dot(out: &mut impl Write, analyzer: &TraitAnalyzer) -> io::Result<()> {
+    // Every trait that is declared, used as a supertrait, or implemented
+    let mut traits: BTreeSet<&str> = BTreeSet::new();
+    for (trait_name, supertraits) in &analyzer.trait_graph {
+        traits.insert(trait_name);
+        traits.extend(supertraits.iter().map(String::as_str));
+    }
+    let types: BTreeMap<&str, BTreeSet<String>> = analyzer.impl_map.keys()
+        .map(|type_name| (type_name.as_str(), analyzer.implemented_traits(type_name)))
+        .collect();
+    for implemented in types.values() {
+        traits.extend(implemented.iter().map(String::as_str));
+    }
+
+    writeln!(out, "digraph traits {{")?;
+    writeln!(out, "    rankdir=BT;")?;
+    writeln!(out, "    node [style=filled, fontname=\"Helvetica\"];")?;
+
+    writeln!(out)?;
+    for trait_name in &traits {
+        let depth = analyzer.trait_depth(trait_name);
+        let tooltip = match analyzer.trait_location(trait_name) {
+            Some(location) => format!("depth {}, declared at {}", depth, location),
+            None => format!("depth {}", depth),
+        };
+        writeln!(
+            out,
+            "    {} [label={}, shape=box, fillcolor=\"{}\", tooltip={}];",
+            quoted(&format!("trait:{}", trait_name)),
+            quoted(trait_name),
+            depth_color(depth),
+            quoted(&tooltip)
+        )?;
+    }
+    for type_name in types.keys() {
+        let depth = analyzer.calculate_max_depth(type_name);
+        writeln!(
+            out,
+            "    {} [label={}, shape=ellipse, fillcolor=\"{}\", tooltip=\"depth {}\"];",
+            quoted(&format!("type:{}", type_name)),
+            quoted(type_name),
+            depth_color(depth),
+            depth
+        )?;
+    }
+
+    writeln!(out)?;
+    for trait_name in &traits {
+        let mut supertraits = analyzer.supertraits_of(trait_name);
+        supertraits.sort();
+        for supertrait in supertraits {
+            writeln!(
+                out,
+                "    {} -> {};",
+                quoted(&format!("trait:{}", trait_name)),
+                quoted(&format!("trait:{}", supertrait))
+            )?;
+        }
+    }
+    for (type_name, implemented) in &types {
+        for trait_name in implemented {
+            writeln!(
+                out,
+                "    {} -> {} [style=dashed];",
+                quoted(&format!("type:{}", type_name)),
+                quoted(&format!("trait:{}", trait_name))
+            )?;
+        }
+    }
+
+    writeln!(out, "}}")
+}
@@ -0,0 +1,52 @@
+//! `--expand`: the crate as `cargo expand` prints it, including the impls that
+//! derive and attribute macros (Anchor's `#[account]`, `#[program]`, ...) generate.
+
+use std::io;
+use std::path::Path;
+use std::process::Command;
+
+use mtd::{FileAnalyzer, Features, Options, TraitAnalyzer};
+
+/// Expand the crate in `crate_dir` with `cargo expand` and analyze the result
+pub fn analyze_expanded(crate_dir: &Path, options: &Options) -> io::Result<TraitAnalyzer> {
+    let source = expand(crate_dir, &options.features)?;
+    let mut file_analyzer = FileAnalyzer::new(options);
+    file_analyzer.analyze_source(&source, &crate_dir.join("<cargo expand>"), Vec::new())?;
+
+    let mut analyzer = TraitAnalyzer::new(options.clone());
+    analyzer.add_file_analysis(&file_analyzer);
+    Ok(analyzer)
+}
+
+fn expand(crate_dir: &Path, features: &Features) -> io::Result<String> {
+    if !crate_dir.join("Cargo.toml").is_file() {
+        return Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("--expand needs a crate directory, but '{}' has no Cargo.toml", crate_dir.display()),
+        ));
+    }
+
+    eprintln!("Expanding macros: cargo expand in {}", crate_dir.display());
+    let mut command = Command::new("cargo");
+    command.arg("expand").current_dir(crate_dir);
+    match features {
+        Features::Unchecked => {}
+        Features::Enabled(enabled) => {
+            let mut enabled: Vec<&str> = enabled.iter().map(String::as_str).collect();
+            enabled.sort();
+            command.arg("--features").arg(enabled.join(","));
+        }
+        Features::All => {
+            command.arg("--all-features");
+        }
+    }
+
+    let output = command.output()?;
+    if !output.status.success() {
+        return Err(io::Error::other(format!(
+            "cargo expand failed (install it with `cargo install cargo-expand`): {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    String::from_utf8(output.stdout).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
@@ -0,0 +1,61 @@
+//! Supertraits of traits that are declared outside the analyzed tree, so their
+//! hierarchies still count toward a type's depth.
+
+/// std/core/alloc traits that have supertraits. Traits without any (`Clone`,
+/// `Debug`, `Iterator`, ...) need no entry since unknown traits count as depth 1.
+const STD_TRAITS: &[(&str, &[&str])] = &[
+    // std::marker, std::clone
+    ("Copy", &["Clone"]),
+    // std::cmp
+    ("Eq", &["PartialEq"]),
+    ("PartialOrd", &["PartialEq"]),
+    ("Ord", &["Eq", "PartialOrd"]),
+    // std::error
+    ("Error", &["Debug", "Display"]),
+    // std::iter
+    ("DoubleEndedIterator", &["Iterator"]),
+    ("ExactSizeIterator", &["Iterator"]),
+    ("FusedIterator", &["Iterator"]),
+    // std::ops
+    ("Fn", &["FnMut"]),
+    ("FnMut", &["FnOnce"]),
+    ("AsyncFn", &["AsyncFnMut"]),
+    ("AsyncFnMut", &["AsyncFnOnce"]),
+    ("DerefMut", &["Deref"]),
+    ("IndexMut", &["Index"]),
+    // std::borrow
+    ("BorrowMut", &["Borrow"]),
+    // std::io
+    ("BufRead", &["Read"]),
+];
+
+/// std/core/alloc traits that cannot be used as `dyn Trait`, and so neither can any
+/// trait that has them as a supertrait
+const NOT_OBJECT_SAFE: &[&str] = &[
+    "Sized", "Clone", "Copy", "Default", "PartialEq", "Eq", "PartialOrd", "Ord", "Hash",
+    "From", "Into", "TryFrom", "TryInto", "FromStr", "FromIterator", "IntoIterator", "Extend",
+];
+
+/// Name of a std/core/alloc trait referenced by name or by a `std::`, `core::` or
+/// `alloc::` path, or `None` for paths into other crates
+fn std_name(trait_name: &str) -> Option<&str> {
+    match trait_name.trim_start_matches("::").split_once("::") {
+        None => Some(trait_name),
+        Some(("std" | "core" | "alloc", _)) => trait_name.rsplit("::").next(),
+        Some(_) => None,
+    }
+}
+
+/// Supertraits of a std/core/alloc trait, or `None` if the trait is not in the table.
+/// Accepts the trait by name or by a `std::`, `core::` or `alloc::` path.
+pub fn supertraits(trait_name: &str) -> Option<&'static [&'static str]> {
+    let name = std_name(trait_name)?;
+    STD_TRAITS.iter()
+        .find(|(known, _)| *known == name)
+        .map(|(_, supertraits)| *supertraits)
+}
+
+/// Whether a trait declared outside the analyzed tree is known not to be object safe
+pub fn is_not_object_safe(trait_name: &str) -> bool {
+    std_name(trait_name).is_some_and(|name| NOT_OBJECT_SAFE.contains(&name))
+}
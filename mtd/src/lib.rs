@@ -0,0 +1,1353 @@
+//! Trait hierarchy analysis behind the `mtd` binary: parses Rust sources, builds the
+//! supertrait graph and the traits each type implements, and measures how deep the
+//! hierarchies behind every type go.
+
+mod bounds;
+mod cfg;
+mod known_traits;
+mod object_safety;
+mod paths;
+
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+pub use bounds::BoundComplexity;
+pub use cfg::Features;
+/// Where an item is declared, or an impl is written: the `impl` keyword, or the trait
+/// name in a `#[derive]`
+pub use trr_core::Location;
+
+use cfg::CfgContext;
+use paths::Scopes;
+use quote::{quote, ToTokens};
+use serde::Serialize;
+use syn::punctuated::Punctuated;
+use syn::spanned::Spanned;
+use syn::visit::{self, Visit};
+use trr_core::walk;
+use syn::visit_mut::{self, VisitMut};
+use syn::{
+    Attribute, GenericArgument, Generics, Ident, ImplItemFn, Item, ItemEnum, ItemFn, ItemImpl, ItemMod,
+    ItemStruct, ItemType, ItemTrait, ItemUnion, PathArguments, TraitItem, TraitItemFn, TypeTraitObject, TraitBoundModifier,
+    Type, TypeParamBound, WherePredicate,
+};
+
+/// A trait declaration, keyed by its path from the crate root
+pub struct TraitInfo {
+    pub name: String,
+    pub supertraits: Vec<String>,
+    /// Names of the trait's generic type parameters, in declaration order
+    pub type_params: Vec<String>,
+    /// Associated types with trait bounds (`type Backend: Database`), with their bounds
+    pub associated_bounds: Vec<(String, Vec<String>)>,
+    pub surface: TraitSurface,
+    /// Items that keep the trait from being object safe, without its supertraits
+    pub object_safety_violations: Vec<String>,
+    /// `unsafe trait`
+    pub is_unsafe: bool,
+    pub location: Location,
+}
+
+/// Number of items a trait declares itself
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize)]
+pub struct TraitSurface {
+    /// Methods without a default body
+    pub required_methods: usize,
+    /// Methods with a default body
+    pub provided_methods: usize,
+    pub associated_types: usize,
+    pub associated_consts: usize,
+}
+
+impl TraitSurface {
+    fn of(item: &ItemTrait) -> Self {
+        let mut surface = TraitSurface::default();
+        for trait_item in &item.items {
+            match trait_item {
+                TraitItem::Fn(method) if method.default.is_some() => surface.provided_methods += 1,
+                TraitItem::Fn(_) => surface.required_methods += 1,
+                TraitItem::Type(_) => surface.associated_types += 1,
+                TraitItem::Const(_) => surface.associated_consts += 1,
+                _ => {}
+            }
+        }
+        surface
+    }
+
+    pub fn methods(&self) -> usize {
+        self.required_methods + self.provided_methods
+    }
+
+    fn add(&mut self, other: &TraitSurface) {
+        self.required_methods += other.required_methods;
+        self.provided_methods += other.provided_methods;
+        self.associated_types += other.associated_types;
+        self.associated_consts += other.associated_consts;
+    }
+}
+
+/// A trait implemented for a concrete type, by an `impl` block or a derive
+pub struct ImplInfo {
+    pub type_name: String,
+    pub trait_name: String,
+    /// Generated by `#[derive(...)]` rather than written out
+    pub derived: bool,
+    pub location: Location,
+}
+
+/// An `unsafe impl`, whose soundness rests on the implementor upholding the trait's contract
+#[derive(Clone, Serialize)]
+pub struct UnsafeImpl {
+    pub trait_name: String,
+    /// The implementing type as written (a type parameter for blanket impls)
+    pub type_name: String,
+    pub location: Location,
+}
+
+/// A generic function, method or impl with trait bounds
+#[derive(Clone, Serialize)]
+pub struct BoundedSignature {
+    /// `module::function`, `Type::method`, `Trait::method`, or the impl header
+    /// (`impl Validator for Pool<T>`)
+    pub name: String,
+    #[serde(flatten)]
+    pub complexity: BoundComplexity,
+    pub location: Location,
+}
+
+/// `impl<T: Bound> Trait for T`: every type satisfying all bounds implements the trait
+struct BlanketImplInfo {
+    trait_name: String,
+    bounds: Vec<String>,
+    location: Location,
+}
+
+/// A trait object type (`Box<dyn T>`, `&dyn T`, ...) written somewhere in the source
+#[derive(Clone)]
+pub struct DynUsage {
+    /// The trait dispatched through, ignoring auto traits like `Send`
+    pub trait_name: String,
+    /// What holds the trait object: `Box`, `Arc`, `&`, `&mut`, `*const`, ..., or `dyn`
+    /// when it is not wrapped (e.g. `impl Foo for dyn T`)
+    pub container: String,
+    pub location: Location,
+}
+
+/// The traits and impls declared in one source file
+pub struct FileAnalyzer {
+    pub traits: Vec<TraitInfo>,
+    pub impls: Vec<ImplInfo>,
+    pub dyn_usages: Vec<DynUsage>,
+    pub unsafe_impls: Vec<UnsafeImpl>,
+    pub bounded_signatures: Vec<BoundedSignature>,
+    /// Structs, enums, unions and type aliases declared in the file
+    pub declared_types: Vec<String>,
+    blanket_impls: Vec<BlanketImplInfo>,
+    scopes: Scopes,
+    /// Path of the module being visited, relative to the crate root
+    module: Vec<String>,
+    file: PathBuf,
+    cfg: CfgContext,
+    /// Inside a function body; `module` then ends with the function's name
+    in_body: bool,
+    /// Leave out items declared inside function bodies
+    skip_local_items: bool,
+    /// Type or trait whose methods are being visited
+    method_owner: Option<String>,
+    /// Container of the trait object type about to be visited
+    dyn_container: Option<String>,
+    crates: Vec<Crate>,
+}
+
+impl FileAnalyzer {
+    pub fn new(options: &Options) -> Self {
+        FileAnalyzer {
+            traits: Vec::new(),
+            impls: Vec::new(),
+            dyn_usages: Vec::new(),
+            unsafe_impls: Vec::new(),
+            bounded_signatures: Vec::new(),
+            declared_types: Vec::new(),
+            blanket_impls: Vec::new(),
+            scopes: Scopes::default(),
+            module: Vec::new(),
+            file: PathBuf::new(),
+            cfg: CfgContext {
+                features: options.features.clone(),
+                no_tests: options.no_tests,
+            },
+            in_body: false,
+            skip_local_items: options.no_local_items,
+            method_owner: None,
+            dyn_container: None,
+            crates: options.crates.clone(),
+        }
+    }
+
+    pub fn analyze_file(&mut self, path: &Path) -> io::Result<()> {
+        let content = fs::read_to_string(path)?;
+        let crate_root: Vec<String> = Crate::containing(&self.crates, path)
+            .map(|krate| vec![krate.key()])
+            .unwrap_or_default();
+        let module = crate_root.iter().cloned().chain(paths::module_path_for_file(path)).collect();
+        self.analyze_in_crate(&content, path, crate_root, module)
+    }
+
+    /// Analyze source text as the contents of `module` (empty for the crate root),
+    /// with locations reported in `path`
+    pub fn analyze_source(&mut self, source: &str, path: &Path, module: Vec<String>) -> io::Result<()> {
+        self.analyze_in_crate(source, path, Vec::new(), module)
+    }
+
+    fn analyze_in_crate(
+        &mut self,
+        source: &str,
+        path: &Path,
+        crate_root: Vec<String>,
+        module: Vec<String>,
+    ) -> io::Result<()> {
+        let file = syn::parse_file(source)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+
+        self.file = path.to_path_buf();
+        self.module = module;
+        self.scopes = Scopes::collect(&file, &crate_root, &self.module);
+        self.visit_file(&file);
+
+        Ok(())
+    }
+
+    /// The analyzed file
+    pub fn path(&self) -> &Path {
+        &self.file
+    }
+
+    /// Canonical name of a trait or type path referenced from the current module
+    fn resolve(&self, path: &syn::Path) -> String {
+        self.scopes.resolve(&self.module, path)
+    }
+
+    fn location(&self, span: proc_macro2::Span) -> Location {
+        Location::new(self.file.clone(), span.start().line)
+    }
+
+    fn skips_local_items(&self) -> bool {
+        self.in_body && self.skip_local_items
+    }
+
+    /// Record a signature if it bounds its generics at all
+    fn add_bounded_signature(&mut self, name: String, complexity: BoundComplexity, span: proc_macro2::Span) {
+        if complexity.bounds > 0 && !self.skips_local_items() {
+            self.bounded_signatures.push(BoundedSignature {
+                name,
+                complexity,
+                location: self.location(span),
+            });
+        }
+    }
+
+    /// Name of a method of the impl or trait being visited
+    fn method_name(&self, ident: &Ident) -> String {
+        match &self.method_owner {
+            Some(owner) => format!("{}::{}", owner, ident),
+            None => paths::qualify(&self.module, &ident.to_string()),
+        }
+    }
+
+    /// Visit a function body, whose local items are keyed under the function's name
+    /// (`tests::setup::MockVault`) just as `Scopes` declares them
+    fn visit_body(&mut self, ident: &Ident, visit_body: impl FnOnce(&mut Self)) {
+        let outer = std::mem::replace(&mut self.in_body, true);
+        let owner = self.method_owner.take();
+        self.module.push(ident.to_string());
+        visit_body(self);
+        self.module.pop();
+        self.method_owner = owner;
+        self.in_body = outer;
+    }
+
+    fn resolve_type(&self, ty: &Type) -> String {
+        match ty {
+            Type::Path(type_path) if type_path.qself.is_none() => self.resolve(&type_path.path),
+            _ => render_tokens(ty),
+        }
+    }
+
+    fn declare_type(&mut self, ident: &Ident) {
+        if !self.skips_local_items() {
+            self.declared_types.push(paths::qualify(&self.module, &ident.to_string()));
+        }
+    }
+
+    /// Record the impls generated by `#[derive(...)]` on a type declaration.
+    /// Derive paths are keyed by their last segment (`serde::Serialize` -> `Serialize`).
+    fn add_derived_impls(&mut self, attrs: &[Attribute], ident: &Ident, generics: &Generics) {
+        if self.skips_local_items() {
+            return;
+        }
+        let (_, type_generics, _) = generics.split_for_impl();
+        let type_name = paths::qualify(&self.module, &render_tokens(&quote!(#ident #type_generics)));
+
+        for path in self.cfg.derive_paths(attrs) {
+            if let Some(segment) = path.segments.last() {
+                self.impls.push(ImplInfo {
+                    type_name: type_name.clone(),
+                    trait_name: segment.ident.to_string(),
+                    derived: true,
+                    location: self.location(path.span()),
+                });
+            }
+        }
+    }
+}
+
+impl<'ast> Visit<'ast> for FileAnalyzer {
+    fn visit_item(&mut self, item: &'ast Item) {
+        let attrs = match item {
+            Item::Mod(item) => &item.attrs,
+            Item::Fn(item) => &item.attrs,
+            Item::Impl(item) => &item.attrs,
+            Item::Trait(item) => &item.attrs,
+            Item::Struct(item) => &item.attrs,
+            Item::Enum(item) => &item.attrs,
+            Item::Union(item) => &item.attrs,
+            _ => return visit::visit_item(self, item),
+        };
+        if !self.cfg.is_enabled(attrs) {
+            return;
+        }
+        visit::visit_item(self, item);
+    }
+
+    fn visit_item_trait(&mut self, item: &'ast ItemTrait) {
+        if self.skips_local_items() {
+            return visit::visit_item_trait(self, item);
+        }
+        let mut supertraits: Vec<String> = trait_bound_paths(&item.supertraits)
+            .map(|path| self.resolve(path))
+            .collect();
+
+        // `trait Foo where Self: Bar` declares Bar as a supertrait just like `trait Foo: Bar`
+        if let Some(where_clause) = &item.generics.where_clause {
+            for predicate in &where_clause.predicates {
+                if let WherePredicate::Type(predicate) = predicate {
+                    let bounds_self = matches!(&predicate.bounded_ty, Type::Path(type_path)
+                        if type_path.qself.is_none() && type_path.path.is_ident("Self"));
+                    if bounds_self {
+                        for supertrait in trait_bound_paths(&predicate.bounds).map(|path| self.resolve(path)) {
+                            if !supertraits.contains(&supertrait) {
+                                supertraits.push(supertrait);
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
+        let mut associated_bounds: Vec<(String, Vec<String>)> = item.items.iter()
+            .filter_map(|trait_item| match trait_item {
+                TraitItem::Type(associated) => Some((
+                    associated.ident.to_string(),
+                    trait_bound_paths(&associated.bounds).map(|path| self.resolve(path)).collect(),
+                )),
+                _ => None,
+            })
+            .collect();
+
+        // `where Self::Backend: Database` bounds an associated type too
+        if let Some(where_clause) = &item.generics.where_clause {
+            for predicate in &where_clause.predicates {
+                let WherePredicate::Type(predicate) = predicate else { continue };
+                let Type::Path(type_path) = &predicate.bounded_ty else { continue };
+                let segments: Vec<_> = type_path.path.segments.iter().collect();
+                if let [first, associated] = segments.as_slice() {
+                    if type_path.qself.is_none() && first.ident == "Self" {
+                        let bounds = trait_bound_paths(&predicate.bounds).map(|path| self.resolve(path));
+                        match associated_bounds.iter_mut().find(|(name, _)| associated.ident == name) {
+                            Some((_, existing)) => existing.extend(bounds),
+                            None => associated_bounds.push((associated.ident.to_string(), bounds.collect())),
+                        }
+                    }
+                }
+            }
+        }
+        associated_bounds.retain(|(_, bounds)| !bounds.is_empty());
+
+        self.traits.push(TraitInfo {
+            name: paths::qualify(&self.module, &item.ident.to_string()),
+            supertraits,
+            type_params: item.generics.type_params().map(|param| param.ident.to_string()).collect(),
+            associated_bounds,
+            surface: TraitSurface::of(item),
+            object_safety_violations: object_safety::violations(item),
+            is_unsafe: item.unsafety.is_some(),
+            location: self.location(item.ident.span()),
+        });
+
+        let outer = self.method_owner.replace(paths::qualify(&self.module, &item.ident.to_string()));
+        visit::visit_item_trait(self, item);
+        self.method_owner = outer;
+    }
+
+    fn visit_item_impl(&mut self, item: &'ast ItemImpl) {
+        if self.skips_local_items() {
+            return visit::visit_item_impl(self, item);
+        }
+        // Only positive trait impls; inherent impls and `impl !Trait for T` are skipped
+        if let Some((None, trait_path, _)) = &item.trait_ {
+            if item.unsafety.is_some() {
+                self.unsafe_impls.push(UnsafeImpl {
+                    trait_name: self.resolve(trait_path),
+                    type_name: self.resolve_type(&item.self_ty),
+                    location: self.location(item.impl_token.span),
+                });
+            }
+            match blanket_bounds(item) {
+                Some(bounds) => self.blanket_impls.push(BlanketImplInfo {
+                    trait_name: self.resolve(trait_path),
+                    bounds: bounds.into_iter().map(|path| self.resolve(path)).collect(),
+                    location: self.location(item.impl_token.span),
+                }),
+                None => self.impls.push(ImplInfo {
+                    type_name: self.resolve_type(&item.self_ty),
+                    trait_name: self.resolve(trait_path),
+                    derived: false,
+                    location: self.location(item.impl_token.span),
+                }),
+            }
+        }
+
+        let type_name = self.resolve_type(&item.self_ty);
+        let header = match &item.trait_ {
+            Some((negative, trait_path, _)) => format!(
+                "impl {}{} for {}",
+                if negative.is_some() { "!" } else { "" },
+                self.resolve(trait_path),
+                type_name
+            ),
+            None => format!("impl {}", type_name),
+        };
+        self.add_bounded_signature(header, BoundComplexity::of_generics(&item.generics), item.impl_token.span);
+
+        let outer = self.method_owner.replace(base_trait_name(&type_name).to_string());
+        visit::visit_item_impl(self, item);
+        self.method_owner = outer;
+    }
+
+    fn visit_item_struct(&mut self, item: &'ast ItemStruct) {
+        self.declare_type(&item.ident);
+        self.add_derived_impls(&item.attrs, &item.ident, &item.generics);
+        visit::visit_item_struct(self, item);
+    }
+
+    fn visit_item_enum(&mut self, item: &'ast ItemEnum) {
+        self.declare_type(&item.ident);
+        self.add_derived_impls(&item.attrs, &item.ident, &item.generics);
+        visit::visit_item_enum(self, item);
+    }
+
+    fn visit_item_union(&mut self, item: &'ast ItemUnion) {
+        self.declare_type(&item.ident);
+        self.add_derived_impls(&item.attrs, &item.ident, &item.generics);
+        visit::visit_item_union(self, item);
+    }
+
+    fn visit_item_type(&mut self, item: &'ast ItemType) {
+        self.declare_type(&item.ident);
+        visit::visit_item_type(self, item);
+    }
+
+    fn visit_item_mod(&mut self, item: &'ast ItemMod) {
+        self.module.push(item.ident.to_string());
+        visit::visit_item_mod(self, item);
+        self.module.pop();
+    }
+
+    fn visit_item_fn(&mut self, item: &'ast ItemFn) {
+        let name = paths::qualify(&self.module, &item.sig.ident.to_string());
+        self.add_bounded_signature(name, BoundComplexity::of_signature(&item.sig), item.sig.ident.span());
+        self.visit_body(&item.sig.ident, |analyzer| visit::visit_item_fn(analyzer, item));
+    }
+
+    fn visit_impl_item_fn(&mut self, item: &'ast ImplItemFn) {
+        let name = self.method_name(&item.sig.ident);
+        self.add_bounded_signature(name, BoundComplexity::of_signature(&item.sig), item.sig.ident.span());
+        self.visit_body(&item.sig.ident, |analyzer| visit::visit_impl_item_fn(analyzer, item));
+    }
+
+    fn visit_trait_item_fn(&mut self, item: &'ast TraitItemFn) {
+        let name = self.method_name(&item.sig.ident);
+        self.add_bounded_signature(name, BoundComplexity::of_signature(&item.sig), item.sig.ident.span());
+        self.visit_body(&item.sig.ident, |analyzer| visit::visit_trait_item_fn(analyzer, item));
+    }
+
+    fn visit_type(&mut self, ty: &'ast Type) {
+        match ty {
+            Type::TraitObject(object) if object.dyn_token.is_some() => {
+                let container = self.dyn_container.take().unwrap_or_else(|| "dyn".to_string());
+                if let Some(path) = principal_trait(object) {
+                    self.dyn_usages.push(DynUsage {
+                        trait_name: self.resolve(path),
+                        container,
+                        location: self.location(object.span()),
+                    });
+                }
+                visit::visit_type(self, ty);
+            }
+            // `&(dyn A + Send)` keeps the container of the parenthesized object
+            Type::Paren(_) | Type::Group(_) => visit::visit_type(self, ty),
+            _ => {
+                let outer = std::mem::replace(&mut self.dyn_container, dyn_container(ty));
+                visit::visit_type(self, ty);
+                self.dyn_container = outer;
+            }
+        }
+    }
+}
+
+/// Whether a type is a `dyn Trait` object, possibly in parentheses
+fn is_trait_object(ty: &Type) -> bool {
+    match ty {
+        Type::TraitObject(object) => object.dyn_token.is_some(),
+        Type::Paren(paren) => is_trait_object(&paren.elem),
+        Type::Group(group) => is_trait_object(&group.elem),
+        _ => false,
+    }
+}
+
+/// The container a type provides for a trait object directly inside it:
+/// `&` for `&dyn T`, `Box` for `Box<dyn T>`, `*const` for `*const dyn T`
+fn dyn_container(ty: &Type) -> Option<String> {
+    match ty {
+        Type::Reference(reference) if is_trait_object(&reference.elem) => {
+            Some(if reference.mutability.is_some() { "&mut" } else { "&" }.to_string())
+        }
+        Type::Ptr(pointer) if is_trait_object(&pointer.elem) => {
+            Some(if pointer.mutability.is_some() { "*mut" } else { "*const" }.to_string())
+        }
+        Type::Path(type_path) => {
+            let segment = type_path.path.segments.last()?;
+            let PathArguments::AngleBracketed(args) = &segment.arguments else { return None };
+            args.args.iter()
+                .any(|arg| matches!(arg, GenericArgument::Type(ty) if is_trait_object(ty)))
+                .then(|| segment.ident.to_string())
+        }
+        _ => None,
+    }
+}
+
+/// The trait a trait object dispatches through: its first bound that is not an
+/// auto trait, or the first bound if there are only auto traits
+fn principal_trait(object: &TypeTraitObject) -> Option<&syn::Path> {
+    const AUTO_TRAITS: &[&str] = &["Send", "Sync", "Unpin", "UnwindSafe", "RefUnwindSafe"];
+    let mut traits = trait_bound_paths(&object.bounds).peekable();
+    let first = *traits.peek()?;
+    let is_auto = |path: &syn::Path| {
+        path.segments.last().is_some_and(|segment| AUTO_TRAITS.iter().any(|auto| segment.ident == auto))
+    };
+    Some(traits.find(|path| !is_auto(path)).unwrap_or(first))
+}
+
+/// If the impl is for one of its own type parameters (`impl<T: A + B> Trait for T`),
+/// the trait bounds on that parameter from both the parameter list and the where clause
+fn blanket_bounds(item: &ItemImpl) -> Option<Vec<&syn::Path>> {
+    let Type::Path(self_ty) = &*item.self_ty else { return None };
+    let param = item.generics.type_params()
+        .find(|param| self_ty.qself.is_none() && self_ty.path.is_ident(&param.ident))?;
+
+    let mut bounds: Vec<&syn::Path> = trait_bound_paths(&param.bounds).collect();
+    if let Some(where_clause) = &item.generics.where_clause {
+        for predicate in &where_clause.predicates {
+            if let WherePredicate::Type(predicate) = predicate {
+                let bounds_param = matches!(&predicate.bounded_ty, Type::Path(type_path)
+                    if type_path.qself.is_none() && type_path.path.is_ident(&param.ident));
+                if bounds_param {
+                    bounds.extend(trait_bound_paths(&predicate.bounds));
+                }
+            }
+        }
+    }
+    Some(bounds)
+}
+
+/// Paths of the trait bounds in a bound list, skipping lifetimes and `?Sized`-style
+/// relaxations that do not add a supertrait
+fn trait_bound_paths<'a, P>(
+    bounds: &'a Punctuated<TypeParamBound, P>,
+) -> impl Iterator<Item = &'a syn::Path> + 'a {
+    bounds.iter().filter_map(|bound| match bound {
+        TypeParamBound::Trait(trait_bound)
+            if matches!(trait_bound.modifier, TraitBoundModifier::None) =>
+        {
+            Some(&trait_bound.path)
+        }
+        _ => None,
+    })
+}
+
+/// The trait a reference names, without generic arguments
+/// (`GenericTrait<i32>` -> `GenericTrait`, `Fn(u8)` -> `Fn`)
+fn base_trait_name(name: &str) -> &str {
+    name.find(['<', '(']).map_or(name, |end| &name[..end])
+}
+
+/// Replaces a trait's type parameters with the types of one instantiation
+struct SubstituteParams(HashMap<String, Type>);
+
+impl VisitMut for SubstituteParams {
+    fn visit_type_mut(&mut self, ty: &mut Type) {
+        if let Type::Path(type_path) = ty {
+            if let Some(replacement) = type_path.path.get_ident()
+                .filter(|_| type_path.qself.is_none())
+                .and_then(|ident| self.0.get(&ident.to_string()))
+            {
+                *ty = replacement.clone();
+                return;
+            }
+        }
+        visit_mut::visit_type_mut(self, ty);
+    }
+}
+
+/// Instantiate the supertraits declared for a generic trait with the type arguments
+/// of `instance` (`GenericBase<T>` becomes `GenericBase<i32>` for `GenericTrait<i32>`)
+fn instantiate_supertraits(supertraits: &[String], params: &[String], instance: &str) -> Vec<String> {
+    let args: Vec<Type> = syn::parse_str::<syn::Path>(instance)
+        .ok()
+        .and_then(|path| path.segments.last().cloned())
+        .map(|segment| match segment.arguments {
+            PathArguments::AngleBracketed(args) => args.args.into_iter()
+                .filter_map(|arg| match arg {
+                    GenericArgument::Type(ty) => Some(ty),
+                    _ => None,
+                })
+                .collect(),
+            _ => Vec::new(),
+        })
+        .unwrap_or_default();
+    let mut substitute = SubstituteParams(params.iter().cloned().zip(args).collect());
+
+    supertraits.iter()
+        .map(|supertrait| match syn::parse_str::<syn::Path>(supertrait) {
+            Ok(mut path) => {
+                substitute.visit_path_mut(&mut path);
+                render_tokens(&path)
+            }
+            Err(_) => supertrait.clone(),
+        })
+        .collect()
+}
+
+/// Render a path or type as compact source text (`a::B<T>`), keeping spaces only
+/// where they separate two words (`dyn Trait`)
+fn render_tokens(tokens: &impl ToTokens) -> String {
+    let raw = tokens.to_token_stream().to_string();
+    let is_word = |c: char| c.is_alphanumeric() || c == '_';
+    let mut rendered = String::with_capacity(raw.len());
+    let mut chars = raw.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c == ' ' {
+            let prev_is_word = rendered.chars().last().is_some_and(is_word);
+            let next_is_word = chars.peek().is_some_and(|&next| is_word(next));
+            if prev_is_word && next_is_word {
+                rendered.push(' ');
+            }
+        } else {
+            rendered.push(c);
+        }
+    }
+    rendered
+}
+
+/// A crate of a workspace, covering every file below its root
+#[derive(Clone, Debug)]
+pub struct Crate {
+    /// Package name, as in `Cargo.toml`
+    pub name: String,
+    /// Directory holding the crate's `Cargo.toml`
+    pub root: PathBuf,
+}
+
+impl Crate {
+    /// The name other crates refer to it by (`my_crate` for `my-crate`)
+    pub fn key(&self) -> String {
+        self.name.replace('-', "_")
+    }
+
+    /// The innermost crate whose root holds `path`
+    pub fn containing<'a>(crates: &'a [Crate], path: &Path) -> Option<&'a Crate> {
+        crates.iter()
+            .filter(|krate| path.starts_with(&krate.root))
+            .max_by_key(|krate| krate.root.components().count())
+    }
+}
+
+/// Settings that change which items are analyzed and how they are combined into the graph
+#[derive(Clone)]
+pub struct Options {
+    /// Descend into subdirectories of the target
+    pub recursive: bool,
+    /// Keep each instantiation of a generic trait (`Trait<u8>`, `Trait<u16>`) as its
+    /// own node instead of folding them into the declared trait
+    pub generic_instances: bool,
+    /// Skip `#[cfg(test)]` items and test/bench files
+    pub no_tests: bool,
+    /// Skip traits, impls and derives declared inside function bodies
+    pub no_local_items: bool,
+    /// Features used to evaluate `#[cfg(feature = "...")]` conditions
+    pub features: Features,
+    /// Files and directories to skip, matched against the end of each path
+    /// (`generated` skips every directory named `generated`, `src/bin/tool.rs` that file)
+    pub exclude: Vec<PathBuf>,
+    /// Traits left out of every hierarchy, by full path or by name (`Debug` for `std::fmt::Debug`)
+    pub ignored_traits: HashSet<String>,
+    /// Crates of a workspace. Items are keyed with the crate in front (`core::Validator`),
+    /// so references between crates (`use core::Validator`) meet their declarations.
+    pub crates: Vec<Crate>,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Options {
+            recursive: true,
+            generic_instances: false,
+            no_tests: false,
+            no_local_items: false,
+            features: Features::default(),
+            exclude: Vec::new(),
+            ignored_traits: HashSet::new(),
+            crates: Vec::new(),
+        }
+    }
+}
+
+/// The trait graph and type -> trait implementations of a set of files
+pub struct TraitAnalyzer {
+    /// Trait name -> direct supertraits
+    pub trait_graph: HashMap<String, Vec<String>>,
+    /// Type name -> directly implemented traits
+    pub impl_map: HashMap<String, HashSet<String>>,
+    /// Trait name -> traits bounding its associated types, for traits that have any
+    pub associated_bounds: HashMap<String, Vec<String>>,
+    /// Trait name -> items declared by the trait itself
+    pub trait_surfaces: HashMap<String, TraitSurface>,
+    pub dyn_usages: Vec<DynUsage>,
+    /// Traits declared `unsafe trait`
+    pub unsafe_traits: BTreeSet<String>,
+    pub unsafe_impls: Vec<UnsafeImpl>,
+    pub bounded_signatures: Vec<BoundedSignature>,
+    blanket_impls: Vec<BlanketImplInfo>,
+    /// Types declared in the analyzed files
+    declared_types: HashSet<String>,
+    /// Trait impls written out as `impl` blocks, with the trait as referenced
+    explicit_impls: Vec<ImplInfo>,
+    /// Declared trait -> where it is declared
+    trait_locations: HashMap<String, Location>,
+    /// (type, trait) -> first impl of the trait for the type
+    impl_locations: HashMap<(String, String), Location>,
+    /// Declared trait -> its own items that are not object safe
+    object_safety: HashMap<String, Vec<String>>,
+    /// Generic type parameters of each declared trait
+    trait_params: HashMap<String, Vec<String>>,
+    /// Last path segment -> declared traits with that name, for references that
+    /// could not be resolved to a canonical path (e.g. through glob imports)
+    traits_by_name: HashMap<String, Vec<String>>,
+    options: Options,
+}
+
+impl TraitAnalyzer {
+    pub fn new(options: Options) -> Self {
+        TraitAnalyzer {
+            trait_graph: HashMap::new(),
+            impl_map: HashMap::new(),
+            associated_bounds: HashMap::new(),
+            trait_surfaces: HashMap::new(),
+            dyn_usages: Vec::new(),
+            unsafe_traits: BTreeSet::new(),
+            unsafe_impls: Vec::new(),
+            bounded_signatures: Vec::new(),
+            blanket_impls: Vec::new(),
+            declared_types: HashSet::new(),
+            explicit_impls: Vec::new(),
+            trait_locations: HashMap::new(),
+            impl_locations: HashMap::new(),
+            object_safety: HashMap::new(),
+            trait_params: HashMap::new(),
+            traits_by_name: HashMap::new(),
+            options,
+        }
+    }
+
+    /// Name under which a referenced trait enters the graph
+    fn trait_key(&self, trait_name: &str) -> String {
+        if self.options.generic_instances {
+            trait_name.to_string()
+        } else {
+            base_trait_name(trait_name).to_string()
+        }
+    }
+
+    pub fn add_file_analysis(&mut self, file_analyzer: &FileAnalyzer) {
+        // Add traits to graph
+        for trait_info in &file_analyzer.traits {
+            let supertraits = trait_info.supertraits.iter()
+                .map(|supertrait| self.trait_key(supertrait))
+                .collect();
+            self.trait_graph.insert(trait_info.name.clone(), supertraits);
+            self.trait_params.insert(trait_info.name.clone(), trait_info.type_params.clone());
+            self.trait_surfaces.insert(trait_info.name.clone(), trait_info.surface);
+            self.object_safety.insert(trait_info.name.clone(), trait_info.object_safety_violations.clone());
+            self.trait_locations.insert(trait_info.name.clone(), trait_info.location.clone());
+            if trait_info.is_unsafe {
+                self.unsafe_traits.insert(trait_info.name.clone());
+            }
+            let associated_bounds: Vec<String> = trait_info.associated_bounds.iter()
+                .flat_map(|(_, bounds)| bounds)
+                .map(|bound| self.trait_key(bound))
+                .collect();
+            if !associated_bounds.is_empty() {
+                self.associated_bounds.insert(trait_info.name.clone(), associated_bounds);
+            }
+            let short_name = trait_info.name.rsplit("::").next().unwrap_or(&trait_info.name);
+            let declared = self.traits_by_name.entry(short_name.to_string()).or_default();
+            if !declared.contains(&trait_info.name) {
+                declared.push(trait_info.name.clone());
+            }
+        }
+
+        self.declared_types.extend(file_analyzer.declared_types.iter().cloned());
+
+        // Add implementations
+        for impl_info in &file_analyzer.impls {
+            if !impl_info.derived {
+                self.explicit_impls.push(ImplInfo {
+                    type_name: impl_info.type_name.clone(),
+                    trait_name: impl_info.trait_name.clone(),
+                    derived: false,
+                    location: impl_info.location.clone(),
+                });
+            }
+            let trait_name = self.trait_key(&impl_info.trait_name);
+            self.impl_locations
+                .entry((impl_info.type_name.clone(), trait_name.clone()))
+                .or_insert_with(|| impl_info.location.clone());
+            self.impl_map
+                .entry(impl_info.type_name.clone())
+                .or_default()
+                .insert(trait_name);
+        }
+
+        for unsafe_impl in &file_analyzer.unsafe_impls {
+            self.unsafe_impls.push(UnsafeImpl {
+                trait_name: self.trait_key(&unsafe_impl.trait_name),
+                ..unsafe_impl.clone()
+            });
+        }
+
+        self.bounded_signatures.extend(file_analyzer.bounded_signatures.iter().cloned());
+
+        for usage in &file_analyzer.dyn_usages {
+            self.dyn_usages.push(DynUsage {
+                trait_name: self.trait_key(&usage.trait_name),
+                ..usage.clone()
+            });
+        }
+
+        for blanket_impl in &file_analyzer.blanket_impls {
+            self.blanket_impls.push(BlanketImplInfo {
+                trait_name: self.trait_key(&blanket_impl.trait_name),
+                bounds: blanket_impl.bounds.iter().map(|bound| self.trait_key(bound)).collect(),
+                location: blanket_impl.location.clone(),
+            });
+        }
+    }
+
+    /// Types whose deepest chain is longer than `limit`, deepest first
+    pub fn types_over_limit(&self, limit: usize) -> Vec<(&str, Vec<String>)> {
+        let mut offending: Vec<(&str, Vec<String>)> = self.impl_map.keys()
+            .map(|type_name| (type_name.as_str(), self.deepest_chain(type_name)))
+            .filter(|(_, chain)| chain.len() > limit)
+            .collect();
+        offending.sort_by(|a, b| b.1.len().cmp(&a.1.len()).then(a.0.cmp(b.0)));
+        offending
+    }
+
+    /// Groups of declared traits that are each other's supertraits (`trait A: B {}` with
+    /// `trait B: A {}`, or `trait A: A {}`), sorted by name. Depths are cut short where a
+    /// chain would go around such a cycle.
+    pub fn supertrait_cycles(&self) -> Vec<Vec<String>> {
+        let reachable: BTreeMap<&String, HashSet<String>> = self.trait_graph.keys()
+            .map(|trait_name| (trait_name, self.reachable_supertraits(trait_name)))
+            .collect();
+
+        let mut cycles = Vec::new();
+        let mut in_cycle: HashSet<String> = HashSet::new();
+        for (trait_name, reach) in &reachable {
+            if in_cycle.contains(trait_name.as_str()) || !reach.contains(trait_name.as_str()) {
+                continue;
+            }
+            let cycle: Vec<String> = reachable.iter()
+                .filter(|(other, other_reach)| reach.contains(other.as_str()) && other_reach.contains(trait_name.as_str()))
+                .map(|(other, _)| other.to_string())
+                .collect();
+            in_cycle.extend(cycle.iter().cloned());
+            cycles.push(cycle);
+        }
+        cycles
+    }
+
+    /// Base names of all traits reachable through one or more supertrait edges
+    fn reachable_supertraits(&self, trait_name: &str) -> HashSet<String> {
+        let mut seen = HashSet::new();
+        let mut pending = self.supertraits_of(trait_name);
+        while let Some(supertrait) = pending.pop() {
+            if seen.insert(base_trait_name(&supertrait).to_string()) {
+                pending.extend(self.supertraits_of(&supertrait));
+            }
+        }
+        seen
+    }
+
+    /// Where a declared trait is declared
+    pub fn trait_location(&self, trait_name: &str) -> Option<&Location> {
+        self.trait_locations.get(base_trait_name(&self.canonical_trait(trait_name)))
+    }
+
+    /// Types implementing a trait directly, through a derive or through a blanket impl
+    pub fn implementors(&self, trait_name: &str) -> Vec<&str> {
+        let mut implementors: Vec<&str> = self.impl_map.keys()
+            .filter(|type_name| {
+                self.implemented_traits(type_name).iter().any(|implemented| base_trait_name(implemented) == trait_name)
+            })
+            .map(String::as_str)
+            .collect();
+        implementors.sort();
+        implementors
+    }
+
+    /// The `count` deepest types with their deepest chains, deepest first
+    pub fn deepest_types(&self, count: usize) -> Vec<(&str, Vec<String>)> {
+        let mut types = self.types_over_limit(0);
+        types.truncate(count);
+        types
+    }
+
+    /// Where the impl of `trait_name` (as returned by `implemented_traits`) for a type is
+    /// written, falling back to the blanket impl that provides it
+    pub fn impl_location(&self, type_name: &str, trait_name: &str) -> Option<&Location> {
+        self.impl_map.get(type_name)
+            .into_iter()
+            .flatten()
+            .find(|implemented| self.canonical_trait(implemented) == trait_name)
+            .and_then(|implemented| self.impl_locations.get(&(type_name.to_string(), implemented.clone())))
+            .or_else(|| {
+                self.blanket_impls.iter()
+                    .find(|blanket_impl| self.canonical_trait(&blanket_impl.trait_name) == trait_name)
+                    .map(|blanket_impl| &blanket_impl.location)
+            })
+    }
+
+    /// Traits a type implements, directly or through blanket impls whose bounds it meets
+    pub fn implemented_traits(&self, type_name: &str) -> BTreeSet<String> {
+        let mut traits: BTreeSet<String> = self.impl_map.get(type_name)
+            .into_iter()
+            .flatten()
+            .map(|trait_name| self.canonical_trait(trait_name))
+            .collect();
+
+        // Blanket impls can satisfy each other's bounds, so apply them until nothing changes
+        loop {
+            let satisfied = self.with_supertraits(&traits);
+            let before = traits.len();
+            for blanket_impl in &self.blanket_impls {
+                let bounds_met = blanket_impl.bounds.iter()
+                    .all(|bound| satisfied.contains(base_trait_name(&self.canonical_trait(bound))));
+                if bounds_met {
+                    traits.insert(self.canonical_trait(&blanket_impl.trait_name));
+                }
+            }
+            if traits.len() == before {
+                traits.retain(|trait_name| !self.is_ignored(trait_name));
+                return traits;
+            }
+        }
+    }
+
+    /// Base names of the given traits and all of their supertraits
+    fn with_supertraits(&self, traits: &BTreeSet<String>) -> HashSet<String> {
+        let mut seen = HashSet::new();
+        let mut pending: Vec<String> = traits.iter().cloned().collect();
+        while let Some(trait_name) = pending.pop() {
+            if seen.insert(base_trait_name(&trait_name).to_string()) {
+                pending.extend(self.supertraits_of(&trait_name));
+            }
+        }
+        seen
+    }
+
+    /// The declared trait a reference names. References that were not resolved to a
+    /// declared trait fall back to the only declared trait with the same name, if any.
+    fn canonical_trait(&self, trait_name: &str) -> String {
+        let base = base_trait_name(trait_name);
+        if self.trait_graph.contains_key(base) {
+            return trait_name.to_string();
+        }
+        let short_name = base.rsplit("::").next().unwrap_or(base);
+        match self.traits_by_name.get(short_name).map(Vec::as_slice) {
+            Some([declared]) => format!("{}{}", declared, &trait_name[base.len()..]),
+            _ => trait_name.to_string(),
+        }
+    }
+
+    /// Direct supertraits of a referenced trait. An instantiation such as
+    /// `GenericTrait<i32>` gets its declared supertraits with `T` replaced by `i32`.
+    pub fn supertraits_of(&self, trait_name: &str) -> Vec<String> {
+        let supertraits = if let Some(supertraits) = self.trait_graph.get(trait_name) {
+            supertraits.clone()
+        } else {
+            let base = base_trait_name(trait_name);
+            match (self.trait_graph.get(base), self.trait_params.get(base)) {
+                (Some(supertraits), Some(params)) => instantiate_supertraits(supertraits, params, trait_name),
+                (Some(supertraits), None) => supertraits.clone(),
+                _ => known_traits::supertraits(base)
+                    .map(|supertraits| supertraits.iter().map(|name| name.to_string()).collect())
+                    .unwrap_or_default(),
+            }
+        };
+        supertraits.iter()
+            .filter(|supertrait| !self.is_ignored(supertrait))
+            .map(|supertrait| self.canonical_trait(supertrait))
+            .collect()
+    }
+
+    /// Whether a trait is in `Options::ignored_traits`, by its path or its name
+    fn is_ignored(&self, trait_name: &str) -> bool {
+        let ignored = &self.options.ignored_traits;
+        let base = base_trait_name(trait_name);
+        !ignored.is_empty()
+            && (ignored.contains(base) || base.rsplit("::").next().is_some_and(|name| ignored.contains(name)))
+    }
+
+    pub fn calculate_max_depth(&self, type_name: &str) -> usize {
+        self.deepest_chain(type_name).len()
+    }
+
+    /// The longest supertrait chain among a type's traits, starting from the
+    /// implemented trait (`[C, B, A]` for `impl C for T` with `C: B`, `B: A`).
+    /// Ties go to the alphabetically first trait so the output is stable.
+    pub fn deepest_chain(&self, type_name: &str) -> Vec<String> {
+        let mut visited = HashSet::new();
+        let mut deepest = Vec::new();
+
+        for trait_name in self.implemented_traits(type_name) {
+            let chain = self.dfs_trait_chain(&trait_name, &mut visited);
+            if chain.len() > deepest.len() {
+                deepest = chain;
+            }
+        }
+
+        deepest
+    }
+
+    /// Length of the longest supertrait chain starting at a trait, counting the trait itself
+    pub fn trait_depth(&self, trait_name: &str) -> usize {
+        self.trait_chain(trait_name).len()
+    }
+
+    /// The longest supertrait chain starting at a trait (`[C, B, A]` for `C: B`, `B: A`)
+    pub fn trait_chain(&self, trait_name: &str) -> Vec<String> {
+        self.dfs_trait_chain(trait_name, &mut HashSet::new())
+    }
+
+    fn dfs_trait_chain(&self, trait_name: &str, visited: &mut HashSet<String>) -> Vec<String> {
+        if !visited.insert(trait_name.to_string()) {
+            return Vec::new();
+        }
+
+        let mut deepest = Vec::new();
+        for supertrait in self.supertraits_of(trait_name) {
+            let chain = self.dfs_trait_chain(&supertrait, visited);
+            if chain.len() > deepest.len() {
+                deepest = chain;
+            }
+        }
+
+        visited.remove(trait_name);
+        deepest.insert(0, trait_name.to_string());
+        deepest
+    }
+
+    /// The longest chain from a trait that follows both supertraits and the bounds on
+    /// associated types: `[Store, Database, Storage]` for `trait Store { type Backend: Database; }`
+    /// with `Database: Storage`
+    pub fn associated_bound_chain(&self, trait_name: &str) -> Vec<String> {
+        self.dfs_associated_chain(trait_name, &mut HashSet::new())
+    }
+
+    fn dfs_associated_chain(&self, trait_name: &str, visited: &mut HashSet<String>) -> Vec<String> {
+        if !visited.insert(trait_name.to_string()) {
+            return Vec::new();
+        }
+
+        let associated = self.associated_bounds.get(base_trait_name(trait_name)).into_iter().flatten()
+            .map(|bound| self.canonical_trait(bound));
+        let mut deepest = Vec::new();
+        for next in self.supertraits_of(trait_name).into_iter().chain(associated) {
+            let chain = self.dfs_associated_chain(&next, visited);
+            if chain.len() > deepest.len() {
+                deepest = chain;
+            }
+        }
+
+        visited.remove(trait_name);
+        deepest.insert(0, trait_name.to_string());
+        deepest
+    }
+
+    /// Items of a trait together with those of all its declared supertraits: what an
+    /// implementor has to know about
+    pub fn inherited_surface(&self, trait_name: &str) -> TraitSurface {
+        let mut surface = TraitSurface::default();
+        for name in self.with_supertraits(&BTreeSet::from([trait_name.to_string()])) {
+            if let Some(own) = self.trait_surfaces.get(&name) {
+                surface.add(own);
+            }
+        }
+        surface
+    }
+
+    /// Why `dyn Trait` cannot be formed for a trait, through its own items or its
+    /// supertraits; empty for object-safe traits and for traits mtd knows nothing about
+    pub fn object_safety_violations(&self, trait_name: &str) -> Vec<String> {
+        let trait_name = self.canonical_trait(trait_name);
+        let base = base_trait_name(&trait_name);
+        let mut violations = self.object_safety.get(base).cloned().unwrap_or_default();
+
+        let mut supertraits: Vec<String> = self.with_supertraits(&BTreeSet::from([trait_name.clone()]))
+            .into_iter()
+            .filter(|supertrait| supertrait != base)
+            .collect();
+        supertraits.sort();
+        for supertrait in supertraits {
+            if supertrait.rsplit("::").next() == Some("Sized") {
+                violations.push("requires `Self: Sized`".to_string());
+            } else if known_traits::is_not_object_safe(&supertrait)
+                || self.object_safety.get(&supertrait).is_some_and(|own| !own.is_empty())
+            {
+                violations.push(format!("supertrait `{}` is not object safe", supertrait));
+            }
+        }
+        violations
+    }
+
+    /// Traits used as trait objects, most used first
+    pub fn dyn_traits(&self) -> Vec<DynTraitUsage> {
+        let mut by_trait: BTreeMap<String, DynTraitUsage> = BTreeMap::new();
+        for usage in &self.dyn_usages {
+            let trait_name = self.canonical_trait(&usage.trait_name);
+            let entry = by_trait.entry(trait_name.clone()).or_insert_with(|| DynTraitUsage {
+                depth: self.trait_depth(&trait_name),
+                object_safety_violations: self.object_safety_violations(&trait_name),
+                trait_name,
+                uses: 0,
+                containers: BTreeMap::new(),
+                locations: Vec::new(),
+            });
+            entry.uses += 1;
+            *entry.containers.entry(usage.container.clone()).or_default() += 1;
+            entry.locations.push(usage.location.to_string());
+        }
+
+        let mut traits: Vec<DynTraitUsage> = by_trait.into_values().collect();
+        traits.sort_by(|a, b| b.uses.cmp(&a.uses).then(b.depth.cmp(&a.depth)));
+        traits
+    }
+
+    /// Impls of a trait or for a type declared outside the analyzed files
+    /// (`impl From<State> for Vec<u8>`, `impl serde::Serialize for External`), in the
+    /// order they were found. Derived impls and ignored traits are left out.
+    pub fn foreign_impls(&self) -> Vec<ForeignImpl> {
+        self.explicit_impls.iter()
+            .filter(|impl_info| !self.is_ignored(&impl_info.trait_name))
+            .map(|impl_info| {
+                let trait_key = self.canonical_trait(&self.trait_key(&impl_info.trait_name));
+                ForeignImpl {
+                    trait_name: impl_info.trait_name.clone(),
+                    type_name: impl_info.type_name.clone(),
+                    foreign_trait: !self.trait_graph.contains_key(base_trait_name(&trait_key)),
+                    foreign_type: !self.is_declared_type(&impl_info.type_name),
+                    location: impl_info.location.clone(),
+                }
+            })
+            .filter(|foreign| foreign.foreign_trait || foreign.foreign_type)
+            .collect()
+    }
+
+    /// Whether a type is declared in the analyzed files; names that could not be
+    /// resolved to a path (e.g. through glob imports) match by their last segment
+    fn is_declared_type(&self, type_name: &str) -> bool {
+        let base = base_trait_name(type_name);
+        self.declared_types.contains(base)
+            || (!base.contains("::")
+                && self.declared_types.iter().any(|declared| declared.rsplit("::").next() == Some(base)))
+    }
+
+    /// Generic signatures with the most bounds first, then the deepest nesting
+    pub fn bound_heavy_signatures(&self) -> Vec<&BoundedSignature> {
+        let mut signatures: Vec<&BoundedSignature> = self.bounded_signatures.iter().collect();
+        signatures.sort_by(|a, b| {
+            b.complexity.bounds.cmp(&a.complexity.bounds)
+                .then(b.complexity.nesting.cmp(&a.complexity.nesting))
+                .then(a.name.cmp(&b.name))
+        });
+        signatures
+    }
+
+    pub fn get_summary(&self) -> AnalysisSummary {
+        let mut max_depth = 0;
+        for type_name in self.impl_map.keys() {
+            max_depth = max_depth.max(self.calculate_max_depth(type_name));
+        }
+        let max_associated_bound_depth = self.trait_graph.keys()
+            .map(|trait_name| self.associated_bound_chain(trait_name).len())
+            .max()
+            .unwrap_or(0);
+
+        let mut surface = TraitSurface::default();
+        for own in self.trait_surfaces.values() {
+            surface.add(own);
+        }
+        let max_inherited_methods = self.trait_graph.keys()
+            .map(|trait_name| self.inherited_surface(trait_name).methods())
+            .max()
+            .unwrap_or(0);
+
+        AnalysisSummary {
+            max_depth,
+            max_associated_bound_depth,
+            required_method_count: surface.required_methods,
+            provided_method_count: surface.provided_methods,
+            associated_item_count: surface.associated_types + surface.associated_consts,
+            max_inherited_methods,
+            max_signature_bounds: self.bounded_signatures.iter()
+                .map(|signature| signature.complexity.bounds)
+                .max()
+                .unwrap_or(0),
+            trait_count: self.trait_graph.len(),
+            impl_count: self.impl_map.len(),
+        }
+    }
+}
+
+/// An impl on the boundary of the analyzed code, where the trait, the type or both
+/// come from elsewhere
+#[derive(Serialize)]
+pub struct ForeignImpl {
+    pub trait_name: String,
+    pub type_name: String,
+    pub foreign_trait: bool,
+    pub foreign_type: bool,
+    pub location: Location,
+}
+
+impl ForeignImpl {
+    /// `trait`, `type`, or `trait and type`
+    pub fn foreign_side(&self) -> &'static str {
+        match (self.foreign_trait, self.foreign_type) {
+            (true, true) => "trait and type",
+            (true, false) => "trait",
+            _ => "type",
+        }
+    }
+}
+
+/// How often and where one trait is used as a trait object
+#[derive(Serialize)]
+pub struct DynTraitUsage {
+    pub trait_name: String,
+    pub uses: usize,
+    /// Trait depth of the dispatched trait
+    pub depth: usize,
+    /// Container (`Box`, `&`, `Arc`, ...) -> number of uses
+    pub containers: BTreeMap<String, usize>,
+    /// `file:line` of every use
+    pub locations: Vec<String>,
+    /// Why the trait is not object safe, although it is used as one; empty if it is
+    pub object_safety_violations: Vec<String>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct AnalysisSummary {
+    pub max_depth: usize,
+    /// Deepest chain through supertraits and associated type bounds of any declared trait
+    pub max_associated_bound_depth: usize,
+    /// Methods without a default body, over all declared traits
+    pub required_method_count: usize,
+    /// Methods with a default body, over all declared traits
+    pub provided_method_count: usize,
+    /// Associated types and consts, over all declared traits
+    pub associated_item_count: usize,
+    /// Most methods any declared trait has together with its supertraits
+    pub max_inherited_methods: usize,
+    /// Most trait bounds on the generics of any function, method or impl
+    pub max_signature_bounds: usize,
+    pub trait_count: usize,
+    pub impl_count: usize,
+}
+
+
+/// Result of analyzing a directory tree or a single file
+pub struct TraitReport {
+    /// All analyzed files combined
+    pub analyzer: TraitAnalyzer,
+    /// Per-file results, in the order the files were visited
+    pub files: Vec<FileAnalyzer>,
+    /// Files that could not be read or parsed
+    pub errors: Vec<(PathBuf, io::Error)>,
+    options: Options,
+}
+
+impl TraitReport {
+    pub fn summary(&self) -> AnalysisSummary {
+        self.analyzer.get_summary()
+    }
+
+    /// An analyzer over only the files accepted by `include`, e.g. one file or the
+    /// files directly inside one directory
+    pub fn analyzer_for(&self, include: impl Fn(&Path) -> bool) -> TraitAnalyzer {
+        let mut analyzer = TraitAnalyzer::new(self.options.clone());
+        for file in self.files.iter().filter(|file| include(file.path())) {
+            analyzer.add_file_analysis(file);
+        }
+        analyzer
+    }
+}
+
+/// Analyze every `.rs` file under `path` (or `path` itself if it is a file)
+pub fn analyze(path: &Path, options: Options) -> io::Result<TraitReport> {
+    let mut report = TraitReport {
+        analyzer: TraitAnalyzer::new(options.clone()),
+        files: Vec::new(),
+        errors: Vec::new(),
+        options: options.clone(),
+    };
+
+    let files = walk::rust_files(path, options.recursive, &mut |path| {
+        (options.no_tests && walk::is_test_path(path)) || options.exclude.iter().any(|excluded| path.ends_with(excluded))
+    })?;
+    for file in files {
+        let mut file_analyzer = FileAnalyzer::new(&options);
+        match file_analyzer.analyze_file(&file) {
+            Ok(()) => {
+                report.analyzer.add_file_analysis(&file_analyzer);
+                report.files.push(file_analyzer);
+            }
+            Err(e) => report.errors.push((file, e)),
+        }
+    }
+
+    Ok(report)
+}
+
@@ -1,391 +1,348 @@
-use std::collections::{HashMap, HashSet};
+mod baseline;
+mod config;
+mod csv;
+mod dot;
+mod expand;
+mod markdown;
+mod sarif;
+mod workspace;
+
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 use std::env;
 use std::fs;
 use std::io;
 use std::path::{Path, PathBuf};
 
-struct TraitInfo {
-    name: String,
-    supertraits: Vec<String>,
+use baseline::{Baseline, DepthChange};
+use clap::{Args, CommandFactory, ErrorKind, Parser, Subcommand};
+use mtd::{
+    AnalysisSummary, BoundedSignature, Crate, DynTraitUsage, Features, ForeignImpl, Location, Options, TraitAnalyzer,
+    TraitSurface, UnsafeImpl,
+};
+use serde::Serialize;
+use trr_core::output::{self, Format};
+
+/// Signatures listed in the text and Markdown bound reports without `-v`
+const BOUND_HEAVY_SHOWN: usize = 10;
+
+/// Structured form of the analysis for `--format json`
+#[derive(Serialize)]
+struct JsonReport<'a> {
+    summary: AnalysisSummary,
+    /// Trait name -> direct supertraits
+    trait_graph: BTreeMap<&'a str, &'a [String]>,
+    /// Trait name -> depth through supertraits and associated type bounds, for traits
+    /// whose associated types have bounds
+    associated_bound_depths: BTreeMap<&'a str, usize>,
+    /// Type name -> implemented traits, including those from blanket impls
+    impl_map: BTreeMap<&'a str, BTreeSet<String>>,
+    /// Type name -> maximum trait depth
+    type_depths: BTreeMap<&'a str, usize>,
+    /// Trait name -> items it declares, and methods including its supertraits'
+    trait_surfaces: BTreeMap<&'a str, TraitSurfaceReport>,
+    /// Declared traits that are not object safe -> why
+    object_safety: BTreeMap<&'a str, Vec<String>>,
+    /// Traits used as trait objects, most used first
+    dyn_traits: Vec<DynTraitUsage>,
+    /// Groups of traits that are each other's supertraits
+    supertrait_cycles: Vec<Vec<CycleMember<'a>>>,
+    unsafe_traits: Vec<UnsafeTraitReport<'a>>,
+    unsafe_impls: &'a [UnsafeImpl],
+    /// Impls where the trait or the type is declared outside the analyzed files
+    foreign_impls: Vec<ForeignImpl>,
+    /// Generic functions, methods and impls with trait bounds, most bounds first
+    bound_heavy_signatures: Vec<&'a BoundedSignature>,
+    /// Declared trait -> where it is declared
+    trait_locations: BTreeMap<&'a str, &'a Location>,
+    /// Type name -> implemented trait -> the impl, derive or blanket impl providing it
+    impl_locations: BTreeMap<&'a str, BTreeMap<String, &'a Location>>,
+    /// Type name -> deepest supertrait chain, starting from the implemented trait
+    type_chains: BTreeMap<&'a str, Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    files: Option<BTreeMap<String, &'a AnalysisSummary>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    directories: Option<BTreeMap<String, AnalysisSummary>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    target: Option<AnalysisSummary>,
+    /// The `--top` deepest types, deepest first
+    #[serde(skip_serializing_if = "Option::is_none")]
+    top: Option<Vec<TopType<'a>>>,
+    /// Crate name -> summary, when the target is a workspace with several crates
+    #[serde(skip_serializing_if = "Option::is_none")]
+    crates: Option<BTreeMap<&'a str, AnalysisSummary>>,
+    /// The same analysis on the `cargo expand` output (`--expand`)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    expanded: Option<ExpandedReport>,
+    /// Types whose depth differs from `--baseline`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    baseline_changes: Option<Vec<DepthChange>>,
 }
 
-struct ImplInfo {
-    type_name: String,
+#[derive(Serialize)]
+struct DiffReport<'a> {
+    old_revision: &'a str,
+    new_revision: &'a str,
+    old_summary: AnalysisSummary,
+    new_summary: AnalysisSummary,
+    changes: Vec<DepthChange>,
+}
+
+#[derive(Serialize)]
+struct CycleMember<'a> {
     trait_name: String,
+    location: Option<&'a Location>,
 }
 
-struct FileAnalyzer {
-    traits: Vec<TraitInfo>,
-    impls: Vec<ImplInfo>,
+#[derive(Serialize)]
+struct UnsafeTraitReport<'a> {
+    trait_name: &'a str,
+    location: Option<&'a Location>,
+    /// Types implementing the trait, directly or through derives and blanket impls
+    implementors: Vec<&'a str>,
 }
 
-impl FileAnalyzer {
-    fn new() -> Self {
-        FileAnalyzer {
-            traits: Vec::new(),
-            impls: Vec::new(),
-        }
-    }
+#[derive(Serialize)]
+struct TopType<'a> {
+    type_name: &'a str,
+    depth: usize,
+    chain: Vec<String>,
+    /// `file:line` of the impl that starts the chain
+    location: Option<String>,
+}
 
-    fn analyze_file(&mut self, path: &Path) -> io::Result<()> {
-        let content = fs::read_to_string(path)?;
-        
-        // Parse the entire file content, handling multiline declarations
-        self.parse_content(&content);
-        
-        Ok(())
-    }
+#[derive(Serialize)]
+struct ExpandedReport {
+    summary: AnalysisSummary,
+    type_depths: BTreeMap<String, usize>,
+}
 
-    fn parse_content(&mut self, content: &str) {
-        let mut chars = content.chars().peekable();
-        let mut current_line = String::new();
-        let mut in_multiline_declaration = false;
-        let mut brace_depth = 0;
-        let mut declaration_buffer = String::new();
-
-        while let Some(ch) = chars.next() {
-            match ch {
-                '\n' | '\r' => {
-                    if !in_multiline_declaration {
-                        self.process_line(&current_line.trim());
-                        current_line.clear();
-                    } else {
-                        declaration_buffer.push(' ');
-                    }
-                }
-                '{' => {
-                    current_line.push(ch);
-                    if in_multiline_declaration {
-                        declaration_buffer.push(ch);
-                        brace_depth += 1;
-                        if brace_depth == 1 {
-                            // End of declaration, process it
-                            self.process_line(&declaration_buffer.trim());
-                            declaration_buffer.clear();
-                            in_multiline_declaration = false;
-                            brace_depth = 0;
-                        }
-                    }
-                }
-                '}' => {
-                    current_line.push(ch);
-                    if in_multiline_declaration && brace_depth > 0 {
-                        declaration_buffer.push(ch);
-                        brace_depth -= 1;
-                    }
-                }
-                _ => {
-                    current_line.push(ch);
-                    if in_multiline_declaration {
-                        declaration_buffer.push(ch);
-                    }
-                }
-            }
+#[derive(Serialize)]
+struct TraitSurfaceReport {
+    #[serde(flatten)]
+    surface: TraitSurface,
+    /// Methods of the trait and all its supertraits
+    inherited_methods: usize,
+}
 
-            // Check if we're starting a multiline declaration
-            if !in_multiline_declaration && (
-                self.is_trait_declaration_start(&current_line) || 
-                self.is_impl_declaration_start(&current_line)
-            ) {
-                // Check if the line ends without opening brace - might be multiline
-                let trimmed = current_line.trim();
-                if !trimmed.contains('{') && !trimmed.ends_with(';') {
-                    in_multiline_declaration = true;
-                    declaration_buffer = current_line.clone();
-                    current_line.clear();
-                }
-            }
-        }
 
-        // Process any remaining line
-        if !current_line.trim().is_empty() {
-            self.process_line(&current_line.trim());
-        }
-    }
+#[derive(Parser, Debug)]
+#[clap(author, version, about = "Trait hierarchy depth of Rust code")]
+#[clap(args_conflicts_with_subcommands = true)]
+#[clap(after_help = "If DIR is not specified, the current directory (or the repository root with --repo) is used.\n\
+`diff` compares two Git revisions of DIR (text or json), with --fail-above applying to newly introduced depth.")]
+struct Cli {
+    #[clap(subcommand)]
+    command: Option<CliCommand>,
 
-    fn is_trait_declaration_start(&self, line: &str) -> bool {
-        let trimmed = line.trim();
-        // Handle all visibility modifiers and unsafe
-        trimmed.starts_with("trait ") ||
-        trimmed.starts_with("pub trait ") ||
-        trimmed.starts_with("pub(crate) trait ") ||
-        trimmed.starts_with("pub(super) trait ") ||
-        trimmed.starts_with("pub(self) trait ") ||
-        trimmed.starts_with("pub(in ") && trimmed.contains(") trait ") ||
-        trimmed.starts_with("unsafe trait ") ||
-        trimmed.starts_with("pub unsafe trait ") ||
-        trimmed.starts_with("pub(crate) unsafe trait ") ||
-        trimmed.starts_with("pub(super) unsafe trait ")
-    }
+    #[clap(flatten)]
+    args: AnalyzeArgs,
+}
 
-    fn is_impl_declaration_start(&self, line: &str) -> bool {
-        let trimmed = line.trim();
-        trimmed.starts_with("impl ") ||
-        trimmed.starts_with("unsafe impl ")
-    }
+#[derive(Subcommand, Debug)]
+enum CliCommand {
+    /// Compare the trait depth of two Git revisions of DIR
+    Diff {
+        /// Revision to compare against
+        #[clap(value_name = "OLD_REV")]
+        old: String,
+
+        /// Revision checked against OLD_REV
+        #[clap(value_name = "NEW_REV")]
+        new: String,
+
+        #[clap(flatten)]
+        args: AnalyzeArgs,
+    },
+}
 
-    fn process_line(&mut self, line: &str) {
-        if self.is_trait_declaration_start(line) {
-            if let Some(trait_info) = self.parse_trait_declaration(line) {
-                self.traits.push(trait_info);
-            }
-        } else if self.is_impl_declaration_start(line) {
-            if let Some(impl_info) = self.parse_impl_declaration(line) {
-                self.impls.push(impl_info);
-            }
-        }
-    }
+#[derive(Args, Debug)]
+struct AnalyzeArgs {
+    /// Directory to analyze (relative to --repo when given)
+    #[clap(value_name = "DIR")]
+    dir: Option<PathBuf>,
 
-    fn parse_trait_declaration(&self, line: &str) -> Option<TraitInfo> {
-        // Remove all visibility and safety modifiers
-        let mut cleaned = line.trim();
-        
-        // Remove visibility modifiers
-        if cleaned.starts_with("pub(") {
-            if let Some(end_paren) = cleaned.find(')') {
-                cleaned = &cleaned[end_paren + 1..].trim();
-            }
-        } else if cleaned.starts_with("pub ") {
-            cleaned = &cleaned[4..];
-        }
-        
-        // Remove unsafe modifier
-        if cleaned.starts_with("unsafe ") {
-            cleaned = &cleaned[7..];
-        }
-        
-        // Remove trait keyword
-        if cleaned.starts_with("trait ") {
-            cleaned = &cleaned[6..];
-        } else {
-            return None;
-        }
+    /// Show detailed analysis for each file
+    #[clap(short, long)]
+    verbose: bool,
 
-        // Find the trait name and supertraits
-        let colon_pos = cleaned.find(':');
-        let brace_pos = cleaned.find('{');
-        
-        let name_end = match (colon_pos, brace_pos) {
-            (Some(colon), Some(brace)) => colon.min(brace),
-            (Some(colon), None) => colon,
-            (None, Some(brace)) => brace,
-            (None, None) => cleaned.len(),
-        };
+    /// Show maximum trait depth per file
+    #[clap(short, long)]
+    files: bool,
 
-        let name = cleaned[..name_end].trim().to_string();
-        if name.is_empty() {
-            return None;
-        }
+    /// Show maximum trait depth per directory (recursive)
+    #[clap(short, long)]
+    dirs: bool,
 
-        let supertraits = if let Some(colon_pos) = colon_pos {
-            let supertrait_part = if let Some(brace_pos) = brace_pos {
-                &cleaned[colon_pos + 1..brace_pos]
-            } else {
-                &cleaned[colon_pos + 1..]
-            };
-            
-            supertrait_part
-                .split('+')
-                .map(|s| self.clean_identifier(s.trim()))
-                .filter(|s| !s.is_empty())
-                .collect()
-        } else {
-            Vec::new()
-        };
+    /// Show analysis for the target directory only (non-recursive)
+    #[clap(short = 't', long = "target")]
+    target_only: bool,
 
-        Some(TraitInfo {
-            name: self.clean_identifier(&name),
-            supertraits,
-        })
-    }
+    /// Output format [default: text]
+    #[clap(long, value_enum)]
+    format: Option<Format>,
 
-    fn parse_impl_declaration(&self, line: &str) -> Option<ImplInfo> {
-        let mut cleaned = line.trim();
-        
-        // Remove unsafe modifier
-        if cleaned.starts_with("unsafe ") {
-            cleaned = &cleaned[7..];
-        }
-        
-        // Remove impl keyword
-        if cleaned.starts_with("impl ") {
-            cleaned = &cleaned[5..];
-        } else {
-            return None;
-        }
+    /// Clone a Git URL (or use a local checkout) and analyze DIR inside it
+    #[clap(long, value_name = "REPO_URL_OR_PATH")]
+    repo: Option<String>,
 
-        // Handle cases like "impl Trait for Type"
-        if let Some(for_idx) = cleaned.find(" for ") {
-            let trait_part = &cleaned[..for_idx];
-            let type_part = &cleaned[for_idx + 5..];
-            
-            let trait_name = self.clean_identifier(trait_part.trim());
-            let type_name = self.clean_identifier(type_part.trim());
-            
-            if !trait_name.is_empty() && !type_name.is_empty() {
-                return Some(ImplInfo {
-                    type_name,
-                    trait_name,
-                });
-            }
-        }
-        
-        None
-    }
+    /// Branch, tag or commit of --repo to analyze
+    #[clap(long, value_name = "REV", requires = "repo")]
+    rev: Option<String>,
 
-    fn clean_identifier(&self, identifier: &str) -> String {
-        identifier
-            .trim()
-            .trim_end_matches('{')
-            .trim_end_matches('}')
-            .trim()
-            .to_string()
-    }
-}
+    /// List only the N deepest types, with their chains and impl locations
+    #[clap(long, value_name = "N")]
+    top: Option<usize>,
 
-struct TraitAnalyzer {
-    trait_graph: HashMap<String, Vec<String>>,
-    impl_map: HashMap<String, HashSet<String>>,
-}
+    /// Exit with status 1 and list the offending types if any type is deeper than DEPTH
+    #[clap(long, value_name = "DEPTH")]
+    fail_above: Option<usize>,
 
-impl TraitAnalyzer {
-    fn new() -> Self {
-        TraitAnalyzer {
-            trait_graph: HashMap::new(),
-            impl_map: HashMap::new(),
-        }
-    }
+    /// Write the types over the --fail-above limit as SARIF results
+    #[clap(long, value_name = "FILE")]
+    sarif: Option<PathBuf>,
 
-    fn add_file_analysis(&mut self, file_analyzer: &FileAnalyzer) {
-        // Add traits to graph
-        for trait_info in &file_analyzer.traits {
-            self.trait_graph.insert(
-                trait_info.name.clone(),
-                trait_info.supertraits.clone(),
-            );
-        }
+    /// Save the depth of every type as a baseline
+    #[clap(long, value_name = "FILE")]
+    write_baseline: Option<PathBuf>,
 
-        // Add implementations
-        for impl_info in &file_analyzer.impls {
-            self.impl_map
-                .entry(impl_info.type_name.clone())
-                .or_insert_with(|| HashSet::new())
-                .insert(impl_info.trait_name.clone());
-        }
-    }
+    /// Report changes since a baseline; --fail-above and --sarif skip types no deeper than in it
+    #[clap(long, value_name = "FILE")]
+    baseline: Option<PathBuf>,
 
-    fn calculate_max_depth(&self, type_name: &str) -> usize {
-        let mut visited = HashSet::new();
-        let mut max_depth = 0;
+    /// Write the trait hierarchy and type implementations as a Graphviz DOT file
+    #[clap(long, value_name = "FILE")]
+    dot: Option<PathBuf>,
 
-        if let Some(traits) = self.impl_map.get(type_name) {
-            for trait_name in traits {
-                let depth = self.dfs_trait_depth(trait_name, &mut visited);
-                max_depth = max_depth.max(depth);
-            }
-        }
+    /// Also analyze the crate as expanded by `cargo expand` and compare raw and expanded depth
+    #[clap(long)]
+    expand: bool,
 
-        max_depth
-    }
+    /// Read defaults from this mtd.toml/trr.toml instead of the nearest one
+    #[clap(long, value_name = "FILE")]
+    config: Option<PathBuf>,
 
-    fn dfs_trait_depth(&self, trait_name: &str, visited: &mut HashSet<String>) -> usize {
-        if !visited.insert(trait_name.to_string()) {
-            return 0;
-        }
+    /// Ignore mtd.toml and trr.toml
+    #[clap(long, conflicts_with = "config")]
+    no_config: bool,
 
-        let mut max_depth = 0;
-        if let Some(supertraits) = self.trait_graph.get(trait_name) {
-            for supertrait in supertraits {
-                let depth = self.dfs_trait_depth(supertrait, visited);
-                max_depth = max_depth.max(depth);
-            }
-        }
+    /// Skip #[cfg(test)] items and tests/, benches/, tests.rs files
+    #[clap(long)]
+    no_tests: bool,
 
-        visited.remove(trait_name);
-        max_depth + 1
-    }
+    /// Skip traits and impls declared inside function bodies
+    #[clap(long)]
+    no_local_items: bool,
 
-    fn get_summary(&self) -> AnalysisSummary {
-        let mut max_depth = 0;
-        for (type_name, _) in &self.impl_map {
-            max_depth = max_depth.max(self.calculate_max_depth(type_name));
-        }
-        
-        AnalysisSummary {
-            max_depth,
-            trait_count: self.trait_graph.len(),
-            impl_count: self.impl_map.len(),
-        }
-    }
+    /// Count #[cfg(feature = ...)] items only for these comma-separated features
+    #[clap(long, value_name = "LIST", multiple_occurrences = true)]
+    features: Vec<String>,
+
+    /// Count #[cfg(feature = ...)] items as if every feature were enabled
+    #[clap(long)]
+    all_features: bool,
+
+    /// Treat each instantiation of a generic trait (Trait<u8>, Trait<u16>) as a separate trait
+    #[clap(long)]
+    generic_instances: bool,
 }
 
-struct AnalysisSummary {
-    max_depth: usize,
-    trait_count: usize,
-    impl_count: usize,
+/// Types deeper than `limit`, leaving out those the baseline already had at that depth
+fn offending_types<'a>(
+    trait_analyzer: &'a TraitAnalyzer,
+    limit: usize,
+    baseline: Option<&Baseline>,
+) -> Vec<(&'a str, Vec<String>)> {
+    trait_analyzer.types_over_limit(limit).into_iter()
+        .filter(|(type_name, chain)| !baseline.is_some_and(|baseline| baseline.covers(type_name, chain.len())))
+        .collect()
 }
 
-fn visit_dirs(dir: &Path, cb: &mut dyn FnMut(&Path), recursive: bool) -> io::Result<()> {
-    if dir.is_dir() {
-        for entry in fs::read_dir(dir)? {
-            let entry = entry?;
-            let path = entry.path();
-            if path.is_dir() {
-                if recursive {
-                    visit_dirs(&path, cb, recursive)?;
-                }
-            } else if path.extension().map_or(false, |ext| ext == "rs") {
-                cb(&path);
-            }
-        }
+/// With `--fail-above`, list the types deeper than the limit and exit with status 1
+fn enforce_depth_limit(trait_analyzer: &TraitAnalyzer, fail_above: Option<usize>, baseline: Option<&Baseline>) {
+    let Some(limit) = fail_above else { return };
+    let offending = offending_types(trait_analyzer, limit, baseline);
+    if offending.is_empty() {
+        return;
     }
-    Ok(())
-}
 
-fn print_help() {
-    println!("Usage: {} [OPTIONS] [TARGET_DIR]", env::args().next().unwrap());
-    println!("Options:");
-    println!("  -h, --help     Show this help message");
-    println!("  -v, --verbose  Show detailed analysis for each file");
-    println!("  -f, --files    Show maximum trait depth per file");
-    println!("  -d, --dirs     Show maximum trait depth per directory (recursive)");
-    println!("  -t, --target   Show analysis for target directory only (non-recursive)");
-    println!();
-    println!("If TARGET_DIR is not specified, the current directory will be used.");
+    let since = if baseline.is_some() { " beyond the baseline" } else { "" };
+    let header = format!("{} type(s) exceed the maximum trait depth of {}{}", offending.len(), limit, since);
+    output::fail(&header, offending.iter().map(|(type_name, chain)| {
+        let location = trait_analyzer.impl_location(type_name, &chain[0])
+            .map(|location| format!(" (impl at {})", location))
+            .unwrap_or_default();
+        format!("{} (depth {}): {} → {}{}", type_name, chain.len(), type_name, chain.join(" → "), location)
+    }));
 }
 
 fn main() -> io::Result<()> {
-    let args: Vec<String> = env::args().collect();
-    let mut verbose = false;
-    let mut show_per_file = false;
-    let mut show_per_dir = false;
-    let mut target_only = false;
-    let mut target_dir = None;
-
-    let mut i = 1;
-    while i < args.len() {
-        match args[i].as_str() {
-            "-h" | "--help" => {
-                print_help();
-                return Ok(());
-            }
-            "-v" | "--verbose" => verbose = true,
-            "-f" | "--files" => show_per_file = true,
-            "-d" | "--dirs" => show_per_dir = true,
-            "-t" | "--target" => target_only = true,
-            dir if !dir.starts_with('-') => {
-                target_dir = Some(PathBuf::from(dir));
-            }
-            _ => {
-                eprintln!("Unknown option: {}", args[i]);
-                print_help();
-                return Ok(());
-            }
-        }
-        i += 1;
+    let cli = Cli::parse();
+    let (args, diff_revisions) = match cli.command {
+        Some(CliCommand::Diff { old, new, args }) => (args, Some((old, new))),
+        None => (cli.args, None),
+    };
+
+    // Settings from mtd.toml/trr.toml are defaults that the options below override
+    let config_file = match args.config {
+        Some(path) => Some(path),
+        None if args.no_config => None,
+        None => config::find(&env::current_dir()?),
+    };
+    let config = config_file.as_deref().map(config::load).transpose()?.unwrap_or_default();
+    let format = match (args.format, config.format.as_deref()) {
+        (Some(format), _) => format,
+        (None, Some(name)) => name.parse().map_err(|e| io::Error::new(io::ErrorKind::InvalidData, format!("{} in config", e)))?,
+        (None, None) => Format::Text,
+    };
+    let fail_above = args.fail_above.or(config.fail_above);
+    let mut features: HashSet<String> = config.features.into_iter().collect();
+    features.extend(args.features.iter().flat_map(|list| list.split([',', ' '])).filter(|feature| !feature.is_empty()).map(str::to_string));
+    let mut analyzer_options = Options {
+        generic_instances: args.generic_instances || config.generic_instances,
+        no_tests: args.no_tests || config.no_tests,
+        no_local_items: args.no_local_items || config.no_local_items,
+        features: if args.all_features || config.all_features {
+            Features::All
+        } else if !features.is_empty() {
+            Features::Enabled(features)
+        } else {
+            Features::Unchecked
+        },
+        exclude: config.exclude,
+        ignored_traits: config.ignore_traits.into_iter().collect(),
+        ..Options::default()
+    };
+    if args.sarif.is_some() && fail_above.is_none() {
+        Cli::command()
+            .error(ErrorKind::MissingRequiredArgument, "--sarif reports the types over the --fail-above limit, so it requires --fail-above")
+            .exit();
     }
+    let AnalyzeArgs {
+        dir: target_dir,
+        verbose,
+        files: show_per_file,
+        dirs: show_per_dir,
+        target_only,
+        repo,
+        rev: revision,
+        top,
+        sarif: sarif_file,
+        write_baseline: write_baseline_file,
+        baseline: baseline_file,
+        dot: dot_file,
+        expand,
+        ..
+    } = args;
 
     let target_dir = target_dir.unwrap_or_else(|| PathBuf::from("."));
+    // Keeps a cloned repository alive until the analysis is done
+    let repository = repo.as_deref()
+        .map(|repo| trr_core::git::Repository::open(repo, revision.as_deref()))
+        .transpose()?;
+    let target_dir = match &repository {
+        Some(repository) => repository.root().join(target_dir),
+        None => target_dir,
+    };
     if !target_dir.exists() {
         return Err(io::Error::new(
             io::ErrorKind::NotFound,
@@ -393,52 +350,234 @@ fn main() -> io::Result<()> {
         ));
     }
 
-    println!("Analyzing Rust files in directory: {}", target_dir.display());
-    if target_only {
-        println!("(Non-recursive analysis)");
+    analyzer_options.recursive = !target_only;
+    if let Some((old_revision, new_revision)) = &diff_revisions {
+        return run_diff(&target_dir, old_revision, new_revision, analyzer_options, format, fail_above);
+    }
+    let baseline = baseline_file.as_deref().map(Baseline::load).transpose()?;
+
+    // Group a workspace by crate
+    if target_dir.join("Cargo.toml").is_file() {
+        match workspace::members(&target_dir) {
+            Ok(crates) if crates.len() > 1 => analyzer_options.crates = crates,
+            Ok(_) => {}
+            Err(e) => eprintln!("Warning: results are not grouped by crate: {}", e),
+        }
     }
-    
+    let crates = analyzer_options.crates.clone();
+
+    if format == Format::Text {
+        println!("Analyzing Rust files in directory: {}", target_dir.display());
+        if target_only {
+            println!("(Non-recursive analysis)");
+        }
+    }
+
+    let expanded = if expand {
+        Some(expand::analyze_expanded(&target_dir, &analyzer_options)?)
+    } else {
+        None
+    };
+    let report = mtd::analyze(&target_dir, analyzer_options)?;
+    let trait_analyzer = &report.analyzer;
+
+    if verbose {
+        for file_analyzer in &report.files {
+            // Keep stdout clean for the JSON or Markdown document
+            if format != Format::Text {
+                eprintln!("Analyzing file: {} ({} traits, {} implementations)",
+                    file_analyzer.path().display(),
+                    file_analyzer.traits.len(),
+                    file_analyzer.impls.len());
+            } else {
+                println!("\nAnalyzing file: {}", file_analyzer.path().display());
+                println!("Found {} traits and {} implementations",
+                    file_analyzer.traits.len(),
+                    file_analyzer.impls.len());
+            }
+        }
+    }
+    for (path, e) in &report.errors {
+        eprintln!("Error analyzing {}: {}", path.display(), e);
+    }
+
+    // Separate summaries for each file, and for the files directly inside each directory
     let mut file_summaries = HashMap::new();
+    if show_per_file {
+        for file_analyzer in &report.files {
+            let path = file_analyzer.path();
+            file_summaries.insert(path.to_path_buf(), report.analyzer_for(|file| file == path).get_summary());
+        }
+    }
     let mut dir_summaries = HashMap::new();
-    let mut trait_analyzer = TraitAnalyzer::new();
+    if show_per_dir || target_only {
+        for file_analyzer in &report.files {
+            let dir_path = file_analyzer.path().parent().unwrap_or(Path::new(""));
+            dir_summaries.entry(dir_path.to_path_buf())
+                .or_insert_with(|| report.analyzer_for(|file| file.parent() == Some(dir_path)));
+        }
+    }
 
-    // Collect file-level and directory-level data
-    visit_dirs(&target_dir, &mut |path: &Path| {
-        let mut file_analyzer = FileAnalyzer::new();
-        match file_analyzer.analyze_file(path) {
-            Ok(()) => {
-                if verbose {
-                    println!("\nAnalyzing file: {}", path.display());
-                    println!("Found {} traits and {} implementations", 
-                        file_analyzer.traits.len(),
-                        file_analyzer.impls.len());
-                }
+    let crate_summaries: Vec<(&Crate, AnalysisSummary)> = crates.iter()
+        .map(|krate| {
+            let analyzer = report.analyzer_for(|file| {
+                Crate::containing(&crates, file).is_some_and(|containing| containing.root == krate.root)
+            });
+            let mut summary = analyzer.get_summary();
+            // Chains of the crate's types continue into the crates it depends on
+            let prefix = format!("{}::", krate.key());
+            summary.max_depth = trait_analyzer.impl_map.keys()
+                .filter(|type_name| type_name.starts_with(&prefix))
+                .map(|type_name| trait_analyzer.calculate_max_depth(type_name))
+                .max()
+                .unwrap_or(0);
+            (krate, summary)
+        })
+        .collect();
+
+    let top_types: Option<Vec<TopType>> = top.map(|count| trait_analyzer.deepest_types(count).into_iter()
+        .map(|(type_name, chain)| TopType {
+            type_name,
+            depth: chain.len(),
+            location: trait_analyzer.impl_location(type_name, &chain[0])
+                .map(Location::to_string),
+            chain,
+        })
+        .collect());
 
-                // Create a separate analyzer for this file
-                if show_per_file {
-                    let mut single_file_analyzer = TraitAnalyzer::new();
-                    single_file_analyzer.add_file_analysis(&file_analyzer);
-                    let summary = single_file_analyzer.get_summary();
-                    file_summaries.insert(path.to_path_buf(), summary);
-                }
+    if let Some(write_baseline_file) = &write_baseline_file {
+        Baseline::of(trait_analyzer).write(write_baseline_file)?;
+    }
+    let baseline_changes = baseline.as_ref()
+        .map(|baseline| baseline.changes(&Baseline::of(trait_analyzer)));
 
-                // Add to directory summary
-                if show_per_dir || target_only {
-                    let dir_path = path.parent().unwrap_or(Path::new("")).to_path_buf();
-                    let dir_analyzer = dir_summaries
-                        .entry(dir_path)
-                        .or_insert_with(TraitAnalyzer::new);
-                    dir_analyzer.add_file_analysis(&file_analyzer);
-                }
+    if let Some(dot_file) = &dot_file {
+        let mut out = io::BufWriter::new(fs::File::create(dot_file)?);
+        dot::write_dot(&mut out, trait_analyzer)?;
+    }
 
-                // Add to global analyzer
-                trait_analyzer.add_file_analysis(&file_analyzer);
-            }
-            Err(e) => {
-                eprintln!("Error analyzing {}: {}", path.display(), e);
-            }
-        }
-    }, !target_only)?;
+    // Artifact URIs are relative to the repository root, or to where mtd runs
+    let sarif_base = match &repository {
+        Some(repository) => repository.root().to_path_buf(),
+        None => PathBuf::from("."),
+    };
+    if let (Some(sarif_file), Some(limit)) = (&sarif_file, fail_above) {
+        let offending = offending_types(trait_analyzer, limit, baseline.as_ref());
+        let out = io::BufWriter::new(fs::File::create(sarif_file)?);
+        sarif::write_sarif(out, trait_analyzer, &offending, Some(limit), &sarif_base)?;
+    }
+
+    if format == Format::Sarif {
+        // Without a limit, the deepest types
+        let max_depth = trait_analyzer.get_summary().max_depth;
+        let offending = match fail_above {
+            Some(limit) => offending_types(trait_analyzer, limit, baseline.as_ref()),
+            None if max_depth > 0 => trait_analyzer.types_over_limit(max_depth - 1),
+            None => Vec::new(),
+        };
+        sarif::write_sarif(io::stdout().lock(), trait_analyzer, &offending, fail_above, &sarif_base)?;
+        println!();
+        enforce_depth_limit(trait_analyzer, fail_above, baseline.as_ref());
+        return Ok(());
+    }
+
+    if format == Format::Csv {
+        csv::write_csv(&mut io::stdout().lock(), trait_analyzer)?;
+        enforce_depth_limit(trait_analyzer, fail_above, baseline.as_ref());
+        return Ok(());
+    }
+
+    if format == Format::Markdown {
+        markdown::write_markdown(&mut io::stdout().lock(), trait_analyzer, &crate_summaries, expanded.as_ref(), top, &target_dir)?;
+        enforce_depth_limit(trait_analyzer, fail_above, baseline.as_ref());
+        return Ok(());
+    }
+
+    if format == Format::Json {
+        let report = JsonReport {
+            summary: trait_analyzer.get_summary(),
+            trait_graph: trait_analyzer.trait_graph.iter()
+                .map(|(name, supertraits)| (name.as_str(), supertraits.as_slice()))
+                .collect(),
+            associated_bound_depths: trait_analyzer.associated_bounds.keys()
+                .map(|trait_name| (trait_name.as_str(), trait_analyzer.associated_bound_chain(trait_name).len()))
+                .collect(),
+            impl_map: trait_analyzer.impl_map.keys()
+                .map(|type_name| (type_name.as_str(), trait_analyzer.implemented_traits(type_name)))
+                .collect(),
+            type_depths: trait_analyzer.impl_map.keys()
+                .map(|type_name| (type_name.as_str(), trait_analyzer.calculate_max_depth(type_name)))
+                .collect(),
+            trait_surfaces: trait_analyzer.trait_surfaces.iter()
+                .map(|(trait_name, surface)| (trait_name.as_str(), TraitSurfaceReport {
+                    surface: *surface,
+                    inherited_methods: trait_analyzer.inherited_surface(trait_name).methods(),
+                }))
+                .collect(),
+            object_safety: trait_analyzer.trait_graph.keys()
+                .map(|trait_name| (trait_name.as_str(), trait_analyzer.object_safety_violations(trait_name)))
+                .filter(|(_, violations)| !violations.is_empty())
+                .collect(),
+            dyn_traits: trait_analyzer.dyn_traits(),
+            supertrait_cycles: trait_analyzer.supertrait_cycles().into_iter()
+                .map(|cycle| cycle.into_iter()
+                    .map(|trait_name| CycleMember {
+                        location: trait_analyzer.trait_location(&trait_name),
+                        trait_name,
+                    })
+                    .collect())
+                .collect(),
+            unsafe_traits: trait_analyzer.unsafe_traits.iter()
+                .map(|trait_name| UnsafeTraitReport {
+                    trait_name,
+                    location: trait_analyzer.trait_location(trait_name),
+                    implementors: trait_analyzer.implementors(trait_name),
+                })
+                .collect(),
+            unsafe_impls: &trait_analyzer.unsafe_impls,
+            foreign_impls: trait_analyzer.foreign_impls(),
+            bound_heavy_signatures: trait_analyzer.bound_heavy_signatures(),
+            trait_locations: trait_analyzer.trait_graph.keys()
+                .filter_map(|trait_name| Some((trait_name.as_str(), trait_analyzer.trait_location(trait_name)?)))
+                .collect(),
+            impl_locations: trait_analyzer.impl_map.keys()
+                .map(|type_name| (type_name.as_str(), trait_analyzer.implemented_traits(type_name).into_iter()
+                    .filter_map(|trait_name| {
+                        let location = trait_analyzer.impl_location(type_name, &trait_name)?;
+                        Some((trait_name, location))
+                    })
+                    .collect()))
+                .collect(),
+            type_chains: trait_analyzer.impl_map.keys()
+                .map(|type_name| (type_name.as_str(), trait_analyzer.deepest_chain(type_name)))
+                .collect(),
+            files: show_per_file.then(|| file_summaries.iter()
+                .map(|(path, summary)| (path.display().to_string(), summary))
+                .collect()),
+            directories: show_per_dir.then(|| dir_summaries.iter()
+                .map(|(dir_path, analyzer)| (dir_path.display().to_string(), analyzer.get_summary()))
+                .collect()),
+            target: if target_only {
+                dir_summaries.get(&target_dir).map(TraitAnalyzer::get_summary)
+            } else {
+                None
+            },
+            top: top_types,
+            crates: (!crate_summaries.is_empty()).then(|| crate_summaries.iter()
+                .map(|(krate, summary)| (krate.name.as_str(), summary.clone()))
+                .collect()),
+            expanded: expanded.as_ref().map(|expanded| ExpandedReport {
+                summary: expanded.get_summary(),
+                type_depths: expanded.impl_map.keys()
+                    .map(|type_name| (type_name.clone(), expanded.calculate_max_depth(type_name)))
+                    .collect(),
+            }),
+            baseline_changes,
+        };
+        output::write_json(&mut io::stdout().lock(), "mtd", &report)?;
+        enforce_depth_limit(trait_analyzer, fail_above, baseline.as_ref());
+        return Ok(());
+    }
 
     // Print file-level summaries if requested
     if show_per_file {
@@ -479,31 +618,263 @@ fn main() -> io::Result<()> {
         }
     }
 
+    if !crate_summaries.is_empty() {
+        println!("\nCrate-Level Summary:");
+        println!("===================");
+        for (krate, summary) in &crate_summaries {
+            println!("\n{} ({})", krate.name, krate.root.display());
+            println!("  Maximum Trait Depth: {}", summary.max_depth);
+            println!("  Trait Count: {}", summary.trait_count);
+            println!("  Implementation Count: {}", summary.impl_count);
+        }
+    }
+
     // Print global summary
     let global_summary = trait_analyzer.get_summary();
     println!("\nGlobal Summary:");
     println!("==============");
     println!("Overall Maximum Trait Depth: {}", global_summary.max_depth);
+    println!("Maximum Associated Bound Depth: {}", global_summary.max_associated_bound_depth);
     println!("Total Trait Count: {}", global_summary.trait_count);
     println!("Total Implementation Count: {}", global_summary.impl_count);
+    println!("Trait Methods: {} required, {} provided ({} associated items)",
+        global_summary.required_method_count,
+        global_summary.provided_method_count,
+        global_summary.associated_item_count);
+    println!("Most Methods Including Supertraits: {}", global_summary.max_inherited_methods);
+    println!("Most Bounds on One Signature: {}", global_summary.max_signature_bounds);
+
+    let cycles = trait_analyzer.supertrait_cycles();
+    if !cycles.is_empty() {
+        println!("\nSupertrait Cycles:");
+        println!("=================");
+        for cycle in &cycles {
+            println!("{} (depths through these traits are cut short)", cycle.join(" ⇄ "));
+            for trait_name in cycle {
+                if let Some(location) = trait_analyzer.trait_location(trait_name) {
+                    println!("  - {} at {}", trait_name, location);
+                }
+            }
+        }
+    }
+
+    if let Some(changes) = &baseline_changes {
+        println!("\nChanges Since Baseline:");
+        println!("======================");
+        baseline::print_changes(changes);
+    }
 
-    // Print trait hierarchy if no specific summary was requested
-    if !show_per_file && !show_per_dir && !target_only {
+    if let Some(expanded) = &expanded {
+        let expanded_summary = expanded.get_summary();
+        println!("\nMacro Expansion (raw → expanded):");
+        println!("=================================");
+        println!("Overall Maximum Trait Depth: {} → {}", global_summary.max_depth, expanded_summary.max_depth);
+        println!("Total Trait Count: {} → {}", global_summary.trait_count, expanded_summary.trait_count);
+        println!("Total Implementation Count: {} → {}", global_summary.impl_count, expanded_summary.impl_count);
+
+        let mut type_names: BTreeSet<&String> = trait_analyzer.impl_map.keys().collect();
+        type_names.extend(expanded.impl_map.keys());
+        for type_name in type_names {
+            let raw_depth = trait_analyzer.calculate_max_depth(type_name);
+            let expanded_depth = expanded.calculate_max_depth(type_name);
+            if raw_depth != expanded_depth {
+                println!("{}: depth {} → {}", type_name, raw_depth, expanded_depth);
+            }
+        }
+    }
+
+    if let Some(top_types) = &top_types {
+        println!("\nTop {} Deepest Types:", top_types.len());
+        for (rank, top_type) in top_types.iter().enumerate() {
+            let location = top_type.location.as_ref()
+                .map(|location| format!(" at {}", location))
+                .unwrap_or_default();
+            println!("{}. {} (depth {}){}", rank + 1, top_type.type_name, top_type.depth, location);
+            println!("   {} → {}", top_type.type_name, top_type.chain.join(" → "));
+        }
+    }
+
+    // Print trait hierarchy if no specific summary or top list was requested
+    if !show_per_file && !show_per_dir && !target_only && top_types.is_none() {
         println!("\nTrait Hierarchy:");
         for (trait_name, supertraits) in &trait_analyzer.trait_graph {
-            println!("{} -> {:?}", trait_name, supertraits);
+            let surface = trait_analyzer.trait_surfaces.get(trait_name).copied().unwrap_or_default();
+            let location = trait_analyzer.trait_location(trait_name)
+                .map(|location| format!(" at {}", location))
+                .unwrap_or_default();
+            println!("{} -> {:?} ({} required, {} provided, {} associated; {} methods with supertraits){}",
+                trait_name,
+                supertraits,
+                surface.required_methods,
+                surface.provided_methods,
+                surface.associated_types + surface.associated_consts,
+                trait_analyzer.inherited_surface(trait_name).methods(),
+                location);
+        }
+
+        let mut with_associated_bounds: Vec<&String> = trait_analyzer.associated_bounds.keys().collect();
+        if !with_associated_bounds.is_empty() {
+            with_associated_bounds.sort();
+            println!("\nAssociated Type Bounds:");
+            for trait_name in with_associated_bounds {
+                let chain = trait_analyzer.associated_bound_chain(trait_name);
+                println!("{} -> {:?} (associated bound depth {}: {})",
+                    trait_name,
+                    trait_analyzer.associated_bounds[trait_name],
+                    chain.len(),
+                    chain.join(" → "));
+            }
+        }
+
+        let mut not_object_safe: Vec<(&String, Vec<String>)> = trait_analyzer.trait_graph.keys()
+            .map(|trait_name| (trait_name, trait_analyzer.object_safety_violations(trait_name)))
+            .filter(|(_, violations)| !violations.is_empty())
+            .collect();
+        if !not_object_safe.is_empty() {
+            not_object_safe.sort();
+            println!("\nNot Object Safe:");
+            for (trait_name, violations) in not_object_safe {
+                println!("{}: {}", trait_name, violations.join("; "));
+            }
+        }
+
+        let dyn_traits = trait_analyzer.dyn_traits();
+        if !dyn_traits.is_empty() {
+            println!("\nDynamic Dispatch (dyn Trait usage):");
+            for usage in &dyn_traits {
+                let containers: Vec<String> = usage.containers.iter()
+                    .map(|(container, count)| format!("{} {}", container, count))
+                    .collect();
+                println!("{}: {} use(s) [{}], trait depth {}",
+                    usage.trait_name, usage.uses, containers.join(", "), usage.depth);
+                if !usage.object_safety_violations.is_empty() {
+                    println!("  warning: not object safe: {}", usage.object_safety_violations.join("; "));
+                }
+                if verbose {
+                    for location in &usage.locations {
+                        println!("  - {}", location);
+                    }
+                }
+            }
+        }
+
+        if !trait_analyzer.unsafe_traits.is_empty() || !trait_analyzer.unsafe_impls.is_empty() {
+            println!("\nUnsafe Traits and Impls:");
+            for trait_name in &trait_analyzer.unsafe_traits {
+                let location = trait_analyzer.trait_location(trait_name)
+                    .map(|location| format!(" at {}", location))
+                    .unwrap_or_default();
+                println!("unsafe trait {}{}, implemented by: {}",
+                    trait_name, location, trait_analyzer.implementors(trait_name).join(", "));
+            }
+            for unsafe_impl in &trait_analyzer.unsafe_impls {
+                println!("unsafe impl {} for {} at {}",
+                    unsafe_impl.trait_name,
+                    unsafe_impl.type_name,
+                    unsafe_impl.location);
+            }
+        }
+
+        let foreign_impls = trait_analyzer.foreign_impls();
+        if !foreign_impls.is_empty() {
+            println!("\nForeign Impls (trait or type declared elsewhere):");
+            for foreign in &foreign_impls {
+                println!("impl {} for {} (foreign {}) at {}",
+                    foreign.trait_name,
+                    foreign.type_name,
+                    foreign.foreign_side(),
+                    foreign.location);
+            }
+        }
+
+        let signatures = trait_analyzer.bound_heavy_signatures();
+        if !signatures.is_empty() {
+            // All of them with -v, otherwise the heaviest few
+            let shown = if verbose { signatures.len() } else { BOUND_HEAVY_SHOWN.min(signatures.len()) };
+            println!("\nBound-Heavy Signatures ({} of {}):", shown, signatures.len());
+            for signature in &signatures[..shown] {
+                let complexity = &signature.complexity;
+                println!("{}: {} bound(s) on {} type parameter(s), {} on associated types, nesting {} at {}",
+                    signature.name,
+                    complexity.bounds,
+                    complexity.type_params,
+                    complexity.projection_bounds,
+                    complexity.nesting,
+                    signature.location);
+            }
         }
 
         println!("\nType Implementations and Maximum Trait Depth:");
-        for (type_name, traits) in &trait_analyzer.impl_map {
+        for type_name in trait_analyzer.impl_map.keys() {
             println!("\n{} implements:", type_name);
-            for trait_name in traits {
-                println!("  - {}", trait_name);
+            for trait_name in trait_analyzer.implemented_traits(type_name) {
+                match trait_analyzer.impl_location(type_name, &trait_name) {
+                    Some(location) => println!("  - {} ({})", trait_name, location),
+                    None => println!("  - {}", trait_name),
+                }
+            }
+            let chain = trait_analyzer.deepest_chain(type_name);
+            println!("Maximum trait depth: {}", chain.len());
+            if !chain.is_empty() {
+                println!("Deepest chain: {} → {}", type_name, chain.join(" → "));
             }
-            let depth = trait_analyzer.calculate_max_depth(type_name);
-            println!("Maximum trait depth: {}", depth);
         }
     }
 
+    enforce_depth_limit(trait_analyzer, fail_above, baseline.as_ref());
     Ok(())
 } 
+
+/// `mtd diff <OLD_REV> <NEW_REV> [DIR]`: the depth of every type in two revisions of
+/// DIR, checked out next to the working tree
+fn run_diff(
+    target_dir: &Path,
+    old_revision: &str,
+    new_revision: &str,
+    options: Options,
+    format: Format,
+    fail_above: Option<usize>,
+) -> io::Result<()> {
+    if !matches!(format, Format::Text | Format::Json) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("mtd diff prints text or json, not {}", format),
+        ));
+    }
+    let analyze_revision = |revision: &str| -> io::Result<TraitAnalyzer> {
+        let (_checkout, dir) = trr_core::git::checkout_revision(target_dir, revision)?;
+        if !dir.exists() {
+            return Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("'{}' does not exist at {}", target_dir.display(), revision),
+            ));
+        }
+        Ok(mtd::analyze(&dir, options.clone())?.analyzer)
+    };
+    let old_analyzer = analyze_revision(old_revision)?;
+    let new_analyzer = analyze_revision(new_revision)?;
+    let old_baseline = Baseline::of(&old_analyzer);
+    let changes = old_baseline.changes(&Baseline::of(&new_analyzer));
+
+    match format {
+        Format::Json => {
+            let report = DiffReport {
+                old_revision,
+                new_revision,
+                old_summary: old_analyzer.get_summary(),
+                new_summary: new_analyzer.get_summary(),
+                changes,
+            };
+            output::write_json(&mut io::stdout().lock(), "mtd", &report)?;
+        }
+        _ => {
+            println!("Trait depth changes from {} to {} in {}:", old_revision, new_revision, target_dir.display());
+            println!("Overall Maximum Trait Depth: {} → {}",
+                old_analyzer.get_summary().max_depth,
+                new_analyzer.get_summary().max_depth);
+            baseline::print_changes(&changes);
+        }
+    }
+    enforce_depth_limit(&new_analyzer, fail_above, Some(&old_baseline));
+    Ok(())
+}
@@ -0,0 +1,259 @@
+//! `--format markdown`: a review-ready report with summary tables, the deepest
+//! chain of every type, and the trait hierarchy as nested lists.
+
+use std::collections::{BTreeSet, HashSet};
+use std::io::{self, Write};
+use std::path::Path;
+
+use mtd::{AnalysisSummary, Crate, TraitAnalyzer};
+use trr_core::output::{markdown_cell, markdown_code as code};
+
+pub fn write_markdown(
+    out: &mut impl Write,
+    analyzer: &TraitAnalyzer,
+    crates: &[(&Crate, AnalysisSummary)],
+    expanded: Option<&TraitAnalyzer>,
+    top: Option<usize>,
+    target: &Path,
+) -> io::Result<()> {
+    let summary = analyzer.get_summary();
+    let mut types: Vec<(&str, Vec<String>)> = analyzer.impl_map.keys()
+        .map(|type_name| (type_name.as_str(), analyzer.deepest_chain(type_name)))
+        .collect();
+    types.sort_by(|a, b| b.1.len().cmp(&a.1.len()).then(a.0.cmp(b.0)));
+    let type_count = types.len();
+
+    writeln!(out, "# Trait Depth Report")?;
+    writeln!(out)?;
+    writeln!(out, "Target: {}", code(&target.display().to_string()))?;
+    writeln!(out)?;
+    writeln!(out, "## Summary")?;
+    writeln!(out)?;
+    writeln!(out, "| Metric | Value |")?;
+    writeln!(out, "| --- | ---: |")?;
+    writeln!(out, "| Maximum trait depth | {} |", summary.max_depth)?;
+    writeln!(out, "| Maximum associated bound depth | {} |", summary.max_associated_bound_depth)?;
+    writeln!(out, "| Traits declared | {} |", summary.trait_count)?;
+    writeln!(out, "| Required / provided methods | {} / {} |", summary.required_method_count, summary.provided_method_count)?;
+    writeln!(out, "| Most methods including supertraits | {} |", summary.max_inherited_methods)?;
+    writeln!(out, "| Most bounds on one signature | {} |", summary.max_signature_bounds)?;
+    writeln!(out, "| Types with trait impls | {} |", summary.impl_count)?;
+    if let Some((type_name, _)) = types.first() {
+        writeln!(out, "| Deepest type | {} |", code(type_name))?;
+    }
+
+    if !crates.is_empty() {
+        writeln!(out)?;
+        writeln!(out, "## Crates")?;
+        writeln!(out)?;
+        writeln!(out, "| Crate | Maximum trait depth | Traits | Types with trait impls |")?;
+        writeln!(out, "| --- | ---: | ---: | ---: |")?;
+        for (krate, summary) in crates {
+            writeln!(out, "| {} | {} | {} | {} |", code(&krate.name), summary.max_depth, summary.trait_count, summary.impl_count)?;
+        }
+    }
+
+    if let Some(expanded) = expanded {
+        let expanded_summary = expanded.get_summary();
+        writeln!(out)?;
+        writeln!(out, "## Macro Expansion")?;
+        writeln!(out)?;
+        writeln!(out, "| Metric | Raw | Expanded |")?;
+        writeln!(out, "| --- | ---: | ---: |")?;
+        writeln!(out, "| Maximum trait depth | {} | {} |", summary.max_depth, expanded_summary.max_depth)?;
+        writeln!(out, "| Traits declared | {} | {} |", summary.trait_count, expanded_summary.trait_count)?;
+        writeln!(out, "| Types with trait impls | {} | {} |", summary.impl_count, expanded_summary.impl_count)?;
+    }
+
+    writeln!(out)?;
+    writeln!(out, "## Types")?;
+    writeln!(out)?;
+    if let Some(top) = top.filter(|&top| top < type_count) {
+        types.truncate(top);
+        writeln!(out, "The {} deepest of {} types.", top, type_count)?;
+        writeln!(out)?;
+    }
+    if types.is_empty() {
+        writeln!(out, "No trait implementations found.")?;
+    } else {
+        writeln!(out, "| Type | Depth | Deepest chain | Traits | Impl |")?;
+        writeln!(out, "| --- | ---: | --- | ---: | --- |")?;
+        for (type_name, chain) in &types {
+            // Where the chain starts: the impl of its first trait
+            let location = chain.first()
+                .and_then(|trait_name| analyzer.impl_location(type_name, trait_name))
+                .map(|location| code(&location.to_string()))
+                .unwrap_or_default();
+            let chain: Vec<String> = chain.iter().map(|trait_name| code(trait_name)).collect();
+            writeln!(
+                out,
+                "| {} | {} | {} | {} | {} |",
+                code(type_name),
+                chain.len(),
+                chain.join(" → "),
+                analyzer.implemented_traits(type_name).len(),
+                location
+            )?;
+        }
+    }
+
+    let dyn_traits = analyzer.dyn_traits();
+    if !dyn_traits.is_empty() {
+        writeln!(out)?;
+        writeln!(out, "## Dynamic Dispatch")?;
+        writeln!(out)?;
+        writeln!(out, "| Trait | Uses | Containers | Depth | Object safe |")?;
+        writeln!(out, "| --- | ---: | --- | ---: | --- |")?;
+        for usage in &dyn_traits {
+            let containers: Vec<String> = usage.containers.iter()
+                .map(|(container, count)| format!("{} {}", code(container), count))
+                .collect();
+            let object_safe = if usage.object_safety_violations.is_empty() {
+                "yes".to_string()
+            } else {
+                format!("**no**: {}", markdown_cell(&usage.object_safety_violations.join("; ")))
+            };
+            writeln!(
+                out,
+                "| {} | {} | {} | {} | {} |",
+                code(&usage.trait_name),
+                usage.uses,
+                containers.join(", "),
+                usage.depth,
+                object_safe
+            )?;
+        }
+    }
+
+    if !analyzer.unsafe_traits.is_empty() || !analyzer.unsafe_impls.is_empty() {
+        writeln!(out)?;
+        writeln!(out, "## Unsafe Traits and Impls")?;
+        writeln!(out)?;
+        writeln!(out, "| Item | Implementors | Location |")?;
+        writeln!(out, "| --- | --- | --- |")?;
+        for trait_name in &analyzer.unsafe_traits {
+            let implementors: Vec<String> = analyzer.implementors(trait_name).into_iter().map(code).collect();
+            let location = analyzer.trait_location(trait_name)
+                .map(|location| code(&location.to_string()))
+                .unwrap_or_default();
+            writeln!(out, "| unsafe trait {} | {} | {} |", code(trait_name), implementors.join(", "), location)?;
+        }
+        for unsafe_impl in &analyzer.unsafe_impls {
+            writeln!(
+                out,
+                "| unsafe impl {} | {} | {} |",
+                code(&unsafe_impl.trait_name),
+                code(&unsafe_impl.type_name),
+                code(&unsafe_impl.location.to_string())
+            )?;
+        }
+    }
+
+    let foreign_impls = analyzer.foreign_impls();
+    if !foreign_impls.is_empty() {
+        writeln!(out)?;
+        writeln!(out, "## Foreign Impls")?;
+        writeln!(out)?;
+        writeln!(out, "| Trait | Type | Foreign | Location |")?;
+        writeln!(out, "| --- | --- | --- | --- |")?;
+        for foreign in &foreign_impls {
+            writeln!(
+                out,
+                "| {} | {} | {} | {} |",
+                code(&foreign.trait_name),
+                code(&foreign.type_name),
+                foreign.foreign_side(),
+                code(&foreign.location.to_string())
+            )?;
+        }
+    }
+
+    let signatures = analyzer.bound_heavy_signatures();
+    if !signatures.is_empty() {
+        writeln!(out)?;
+        writeln!(out, "## Bound-Heavy Signatures")?;
+        writeln!(out)?;
+        if signatures.len() > crate::BOUND_HEAVY_SHOWN {
+            writeln!(out, "The {} most bounded of {} generic signatures.", crate::BOUND_HEAVY_SHOWN, signatures.len())?;
+            writeln!(out)?;
+        }
+        writeln!(out, "| Signature | Bounds | Type parameters | On associated types | Nesting | Location |")?;
+        writeln!(out, "| --- | ---: | ---: | ---: | ---: | --- |")?;
+        for signature in signatures.iter().take(crate::BOUND_HEAVY_SHOWN) {
+            let complexity = &signature.complexity;
+            writeln!(
+                out,
+                "| {} | {} | {} | {} | {} | {} |",
+                code(&signature.name),
+                complexity.bounds,
+                complexity.type_params,
+                complexity.projection_bounds,
+                complexity.nesting,
+                code(&signature.location.to_string())
+            )?;
+        }
+    }
+
+    let cycles = analyzer.supertrait_cycles();
+    if !cycles.is_empty() {
+        writeln!(out)?;
+        writeln!(out, "## Supertrait Cycles")?;
+        writeln!(out)?;
+        for cycle in &cycles {
+            let members: Vec<String> = cycle.iter()
+                .map(|trait_name| match analyzer.trait_location(trait_name) {
+                    Some(location) => format!("{} ({})", code(trait_name), location),
+                    None => code(trait_name),
+                })
+                .collect();
+            writeln!(out, "- {}", members.join(" ⇄ "))?;
+        }
+    }
+
+    writeln!(out)?;
+    writeln!(out, "## Trait Hierarchy")?;
+    writeln!(out)?;
+    // Start from the traits no other declared trait builds on; their supertraits nest below
+    let used_as_supertrait: HashSet<String> = analyzer.trait_graph.keys()
+        .flat_map(|trait_name| analyzer.supertraits_of(trait_name))
+        .collect();
+    let mut roots: BTreeSet<&str> = analyzer.trait_graph.keys()
+        .map(String::as_str)
+        .filter(|trait_name| !used_as_supertrait.contains(*trait_name))
+        .collect();
+    if analyzer.trait_graph.is_empty() {
+        writeln!(out, "No traits declared.")?;
+    } else if roots.is_empty() {
+        // Every declared trait is part of a cycle
+        roots = analyzer.trait_graph.keys().map(String::as_str).collect();
+    }
+    for root in roots {
+        write_trait_tree(out, analyzer, root, 0, &mut HashSet::new())?;
+    }
+    Ok(())
+}
+
+fn write_trait_tree(
+    out: &mut impl Write,
+    analyzer: &TraitAnalyzer,
+    trait_name: &str,
+    level: usize,
+    ancestors: &mut HashSet<String>,
+) -> io::Result<()> {
+    let indent = "  ".repeat(level);
+    if !ancestors.insert(trait_name.to_string()) {
+        return writeln!(out, "{}- {} (cycle)", indent, code(trait_name));
+    }
+    let location = analyzer.trait_location(trait_name)
+        .map(|location| format!(" at {}", code(&location.to_string())))
+        .unwrap_or_default();
+    writeln!(out, "{}- {} (depth {}){}", indent, code(trait_name), analyzer.trait_depth(trait_name), location)?;
+
+    let mut supertraits = analyzer.supertraits_of(trait_name);
+    supertraits.sort();
+    for supertrait in supertraits {
+        write_trait_tree(out, analyzer, &supertrait, level + 1, ancestors)?;
+    }
+    ancestors.remove(trait_name);
+    Ok(())
+}
@@ -0,0 +1,101 @@
+//! Object safety (dyn compatibility) of trait declarations: whether `dyn Trait` can
+//! be formed at all. Only the trait's own items are checked here; supertraits are
+//! followed by the analyzer once the whole graph is known.
+
+use syn::visit::{self, Visit};
+use syn::{
+    FnArg, GenericParam, Generics, ItemTrait, ReturnType, Signature, TraitBoundModifier, TraitItem,
+    Type, TypeImplTrait, TypeParamBound, TypePath, WherePredicate,
+};
+
+/// Why the items of `item` keep it from being object safe; empty if they don't
+pub fn violations(item: &ItemTrait) -> Vec<String> {
+    let mut violations = Vec::new();
+    for trait_item in &item.items {
+        match trait_item {
+            TraitItem::Const(constant) => {
+                violations.push(format!("associated const `{}`", constant.ident));
+            }
+            TraitItem::Type(associated) if !associated.generics.params.is_empty() => {
+                violations.push(format!("generic associated type `{}`", associated.ident));
+            }
+            // `where Self: Sized` takes a method out of the vtable
+            TraitItem::Fn(method) if !requires_sized(&method.sig.generics) => {
+                violations.extend(method_violation(&method.sig));
+            }
+            _ => {}
+        }
+    }
+    violations
+}
+
+fn method_violation(sig: &Signature) -> Option<String> {
+    let name = &sig.ident;
+    if sig.receiver().is_none() {
+        return Some(format!("associated function `{}` has no `self` receiver", name));
+    }
+    if sig.asyncness.is_some() {
+        return Some(format!("method `{}` is `async`", name));
+    }
+    if sig.generics.params.iter().any(|param| !matches!(param, GenericParam::Lifetime(_))) {
+        return Some(format!("method `{}` has generic type parameters", name));
+    }
+
+    let mut inputs = TypeScan::default();
+    for input in &sig.inputs {
+        if let FnArg::Typed(argument) = input {
+            inputs.visit_type(&argument.ty);
+        }
+    }
+    let mut output = TypeScan::default();
+    if let ReturnType::Type(_, ty) = &sig.output {
+        output.visit_type(ty);
+    }
+
+    if inputs.impl_trait {
+        Some(format!("method `{}` takes `impl Trait` arguments", name))
+    } else if output.impl_trait {
+        Some(format!("method `{}` returns `impl Trait`", name))
+    } else if inputs.mentions_self || output.mentions_self {
+        Some(format!("method `{}` uses `Self` outside its receiver", name))
+    } else {
+        None
+    }
+}
+
+/// `where Self: Sized`
+fn requires_sized(generics: &Generics) -> bool {
+    let Some(where_clause) = &generics.where_clause else { return false };
+    where_clause.predicates.iter().any(|predicate| match predicate {
+        WherePredicate::Type(predicate) => {
+            matches!(&predicate.bounded_ty, Type::Path(type_path)
+                if type_path.qself.is_none() && type_path.path.is_ident("Self"))
+                && predicate.bounds.iter().any(|bound| matches!(bound, TypeParamBound::Trait(trait_bound)
+                    if matches!(trait_bound.modifier, TraitBoundModifier::None)
+                        && trait_bound.path.segments.last().is_some_and(|segment| segment.ident == "Sized")))
+        }
+        _ => false,
+    })
+}
+
+/// What a parameter or return type refers to. `Self::Item` and `<Self as T>::Item`
+/// name associated types and don't count as `Self`.
+#[derive(Default)]
+struct TypeScan {
+    mentions_self: bool,
+    impl_trait: bool,
+}
+
+impl<'ast> Visit<'ast> for TypeScan {
+    fn visit_type_path(&mut self, type_path: &'ast TypePath) {
+        if type_path.qself.is_none() && type_path.path.is_ident("Self") {
+            self.mentions_self = true;
+        }
+        visit::visit_type_path(self, type_path);
+    }
+
+    fn visit_type_impl_trait(&mut self, impl_trait: &'ast TypeImplTrait) {
+        self.impl_trait = true;
+        visit::visit_type_impl_trait(self, impl_trait);
+    }
+}
@@ -0,0 +1,266 @@
+//! Canonical names for traits and types. Every name is turned into a path relative
+//! to the crate root (`module_a::TraitA`, or just `TraitA` at the root), following
+//! `crate::`, `self::` and `super::` prefixes and `use` imports, so that the same
+//! item is keyed identically wherever it is referenced. Items local to a function body
+//! are keyed under the function's name (`tests::setup::MockVault`).
+
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+
+use syn::visit::{self, Visit};
+use syn::{Ident, ImplItemFn, ItemFn, ItemMod, ItemUse, TraitItemFn, UseTree};
+
+use super::render_tokens;
+
+/// Names declared and imported in one module
+#[derive(Default)]
+struct Scope {
+    declared: HashSet<String>,
+    /// Local name -> crate-relative (or external) path
+    imports: HashMap<String, Vec<String>>,
+}
+
+/// Declarations and imports of every module in a file, keyed by module path
+#[derive(Default)]
+pub struct Scopes {
+    scopes: HashMap<Vec<String>, Scope>,
+    /// Module path that `crate::` refers to: empty, or the crate name in a workspace
+    crate_root: Vec<String>,
+    current_module: Vec<String>,
+    /// Scopes that are function bodies rather than modules; they see the items of
+    /// the scopes enclosing them
+    bodies: HashSet<Vec<String>>,
+    /// Imports as written, resolved once all declarations are known
+    raw_imports: Vec<(Vec<String>, String, Vec<String>)>,
+}
+
+impl Scopes {
+    /// Collect the declarations and imports of `file`, whose own module path is `module`
+    /// in the crate at `crate_root`
+    pub fn collect(file: &syn::File, crate_root: &[String], module: &[String]) -> Self {
+        let mut scopes = Scopes {
+            crate_root: crate_root.to_vec(),
+            current_module: module.to_vec(),
+            ..Scopes::default()
+        };
+        scopes.visit_file(file);
+
+        // `use foo::Bar` may refer to a module declared later in the same file
+        for (module, local_name, path) in std::mem::take(&mut scopes.raw_imports) {
+            let target = scopes.absolute(&module, &path);
+            scopes.scopes.entry(module).or_default().imports.insert(local_name, target);
+        }
+        scopes
+    }
+
+    /// Canonical name of `path` as referenced from inside `module`. Paths that do not
+    /// start at a local item (std, other crates, glob imports) are kept as written.
+    pub fn resolve(&self, module: &[String], path: &syn::Path) -> String {
+        if path.leading_colon.is_some() || path.segments.is_empty() {
+            return render_tokens(path);
+        }
+
+        let idents: Vec<String> = path.segments.iter().map(|segment| segment.ident.to_string()).collect();
+        let (base, consumed) = match idents[0].as_str() {
+            "crate" | "self" | "super" => {
+                let prefix_len = idents.iter()
+                    .take_while(|ident| matches!(ident.as_str(), "crate" | "self" | "super"))
+                    .count();
+                (self.absolute(module, &idents[..prefix_len]), prefix_len)
+            }
+            first => match self.lookup(module, first) {
+                Some(found) => found,
+                None => return render_tokens(path),
+            },
+        };
+
+        let rest = path.segments.iter().skip(consumed).map(render_tokens);
+        base.into_iter().chain(rest).collect::<Vec<_>>().join("::")
+    }
+
+    /// Where a local name is imported from or declared, searching from `module`
+    /// outwards through enclosing function bodies
+    fn lookup(&self, module: &[String], name: &str) -> Option<(Vec<String>, usize)> {
+        let mut scope_path = module;
+        loop {
+            if let Some(scope) = self.scopes.get(scope_path) {
+                if let Some(target) = scope.imports.get(name) {
+                    return Some((target.clone(), 1));
+                }
+                if scope.declared.contains(name) {
+                    return Some((scope_path.to_vec(), 0));
+                }
+            }
+            if !self.bodies.contains(scope_path) {
+                return None;
+            }
+            scope_path = &scope_path[..scope_path.len() - 1];
+        }
+    }
+
+    /// The module containing `scope`, which may be a function body
+    fn enclosing_module<'a>(&self, mut scope: &'a [String]) -> &'a [String] {
+        while self.bodies.contains(scope) {
+            scope = &scope[..scope.len() - 1];
+        }
+        scope
+    }
+
+    /// Resolve the path of a `use` item or a `crate`/`self`/`super` prefix to
+    /// segments relative to the crate root
+    fn absolute(&self, module: &[String], path: &[String]) -> Vec<String> {
+        let mut resolved = module.to_vec();
+        let mut rest = path;
+        match path.first().map(String::as_str) {
+            Some("crate") => {
+                resolved = self.crate_root.clone();
+                rest = &path[1..];
+            }
+            Some("self") | Some("super") => {
+                resolved = self.enclosing_module(module).to_vec();
+                while let Some(first) = rest.first() {
+                    match first.as_str() {
+                        "self" => {}
+                        "super" => {
+                            resolved.pop();
+                        }
+                        _ => break,
+                    }
+                    rest = &rest[1..];
+                }
+            }
+            Some(first) => match self.lookup(module, first) {
+                // `use helpers::Item` inside a body may name a module declared further out
+                Some((scope_path, 0)) => resolved = scope_path,
+                _ => resolved.clear(),
+            },
+            // An external crate
+            None => resolved.clear(),
+        }
+        resolved.extend(rest.iter().cloned());
+        resolved
+    }
+
+    /// Visit a function body as its own scope
+    fn visit_body(&mut self, ident: &Ident, visit_body: impl FnOnce(&mut Self)) {
+        self.current_module.push(ident.to_string());
+        self.bodies.insert(self.current_module.clone());
+        visit_body(self);
+        self.current_module.pop();
+    }
+
+    fn declare(&mut self, name: String) {
+        self.scopes.entry(self.current_module.clone()).or_default().declared.insert(name);
+    }
+
+    fn collect_use_tree(&mut self, tree: &UseTree, mut prefix: Vec<String>) {
+        match tree {
+            UseTree::Path(use_path) => {
+                prefix.push(use_path.ident.to_string());
+                self.collect_use_tree(&use_path.tree, prefix);
+            }
+            UseTree::Name(use_name) => {
+                // `use foo::{self}` imports `foo` itself
+                let local_name = if use_name.ident == "self" {
+                    match prefix.last() {
+                        Some(last) => last.clone(),
+                        None => return,
+                    }
+                } else {
+                    prefix.push(use_name.ident.to_string());
+                    use_name.ident.to_string()
+                };
+                self.raw_imports.push((self.current_module.clone(), local_name, prefix));
+            }
+            UseTree::Rename(use_rename) => {
+                if use_rename.ident != "self" {
+                    prefix.push(use_rename.ident.to_string());
+                }
+                self.raw_imports.push((self.current_module.clone(), use_rename.rename.to_string(), prefix));
+            }
+            // Glob imports cannot be resolved without the target module's contents
+            UseTree::Glob(_) => {}
+            UseTree::Group(use_group) => {
+                for tree in &use_group.items {
+                    self.collect_use_tree(tree, prefix.clone());
+                }
+            }
+        }
+    }
+}
+
+impl<'ast> Visit<'ast> for Scopes {
+    fn visit_item(&mut self, item: &'ast syn::Item) {
+        let ident = match item {
+            syn::Item::Trait(item) => Some(&item.ident),
+            syn::Item::TraitAlias(item) => Some(&item.ident),
+            syn::Item::Struct(item) => Some(&item.ident),
+            syn::Item::Enum(item) => Some(&item.ident),
+            syn::Item::Union(item) => Some(&item.ident),
+            syn::Item::Type(item) => Some(&item.ident),
+            _ => None,
+        };
+        if let Some(ident) = ident {
+            self.declare(ident.to_string());
+        }
+        visit::visit_item(self, item);
+    }
+
+    fn visit_item_mod(&mut self, item: &'ast ItemMod) {
+        self.declare(item.ident.to_string());
+        self.current_module.push(item.ident.to_string());
+        visit::visit_item_mod(self, item);
+        self.current_module.pop();
+    }
+
+    fn visit_item_use(&mut self, item: &'ast ItemUse) {
+        self.collect_use_tree(&item.tree, Vec::new());
+    }
+
+    fn visit_item_fn(&mut self, item: &'ast ItemFn) {
+        self.visit_body(&item.sig.ident, |scopes| visit::visit_item_fn(scopes, item));
+    }
+
+    fn visit_impl_item_fn(&mut self, item: &'ast ImplItemFn) {
+        self.visit_body(&item.sig.ident, |scopes| visit::visit_impl_item_fn(scopes, item));
+    }
+
+    fn visit_trait_item_fn(&mut self, item: &'ast TraitItemFn) {
+        self.visit_body(&item.sig.ident, |scopes| visit::visit_trait_item_fn(scopes, item));
+    }
+}
+
+/// Module path of a source file, relative to the crate root directory (the nearest
+/// ancestor holding `lib.rs` or `main.rs`): `src/state/vault.rs` is `state::vault`.
+/// Files outside any crate are treated as crate roots.
+pub fn module_path_for_file(path: &Path) -> Vec<String> {
+    let is_crate_root = |dir: &Path| dir.join("lib.rs").is_file() || dir.join("main.rs").is_file();
+    let Some(root) = path.ancestors().skip(1).find(|dir| is_crate_root(dir)) else {
+        return Vec::new();
+    };
+    let Ok(relative) = path.strip_prefix(root) else {
+        return Vec::new();
+    };
+
+    let mut module: Vec<String> = relative.iter()
+        .map(|component| component.to_string_lossy().into_owned())
+        .collect();
+    if let Some(file_name) = module.pop() {
+        match file_name.strip_suffix(".rs") {
+            Some("mod") => {}
+            Some("lib" | "main") if module.is_empty() => {}
+            Some(stem) => module.push(stem.to_string()),
+            None => module.push(file_name),
+        }
+    }
+    module
+}
+
+/// `module::name`, or just `name` at the crate root
+pub fn qualify(module: &[String], name: &str) -> String {
+    if module.is_empty() {
+        name.to_string()
+    } else {
+        format!("{}::{}", module.join("::"), name)
+    }
+}
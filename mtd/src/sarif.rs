@@ -0,0 +1,60 @@
+//! SARIF 2.1.0 output: one result per type whose trait depth is over the
+//! `--fail-above` limit, located at the impl that starts its deepest chain. Without a
+//! limit, the types at the maximum depth are reported as notes.
+
+use std::io::{self, Write};
+use std::path::Path;
+
+use serde_json::json;
+use trr_core::sarif::{location, write_log};
+
+use mtd::TraitAnalyzer;
+
+const RULE_ID: &str = "trait-depth";
+
+/// `offending` holds the types over `limit`, or at the maximum depth when there is no
+/// limit, with their deepest chains
+pub fn write_sarif(
+    out: impl Write,
+    analyzer: &TraitAnalyzer,
+    offending: &[(&str, Vec<String>)],
+    limit: Option<usize>,
+    base: &Path,
+) -> io::Result<()> {
+    let results: Vec<_> = offending.iter()
+        .map(|(type_name, chain)| {
+            let locations: Vec<_> = analyzer.impl_location(type_name, &chain[0])
+                .map(|impl_location| location(impl_location, base))
+                .into_iter()
+                .collect();
+            let (level, text) = match limit {
+                Some(limit) => ("warning", format!(
+                    "{} has a maximum trait depth of {} (limit {}): {} → {}",
+                    type_name, chain.len(), limit, type_name, chain.join(" → ")
+                )),
+                None => ("note", format!(
+                    "{} has the maximum trait depth of {}: {} → {}",
+                    type_name, chain.len(), type_name, chain.join(" → ")
+                )),
+            };
+            json!({
+                "ruleId": RULE_ID,
+                "level": level,
+                "message": { "text": text },
+                "locations": locations,
+                "properties": { "depth": chain.len(), "chain": chain },
+            })
+        })
+        .collect();
+
+    let rule = json!({
+        "id": RULE_ID,
+        "name": "MaximumTraitDepth",
+        "shortDescription": { "text": "Trait hierarchy deeper than the allowed maximum" },
+        "fullDescription": {
+            "text": "The longest supertrait chain among the traits a type implements exceeds the configured limit.",
+        },
+        "defaultConfiguration": { "level": "warning" },
+    });
+    write_log(out, "mtd", env!("CARGO_PKG_VERSION"), vec![rule], results)
+}
@@ -0,0 +1,46 @@
+//! Cargo workspaces given as the target: the member crates, found with
+//! `cargo metadata`, so results can be grouped by crate.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use mtd::Crate;
+use serde::Deserialize;
+
+#[derive(Deserialize)]
+struct Metadata {
+    packages: Vec<Package>,
+}
+
+#[derive(Deserialize)]
+struct Package {
+    name: String,
+    manifest_path: PathBuf,
+}
+
+/// The member crates of the workspace whose `Cargo.toml` is in `dir`, with roots
+/// below `dir` as given (so they match the paths of the analyzed files)
+pub fn members(dir: &Path) -> io::Result<Vec<Crate>> {
+    let output = Command::new("cargo")
+        .args(["metadata", "--no-deps", "--format-version", "1", "--manifest-path"])
+        .arg(dir.join("Cargo.toml"))
+        .output()?;
+    if !output.status.success() {
+        return Err(io::Error::other(format!(
+            "cargo metadata failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    let metadata: Metadata = serde_json::from_slice(&output.stdout)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+
+    let canonical_dir = fs::canonicalize(dir)?;
+    Ok(metadata.packages.into_iter()
+        .filter_map(|package| {
+            let root = package.manifest_path.parent()?.strip_prefix(&canonical_dir).ok()?;
+            Some(Crate { name: package.name, root: dir.join(root) })
+        })
+        .collect())
+}
@@ -0,0 +1,27 @@
+// Bounds on associated types add hierarchy that supertraits alone do not show
+
+pub trait Storage {}
+pub trait Database: Storage {}
+pub trait Codec {}
+
+pub trait Store {
+    type Backend: Database;
+    type Key;
+}
+// Expected: Store has trait depth 1 and associated bound depth 3
+// (Store -> Database -> Storage)
+
+pub trait Cache: Store
+where
+    Self::Encoder: Codec,
+{
+    type Encoder;
+}
+// Expected: Cache has trait depth 2 and associated bound depth 4
+// (Cache -> Store -> Database -> Storage)
+
+pub struct Memory;
+impl Store for Memory {
+    type Backend = Memory;
+    type Key = u64;
+}
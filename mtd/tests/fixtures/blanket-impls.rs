@@ -0,0 +1,25 @@
+// Blanket impls apply to every type that meets their bounds
+
+pub trait Base {}
+pub trait Extended: Base {}
+pub trait Audited: Extended {}
+
+impl<T: Base> Extended for T {}
+impl<T> Audited for T where T: Extended + Clone {}
+
+#[derive(Clone)]
+pub struct Vault;
+impl Base for Vault {}
+// Expected: Vault implements Extended and Audited, depth 3 (Audited -> Extended -> Base)
+
+pub struct Plain;
+impl Base for Plain {}
+// Expected: Plain implements Extended but not Audited (not Clone), depth 2
+
+pub struct Other;
+impl Clone for Other {
+    fn clone(&self) -> Self {
+        Other
+    }
+}
+// Expected: Other gets neither blanket impl, depth 1
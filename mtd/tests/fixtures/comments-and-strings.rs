@@ -0,0 +1,25 @@
+// Declarations that only appear in comments, doc comments, and string
+// literals must not reach the trait graph
+
+pub trait Real {}
+
+pub struct Counted;
+impl Real for Counted {}
+// Expected: Counted should have depth 1
+
+// impl Real for Ghost {}
+// pub trait Phantom: Real {}
+
+/* impl Phantom for Counted {}
+   pub trait Deeper: Phantom {} */
+
+/// ```
+/// impl Phantom for Doc {}
+/// ```
+pub fn documented() {}
+
+pub const SNIPPET: &str = "impl Phantom for Quoted {}";
+pub const RAW_SNIPPET: &str = r#"
+pub trait Phantom: Real {}
+impl Phantom for RawQuoted {}
+"#;
@@ -0,0 +1,6 @@
+// Excluded by the config; would otherwise exceed the limit of 2
+pub trait A {}
+pub trait B: A {}
+pub trait C: B {}
+pub struct Generated;
+impl C for Generated {}
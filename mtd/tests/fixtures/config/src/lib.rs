@@ -0,0 +1,9 @@
+// Analyzed with the mtd.toml next to src/: Debug and Clone are ignored, so Vault
+// has depth 2 (Journal → Ledger) instead of 3 through `Ledger: Clone`
+pub trait Ledger: Clone {}
+pub trait Journal: Ledger {}
+
+#[derive(Clone, Debug)]
+pub struct Vault;
+impl Ledger for Vault {}
+impl Journal for Vault {}
@@ -0,0 +1,15 @@
+// Supertrait cycles, as left behind by copy-paste or macro errors
+
+pub trait Alpha: Beta {}
+pub trait Beta: Gamma {}
+pub trait Gamma: Alpha {}
+
+pub trait Selfish: Selfish {}
+
+pub trait Base {}
+pub trait Derived: Base + Alpha {}
+
+pub struct Widget;
+impl Derived for Widget {}
+
+// Expected cycles: Alpha ⇄ Beta ⇄ Gamma and Selfish; Base and Derived are not in one
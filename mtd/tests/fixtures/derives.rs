@@ -0,0 +1,21 @@
+// Impls generated by #[derive] count like hand-written ones
+
+use serde::Serialize;
+
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+pub struct Key(u64);
+// Expected: Key should have depth 3 (Ord -> Eq -> PartialEq)
+
+pub trait Indexed: Ord {}
+
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Slot<T> {
+    Empty,
+    Filled(T),
+}
+impl<T: Ord> Indexed for Slot<T> {}
+// Expected: Slot<T> should have depth 4 (Indexed -> Ord -> Eq -> PartialEq)
+
+#[derive(serde::Deserialize)]
+pub struct Config;
+// Expected: Config should have depth 1
@@ -0,0 +1,22 @@
+// Trait objects in fields, signatures, impls and function bodies
+
+use std::sync::Arc;
+
+pub trait Base {}
+pub trait Handler: Base {}
+pub trait Logger {}
+
+pub struct Router {
+    pub handlers: Vec<Box<dyn Handler + Send>>,
+    pub fallback: Arc<dyn Handler>,
+    pub logger: Option<&'static dyn Logger>,
+}
+
+pub fn dispatch(handler: &(dyn Handler + Sync), logger: &mut dyn Logger) {
+    let boxed: Box<dyn Logger> = todo!();
+}
+
+impl Base for dyn Logger {}
+
+// Expected: Handler used 3 times (Box 1, Arc 1, & 1) at trait depth 2;
+// Logger used 4 times (& 1, &mut 1, Box 1, dyn 1) at trait depth 1
@@ -0,0 +1,14 @@
+#![feature(prelude_import)]
+#[prelude_import]
+use std::prelude::rust_2021::*;
+#[macro_use]
+extern crate std;
+pub trait Discriminator {}
+pub trait Owner: Discriminator {}
+pub trait Account: Owner {}
+pub struct Vault;
+impl Discriminator for Vault {}
+impl Owner for Vault {}
+impl Account for Vault {}
+pub struct Plain;
+impl Discriminator for Plain {}
@@ -0,0 +1,12 @@
+// An attribute macro generates the `Owner` and `Discriminator` impls of `Vault`,
+// which only show up in the `cargo expand` output (../../expand-output.rs)
+
+pub trait Discriminator {}
+pub trait Owner: Discriminator {}
+pub trait Account: Owner {}
+
+#[account]
+pub struct Vault;
+
+pub struct Plain;
+impl Discriminator for Plain {}
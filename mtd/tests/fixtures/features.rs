@@ -0,0 +1,32 @@
+// Conditional traits, impls and derives
+
+pub trait Base {}
+pub trait Extended: Base {}
+pub trait Scaled: Extended {}
+
+pub struct Vault;
+impl Base for Vault {}
+
+#[cfg(feature = "extended")]
+impl Extended for Vault {}
+
+#[cfg(all(feature = "extended", feature = "scaled"))]
+impl Scaled for Vault {}
+
+#[cfg(not(feature = "extended"))]
+pub struct Fallback;
+#[cfg(not(feature = "extended"))]
+impl Base for Fallback {}
+
+#[cfg_attr(feature = "ordering", derive(PartialEq, Eq, PartialOrd, Ord))]
+pub struct Key;
+
+#[cfg(target_os = "solana")]
+pub struct OnChain;
+#[cfg(target_os = "solana")]
+impl Base for OnChain {}
+
+// Expected depths:
+//   no flags (every item counted): Vault 3, Fallback 1, Key 3, OnChain 1
+//   --features extended: Vault 2, no Fallback, no Key, OnChain 1
+//   --all-features: Vault 3, no Fallback, Key 3, OnChain 1
@@ -0,0 +1,38 @@
+// Impls on the boundary of the analyzed code
+
+use serde::{Deserialize, Serialize};
+
+pub trait Settle {}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct VaultState {
+    pub balance: u64,
+}
+
+pub type Lamports = u64;
+
+impl Settle for VaultState {}
+impl Settle for Lamports {}
+impl Settle for Vec<u8> {}
+
+impl From<VaultState> for Vec<u8> {
+    fn from(state: VaultState) -> Self {
+        state.balance.to_le_bytes().to_vec()
+    }
+}
+
+impl std::fmt::Display for VaultState {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.balance)
+    }
+}
+
+impl<'de> Deserialize<'de> for VaultState {
+    fn deserialize<D: serde::Deserializer<'de>>(_deserializer: D) -> Result<Self, D::Error> {
+        unimplemented!()
+    }
+}
+
+// Expected: Settle for Vec<u8> (foreign type), From<VaultState> for Vec<u8> (trait and
+// type), Display and Deserialize for VaultState (foreign trait); the derives and the
+// impls of Settle for local types are not listed
@@ -0,0 +1,38 @@
+// Generic signatures with increasingly heavy trait bounds
+
+pub trait Account {}
+pub trait Encode<T> {}
+pub trait Storage {
+    type Backend;
+}
+pub trait Database {}
+
+pub struct Pool<T>(T);
+
+pub fn plain(amount: u64) -> u64 {
+    amount
+}
+
+pub fn single<T: Account>(account: T) -> T {
+    account
+}
+
+pub fn settle<T: Account + Encode<Vec<u8>>, U: Storage>(_account: T, _store: U, _hook: impl Fn(&T) -> Option<Vec<u8>>)
+where
+    U::Backend: Database + Send,
+{
+}
+
+impl<T: Account + Clone> Account for Pool<T> where T: Send + Sync {}
+
+impl<T: ?Sized> Pool<Box<T>> {
+    pub fn with<S: Storage>(self, _store: S) -> Self
+    where
+        <S as Storage>::Backend: Database,
+    {
+        self
+    }
+}
+
+// Expected, most bounds first: settle (6 bounds, 3 type parameters, 1 on associated
+// types, nesting 3), impl Account for Pool<T> (4), Pool::with (2), single (1)
@@ -0,0 +1,15 @@
+// Generic traits implemented at several instantiations.
+// Expected depths (default): Packet 3
+// Expected depths (--generic-instances): Packet 3, with Encode<u8> and
+// Encode<u16> listed as separate traits
+
+pub trait Sink<T> {}
+pub trait Encode<T>: Sink<T> {}
+pub trait Frame<T>: Encode<T> {}
+
+pub struct Packet;
+impl Sink<u8> for Packet {}
+impl Encode<u8> for Packet {}
+impl Frame<u8> for Packet {}
+impl Sink<u16> for Packet {}
+impl Encode<u16> for Packet {}
@@ -0,0 +1,45 @@
+// Traits and impls declared inside function bodies: test doubles and helpers
+
+pub trait Storage {}
+pub trait Validator: Storage {}
+
+pub struct Vault;
+impl Storage for Vault {}
+
+#[cfg(test)]
+mod tests {
+    use super::{Storage, Validator};
+
+    #[test]
+    fn rejects_empty_vault() {
+        struct MockVault;
+        impl Storage for MockVault {}
+        impl Validator for MockVault {}
+    }
+
+    #[test]
+    fn accepts_audited_vault() {
+        trait Audited: Validator {}
+
+        // Same name as in the test above, but a different type
+        struct MockVault;
+        impl Storage for MockVault {}
+        impl Validator for MockVault {}
+        impl Audited for MockVault {}
+    }
+}
+
+pub fn register() {
+    const _: () = {
+        impl Validator for super_vault::Handle {}
+    };
+}
+
+mod super_vault {
+    pub struct Handle;
+    impl super::Storage for Handle {}
+}
+
+// Expected depths: Vault 1, tests::rejects_empty_vault::MockVault 2,
+// tests::accepts_audited_vault::MockVault 3, super_vault::Handle 2;
+// with --no-local-items only Vault and super_vault::Handle (depth 1)
@@ -0,0 +1,8 @@
+// A crate spread over several modules, referencing traits and types by
+// crate::, super::, self:: and imported paths
+pub mod state;
+pub mod traits;
+
+pub struct Root;
+impl traits::Base for Root {}
+// Expected: Root should have depth 1
@@ -0,0 +1,9 @@
+pub mod vault;
+
+use crate::traits::{audit::Audited, Base, Extended};
+
+pub struct Pool;
+impl Base for Pool {}
+impl Extended for Pool {}
+impl Audited for Pool {}
+// Expected: state::Pool should have depth 3 (traits::audit::Audited -> traits::Extended -> traits::Base)
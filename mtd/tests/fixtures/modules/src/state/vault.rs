@@ -0,0 +1,8 @@
+use super::super::traits as t;
+use crate::traits::*;
+
+pub struct Vault;
+impl t::Base for Vault {}
+impl Extended for Vault {}
+// Expected: state::vault::Vault should have depth 2, with Extended (a glob import)
+// matched to traits::Extended
@@ -0,0 +1,8 @@
+pub trait Base {}
+pub trait Extended: Base {}
+
+pub mod audit {
+    use super::Extended;
+
+    pub trait Audited: Extended {}
+}
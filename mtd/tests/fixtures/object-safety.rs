@@ -0,0 +1,39 @@
+// Object-safe and non-object-safe traits, some of them used as trait objects
+
+pub trait Render {
+    fn render(&self) -> String;
+    fn boxed(self: Box<Self>) -> Box<dyn Render>;
+    fn item(&self) -> Option<Self::Output>;
+    type Output;
+    // Not in the vtable, so allowed
+    fn new() -> Self where Self: Sized;
+    fn map<F: Fn()>(&self, f: F) where Self: Sized;
+}
+
+pub trait Cloner: Clone {
+    fn name(&self) -> &str;
+}
+
+pub trait Builder {
+    fn build() -> Self;
+}
+
+pub trait Visitor {
+    const DEPTH: usize;
+    fn visit<T>(&self, value: T);
+    fn merge(&self, other: &Self);
+    fn iter(&self) -> impl Iterator<Item = u8>;
+}
+
+pub trait Sub: Builder {}
+
+pub trait Sizes where Self: Sized {}
+
+pub struct Scene {
+    pub layers: Vec<Box<dyn Render>>,
+    pub builder: Box<dyn Builder>,
+    pub sub: &'static dyn Sub,
+}
+
+// Expected: Render is object safe; Cloner, Builder, Visitor, Sub and Sizes are not;
+// Builder and Sub are flagged as used as trait objects
@@ -0,0 +1,20 @@
+// Traits imported under other names, on their own and inside a group
+use crate::traits::Validator as V;
+use crate::{traits::{Audited as Checked, Storage as S}};
+
+pub struct Pool;
+impl V for Pool {}
+impl S for Pool {}
+
+pub struct Vault;
+impl Checked for Vault {}
+
+mod inner {
+    use super::super::traits::Audited as A;
+
+    pub struct Nested;
+    impl A for Nested {}
+}
+
+// Expected: pool::Pool depth 2, pool::Vault and pool::inner::Nested depth 3,
+// all through traits::*; no trait is recorded as V, S, Checked or A
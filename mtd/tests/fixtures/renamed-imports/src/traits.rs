@@ -0,0 +1,3 @@
+pub trait Storage {}
+pub trait Validator: Storage {}
+pub trait Audited: Validator {}
@@ -0,0 +1,26 @@
+// Two modules declaring `trait Validator` with different hierarchies
+
+pub mod oracle {
+    pub trait Source {}
+    pub trait Validator: Source {}
+}
+
+pub mod vault {
+    pub trait Storage {}
+    pub trait Ledger: Storage {}
+    pub trait Validator: Ledger {}
+
+    pub struct Pool;
+    impl Validator for Pool {}
+}
+
+pub mod feeds {
+    use crate::oracle::Validator;
+
+    pub struct Feed;
+    impl Validator for Feed {}
+    impl super::oracle::Source for Feed {}
+}
+
+// Expected: vault::Pool depth 3 (vault::Validator → vault::Ledger → vault::Storage),
+// feeds::Feed depth 2 (oracle::Validator → oracle::Source)
@@ -0,0 +1,33 @@
+// Local traits built on std traits whose declarations are not in the tree
+
+use std::fmt;
+
+pub trait Failure: std::error::Error {}
+
+#[derive(Debug)]
+pub struct Overflow;
+impl fmt::Display for Overflow {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "overflow")
+    }
+}
+impl std::error::Error for Overflow {}
+impl Failure for Overflow {}
+// Expected: Overflow should have depth 3 (Failure -> Error -> Debug)
+
+pub trait Cursor: DoubleEndedIterator {}
+
+pub struct Walk;
+impl Iterator for Walk {
+    type Item = u8;
+    fn next(&mut self) -> Option<u8> {
+        None
+    }
+}
+impl DoubleEndedIterator for Walk {
+    fn next_back(&mut self) -> Option<u8> {
+        None
+    }
+}
+impl Cursor for Walk {}
+// Expected: Walk should have depth 3 (Cursor -> DoubleEndedIterator -> Iterator)
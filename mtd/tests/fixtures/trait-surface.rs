@@ -0,0 +1,38 @@
+// Traits of the same depth with very different surfaces
+
+pub trait Storage {
+    fn load(&self, key: &str) -> Vec<u8>;
+    fn store(&mut self, key: &str, value: Vec<u8>);
+    fn contains(&self, key: &str) -> bool {
+        !self.load(key).is_empty()
+    }
+}
+
+pub trait Ledger: Storage {
+    type Entry;
+    const VERSION: u8;
+    fn append(&mut self, entry: Self::Entry);
+    fn len(&self) -> usize {
+        0
+    }
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+pub trait Marker {}
+pub trait Tagged: Marker {}
+
+pub struct Book;
+impl Storage for Book {
+    fn load(&self, _key: &str) -> Vec<u8> { Vec::new() }
+    fn store(&mut self, _key: &str, _value: Vec<u8>) {}
+}
+impl Ledger for Book {
+    type Entry = u64;
+    const VERSION: u8 = 1;
+    fn append(&mut self, _entry: u64) {}
+}
+
+// Expected: 3 required and 3 provided methods, 2 associated items; Ledger has
+// 6 methods including Storage's, Tagged none
@@ -0,0 +1,20 @@
+// Unsafe traits and unsafe impls, including one for a std marker trait
+
+pub unsafe trait Zeroable {}
+pub unsafe trait Pod: Zeroable + Copy {}
+pub trait Safe {}
+
+#[derive(Clone, Copy)]
+pub struct Vault;
+unsafe impl Zeroable for Vault {}
+unsafe impl Pod for Vault {}
+
+pub struct Handle(*mut u8);
+unsafe impl Send for Handle {}
+impl Safe for Handle {}
+
+pub struct Wrapper<T>(T);
+unsafe impl<T: Pod> Zeroable for Wrapper<T> {}
+
+// Expected: unsafe traits Zeroable (Vault, Wrapper<T>) and Pod (Vault);
+// unsafe impls Zeroable for Vault, Pod for Vault, Send for Handle, Zeroable for Wrapper<T>
@@ -0,0 +1,33 @@
+// Supertraits declared through where clauses instead of the colon list
+
+pub trait Storage {}
+pub trait Versioned {}
+
+// Equivalent to `pub trait Ledger: Storage`
+pub trait Ledger
+where
+    Self: Storage,
+{
+}
+
+// Colon list and where clause combined
+pub trait Journal: Versioned
+where
+    Self: Ledger + Sized,
+{
+}
+
+struct Book;
+impl Journal for Book {}
+// Expected: Book should have depth 3 (Journal -> Ledger -> Storage)
+
+// Bounds on other types are not supertraits
+pub trait Converter<T>
+where
+    T: Storage,
+{
+}
+
+struct Plain;
+impl Converter<u8> for Plain {}
+// Expected: Plain should have depth 1
@@ -0,0 +1,24 @@
+// Hierarchies that exist only for tests; skipped with --no-tests
+pub trait Base {}
+
+pub struct Vault;
+impl Base for Vault {}
+// Expected: Vault should have depth 1
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    trait Mocked: Base {}
+    trait DeepMock: Mocked {}
+
+    struct Mock;
+    impl Base for Mock {}
+    impl DeepMock for Mock {}
+    // Expected: tests::Mock should have depth 3, and be skipped with --no-tests
+}
+
+#[cfg(all(test, feature = "fuzz"))]
+pub struct FuzzTarget;
+#[cfg(all(test, feature = "fuzz"))]
+impl Base for FuzzTarget {}
@@ -0,0 +1,6 @@
+pub trait Harness {}
+pub trait Scenario: Harness {}
+
+pub struct Case;
+impl Scenario for Case {}
+// Expected: Case should have depth 2, and be skipped with --no-tests
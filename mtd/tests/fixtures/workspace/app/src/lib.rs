@@ -0,0 +1,12 @@
+// A `Validator` of its own next to the one imported from vault-core
+use vault_core::Validator as CoreValidator;
+
+pub trait Validator {}
+pub trait Guard: CoreValidator {}
+
+pub struct Gate;
+impl Guard for Gate {}
+impl Validator for Gate {}
+
+// Expected: vault_app::Gate depth 3 (vault_app::Guard → vault_core::Validator →
+// vault_core::Storage), vault_core::Pool depth 1
@@ -0,0 +1,5 @@
+pub trait Storage {}
+pub trait Validator: Storage {}
+
+pub struct Pool;
+impl Storage for Pool {}
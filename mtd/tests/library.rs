@@ -0,0 +1,40 @@
+// Calls the mtd library directly instead of running the binary.
+
+use std::path::Path;
+
+use mtd::{analyze, Features, Options};
+
+fn fixture(name: &str) -> std::path::PathBuf {
+    Path::new(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures")).join(name)
+}
+
+#[test]
+fn analyze_directory() {
+    let report = analyze(&fixture("modules"), Options::default()).unwrap();
+
+    assert!(report.errors.is_empty());
+    assert_eq!(report.files.len(), 4);
+    assert_eq!(report.summary().max_depth, 3);
+    assert_eq!(
+        report.analyzer.deepest_chain("state::Pool"),
+        ["traits::audit::Audited", "traits::Extended", "traits::Base"]
+    );
+
+    let traits_file = fixture("modules").join("src/traits.rs");
+    let file_summary = report.analyzer_for(|path| path == traits_file).get_summary();
+    assert_eq!(file_summary.trait_count, 3);
+    assert_eq!(file_summary.impl_count, 0);
+}
+
+#[test]
+fn analyze_with_options() {
+    let options = Options {
+        features: Features::All,
+        no_tests: true,
+        ..Options::default()
+    };
+    let report = analyze(&fixture("features.rs"), options).unwrap();
+
+    assert_eq!(report.analyzer.calculate_max_depth("Vault"), 3);
+    assert!(!report.analyzer.impl_map.contains_key("Fallback"));
+}
@@ -0,0 +1,842 @@
+// Runs the mtd binary over the fixtures in tests/fixtures and checks the
+// per-type depths it reports against the expectations written in the fixtures.
+
+use std::collections::HashMap;
+use std::path::Path;
+use std::process::Command;
+
+fn fixtures_dir() -> &'static Path {
+    Path::new(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures"))
+}
+
+/// Run mtd with the given arguments and return its stdout
+fn run_mtd(args: &[&str]) -> String {
+    let output = Command::new(env!("CARGO_BIN_EXE_mtd"))
+        .args(args)
+        .output()
+        .expect("failed to run mtd");
+    assert!(
+        output.status.success(),
+        "mtd failed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    String::from_utf8(output.stdout).expect("mtd output is not UTF-8")
+}
+
+/// Collect the "Maximum trait depth" reported for each type
+fn type_depths(output: &str) -> HashMap<String, usize> {
+    let mut depths = HashMap::new();
+    let mut current_type = None;
+    for line in output.lines() {
+        if let Some(type_name) = line.strip_suffix(" implements:") {
+            current_type = Some(type_name.to_string());
+        } else if let Some(depth) = line.strip_prefix("Maximum trait depth: ") {
+            if let Some(type_name) = current_type.take() {
+                depths.insert(type_name, depth.trim().parse().unwrap());
+            }
+        }
+    }
+    depths
+}
+
+#[test]
+fn default_fixture_depths() {
+    let fixture = fixtures_dir().join("default-test.rs");
+    let output = run_mtd(&[fixture.to_str().unwrap()]);
+    let depths = type_depths(&output);
+
+    let expected = [
+        ("BasicType", 3),
+        ("SimpleType", 2),
+        ("MultiType", 2),
+        ("module_b::TypeB", 1),
+        ("DeepType", 1),
+        ("UnsafeType", 1),
+        ("CrateType", 1),
+        ("SuperType", 1),
+        ("RestrictedType", 1),
+        ("MultilineType", 2),
+        ("ComplexType", 2),
+        ("DeepInheritanceType", 5),
+        ("DiamondType", 3),
+        ("SpacedType", 1),
+        ("TabType", 1),
+        ("CommentedType", 1),
+        ("GenericType", 2),
+    ];
+    for (type_name, depth) in expected {
+        assert_eq!(depths.get(type_name), Some(&depth), "depth of {}", type_name);
+    }
+    assert!(output.contains("Overall Maximum Trait Depth: 5"));
+}
+
+#[test]
+fn inherent_impls_and_trait_objects_are_not_traits() {
+    let fixture = fixtures_dir().join("default-test.rs");
+    let output = run_mtd(&[fixture.to_str().unwrap()]);
+    let depths = type_depths(&output);
+
+    assert!(!depths.contains_key("ContextType"));
+    assert!(!output.contains("TraitObjectType"));
+}
+
+#[test]
+fn json_output() {
+    let fixture = fixtures_dir().join("default-test.rs");
+    let output = run_mtd(&["--format", "json", fixture.to_str().unwrap()]);
+    let report: serde_json::Value = serde_json::from_str(&output).expect("stdout is one JSON document");
+
+    assert_eq!(report["summary"]["max_depth"], 5);
+    assert_eq!(report["type_depths"]["DiamondType"], 3);
+    assert_eq!(report["trait_graph"]["DiamondTop"], serde_json::json!(["DiamondLeft", "DiamondRight"]));
+    assert_eq!(report["impl_map"]["BasicType"], serde_json::json!(["C"]));
+}
+
+#[test]
+fn versioned_json_and_sarif_on_stdout() {
+    let fixture = fixtures_dir().join("where-clause.rs");
+    let report: serde_json::Value = serde_json::from_str(&run_mtd(&["--format", "json", fixture.to_str().unwrap()])).unwrap();
+    assert_eq!(report["schema_version"], 1);
+    assert_eq!(report["tool"], "mtd");
+
+    // Without --fail-above, the deepest types are notes
+    let sarif: serde_json::Value = serde_json::from_str(&run_mtd(&["--format", "sarif", fixture.to_str().unwrap()])).unwrap();
+    assert_eq!(sarif["runs"][0]["properties"]["schemaVersion"], 1);
+    let results = sarif["runs"][0]["results"].as_array().unwrap();
+    assert_eq!(results.len(), 1);
+    assert_eq!(results[0]["level"], "note");
+    assert_eq!(results[0]["properties"]["depth"], 3);
+}
+
+#[test]
+fn where_clause_supertraits() {
+    let fixture = fixtures_dir().join("where-clause.rs");
+    let depths = type_depths(&run_mtd(&[fixture.to_str().unwrap()]));
+
+    assert_eq!(depths.get("Book"), Some(&3));
+    assert_eq!(depths.get("Plain"), Some(&1));
+}
+
+#[test]
+fn generic_traits_fold_into_declared_trait() {
+    let fixture = fixtures_dir().join("generic-instances.rs");
+    let output = run_mtd(&["--format", "json", fixture.to_str().unwrap()]);
+    let report: serde_json::Value = serde_json::from_str(&output).unwrap();
+
+    assert_eq!(report["type_depths"]["Packet"], 3);
+    let mut traits: Vec<_> = report["impl_map"]["Packet"].as_array().unwrap().iter()
+        .map(|name| name.as_str().unwrap())
+        .collect();
+    traits.sort();
+    assert_eq!(traits, ["Encode", "Frame", "Sink"]);
+}
+
+#[test]
+fn generic_instances_stay_distinct() {
+    let fixture = fixtures_dir().join("generic-instances.rs");
+    let output = run_mtd(&["--format", "json", "--generic-instances", fixture.to_str().unwrap()]);
+    let report: serde_json::Value = serde_json::from_str(&output).unwrap();
+
+    assert_eq!(report["type_depths"]["Packet"], 3);
+    let mut traits: Vec<_> = report["impl_map"]["Packet"].as_array().unwrap().iter()
+        .map(|name| name.as_str().unwrap())
+        .collect();
+    traits.sort();
+    assert_eq!(traits, ["Encode<u16>", "Encode<u8>", "Frame<u8>", "Sink<u16>", "Sink<u8>"]);
+}
+
+#[test]
+fn derived_impls() {
+    let fixture = fixtures_dir().join("derives.rs");
+    let depths = type_depths(&run_mtd(&[fixture.to_str().unwrap()]));
+
+    assert_eq!(depths.get("Key"), Some(&3));
+    assert_eq!(depths.get("Slot<T>"), Some(&4));
+    assert_eq!(depths.get("Config"), Some(&1));
+}
+
+#[test]
+fn deepest_chain_per_type() {
+    let fixture = fixtures_dir().join("default-test.rs");
+    let output = run_mtd(&[fixture.to_str().unwrap()]);
+    assert!(output.contains("Deepest chain: BasicType → C → B → A"));
+
+    let output = run_mtd(&["--format", "json", fixture.to_str().unwrap()]);
+    let report: serde_json::Value = serde_json::from_str(&output).unwrap();
+    assert_eq!(
+        report["type_chains"]["DeepInheritanceType"],
+        serde_json::json!(["Level5", "Level4", "Level3", "Level2", "Level1"])
+    );
+}
+
+#[test]
+fn dot_export() {
+    let fixture = fixtures_dir().join("where-clause.rs");
+    let dir = tempfile::tempdir().unwrap();
+    let dot_file = dir.path().join("traits.dot");
+    run_mtd(&["--dot", dot_file.to_str().unwrap(), fixture.to_str().unwrap()]);
+
+    let dot = std::fs::read_to_string(dot_file).unwrap();
+    assert!(dot.starts_with("digraph traits {"));
+    assert!(dot.contains("\"trait:Journal\" -> \"trait:Ledger\";"));
+    assert!(dot.contains("\"type:Book\" -> \"trait:Journal\" [style=dashed];"));
+}
+
+#[test]
+fn std_trait_hierarchies() {
+    let fixture = fixtures_dir().join("std-traits.rs");
+    let depths = type_depths(&run_mtd(&[fixture.to_str().unwrap()]));
+
+    assert_eq!(depths.get("Overflow"), Some(&3));
+    assert_eq!(depths.get("Walk"), Some(&3));
+}
+
+#[test]
+fn blanket_impls() {
+    let fixture = fixtures_dir().join("blanket-impls.rs");
+    let output = run_mtd(&[fixture.to_str().unwrap()]);
+    let depths = type_depths(&output);
+
+    assert_eq!(depths.get("Vault"), Some(&3));
+    assert_eq!(depths.get("Plain"), Some(&2));
+    assert_eq!(depths.get("Other"), Some(&1));
+    assert!(!depths.contains_key("T"));
+}
+
+#[test]
+fn module_paths_are_canonical() {
+    let fixture = fixtures_dir().join("modules");
+    let output = run_mtd(&["--format", "json", fixture.to_str().unwrap()]);
+    let report: serde_json::Value = serde_json::from_str(&output).unwrap();
+
+    assert_eq!(report["type_depths"]["Root"], 1);
+    assert_eq!(report["type_depths"]["state::Pool"], 3);
+    assert_eq!(report["type_depths"]["state::vault::Vault"], 2);
+    assert_eq!(report["trait_graph"]["traits::audit::Audited"], serde_json::json!(["traits::Extended"]));
+    assert_eq!(
+        report["impl_map"]["state::vault::Vault"],
+        serde_json::json!(["traits::Base", "traits::Extended"])
+    );
+}
+
+#[test]
+fn no_tests_skips_test_code() {
+    let fixture = fixtures_dir().join("with-tests");
+    let depths = type_depths(&run_mtd(&[fixture.to_str().unwrap()]));
+    assert_eq!(depths.get("tests::Mock"), Some(&3));
+    assert_eq!(depths.get("Case"), Some(&2));
+    assert!(depths.contains_key("FuzzTarget"));
+
+    let depths = type_depths(&run_mtd(&["--no-tests", fixture.to_str().unwrap()]));
+    assert_eq!(depths.get("Vault"), Some(&1));
+    assert_eq!(depths.len(), 1, "only Vault remains: {:?}", depths);
+}
+
+#[test]
+fn fail_above_limit() {
+    let fixture = fixtures_dir().join("where-clause.rs");
+    run_mtd(&["--fail-above", "3", fixture.to_str().unwrap()]);
+
+    let output = Command::new(env!("CARGO_BIN_EXE_mtd"))
+        .args(["--fail-above", "2", fixture.to_str().unwrap()])
+        .output()
+        .expect("failed to run mtd");
+    assert_eq!(output.status.code(), Some(1));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("1 type(s) exceed the maximum trait depth of 2"));
+    assert!(stderr.contains("Book (depth 3): Book → Journal → Ledger → Storage"));
+}
+
+#[test]
+fn sarif_results_point_at_impls() {
+    let fixture = fixtures_dir().join("where-clause.rs");
+    let dir = tempfile::tempdir().unwrap();
+    let sarif_file = dir.path().join("mtd.sarif");
+    let status = Command::new(env!("CARGO_BIN_EXE_mtd"))
+        .args(["--fail-above", "2", "--sarif", sarif_file.to_str().unwrap(), fixture.to_str().unwrap()])
+        .output()
+        .expect("failed to run mtd")
+        .status;
+    assert_eq!(status.code(), Some(1));
+
+    let sarif: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(sarif_file).unwrap()).unwrap();
+    assert_eq!(sarif["version"], "2.1.0");
+    let results = sarif["runs"][0]["results"].as_array().unwrap();
+    assert_eq!(results.len(), 1);
+    assert_eq!(results[0]["properties"]["depth"], 3);
+    let location = &results[0]["locations"][0]["physicalLocation"];
+    assert!(location["artifactLocation"]["uri"].as_str().unwrap().ends_with("where-clause.rs"));
+    assert_eq!(location["region"]["startLine"], 21);
+}
+
+#[test]
+fn markdown_report() {
+    let fixture = fixtures_dir().join("where-clause.rs");
+    let output = run_mtd(&["--format", "markdown", fixture.to_str().unwrap()]);
+
+    assert!(output.starts_with("# Trait Depth Report"));
+    assert!(output.contains("| Maximum trait depth | 3 |"));
+    assert!(output.contains("| `Book` | 3 | `Journal` → `Ledger` → `Storage` | 1 |"));
+    assert!(output.contains("- `Journal` (depth 3) at `"));
+    assert!(output.contains("where-clause.rs:14`\n  - `Ledger` (depth 2) at `"));
+    assert!(output.contains("where-clause.rs:7`\n    - `Storage` (depth 1) at `"));
+}
+
+#[test]
+fn comments_and_string_literals_are_ignored() {
+    let fixture = fixtures_dir().join("comments-and-strings.rs");
+    let output = run_mtd(&["--format", "json", fixture.to_str().unwrap()]);
+    let report: serde_json::Value = serde_json::from_str(&output).unwrap();
+
+    assert_eq!(report["trait_graph"], serde_json::json!({ "Real": [] }));
+    assert_eq!(report["impl_map"], serde_json::json!({ "Counted": ["Real"] }));
+}
+
+#[test]
+fn feature_gated_items() {
+    let fixture = fixtures_dir().join("features.rs");
+    let fixture = fixture.to_str().unwrap();
+
+    let depths = type_depths(&run_mtd(&[fixture]));
+    assert_eq!(depths.get("Vault"), Some(&3));
+    assert_eq!(depths.get("Fallback"), Some(&1));
+    assert_eq!(depths.get("Key"), Some(&3));
+
+    let depths = type_depths(&run_mtd(&["--features", "extended", fixture]));
+    assert_eq!(depths.get("Vault"), Some(&2));
+    assert!(!depths.contains_key("Fallback"));
+    assert!(!depths.contains_key("Key"));
+    assert_eq!(depths.get("OnChain"), Some(&1));
+
+    let depths = type_depths(&run_mtd(&["--all-features", fixture]));
+    assert_eq!(depths.get("Vault"), Some(&3));
+    assert!(!depths.contains_key("Fallback"));
+    assert_eq!(depths.get("Key"), Some(&3));
+}
+
+#[test]
+fn associated_bound_depth() {
+    let fixture = fixtures_dir().join("associated-types.rs");
+    let output = run_mtd(&["--format", "json", fixture.to_str().unwrap()]);
+    let report: serde_json::Value = serde_json::from_str(&output).unwrap();
+
+    assert_eq!(report["type_depths"]["Memory"], 1);
+    assert_eq!(report["associated_bound_depths"]["Store"], 3);
+    assert_eq!(report["associated_bound_depths"]["Cache"], 4);
+    assert_eq!(report["summary"]["max_associated_bound_depth"], 4);
+}
+
+#[test]
+fn dyn_trait_usage() {
+    let fixture = fixtures_dir().join("dyn-usage.rs");
+    let output = run_mtd(&["--format", "json", fixture.to_str().unwrap()]);
+    let report: serde_json::Value = serde_json::from_str(&output).unwrap();
+
+    let dyn_traits = report["dyn_traits"].as_array().unwrap();
+    assert_eq!(dyn_traits[0]["trait_name"], "Logger");
+    assert_eq!(dyn_traits[0]["uses"], 4);
+    assert_eq!(dyn_traits[0]["containers"], serde_json::json!({ "&": 1, "&mut": 1, "Box": 1, "dyn": 1 }));
+    assert_eq!(dyn_traits[1]["trait_name"], "Handler");
+    assert_eq!(dyn_traits[1]["depth"], 2);
+    assert_eq!(dyn_traits[1]["containers"], serde_json::json!({ "&": 1, "Arc": 1, "Box": 1 }));
+}
+
+#[test]
+fn trait_surface_metrics() {
+    let fixture = fixtures_dir().join("trait-surface.rs");
+    let output = run_mtd(&["--format", "json", fixture.to_str().unwrap()]);
+    let report: serde_json::Value = serde_json::from_str(&output).unwrap();
+
+    let summary = &report["summary"];
+    assert_eq!(summary["required_method_count"], 3);
+    assert_eq!(summary["provided_method_count"], 3);
+    assert_eq!(summary["associated_item_count"], 2);
+    assert_eq!(summary["max_inherited_methods"], 6);
+
+    let ledger = &report["trait_surfaces"]["Ledger"];
+    assert_eq!(ledger["required_methods"], 1);
+    assert_eq!(ledger["provided_methods"], 2);
+    assert_eq!(ledger["associated_types"], 1);
+    assert_eq!(ledger["associated_consts"], 1);
+    assert_eq!(ledger["inherited_methods"], 6);
+    assert_eq!(report["trait_surfaces"]["Tagged"]["inherited_methods"], 0);
+
+    let text = run_mtd(&[fixture.to_str().unwrap()]);
+    assert!(text.contains("Trait Methods: 3 required, 3 provided (2 associated items)"));
+    assert!(text.contains("Ledger -> [\"Storage\"] (1 required, 2 provided, 2 associated; 6 methods with supertraits)"));
+}
+
+#[test]
+fn object_safety() {
+    let fixture = fixtures_dir().join("object-safety.rs");
+    let output = run_mtd(&["--format", "json", fixture.to_str().unwrap()]);
+    let report: serde_json::Value = serde_json::from_str(&output).unwrap();
+
+    let object_safety = report["object_safety"].as_object().unwrap();
+    let mut not_object_safe: Vec<&str> = object_safety.keys().map(String::as_str).collect();
+    not_object_safe.sort();
+    assert_eq!(not_object_safe, ["Builder", "Cloner", "Sizes", "Sub", "Visitor"]);
+    assert_eq!(object_safety["Cloner"], serde_json::json!(["supertrait `Clone` is not object safe"]));
+    assert_eq!(object_safety["Sizes"], serde_json::json!(["requires `Self: Sized`"]));
+    assert_eq!(object_safety["Sub"], serde_json::json!(["supertrait `Builder` is not object safe"]));
+    assert_eq!(object_safety["Visitor"], serde_json::json!([
+        "associated const `DEPTH`",
+        "method `visit` has generic type parameters",
+        "method `merge` uses `Self` outside its receiver",
+        "method `iter` returns `impl Trait`",
+    ]));
+
+    let flagged: Vec<&str> = report["dyn_traits"].as_array().unwrap().iter()
+        .filter(|usage| !usage["object_safety_violations"].as_array().unwrap().is_empty())
+        .map(|usage| usage["trait_name"].as_str().unwrap())
+        .collect();
+    assert_eq!(flagged, ["Sub", "Builder"]);
+
+    let text = run_mtd(&[fixture.to_str().unwrap()]);
+    assert!(text.contains("Builder: 1 use(s) [Box 1], trait depth 1\n  warning: not object safe: associated function `build` has no `self` receiver"));
+}
+
+/// `cargo expand` is replaced by a script printing `tests/fixtures/expand-output.rs`,
+/// found by cargo as the `cargo-expand` subcommand on PATH
+#[cfg(unix)]
+#[test]
+fn expanded_depth() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let bin_dir = tempfile::tempdir().unwrap();
+    let script = bin_dir.path().join("cargo-expand");
+    let expanded = fixtures_dir().join("expand-output.rs");
+    std::fs::write(&script, format!("#!/bin/sh\ncat '{}'\n", expanded.display())).unwrap();
+    std::fs::set_permissions(&script, std::fs::Permissions::from_mode(0o755)).unwrap();
+    let path = format!("{}:{}", bin_dir.path().display(), std::env::var("PATH").unwrap_or_default());
+
+    let crate_dir = fixtures_dir().join("expand");
+    let output = Command::new(env!("CARGO_BIN_EXE_mtd"))
+        .args(["--expand", "--format", "json", crate_dir.to_str().unwrap()])
+        .env("PATH", &path)
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    let report: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+
+    assert_eq!(report["summary"]["max_depth"], 1);
+    assert_eq!(report["expanded"]["summary"]["max_depth"], 3);
+    assert_eq!(report["expanded"]["type_depths"]["Vault"], 3);
+    assert_eq!(report["expanded"]["type_depths"]["Plain"], 1);
+
+    let output = Command::new(env!("CARGO_BIN_EXE_mtd"))
+        .args(["--expand", crate_dir.to_str().unwrap()])
+        .env("PATH", &path)
+        .output()
+        .unwrap();
+    let text = String::from_utf8(output.stdout).unwrap();
+    assert!(text.contains("Overall Maximum Trait Depth: 1 → 3"));
+    assert!(text.contains("Vault: depth 0 → 3"));
+}
+
+#[test]
+fn expand_requires_a_crate() {
+    let output = Command::new(env!("CARGO_BIN_EXE_mtd"))
+        .args(["--expand", fixtures_dir().join("modules/src").to_str().unwrap()])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("has no Cargo.toml"));
+}
+
+const SHALLOW_LEDGER: &str = "pub trait Storage {}\npub trait Ledger: Storage {}\npub trait Journal: Ledger {}\n\
+    pub struct Book;\nimpl Journal for Book {}\n";
+const DEEPER_LEDGER: &str = "pub trait Storage {}\npub trait Ledger: Storage {}\npub trait Journal: Ledger {}\n\
+    pub trait Archive: Journal {}\npub struct Book;\nimpl Journal for Book {}\n\
+    pub struct Shelf;\nimpl Archive for Shelf {}\npub struct Note;\nimpl Storage for Note {}\n";
+
+#[test]
+fn baseline_hides_established_depth() {
+    let dir = tempfile::tempdir().unwrap();
+    let source = dir.path().join("lib.rs");
+    let baseline = dir.path().join("mtd-baseline.json");
+    std::fs::write(&source, SHALLOW_LEDGER).unwrap();
+    run_mtd(&["--write-baseline", baseline.to_str().unwrap(), source.to_str().unwrap()]);
+    let written: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(&baseline).unwrap()).unwrap();
+    assert_eq!(written["type_depths"], serde_json::json!({ "Book": 3 }));
+
+    // Book was already at depth 3, so only the new, deeper Shelf trips the limit
+    std::fs::write(&source, DEEPER_LEDGER).unwrap();
+    let output = Command::new(env!("CARGO_BIN_EXE_mtd"))
+        .args(["--baseline", baseline.to_str().unwrap(), "--fail-above", "2", source.to_str().unwrap()])
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(1));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("1 type(s) exceed the maximum trait depth of 2 beyond the baseline"));
+    assert!(stderr.contains("Shelf (depth 4)"));
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("Changes Since Baseline:"));
+    assert!(stdout.contains("+ Note: depth 1 (new)"));
+    assert!(stdout.contains("+ Shelf: depth 4 (new)"));
+    assert!(!stdout.contains("Book: depth"));
+
+    run_mtd(&["--baseline", baseline.to_str().unwrap(), "--fail-above", "4", source.to_str().unwrap()]);
+}
+
+#[test]
+fn diff_between_revisions() {
+    let repo = tempfile::tempdir().unwrap();
+    let git = |args: &[&str]| {
+        let status = Command::new("git")
+            .args(["-c", "user.name=mtd", "-c", "user.email=mtd@example.com"])
+            .args(args)
+            .current_dir(repo.path())
+            .output()
+            .unwrap()
+            .status;
+        assert!(status.success(), "git {:?}", args);
+    };
+    std::fs::create_dir(repo.path().join("src")).unwrap();
+    git(&["init", "--quiet"]);
+    std::fs::write(repo.path().join("src/lib.rs"), SHALLOW_LEDGER).unwrap();
+    git(&["add", "."]);
+    git(&["commit", "--quiet", "-m", "shallow"]);
+    std::fs::write(repo.path().join("src/lib.rs"), DEEPER_LEDGER).unwrap();
+    git(&["commit", "--quiet", "-am", "deeper"]);
+    // Uncommitted changes are not part of either revision
+    std::fs::write(repo.path().join("src/lib.rs"), "").unwrap();
+
+    let mtd = |args: &[&str]| Command::new(env!("CARGO_BIN_EXE_mtd"))
+        .args(args)
+        .current_dir(repo.path())
+        .output()
+        .unwrap();
+    let output = mtd(&["diff", "HEAD~1", "HEAD", "src"]);
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("Overall Maximum Trait Depth: 3 → 4"));
+    assert!(stdout.contains("+ Shelf: depth 4 (new)"));
+
+    let output = mtd(&["diff", "HEAD~1", "HEAD", "--format", "json", "--fail-above", "3", "src"]);
+    assert_eq!(output.status.code(), Some(1));
+    let report: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(report["changes"], serde_json::json!([
+        { "type_name": "Note", "old_depth": null, "new_depth": 1 },
+        { "type_name": "Shelf", "old_depth": null, "new_depth": 4 },
+    ]));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Shelf (depth 4)"));
+
+    let output = mtd(&["diff", "HEAD", "HEAD~1", "src"]);
+    assert!(String::from_utf8_lossy(&output.stdout).contains("- Shelf: depth 4 (removed)"));
+}
+
+#[test]
+fn invalid_arguments_fail() {
+    for args in [&["--top", "x"][..], &["--bogus"], &["--format", "xml"], &["--fail-above", "-1"], &["--no-config", "--sarif", "out.sarif"], &["diff"]] {
+        let output = Command::new(env!("CARGO_BIN_EXE_mtd"))
+            .args(args)
+            .arg(fixtures_dir())
+            .output()
+            .unwrap();
+        assert_eq!(output.status.code(), Some(2), "{:?}", args);
+        // Nothing reaches stdout, where a JSON document would go
+        assert!(output.stdout.is_empty(), "{:?}", args);
+        assert!(!output.stderr.is_empty(), "{:?}", args);
+    }
+}
+
+#[test]
+fn config_file_defaults() {
+    let config_dir = fixtures_dir().join("config");
+    let mtd = |args: &[&str]| Command::new(env!("CARGO_BIN_EXE_mtd"))
+        .args(args)
+        .current_dir(&config_dir)
+        .output()
+        .unwrap();
+
+    // format = "json" and fail-above = 2 come from mtd.toml; the excluded file would fail
+    let output = mtd(&["src"]);
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    let report: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(report["type_depths"], serde_json::json!({ "Vault": 2 }));
+    assert_eq!(report["impl_map"]["Vault"], serde_json::json!(["Journal", "Ledger"]));
+
+    // Command-line options take precedence
+    let output = mtd(&["--format", "text", "--fail-above", "1", "src"]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stdout).contains("Overall Maximum Trait Depth: 2"));
+
+    let output = mtd(&["--no-config", "src"]);
+    assert!(output.status.success());
+    let depths = type_depths(&String::from_utf8_lossy(&output.stdout));
+    assert_eq!(depths.get("Vault"), Some(&3));
+    assert_eq!(depths.get("generated::bindings::Generated"), Some(&3));
+}
+
+#[test]
+fn trr_config_section() {
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::write(dir.path().join("trr.toml"), "[mscd]\nthreshold = 10\n\n[mtd]\nignore-traits = [\"Ledger\"]\n").unwrap();
+    let source = fixtures_dir().join("where-clause.rs");
+    let output = Command::new(env!("CARGO_BIN_EXE_mtd"))
+        .args(["--format", "json", source.to_str().unwrap()])
+        .current_dir(dir.path())
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    let report: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    // Journal → Ledger → Storage without Ledger leaves Journal → Versioned
+    assert_eq!(report["type_depths"]["Book"], 2);
+
+    std::fs::write(dir.path().join("mtd.toml"), "fail-above = \"deep\"\n").unwrap();
+    let output = Command::new(env!("CARGO_BIN_EXE_mtd"))
+        .arg(source.to_str().unwrap())
+        .current_dir(dir.path())
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Invalid config"));
+}
+
+#[test]
+fn same_named_traits_stay_separate() {
+    let fixture = fixtures_dir().join("same-names.rs");
+    let output = run_mtd(&["--format", "json", fixture.to_str().unwrap()]);
+    let report: serde_json::Value = serde_json::from_str(&output).unwrap();
+
+    assert_eq!(report["trait_graph"]["oracle::Validator"], serde_json::json!(["oracle::Source"]));
+    assert_eq!(report["trait_graph"]["vault::Validator"], serde_json::json!(["vault::Ledger"]));
+    assert!(report["trait_graph"].get("Validator").is_none());
+    assert_eq!(report["type_depths"]["vault::Pool"], 3);
+    assert_eq!(report["type_depths"]["feeds::Feed"], 2);
+    assert_eq!(report["type_chains"]["feeds::Feed"], serde_json::json!(["oracle::Validator", "oracle::Source"]));
+}
+
+#[test]
+fn workspace_grouped_by_crate() {
+    let workspace = fixtures_dir().join("workspace");
+    let output = run_mtd(&["--format", "json", workspace.to_str().unwrap()]);
+    let report: serde_json::Value = serde_json::from_str(&output).unwrap();
+
+    assert_eq!(report["crates"]["vault-core"]["max_depth"], 1);
+    assert_eq!(report["crates"]["vault-core"]["trait_count"], 2);
+    assert_eq!(report["crates"]["vault-app"]["max_depth"], 3);
+    assert_eq!(report["crates"]["vault-app"]["trait_count"], 2);
+    // The combined view follows chains across crates and keeps both `Validator`s apart
+    assert_eq!(report["summary"]["trait_count"], 4);
+    assert_eq!(
+        report["type_chains"]["vault_app::Gate"],
+        serde_json::json!(["vault_app::Guard", "vault_core::Validator", "vault_core::Storage"])
+    );
+
+    let text = run_mtd(&[workspace.to_str().unwrap()]);
+    assert!(text.contains("Crate-Level Summary:"));
+    assert!(text.contains("vault-app ("));
+
+    // A single crate is not grouped
+    let output = run_mtd(&["--format", "json", workspace.join("core").to_str().unwrap()]);
+    let report: serde_json::Value = serde_json::from_str(&output).unwrap();
+    assert!(report.get("crates").is_none());
+    assert_eq!(report["type_depths"]["Pool"], 1);
+}
+
+#[test]
+fn top_deepest_types() {
+    let fixture = fixtures_dir().join("default-test.rs");
+    let output = run_mtd(&["--top", "2", fixture.to_str().unwrap()]);
+    assert!(output.contains("Top 2 Deepest Types:\n1. DeepInheritanceType (depth 5) at "));
+    assert!(output.contains("default-test.rs:129\n   DeepInheritanceType → Level5 → Level4 → Level3 → Level2 → Level1\n2. BasicType (depth 3)"));
+    // The full per-type listing is left out
+    assert!(!output.contains("implements:"));
+
+    let output = run_mtd(&["--top", "1", "--format", "json", fixture.to_str().unwrap()]);
+    let report: serde_json::Value = serde_json::from_str(&output).unwrap();
+    let top = report["top"].as_array().unwrap();
+    assert_eq!(top.len(), 1);
+    assert_eq!(top[0]["type_name"], "DeepInheritanceType");
+    assert_eq!(top[0]["depth"], 5);
+    assert!(top[0]["location"].as_str().unwrap().ends_with("default-test.rs:129"));
+
+    let output = run_mtd(&["--top", "3", "--format", "markdown", fixture.to_str().unwrap()]);
+    assert!(output.contains("The 3 deepest of 17 types."));
+}
+
+#[test]
+fn unsafe_inventory() {
+    let fixture = fixtures_dir().join("unsafe-items.rs");
+    let output = run_mtd(&["--format", "json", fixture.to_str().unwrap()]);
+    let report: serde_json::Value = serde_json::from_str(&output).unwrap();
+
+    let unsafe_traits = report["unsafe_traits"].as_array().unwrap();
+    assert_eq!(unsafe_traits.len(), 2);
+    assert_eq!(unsafe_traits[0]["trait_name"], "Pod");
+    assert_eq!(unsafe_traits[0]["location"]["line"], 4);
+    assert_eq!(unsafe_traits[1]["trait_name"], "Zeroable");
+    assert_eq!(unsafe_traits[1]["implementors"], serde_json::json!(["Vault", "Wrapper<T>"]));
+
+    let unsafe_impls: Vec<(&str, &str, u64)> = report["unsafe_impls"].as_array().unwrap().iter()
+        .map(|unsafe_impl| (
+            unsafe_impl["trait_name"].as_str().unwrap(),
+            unsafe_impl["type_name"].as_str().unwrap(),
+            unsafe_impl["location"]["line"].as_u64().unwrap(),
+        ))
+        .collect();
+    assert_eq!(unsafe_impls, [
+        ("Zeroable", "Vault", 9),
+        ("Pod", "Vault", 10),
+        ("Send", "Handle", 13),
+        ("Zeroable", "Wrapper<T>", 17),
+    ]);
+
+    let text = run_mtd(&[fixture.to_str().unwrap()]);
+    assert!(text.contains("unsafe trait Zeroable at "));
+    assert!(text.contains("unsafe impl Send for Handle at "));
+}
+
+#[test]
+fn renamed_imports() {
+    let fixture = fixtures_dir().join("renamed-imports");
+    let output = run_mtd(&["--format", "json", fixture.to_str().unwrap()]);
+    let report: serde_json::Value = serde_json::from_str(&output).unwrap();
+
+    assert_eq!(report["type_depths"], serde_json::json!({
+        "pool::Pool": 2,
+        "pool::Vault": 3,
+        "pool::inner::Nested": 3,
+    }));
+    assert_eq!(report["impl_map"]["pool::Pool"], serde_json::json!(["traits::Storage", "traits::Validator"]));
+    assert_eq!(report["impl_map"]["pool::inner::Nested"], serde_json::json!(["traits::Audited"]));
+}
+
+#[test]
+fn csv_rows_per_type_and_trait() {
+    let fixture = fixtures_dir().join("where-clause.rs");
+    let output = run_mtd(&["--format", "csv", fixture.to_str().unwrap()]);
+    let mut lines = output.lines();
+    assert_eq!(lines.next(), Some("type,trait,depth,chain,file,line"));
+    let book = lines.next().unwrap();
+    assert!(book.starts_with("Book,Journal,3,Journal → Ledger → Storage,"));
+    assert!(book.ends_with("where-clause.rs,21"));
+
+    // Fields with commas are quoted
+    let dir = tempfile::tempdir().unwrap();
+    let source = dir.path().join("pair.rs");
+    std::fs::write(&source, "pub trait Swap {}\npub struct Pair<A, B>(A, B);\nimpl<A, B> Swap for Pair<A, B> {}\n").unwrap();
+    let output = run_mtd(&["--format", "csv", source.to_str().unwrap()]);
+    assert!(output.lines().nth(1).unwrap().starts_with("\"Pair<A,B>\",Swap,1,Swap,"));
+}
+
+#[test]
+fn supertrait_cycles() {
+    let fixture = fixtures_dir().join("cycles.rs");
+    let output = run_mtd(&["--format", "json", fixture.to_str().unwrap()]);
+    let report: serde_json::Value = serde_json::from_str(&output).unwrap();
+
+    let cycles: Vec<Vec<(&str, u64)>> = report["supertrait_cycles"].as_array().unwrap().iter()
+        .map(|cycle| cycle.as_array().unwrap().iter()
+            .map(|member| (
+                member["trait_name"].as_str().unwrap(),
+                member["location"]["line"].as_u64().unwrap(),
+            ))
+            .collect())
+        .collect();
+    assert_eq!(cycles, [
+        vec![("Alpha", 3), ("Beta", 4), ("Gamma", 5)],
+        vec![("Selfish", 7)],
+    ]);
+
+    let text = run_mtd(&[fixture.to_str().unwrap()]);
+    assert!(text.contains("Alpha ⇄ Beta ⇄ Gamma"));
+}
+
+#[test]
+fn items_in_function_bodies() {
+    let fixture = fixtures_dir().join("local-items.rs");
+    let output = run_mtd(&["--format", "json", fixture.to_str().unwrap()]);
+    let report: serde_json::Value = serde_json::from_str(&output).unwrap();
+    assert_eq!(report["type_depths"], serde_json::json!({
+        "Vault": 1,
+        "super_vault::Handle": 2,
+        "tests::rejects_empty_vault::MockVault": 2,
+        "tests::accepts_audited_vault::MockVault": 3,
+    }));
+
+    let output = run_mtd(&["--no-local-items", "--format", "json", fixture.to_str().unwrap()]);
+    let report: serde_json::Value = serde_json::from_str(&output).unwrap();
+    assert_eq!(report["type_depths"], serde_json::json!({
+        "Vault": 1,
+        "super_vault::Handle": 1,
+    }));
+}
+
+#[test]
+fn bound_heavy_signatures() {
+    let fixture = fixtures_dir().join("generic-bounds.rs");
+    let output = run_mtd(&["--format", "json", fixture.to_str().unwrap()]);
+    let report: serde_json::Value = serde_json::from_str(&output).unwrap();
+    assert_eq!(report["summary"]["max_signature_bounds"], 6);
+
+    let signatures = report["bound_heavy_signatures"].as_array().unwrap();
+    let names: Vec<&str> = signatures.iter().map(|signature| signature["name"].as_str().unwrap()).collect();
+    assert_eq!(names, ["settle", "impl Account for Pool<T>", "Pool::with", "single"]);
+
+    let settle = &signatures[0];
+    assert_eq!(settle["bounds"], 6);
+    assert_eq!(settle["type_params"], 3);
+    assert_eq!(settle["projection_bounds"], 1);
+    // `impl Fn(&T) -> Option<Vec<u8>>`
+    assert_eq!(settle["nesting"], 3);
+    assert_eq!(settle["location"]["line"], 20);
+    assert_eq!(signatures[2]["projection_bounds"], 1);
+
+    let text = run_mtd(&[fixture.to_str().unwrap()]);
+    assert!(text.contains("Bound-Heavy Signatures (4 of 4):"));
+    assert!(text.contains("settle: 6 bound(s) on 3 type parameter(s), 1 on associated types, nesting 3 at "));
+}
+
+#[test]
+fn foreign_impls() {
+    let fixture = fixtures_dir().join("foreign-impls.rs");
+    let output = run_mtd(&["--format", "json", fixture.to_str().unwrap()]);
+    let report: serde_json::Value = serde_json::from_str(&output).unwrap();
+
+    let foreign: Vec<(&str, &str, bool, bool, u64)> = report["foreign_impls"].as_array().unwrap().iter()
+        .map(|foreign| (
+            foreign["trait_name"].as_str().unwrap(),
+            foreign["type_name"].as_str().unwrap(),
+            foreign["foreign_trait"].as_bool().unwrap(),
+            foreign["foreign_type"].as_bool().unwrap(),
+            foreign["location"]["line"].as_u64().unwrap(),
+        ))
+        .collect();
+    assert_eq!(foreign, [
+        ("Settle", "Vec<u8>", false, true, 16),
+        ("From<VaultState>", "Vec<u8>", true, true, 18),
+        ("std::fmt::Display", "VaultState", true, false, 24),
+        ("serde::Deserialize", "VaultState", true, false, 30),
+    ]);
+
+    let output = run_mtd(&[fixture.to_str().unwrap()]);
+    assert!(output.contains("impl From<VaultState> for Vec<u8> (foreign trait and type) at "));
+}
+
+#[test]
+fn source_locations() {
+    let fixture = fixtures_dir().join("where-clause.rs");
+    let output = run_mtd(&["--format", "json", fixture.to_str().unwrap()]);
+    let report: serde_json::Value = serde_json::from_str(&output).unwrap();
+    assert_eq!(report["trait_locations"]["Journal"]["line"], 14);
+    assert_eq!(report["trait_locations"]["Ledger"]["line"], 7);
+    assert_eq!(report["impl_locations"]["Book"]["Journal"]["line"], 21);
+    assert_eq!(report["impl_locations"]["Plain"]["Converter"]["line"], 32);
+
+    let text = run_mtd(&[fixture.to_str().unwrap()]);
+    assert!(text.contains("Book implements:\n  - Journal ("));
+    assert!(text.contains("where-clause.rs:21)\n"));
+    let journal = text.lines().find(|line| line.starts_with("Journal -> ")).unwrap();
+    assert!(journal.ends_with("where-clause.rs:14"));
+
+    let output = Command::new(env!("CARGO_BIN_EXE_mtd"))
+        .args(["--fail-above", "2", fixture.to_str().unwrap()])
+        .output()
+        .expect("failed to run mtd");
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("Book (depth 3): Book → Journal → Ledger → Storage (impl at "));
+    assert!(stderr.contains("where-clause.rs:21)"));
+}
@@ -0,0 +1,310 @@
+//! Unchecked arithmetic: `+`, `-` and `*` (and `+=`, `-=`, `*=`) on lamport and token
+//! amounts in Solana program crates built without overflow checks.
+//!
+//! Solana programs are built with the release profile, where integer overflow wraps
+//! silently unless the workspace sets `overflow-checks = true`. A wrapped balance or
+//! fee is a classic exploit, so in crates without the checks every such operation on
+//! an amount-like value should use `checked_*` or `saturating_*` instead.
+//!
+//! An operand is amount-like when one of its identifiers is named like an amount (see
+//! [`AMOUNT_NAMES`]): `vault.amount`, `**from.lamports.borrow_mut()`,
+//! `ctx.accounts.pool.total_supply()`. Operations on floats (float literals or `as f64`
+//! casts), on two literals, and in `#[cfg(test)]` items, `#[test]` functions and
+//! `tests/` and `benches/` are left out.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use mdd::manifests::Manifests;
+use proc_macro2::Span;
+use serde::Serialize;
+use syn::spanned::Spanned;
+use syn::visit::{self, Visit};
+use syn::{BinOp, Expr, ImplItemFn, ItemFn, ItemImpl, ItemMod, ItemTrait, Lit, TraitItemFn};
+pub use trr_core::Location;
+use trr_core::source::{is_cfg_test, location};
+use trr_core::walk;
+
+/// Words that make an identifier amount-like
+pub const AMOUNT_NAMES: &[&str] = &[
+    "lamports", "amount", "balance", "supply", "fee", "price", "reward", "stake", "deposit", "withdraw",
+    "collateral", "debt", "liquidity", "shares", "principal", "interest", "payout", "tokens", "total",
+];
+
+/// Whether an identifier is named like an amount
+pub fn is_amount_name(name: &str) -> bool {
+    let name = name.to_lowercase();
+    AMOUNT_NAMES.iter().any(|amount| name.contains(amount))
+}
+
+/// A crate the analyzed files belong to
+#[derive(Clone, Debug, Serialize)]
+pub struct Crate {
+    pub name: String,
+    pub root: PathBuf,
+    /// Depends directly on one of [`mdd::PROGRAM_FRAMEWORKS`], or has no manifest to
+    /// tell
+    pub program: bool,
+    /// `overflow-checks = true` in the release profile of its workspace
+    pub overflow_checks: bool,
+    /// Analyzed: a program without overflow checks, or any crate when asked
+    pub analyzed: bool,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct Operation {
+    /// `+`, `-=`, ...
+    pub operator: String,
+    /// The source of the operation, when it fits on one line
+    pub expression: Option<String>,
+    pub location: Location,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct FunctionArithmetic {
+    #[serde(rename = "crate")]
+    pub krate: String,
+    /// Inline modules, then the impl type or trait for methods, then the name
+    pub name: String,
+    pub location: Location,
+    pub operations: Vec<Operation>,
+}
+
+#[derive(Debug, Default)]
+pub struct Analysis {
+    pub crates: Vec<Crate>,
+    /// Most operations first
+    pub functions: Vec<FunctionArithmetic>,
+    /// Files that could not be read or parsed
+    pub errors: Vec<(PathBuf, String)>,
+}
+
+impl Analysis {
+    pub fn analyzed_crates(&self) -> usize {
+        self.crates.iter().filter(|krate| krate.analyzed).count()
+    }
+
+    /// Program crates skipped because their workspace checks for overflow
+    pub fn protected_crates(&self) -> usize {
+        self.crates.iter().filter(|krate| krate.program && krate.overflow_checks && !krate.analyzed).count()
+    }
+
+    pub fn operations(&self) -> usize {
+        self.functions.iter().map(|function| function.operations.len()).sum()
+    }
+}
+
+/// Which crates to analyze
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Options {
+    /// Library and client crates too, not only programs
+    pub all_crates: bool,
+    /// Crates whose workspace enables overflow checks too
+    pub ignore_overflow_checks: bool,
+}
+
+/// Whether the release profile of the workspace of the crate at `root` checks for
+/// overflow
+fn overflow_checks(manifests: &mut Manifests, root: &Path) -> bool {
+    let workspace = manifests.workspace_root(root);
+    manifests.get(&workspace)
+        .and_then(|manifest| manifest.get("profile")?.get("release")?.get("overflow-checks")?.as_bool())
+        .unwrap_or(false)
+}
+
+/// Find the unchecked arithmetic of `source`, the contents of `path`, in `krate`
+pub fn analyze_source(path: &Path, source: &str, krate: &str) -> syn::Result<Vec<FunctionArithmetic>> {
+    let file = syn::parse_file(source)?;
+    let mut visitor = FileVisitor { path, source, krate, scope: Vec::new(), functions: Vec::new() };
+    visitor.visit_file(&file);
+    Ok(visitor.functions)
+}
+
+/// Find the unchecked arithmetic of the crates under `dir` (or `dir` itself, if it is a
+/// file) that `options` select, leaving out `target/` and test code
+pub fn analyze(dir: &Path, options: Options) -> io::Result<Analysis> {
+    let files = walk::program_files(dir)?;
+
+    let mut manifests = Manifests::default();
+    let mut analysis = Analysis::default();
+    for file in files {
+        let krate = manifests.crate_of(&file, dir);
+        let index = match analysis.crates.iter().position(|known| known.root == krate.root) {
+            Some(index) => index,
+            None => {
+                let overflow_checks = overflow_checks(&mut manifests, &krate.root);
+                let analyzed = (krate.program || options.all_crates) && (!overflow_checks || options.ignore_overflow_checks);
+                analysis.crates.push(Crate { name: krate.name, root: krate.root, program: krate.program, overflow_checks, analyzed });
+                analysis.crates.len() - 1
+            }
+        };
+        if !analysis.crates[index].analyzed {
+            continue;
+        }
+        let result = fs::read_to_string(&file)
+            .map_err(|e| e.to_string())
+            .and_then(|source| analyze_source(&file, &source, &analysis.crates[index].name).map_err(|e| e.to_string()));
+        match result {
+            Ok(functions) => analysis.functions.extend(functions),
+            Err(e) => analysis.errors.push((file, e)),
+        }
+    }
+    analysis.crates.sort_by(|a, b| a.name.cmp(&b.name));
+    analysis.functions.sort_by(|a, b| b.operations.len().cmp(&a.operations.len())
+        .then_with(|| a.location.file.cmp(&b.location.file))
+        .then_with(|| a.location.line.cmp(&b.location.line)));
+    Ok(analysis)
+}
+
+struct FileVisitor<'a> {
+    path: &'a Path,
+    source: &'a str,
+    krate: &'a str,
+    /// Inline modules, impl types, traits and enclosing functions
+    scope: Vec<String>,
+    functions: Vec<FunctionArithmetic>,
+}
+
+impl FileVisitor<'_> {
+    fn add_function(&mut self, sig: &syn::Signature, block: &syn::Block) {
+        let mut body = BodyVisitor { path: self.path, source: self.source, operations: Vec::new() };
+        body.visit_block(block);
+        if !body.operations.is_empty() {
+            let mut name = self.scope.clone();
+            name.push(sig.ident.to_string());
+            self.functions.push(FunctionArithmetic {
+                krate: self.krate.to_string(),
+                name: name.join("::"),
+                location: location(self.path, sig.ident.span()),
+                operations: body.operations,
+            });
+        }
+
+        // Functions declared in the body
+        self.scope.push(sig.ident.to_string());
+        self.visit_block(block);
+        self.scope.pop();
+    }
+}
+
+impl<'ast> Visit<'ast> for FileVisitor<'_> {
+    fn visit_item_mod(&mut self, item: &'ast ItemMod) {
+        if is_cfg_test(&item.attrs) {
+            return;
+        }
+        self.scope.push(item.ident.to_string());
+        visit::visit_item_mod(self, item);
+        self.scope.pop();
+    }
+
+    fn visit_item_fn(&mut self, item: &'ast ItemFn) {
+        if !is_cfg_test(&item.attrs) {
+            self.add_function(&item.sig, &item.block);
+        }
+    }
+
+    fn visit_item_impl(&mut self, item: &'ast ItemImpl) {
+        if is_cfg_test(&item.attrs) {
+            return;
+        }
+        let owner = match &*item.self_ty {
+            syn::Type::Path(path) => path.path.segments.last().map(|segment| segment.ident.to_string()),
+            _ => None,
+        };
+        self.scope.push(owner.unwrap_or_else(|| "_".to_string()));
+        visit::visit_item_impl(self, item);
+        self.scope.pop();
+    }
+
+    fn visit_impl_item_fn(&mut self, item: &'ast ImplItemFn) {
+        if !is_cfg_test(&item.attrs) {
+            self.add_function(&item.sig, &item.block);
+        }
+    }
+
+    fn visit_item_trait(&mut self, item: &'ast ItemTrait) {
+        self.scope.push(item.ident.to_string());
+        visit::visit_item_trait(self, item);
+        self.scope.pop();
+    }
+
+    fn visit_trait_item_fn(&mut self, item: &'ast TraitItemFn) {
+        if let Some(block) = &item.default {
+            self.add_function(&item.sig, block);
+        }
+    }
+}
+
+/// What an operand is made of
+#[derive(Default)]
+struct Operand {
+    amount: bool,
+    float: bool,
+    literal: bool,
+}
+
+impl<'ast> Visit<'ast> for Operand {
+    fn visit_ident(&mut self, ident: &'ast syn::Ident) {
+        self.amount |= is_amount_name(&ident.to_string());
+        self.float |= ident == "f32" || ident == "f64";
+    }
+
+    fn visit_lit(&mut self, lit: &'ast Lit) {
+        self.float |= matches!(lit, Lit::Float(_));
+    }
+}
+
+fn operand(expr: &Expr) -> Operand {
+    let mut operand = Operand { literal: matches!(expr, Expr::Lit(_)), ..Operand::default() };
+    operand.visit_expr(expr);
+    operand
+}
+
+/// The unchecked operations of one body, leaving out the items declared in it
+struct BodyVisitor<'a> {
+    path: &'a Path,
+    source: &'a str,
+    operations: Vec<Operation>,
+}
+
+impl BodyVisitor<'_> {
+    /// The source of `span`, if it is on one line
+    fn snippet(&self, span: Span) -> Option<String> {
+        let (start, end) = (span.start(), span.end());
+        if start.line != end.line {
+            return None;
+        }
+        let line = self.source.lines().nth(start.line - 1)?;
+        let text: String = line.chars().skip(start.column).take(end.column - start.column).collect();
+        Some(text)
+    }
+}
+
+impl<'ast> Visit<'ast> for BodyVisitor<'_> {
+    fn visit_item(&mut self, _: &'ast syn::Item) {}
+
+    fn visit_expr_binary(&mut self, expr: &'ast syn::ExprBinary) {
+        let operator = match expr.op {
+            BinOp::Add(_) => Some("+"),
+            BinOp::Sub(_) => Some("-"),
+            BinOp::Mul(_) => Some("*"),
+            BinOp::AddAssign(_) => Some("+="),
+            BinOp::SubAssign(_) => Some("-="),
+            BinOp::MulAssign(_) => Some("*="),
+            _ => None,
+        };
+        if let Some(operator) = operator {
+            let (left, right) = (operand(&expr.left), operand(&expr.right));
+            let unchecked = (left.amount || right.amount) && !(left.float || right.float) && !(left.literal && right.literal);
+            if unchecked {
+                self.operations.push(Operation {
+                    operator: operator.to_string(),
+                    expression: self.snippet(expr.span()),
+                    location: location(self.path, expr.op.span()),
+                });
+            }
+        }
+        visit::visit_expr_binary(self, expr);
+    }
+}
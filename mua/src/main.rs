@@ -0,0 +1,174 @@
+mod report;
+mod sarif;
+
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::process;
+
+use clap::Parser;
+use serde::Serialize;
+use trr_core::git::Repository;
+use trr_core::output::{self, Format};
+
+use mua::{Analysis, Crate, FunctionArithmetic, Options};
+
+#[derive(Parser)]
+#[clap(author, version, about = "MUA (Unchecked Arithmetic) Detector")]
+#[clap(after_help = "Examples:
+  mua programs/vault
+  mua --top 10 programs
+  mua --all-crates --ignore-overflow-checks .
+  mua --format sarif programs > arithmetic.sarif
+  mua --fail-above 0 programs")]
+struct Args {
+    /// Directory to analyze (relative to --repo when given)
+    #[clap(value_name = "DIR", default_value = ".")]
+    dir: PathBuf,
+
+    /// Git repository URL to clone, or path of a local checkout
+    #[clap(long, value_name = "REPO_URL_OR_PATH")]
+    repo: Option<String>,
+
+    /// Branch, tag or commit of --repo to analyze
+    #[clap(long, value_name = "REV", requires = "repo")]
+    rev: Option<String>,
+
+    /// Output format
+    #[clap(long, value_enum, default_value = "text")]
+    format: Format,
+
+    /// Only the N functions with the most unchecked operations
+    #[clap(long, value_name = "N")]
+    top: Option<usize>,
+
+    /// Analyze every crate, not only those depending on a Solana program framework
+    #[clap(long)]
+    all_crates: bool,
+
+    /// Analyze crates whose workspace enables overflow-checks in the release profile too
+    #[clap(long)]
+    ignore_overflow_checks: bool,
+
+    /// Exit with status 1 if there are more than N unchecked operations
+    #[clap(long, value_name = "N")]
+    fail_above: Option<usize>,
+}
+
+/// Structured form of the analysis for `--format json`
+#[derive(Serialize)]
+struct JsonReport<'a> {
+    summary: Summary,
+    crates: &'a [Crate],
+    /// Functions, most unchecked operations first
+    functions: &'a [FunctionArithmetic],
+    errors: Vec<FileError>,
+}
+
+#[derive(Serialize)]
+struct Summary {
+    /// Crates analyzed
+    crates: usize,
+    /// Program crates skipped because their workspace checks for overflow
+    protected_crates: usize,
+    functions: usize,
+    operations: usize,
+}
+
+#[derive(Serialize)]
+struct FileError {
+    file: PathBuf,
+    error: String,
+}
+
+fn print_text(analysis: &Analysis, functions: &[FunctionArithmetic]) {
+    println!("\nCrates:");
+    for krate in &analysis.crates {
+        let kind = if krate.program { "program" } else { "library" };
+        let checks = if krate.overflow_checks { "overflow-checks on" } else { "overflow-checks off" };
+        let skipped = if krate.analyzed { "" } else { ", skipped" };
+        println!("  {} ({}, {}{})", krate.name, kind, checks, skipped);
+    }
+    if !functions.is_empty() {
+        println!("\nUnchecked arithmetic by function:");
+    }
+    for function in functions {
+        println!("\n{}::{} ({}): {} operation(s)", function.krate, function.name, function.location, function.operations.len());
+        for operation in &function.operations {
+            match &operation.expression {
+                Some(expression) => println!("  {:<3} {} at {}", operation.operator, expression, operation.location),
+                None => println!("  {:<3} at {}", operation.operator, operation.location),
+            }
+        }
+    }
+
+    println!("\nSummary:");
+    println!("Crates analyzed: {}", analysis.analyzed_crates());
+    println!("Crates skipped for overflow checks: {}", analysis.protected_crates());
+    println!("Functions with unchecked arithmetic: {}", analysis.functions.len());
+    println!("Unchecked operations: {}", analysis.operations());
+}
+
+fn write_json(out: &mut dyn Write, analysis: &Analysis, functions: &[FunctionArithmetic]) -> io::Result<()> {
+    let report = JsonReport {
+        summary: Summary {
+            crates: analysis.analyzed_crates(),
+            protected_crates: analysis.protected_crates(),
+            functions: analysis.functions.len(),
+            operations: analysis.operations(),
+        },
+        crates: &analysis.crates,
+        functions,
+        errors: analysis.errors.iter()
+            .map(|(file, error)| FileError { file: file.clone(), error: error.clone() })
+            .collect(),
+    };
+    output::write_json(out, "mua", &report)
+}
+
+fn run(args: &Args) -> Result<(), Box<dyn std::error::Error>> {
+    let repository = args.repo.as_deref().map(|repo| Repository::open(repo, args.rev.as_deref())).transpose()?;
+    let dir = match &repository {
+        Some(repository) => repository.subdir(&args.dir)?,
+        None => args.dir.clone(),
+    };
+    if !dir.exists() {
+        return Err(format!("Path '{}' does not exist", dir.display()).into());
+    }
+    if args.format == Format::Text {
+        println!("Analyzing: {}", dir.display());
+    }
+
+    let options = Options { all_crates: args.all_crates, ignore_overflow_checks: args.ignore_overflow_checks };
+    let analysis = mua::analyze(&dir, options)?;
+    for (file, error) in &analysis.errors {
+        eprintln!("Error analyzing {}: {}", file.display(), error);
+    }
+    let functions = &analysis.functions[..args.top.map_or(analysis.functions.len(), |top| top.min(analysis.functions.len()))];
+
+    let mut out = io::stdout().lock();
+    match args.format {
+        Format::Text => print_text(&analysis, functions),
+        Format::Json => write_json(&mut out, &analysis, functions)?,
+        Format::Csv => report::write_csv(&mut out, functions)?,
+        Format::Markdown => report::write_markdown(&mut out, &dir, &analysis, functions)?,
+        Format::Sarif => {
+            // Artifact URIs are relative to the repository root, or to where mua runs
+            let base = repository.as_ref().map_or_else(|| PathBuf::from("."), |repository| repository.root().to_path_buf());
+            sarif::write_sarif(&mut out, functions, &base)?;
+            writeln!(out)?;
+        }
+    }
+    drop(out);
+    output::enforce_limit(args.fail_above, analysis.operations(), "unchecked arithmetic operation(s)", analysis.functions.iter().map(|function| {
+        format!("{}::{} ({}) at {}", function.krate, function.name, function.operations.len(), function.location)
+    }));
+    Ok(())
+}
+
+fn main() {
+    let args = Args::parse();
+    if let Err(e) = run(&args) {
+        eprintln!("Error: {}", e);
+        process::exit(1);
+    }
+}
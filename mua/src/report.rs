@@ -0,0 +1,77 @@
+//! `--format csv`, one row per operation, and `--format markdown`, a review-ready report
+//! with the crates and the functions doing unchecked arithmetic.
+
+use std::io::{self, Write};
+use std::path::Path;
+
+use trr_core::output::{markdown_code, write_csv_row};
+
+use mua::{Analysis, FunctionArithmetic};
+
+/// Rows are by function, most unchecked operations first
+pub fn write_csv(out: &mut dyn Write, functions: &[FunctionArithmetic]) -> io::Result<()> {
+    write_csv_row(out, &["crate", "function", "operator", "expression", "file", "line", "column"])?;
+    for function in functions {
+        for operation in &function.operations {
+            write_csv_row(out, &[
+                &function.krate,
+                &function.name,
+                &operation.operator,
+                operation.expression.as_deref().unwrap_or(""),
+                &operation.location.file.display().to_string(),
+                &operation.location.line.to_string(),
+                &operation.location.column.map_or(String::new(), |column| column.to_string()),
+            ])?;
+        }
+    }
+    Ok(())
+}
+
+pub fn write_markdown(out: &mut dyn Write, target: &Path, analysis: &Analysis, functions: &[FunctionArithmetic]) -> io::Result<()> {
+    writeln!(out, "# Unchecked Arithmetic Report\n")?;
+    writeln!(out, "Target: {}\n", markdown_code(&target.display().to_string()))?;
+    writeln!(out, "## Summary\n")?;
+    writeln!(out, "| Metric | Value |")?;
+    writeln!(out, "| --- | ---: |")?;
+    writeln!(out, "| Crates analyzed | {} |", analysis.analyzed_crates())?;
+    writeln!(out, "| Crates skipped for overflow checks | {} |", analysis.protected_crates())?;
+    writeln!(out, "| Functions with unchecked arithmetic | {} |", analysis.functions.len())?;
+    writeln!(out, "| Unchecked operations | {} |", analysis.operations())?;
+
+    if !analysis.crates.is_empty() {
+        writeln!(out, "\n## Crates\n")?;
+        writeln!(out, "| Crate | Program | Overflow checks | Analyzed |")?;
+        writeln!(out, "| --- | --- | --- | --- |")?;
+        let yes_no = |value: bool| if value { "yes" } else { "no" };
+        for krate in &analysis.crates {
+            writeln!(
+                out,
+                "| {} | {} | {} | {} |",
+                markdown_code(&krate.name),
+                yes_no(krate.program),
+                yes_no(krate.overflow_checks),
+                yes_no(krate.analyzed),
+            )?;
+        }
+    }
+
+    if functions.is_empty() {
+        return Ok(());
+    }
+    writeln!(out, "\n## Operations\n")?;
+    writeln!(out, "| Function | Operator | Expression | Location |")?;
+    writeln!(out, "| --- | --- | --- | --- |")?;
+    for function in functions {
+        for operation in &function.operations {
+            writeln!(
+                out,
+                "| {} | {} | {} | {} |",
+                markdown_code(&format!("{}::{}", function.krate, function.name)),
+                markdown_code(&operation.operator),
+                operation.expression.as_deref().map_or(String::new(), markdown_code),
+                markdown_code(&operation.location.to_string()),
+            )?;
+        }
+    }
+    Ok(())
+}
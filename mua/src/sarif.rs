@@ -0,0 +1,43 @@
+//! SARIF 2.1.0 output: an `unchecked-arithmetic` warning per operation, located at its
+//! operator.
+
+use std::io::{self, Write};
+use std::path::Path;
+
+use serde_json::json;
+use trr_core::sarif::{location, write_log};
+
+use mua::FunctionArithmetic;
+
+const RULE: &str = "unchecked-arithmetic";
+
+pub fn write_sarif(out: &mut dyn Write, functions: &[FunctionArithmetic], base: &Path) -> io::Result<()> {
+    let mut results = Vec::new();
+    for function in functions {
+        for operation in &function.operations {
+            let text = match &operation.expression {
+                Some(expression) => format!("Unchecked `{}` in {}: {}", operation.operator, function.name, expression),
+                None => format!("Unchecked `{}` in {}", operation.operator, function.name),
+            };
+            results.push(json!({
+                "ruleId": RULE,
+                "ruleIndex": 0,
+                "level": "warning",
+                "message": { "text": text },
+                "locations": [location(&operation.location, base)],
+                "properties": { "crate": function.krate, "function": function.name },
+            }));
+        }
+    }
+
+    let rules = vec![json!({
+        "id": RULE,
+        "name": "UncheckedArithmetic",
+        "shortDescription": { "text": "Unchecked arithmetic on an amount without overflow checks" },
+        "fullDescription": {
+            "text": "+, - or * on a lamport or token amount in a program crate whose release profile does not enable overflow-checks. Overflow wraps silently in such builds; use checked_* or saturating_* operations, or enable overflow-checks.",
+        },
+        "defaultConfiguration": { "level": "warning" },
+    })];
+    write_log(out, "mua", env!("CARGO_PKG_VERSION"), rules, results)
+}
@@ -0,0 +1,3 @@
+pub fn add_liquidity(reserve: u64, liquidity: u64) -> u64 {
+    reserve + liquidity
+}
@@ -0,0 +1,5 @@
+fn main() {
+    let amount: u64 = 5;
+    let doubled = amount * 2;
+    println!("{}", doubled);
+}
@@ -0,0 +1,41 @@
+use anchor_lang::prelude::*;
+
+declare_id!("Vau1t11111111111111111111111111111111111111");
+
+#[program]
+pub mod vault {
+    use super::*;
+
+    pub fn deposit(ctx: Context<Deposit>, amount: u64) -> Result<()> {
+        let vault = &mut ctx.accounts.vault;
+        vault.balance += amount;
+        vault.total_deposits = vault.total_deposits + 1;
+        Ok(())
+    }
+
+    pub fn withdraw(ctx: Context<Withdraw>, amount: u64) -> Result<()> {
+        let fee = amount * FEE_BPS / 10_000;
+        **ctx.accounts.vault.to_account_info().try_borrow_mut_lamports()? -= amount - fee;
+        ctx.accounts.vault.balance = ctx.accounts.vault.balance.checked_sub(amount).unwrap();
+        Ok(())
+    }
+}
+
+const FEE_BPS: u64 = 30;
+
+pub fn share_price(total_supply: u64, assets: u64) -> f64 {
+    assets as f64 / (total_supply as f64 + 1.0)
+}
+
+pub fn slots(start: u64, end: u64) -> u64 {
+    end - start
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn sums() {
+        let amount = 1u64;
+        assert_eq!(amount + 1, 2);
+    }
+}
@@ -0,0 +1,91 @@
+// Checks tests/fixtures: `unprotected`, a workspace whose vault program and client
+// build without overflow checks, and `protected`, whose release profile enables them.
+
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use mua::Options;
+
+fn fixture(name: &str) -> PathBuf {
+    Path::new(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures")).join(name)
+}
+
+#[test]
+fn operations() {
+    let analysis = mua::analyze(&fixture("unprotected"), Options::default()).unwrap();
+    let operations: Vec<_> = analysis.functions.iter()
+        .flat_map(|function| function.operations.iter().map(move |operation| {
+            (function.name.as_str(), operation.operator.as_str(), operation.expression.as_deref().unwrap_or(""), operation.location.line)
+        }))
+        .collect();
+    assert_eq!(operations, [
+        ("vault::withdraw", "*", "amount * FEE_BPS", 17),
+        ("vault::withdraw", "-=", "**ctx.accounts.vault.to_account_info().try_borrow_mut_lamports()? -= amount - fee", 18),
+        ("vault::withdraw", "-", "amount - fee", 18),
+        ("vault::deposit", "+=", "vault.balance += amount", 11),
+        ("vault::deposit", "+", "vault.total_deposits + 1", 12),
+    ]);
+    assert_eq!(analysis.functions[0].location.line, 16);
+    assert!(analysis.errors.is_empty());
+}
+
+#[test]
+fn crates() {
+    let analysis = mua::analyze(&fixture(""), Options::default()).unwrap();
+    let crates: Vec<_> = analysis.crates.iter()
+        .map(|krate| (krate.name.as_str(), krate.program, krate.overflow_checks, krate.analyzed))
+        .collect();
+    assert_eq!(crates, [
+        ("client", false, false, false),
+        ("pool", true, true, false),
+        ("vault", true, false, true),
+    ]);
+    assert_eq!(analysis.protected_crates(), 1);
+
+    let options = Options { all_crates: true, ignore_overflow_checks: true };
+    let analysis = mua::analyze(&fixture(""), options).unwrap();
+    let functions: Vec<_> = analysis.functions.iter().map(|function| (function.krate.as_str(), function.name.as_str())).collect();
+    assert_eq!(functions, [
+        ("vault", "vault::withdraw"),
+        ("vault", "vault::deposit"),
+        ("pool", "add_liquidity"),
+        ("client", "main"),
+    ]);
+}
+
+#[test]
+fn skips_floats_literals_and_tests() {
+    let source = "
+        fn price(total_supply: u64, assets: u64) -> f64 { assets as f64 / (total_supply as f64 + 1.0) }
+        fn constant() -> u64 { 2 * 3 }
+        fn unrelated(a: u64, b: u64) -> u64 { a + b }
+        #[cfg(test)]
+        mod tests { fn sums(amount: u64) -> u64 { amount + 1 } }
+        #[test]
+        fn adds() { let _ = AMOUNT + 1; }
+        impl Vault { fn credit(&mut self, amount: u64) { self.balance = self.balance.checked_add(amount).unwrap(); } }
+    ";
+    let functions = mua::analyze_source(Path::new("lib.rs"), source, "vault").unwrap();
+    assert!(functions.is_empty(), "{:?}", functions);
+}
+
+#[test]
+fn fail_above_and_sarif() {
+    let run = |args: &[&str]| Command::new(env!("CARGO_BIN_EXE_mua"))
+        .args(args)
+        .arg(fixture("unprotected"))
+        .output()
+        .expect("failed to run mua");
+
+    assert!(run(&["--fail-above", "5"]).status.success());
+
+    let output = run(&["--fail-above", "0", "--format", "sarif"]);
+    assert_eq!(output.status.code(), Some(1));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("5 unchecked arithmetic operation(s), more than 0"), "stderr: {}", stderr);
+    let log: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let results = log["runs"][0]["results"].as_array().unwrap();
+    assert_eq!(results.len(), 5);
+    assert_eq!(results[0]["ruleId"], "unchecked-arithmetic");
+    assert_eq!(results[0]["locations"][0]["physicalLocation"]["region"]["startColumn"], 26);
+}
@@ -0,0 +1,123 @@
+//! What the analyzers reading Anchor programs share: the accounts struct of a handler's
+//! `Context`, `#[derive(Accounts)]` structs and the constraints of their fields.
+
+use proc_macro2::{TokenStream, TokenTree};
+use syn::{Attribute, GenericArgument, PathArguments, Signature, Type};
+
+/// The type in a `Box<T>` or `Option<T>`, or the type itself
+pub fn inner_type(ty: &Type) -> &Type {
+    if let Type::Path(path) = ty {
+        if let Some(segment) = path.path.segments.last().filter(|segment| segment.ident == "Box" || segment.ident == "Option") {
+            if let PathArguments::AngleBracketed(args) = &segment.arguments {
+                if let Some(GenericArgument::Type(inner)) = args.args.first() {
+                    return inner_type(inner);
+                }
+            }
+        }
+    }
+    ty
+}
+
+/// The last segment of a type's path, through `Box`, `Option` and references
+pub fn type_name(ty: &Type) -> Option<String> {
+    match inner_type(ty) {
+        Type::Path(path) => path.path.segments.last().map(|segment| segment.ident.to_string()),
+        Type::Reference(reference) => type_name(&reference.elem),
+        _ => None,
+    }
+}
+
+/// The `T` of a `Context<T>` (or `Context<'_, '_, '_, 'info, T>`) parameter
+pub fn context_accounts(sig: &Signature) -> Option<String> {
+    sig.inputs.iter().find_map(|input| {
+        let syn::FnArg::Typed(typed) = input else { return None };
+        let Type::Path(path) = &*typed.ty else { return None };
+        let segment = path.path.segments.last().filter(|segment| segment.ident == "Context")?;
+        let PathArguments::AngleBracketed(args) = &segment.arguments else { return None };
+        args.args.iter().rev().find_map(|arg| match arg {
+            GenericArgument::Type(ty) => type_name(ty),
+            _ => None,
+        })
+    })
+}
+
+/// Whether the attributes derive `Accounts`
+pub fn derives_accounts(attrs: &[Attribute]) -> bool {
+    attrs.iter()
+        .filter(|attr| attr.path().is_ident("derive"))
+        .any(|attr| attr.meta.require_list().is_ok_and(|list| {
+            list.tokens.clone().into_iter().any(|tree| matches!(tree, TokenTree::Ident(ident) if ident == "Accounts"))
+        }))
+}
+
+/// The comma-separated constraints of `#[account(...)]` attributes, each as its tokens
+pub fn account_constraints(attrs: &[Attribute]) -> Vec<Vec<TokenTree>> {
+    let mut constraints = Vec::new();
+    for attr in attrs.iter().filter(|attr| attr.path().is_ident("account")) {
+        let Ok(list) = attr.meta.require_list() else { continue };
+        let mut current = Vec::new();
+        for tree in list.tokens.clone() {
+            if matches!(&tree, TokenTree::Punct(punct) if punct.as_char() == ',') {
+                constraints.push(std::mem::take(&mut current));
+            } else {
+                current.push(tree);
+            }
+        }
+        constraints.push(current);
+    }
+    constraints.retain(|constraint| !constraint.is_empty());
+    constraints
+}
+
+/// The constraints of `#[account(...)]` attributes, as the path before `=` (`init`,
+/// `space`, `token::mint`, `realloc::zero`) and the tokens after it
+pub fn keyed_constraints(attrs: &[Attribute]) -> Vec<(String, TokenStream)> {
+    account_constraints(attrs).into_iter()
+        .map(|constraint| {
+            let assign = constraint.iter().position(|tree| matches!(tree, TokenTree::Punct(punct) if punct.as_char() == '='));
+            let (key, value) = constraint.split_at(assign.unwrap_or(constraint.len()));
+            let key: String = key.iter().map(ToString::to_string).collect();
+            (key, value.iter().skip(1).cloned().collect())
+        })
+        .filter(|(key, _)| !key.is_empty())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn contexts() {
+        let file: syn::File = syn::parse_str(
+            "fn a(ctx: Context<Deposit>) {} fn b(amount: u64, ctx: Context<'_, '_, '_, 'info, Box<Withdraw<'info>>>) {} fn c(x: u8) {}",
+        ).unwrap();
+        let accounts: Vec<Option<String>> = file.items.iter()
+            .map(|item| match item {
+                syn::Item::Fn(item) => context_accounts(&item.sig),
+                _ => unreachable!(),
+            })
+            .collect();
+        assert_eq!(accounts, [Some("Deposit".to_string()), Some("Withdraw".to_string()), None]);
+    }
+
+    #[test]
+    fn accounts_structs() {
+        let item: syn::ItemStruct = syn::parse_str(
+            "#[derive(Accounts)] #[instruction(amount: u64)] struct Deposit<'info> {
+                #[account(mut, has_one = authority, seeds = [b\"vault\"], bump,)]
+                #[account(realloc::zero = false)]
+                vault: Box<Account<'info, Vault>>,
+            }",
+        ).unwrap();
+        assert!(derives_accounts(&item.attrs));
+        let field = item.fields.iter().next().unwrap();
+        assert_eq!(type_name(&field.ty).as_deref(), Some("Account"));
+
+        let keys: Vec<String> = keyed_constraints(&field.attrs).into_iter()
+            .map(|(key, value)| format!("{}={}", key, value))
+            .collect();
+        assert_eq!(keys, ["mut=", "has_one=authority", "seeds=[b\"vault\"]", "bump=", "realloc::zero=false"]);
+        assert_eq!(account_constraints(&field.attrs).len(), 5);
+    }
+}
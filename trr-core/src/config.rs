@@ -0,0 +1,34 @@
+//! Config files: a tool's own `<tool>.toml`, or its table in a `trr.toml` shared by all
+//! the tools. The nearest one in the working directory or its ancestors is used.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use serde::de::DeserializeOwned;
+
+/// The nearest `<tool>.toml` or `trr.toml` in `dir` or its ancestors
+pub fn find(dir: &Path, tool: &str) -> Option<PathBuf> {
+    let own = format!("{}.toml", tool);
+    dir.ancestors()
+        .flat_map(|dir| [dir.join(&own), dir.join("trr.toml")])
+        .find(|path| path.is_file())
+}
+
+/// The config of `tool` in `path`: the whole file, or the `[tool]` table of a `trr.toml`
+/// (the default config when it has none)
+pub fn load<T: DeserializeOwned + Default>(path: &Path, tool: &str) -> io::Result<T> {
+    let content = fs::read_to_string(path)?;
+    let invalid = |e: toml::de::Error| {
+        io::Error::new(io::ErrorKind::InvalidData, format!("Invalid config {}: {}", path.display(), e))
+    };
+    if path.file_name().is_some_and(|name| name == "trr.toml") {
+        let mut tables: toml::Table = toml::from_str(&content).map_err(invalid)?;
+        match tables.remove(tool) {
+            Some(table) => table.try_into().map_err(invalid),
+            None => Ok(T::default()),
+        }
+    } else {
+        toml::from_str(&content).map_err(invalid)
+    }
+}
@@ -0,0 +1,353 @@
+//! Fetching of repositories given with `--repo`: Git URLs (HTTPS or SSH) are fetched at
+//! a branch, tag or commit, with only the history that commit needs, into a temporary
+//! directory or a cache kept between runs (locked while fetching, and checked out anew
+//! for each run); local checkouts are used as they are or checked out at a revision.
+//! Also checkouts of other revisions to compare against, and the commits that touched
+//! a directory.
+
+use std::env;
+use std::fs::{self, File};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use tempfile::TempDir;
+use url::Url;
+
+/// How to fetch a repository
+#[derive(Clone, Debug, Default)]
+pub struct Fetch {
+    /// Branch, tag or commit to analyze, instead of the remote's default branch or a
+    /// local checkout's working tree
+    pub revision: Option<String>,
+    /// Directory keeping fetched repositories between runs, one per URL, so a later
+    /// run only fetches what changed (`TRR_CACHE_DIR`)
+    pub cache_dir: Option<PathBuf>,
+    /// Access token sent to HTTPS remotes, for private repositories (`TRR_GIT_TOKEN`).
+    /// SSH remotes use the SSH agent and keys as `git` does.
+    pub token: Option<String>,
+    /// Hosts the token is sent to (`TRR_GIT_TOKEN_HOST`, comma-separated); every HTTPS
+    /// host when empty
+    pub token_hosts: Vec<String>,
+}
+
+impl Fetch {
+    /// Fetching `revision`, with the cache directory and token from the environment
+    pub fn from_env(revision: Option<&str>) -> Self {
+        let var = |name| env::var_os(name).filter(|value| !value.is_empty());
+        Fetch {
+            revision: revision.map(str::to_string),
+            cache_dir: var("TRR_CACHE_DIR").map(PathBuf::from),
+            token: var("TRR_GIT_TOKEN").map(|token| token.to_string_lossy().into_owned()),
+            token_hosts: var("TRR_GIT_TOKEN_HOST")
+                .map(|hosts| hosts.to_string_lossy().split(',').map(|host| host.trim().to_ascii_lowercase()).filter(|host| !host.is_empty()).collect())
+                .unwrap_or_default(),
+        }
+    }
+
+    /// For URLs someone else chose, as `trr serve` takes them: the token only goes to
+    /// the hosts it is limited to, and nowhere when it is not limited
+    pub fn untrusted(mut self) -> Self {
+        if self.token_hosts.is_empty() {
+            self.token = None;
+        }
+        self
+    }
+
+    /// The token to send to `url`: an HTTPS URL of an allowed host
+    fn token_for(&self, url: &str) -> Option<&str> {
+        let url = Url::parse(url).ok().filter(|url| url.scheme() == "https")?;
+        let host = url.host_str()?.to_ascii_lowercase();
+        if !self.token_hosts.is_empty() && !self.token_hosts.contains(&host) {
+            return None;
+        }
+        self.token.as_deref()
+    }
+}
+
+/// A repository ready to be analyzed. A repository fetched into a temporary directory
+/// is removed when this value is dropped.
+pub struct Repository {
+    root: PathBuf,
+    _temp_dir: Option<TempDir>,
+}
+
+impl Repository {
+    /// Fetch `repo` at `revision` (its default branch or working tree when `None`), with
+    /// the cache and credentials of the environment
+    pub fn open(repo: &str, revision: Option<&str>) -> io::Result<Self> {
+        Repository::fetch(repo, &Fetch::from_env(revision))
+    }
+
+    /// Fetch `repo` if it is a Git URL, otherwise use it as a local checkout
+    pub fn fetch(repo: &str, fetch: &Fetch) -> io::Result<Self> {
+        if let Some(revision) = &fetch.revision {
+            check_revision(revision)?;
+        }
+        if is_git_url(repo) {
+            let Some(cache_dir) = &fetch.cache_dir else {
+                let temp_dir = TempDir::new()?;
+                fetch_remote(repo, temp_dir.path(), fetch)?;
+                return Ok(Repository { root: temp_dir.path().to_path_buf(), _temp_dir: Some(temp_dir) });
+            };
+            // The cache holds the objects; each run gets its own checkout of them, so runs
+            // at other revisions of the same URL cannot change the tree under it
+            fs::create_dir_all(cache_dir)?;
+            let key = cache_key(repo);
+            let lock = File::create(cache_dir.join(format!("{}.lock", key)))?;
+            lock.lock()?;
+            let cache = cache_dir.join(key);
+            fetch_remote(repo, &cache, fetch)?;
+            let commit = git(&cache, &["rev-parse", "HEAD"])?;
+            let (temp_dir, root) = checkout_revision(&cache, &commit)?;
+            return Ok(Repository { root, _temp_dir: Some(temp_dir) });
+        }
+
+        let root = PathBuf::from(repo);
+        if !root.is_dir() {
+            return Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("Repository path '{}' does not exist or is not a directory", root.display()),
+            ));
+        }
+        match &fetch.revision {
+            Some(revision) => {
+                let (temp_dir, root) = checkout_revision(&root, revision)?;
+                Ok(Repository { root, _temp_dir: Some(temp_dir) })
+            }
+            None => Ok(Repository { root, _temp_dir: None }),
+        }
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// `relative` inside the repository, which has to exist
+    pub fn subdir(&self, relative: &Path) -> io::Result<PathBuf> {
+        let path = self.root.join(relative);
+        if !path.exists() {
+            return Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("'{}' does not exist in the repository", relative.display()),
+            ));
+        }
+        Ok(path)
+    }
+}
+
+/// Check if a string names a remote repository rather than a local path
+fn is_git_url(s: &str) -> bool {
+    Url::parse(s).is_ok() || s.starts_with("git@")
+}
+
+/// Refuse a revision git would read as an option, such as `--upload-pack=<command>`.
+/// Revisions are also passed after `--end-of-options`; this gives a clearer error.
+fn check_revision(revision: &str) -> io::Result<()> {
+    if revision.is_empty() || revision.starts_with('-') {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("'{}' is not a branch, tag or commit", revision),
+        ));
+    }
+    Ok(())
+}
+
+/// Directory name of a URL in the cache: letters, digits, `.` and `-` as they are, and
+/// every other byte as `_` and its hex value, so no two URLs share a directory
+fn cache_key(url: &str) -> String {
+    let mut key = String::new();
+    for byte in url.bytes() {
+        if byte.is_ascii_alphanumeric() || byte == b'.' || byte == b'-' {
+            key.push(byte as char);
+        } else {
+            key.push_str(&format!("_{:02x}", byte));
+        }
+    }
+    key
+}
+
+/// Fetch `fetch.revision` of `url` into `dir`, a new or a cached repository, and check
+/// it out. Only that commit is fetched, unless the remote cannot resolve the revision
+/// (an abbreviated commit), which needs the whole history.
+fn fetch_remote(url: &str, dir: &Path, fetch: &Fetch) -> io::Result<()> {
+    if dir.join(".git").is_dir() {
+        eprintln!("Updating cached repository: {}", url);
+        // Never fetch another repository into this one's cache
+        if git(dir, &["config", "--get", "remote.origin.url"]).ok().as_deref() != Some(url) {
+            git(dir, &["remote", "set-url", "--end-of-options", "origin", url])?;
+        }
+    } else {
+        eprintln!("Cloning repository: {}", url);
+        fs::create_dir_all(dir)?;
+        git(dir, &["init", "--quiet"])?;
+        git(dir, &["remote", "add", "--end-of-options", "origin", url])?;
+    }
+
+    let revision = fetch.revision.as_deref().unwrap_or("HEAD");
+    check_revision(revision)?;
+    let remote = |args: &[&str]| run(remote_command(dir, url, fetch).args(args), args[0]);
+    if remote(&["fetch", "--quiet", "--depth", "1", "--end-of-options", "origin", revision]).is_ok() {
+        git(dir, &["checkout", "--quiet", "--force", "--detach", "FETCH_HEAD"])?;
+    } else {
+        let unshallow = dir.join(".git/shallow").exists();
+        let mut args = vec!["fetch", "--quiet", "--tags"];
+        if unshallow {
+            args.push("--unshallow");
+        }
+        args.extend(["--end-of-options", "origin"]);
+        remote(&args)?;
+        let commit = git(dir, &["rev-parse", "--verify", "--quiet", "--end-of-options", &format!("{}^{{commit}}", revision)])
+            .map_err(|_| io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("'{}' is not a branch, tag or commit of {}", revision, url),
+            ))?;
+        git(dir, &["checkout", "--quiet", "--force", "--detach", &commit])?;
+    }
+    Ok(())
+}
+
+/// git in `dir`, talking to `url`: never prompting for credentials, and sending the
+/// token to an allowed HTTPS remote through the environment rather than the command line
+fn remote_command(dir: &Path, url: &str, fetch: &Fetch) -> Command {
+    let mut command = Command::new("git");
+    command.current_dir(dir).env("GIT_TERMINAL_PROMPT", "0");
+    if let Some(token) = fetch.token_for(url) {
+        let credentials = base64(format!("x-access-token:{}", token).as_bytes());
+        command
+            .env("GIT_CONFIG_COUNT", "1")
+            .env("GIT_CONFIG_KEY_0", "http.extraHeader")
+            .env("GIT_CONFIG_VALUE_0", format!("Authorization: Basic {}", credentials));
+    }
+    command
+}
+
+fn base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut encoded = String::new();
+    for chunk in bytes.chunks(3) {
+        let bits = chunk.iter().enumerate().fold(0u32, |bits, (i, &byte)| bits | (byte as u32) << (16 - 8 * i));
+        for i in 0..4 {
+            if i <= chunk.len() {
+                encoded.push(ALPHABET[(bits >> (18 - 6 * i) & 0x3f) as usize] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+    encoded
+}
+
+/// A revision of a repository checked out into a temporary directory, leaving the
+/// repository's own working tree untouched. Returns the checkout and the directory
+/// within it that corresponds to `dir`.
+pub fn checkout_revision(dir: &Path, revision: &str) -> io::Result<(TempDir, PathBuf)> {
+    check_revision(revision)?;
+    let commit = git(dir, &["rev-parse", "--verify", "--end-of-options", &format!("{}^{{commit}}", revision)])?;
+    let top_level = git(dir, &["rev-parse", "--show-toplevel"])?;
+    let prefix = git(dir, &["rev-parse", "--show-prefix"])?;
+
+    let temp_dir = TempDir::new()?;
+    let checkout = temp_dir.path().to_string_lossy().into_owned();
+    git(dir, &["clone", "--quiet", "--shared", "--no-checkout", "--end-of-options", &top_level, &checkout])?;
+    git(temp_dir.path(), &["checkout", "--quiet", "--detach", &commit])?;
+
+    let checkout_dir = temp_dir.path().join(prefix);
+    Ok((temp_dir, checkout_dir))
+}
+
+/// A commit, and when it was made in seconds since the Unix epoch
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Commit {
+    pub id: String,
+    pub time: i64,
+}
+
+/// The last `count` commits of `revision` that touched `dir`, newest first
+pub fn commits(dir: &Path, revision: &str, count: usize) -> io::Result<Vec<Commit>> {
+    check_revision(revision)?;
+    let log = git(dir, &["log", &format!("--max-count={}", count), "--format=%H %ct", "--end-of-options", revision, "--", "."])?;
+    log.lines()
+        .map(|line| {
+            let (id, time) = line.split_once(' ').unwrap_or((line, ""));
+            let time = time.parse().map_err(|_| io::Error::other(format!("unexpected git log line: {}", line)))?;
+            Ok(Commit { id: id.to_string(), time })
+        })
+        .collect()
+}
+
+/// Run git in `dir` and return its trimmed stdout
+fn git(dir: &Path, args: &[&str]) -> io::Result<String> {
+    run(Command::new("git").args(args).current_dir(dir), args[0])
+}
+
+/// Run a git `subcommand` and return its trimmed stdout
+fn run(command: &mut Command, subcommand: &str) -> io::Result<String> {
+    let output = command.output()?;
+    if !output.status.success() {
+        return Err(io::Error::other(format!(
+            "git {} failed: {}",
+            subcommand,
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cache_keys() {
+        assert_eq!(cache_key("https://github.com/user/repo.git"), "https_3a_2f_2fgithub.com_2fuser_2frepo.git");
+        assert_eq!(cache_key("git@github.com:user/repo.git"), "git_40github.com_3auser_2frepo.git");
+        // No separators or parent directories survive
+        assert_eq!(cache_key("file:///../../etc"), "file_3a_2f_2f_2f.._2f.._2fetc");
+        assert_ne!(cache_key("https://a/b-c"), cache_key("https://a/b_c"));
+        // Each escape starts with `_`, which is escaped itself
+        assert_ne!(cache_key("https://gitlab.com/group/sub_repo.git"), cache_key("https://gitlab.com/group/sub/repo.git"));
+        assert_ne!(cache_key("https://a/b_2fc"), cache_key("https://a/b/c"));
+    }
+
+    #[test]
+    fn token_hosts() {
+        let sends_token = |fetch: &Fetch, url: &str| {
+            remote_command(Path::new("."), url, fetch).get_envs().any(|(key, _)| key == "GIT_CONFIG_VALUE_0")
+        };
+        let fetch = Fetch { token: Some("ghp_1".to_string()), ..Fetch::default() };
+        assert!(sends_token(&fetch, "https://github.com/user/repo.git"));
+        assert!(!sends_token(&fetch, "ssh://git@github.com/user/repo.git"));
+        assert!(!sends_token(&fetch, "http://github.com/user/repo.git"));
+        // Without a host list, URLs from others get no token at all
+        assert!(!sends_token(&fetch.clone().untrusted(), "https://github.com/user/repo.git"));
+
+        let limited = Fetch { token_hosts: vec!["github.com".to_string()], ..fetch };
+        assert!(sends_token(&limited, "https://GitHub.com/user/repo.git"));
+        assert!(!sends_token(&limited, "https://attacker.example/user/repo.git"));
+        assert!(!sends_token(&limited, "https://github.com.attacker.example/repo.git"));
+        assert!(!sends_token(&limited, "https://github.com@attacker.example/repo.git"));
+        assert!(sends_token(&limited.clone().untrusted(), "https://github.com/user/repo.git"));
+    }
+
+    #[test]
+    fn base64_padding() {
+        assert_eq!(base64(b""), "");
+        assert_eq!(base64(b"f"), "Zg==");
+        assert_eq!(base64(b"fo"), "Zm8=");
+        assert_eq!(base64(b"foo"), "Zm9v");
+        assert_eq!(base64(b"foobar"), "Zm9vYmFy");
+        assert_eq!(base64(b"x-access-token:ghp_1"), "eC1hY2Nlc3MtdG9rZW46Z2hwXzE=");
+        assert_eq!(base64(&[0xff, 0xfe, 0xfd]), "//79");
+    }
+
+    #[test]
+    fn revisions() {
+        assert!(check_revision("main").is_ok());
+        assert!(check_revision("v1.2.0").is_ok());
+        assert!(check_revision("HEAD~3").is_ok());
+        assert!(check_revision("").is_err());
+        assert!(check_revision("--upload-pack=sh").is_err());
+        assert!(check_revision("-c").is_err());
+    }
+}
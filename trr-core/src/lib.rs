@@ -0,0 +1,45 @@
+//! What the analyzers share: walking a tree for Rust files, fetching the repository to
+//! analyze, source locations, config files, and the report formats with SARIF output.
+//! With the `syn` feature, also the helpers of the analyzers that parse source with syn.
+
+#[cfg(feature = "syn")]
+pub mod anchor;
+pub mod config;
+pub mod git;
+pub mod output;
+pub mod sarif;
+#[cfg(feature = "syn")]
+pub mod source;
+pub mod walk;
+
+use std::fmt;
+use std::path::PathBuf;
+
+use serde::Serialize;
+
+/// A position in an analyzed file
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize)]
+pub struct Location {
+    pub file: PathBuf,
+    pub line: usize,
+    /// 1-based, when the analyzer knows it
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub column: Option<usize>,
+}
+
+impl Location {
+    pub fn new(file: PathBuf, line: usize) -> Self {
+        Location { file, line, column: None }
+    }
+}
+
+/// `file:line`, or `file:line:column`
+impl fmt::Display for Location {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.file.display(), self.line)?;
+        if let Some(column) = self.column {
+            write!(f, ":{}", column)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,137 @@
+//! The report formats every analyzer takes with `--format`, and the parts of them that
+//! do not depend on the analyzer: the versioned JSON document, CSV rows and Markdown
+//! table cells. Also the failure listings of `--fail-above` and the like.
+
+use std::fmt;
+use std::io::{self, Write};
+use std::process;
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+/// Version of the JSON documents and SARIF logs the tools print. It changes when a
+/// field is removed, renamed or changes meaning; new fields keep it.
+pub const SCHEMA_VERSION: u32 = 1;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+#[cfg_attr(feature = "clap", derive(clap::ValueEnum))]
+#[serde(rename_all = "lowercase")]
+pub enum Format {
+    /// The analyzer's own report, for reading in a terminal
+    Text,
+    /// One document, starting with `schema_version` and `tool`
+    Json,
+    /// One row per item the analyzer measures
+    Csv,
+    /// A report to paste into a review
+    #[cfg_attr(feature = "clap", clap(alias = "md"))]
+    #[serde(alias = "md")]
+    Markdown,
+    /// A SARIF 2.1.0 log, for code-scanning UIs
+    Sarif,
+}
+
+impl Format {
+    /// For error messages
+    pub const EXPECTED: &'static str = "text, json, csv, markdown or sarif";
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Format::Text => "text",
+            Format::Json => "json",
+            Format::Csv => "csv",
+            Format::Markdown => "markdown",
+            Format::Sarif => "sarif",
+        }
+    }
+}
+
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name {
+            "text" => Ok(Format::Text),
+            "json" => Ok(Format::Json),
+            "csv" => Ok(Format::Csv),
+            "markdown" | "md" => Ok(Format::Markdown),
+            "sarif" => Ok(Format::Sarif),
+            _ => Err(format!("Unknown format: {} (expected {})", name, Format::EXPECTED)),
+        }
+    }
+}
+
+impl fmt::Display for Format {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.pad(self.name())
+    }
+}
+
+/// A JSON report of `tool`, with the schema version and the tool's name ahead of the
+/// report's own fields
+#[derive(Serialize)]
+pub struct Document<'a, T> {
+    pub schema_version: u32,
+    pub tool: &'a str,
+    #[serde(flatten)]
+    pub report: T,
+}
+
+impl<'a, T: Serialize> Document<'a, T> {
+    pub fn new(tool: &'a str, report: T) -> Self {
+        Document { schema_version: SCHEMA_VERSION, tool, report }
+    }
+}
+
+/// Write `report`, which must serialize as a JSON object, as the pretty-printed
+/// document of `tool`
+pub fn write_json(out: &mut dyn Write, tool: &str, report: impl Serialize) -> io::Result<()> {
+    serde_json::to_writer_pretty(&mut *out, &Document::new(tool, report))?;
+    writeln!(out)
+}
+
+/// Quote a CSV field if it holds a separator, a quote or a line break (RFC 4180)
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+pub fn write_csv_row(out: &mut dyn Write, fields: &[&str]) -> io::Result<()> {
+    let fields: Vec<String> = fields.iter().map(|value| csv_field(value)).collect();
+    writeln!(out, "{}", fields.join(","))
+}
+
+/// Text for a Markdown table cell, with the pipes that would end it escaped
+pub fn markdown_cell(text: &str) -> String {
+    text.replace('|', "\\|")
+}
+
+/// `text` as inline code in a Markdown table cell
+pub fn markdown_code(text: &str) -> String {
+    format!("`{}`", markdown_cell(text))
+}
+
+/// List the items failing a check on stderr, under `header`
+pub fn list_failures<T: fmt::Display>(header: &str, items: impl IntoIterator<Item = T>) {
+    eprintln!("\n{}:", header);
+    for item in items {
+        eprintln!("  {}", item);
+    }
+}
+
+/// List the items failing a check on stderr, under `header`, and exit with status 1
+pub fn fail<T: fmt::Display>(header: &str, items: impl IntoIterator<Item = T>) -> ! {
+    list_failures(header, items);
+    process::exit(1)
+}
+
+/// `--fail-above <limit>`: when `count` is over `limit`, list `items` under
+/// "<count> <what>, more than <limit>" and exit with status 1
+pub fn enforce_limit<T: fmt::Display>(limit: Option<usize>, count: usize, what: &str, items: impl IntoIterator<Item = T>) {
+    if let Some(limit) = limit.filter(|&limit| count > limit) {
+        fail(&format!("{} {}, more than {}", count, what, limit), items);
+    }
+}
@@ -0,0 +1,55 @@
+//! Building blocks of the SARIF 2.1.0 logs the tools write for code-scanning UIs.
+
+use std::io::{self, Write};
+use std::path::Path;
+
+use serde_json::{json, Value};
+
+use crate::output::SCHEMA_VERSION;
+use crate::Location;
+
+/// Path of an analyzed file as an artifact URI, relative to `base` (the working
+/// directory) when it is inside it
+pub fn artifact_uri(file: &Path, base: &Path) -> String {
+    let relative = file.strip_prefix(base).unwrap_or(file);
+    relative.iter()
+        .map(|component| component.to_string_lossy())
+        .filter(|component| component != ".")
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// A `physicalLocation` at `location`
+pub fn location(location: &Location, base: &Path) -> Value {
+    let mut region = json!({ "startLine": location.line });
+    if let Some(column) = location.column {
+        region["startColumn"] = json!(column);
+    }
+    json!({
+        "physicalLocation": {
+            "artifactLocation": { "uri": artifact_uri(&location.file, base) },
+            "region": region,
+        }
+    })
+}
+
+/// A log of one run of `tool`, whose results refer to `rules` by id and index
+pub fn write_log(out: impl Write, tool: &str, version: &str, rules: Vec<Value>, results: Vec<Value>) -> io::Result<()> {
+    let log = json!({
+        "$schema": "https://json.schemastore.org/sarif-2.1.0.json",
+        "version": "2.1.0",
+        "runs": [{
+            "tool": {
+                "driver": {
+                    "name": tool,
+                    "version": version,
+                    "rules": rules,
+                }
+            },
+            "results": results,
+            "properties": { "schemaVersion": SCHEMA_VERSION },
+        }],
+    });
+    serde_json::to_writer_pretty(out, &log)?;
+    Ok(())
+}
@@ -0,0 +1,89 @@
+//! What the analyzers parsing Rust source with syn share: reading and parsing the
+//! files to analyze, leaving out test-only items and locating spans.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use proc_macro2::{LineColumn, Span};
+use syn::Attribute;
+
+use crate::Location;
+
+/// Read and parse each of `files`, handing every parsed file to `add` with its path and
+/// source. Files that cannot be read or parsed go to `errors`, unless already there.
+pub fn parse_files(files: Vec<PathBuf>, errors: &mut Vec<(PathBuf, String)>, mut add: impl FnMut(PathBuf, &str, syn::File)) {
+    for file in files {
+        let parsed = fs::read_to_string(&file)
+            .map_err(|e| e.to_string())
+            .and_then(|source| syn::parse_file(&source).map(|syntax| (source, syntax)).map_err(|e| e.to_string()));
+        match parsed {
+            Ok((source, syntax)) => add(file, &source, syntax),
+            Err(e) => {
+                if !errors.iter().any(|(path, _)| *path == file) {
+                    errors.push((file, e));
+                }
+            }
+        }
+    }
+}
+
+/// Whether the attributes make an item test-only: `#[cfg(test)]` or `#[test]`
+pub fn is_cfg_test(attrs: &[Attribute]) -> bool {
+    attrs.iter().any(|attr| {
+        attr.path().is_ident("test")
+            || (attr.path().is_ident("cfg") && attr.parse_args::<syn::Ident>().is_ok_and(|ident| ident == "test"))
+    })
+}
+
+/// Where `span` starts in `path`. Needs proc-macro2's `span-locations`, which this
+/// feature enables.
+pub fn location(path: &Path, span: Span) -> Location {
+    location_at(path, span.start())
+}
+
+/// `start` in `path`, with the 0-based column of proc-macro2 made 1-based
+pub fn location_at(path: &Path, start: LineColumn) -> Location {
+    Location { file: path.to_path_buf(), line: start.line, column: Some(start.column + 1) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_items() {
+        let file: syn::File = syn::parse_str("#[cfg(test)] mod tests {} #[test] fn t() {} #[cfg(feature = \"x\")] fn f() {} fn g() {}").unwrap();
+        let test_only: Vec<bool> = file.items.iter()
+            .map(|item| match item {
+                syn::Item::Mod(item) => is_cfg_test(&item.attrs),
+                syn::Item::Fn(item) => is_cfg_test(&item.attrs),
+                _ => unreachable!(),
+            })
+            .collect();
+        assert_eq!(test_only, [true, true, false, false]);
+    }
+
+    #[test]
+    fn parse_errors() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let (good, bad, missing) = (dir.path().join("good.rs"), dir.path().join("bad.rs"), dir.path().join("missing.rs"));
+        fs::write(&good, "struct S;").unwrap();
+        fs::write(&bad, "struct").unwrap();
+        let mut errors = vec![(bad.clone(), "reported before".to_string())];
+        let mut parsed = Vec::new();
+        parse_files(vec![good.clone(), bad.clone(), missing.clone()], &mut errors, |path, source, syntax| {
+            parsed.push((path, source.to_string(), syntax.items.len()));
+        });
+        assert_eq!(parsed, [(good, "struct S;".to_string(), 1)]);
+        let failed: Vec<&PathBuf> = errors.iter().map(|(path, _)| path).collect();
+        assert_eq!(failed, [&bad, &missing]);
+    }
+
+    #[test]
+    fn span_locations() {
+        let file: syn::File = syn::parse_str("fn f() {}\n  struct S;").unwrap();
+        let syn::Item::Struct(item) = &file.items[1] else { unreachable!() };
+        let location = location(Path::new("src/lib.rs"), item.ident.span());
+        assert_eq!(location.to_string(), "src/lib.rs:2:10");
+    }
+}
@@ -0,0 +1,285 @@
+//! The Rust files to analyze under a directory, optionally leaving out `--exclude` globs
+//! and whatever the `.gitignore` files of the repository ignore (`target/`, generated code).
+
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use glob::{MatchOptions, Pattern};
+
+/// The `.rs` files under `path` sorted by path, or `path` itself if it is a Rust
+/// file. Files and directories `skip` accepts are left out, and subdirectories are
+/// only entered when `recursive`. Symbolic links to directories are not followed, so a
+/// link back up the tree cannot loop; links to files are read.
+pub fn rust_files(path: &Path, recursive: bool, skip: &mut dyn FnMut(&Path) -> bool) -> io::Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    if path.is_dir() {
+        visit(path, recursive, skip, &mut files)?;
+    } else if is_rust_file(path) {
+        files.push(path.to_path_buf());
+    }
+    Ok(files)
+}
+
+fn visit(dir: &Path, recursive: bool, skip: &mut dyn FnMut(&Path) -> bool, files: &mut Vec<PathBuf>) -> io::Result<()> {
+    // Sorted, so the files come in the same order on every file system
+    let mut entries: Vec<(PathBuf, fs::FileType)> = fs::read_dir(dir)?
+        .map(|entry| entry.and_then(|entry| Ok((entry.path(), entry.file_type()?))))
+        .collect::<io::Result<_>>()?;
+    entries.sort_by(|(a, _), (b, _)| a.cmp(b));
+    for (path, file_type) in entries {
+        if skip(&path) {
+            continue;
+        }
+        if file_type.is_dir() {
+            if recursive {
+                visit(&path, recursive, skip, files)?;
+            }
+        } else if is_rust_file(&path) && path.is_file() {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+fn is_rust_file(path: &Path) -> bool {
+    path.extension().is_some_and(|ext| ext == "rs")
+}
+
+/// The `.rs` files under `path` (or `path` itself) that make up the program: every one
+/// outside `target/` and test code
+pub fn program_files(path: &Path) -> io::Result<Vec<PathBuf>> {
+    rust_files(path, true, &mut |path| is_test_path(path) || is_target_dir(path))
+}
+
+/// Cargo's build output directory
+pub fn is_target_dir(path: &Path) -> bool {
+    path.file_name().is_some_and(|name| name == "target")
+}
+
+/// Directories and files that hold only tests or benchmarks by convention
+pub fn is_test_path(path: &Path) -> bool {
+    let name = path.file_name().and_then(|name| name.to_str());
+    if path.is_dir() {
+        matches!(name, Some("tests" | "benches"))
+    } else {
+        matches!(name, Some("tests.rs" | "test.rs"))
+    }
+}
+
+/// One line of a `.gitignore`
+struct Rule {
+    pattern: Pattern,
+    negated: bool,
+    dir_only: bool,
+    /// Contains a `/` other than a trailing one, so it matches the path relative to the
+    /// `.gitignore` rather than any file name
+    anchored: bool,
+}
+
+/// The rules of one `.gitignore`, relative to its directory
+struct Gitignore {
+    dir: PathBuf,
+    rules: Vec<Rule>,
+}
+
+impl Gitignore {
+    fn load(dir: &Path) -> Option<Gitignore> {
+        let content = fs::read_to_string(dir.join(".gitignore")).ok()?;
+        Some(Gitignore::parse(dir, &content))
+    }
+
+    fn parse(dir: &Path, content: &str) -> Gitignore {
+        let rules = content.lines()
+            .map(str::trim_end)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .filter_map(|line| {
+                let (negated, line) = match line.strip_prefix('!') {
+                    Some(rest) => (true, rest),
+                    None => (false, line),
+                };
+                let (dir_only, line) = match line.strip_suffix('/') {
+                    Some(rest) => (true, rest),
+                    None => (false, line),
+                };
+                let anchored = line.contains('/');
+                let pattern = Pattern::new(line.trim_start_matches('/')).ok()?;
+                Some(Rule { pattern, negated, dir_only, anchored })
+            })
+            .collect();
+        Gitignore { dir: dir.to_path_buf(), rules }
+    }
+
+    /// `Some(true)` if the last matching rule ignores `path`, `Some(false)` if it
+    /// re-includes it, `None` if no rule matches
+    fn decide(&self, path: &Path, is_dir: bool) -> Option<bool> {
+        let relative = path.strip_prefix(&self.dir).ok()?;
+        let options = MatchOptions { require_literal_separator: true, ..MatchOptions::new() };
+        self.rules.iter().rev()
+            .find(|rule| {
+                (is_dir || !rule.dir_only)
+                    && if rule.anchored {
+                        rule.pattern.matches_path_with(relative, options)
+                    } else {
+                        path.file_name().is_some_and(|name| rule.pattern.matches(&name.to_string_lossy()))
+                    }
+            })
+            .map(|rule| !rule.negated)
+    }
+}
+
+/// Which files and directories to leave out of the walk
+pub struct SourceFilter {
+    pub excludes: Vec<Pattern>,
+    pub gitignore: bool,
+}
+
+impl SourceFilter {
+    /// Every `.rs` file under `dir` that is not excluded, sorted by path
+    pub fn rust_files(&self, dir: &Path) -> io::Result<Vec<PathBuf>> {
+        // `.gitignore` files of `dir` and of its ancestors inside the repository, by directory
+        let mut gitignores: HashMap<PathBuf, Option<Gitignore>> = HashMap::new();
+        let repository_root = dir.ancestors().find(|ancestor| ancestor.join(".git").exists());
+        if self.gitignore {
+            if let Some(root) = repository_root {
+                for ancestor in dir.ancestors().skip(1).take_while(|ancestor| ancestor.starts_with(root)) {
+                    gitignores.insert(ancestor.to_path_buf(), Gitignore::load(ancestor));
+                }
+            }
+        }
+
+        rust_files(dir, true, &mut |path| self.is_excluded(dir, path, &mut gitignores))
+    }
+
+    fn is_excluded(&self, dir: &Path, path: &Path, gitignores: &mut HashMap<PathBuf, Option<Gitignore>>) -> bool {
+        if path.file_name().is_some_and(|name| name == ".git") {
+            return true;
+        }
+        let relative = path.strip_prefix(dir).unwrap_or(path);
+        let excluded = self.excludes.iter().any(|pattern| {
+            pattern.matches_path(relative)
+                || relative.iter().any(|component| pattern.matches(&component.to_string_lossy()))
+        });
+        if excluded || !self.gitignore {
+            return excluded;
+        }
+
+        // Deeper `.gitignore` files take precedence over the ones further up
+        let is_dir = path.is_dir();
+        let mut ignored = false;
+        let mut ancestors: Vec<&Path> = path.ancestors().skip(1).collect();
+        ancestors.reverse();
+        for ancestor in ancestors {
+            let gitignore = gitignores.entry(ancestor.to_path_buf())
+                .or_insert_with(|| if ancestor.starts_with(dir) { Gitignore::load(ancestor) } else { None });
+            if let Some(decision) = gitignore.as_ref().and_then(|gitignore| gitignore.decide(path, is_dir)) {
+                ignored = decision;
+            }
+        }
+        ignored
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn decide(gitignore: &str, path: &str, is_dir: bool) -> Option<bool> {
+        Gitignore::parse(Path::new("/repo"), gitignore).decide(&Path::new("/repo").join(path), is_dir)
+    }
+
+    #[test]
+    fn negation() {
+        let gitignore = "# generated\n*.rs\n\n!keep.rs\n";
+        assert_eq!(decide(gitignore, "src/lib.rs", false), Some(true));
+        assert_eq!(decide(gitignore, "src/keep.rs", false), Some(false));
+        assert_eq!(decide(gitignore, "README.md", false), None);
+        // The last matching rule wins
+        assert_eq!(decide("!keep.rs\n*.rs", "keep.rs", false), Some(true));
+    }
+
+    #[test]
+    fn anchored_and_unanchored() {
+        let gitignore = "/target\ngen.rs\ndocs/gen.rs\n";
+        assert_eq!(decide(gitignore, "target", true), Some(true));
+        assert_eq!(decide(gitignore, "programs/target", true), None);
+        assert_eq!(decide(gitignore, "programs/vault/gen.rs", false), Some(true));
+        assert_eq!(decide(gitignore, "docs/gen.rs", false), Some(true));
+        assert_eq!(decide("docs/api.rs", "programs/docs/api.rs", false), None);
+    }
+
+    #[test]
+    fn double_star() {
+        let gitignore = "**/generated/*.rs\nsrc/**/mock.rs\n";
+        assert_eq!(decide(gitignore, "generated/idl.rs", false), Some(true));
+        assert_eq!(decide(gitignore, "programs/vault/generated/idl.rs", false), Some(true));
+        assert_eq!(decide(gitignore, "programs/vault/generated/nested/idl.rs", false), None);
+        assert_eq!(decide(gitignore, "src/mock.rs", false), Some(true));
+        assert_eq!(decide(gitignore, "src/a/b/mock.rs", false), Some(true));
+        assert_eq!(decide(gitignore, "tests/src/mock.rs", false), None);
+    }
+
+    #[test]
+    fn trailing_slash() {
+        assert_eq!(decide("build/", "build", true), Some(true));
+        assert_eq!(decide("build/", "programs/build", true), Some(true));
+        assert_eq!(decide("build/", "build", false), None);
+        assert_eq!(decide("/out/", "out", true), Some(true));
+        assert_eq!(decide("/out/", "src/out", true), None);
+    }
+
+    #[test]
+    fn source_filter() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let root = dir.path();
+        for path in ["src/b.rs", "src/a.rs", "src/gen/x.rs", "src/gen/keep.rs", "target/debug/build.rs", "vendor/dep.rs", "notes.txt"] {
+            fs::create_dir_all(root.join(path).parent().unwrap()).unwrap();
+            fs::write(root.join(path), "").unwrap();
+        }
+        fs::create_dir(root.join(".git")).unwrap();
+        fs::write(root.join(".gitignore"), "target/\n").unwrap();
+        // A deeper .gitignore takes precedence
+        fs::write(root.join("src/gen/.gitignore"), "*.rs\n!keep.rs\n").unwrap();
+
+        let relative = |filter: &SourceFilter| -> Vec<String> {
+            filter.rust_files(root).unwrap().iter()
+                .map(|path| path.strip_prefix(root).unwrap().to_string_lossy().into_owned())
+                .collect()
+        };
+        let excludes = vec![Pattern::new("vendor").unwrap()];
+        assert_eq!(relative(&SourceFilter { excludes, gitignore: true }), ["src/a.rs", "src/b.rs", "src/gen/keep.rs"]);
+        assert_eq!(
+            relative(&SourceFilter { excludes: Vec::new(), gitignore: false }),
+            ["src/a.rs", "src/b.rs", "src/gen/keep.rs", "src/gen/x.rs", "target/debug/build.rs", "vendor/dep.rs"],
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn symlinked_directories_are_not_entered() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let root = dir.path();
+        fs::create_dir_all(root.join("src")).unwrap();
+        fs::write(root.join("src/lib.rs"), "").unwrap();
+        fs::write(root.join("shared.rs"), "").unwrap();
+        std::os::unix::fs::symlink("..", root.join("src/loop")).unwrap();
+        std::os::unix::fs::symlink("../shared.rs", root.join("src/shared.rs")).unwrap();
+        std::os::unix::fs::symlink("missing", root.join("src/dangling.rs")).unwrap();
+
+        let files = rust_files(&root.join("src"), true, &mut |_| false).unwrap();
+        assert_eq!(files, [root.join("src/lib.rs"), root.join("src/shared.rs")]);
+        let filter = SourceFilter { excludes: Vec::new(), gitignore: true };
+        assert_eq!(filter.rust_files(root).unwrap(), [root.join("shared.rs"), root.join("src/lib.rs"), root.join("src/shared.rs")]);
+    }
+
+    #[test]
+    fn test_paths() {
+        let dir = tempfile::TempDir::new().unwrap();
+        fs::create_dir(dir.path().join("tests")).unwrap();
+        assert!(is_test_path(&dir.path().join("tests")));
+        assert!(is_test_path(Path::new("src/tests.rs")));
+        assert!(!is_test_path(Path::new("src/lib.rs")));
+        assert!(!is_test_path(&dir.path().join("src/tests")));
+    }
+}
@@ -0,0 +1,122 @@
+// Revisions given to --rev reach git's command line: one that looks like an option
+// (`--upload-pack=<command>`, `--output=<file>`) must be refused rather than run. Runs
+// sharing a TRR_CACHE_DIR at other revisions each see their own tree, fetched from the
+// URL they asked for.
+
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+use std::thread;
+
+use tempfile::TempDir;
+use trr_core::git::{self, Fetch, Repository};
+
+fn git(dir: &Path, args: &[&str]) {
+    let status = Command::new("git")
+        .args(["-c", "user.name=trr", "-c", "user.email=trr@example.com"])
+        .args(args)
+        .current_dir(dir)
+        .status()
+        .expect("failed to run git");
+    assert!(status.success(), "git {:?} failed", args);
+}
+
+/// A repository with one commit touching `src/lib.rs`
+fn repository() -> TempDir {
+    let dir = TempDir::new().unwrap();
+    git(dir.path(), &["init", "--quiet"]);
+    fs::create_dir(dir.path().join("src")).unwrap();
+    fs::write(dir.path().join("src/lib.rs"), "pub struct Vault;\n").unwrap();
+    git(dir.path(), &["add", "."]);
+    git(dir.path(), &["commit", "--quiet", "-m", "Add the vault"]);
+    dir
+}
+
+#[test]
+fn option_revisions_are_refused() {
+    let repo = repository();
+    let marker = repo.path().join("pwned");
+    let injections = [
+        format!("--upload-pack=touch {}", marker.display()),
+        format!("--output={}", marker.display()),
+        "-h".to_string(),
+    ];
+    let url = format!("file://{}", repo.path().display());
+    for revision in &injections {
+        let fetch = Fetch { revision: Some(revision.clone()), ..Fetch::default() };
+        let error = Repository::fetch(&url, &fetch).err().expect("fetched an option as a revision");
+        assert!(error.to_string().contains("is not a branch, tag or commit"), "{}", error);
+        assert!(Repository::fetch(&repo.path().to_string_lossy(), &fetch).is_err());
+        assert!(git::checkout_revision(repo.path(), revision).is_err());
+        assert!(git::commits(repo.path(), revision, 1).is_err());
+    }
+    assert!(!marker.exists(), "git ran the injected option");
+}
+
+#[test]
+fn revisions_still_resolve() {
+    let repo = repository();
+    let commits = git::commits(repo.path(), "HEAD", 5).unwrap();
+    assert_eq!(commits.len(), 1);
+    assert_eq!(commits[0].id.len(), 40);
+
+    let (_checkout, dir) = git::checkout_revision(&repo.path().join("src"), "HEAD").unwrap();
+    assert!(dir.join("lib.rs").is_file());
+
+    let url = format!("file://{}", repo.path().display());
+    let fetch = Fetch { revision: Some(commits[0].id.clone()), ..Fetch::default() };
+    let fetched = Repository::fetch(&url, &fetch).unwrap();
+    assert!(fetched.subdir(Path::new("src/lib.rs")).is_ok());
+}
+
+#[test]
+fn cached_revisions_do_not_race() {
+    let repo = repository();
+    git(repo.path(), &["tag", "v1"]);
+    fs::write(repo.path().join("src/lib.rs"), "pub struct Vault2;\n").unwrap();
+    git(repo.path(), &["commit", "--quiet", "-am", "Rename the vault"]);
+    git(repo.path(), &["tag", "v2"]);
+
+    let cache = TempDir::new().unwrap();
+    let url = format!("file://{}", repo.path().display());
+    let fetches: Vec<_> = (0..8)
+        .map(|i| {
+            let (url, cache_dir) = (url.clone(), cache.path().to_path_buf());
+            thread::spawn(move || {
+                let revision = if i % 2 == 0 { "v1" } else { "v2" };
+                let fetch = Fetch { revision: Some(revision.to_string()), cache_dir: Some(cache_dir), ..Fetch::default() };
+                let fetched = Repository::fetch(&url, &fetch).unwrap();
+                // Another thread checking out the other tag must not change this tree
+                thread::sleep(std::time::Duration::from_millis(50));
+                (revision, fs::read_to_string(fetched.root().join("src/lib.rs")).unwrap())
+            })
+        })
+        .collect();
+    for fetch in fetches {
+        let (revision, source) = fetch.join().unwrap();
+        let expected = if revision == "v1" { "pub struct Vault;\n" } else { "pub struct Vault2;\n" };
+        assert_eq!(source, expected, "{}", revision);
+    }
+}
+
+#[test]
+fn cache_refetches_from_its_own_url() {
+    let repo = repository();
+    let other = repository();
+    fs::write(other.path().join("src/lib.rs"), "pub struct Other;\n").unwrap();
+    git(other.path(), &["commit", "--quiet", "-am", "Replace the vault"]);
+
+    let cache = TempDir::new().unwrap();
+    let url = format!("file://{}", repo.path().display());
+    let fetch = Fetch { cache_dir: Some(cache.path().to_path_buf()), ..Fetch::default() };
+    drop(Repository::fetch(&url, &fetch).unwrap());
+
+    // A cached repository whose origin points elsewhere is fetched from the URL asked for
+    let cached = fs::read_dir(cache.path()).unwrap()
+        .map(|entry| entry.unwrap().path())
+        .find(|path| path.is_dir())
+        .unwrap();
+    git(&cached, &["remote", "set-url", "origin", &format!("file://{}", other.path().display())]);
+    let fetched = Repository::fetch(&url, &fetch).unwrap();
+    assert_eq!(fs::read_to_string(fetched.root().join("src/lib.rs")).unwrap(), "pub struct Vault;\n");
+}
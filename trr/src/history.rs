@@ -0,0 +1,444 @@
+//! `trr history`: the metrics of every recorded run, kept in a SQLite database, and
+//! their trend over the runs of one target.
+//!
+//! The database is driven through the `sqlite3` command-line shell, as repositories are
+//! driven through `git`, so building trr needs no SQLite library. A run is keyed by the
+//! target: the repository as given to `--repo` (a local one by its absolute path) and
+//! the directory within it, or the absolute path of a directory analyzed on its own.
+//! Runs keep the commit they analyzed, and trends follow commit times, so commits
+//! recorded out of order still line up; a commit recorded twice counts once, with its
+//! latest run.
+
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::Serialize;
+use serde_json::{json, Value};
+use trr_core::git::Commit;
+use trr_core::output::SCHEMA_VERSION;
+
+use crate::report::{self, Metric};
+
+/// The database used when none is given
+pub const DEFAULT_DATABASE: &str = "trr-history.db";
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS runs (
+    id INTEGER PRIMARY KEY,
+    repo TEXT,
+    target TEXT NOT NULL,
+    revision TEXT,
+    committed_at INTEGER,
+    recorded_at INTEGER NOT NULL,
+    passed INTEGER NOT NULL
+);
+CREATE TABLE IF NOT EXISTS metrics (
+    run_id INTEGER NOT NULL REFERENCES runs (id),
+    tool TEXT NOT NULL,
+    name TEXT NOT NULL,
+    value INTEGER,
+    threshold INTEGER,
+    status TEXT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS runs_by_target ON runs (target, repo);
+CREATE INDEX IF NOT EXISTS metrics_by_run ON metrics (run_id);
+";
+
+/// What a run analyzed
+pub struct Subject {
+    pub repo: Option<String>,
+    pub target: String,
+}
+
+impl Subject {
+    /// `dir`, relative to `repo` when there is one, as runs are recorded
+    pub fn new(dir: &Path, repo: Option<&str>) -> Subject {
+        let absolute = |path: &Path| path.canonicalize().unwrap_or_else(|_| path.to_path_buf()).display().to_string();
+        match repo {
+            Some(repo) if Path::new(repo).exists() => Subject { repo: Some(absolute(Path::new(repo))), target: dir.display().to_string() },
+            Some(repo) => Subject { repo: Some(repo.to_string()), target: dir.display().to_string() },
+            None => Subject { repo: None, target: absolute(dir) },
+        }
+    }
+
+    /// The SQL condition selecting its runs
+    fn condition(&self) -> String {
+        let repo = self.repo.as_deref().map_or_else(|| "IS NULL".to_string(), |repo| format!("= {}", quote(repo)));
+        format!("target = {} AND repo {}", quote(&self.target), repo)
+    }
+}
+
+/// A recorded run, as trends list it
+#[derive(Debug, Serialize)]
+pub struct Run {
+    pub id: i64,
+    pub revision: Option<String>,
+    /// Seconds since the Unix epoch
+    pub committed_at: Option<i64>,
+    pub recorded_at: i64,
+    pub passed: bool,
+}
+
+/// The values of one metric, one per run of the trend
+#[derive(Debug, Serialize)]
+pub struct Series {
+    pub tool: String,
+    pub name: String,
+    pub values: Vec<Option<u64>>,
+}
+
+impl Series {
+    /// The last value less the first, when both are known
+    pub fn change(&self) -> Option<i64> {
+        let first = self.values.first().copied().flatten()?;
+        let last = self.values.last().copied().flatten()?;
+        Some(last as i64 - first as i64)
+    }
+}
+
+/// The metrics to show: those named, those of the tools given, or by default those
+/// `report` checks against thresholds
+pub struct Selection<'a> {
+    pub metrics: &'a [String],
+    pub tools: &'a [String],
+}
+
+impl Selection<'_> {
+    fn selects(&self, tool: &str, name: &str) -> bool {
+        if self.metrics.is_empty() && self.tools.is_empty() {
+            return report::is_checked(tool, name);
+        }
+        self.metrics.iter().any(|metric| metric.eq_ignore_ascii_case(name))
+            || self.tools.iter().any(|selected| selected.eq_ignore_ascii_case(tool))
+    }
+}
+
+/// The runs of a target, oldest first, and the selected metrics over them
+#[derive(Debug, Serialize)]
+pub struct Trend {
+    pub runs: Vec<Run>,
+    pub series: Vec<Series>,
+}
+
+/// A SQL string literal. Quotes are doubled and line breaks kept as they are; NUL is
+/// dropped, since SQLite text ends at one and the shell reads its input as text.
+fn quote(value: &str) -> String {
+    format!("'{}'", value.replace('\0', "").replace('\'', "''"))
+}
+
+fn or_null<T: ToString>(value: Option<T>) -> String {
+    value.map_or_else(|| "NULL".to_string(), |value| value.to_string())
+}
+
+pub struct History {
+    path: PathBuf,
+}
+
+impl History {
+    /// The database at `path`, created if it does not exist
+    pub fn open(path: &Path) -> io::Result<History> {
+        let history = History { path: path.to_path_buf() };
+        history.execute(SCHEMA)?;
+        Ok(history)
+    }
+
+    /// Run `sql` and return the rows of its last statement
+    fn execute(&self, sql: &str) -> io::Result<Vec<Value>> {
+        let mut child = Command::new("sqlite3")
+            .arg("-batch")
+            .arg("-bail")
+            .arg("-json")
+            .arg(&self.path)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|e| io::Error::new(e.kind(), format!("could not run sqlite3 ({}); install the SQLite command-line shell", e)))?;
+        child.stdin.take().expect("piped stdin").write_all(sql.as_bytes())?;
+        let output = child.wait_with_output()?;
+        if !output.status.success() {
+            return Err(io::Error::other(format!(
+                "sqlite3 failed on {}: {}",
+                self.path.display(),
+                String::from_utf8_lossy(&output.stderr).trim()
+            )));
+        }
+        parse_rows(&String::from_utf8_lossy(&output.stdout))
+    }
+
+    /// Record the metrics of a run of `subject` at `commit`, returning the run's id
+    pub fn record(&self, subject: &Subject, commit: Option<&Commit>, passed: bool, metrics: &[Metric]) -> io::Result<i64> {
+        let recorded_at = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |elapsed| elapsed.as_secs());
+        let mut sql = format!(
+            "BEGIN;\nINSERT INTO runs (repo, target, revision, committed_at, recorded_at, passed) VALUES ({}, {}, {}, {}, {}, {});\n",
+            or_null(subject.repo.as_deref().map(quote)),
+            quote(&subject.target),
+            or_null(commit.map(|commit| quote(&commit.id))),
+            or_null(commit.map(|commit| commit.time)),
+            recorded_at,
+            passed as u8,
+        );
+        sql.push_str("CREATE TEMP TABLE recorded AS SELECT last_insert_rowid() AS id;\n");
+        for metric in metrics {
+            let status = serde_json::to_value(metric.status)?;
+            sql.push_str(&format!(
+                "INSERT INTO metrics (run_id, tool, name, value, threshold, status) SELECT id, {}, {}, {}, {}, {} FROM recorded;\n",
+                quote(metric.tool),
+                quote(metric.name),
+                or_null(metric.value),
+                or_null(metric.threshold),
+                quote(status.as_str().unwrap_or_default()),
+            ));
+        }
+        sql.push_str("COMMIT;\nSELECT id FROM recorded;\n");
+        let rows = self.execute(&sql)?;
+        rows.first()
+            .and_then(|row| row["id"].as_i64())
+            .ok_or_else(|| io::Error::other("sqlite3 returned no run id"))
+    }
+
+    /// The last `last` runs of `subject`, one per commit, with the selected metrics
+    pub fn trend(&self, subject: &Subject, selection: &Selection, last: usize) -> io::Result<Trend> {
+        let rows = self.execute(&format!(
+            "SELECT id, revision, committed_at, recorded_at, passed FROM runs WHERE {} \
+             ORDER BY coalesce(committed_at, recorded_at), recorded_at, id;",
+            subject.condition()
+        ))?;
+        let runs = runs(&rows, last);
+        if runs.is_empty() {
+            return Ok(Trend { runs, series: Vec::new() });
+        }
+
+        let ids: Vec<String> = runs.iter().map(|run| run.id.to_string()).collect();
+        let rows = self.execute(&format!(
+            "SELECT run_id, tool, name, value FROM metrics WHERE run_id IN ({}) ORDER BY rowid;",
+            ids.join(", ")
+        ))?;
+        let series = series(&runs, &rows, selection);
+        Ok(Trend { runs, series })
+    }
+}
+
+/// The rows of the JSON array `sqlite3 -json` prints, which is nothing at all when a
+/// statement returns no rows
+fn parse_rows(stdout: &str) -> io::Result<Vec<Value>> {
+    if stdout.trim().is_empty() {
+        return Ok(Vec::new());
+    }
+    match serde_json::from_str(stdout) {
+        Ok(Value::Array(rows)) => Ok(rows),
+        _ => Err(io::Error::other(format!("unexpected sqlite3 output: {}", stdout.trim()))),
+    }
+}
+
+/// The last `last` of the run `rows`, which come oldest first, keeping the latest run
+/// of each commit
+fn runs(rows: &[Value], last: usize) -> Vec<Run> {
+    let mut runs: Vec<Run> = Vec::new();
+    for row in rows {
+        let run = Run {
+            id: row["id"].as_i64().unwrap_or_default(),
+            revision: row["revision"].as_str().map(str::to_string),
+            committed_at: row["committed_at"].as_i64(),
+            recorded_at: row["recorded_at"].as_i64().unwrap_or_default(),
+            passed: row["passed"].as_i64() == Some(1),
+        };
+        runs.retain(|known| run.revision.is_none() || known.revision != run.revision);
+        runs.push(run);
+    }
+    runs.drain(..runs.len().saturating_sub(last));
+    runs
+}
+
+/// The selected metrics of the metric `rows`, one value per run, in the order the
+/// metrics were first recorded
+fn series(runs: &[Run], rows: &[Value], selection: &Selection) -> Vec<Series> {
+    let mut series: Vec<Series> = Vec::new();
+    for row in rows {
+        let (Some(tool), Some(name)) = (row["tool"].as_str(), row["name"].as_str()) else { continue };
+        if !selection.selects(tool, name) {
+            continue;
+        }
+        let Some(index) = runs.iter().position(|run| row["run_id"].as_i64() == Some(run.id)) else { continue };
+        let position = match series.iter().position(|known| known.tool == tool && known.name == name) {
+            Some(position) => position,
+            None => {
+                series.push(Series { tool: tool.to_string(), name: name.to_string(), values: vec![None; runs.len()] });
+                series.len() - 1
+            }
+        };
+        series[position].values[index] = row["value"].as_u64();
+    }
+    series
+}
+
+/// `YYYY-MM-DD` of a time in seconds since the Unix epoch, in UTC
+fn date(seconds: i64) -> String {
+    // Howard Hinnant's civil_from_days
+    let days = seconds.div_euclid(86_400) + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days - era * 146_097;
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 { month_index + 3 } else { month_index - 9 };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+pub fn to_json(subject: &Subject, trend: &Trend) -> Value {
+    json!({
+        "schema_version": SCHEMA_VERSION,
+        "tool": "trr",
+        "repo": subject.repo,
+        "target": subject.target,
+        "runs": trend.runs,
+        "series": trend.series,
+    })
+}
+
+pub fn write_markdown(out: &mut dyn Write, subject: &Subject, trend: &Trend) -> io::Result<()> {
+    writeln!(out, "# Metric History: `{}`\n", subject.target)?;
+    if let Some(repo) = &subject.repo {
+        writeln!(out, "Repository: `{}`\n", repo)?;
+    }
+    if trend.runs.is_empty() {
+        writeln!(out, "No runs recorded.")?;
+        return Ok(());
+    }
+
+    let names: Vec<String> = trend.series.iter().map(|series| format!("{}: {}", series.tool, series.name)).collect();
+    writeln!(out, "| Revision | Date | Result | {} |", names.join(" | "))?;
+    writeln!(out, "|---|---|---|{}", "---:|".repeat(names.len()))?;
+    for (index, run) in trend.runs.iter().enumerate() {
+        let revision = run.revision.as_deref().map_or("—".to_string(), |revision| format!("`{}`", &revision[..revision.len().min(8)]));
+        let values: Vec<String> = trend.series.iter()
+            .map(|series| series.values[index].map_or("—".to_string(), |value| value.to_string()))
+            .collect();
+        writeln!(
+            out,
+            "| {} | {} | {} | {} |",
+            revision,
+            date(run.committed_at.unwrap_or(run.recorded_at)),
+            if run.passed { "pass" } else { "fail" },
+            values.join(" | ")
+        )?;
+    }
+    let changes: Vec<String> = trend.series.iter()
+        .map(|series| series.change().map_or("—".to_string(), |change| format!("{:+}", change)))
+        .collect();
+    writeln!(out, "| **Change** | | | {} |", changes.join(" | "))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::report::Status;
+
+    #[test]
+    fn quoting() {
+        assert_eq!(quote("programs/vault"), "'programs/vault'");
+        assert_eq!(quote("it's"), "'it''s'");
+        assert_eq!(quote("'; DROP TABLE runs; --"), "'''; DROP TABLE runs; --'");
+        assert_eq!(quote("a\0b"), "'ab'");
+        assert_eq!(quote("two\nlines"), "'two\nlines'");
+        assert_eq!(quote(""), "''");
+    }
+
+    #[test]
+    fn dates() {
+        assert_eq!(date(0), "1970-01-01");
+        assert_eq!(date(86_399), "1970-01-01");
+        assert_eq!(date(951_782_400), "2000-02-29");
+        assert_eq!(date(1_709_164_800), "2024-02-29");
+        assert_eq!(date(4_107_542_400), "2100-03-01");
+        assert_eq!(date(-1), "1969-12-31");
+        assert_eq!(date(-86_400), "1969-12-31");
+        assert_eq!(date(-2_203_891_200), "1900-03-01");
+        assert_eq!(date(-11_670_998_400), "1600-02-29");
+    }
+
+    #[test]
+    fn sqlite3_output() {
+        assert!(parse_rows("").unwrap().is_empty());
+        assert!(parse_rows("\n").unwrap().is_empty());
+        let rows = parse_rows("[{\"id\":3,\"revision\":null}]\n").unwrap();
+        assert_eq!(rows[0]["id"], 3);
+        assert!(rows[0]["revision"].is_null());
+        assert!(parse_rows("Error: no such table").is_err());
+        assert!(parse_rows("{\"id\":3}").is_err());
+    }
+
+    #[test]
+    fn trend_of_rows() {
+        let run_rows = vec![
+            json!({ "id": 1, "revision": "aaa", "committed_at": 100, "recorded_at": 500, "passed": 1 }),
+            json!({ "id": 2, "revision": "bbb", "committed_at": 200, "recorded_at": 600, "passed": 0 }),
+            // bbb recorded again: the latest run counts
+            json!({ "id": 3, "revision": "bbb", "committed_at": 200, "recorded_at": 700, "passed": 1 }),
+            json!({ "id": 4, "revision": null, "committed_at": null, "recorded_at": 800, "passed": 1 }),
+        ];
+        let runs = runs(&run_rows, 20);
+        let ids: Vec<i64> = runs.iter().map(|run| run.id).collect();
+        assert_eq!(ids, [1, 3, 4]);
+        assert!(runs[1].passed);
+        assert_eq!(runs[2].committed_at, None);
+
+        let metric_rows = vec![
+            json!({ "run_id": 1, "tool": "mscd", "name": "Struct composition depth", "value": 4 }),
+            json!({ "run_id": 1, "tool": "mcd", "name": "Max CPI depth", "value": 2 }),
+            json!({ "run_id": 2, "tool": "mscd", "name": "Struct composition depth", "value": 9 }),
+            json!({ "run_id": 3, "tool": "mscd", "name": "Struct composition depth", "value": 6 }),
+            json!({ "run_id": 4, "tool": "mscd", "name": "Struct composition depth", "value": 3 }),
+            json!({ "run_id": 4, "tool": "mcd", "name": "Max CPI depth", "value": null }),
+        ];
+        let metrics = ["struct composition depth".to_string()];
+        let series = series(&runs, &metric_rows, &Selection { metrics: &metrics, tools: &[] });
+        assert_eq!(series.len(), 1);
+        assert_eq!(series[0].values, [Some(4), Some(6), Some(3)]);
+        assert_eq!(series[0].change(), Some(-1));
+
+        let tools = ["mcd".to_string()];
+        let series = super::series(&runs, &metric_rows, &Selection { metrics: &[], tools: &tools });
+        assert_eq!(series[0].values, [Some(2), None, None]);
+        assert_eq!(series[0].change(), None);
+
+        // The last runs only
+        let last: Vec<i64> = super::runs(&run_rows, 2).iter().map(|run| run.id).collect();
+        assert_eq!(last, [3, 4]);
+    }
+
+    #[test]
+    fn round_trip() {
+        if Command::new("sqlite3").arg("-version").output().is_err() {
+            eprintln!("sqlite3 is not installed; skipping");
+            return;
+        }
+        let dir = tempfile::TempDir::new().unwrap();
+        let history = History::open(&dir.path().join("history.db")).unwrap();
+        let subject = Subject { repo: Some("https://example.com/o'brien.git".to_string()), target: "programs/it's\nhere".to_string() };
+        let metric = |value| Metric { tool: "mscd", name: "Struct composition depth", value: Some(value), threshold: Some(5), status: Status::Pass };
+        let commit = |id: &str, time| Commit { id: id.to_string(), time };
+
+        // Recorded out of order, and one commit twice
+        history.record(&subject, Some(&commit("bbb", 200)), true, &[metric(5)]).unwrap();
+        history.record(&subject, Some(&commit("aaa", 100)), true, &[metric(3)]).unwrap();
+        history.record(&subject, Some(&commit("bbb", 200)), false, &[metric(7)]).unwrap();
+        let other = Subject { repo: None, target: "/elsewhere".to_string() };
+        history.record(&other, None, true, &[metric(1)]).unwrap();
+
+        let metrics = ["Struct composition depth".to_string()];
+        let trend = history.trend(&subject, &Selection { metrics: &metrics, tools: &[] }, 10).unwrap();
+        let revisions: Vec<&str> = trend.runs.iter().filter_map(|run| run.revision.as_deref()).collect();
+        assert_eq!(revisions, ["aaa", "bbb"]);
+        assert!(!trend.runs[1].passed);
+        assert_eq!(trend.series[0].values, [Some(3), Some(7)]);
+
+        let trend = history.trend(&other, &Selection { metrics: &metrics, tools: &[] }, 10).unwrap();
+        assert_eq!(trend.series[0].values, [Some(1)]);
+    }
+}
@@ -0,0 +1,496 @@
+//! `trr`: one command line for the struct (mscd), trait (mtd), macro (mmed) and CPI
+//! (mcd) depth analyzers, the compute-unit estimator (mcu), the cyclomatic complexity
+//! analyzer (mcc), the dependency depth analyzer (mdd), the duplicate dependency
+//! version detector (mdv), the missing signer check detector (msc), the unchecked
+//! arithmetic detector (mua), the IDL consistency checker (mic), the stack frame
+//! estimator (msf), the potential panic detector (mpd), the floating-point detector
+//! (mfp), the error code inventory (mec), the logging cost analyzer (mlc), the missing
+//! rent exemption detector (mre), the realloc auditor (mra), the sysvar usage report
+//! (msv) and the account data copy detector (mdc), taking the target, `--repo` and
+//! `--format` the same way for all of them. `trr serve` runs them for HTTP clients.
+
+mod history;
+mod report;
+mod serve;
+mod tools;
+
+use std::ffi::OsString;
+use std::env;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::{self, ExitStatus, Stdio};
+
+use clap::{Args, Parser, Subcommand};
+use serde_json::{json, Map, Value};
+use trr_core::git::{self, Commit, Repository};
+use trr_core::output::{Format, SCHEMA_VERSION};
+
+use history::{History, Selection, Subject};
+use report::{Metric, ReportFormat, Thresholds};
+use tools::Tool;
+
+#[derive(Parser)]
+#[clap(author, version, about = "Technical risk review analyzers for Rust programs")]
+#[clap(after_help = "Options after `--` are passed on to the analyzer.\n\nExamples:
+  trr mscd programs/vault/src
+  trr mtd --repo https://github.com/user/repo.git programs/vault -- --no-tests
+  trr mmed --format json . -- --anchor
+  trr mcd --format markdown programs
+  trr mcu programs -- --top 5
+  trr mcc programs -- --no-tests --fail-above 15
+  trr mdd . -- --programs-only
+  trr mdv . -- --fail-on-watched
+  trr msc --format sarif programs
+  trr mua programs -- --top 10
+  trr mic . -- --idl app/src/idl/vault.json
+  trr msf programs -- --top 10
+  trr mpd programs -- --no-indexing
+  trr mfp . -- --all-crates
+  trr mec --format markdown programs
+  trr mlc programs -- --fail-on-leak
+  trr mre programs -- --fail-above 0
+  trr mra programs -- -v
+  trr msv programs -- --fail-on-deprecated
+  trr mdc programs -- --top 10
+  trr all --repo ../protocol programs/drift
+  trr report --max-trait-depth 4 --max-cpi-depth 3 --json report.json programs/vault
+  trr history record --commits 20 programs/vault
+  trr history show --tool mscd --last 20 programs/vault
+  TRR_SERVE_TOKEN=secret trr serve --bind 0.0.0.0:8080 --workers 4")]
+struct Cli {
+    #[clap(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Maximum struct composition depth
+    Mscd(ToolArgs),
+    /// Maximum trait depth
+    Mtd(ToolArgs),
+    /// Maximum macro expansion depth
+    Mmed(ToolArgs),
+    /// Maximum CPI depth per instruction handler
+    Mcd(ToolArgs),
+    /// Heuristic compute-unit estimate per instruction handler
+    Mcu(ToolArgs),
+    /// Cyclomatic complexity and nesting depth per function
+    Mcc(ToolArgs),
+    /// Dependency tree depth and transitive dependencies per workspace crate
+    Mdd(ToolArgs),
+    /// Crates locked at several versions, and the workspace crates pulling each
+    Mdv(ToolArgs),
+    /// Accounts gating state changes without a signer check, per instruction handler
+    Msc(ToolArgs),
+    /// Unchecked arithmetic on amounts in program crates without overflow checks
+    Mua(ToolArgs),
+    /// Drift between Anchor IDLs and the program source
+    Mic(ToolArgs),
+    /// Estimated stack frame per function, and large parameters taken by value
+    Msf(ToolArgs),
+    /// panic!, unwrap, expect, assertions and indexing in program crates
+    Mpd(ToolArgs),
+    /// Floating-point types, literals and math in program crates
+    Mfp(ToolArgs),
+    /// Custom error codes per program, and the codes defined more than once
+    Mec(ToolArgs),
+    /// msg! and sol_log* costs per instruction handler, and logs that may leak
+    Mlc(ToolArgs),
+    /// Handlers creating accounts or moving lamports without consulting rent
+    Mre(ToolArgs),
+    /// Account reallocations: zero-init, sizes from instruction data and lamport top-ups
+    Mra(ToolArgs),
+    /// Sysvars each instruction handler reads, by syscall or account, and deprecated patterns
+    Msv(ToolArgs),
+    /// Account data copied or deserialized whole by the handlers, ranked by estimated size
+    Mdc(ToolArgs),
+    /// Run all the analyzers on the same target
+    All(Target),
+    /// One document with the metrics of all the analyzers, checked against thresholds
+    Report(Box<ReportArgs>),
+    /// Record the metrics of runs in a SQLite database, and show their trend
+    History(HistoryArgs),
+    /// Serve the analyzers over HTTP: queue a repository and revision, get back its report
+    Serve(serve::Options),
+}
+
+#[derive(Args)]
+struct Source {
+    /// Directory to analyze (relative to --repo when given)
+    #[clap(value_name = "DIR", default_value = ".")]
+    dir: PathBuf,
+
+    /// Git repository URL to clone, or path of a local checkout
+    #[clap(long, value_name = "REPO_URL_OR_PATH")]
+    repo: Option<String>,
+
+    /// Branch, tag or commit of --repo to analyze
+    #[clap(long, value_name = "REV", requires = "repo")]
+    rev: Option<String>,
+}
+
+#[derive(Args)]
+struct Target {
+    #[clap(flatten)]
+    source: Source,
+
+    /// Output format
+    #[clap(long, value_enum, default_value = "text")]
+    format: Format,
+}
+
+#[derive(Args)]
+struct ToolArgs {
+    #[clap(flatten)]
+    target: Target,
+
+    /// Options for the analyzer itself
+    #[clap(last = true, value_name = "OPTIONS")]
+    options: Vec<OsString>,
+}
+
+#[derive(Args)]
+struct ReportArgs {
+    #[clap(flatten)]
+    source: Source,
+
+    /// Format of the document printed on stdout
+    #[clap(long, value_enum, default_value = "markdown")]
+    format: ReportFormat,
+
+    /// Also write the document as JSON to this file
+    #[clap(long, value_name = "FILE")]
+    json: Option<PathBuf>,
+
+    /// Also record the metrics in this history database
+    #[clap(long, value_name = "FILE")]
+    history: Option<PathBuf>,
+
+    /// Thresholds not given here are read from the `[trr]` table of the nearest trr.toml
+    #[clap(flatten)]
+    thresholds: Thresholds,
+}
+
+#[derive(Args)]
+struct HistoryArgs {
+    /// The SQLite database
+    #[clap(long, value_name = "FILE", default_value = history::DEFAULT_DATABASE, global = true)]
+    db: PathBuf,
+
+    #[clap(subcommand)]
+    command: HistoryCommand,
+}
+
+#[derive(Subcommand)]
+enum HistoryCommand {
+    /// Run the analyzers and record their metrics, for the target or its last commits
+    Record(Box<RecordArgs>),
+    /// The metrics of the recorded runs of a target, oldest first
+    Show(ShowArgs),
+}
+
+#[derive(Args)]
+struct RecordArgs {
+    #[clap(flatten)]
+    source: Source,
+
+    /// Record each of the last N commits that touched DIR, oldest first, instead of the
+    /// checkout as it is (local repositories only)
+    #[clap(long, value_name = "N")]
+    commits: Option<usize>,
+
+    /// Thresholds not given here are read from the `[trr]` table of the nearest trr.toml
+    #[clap(flatten)]
+    thresholds: Thresholds,
+}
+
+#[derive(Args)]
+struct ShowArgs {
+    /// Directory the runs analyzed (relative to --repo when given)
+    #[clap(value_name = "DIR", default_value = ".")]
+    dir: PathBuf,
+
+    /// Repository the runs were recorded with
+    #[clap(long, value_name = "REPO_URL_OR_PATH")]
+    repo: Option<String>,
+
+    /// Metric to show, by name ("Struct composition depth"); may be repeated
+    #[clap(long, value_name = "NAME")]
+    metric: Vec<String>,
+
+    /// Analyzer whose metrics to show; may be repeated. Without --metric or --tool,
+    /// the metrics checked against thresholds are shown
+    #[clap(long, value_name = "TOOL")]
+    tool: Vec<String>,
+
+    /// Only the last N runs
+    #[clap(long, value_name = "N", default_value = "20")]
+    last: usize,
+
+    /// Output format
+    #[clap(long, value_enum, default_value = "markdown")]
+    format: ReportFormat,
+}
+
+type CliResult<T> = Result<T, Box<dyn std::error::Error>>;
+
+/// Each analyzer's JSON report, or why there is none
+type Reports = Vec<(Tool, Result<Value, String>)>;
+
+/// The directory to analyze, inside the repository when there is one. The repository
+/// is returned too, so a clone stays alive until the analyzers are done.
+fn resolve_target(source: &Source) -> CliResult<(PathBuf, Option<Repository>)> {
+    let repository = source.repo.as_deref().map(|repo| Repository::open(repo, source.rev.as_deref())).transpose()?;
+    let dir = match &repository {
+        Some(repository) => repository.subdir(&source.dir)?,
+        None => source.dir.clone(),
+    };
+    if !dir.exists() {
+        return Err(format!("Path '{}' does not exist", dir.display()).into());
+    }
+    Ok((dir, repository))
+}
+
+fn not_found(tool: Tool) -> impl FnOnce(io::Error) -> Box<dyn std::error::Error> {
+    move |e| format!("could not run {} ({}); install it next to trr or on the PATH", tool.name(), e).into()
+}
+
+fn run_tool(tool: Tool, args: &ToolArgs) -> CliResult<ExitStatus> {
+    let (dir, _repository) = resolve_target(&args.target.source)?;
+    let status = tool.command(&dir, args.target.format, &args.options).status().map_err(not_found(tool))?;
+    Ok(status)
+}
+
+/// Run every analyzer, one section each in text and Markdown, one document keyed by
+/// analyzer in JSON, or one SARIF log with a run per analyzer. Fails if any analyzer
+/// fails, after running them all.
+fn run_all(target: &Target) -> CliResult<bool> {
+    if target.format == Format::Csv {
+        return Err("the analyzers' CSV reports have different columns; run them one at a time for CSV".into());
+    }
+    let (dir, _repository) = resolve_target(&target.source)?;
+    let mut success = true;
+    match target.format {
+        Format::Text | Format::Markdown | Format::Csv => {
+            for tool in Tool::ALL {
+                // Each Markdown report starts with its own heading
+                if target.format == Format::Text {
+                    println!("=== {} ({}) ===\n", tool.title(), tool.name());
+                }
+                let status = tool.command(&dir, target.format, &[]).status().map_err(not_found(tool))?;
+                success &= status.success();
+                println!();
+            }
+        }
+        Format::Json | Format::Sarif => {
+            let mut documents = Vec::new();
+            for tool in Tool::ALL {
+                let output = tool.command(&dir, target.format, &[])
+                    .stderr(Stdio::inherit())
+                    .output()
+                    .map_err(not_found(tool))?;
+                success &= output.status.success();
+                let report = serde_json::from_slice::<Value>(&output.stdout)
+                    .map_err(|e| format!("{} printed no {} report: {}", tool.name(), target.format, e))?;
+                documents.push((tool, report));
+            }
+            println!("{}", serde_json::to_string_pretty(&merge(target.format, &dir, documents))?);
+        }
+    }
+    Ok(success)
+}
+
+/// The analyzers' JSON reports as one document keyed by analyzer, or their SARIF logs
+/// as one log with all their runs
+fn merge(format: Format, dir: &Path, documents: Vec<(Tool, Value)>) -> Value {
+    if format == Format::Json {
+        let mut document = Map::new();
+        document.insert("schema_version".to_string(), json!(SCHEMA_VERSION));
+        document.insert("target".to_string(), json!(dir));
+        for (tool, report) in documents {
+            document.insert(tool.name().to_string(), report);
+        }
+        Value::Object(document)
+    } else {
+        let runs: Vec<Value> = documents.into_iter()
+            .flat_map(|(_, mut log)| match log["runs"].take() {
+                Value::Array(runs) => runs,
+                _ => Vec::new(),
+            })
+            .collect();
+        json!({
+            "$schema": "https://json.schemastore.org/sarif-2.1.0.json",
+            "version": "2.1.0",
+            "runs": runs,
+        })
+    }
+}
+
+/// The JSON report of `tool` on `dir`, or why there is none
+fn json_report(tool: Tool, dir: &Path) -> Result<Value, String> {
+    let output = tool.command(dir, Format::Json, &[])
+        .output()
+        .map_err(|e| not_found(tool)(e).to_string())?;
+    serde_json::from_slice(&output.stdout).map_err(|_| {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        format!("{} printed no JSON report: {}", tool.name(), stderr.trim())
+    })
+}
+
+/// `given`, with the thresholds it leaves out read from the nearest trr.toml
+fn thresholds(given: Thresholds) -> CliResult<Thresholds> {
+    let configured = match trr_core::config::find(&env::current_dir()?, "trr") {
+        Some(path) => trr_core::config::load(&path, "trr")?,
+        None => Thresholds::default(),
+    };
+    Ok(given.or(configured))
+}
+
+/// The report of each of `tools` on `dir`, and their metrics
+fn analyze(dir: &Path, tools: &[Tool], thresholds: &Thresholds) -> (Reports, Vec<Metric>) {
+    let reports: Reports = tools.iter()
+        .map(|&tool| (tool, json_report(tool, dir)))
+        .collect();
+    let metrics = report::metrics(&reports, thresholds);
+    (reports, metrics)
+}
+
+/// The commit checked out in `dir`, if it is in a Git repository
+fn head(dir: &Path) -> Option<Commit> {
+    git::commits(dir, "HEAD", 1).ok()?.into_iter().next()
+}
+
+/// Run every analyzer and print the combined document. Fails if a metric is over its
+/// threshold or could not be measured.
+fn run_report(args: ReportArgs) -> CliResult<bool> {
+    let (dir, _repository) = resolve_target(&args.source)?;
+    let thresholds = thresholds(args.thresholds)?;
+    let (reports, metrics) = analyze(&dir, &Tool::ALL, &thresholds);
+    let passed = report::passed(&metrics);
+    if let Some(path) = &args.history {
+        let subject = Subject::new(&args.source.dir, args.source.repo.as_deref());
+        History::open(path)?.record(&subject, head(&dir).as_ref(), passed, &metrics)?;
+    }
+    if args.format == ReportFormat::Markdown {
+        report::write_markdown(&mut io::stdout().lock(), &dir, &reports, &metrics)?;
+    }
+    let document = report::to_json(&dir, reports, &metrics);
+    if args.format == ReportFormat::Json {
+        println!("{}", serde_json::to_string_pretty(&document)?);
+    }
+    if let Some(path) = &args.json {
+        fs::write(path, serde_json::to_string_pretty(&document)?)
+            .map_err(|e| format!("failed to write {}: {}", path.display(), e))?;
+    }
+    Ok(passed)
+}
+
+/// Record the metrics of the target as it is, or of each of its last commits
+fn run_record(db: &Path, args: RecordArgs) -> CliResult<()> {
+    let history = History::open(db)?;
+    let thresholds = thresholds(args.thresholds)?;
+    let subject = Subject::new(&args.source.dir, args.source.repo.as_deref());
+    let record = |dir: &Path, commit: Option<&Commit>| -> CliResult<()> {
+        let (_, metrics) = analyze(dir, &Tool::ALL, &thresholds);
+        let passed = report::passed(&metrics);
+        let id = history.record(&subject, commit, passed, &metrics)?;
+        let revision = commit.map_or("working tree", |commit| &commit.id[..commit.id.len().min(8)]);
+        eprintln!("Recorded run {}: {} at {} ({})", id, subject.target, revision, if passed { "pass" } else { "fail" });
+        Ok(())
+    };
+
+    let Some(count) = args.commits else {
+        let (dir, _repository) = resolve_target(&args.source)?;
+        return record(&dir, head(&dir).as_ref());
+    };
+    // Past commits need the history a fetch leaves out, so only local checkouts
+    let dir = match &args.source.repo {
+        Some(repo) if Path::new(repo).is_dir() => Path::new(repo).join(&args.source.dir),
+        Some(repo) => return Err(format!("--commits needs a local checkout; clone {} and pass its path to --repo", repo).into()),
+        None => args.source.dir.clone(),
+    };
+    let commits = git::commits(&dir, args.source.rev.as_deref().unwrap_or("HEAD"), count)?;
+    for commit in commits.iter().rev() {
+        let (_checkout, checkout_dir) = git::checkout_revision(&dir, &commit.id)?;
+        record(&checkout_dir, Some(commit))?;
+    }
+    Ok(())
+}
+
+fn run_show(db: &Path, args: ShowArgs) -> CliResult<()> {
+    let history = History::open(db)?;
+    let subject = Subject::new(&args.dir, args.repo.as_deref());
+    let selection = Selection { metrics: &args.metric, tools: &args.tool };
+    let trend = history.trend(&subject, &selection, args.last)?;
+    match args.format {
+        ReportFormat::Markdown => history::write_markdown(&mut io::stdout().lock(), &subject, &trend)?,
+        ReportFormat::Json => println!("{}", serde_json::to_string_pretty(&history::to_json(&subject, &trend))?),
+    }
+    Ok(())
+}
+
+fn main() {
+    let cli = Cli::parse();
+    let result = match cli.command {
+        Command::Mscd(args) => run_tool(Tool::Mscd, &args).map(|status| status.code().unwrap_or(1)),
+        Command::Mtd(args) => run_tool(Tool::Mtd, &args).map(|status| status.code().unwrap_or(1)),
+        Command::Mmed(args) => run_tool(Tool::Mmed, &args).map(|status| status.code().unwrap_or(1)),
+        Command::Mcd(args) => run_tool(Tool::Mcd, &args).map(|status| status.code().unwrap_or(1)),
+        Command::Mcu(args) => run_tool(Tool::Mcu, &args).map(|status| status.code().unwrap_or(1)),
+        Command::Mcc(args) => run_tool(Tool::Mcc, &args).map(|status| status.code().unwrap_or(1)),
+        Command::Mdd(args) => run_tool(Tool::Mdd, &args).map(|status| status.code().unwrap_or(1)),
+        Command::Mdv(args) => run_tool(Tool::Mdv, &args).map(|status| status.code().unwrap_or(1)),
+        Command::Msc(args) => run_tool(Tool::Msc, &args).map(|status| status.code().unwrap_or(1)),
+        Command::Mua(args) => run_tool(Tool::Mua, &args).map(|status| status.code().unwrap_or(1)),
+        Command::Mic(args) => run_tool(Tool::Mic, &args).map(|status| status.code().unwrap_or(1)),
+        Command::Msf(args) => run_tool(Tool::Msf, &args).map(|status| status.code().unwrap_or(1)),
+        Command::Mpd(args) => run_tool(Tool::Mpd, &args).map(|status| status.code().unwrap_or(1)),
+        Command::Mfp(args) => run_tool(Tool::Mfp, &args).map(|status| status.code().unwrap_or(1)),
+        Command::Mec(args) => run_tool(Tool::Mec, &args).map(|status| status.code().unwrap_or(1)),
+        Command::Mlc(args) => run_tool(Tool::Mlc, &args).map(|status| status.code().unwrap_or(1)),
+        Command::Mre(args) => run_tool(Tool::Mre, &args).map(|status| status.code().unwrap_or(1)),
+        Command::Mra(args) => run_tool(Tool::Mra, &args).map(|status| status.code().unwrap_or(1)),
+        Command::Msv(args) => run_tool(Tool::Msv, &args).map(|status| status.code().unwrap_or(1)),
+        Command::Mdc(args) => run_tool(Tool::Mdc, &args).map(|status| status.code().unwrap_or(1)),
+        Command::All(target) => run_all(&target).map(|success| if success { 0 } else { 1 }),
+        Command::Report(args) => run_report(*args).map(|passed| if passed { 0 } else { 1 }),
+        Command::History(args) => match args.command {
+            HistoryCommand::Record(record) => run_record(&args.db, *record).map(|_| 0),
+            HistoryCommand::Show(show) => run_show(&args.db, show).map(|_| 0),
+        },
+        Command::Serve(options) => serve::serve(options).map(|_| 0).map_err(Into::into),
+    };
+    match result {
+        Ok(code) => process::exit(code),
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            process::exit(1);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn merged_json() {
+        let documents = vec![(Tool::Mscd, json!({ "max_depth": 3 })), (Tool::Mmed, json!({ "summary": {} }))];
+        let document = merge(Format::Json, Path::new("programs/vault"), documents);
+        assert_eq!(document["schema_version"], SCHEMA_VERSION);
+        assert_eq!(document["target"], "programs/vault");
+        assert_eq!(document["mscd"]["max_depth"], 3);
+        assert!(document["mmed"]["summary"].is_object());
+    }
+
+    #[test]
+    fn merged_sarif() {
+        let log = |names: &[&str]| json!({ "version": "2.1.0", "runs": names.iter().map(|name| json!({ "tool": { "driver": { "name": name } } })).collect::<Vec<_>>() });
+        let documents = vec![(Tool::Mscd, log(&["mscd"])), (Tool::Mtd, json!({})), (Tool::Mcd, log(&["mcd", "mcd-idl"]))];
+        let document = merge(Format::Sarif, Path::new("."), documents);
+        let names: Vec<_> = document["runs"].as_array().unwrap().iter().map(|run| run["tool"]["driver"]["name"].as_str().unwrap()).collect();
+        assert_eq!(names, ["mscd", "mcd", "mcd-idl"]);
+        assert_eq!(document["version"], "2.1.0");
+    }
+}
@@ -0,0 +1,461 @@
+//! `trr report`: the headline metrics of all the analyzers on one target, checked
+//! against thresholds, as the one document attached to a technical risk review.
+
+use std::io::{self, Write};
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+use trr_core::output::SCHEMA_VERSION;
+
+use crate::tools::Tool;
+
+/// The highest value each depth may reach; unset ones are reported but not checked
+#[derive(clap::Args, Default, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct Thresholds {
+    /// Maximum struct composition depth (mscd)
+    #[clap(long, value_name = "DEPTH")]
+    pub max_struct_depth: Option<u64>,
+
+    /// Maximum trait depth (mtd)
+    #[clap(long, value_name = "DEPTH")]
+    pub max_trait_depth: Option<u64>,
+
+    /// Maximum macro nesting depth (mmed)
+    #[clap(long, value_name = "DEPTH")]
+    pub max_macro_depth: Option<u64>,
+
+    /// Maximum CPI depth of an instruction handler (mcd)
+    #[clap(long, value_name = "DEPTH")]
+    pub max_cpi_depth: Option<u64>,
+
+    /// Highest compute-unit estimate of an instruction handler (mcu)
+    #[clap(long, value_name = "UNITS")]
+    pub max_compute_units: Option<u64>,
+
+    /// Maximum cyclomatic complexity of a function (mcc)
+    #[clap(long, value_name = "N")]
+    pub max_complexity: Option<u64>,
+
+    /// Maximum control-flow nesting depth of a function (mcc)
+    #[clap(long, value_name = "DEPTH")]
+    pub max_nesting: Option<u64>,
+
+    /// Maximum dependency tree depth of a workspace crate (mdd)
+    #[clap(long, value_name = "DEPTH")]
+    pub max_dependency_depth: Option<u64>,
+
+    /// Most watched crates (solana-program, borsh, spl-token, ...) locked at several
+    /// versions (mdv)
+    #[clap(long, value_name = "N")]
+    pub max_watched_duplicates: Option<u64>,
+
+    /// Most accounts gating state changes without a signer check (msc)
+    #[clap(long, value_name = "N")]
+    pub max_missing_signer_checks: Option<u64>,
+
+    /// Most unchecked arithmetic operations on amounts (mua)
+    #[clap(long, value_name = "N")]
+    pub max_unchecked_arithmetic: Option<u64>,
+
+    /// Most differences between the IDLs and the program source (mic)
+    #[clap(long, value_name = "N")]
+    pub max_idl_drift: Option<u64>,
+
+    /// Largest estimated stack frame of a function, in bytes (msf)
+    #[clap(long, value_name = "BYTES")]
+    pub max_stack_frame: Option<u64>,
+
+    /// Most potential panics in program crates (mpd)
+    #[clap(long, value_name = "N")]
+    pub max_potential_panics: Option<u64>,
+
+    /// Most floating-point sites in program crates (mfp)
+    #[clap(long, value_name = "N")]
+    pub max_float_sites: Option<u64>,
+
+    /// Most custom error codes given more than one meaning (mec)
+    #[clap(long, value_name = "N")]
+    pub max_error_code_collisions: Option<u64>,
+
+    /// Most logs that may leak a secret or large data (mlc)
+    #[clap(long, value_name = "N")]
+    pub max_log_leaks: Option<u64>,
+
+    /// Most handlers at risk and Anchor init accounts without room for their data (mre)
+    #[clap(long, value_name = "N")]
+    pub max_rent_findings: Option<u64>,
+
+    /// Most reallocations with a problem (mra)
+    #[clap(long, value_name = "N")]
+    pub max_realloc_problems: Option<u64>,
+
+    /// Most sysvar reads following a deprecated pattern (msv)
+    #[clap(long, value_name = "N")]
+    pub max_deprecated_sysvar_reads: Option<u64>,
+
+    /// Most account data copies of 1024 bytes or more (mdc)
+    #[clap(long, value_name = "N")]
+    pub max_large_data_copies: Option<u64>,
+}
+
+impl Thresholds {
+    /// These thresholds, with the unset ones taken from `defaults`
+    pub fn or(self, defaults: Thresholds) -> Thresholds {
+        Thresholds {
+            max_struct_depth: self.max_struct_depth.or(defaults.max_struct_depth),
+            max_trait_depth: self.max_trait_depth.or(defaults.max_trait_depth),
+            max_macro_depth: self.max_macro_depth.or(defaults.max_macro_depth),
+            max_cpi_depth: self.max_cpi_depth.or(defaults.max_cpi_depth),
+            max_compute_units: self.max_compute_units.or(defaults.max_compute_units),
+            max_complexity: self.max_complexity.or(defaults.max_complexity),
+            max_nesting: self.max_nesting.or(defaults.max_nesting),
+            max_dependency_depth: self.max_dependency_depth.or(defaults.max_dependency_depth),
+            max_watched_duplicates: self.max_watched_duplicates.or(defaults.max_watched_duplicates),
+            max_missing_signer_checks: self.max_missing_signer_checks.or(defaults.max_missing_signer_checks),
+            max_unchecked_arithmetic: self.max_unchecked_arithmetic.or(defaults.max_unchecked_arithmetic),
+            max_idl_drift: self.max_idl_drift.or(defaults.max_idl_drift),
+            max_stack_frame: self.max_stack_frame.or(defaults.max_stack_frame),
+            max_potential_panics: self.max_potential_panics.or(defaults.max_potential_panics),
+            max_float_sites: self.max_float_sites.or(defaults.max_float_sites),
+            max_error_code_collisions: self.max_error_code_collisions.or(defaults.max_error_code_collisions),
+            max_log_leaks: self.max_log_leaks.or(defaults.max_log_leaks),
+            max_rent_findings: self.max_rent_findings.or(defaults.max_rent_findings),
+            max_realloc_problems: self.max_realloc_problems.or(defaults.max_realloc_problems),
+            max_deprecated_sysvar_reads: self.max_deprecated_sysvar_reads.or(defaults.max_deprecated_sysvar_reads),
+            max_large_data_copies: self.max_large_data_copies.or(defaults.max_large_data_copies),
+        }
+    }
+}
+
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ReportFormat {
+    Markdown,
+    Json,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Status {
+    Pass,
+    Fail,
+    /// No threshold applies
+    Unchecked,
+    /// The analyzer did not produce the value
+    Error,
+}
+
+impl Status {
+    fn label(self) -> &'static str {
+        match self {
+            Status::Pass => "✅ pass",
+            Status::Fail => "❌ fail",
+            Status::Unchecked => "",
+            Status::Error => "⚠️ error",
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct Metric {
+    pub tool: &'static str,
+    pub name: &'static str,
+    pub value: Option<u64>,
+    pub threshold: Option<u64>,
+    pub status: Status,
+}
+
+/// The metrics taken from each analyzer's JSON report: name, path of the value in
+/// the report, and whether it has a threshold
+fn metric_paths(tool: Tool) -> &'static [(&'static str, &'static [&'static str], bool)] {
+    match tool {
+        Tool::Mscd => &[
+            ("Structs", &["struct_count"], false),
+            ("Struct composition depth", &["max_depth"], true),
+        ],
+        Tool::Mtd => &[
+            ("Traits", &["summary", "trait_count"], false),
+            ("Trait impls", &["summary", "impl_count"], false),
+            ("Trait depth", &["summary", "max_depth"], true),
+            ("Associated bound depth", &["summary", "max_associated_bound_depth"], false),
+        ],
+        Tool::Mmed => &[
+            ("Files", &["summary", "files_analyzed"], false),
+            ("Macro nesting depth", &["summary", "max_depth"], true),
+        ],
+        Tool::Mcd => &[
+            ("Instruction handlers", &["summary", "handlers"], false),
+            ("CPI sites", &["summary", "cpi_sites"], false),
+            ("CPI depth", &["summary", "max_depth"], true),
+        ],
+        Tool::Mcu => &[
+            ("High risk handlers", &["summary", "high_risk"], false),
+            ("Highest compute-unit estimate", &["summary", "max_units"], true),
+        ],
+        Tool::Mcc => &[
+            ("Functions", &["summary", "functions"], false),
+            ("Cyclomatic complexity", &["summary", "max_complexity"], true),
+            ("Nesting depth", &["summary", "max_nesting"], true),
+        ],
+        Tool::Mdd => &[
+            ("Transitive dependencies", &["summary", "max_transitive"], false),
+            ("Dependency depth", &["summary", "max_depth"], true),
+        ],
+        Tool::Mdv => &[
+            ("Crates with several versions", &["summary", "duplicated"], false),
+            ("Watched crates with several versions", &["summary", "watched_duplicated"], true),
+        ],
+        Tool::Msc => &[
+            ("Handlers checked for signers", &["summary", "handlers"], false),
+            ("Missing signer checks", &["summary", "findings"], true),
+        ],
+        Tool::Mua => &[
+            ("Functions with unchecked arithmetic", &["summary", "functions"], false),
+            ("Unchecked arithmetic operations", &["summary", "operations"], true),
+        ],
+        Tool::Mic => &[
+            ("IDLs checked", &["summary", "idls"], false),
+            ("IDL drift", &["summary", "drifts"], true),
+        ],
+        Tool::Msf => &[
+            ("Largest stack frame", &["summary", "max_frame"], true),
+            ("Large by-value parameters", &["summary", "large_parameters"], false),
+        ],
+        Tool::Mpd => &[
+            ("Functions that may panic", &["summary", "functions"], false),
+            ("Potential panics", &["summary", "sites"], true),
+        ],
+        Tool::Mfp => &[
+            ("Crates using floating point", &["summary", "crates_with_floats"], false),
+            ("Floating-point sites", &["summary", "sites"], true),
+        ],
+        Tool::Mec => &[
+            ("Error codes", &["summary", "codes"], false),
+            ("Colliding error codes", &["summary", "collisions"], true),
+        ],
+        Tool::Mlc => &[
+            ("Highest logging estimate", &["summary", "max_units"], false),
+            ("Logs that may leak", &["summary", "leaks"], true),
+        ],
+        Tool::Mre => &[
+            ("Handlers creating accounts or moving lamports", &["summary", "handlers"], false),
+            ("Rent findings", &["summary", "findings"], true),
+        ],
+        Tool::Mra => &[
+            ("Reallocations", &["summary", "reallocs"], false),
+            ("Reallocations with a problem", &["summary", "flagged"], true),
+        ],
+        Tool::Msv => &[
+            ("Handlers reading sysvars", &["summary", "handlers_reading"], false),
+            ("Deprecated sysvar reads", &["summary", "deprecated"], true),
+        ],
+        Tool::Mdc => &[
+            ("Account data copies", &["summary", "copies"], false),
+            ("Large account data copies", &["summary", "large"], true),
+        ],
+    }
+}
+
+/// Whether the metric `name` of the analyzer named `tool` is checked against a threshold
+pub fn is_checked(tool: &str, name: &str) -> bool {
+    Tool::ALL.iter()
+        .filter(|known| known.name() == tool)
+        .any(|&known| metric_paths(known).iter().any(|&(metric, _, checked)| checked && metric == name))
+}
+
+fn threshold(tool: Tool, metric: &str, thresholds: &Thresholds) -> Option<u64> {
+    match tool {
+        Tool::Mscd => thresholds.max_struct_depth,
+        Tool::Mtd => thresholds.max_trait_depth,
+        Tool::Mmed => thresholds.max_macro_depth,
+        Tool::Mcd => thresholds.max_cpi_depth,
+        Tool::Mcu => thresholds.max_compute_units,
+        Tool::Mcc if metric == "Nesting depth" => thresholds.max_nesting,
+        Tool::Mcc => thresholds.max_complexity,
+        Tool::Mdd => thresholds.max_dependency_depth,
+        Tool::Mdv => thresholds.max_watched_duplicates,
+        Tool::Msc => thresholds.max_missing_signer_checks,
+        Tool::Mua => thresholds.max_unchecked_arithmetic,
+        Tool::Mic => thresholds.max_idl_drift,
+        Tool::Msf => thresholds.max_stack_frame,
+        Tool::Mpd => thresholds.max_potential_panics,
+        Tool::Mfp => thresholds.max_float_sites,
+        Tool::Mec => thresholds.max_error_code_collisions,
+        Tool::Mlc => thresholds.max_log_leaks,
+        Tool::Mre => thresholds.max_rent_findings,
+        Tool::Mra => thresholds.max_realloc_problems,
+        Tool::Msv => thresholds.max_deprecated_sysvar_reads,
+        Tool::Mdc => thresholds.max_large_data_copies,
+    }
+}
+
+/// The metrics of every analyzer, from its report or from the error that kept it
+/// from producing one
+pub fn metrics(reports: &[(Tool, Result<Value, String>)], thresholds: &Thresholds) -> Vec<Metric> {
+    let mut metrics = Vec::new();
+    for (tool, report) in reports {
+        for &(name, path, checked) in metric_paths(*tool) {
+            let value = report.as_ref().ok()
+                .and_then(|report| path.iter().try_fold(report, |value, key| value.get(key)))
+                .and_then(Value::as_u64);
+            let threshold = if checked { threshold(*tool, name, thresholds) } else { None };
+            let status = match (value, threshold) {
+                (None, _) => Status::Error,
+                (Some(_), None) => Status::Unchecked,
+                (Some(value), Some(threshold)) if value > threshold => Status::Fail,
+                (Some(_), Some(_)) => Status::Pass,
+            };
+            metrics.push(Metric { tool: tool.name(), name, value, threshold, status });
+        }
+        // mmed's warnings, summed over their types
+        if let (Tool::Mmed, Ok(report)) = (tool, report) {
+            let count = report.pointer("/summary/warning_counts").and_then(Value::as_array)
+                .map(|counts| counts.iter().filter_map(|entry| entry["count"].as_u64()).sum());
+            let status = if count.is_some() { Status::Unchecked } else { Status::Error };
+            metrics.push(Metric { tool: tool.name(), name: "Warnings", value: count, threshold: None, status });
+        }
+    }
+    metrics
+}
+
+/// Whether no metric failed its threshold or could not be measured
+pub fn passed(metrics: &[Metric]) -> bool {
+    metrics.iter().all(|metric| matches!(metric.status, Status::Pass | Status::Unchecked))
+}
+
+/// The metrics, the pass/fail result and each analyzer's full report
+pub fn to_json(target: &Path, reports: Vec<(Tool, Result<Value, String>)>, metrics: &[Metric]) -> Value {
+    let mut full_reports = Map::new();
+    let mut errors = Map::new();
+    for (tool, report) in reports {
+        match report {
+            Ok(report) => full_reports.insert(tool.name().to_string(), report),
+            Err(e) => errors.insert(tool.name().to_string(), json!(e)),
+        };
+    }
+    json!({
+        "schema_version": SCHEMA_VERSION,
+        "tool": "trr",
+        "target": target,
+        "passed": passed(metrics),
+        "metrics": metrics,
+        "errors": errors,
+        "reports": full_reports,
+    })
+}
+
+pub fn write_markdown(
+    out: &mut dyn Write,
+    target: &Path,
+    reports: &[(Tool, Result<Value, String>)],
+    metrics: &[Metric],
+) -> io::Result<()> {
+    writeln!(out, "# Technical Risk Review: `{}`\n", target.display())?;
+    let failed = metrics.iter().filter(|metric| matches!(metric.status, Status::Fail | Status::Error)).count();
+    if failed == 0 {
+        writeln!(out, "**Result: pass**\n")?;
+    } else {
+        writeln!(out, "**Result: fail** ({} metric(s) over their threshold or not measured)\n", failed)?;
+    }
+
+    writeln!(out, "| Analyzer | Metric | Value | Threshold | Status |")?;
+    writeln!(out, "|---|---|---:|---:|---|")?;
+    let or_dash = |value: Option<u64>| value.map_or("—".to_string(), |value| value.to_string());
+    for metric in metrics {
+        writeln!(
+            out,
+            "| {} | {} | {} | {} | {} |",
+            metric.tool, metric.name, or_dash(metric.value), or_dash(metric.threshold), metric.status.label()
+        )?;
+    }
+
+    let errors: Vec<_> = reports.iter()
+        .filter_map(|(tool, report)| report.as_ref().err().map(|e| (tool, e)))
+        .collect();
+    if !errors.is_empty() {
+        writeln!(out, "\n## Errors\n")?;
+        for (tool, e) in errors {
+            writeln!(out, "- {}: {}", tool.name(), e.lines().next().unwrap_or_default())?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn reports() -> Vec<(Tool, Result<Value, String>)> {
+        vec![
+            (Tool::Mscd, Ok(json!({ "struct_count": 12, "max_depth": 4 }))),
+            (Tool::Mcc, Ok(json!({ "summary": { "functions": 30, "max_complexity": 9, "max_nesting": 5 } }))),
+            (Tool::Mmed, Ok(json!({ "summary": { "files_analyzed": 3, "max_depth": 2, "warning_counts": [{ "count": 2 }, { "count": 5 }] } }))),
+            (Tool::Mdd, Err("mdd printed no JSON report: no Cargo.lock".to_string())),
+        ]
+    }
+
+    fn statuses(metrics: &[Metric]) -> Vec<(&str, &str, Option<u64>, Status)> {
+        metrics.iter().map(|metric| (metric.tool, metric.name, metric.value, metric.status)).collect()
+    }
+
+    #[test]
+    fn metrics_of_reports() {
+        let thresholds = Thresholds { max_struct_depth: Some(4), max_complexity: Some(10), max_nesting: Some(4), ..Thresholds::default() };
+        let metrics = metrics(&reports(), &thresholds);
+        assert_eq!(statuses(&metrics), [
+            ("mscd", "Structs", Some(12), Status::Unchecked),
+            ("mscd", "Struct composition depth", Some(4), Status::Pass),
+            ("mcc", "Functions", Some(30), Status::Unchecked),
+            ("mcc", "Cyclomatic complexity", Some(9), Status::Pass),
+            ("mcc", "Nesting depth", Some(5), Status::Fail),
+            ("mmed", "Files", Some(3), Status::Unchecked),
+            ("mmed", "Macro nesting depth", Some(2), Status::Unchecked),
+            ("mmed", "Warnings", Some(7), Status::Unchecked),
+            ("mdd", "Transitive dependencies", None, Status::Error),
+            ("mdd", "Dependency depth", None, Status::Error),
+        ]);
+        assert_eq!(metrics[4].threshold, Some(4));
+        // Unchecked metrics carry no threshold even when their analyzer has one
+        assert_eq!(metrics[2].threshold, None);
+    }
+
+    #[test]
+    fn passing() {
+        let reports = reports();
+        let metrics = |thresholds| metrics(&reports[..3], &thresholds);
+        assert!(passed(&metrics(Thresholds::default())));
+        assert!(passed(&metrics(Thresholds { max_struct_depth: Some(4), ..Thresholds::default() })));
+        assert!(!passed(&metrics(Thresholds { max_struct_depth: Some(3), ..Thresholds::default() })));
+        // An analyzer that produced no report fails the review, thresholds or not
+        assert!(!passed(&super::metrics(&reports, &Thresholds::default())));
+        assert!(passed(&[]));
+    }
+
+    #[test]
+    fn merged_document() {
+        let reports = reports();
+        let metrics = metrics(&reports, &Thresholds { max_struct_depth: Some(5), ..Thresholds::default() });
+        let document = to_json(Path::new("programs/vault"), reports, &metrics);
+        assert_eq!(document["tool"], "trr");
+        assert_eq!(document["target"], "programs/vault");
+        assert_eq!(document["passed"], false);
+        assert_eq!(document["metrics"].as_array().unwrap().len(), metrics.len());
+        assert_eq!(document["metrics"][1], json!({ "tool": "mscd", "name": "Struct composition depth", "value": 4, "threshold": 5, "status": "pass" }));
+        let analyzers = |key: &str| document[key].as_object().unwrap().keys().cloned().collect::<Vec<_>>();
+        assert_eq!(analyzers("reports"), ["mcc", "mmed", "mscd"]);
+        assert_eq!(analyzers("errors"), ["mdd"]);
+        assert_eq!(document["errors"]["mdd"], "mdd printed no JSON report: no Cargo.lock");
+    }
+
+    #[test]
+    fn merged_thresholds() {
+        let given = Thresholds { max_struct_depth: Some(2), ..Thresholds::default() };
+        let file = Thresholds { max_struct_depth: Some(6), max_cpi_depth: Some(3), ..Thresholds::default() };
+        let thresholds = given.or(file);
+        assert_eq!((thresholds.max_struct_depth, thresholds.max_cpi_depth, thresholds.max_nesting), (Some(2), Some(3), None));
+        assert!(is_checked("mcc", "Nesting depth"));
+        assert!(!is_checked("mcc", "Functions"));
+        assert!(!is_checked("nope", "Nesting depth"));
+    }
+}
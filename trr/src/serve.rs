@@ -0,0 +1,697 @@
+//! `trr serve`: the analyzers behind a small HTTP API, for review portals and bots.
+//!
+//! A job is a repository, a revision, a directory within it and the analyzers to run;
+//! its result is the document `trr report --format json` prints for them. Jobs wait in
+//! a bounded queue and a fixed number of workers run them, so the server takes no more
+//! than `--workers` analyses at a time and refuses new jobs once `--max-queued` wait.
+//!
+//! - `POST /jobs` queues a job and answers `202` with its id
+//! - `GET /jobs/<id>` answers the job, with its result once it is done
+//! - `POST /analyze` queues a job and answers its result when it is done
+//! - `GET /health` answers the queue and worker counts
+//!
+//! The server speaks HTTP/1.1 on `std::net`, one request per connection, with at most
+//! `--max-connections` open and a deadline for reading each request. With
+//! `TRR_SERVE_TOKEN` set, every request needs `Authorization: Bearer <token>`.
+//! Repositories are HTTPS, SSH or scp-like (`git@host:path`) remotes, fetched as
+//! `--repo` fetches them; local paths and `file://` URLs only with `--allow-local`,
+//! since they expose the server's files. Revisions have to be branch, tag or commit
+//! names, never anything git could read as an option.
+
+use std::collections::{BTreeMap, VecDeque};
+use std::env;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::path::{Component, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use trr_core::git::{Fetch, Repository};
+
+use crate::report::{self, Thresholds};
+use crate::tools::Tool;
+
+/// Largest request body accepted, in bytes
+const MAX_BODY: usize = 1 << 20;
+
+/// Longest request or header line accepted, in bytes
+const MAX_LINE: u64 = 8 << 10;
+
+/// Most header lines accepted
+const MAX_HEADERS: usize = 100;
+
+/// Time a client has to send its whole request, and each socket read or write
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+const IO_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(clap::Args)]
+pub struct Options {
+    /// Address to listen on
+    #[clap(long, value_name = "ADDR", default_value = "127.0.0.1:8080")]
+    bind: String,
+
+    /// Jobs analyzed at the same time
+    #[clap(long, value_name = "N", default_value = "2")]
+    workers: usize,
+
+    /// Jobs waiting for a worker before new ones are refused
+    #[clap(long, value_name = "N", default_value = "32")]
+    max_queued: usize,
+
+    /// Connections open at the same time, including those waiting on POST /analyze,
+    /// before new ones are refused
+    #[clap(long, value_name = "N", default_value = "64")]
+    max_connections: usize,
+
+    /// Finished jobs kept for GET /jobs/<id>, the oldest dropped first
+    #[clap(long, value_name = "N", default_value = "100")]
+    keep: usize,
+
+    /// Accept local paths and file:// URLs as repositories, not only remotes
+    #[clap(long)]
+    allow_local: bool,
+}
+
+/// The body of `POST /jobs` and `POST /analyze`
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+struct JobRequest {
+    repo: String,
+    rev: Option<String>,
+    /// Directory within the repository
+    #[serde(default)]
+    dir: Option<PathBuf>,
+    /// Analyzer names; all of them when empty
+    #[serde(default)]
+    tools: Vec<String>,
+    /// As in the `[trr]` table of trr.toml; those left out are read from the server's
+    #[serde(default)]
+    thresholds: Option<Value>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+enum Status {
+    Queued,
+    Running,
+    Done,
+    Failed,
+}
+
+#[derive(Serialize)]
+struct Job {
+    id: u64,
+    status: Status,
+    request: JobRequest,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    result: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+    /// Clients blocked on `POST /analyze` for this job, which keep it from being dropped
+    #[serde(skip)]
+    waiters: usize,
+}
+
+impl Job {
+    fn is_finished(&self) -> bool {
+        matches!(self.status, Status::Done | Status::Failed)
+    }
+}
+
+#[derive(Default)]
+struct Jobs {
+    next_id: u64,
+    queued: VecDeque<u64>,
+    running: usize,
+    all: BTreeMap<u64, Job>,
+}
+
+impl Jobs {
+    /// Drop the oldest finished jobs beyond `keep`, except those a client waits for
+    fn prune(&mut self, keep: usize) {
+        let finished: Vec<u64> = self.all.values()
+            .filter(|job| job.is_finished() && job.waiters == 0)
+            .map(|job| job.id)
+            .collect();
+        for id in &finished[..finished.len().saturating_sub(keep.max(1))] {
+            self.all.remove(id);
+        }
+    }
+}
+
+struct Server {
+    options: Options,
+    token: Option<String>,
+    jobs: Mutex<Jobs>,
+    /// Signaled when a job is queued
+    queued: Condvar,
+    /// Signaled when a job finishes
+    finished: Condvar,
+    connections: AtomicUsize,
+}
+
+#[derive(Debug)]
+struct Response {
+    status: u16,
+    body: Value,
+}
+
+impl Response {
+    fn new(status: u16, body: Value) -> Self {
+        Response { status, body }
+    }
+
+    fn error(status: u16, message: impl Into<String>) -> Self {
+        Response::new(status, json!({ "error": message.into() }))
+    }
+}
+
+fn reason(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        202 => "Accepted",
+        400 => "Bad Request",
+        401 => "Unauthorized",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        408 => "Request Timeout",
+        413 => "Payload Too Large",
+        431 => "Request Header Fields Too Large",
+        500 => "Internal Server Error",
+        503 => "Service Unavailable",
+        _ => "",
+    }
+}
+
+/// A parsed request: method, path and body
+#[derive(Debug)]
+struct Request {
+    method: String,
+    path: String,
+    authorization: Option<String>,
+    body: Vec<u8>,
+}
+
+/// One line of the request head, without its line break
+fn read_line(reader: &mut impl BufRead, deadline: Instant) -> Result<String, Response> {
+    if Instant::now() > deadline {
+        return Err(Response::error(408, "the request took too long"));
+    }
+    let mut line = String::new();
+    reader.take(MAX_LINE).read_line(&mut line).map_err(|e| Response::error(400, e.to_string()))?;
+    if !line.ends_with('\n') {
+        return Err(Response::error(431, "request line or header too long, or cut short"));
+    }
+    Ok(line.trim_end().to_string())
+}
+
+fn read_request(stream: impl Read) -> Result<Request, Response> {
+    let deadline = Instant::now() + REQUEST_TIMEOUT;
+    let mut reader = BufReader::new(stream);
+    let line = read_line(&mut reader, deadline)?;
+    let mut parts = line.split_whitespace();
+    let (Some(method), Some(path)) = (parts.next(), parts.next()) else {
+        return Err(Response::error(400, "malformed request line"));
+    };
+    let (method, path) = (method.to_string(), path.to_string());
+
+    let mut length = 0;
+    let mut authorization = None;
+    for count in 0.. {
+        let header = read_line(&mut reader, deadline)?;
+        if header.is_empty() {
+            break;
+        }
+        if count == MAX_HEADERS {
+            return Err(Response::error(431, format!("more than {} headers", MAX_HEADERS)));
+        }
+        let Some((name, value)) = header.split_once(':') else { continue };
+        match name.trim().to_ascii_lowercase().as_str() {
+            "content-length" => length = value.trim().parse().map_err(|_| Response::error(400, "invalid Content-Length"))?,
+            "authorization" => authorization = Some(value.trim().to_string()),
+            _ => {}
+        }
+    }
+    if length > MAX_BODY {
+        return Err(Response::error(413, format!("request bodies are limited to {} bytes", MAX_BODY)));
+    }
+    let mut body = vec![0; length];
+    reader.read_exact(&mut body).map_err(|e| Response::error(400, e.to_string()))?;
+    Ok(Request { method, path, authorization, body })
+}
+
+fn write_response(mut stream: &TcpStream, response: &Response) -> io::Result<()> {
+    let body = serde_json::to_string_pretty(&response.body)?;
+    write!(
+        stream,
+        "HTTP/1.1 {} {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        response.status,
+        reason(response.status),
+        body.len(),
+        body
+    )?;
+    stream.flush()
+}
+
+/// The analyzers a job names, all of them when it names none
+fn tools(names: &[String]) -> Result<Vec<Tool>, String> {
+    if names.is_empty() {
+        return Ok(Tool::ALL.to_vec());
+    }
+    names.iter()
+        .map(|name| Tool::ALL.iter().copied().find(|tool| tool.name() == name).ok_or_else(|| format!("unknown analyzer '{}'", name)))
+        .collect()
+}
+
+/// Whether `repo` is a remote the server fetches without `--allow-local`: an
+/// `https://` or `ssh://` URL, or an scp-like `user@host:path`
+fn is_remote(repo: &str) -> bool {
+    if repo.is_empty() || repo.starts_with('-') || repo.chars().any(|c| c.is_whitespace() || c.is_control()) {
+        return false;
+    }
+    // A host or user starting with `-` would reach ssh as an option
+    let authority_ok = |authority: &str| !authority.is_empty() && !authority.starts_with('-') && !authority.contains("@-");
+    if let Some(rest) = repo.strip_prefix("https://").or_else(|| repo.strip_prefix("ssh://")) {
+        let authority = rest.split('/').next().unwrap_or_default();
+        return authority_ok(authority);
+    }
+    match repo.split_once(':') {
+        Some((authority, path)) => authority.contains('@') && !authority.contains('/') && authority_ok(authority) && !path.is_empty(),
+        None => false,
+    }
+}
+
+/// Whether `rev` is a plain branch, tag or commit name, as `git check-ref-format`
+/// allows them and without the revision syntax (`^`, `~`, `@{..}`, `:`) git expands
+fn is_ref_name(rev: &str) -> bool {
+    let allowed = |c: char| c.is_ascii_alphanumeric() || matches!(c, '.' | '_' | '-' | '/');
+    !rev.is_empty()
+        && rev.len() <= 255
+        && rev.chars().all(allowed)
+        && !rev.starts_with(['-', '/', '.'])
+        && !rev.ends_with(['/', '.'])
+        && !rev.ends_with(".lock")
+        && !rev.contains("..")
+        && !rev.contains("//")
+        && !rev.contains("/.")
+}
+
+/// Fetch the job's repository, run its analyzers and build its report
+fn run_job(request: &JobRequest) -> Result<Value, String> {
+    let tools = tools(&request.tools)?;
+    let given: Thresholds = match &request.thresholds {
+        Some(thresholds) => serde_json::from_value(thresholds.clone()).map_err(|e| format!("invalid thresholds: {}", e))?,
+        None => Thresholds::default(),
+    };
+    let thresholds = crate::thresholds(given).map_err(|e| e.to_string())?;
+    // Clients name the URL, so the token only goes to the hosts of TRR_GIT_TOKEN_HOST
+    let fetch = Fetch::from_env(request.rev.as_deref()).untrusted();
+    let repository = Repository::fetch(&request.repo, &fetch).map_err(|e| e.to_string())?;
+    let dir = request.dir.clone().unwrap_or_else(|| PathBuf::from("."));
+    let path = repository.subdir(&dir).map_err(|e| e.to_string())?;
+
+    let (reports, metrics) = crate::analyze(&path, &tools, &thresholds);
+    let mut document = report::to_json(&dir, reports, &metrics);
+    document["repo"] = json!(request.repo);
+    document["rev"] = json!(request.rev);
+    Ok(document)
+}
+
+/// Compare a token in time that depends only on their lengths, not on where they differ
+fn same_token(given: &[u8], token: &[u8]) -> bool {
+    given.len() == token.len() && given.iter().zip(token).fold(0u8, |diff, (a, b)| diff | (a ^ b)) == 0
+}
+
+/// Counts an open connection until dropped
+struct Connection<'a>(&'a AtomicUsize);
+
+impl Drop for Connection<'_> {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+impl Server {
+    fn new(options: Options, token: Option<String>) -> Server {
+        Server {
+            options,
+            token,
+            jobs: Mutex::new(Jobs::default()),
+            queued: Condvar::new(),
+            finished: Condvar::new(),
+            connections: AtomicUsize::new(0),
+        }
+    }
+
+    /// Check a job before queueing it, so a bad one fails at once
+    fn validate(&self, request: &JobRequest) -> Result<(), String> {
+        tools(&request.tools)?;
+        if !self.options.allow_local && !is_remote(&request.repo) {
+            return Err("repo has to be an https://, ssh:// or user@host:path remote; start the server with --allow-local for local repositories".to_string());
+        }
+        if let Some(rev) = request.rev.as_deref().filter(|rev| !is_ref_name(rev)) {
+            return Err(format!("rev '{}' is not a branch, tag or commit name", rev));
+        }
+        let escapes = request.dir.as_deref()
+            .is_some_and(|dir| dir.components().any(|component| !matches!(component, Component::Normal(_) | Component::CurDir)));
+        if escapes {
+            return Err("dir has to be a relative path inside the repository".to_string());
+        }
+        Ok(())
+    }
+
+    fn enqueue(&self, body: &[u8]) -> Result<u64, Response> {
+        let request: JobRequest = serde_json::from_slice(body).map_err(|e| Response::error(400, format!("invalid job: {}", e)))?;
+        self.validate(&request).map_err(|e| Response::error(400, e))?;
+        let mut jobs = self.jobs.lock().unwrap();
+        if jobs.queued.len() >= self.options.max_queued {
+            return Err(Response::error(503, format!("{} jobs are already waiting; try again later", jobs.queued.len())));
+        }
+        jobs.next_id += 1;
+        let id = jobs.next_id;
+        jobs.all.insert(id, Job { id, status: Status::Queued, request, result: None, error: None, waiters: 0 });
+        jobs.queued.push_back(id);
+        self.queued.notify_one();
+        Ok(id)
+    }
+
+    fn job(&self, id: u64) -> Option<Value> {
+        let jobs = self.jobs.lock().unwrap();
+        jobs.all.get(&id).map(|job| serde_json::to_value(job).unwrap_or_default())
+    }
+
+    /// Wait for job `id` to finish, and answer its result
+    fn wait(&self, id: u64) -> Response {
+        let mut jobs = self.jobs.lock().unwrap();
+        let Some(job) = jobs.all.get_mut(&id) else {
+            return Response::error(404, "no such job");
+        };
+        job.waiters += 1;
+        let response = loop {
+            let job = jobs.all.get_mut(&id).expect("jobs with waiters are kept");
+            match job.status {
+                Status::Done => break Response::new(200, job.result.clone().unwrap_or_default()),
+                Status::Failed => break Response::error(500, job.error.clone().unwrap_or_default()),
+                Status::Queued | Status::Running => jobs = self.finished.wait(jobs).unwrap(),
+            }
+        };
+        jobs.all.get_mut(&id).expect("jobs with waiters are kept").waiters -= 1;
+        jobs.prune(self.options.keep);
+        response
+    }
+
+    /// Take the next queued job, waiting for one
+    fn next_job(&self) -> (u64, JobRequest) {
+        let mut jobs = self.jobs.lock().unwrap();
+        let id = loop {
+            match jobs.queued.pop_front() {
+                Some(id) => break id,
+                None => jobs = self.queued.wait(jobs).unwrap(),
+            }
+        };
+        jobs.running += 1;
+        let job = jobs.all.get_mut(&id).expect("queued jobs are kept");
+        job.status = Status::Running;
+        (id, job.request.clone())
+    }
+
+    fn finish(&self, id: u64, result: Result<Value, String>) {
+        let mut jobs = self.jobs.lock().unwrap();
+        jobs.running -= 1;
+        if let Some(job) = jobs.all.get_mut(&id) {
+            match result {
+                Ok(document) => {
+                    eprintln!("Job {}: done", id);
+                    job.status = Status::Done;
+                    job.result = Some(document);
+                }
+                Err(e) => {
+                    eprintln!("Job {}: failed: {}", id, e);
+                    job.status = Status::Failed;
+                    job.error = Some(e);
+                }
+            }
+        }
+        jobs.prune(self.options.keep);
+        self.finished.notify_all();
+    }
+
+    fn work(&self) {
+        loop {
+            let (id, request) = self.next_job();
+            eprintln!("Job {}: analyzing {} at {}", id, request.repo, request.rev.as_deref().unwrap_or("its default branch"));
+            self.finish(id, run_job(&request));
+        }
+    }
+
+    fn route(&self, request: &Request) -> Response {
+        if let Some(token) = &self.token {
+            let given = request.authorization.as_deref().and_then(|value| value.strip_prefix("Bearer ")).unwrap_or_default();
+            if !same_token(given.as_bytes(), token.as_bytes()) {
+                return Response::error(401, "missing or wrong bearer token");
+            }
+        }
+        let path = request.path.split('?').next().unwrap_or_default();
+        match (request.method.as_str(), path) {
+            ("GET", "/health") => {
+                let jobs = self.jobs.lock().unwrap();
+                Response::new(200, json!({
+                    "status": "ok",
+                    "workers": self.options.workers,
+                    "running": jobs.running,
+                    "queued": jobs.queued.len(),
+                    "max_queued": self.options.max_queued,
+                    "connections": self.connections.load(Ordering::SeqCst),
+                }))
+            }
+            ("POST", "/jobs") => match self.enqueue(&request.body) {
+                Ok(id) => Response::new(202, json!({ "id": id, "status": Status::Queued, "url": format!("/jobs/{}", id) })),
+                Err(response) => response,
+            },
+            ("POST", "/analyze") => match self.enqueue(&request.body) {
+                Ok(id) => self.wait(id),
+                Err(response) => response,
+            },
+            ("GET", _) if path.starts_with("/jobs/") => {
+                let job = path["/jobs/".len()..].parse().ok().and_then(|id| self.job(id));
+                match job {
+                    Some(job) => Response::new(200, job),
+                    None => Response::error(404, "no such job"),
+                }
+            }
+            (_, "/health" | "/jobs" | "/analyze") => Response::error(405, format!("{} is not allowed on {}", request.method, path)),
+            _ => Response::error(404, format!("no route for {}", path)),
+        }
+    }
+
+    fn handle(&self, stream: TcpStream) {
+        let _connection = Connection(&self.connections);
+        let response = match read_request(&stream) {
+            Ok(request) => self.route(&request),
+            Err(response) => response,
+        };
+        if let Err(e) = write_response(&stream, &response) {
+            eprintln!("Error answering a request: {}", e);
+        }
+    }
+
+    /// Count a new connection, unless `--max-connections` are already open
+    fn admit(&self) -> bool {
+        let admitted = self.connections.fetch_add(1, Ordering::SeqCst) < self.options.max_connections.max(1);
+        if !admitted {
+            self.connections.fetch_sub(1, Ordering::SeqCst);
+        }
+        admitted
+    }
+}
+
+/// Listen on `options.bind` until the process is stopped
+pub fn serve(options: Options) -> io::Result<()> {
+    let listener = TcpListener::bind(&options.bind)?;
+    eprintln!("Serving the analyzers on http://{} with {} worker(s)", listener.local_addr()?, options.workers.max(1));
+    let token = env::var("TRR_SERVE_TOKEN").ok().filter(|token| !token.is_empty());
+    let server = Arc::new(Server::new(options, token));
+    for _ in 0..server.options.workers.max(1) {
+        let server = Arc::clone(&server);
+        thread::spawn(move || server.work());
+    }
+    for stream in listener.incoming() {
+        let stream = match stream {
+            Ok(stream) => stream,
+            Err(e) => {
+                eprintln!("Error accepting a connection: {}", e);
+                continue;
+            }
+        };
+        if let Err(e) = stream.set_read_timeout(Some(IO_TIMEOUT)).and_then(|_| stream.set_write_timeout(Some(IO_TIMEOUT))) {
+            eprintln!("Error setting up a connection: {}", e);
+            continue;
+        }
+        if !server.admit() {
+            let _ = write_response(&stream, &Response::error(503, "too many connections; try again later"));
+            continue;
+        }
+        let server = Arc::clone(&server);
+        // The connection was counted by `admit`; `handle` releases it
+        thread::spawn(move || server.handle(stream));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn options() -> Options {
+        Options { bind: String::new(), workers: 1, max_queued: 2, max_connections: 4, keep: 2, allow_local: false }
+    }
+
+    fn job(repo: &str, rev: Option<&str>, dir: Option<&str>) -> JobRequest {
+        JobRequest { repo: repo.to_string(), rev: rev.map(str::to_string), dir: dir.map(PathBuf::from), tools: Vec::new(), thresholds: None }
+    }
+
+    #[test]
+    fn validate_repositories() {
+        let server = Server::new(options(), None);
+        for repo in ["https://github.com/user/repo.git", "ssh://git@github.com/user/repo.git", "git@github.com:user/repo.git"] {
+            assert!(server.validate(&job(repo, None, None)).is_ok(), "{}", repo);
+        }
+        let refused = [
+            "file:///srv/repo",
+            "/srv/repo",
+            ".",
+            "http://example.com/repo.git",
+            "ext::sh -c touch% /tmp/pwned",
+            "ssh://-oProxyCommand=touch/repo",
+            "-oProxyCommand=x@host:repo",
+            "git@-oProxyCommand=x:repo",
+            "host:repo",
+            "",
+        ];
+        for repo in refused {
+            assert!(server.validate(&job(repo, None, None)).is_err(), "{}", repo);
+        }
+
+        let local = Server::new(Options { allow_local: true, ..options() }, None);
+        assert!(local.validate(&job("file:///srv/repo", None, None)).is_ok());
+        assert!(local.validate(&job("/srv/repo", None, None)).is_ok());
+    }
+
+    #[test]
+    fn validate_revisions_and_dirs() {
+        let server = Server::new(Options { allow_local: true, ..options() }, None);
+        let repo = "https://github.com/user/repo.git";
+        for rev in ["main", "v1.2.0", "release/2024-05", "0123456789abcdef0123456789abcdef01234567"] {
+            assert!(server.validate(&job(repo, Some(rev), None)).is_ok(), "{}", rev);
+        }
+        for rev in ["--upload-pack=touch /tmp/pwned", "-h", "main~1", "HEAD^", "a..b", "main@{1}", "x:y", "a b", "/main", "main.lock", ""] {
+            assert!(server.validate(&job(repo, Some(rev), None)).is_err(), "{}", rev);
+        }
+        assert!(server.validate(&job(repo, None, Some("programs/vault"))).is_ok());
+        assert!(server.validate(&job(repo, None, Some("./programs"))).is_ok());
+        for dir in ["../secrets", "programs/../../etc", "/etc"] {
+            assert!(server.validate(&job(repo, None, Some(dir))).is_err(), "{}", dir);
+        }
+        let mut unknown = job(repo, None, None);
+        unknown.tools = vec!["mscd".to_string(), "nope".to_string()];
+        assert_eq!(server.validate(&unknown).unwrap_err(), "unknown analyzer 'nope'");
+    }
+
+    #[test]
+    fn request_parsing() {
+        let raw = "POST /jobs HTTP/1.1\r\nHost: x\r\nAuthorization: Bearer s\r\ncontent-length: 4\r\n\r\n{}\r\nignored";
+        let request = read_request(raw.as_bytes()).unwrap();
+        assert_eq!((request.method.as_str(), request.path.as_str()), ("POST", "/jobs"));
+        assert_eq!(request.authorization.as_deref(), Some("Bearer s"));
+        assert_eq!(request.body, b"{}\r\n");
+
+        let request = read_request("GET /health HTTP/1.1\n\n".as_bytes()).unwrap();
+        assert!(request.body.is_empty());
+
+        let status = |raw: &str| read_request(raw.as_bytes()).unwrap_err().status;
+        assert_eq!(status("\r\n"), 400);
+        assert_eq!(status("POST /jobs HTTP/1.1\r\nContent-Length: x\r\n\r\n"), 400);
+        assert_eq!(status("POST /jobs HTTP/1.1\r\nContent-Length: 9\r\n\r\n{}"), 400);
+        assert_eq!(status(&format!("POST /jobs HTTP/1.1\r\nContent-Length: {}\r\n\r\n", MAX_BODY + 1)), 413);
+        assert_eq!(status(&format!("GET /{} HTTP/1.1\r\n\r\n", "a".repeat(MAX_LINE as usize))), 431);
+        assert_eq!(status(&format!("GET / HTTP/1.1\r\n{}\r\n", "X: y\r\n".repeat(MAX_HEADERS + 1))), 431);
+        // Cut short before the end of the head
+        assert_eq!(status("GET / HTTP/1.1\r\nHost: x"), 431);
+    }
+
+    #[test]
+    fn queue_limit() {
+        // No workers run, so jobs stay queued
+        let server = Server::new(options(), Some("s".to_string()));
+        let post = |authorization: Option<&str>| {
+            let body = br#"{"repo": "https://github.com/user/repo.git", "tools": ["mscd"]}"#.to_vec();
+            let request = Request { method: "POST".to_string(), path: "/jobs".to_string(), authorization: authorization.map(str::to_string), body };
+            server.route(&request)
+        };
+        assert_eq!(post(None).status, 401);
+        for wrong in ["Bearer t", "Bearer ss", "Bearer ", "Basic s", "s"] {
+            assert_eq!(post(Some(wrong)).status, 401, "{}", wrong);
+        }
+        assert_eq!(post(Some("Bearer s")).status, 202);
+        assert_eq!(post(Some("Bearer s")).status, 202);
+        let refused = post(Some("Bearer s"));
+        assert_eq!(refused.status, 503);
+        assert_eq!(refused.body["error"], "2 jobs are already waiting; try again later");
+
+        // A worker taking a job frees a place
+        let (id, _) = server.next_job();
+        assert_eq!(id, 1);
+        assert_eq!(post(Some("Bearer s")).status, 202);
+        assert_eq!(server.job(1).unwrap()["status"], "running");
+    }
+
+    #[test]
+    fn keep_and_waiters() {
+        let server = Arc::new(Server::new(Options { keep: 1, max_queued: 10, ..options() }, None));
+        let body = br#"{"repo": "https://github.com/user/repo.git"}"#;
+        let waited = server.enqueue(body).unwrap();
+        let waiter = {
+            let server = Arc::clone(&server);
+            thread::spawn(move || server.wait(waited))
+        };
+        while server.jobs.lock().unwrap().all[&waited].waiters == 0 {
+            thread::yield_now();
+        }
+
+        // Two later jobs finish first; finishing the waited one then drops the older of
+        // them, but not the waited job, though it is older still
+        let (id, _) = server.next_job();
+        assert_eq!(id, waited);
+        for _ in 0..2 {
+            server.enqueue(body).unwrap();
+            let (id, _) = server.next_job();
+            server.finish(id, Err("fetch failed".to_string()));
+        }
+        server.finish(waited, Ok(json!({ "passed": true })));
+        assert!(server.job(2).is_none());
+        let response = waiter.join().unwrap();
+        assert_eq!(response.status, 200);
+        assert_eq!(response.body["passed"], true);
+
+        // Once answered, only the last --keep finished jobs remain
+        let kept: Vec<u64> = server.jobs.lock().unwrap().all.keys().copied().collect();
+        assert_eq!(kept, [3]);
+        assert_eq!(server.job(3).unwrap()["error"], "fetch failed");
+        assert!(server.job(waited).is_none());
+    }
+
+    #[test]
+    fn connection_limit() {
+        let server = Server::new(Options { max_connections: 2, ..options() }, None);
+        assert!(server.admit());
+        assert!(server.admit());
+        assert!(!server.admit());
+        drop(Connection(&server.connections));
+        assert!(server.admit());
+        assert_eq!(server.connections.load(Ordering::SeqCst), 2);
+    }
+}
@@ -0,0 +1,121 @@
+//! The analyzers, each run as its own executable with the format and the directory
+//! to analyze, which every analyzer takes as `--format <format> [options] <DIR>`.
+
+use std::env;
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use trr_core::output::Format;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Tool {
+    Mscd,
+    Mtd,
+    Mmed,
+    Mcd,
+    Mcu,
+    Mcc,
+    Mdd,
+    Mdv,
+    Msc,
+    Mua,
+    Mic,
+    Msf,
+    Mpd,
+    Mfp,
+    Mec,
+    Mlc,
+    Mre,
+    Mra,
+    Msv,
+    Mdc,
+}
+
+impl Tool {
+    /// In the order `trr all` runs them
+    pub const ALL: [Tool; 20] = [Tool::Mscd, Tool::Mtd, Tool::Mmed, Tool::Mcd, Tool::Mcu, Tool::Mcc, Tool::Mdd, Tool::Mdv, Tool::Msc, Tool::Mua, Tool::Mic, Tool::Msf, Tool::Mpd, Tool::Mfp, Tool::Mec, Tool::Mlc, Tool::Mre, Tool::Mra, Tool::Msv, Tool::Mdc];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Tool::Mscd => "mscd",
+            Tool::Mtd => "mtd",
+            Tool::Mmed => "mmed",
+            Tool::Mcd => "mcd",
+            Tool::Mcu => "mcu",
+            Tool::Mcc => "mcc",
+            Tool::Mdd => "mdd",
+            Tool::Mdv => "mdv",
+            Tool::Msc => "msc",
+            Tool::Mua => "mua",
+            Tool::Mic => "mic",
+            Tool::Msf => "msf",
+            Tool::Mpd => "mpd",
+            Tool::Mfp => "mfp",
+            Tool::Mec => "mec",
+            Tool::Mlc => "mlc",
+            Tool::Mre => "mre",
+            Tool::Mra => "mra",
+            Tool::Msv => "msv",
+            Tool::Mdc => "mdc",
+        }
+    }
+
+    pub fn title(self) -> &'static str {
+        match self {
+            Tool::Mscd => "Struct Composition Depth",
+            Tool::Mtd => "Trait Depth",
+            Tool::Mmed => "Macro Expansion Depth",
+            Tool::Mcd => "CPI Depth",
+            Tool::Mcu => "Compute Unit Estimate",
+            Tool::Mcc => "Cyclomatic Complexity",
+            Tool::Mdd => "Dependency Depth",
+            Tool::Mdv => "Duplicate Dependency Versions",
+            Tool::Msc => "Missing Signer Checks",
+            Tool::Mua => "Unchecked Arithmetic",
+            Tool::Mic => "IDL Consistency",
+            Tool::Msf => "Stack Frames",
+            Tool::Mpd => "Potential Panics",
+            Tool::Mfp => "Floating Point",
+            Tool::Mec => "Error Codes",
+            Tool::Mlc => "Logging Cost",
+            Tool::Mre => "Rent Exemption",
+            Tool::Mra => "Realloc Audit",
+            Tool::Msv => "Sysvar Usage",
+            Tool::Mdc => "Account Data Copies",
+        }
+    }
+
+    /// The analyzer's executable: the one next to `trr` when they were built or
+    /// installed together, or else the one on the `PATH`
+    fn program(self) -> PathBuf {
+        let file_name = format!("{}{}", self.name(), env::consts::EXE_SUFFIX);
+        env::current_exe().ok()
+            .map(|exe| exe.with_file_name(&file_name))
+            .filter(|sibling| sibling.is_file())
+            .unwrap_or_else(|| PathBuf::from(file_name))
+    }
+
+    /// The command analyzing `dir` in `format`, with `extra` options for the analyzer
+    pub fn command(self, dir: &Path, format: Format, extra: &[OsString]) -> Command {
+        let mut command = Command::new(self.program());
+        command.arg("--format").arg(format.name()).args(extra).arg(dir);
+        command
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn commands() {
+        let extra = [OsString::from("--exclude"), OsString::from("target")];
+        for tool in Tool::ALL {
+            let command = tool.command(Path::new("programs/vault"), Format::Json, &extra);
+            assert!(command.get_program().to_string_lossy().contains(tool.name()));
+            let args: Vec<_> = command.get_args().collect();
+            assert_eq!(args, ["--format", "json", "--exclude", "target", "programs/vault"], "{}", tool.name());
+        }
+    }
+}
@@ -0,0 +1,115 @@
+// Runs the trr binary with stand-in analyzers: shell scripts next to a copy of trr,
+// which it prefers to the ones on the PATH. Each prints the report in <name>.json,
+// records its arguments and exits with the code in <name>.code.
+#![cfg(unix)]
+
+use std::fs;
+use std::os::unix::fs::PermissionsExt;
+use std::path::Path;
+use std::process::{Command, Output};
+
+use serde_json::{json, Value};
+use tempfile::TempDir;
+
+const ANALYZERS: [&str; 20] = [
+    "mscd", "mtd", "mmed", "mcd", "mcu", "mcc", "mdd", "mdv", "msc", "mua", "mic", "msf", "mpd", "mfp", "mec", "mlc", "mre", "mra", "msv", "mdc",
+];
+
+/// A directory holding trr, the stand-in analyzers, all printing `report`, and an empty
+/// `programs` directory to analyze
+fn install(report: &Value) -> TempDir {
+    let dir = TempDir::new().unwrap();
+    fs::create_dir(dir.path().join("programs")).unwrap();
+    fs::copy(env!("CARGO_BIN_EXE_trr"), dir.path().join("trr")).unwrap();
+    for name in ANALYZERS {
+        let script = dir.path().join(name);
+        let body = format!(
+            "#!/bin/sh\ndir=$(dirname \"$0\")\necho \"$@\" > \"$dir/{name}.args\"\ncat \"$dir/{name}.json\"\nexit $(cat \"$dir/{name}.code\")\n"
+        );
+        fs::write(&script, body).unwrap();
+        fs::set_permissions(&script, fs::Permissions::from_mode(0o755)).unwrap();
+        set(dir.path(), name, report, 0);
+    }
+    dir
+}
+
+fn set(dir: &Path, name: &str, report: &Value, code: i32) {
+    fs::write(dir.join(format!("{}.json", name)), report.to_string()).unwrap();
+    fs::write(dir.join(format!("{}.code", name)), code.to_string()).unwrap();
+}
+
+fn trr(dir: &Path, args: &[&str]) -> Output {
+    Command::new(dir.join("trr")).args(args).current_dir(dir).output().expect("failed to run trr")
+}
+
+/// A report with every value `trr report` reads, all set to `value`
+fn report(value: u64) -> Value {
+    let keys = [
+        "trait_count", "impl_count", "max_depth", "max_associated_bound_depth", "files_analyzed", "handlers", "cpi_sites", "high_risk", "max_units",
+        "functions", "max_complexity", "max_nesting", "max_transitive", "duplicated", "watched_duplicated", "findings", "operations", "idls", "drifts",
+        "max_frame", "large_parameters", "sites", "crates_with_floats", "codes", "collisions", "leaks", "reallocs", "flagged", "handlers_reading",
+        "deprecated", "copies", "large",
+    ];
+    let mut summary: serde_json::Map<String, Value> = keys.iter().map(|key| (key.to_string(), json!(value))).collect();
+    summary.insert("warning_counts".to_string(), json!([{ "count": value }]));
+    json!({ "struct_count": value, "max_depth": value, "summary": summary })
+}
+
+#[test]
+fn all_runs_every_analyzer() {
+    let dir = install(&json!({ "runs": [] }));
+    set(dir.path(), "mmed", &json!({ "runs": [{ "tool": { "driver": { "name": "mmed" } } }] }), 0);
+    let output = trr(dir.path(), &["all", "--format", "sarif", "programs"]);
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    let log: Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(log["runs"].as_array().unwrap().len(), 1);
+    // Every analyzer, mmed included, takes the format first and the directory last
+    for name in ANALYZERS {
+        let args = fs::read_to_string(dir.path().join(format!("{}.args", name))).unwrap();
+        assert_eq!(args.trim(), "--format sarif programs", "{}", name);
+    }
+
+    // One failing analyzer fails the run, after all of them ran
+    set(dir.path(), "mtd", &json!({ "runs": [] }), 1);
+    for name in ANALYZERS {
+        fs::remove_file(dir.path().join(format!("{}.args", name))).unwrap();
+    }
+    let output = trr(dir.path(), &["all", "--format", "json", "programs"]);
+    assert_eq!(output.status.code(), Some(1));
+    let document: Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(document["target"], "programs");
+    assert!(ANALYZERS.iter().all(|name| document.get(name).is_some() && dir.path().join(format!("{}.args", name)).exists()));
+}
+
+#[test]
+fn report_exit_code() {
+    let dir = install(&report(3));
+    let passes = |args: &[&str]| {
+        let output = trr(dir.path(), &[&["report", "--format", "json"], args].concat());
+        let document: Value = serde_json::from_slice(&output.stdout).unwrap();
+        assert_eq!(document["passed"], output.status.success());
+        output.status.code()
+    };
+    assert_eq!(passes(&[]), Some(0));
+    assert_eq!(passes(&["--max-struct-depth", "3", "--max-cpi-depth", "5"]), Some(0));
+    assert_eq!(passes(&["--max-struct-depth", "2"]), Some(1));
+
+    // Thresholds left out on the command line come from trr.toml
+    fs::write(dir.path().join("trr.toml"), "[trr]\nmax-nesting = 2\n").unwrap();
+    assert_eq!(passes(&[]), Some(1));
+    assert_eq!(passes(&["--max-nesting", "3"]), Some(0));
+
+    // An analyzer without a report fails the review even though its exit code is 0
+    set(dir.path(), "mdv", &json!("not a report"), 0);
+    assert_eq!(passes(&["--max-nesting", "3"]), Some(1));
+}
+
+#[test]
+fn single_analyzer_exit_code() {
+    let dir = install(&json!({}));
+    set(dir.path(), "mcd", &json!({}), 3);
+    let output = trr(dir.path(), &["mcd", "--format", "json", "programs", "--", "--fail-above", "2"]);
+    assert_eq!(output.status.code(), Some(3));
+    let args = fs::read_to_string(dir.path().join("mcd.args")).unwrap();
+    assert_eq!(args.trim(), "--format json --fail-above 2 programs");
+}